@@ -186,10 +186,10 @@ SET
    ignore_state_before_epoch = :ignore_state_before_epoch
 WHERE id = :job_id AND organization_id = :organization_id;
 
---! create_job(ttl_micros?)
+--! create_job(ttl_micros?, worker_overrides?)
 INSERT INTO job_configs
-(id, organization_id, pipeline_name, created_by, pipeline_id, checkpoint_interval_micros, ttl_micros)
-VALUES (:id, :organization_id, :pipeline_name, :created_by, :pipeline_id, :checkpoint_interval_micros, :ttl_micros);
+(id, organization_id, pipeline_name, created_by, pipeline_id, checkpoint_interval_micros, ttl_micros, worker_overrides)
+VALUES (:id, :organization_id, :pipeline_name, :created_by, :pipeline_id, :checkpoint_interval_micros, :ttl_micros, :worker_overrides);
 
 --! create_job_status
 INSERT INTO job_statuses (pub_id, id, organization_id) VALUES (:pub_id, :id, :organization_id);
@@ -267,6 +267,17 @@ WHERE job_id = :job_id
     AND epoch = :epoch
     AND state != 'failed';
 
+--! get_checkpoint_gc_jobs: (state?, epoch?, min_epoch?)
+SELECT job_configs.id as job_id, job_statuses.state, checkpoints.epoch, checkpoints.min_epoch
+FROM job_configs
+    INNER JOIN job_statuses ON job_configs.id = job_statuses.id
+    LEFT JOIN checkpoints ON checkpoints.job_id = job_configs.id
+        AND (checkpoints.state = 'ready' OR checkpoints.state = 'committing')
+        AND checkpoints.epoch = (
+            SELECT MAX(epoch) FROM checkpoints
+            WHERE job_id = job_configs.id AND (state = 'ready' OR state = 'committing')
+        );
+
 --! delete_pipeline_for_job
 DELETE FROM pipelines WHERE pipelines.id = (
     SELECT pipeline_id
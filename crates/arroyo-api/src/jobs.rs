@@ -1,8 +1,11 @@
 use crate::queries::api_queries::{DbCheckpoint, DbLogMessage, DbPipelineJob};
 use arroyo_rpc::api_types::checkpoints::{
-    Checkpoint, JobCheckpointSpan, OperatorCheckpointGroup, SubtaskCheckpointGroup,
+    Checkpoint, CheckpointGcPost, CheckpointGcResult, JobCheckpointSpan, OperatorCheckpointGroup,
+    StateTableCollection, StateTableQueryParams, StateTableRows, SubtaskCheckpointGroup,
+};
+use arroyo_rpc::api_types::pipelines::{
+    JobLogLevel, JobLogMessage, OutputData, StopType, WorkerOverrides,
 };
-use arroyo_rpc::api_types::pipelines::{JobLogLevel, JobLogMessage, OutputData, StopType};
 use arroyo_rpc::api_types::{
     CheckpointCollection, JobCollection, JobLogMessageCollection,
     OperatorCheckpointGroupCollection, PaginationQueryParams,
@@ -10,9 +13,12 @@ use arroyo_rpc::api_types::{
 use arroyo_rpc::grpc::api::OperatorCheckpointDetail;
 use arroyo_rpc::public_ids::{IdTypes, generate_id};
 use arroyo_rpc::{get_event_spans, grpc};
+use arroyo_state::parquet::JobCheckpoints;
+use arroyo_state::{StateBackend, get_storage_provider, inspect};
 use axum::Json;
 use axum::extract::{Path, Query, State};
 use axum::response::sse::{Event, Sse};
+use axum_extra::extract::WithRejection;
 use futures_util::stream::Stream;
 use std::convert::Infallible;
 use std::str::FromStr;
@@ -27,20 +33,21 @@ const PREVIEW_TTL: Duration = Duration::from_secs(60);
 use crate::pipelines::{query_job_by_pub_id, query_pipeline_by_pub_id};
 use crate::rest::AppState;
 use crate::rest_utils::{
-    BearerAuth, ErrorResp, authenticate, bad_request, log_and_map, not_found, paginate_results,
-    validate_pagination_params,
+    ApiError, BearerAuth, ErrorResp, authenticate, bad_request, forbidden, log_and_map, not_found,
+    paginate_results, validate_pagination_params,
 };
 use crate::types::public::LogLevel;
 use crate::{AuthData, queries::api_queries, to_micros, types::public};
 use arroyo_rpc::config::config;
 use arroyo_rpc::controller_client;
 use arroyo_rpc::errors::ErrorDomain;
-use cornucopia_async::DatabaseSource;
+use cornucopia_async::{Database, DatabaseSource};
 
 pub(crate) async fn create_job(
     pipeline_name: &str,
     pipeline_id: i64,
     checkpoint_interval: Duration,
+    worker_overrides: Option<&WorkerOverrides>,
     preview: bool,
     auth: &AuthData,
     db: &DatabaseSource,
@@ -96,6 +103,7 @@ pub(crate) async fn create_job(
         } else {
             None
         }),
+        &worker_overrides.map(|o| serde_json::to_value(o).unwrap()),
     )
     .await?;
 
@@ -354,6 +362,198 @@ pub async fn get_checkpoint_details(
     Ok(Json(OperatorCheckpointGroupCollection { data: operators }))
 }
 
+async fn check_checkpoint_exists(
+    db: &Database<'_>,
+    auth_data: &AuthData,
+    job_pub_id: &str,
+    epoch: u32,
+) -> Result<(), ErrorResp> {
+    api_queries::fetch_get_checkpoint_details(
+        db,
+        &job_pub_id,
+        &auth_data.organization_id,
+        &(epoch as i32),
+    )
+    .await
+    .map_err(log_and_map)?
+    .into_iter()
+    .next()
+    .ok_or_else(|| {
+        not_found(&format!(
+            "Checkpoint with epoch {epoch} for job '{job_pub_id}'"
+        ))
+    })?;
+
+    Ok(())
+}
+
+/// List the state tables in an operator's checkpoint
+#[utoipa::path(
+    get,
+    path = "/v1/pipelines/{pipeline_id}/jobs/{job_id}/checkpoints/{epoch}/operators/{operator_id}/tables",
+    tag = "jobs",
+    params(
+        ("pipeline_id" = String, Path, description = "Pipeline id"),
+        ("job_id" = String, Path, description = "Job id"),
+        ("epoch" = u32, Path, description = "Epoch"),
+        ("operator_id" = String, Path, description = "Operator id"),
+    ),
+    responses(
+        (status = 200, description = "Got the operator's state tables", body = StateTableCollection),
+    ),
+)]
+pub async fn get_checkpoint_state_tables(
+    State(state): State<AppState>,
+    bearer_auth: BearerAuth,
+    Path((pipeline_pub_id, job_pub_id, epoch, operator_id)): Path<(String, String, u32, String)>,
+) -> Result<Json<StateTableCollection>, ErrorResp> {
+    let db = state.database.client().await?;
+    let auth_data = authenticate(&state.database, bearer_auth).await?;
+
+    query_job_by_pub_id(&pipeline_pub_id, &job_pub_id, &db, &auth_data).await?;
+    check_checkpoint_exists(&db, &auth_data, &job_pub_id, epoch).await?;
+
+    let storage = get_storage_provider().await.map_err(log_and_map)?;
+    let tables = inspect::list_tables(storage, &job_pub_id, epoch, &operator_id)
+        .await
+        .map_err(log_and_map)?
+        .ok_or_else(|| {
+            not_found(&format!(
+                "Operator '{operator_id}' in checkpoint {epoch} for job '{job_pub_id}'"
+            ))
+        })?;
+
+    Ok(Json(StateTableCollection { data: tables }))
+}
+
+/// Read the rows of a state table in an operator's checkpoint
+#[utoipa::path(
+    get,
+    path = "/v1/pipelines/{pipeline_id}/jobs/{job_id}/checkpoints/{epoch}/operators/{operator_id}/tables/{table}",
+    tag = "jobs",
+    params(
+        ("pipeline_id" = String, Path, description = "Pipeline id"),
+        ("job_id" = String, Path, description = "Job id"),
+        ("epoch" = u32, Path, description = "Epoch"),
+        ("operator_id" = String, Path, description = "Operator id"),
+        ("table" = String, Path, description = "Table name"),
+        StateTableQueryParams,
+    ),
+    responses(
+        (status = 200, description = "Got the table's rows", body = StateTableRows),
+    ),
+)]
+pub async fn get_checkpoint_state_table(
+    State(state): State<AppState>,
+    bearer_auth: BearerAuth,
+    Path((pipeline_pub_id, job_pub_id, epoch, operator_id, table)): Path<(
+        String,
+        String,
+        u32,
+        String,
+        String,
+    )>,
+    query_params: Query<StateTableQueryParams>,
+) -> Result<Json<StateTableRows>, ErrorResp> {
+    let db = state.database.client().await?;
+    let auth_data = authenticate(&state.database, bearer_auth).await?;
+
+    query_job_by_pub_id(&pipeline_pub_id, &job_pub_id, &db, &auth_data).await?;
+    check_checkpoint_exists(&db, &auth_data, &job_pub_id, epoch).await?;
+
+    let limit = query_params.limit.unwrap_or(100) as usize;
+    if limit == 0 || limit > inspect::MAX_ROWS {
+        return Err(bad_request(format!(
+            "limit must be between 1 and {}",
+            inspect::MAX_ROWS
+        )));
+    }
+
+    let storage = get_storage_provider().await.map_err(log_and_map)?;
+    let rows = inspect::read_table(
+        storage,
+        &job_pub_id,
+        epoch,
+        &operator_id,
+        &table,
+        query_params.key.as_deref(),
+        query_params.offset.unwrap_or(0) as usize,
+        limit,
+    )
+    .await
+    .map_err(log_and_map)?
+    .ok_or_else(|| {
+        not_found(&format!(
+            "Table '{table}' for operator '{operator_id}' in checkpoint {epoch}"
+        ))
+    })?;
+
+    Ok(Json(rows))
+}
+
+/// Deletes checkpoint files that aren't needed by any job's retained checkpoints, or reports
+/// them if `dry_run` is set. The checkpoints of jobs this database doesn't know about are only
+/// deleted if `delete_orphans` is set.
+pub async fn collect_checkpoint_garbage(
+    db: &DatabaseSource,
+    dry_run: bool,
+    delete_orphans: bool,
+) -> anyhow::Result<CheckpointGcResult> {
+    let jobs = api_queries::fetch_get_checkpoint_gc_jobs(&db.client().await?)
+        .await?
+        .into_iter()
+        .map(|job| {
+            (
+                job.job_id,
+                JobCheckpoints {
+                    epoch: job.epoch.map(|e| e as u32),
+                    min_epoch: job.min_epoch.unwrap_or_default() as u32,
+                    running: !matches!(
+                        job.state.as_deref(),
+                        Some("Created" | "Stopped" | "Finished" | "Failed")
+                    ),
+                },
+            )
+        })
+        .collect::<HashMap<_, _>>();
+
+    Ok(StateBackend::collect_garbage(&jobs, dry_run, delete_orphans).await?)
+}
+
+/// Delete checkpoint files that are no longer needed
+#[utoipa::path(
+    post,
+    path = "/v1/admin/checkpoint_gc",
+    tag = "admin",
+    request_body = CheckpointGcPost,
+    responses(
+        (status = 200, description = "Collected checkpoint garbage", body = CheckpointGcResult),
+        (status = 403, description = "Caller is not an admin", body = ErrorResp),
+    ),
+)]
+pub async fn checkpoint_gc(
+    State(state): State<AppState>,
+    bearer_auth: BearerAuth,
+    WithRejection(Json(req), _): WithRejection<Json<CheckpointGcPost>, ApiError>,
+) -> Result<Json<CheckpointGcResult>, ErrorResp> {
+    let auth_data = authenticate(&state.database, bearer_auth).await?;
+
+    // collection covers the checkpoints of every job in every organization
+    if auth_data.role != "admin" {
+        return Err(forbidden("only admins can collect checkpoint garbage"));
+    }
+
+    let result = collect_checkpoint_garbage(
+        &state.database,
+        req.dry_run.unwrap_or(false),
+        req.delete_orphans.unwrap_or(false),
+    )
+    .await
+    .map_err(log_and_map)?;
+
+    Ok(Json(result))
+}
+
 /// Subscribe to a job's output
 #[utoipa::path(
     get,
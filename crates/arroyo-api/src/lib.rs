@@ -27,7 +27,8 @@ use crate::connection_tables::{
 };
 use crate::connectors::__path_get_connectors;
 use crate::jobs::{
-    __path_get_checkpoint_details, __path_get_job_checkpoints, __path_get_job_errors,
+    __path_checkpoint_gc, __path_get_checkpoint_details, __path_get_checkpoint_state_table,
+    __path_get_checkpoint_state_tables, __path_get_job_checkpoints, __path_get_job_errors,
     __path_get_job_output, __path_get_jobs,
 };
 use crate::metrics::__path_get_operator_metric_groups;
@@ -60,6 +61,8 @@ mod rest_utils;
 pub mod sql;
 mod udfs;
 
+pub use jobs::collect_checkpoint_garbage;
+
 include!(concat!(env!("OUT_DIR"), "/api-sql.rs"));
 
 fn default_max_nexmark_qps() -> f64 {
@@ -273,6 +276,9 @@ impl IntoResponse for HttpError {
         test_connection_table,
         test_schema,
         get_checkpoint_details,
+        get_checkpoint_state_tables,
+        get_checkpoint_state_table,
+        checkpoint_gc,
         create_udf,
         get_udfs,
         delete_udf
@@ -280,6 +286,7 @@ impl IntoResponse for HttpError {
     components(schemas(
         ErrorResp,
         PipelinePost,
+        WorkerOverrides,
         PreviewPost,
         PipelinePatch,
         PipelineRestart,
@@ -298,6 +305,13 @@ impl IntoResponse for HttpError {
         JobLogLevel,
         Checkpoint,
         CheckpointCollection,
+        CheckpointGcPost,
+        CheckpointGcResult,
+        StateTable,
+        StateTableType,
+        StateTableCollection,
+        StateTableQueryParams,
+        StateTableRows,
         OutputData,
         MetricName,
         Metric,
@@ -361,6 +375,7 @@ impl IntoResponse for HttpError {
         (name = "pipelines", description = "Pipeline management endpoints"),
         (name = "jobs", description = "Job management endpoints"),
         (name = "connectors", description = "Connector management endpoints"),
+        (name = "admin", description = "Administrative endpoints"),
     )
 )]
 pub struct ApiDoc;
@@ -17,7 +17,7 @@ use crate::{compiler_service, connection_profiles, jobs, types};
 use arroyo_datastream::default_sink;
 use arroyo_rpc::api_types::pipelines::{
     FailureReason, Job, Pipeline, PipelinePatch, PipelinePost, PipelineRestart, PreviewPost,
-    QueryValidationResult, StopType, ValidateQueryPost,
+    QueryValidationResult, StopType, ValidateQueryPost, WorkerOverrides,
 };
 use arroyo_rpc::api_types::udfs::{GlobalUdf, Udf, UdfLanguage};
 use arroyo_rpc::api_types::{JobCollection, PaginationQueryParams, PipelineCollection};
@@ -297,6 +297,8 @@ pub(crate) async fn create_pipeline_int(
     udfs: Vec<Udf>,
     parallelism: u64,
     checkpoint_interval: Duration,
+    worker_overrides: Option<WorkerOverrides>,
+    unaligned_checkpoints: bool,
     is_preview: bool,
     enable_sinks: bool,
     auth: AuthData,
@@ -310,6 +312,12 @@ pub(crate) async fn create_pipeline_int(
         )));
     }
 
+    if let Some(overrides) = &worker_overrides {
+        overrides
+            .validate()
+            .map_err(|e| bad_request(format!("Invalid worker_overrides: {e}")))?;
+    }
+
     let pub_id = generate_id(IdTypes::Pipeline);
 
     let mut compiled =
@@ -386,6 +394,8 @@ pub(crate) async fn create_pipeline_int(
             ),
         })?;
 
+    compiled.program.program_config.unaligned_checkpoints = unaligned_checkpoints;
+
     let proto_program: ArrowProgram = compiled.program.clone().into();
 
     let program_bytes = proto_program.encode_to_vec();
@@ -432,6 +442,7 @@ pub(crate) async fn create_pipeline_int(
         &name,
         pipeline_id,
         checkpoint_interval,
+        worker_overrides.as_ref(),
         is_preview,
         &auth,
         db,
@@ -601,6 +612,8 @@ pub async fn create_pipeline(
         pipeline_post.udfs.unwrap_or_default(),
         pipeline_post.parallelism,
         checkpoint_interval,
+        pipeline_post.worker_overrides,
+        pipeline_post.unaligned_checkpoints.unwrap_or_default(),
         false,
         true,
         auth_data.clone(),
@@ -640,6 +653,8 @@ pub async fn create_preview_pipeline(
         req.udfs.unwrap_or_default(),
         1,
         Duration::MAX,
+        None,
+        false,
         true,
         req.enable_sinks,
         auth_data.clone(),
@@ -22,7 +22,8 @@ use crate::connection_tables::{
 };
 use crate::connectors::get_connectors;
 use crate::jobs::{
-    get_checkpoint_details, get_job_checkpoints, get_job_errors, get_job_output, get_jobs,
+    checkpoint_gc, get_checkpoint_details, get_checkpoint_state_table, get_checkpoint_state_tables,
+    get_job_checkpoints, get_job_errors, get_job_output, get_jobs,
 };
 use crate::metrics::get_operator_metric_groups;
 use crate::pipelines::{
@@ -140,6 +141,14 @@ pub fn create_rest_app(database: DatabaseSource) -> Router {
             "/:job_id/checkpoints/:checkpoint_id/operator_checkpoint_groups",
             get(get_checkpoint_details),
         )
+        .route(
+            "/:job_id/checkpoints/:checkpoint_id/operators/:operator_id/tables",
+            get(get_checkpoint_state_tables),
+        )
+        .route(
+            "/:job_id/checkpoints/:checkpoint_id/operators/:operator_id/tables/:table",
+            get(get_checkpoint_state_table),
+        )
         .route("/:job_id/output", get(get_job_output))
         .route(
             "/:job_id/operator_metric_groups",
@@ -179,6 +188,7 @@ pub fn create_rest_app(database: DatabaseSource) -> Router {
         .route("/pipelines/:id/restart", post(restart_pipeline))
         .route("/pipelines/:id", delete(delete_pipeline))
         .nest("/pipelines/:id/jobs", jobs_routes)
+        .route("/admin/checkpoint_gc", post(checkpoint_gc))
         .fallback(api_fallback);
 
     Router::new()
@@ -117,6 +117,13 @@ pub(crate) fn bad_request(message: impl Into<String>) -> ErrorResp {
     }
 }
 
+pub(crate) fn forbidden(message: impl Into<String>) -> ErrorResp {
+    ErrorResp {
+        status_code: StatusCode::FORBIDDEN,
+        message: message.into(),
+    }
+}
+
 pub(crate) fn service_unavailable(object: &str) -> ErrorResp {
     ErrorResp {
         status_code: StatusCode::SERVICE_UNAVAILABLE,
@@ -99,6 +99,7 @@ impl Connector for BlackholeConnector {
             bad_data: None,
             framing: None,
             metadata_fields: vec![],
+            partition_watermarks: None,
         };
 
         Ok(Connection::new(
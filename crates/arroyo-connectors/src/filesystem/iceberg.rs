@@ -248,6 +248,7 @@ impl Connector for IcebergConnector {
             bad_data: schema.bad_data.clone(),
             framing: schema.framing.clone(),
             metadata_fields: schema.metadata_fields(),
+            partition_watermarks: None,
         };
 
         Ok(Connection::new(
@@ -154,6 +154,7 @@ impl Connector for FluvioConnector {
             bad_data: schema.bad_data.clone(),
             framing: schema.framing.clone(),
             metadata_fields: schema.metadata_fields(),
+            partition_watermarks: None,
         };
 
         Ok(Connection::new(
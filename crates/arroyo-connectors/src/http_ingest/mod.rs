@@ -0,0 +1,186 @@
+mod operator;
+
+use anyhow::{anyhow, bail};
+use arroyo_operator::connector::{Connection, Connector};
+use arroyo_operator::operator::ConstructedOperator;
+use arroyo_rpc::api_types::connections::{
+    ConnectionProfile, ConnectionSchema, ConnectionType, TestSourceMessage,
+};
+use arroyo_rpc::var_str::VarStr;
+use arroyo_rpc::{ConnectorOptions, OperatorConfig};
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc::Sender;
+use typify::import_types;
+
+use crate::EmptyConfig;
+use crate::http_ingest::operator::HttpIngestSourceFunc;
+
+const TABLE_SCHEMA: &str = include_str!("./table.json");
+
+import_types!(schema = "src/http_ingest/table.json", convert = { {type = "string", format = "var-str"} = VarStr });
+const ICON: &str = include_str!("./http.svg");
+
+const DEFAULT_BIND_ADDRESS: &str = "0.0.0.0";
+const DEFAULT_MAX_QUEUED_REQUESTS: usize = 64;
+
+pub struct HttpIngestConnector {}
+
+impl Connector for HttpIngestConnector {
+    type ProfileT = EmptyConfig;
+    type TableT = HttpIngestTable;
+
+    fn name(&self) -> &'static str {
+        "http_ingest"
+    }
+
+    fn metadata(&self) -> arroyo_rpc::api_types::connections::Connector {
+        arroyo_rpc::api_types::connections::Connector {
+            id: "http_ingest".to_string(),
+            name: "HTTP Ingest".to_string(),
+            icon: ICON.to_string(),
+            description: "Accept data pushed to the pipeline over HTTP".to_string(),
+            enabled: true,
+            source: true,
+            sink: false,
+            testing: false,
+            hidden: false,
+            custom_schemas: true,
+            connection_config: None,
+            table_config: TABLE_SCHEMA.to_owned(),
+        }
+    }
+
+    fn test(
+        &self,
+        _: &str,
+        _: Self::ProfileT,
+        _: Self::TableT,
+        _: Option<&ConnectionSchema>,
+        tx: Sender<TestSourceMessage>,
+    ) {
+        tokio::task::spawn(async move {
+            tx.send(TestSourceMessage::done(
+                "HTTP ingest tables listen for data once the pipeline is running",
+            ))
+            .await
+            .unwrap();
+        });
+    }
+
+    fn table_type(&self, _: Self::ProfileT, _: Self::TableT) -> ConnectionType {
+        ConnectionType::Source
+    }
+
+    fn from_config(
+        &self,
+        id: Option<i64>,
+        name: &str,
+        config: Self::ProfileT,
+        mut table: Self::TableT,
+        schema: Option<&ConnectionSchema>,
+    ) -> anyhow::Result<Connection> {
+        let path = table.path.get_or_insert_with(|| name.to_string());
+        if path.is_empty() || path.contains('/') {
+            bail!("invalid path '{}' for HTTP ingest table", path);
+        }
+
+        let description = format!(
+            "HttpIngest<{}:{}/ingest/{}>",
+            table
+                .bind_address
+                .as_deref()
+                .unwrap_or(DEFAULT_BIND_ADDRESS),
+            table.port.get(),
+            path
+        );
+
+        let schema = schema
+            .map(|s| s.to_owned())
+            .ok_or_else(|| anyhow!("no schema defined for HTTP ingest connection"))?;
+
+        let format = schema
+            .format
+            .as_ref()
+            .map(|t| t.to_owned())
+            .ok_or_else(|| anyhow!("'format' must be set for HTTP ingest connection"))?;
+
+        let config = OperatorConfig {
+            connection: serde_json::to_value(config).unwrap(),
+            table: serde_json::to_value(table).unwrap(),
+            rate_limit: None,
+            format: Some(format),
+            bad_data: schema.bad_data.clone(),
+            framing: schema.framing.clone(),
+            metadata_fields: schema.metadata_fields(),
+            partition_watermarks: None,
+        };
+
+        Ok(Connection::new(
+            id,
+            self.name(),
+            name.to_string(),
+            ConnectionType::Source,
+            schema,
+            &config,
+            description,
+        ))
+    }
+
+    fn from_options(
+        &self,
+        name: &str,
+        options: &mut ConnectorOptions,
+        schema: Option<&ConnectionSchema>,
+        _profile: Option<&ConnectionProfile>,
+    ) -> anyhow::Result<Connection> {
+        let port = options.pull_u64("port")?;
+        let port = u16::try_from(port)
+            .ok()
+            .and_then(|p| p.try_into().ok())
+            .ok_or_else(|| anyhow!("port must be between 1 and 65535"))?;
+
+        let table = HttpIngestTable {
+            port,
+            bind_address: options.pull_opt_str("bind_address")?,
+            path: options.pull_opt_str("path")?,
+            bearer_token: options.pull_opt_str("bearer_token")?.map(VarStr::new),
+            max_queued_requests: options
+                .pull_opt_u64("max_queued_requests")?
+                .map(|t| t.try_into())
+                .transpose()
+                .map_err(|_| anyhow!("max_queued_requests must be greater than 0"))?,
+        };
+
+        self.from_config(None, name, EmptyConfig {}, table, schema)
+    }
+
+    fn make_operator(
+        &self,
+        _: Self::ProfileT,
+        table: Self::TableT,
+        config: OperatorConfig,
+    ) -> anyhow::Result<ConstructedOperator> {
+        Ok(ConstructedOperator::from_source(Box::new(
+            HttpIngestSourceFunc {
+                bind_address: table
+                    .bind_address
+                    .unwrap_or_else(|| DEFAULT_BIND_ADDRESS.to_string()),
+                port: table.port.get(),
+                path: table
+                    .path
+                    .ok_or_else(|| anyhow!("path must be set for HTTP ingest table"))?,
+                bearer_token: table.bearer_token.map(|t| t.sub_env_vars()).transpose()?,
+                max_queued_requests: table
+                    .max_queued_requests
+                    .map(|n| n.get() as usize)
+                    .unwrap_or(DEFAULT_MAX_QUEUED_REQUESTS),
+                format: config
+                    .format
+                    .ok_or_else(|| anyhow!("format is required for HTTP ingest source"))?,
+                framing: config.framing,
+                bad_data: config.bad_data,
+                metadata_fields: config.metadata_fields,
+            },
+        )))
+    }
+}
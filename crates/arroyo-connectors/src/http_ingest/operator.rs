@@ -0,0 +1,421 @@
+use arrow::array::RecordBatch;
+use arroyo_formats::de::ArrowDeserializer;
+use arroyo_operator::SourceFinishType;
+use arroyo_operator::context::{SourceCollector, SourceContext};
+use arroyo_operator::operator::SourceOperator;
+use arroyo_rpc::df::ArroyoSchema;
+use arroyo_rpc::errors::{DataflowError, DataflowResult};
+use arroyo_rpc::formats::{BadData, Format, Framing};
+use arroyo_rpc::grpc::rpc::StopMode;
+use arroyo_rpc::{ControlMessage, MetadataField, connector_err};
+use async_trait::async_trait;
+use axum::Router;
+use axum::body::Bytes;
+use axum::extract::{Path, State};
+use axum::http::{HeaderMap, StatusCode, header};
+use axum::response::{IntoResponse, Response};
+use axum::routing::post;
+use serde_json::json;
+use std::sync::Arc;
+use std::time::SystemTime;
+use tokio::net::TcpListener;
+use tokio::select;
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::{mpsc, oneshot};
+use tracing::{debug, info};
+
+// how long clients should wait before retrying when the source is backed up
+const RETRY_AFTER_SECS: &str = "1";
+
+/// A request that has been parsed into a batch, along with a channel to tell the handler once
+/// the batch has been sent into the pipeline
+type IngestRequest = (RecordBatch, oneshot::Sender<()>);
+
+/// Listens for `POST /ingest/:table` requests, each of which is parsed with the table's format
+/// and framing and sent into the pipeline as a batch. Each subtask runs its own server, on the
+/// configured port plus its task index.
+///
+/// Requests are acknowledged once their data has been sent downstream. When the source is
+/// backed up, requests are rejected with 429, and with 503 once it's shutting down; as there's
+/// no way to replay pushed data, clients are responsible for retrying.
+pub struct HttpIngestSourceFunc {
+    pub bind_address: String,
+    pub port: u16,
+    pub path: String,
+    pub bearer_token: Option<String>,
+    pub max_queued_requests: usize,
+    pub format: Format,
+    pub framing: Option<Framing>,
+    pub bad_data: Option<BadData>,
+    pub metadata_fields: Vec<MetadataField>,
+}
+
+struct IngestState {
+    path: String,
+    bearer_token: Option<String>,
+    format: Format,
+    framing: Option<Framing>,
+    bad_data: BadData,
+    metadata_fields: Vec<MetadataField>,
+    schema: Arc<ArroyoSchema>,
+    tx: mpsc::Sender<IngestRequest>,
+}
+
+fn error_response(status: StatusCode, message: impl Into<String>) -> Response {
+    (status, axum::Json(json!({ "error": message.into() }))).into_response()
+}
+
+fn unavailable(status: StatusCode, message: &str) -> Response {
+    let mut response = error_response(status, message);
+    response.headers_mut().insert(
+        header::RETRY_AFTER,
+        header::HeaderValue::from_static(RETRY_AFTER_SECS),
+    );
+    response
+}
+
+/// Compares two byte strings in time that depends only on their lengths, so that a token can't be
+/// guessed a byte at a time from how long rejections take
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+
+    a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+impl IngestState {
+    fn authorized(&self, headers: &HeaderMap) -> bool {
+        let Some(token) = &self.bearer_token else {
+            return true;
+        };
+
+        headers
+            .get(header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .is_some_and(|t| constant_time_eq(t.as_bytes(), token.as_bytes()))
+    }
+
+    /// Parses the body into a batch, applying the bad data policy to the request as a whole:
+    /// with `fail`, any invalid data rejects the request; with `drop`, only the invalid records
+    /// are dropped. Returns the batch and the number of dropped records.
+    async fn parse(&self, body: &[u8]) -> Result<(Option<RecordBatch>, usize), Response> {
+        let mut deserializer = ArrowDeserializer::new(
+            self.format.clone(),
+            self.schema.clone(),
+            &self.metadata_fields,
+            self.framing.clone(),
+            self.bad_data.clone(),
+        );
+
+        let mut errors = deserializer
+            .deserialize_slice(body, SystemTime::now(), None)
+            .await;
+        let (batch, flush_errors) = deserializer.flush_buffer();
+        errors.extend(flush_errors);
+
+        let mut dropped = 0;
+        for error in errors {
+            match (&self.bad_data, error) {
+                (BadData::Drop { .. }, DataflowError::DataError { count, .. }) => {
+                    dropped += count;
+                }
+                (_, DataflowError::DataError { details, .. }) => {
+                    return Err(error_response(StatusCode::BAD_REQUEST, details));
+                }
+                (_, e) => {
+                    return Err(error_response(
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        e.to_string(),
+                    ));
+                }
+            }
+        }
+
+        Ok((batch, dropped))
+    }
+}
+
+async fn ingest(
+    State(state): State<Arc<IngestState>>,
+    Path(table): Path<String>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    if table != state.path {
+        return error_response(StatusCode::NOT_FOUND, format!("no table '{table}'"));
+    }
+
+    if !state.authorized(&headers) {
+        return error_response(StatusCode::UNAUTHORIZED, "invalid or missing bearer token");
+    }
+
+    // reject before doing the work of parsing if we're already backed up
+    if state.tx.capacity() == 0 {
+        return unavailable(StatusCode::TOO_MANY_REQUESTS, "ingest queue is full");
+    }
+
+    let (batch, dropped) = match state.parse(&body).await {
+        Ok(parsed) => parsed,
+        Err(response) => return response,
+    };
+
+    let Some(batch) = batch else {
+        return (
+            StatusCode::OK,
+            axum::Json(json!({ "accepted": 0, "dropped": dropped })),
+        )
+            .into_response();
+    };
+    let accepted = batch.num_rows();
+
+    let (ack_tx, ack_rx) = oneshot::channel();
+    match state.tx.try_send((batch, ack_tx)) {
+        Ok(()) => {}
+        Err(TrySendError::Full(_)) => {
+            return unavailable(StatusCode::TOO_MANY_REQUESTS, "ingest queue is full");
+        }
+        Err(TrySendError::Closed(_)) => {
+            return unavailable(StatusCode::SERVICE_UNAVAILABLE, "source is not running");
+        }
+    }
+
+    if ack_rx.await.is_err() {
+        return unavailable(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "source stopped before the request was ingested",
+        );
+    }
+
+    (
+        StatusCode::OK,
+        axum::Json(json!({ "accepted": accepted, "dropped": dropped })),
+    )
+        .into_response()
+}
+
+#[async_trait]
+impl SourceOperator for HttpIngestSourceFunc {
+    fn name(&self) -> String {
+        format!("http-ingest-{}", self.path)
+    }
+
+    async fn run(
+        &mut self,
+        ctx: &mut SourceContext,
+        collector: &mut SourceCollector,
+    ) -> DataflowResult<SourceFinishType> {
+        let (tx, rx) = mpsc::channel(self.max_queued_requests);
+
+        let state = Arc::new(IngestState {
+            path: self.path.clone(),
+            bearer_token: self.bearer_token.clone(),
+            format: self.format.clone(),
+            framing: self.framing.clone(),
+            bad_data: self.bad_data.clone().unwrap_or_default(),
+            metadata_fields: self.metadata_fields.clone(),
+            schema: ctx.out_schema.clone(),
+            tx,
+        });
+
+        let port = self
+            .port
+            .checked_add(ctx.task_info.task_index as u16)
+            .ok_or_else(|| {
+                connector_err!(
+                    User,
+                    NoRetry,
+                    "port {} is too large for subtask {}",
+                    self.port,
+                    ctx.task_info.task_index
+                )
+            })?;
+        let address = format!("{}:{}", self.bind_address, port);
+        let listener = TcpListener::bind(&address).await.map_err(|e| {
+            connector_err!(
+                External,
+                WithBackoff,
+                "failed to listen on {}: {}",
+                address,
+                e
+            )
+        })?;
+        info!("listening for HTTP ingest requests on {}", address);
+
+        let app = Router::new()
+            .route("/ingest/:table", post(ingest))
+            .with_state(state);
+
+        // the server shuts down once this is dropped, when the source finishes
+        let (_shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+        tokio::spawn(async move {
+            let server = axum::serve(listener, app).with_graceful_shutdown(async {
+                let _ = shutdown_rx.await;
+            });
+            if let Err(e) = server.await {
+                tracing::error!("HTTP ingest server failed: {}", e);
+            }
+        });
+
+        self.run_int(rx, ctx, collector).await
+    }
+}
+
+impl HttpIngestSourceFunc {
+    async fn run_int(
+        &mut self,
+        mut rx: mpsc::Receiver<IngestRequest>,
+        ctx: &mut SourceContext,
+        collector: &mut SourceCollector,
+    ) -> DataflowResult<SourceFinishType> {
+        loop {
+            select! {
+                Some((batch, ack)) = rx.recv() => {
+                    collector.collect(batch).await?;
+                    // the client may have disconnected, in which case there's no one to tell
+                    let _ = ack.send(());
+                }
+                control_message = ctx.control_rx.recv() => {
+                    match control_message {
+                        Some(ControlMessage::Checkpoint(c)) => {
+                            debug!("starting checkpointing {}", ctx.task_info.task_index);
+                            if self.start_checkpoint(c, ctx, collector).await {
+                                return Ok(SourceFinishType::Immediate);
+                            }
+                        }
+                        Some(ControlMessage::Stop { mode }) => {
+                            info!("Stopping HTTP ingest source: {:?}", mode);
+
+                            match mode {
+                                StopMode::Graceful => {
+                                    return Ok(SourceFinishType::Graceful);
+                                }
+                                StopMode::Immediate => {
+                                    return Ok(SourceFinishType::Immediate);
+                                }
+                            }
+                        }
+                        Some(ControlMessage::Commit { .. }) => {
+                            unreachable!("sources shouldn't receive commit messages");
+                        }
+                        Some(ControlMessage::LoadCompacted { compacted }) => {
+                            ctx.load_compacted(compacted).await;
+                        }
+                        Some(ControlMessage::NoOp) => {}
+                        None => {
+                            info!("control channel closed, stopping HTTP ingest source");
+                            return Ok(SourceFinishType::Immediate);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::datatypes::{DataType, Field};
+    use arroyo_rpc::formats::JsonFormat;
+
+    fn state(
+        bearer_token: Option<&str>,
+        max_queued_requests: usize,
+    ) -> (Arc<IngestState>, mpsc::Receiver<IngestRequest>) {
+        let (tx, rx) = mpsc::channel(max_queued_requests);
+        let state = IngestState {
+            path: "events".to_string(),
+            bearer_token: bearer_token.map(|t| t.to_string()),
+            format: Format::Json(JsonFormat::default()),
+            framing: None,
+            bad_data: BadData::default(),
+            metadata_fields: vec![],
+            schema: Arc::new(ArroyoSchema::from_fields(vec![Field::new(
+                "value",
+                DataType::Int64,
+                false,
+            )])),
+            tx,
+        };
+
+        (Arc::new(state), rx)
+    }
+
+    fn auth_headers(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::AUTHORIZATION, value.parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_constant_time_eq() {
+        assert!(constant_time_eq(b"secret", b"secret"));
+        assert!(constant_time_eq(b"", b""));
+        assert!(!constant_time_eq(b"secret", b"secreT"));
+        assert!(!constant_time_eq(b"secret", b"secret2"));
+        assert!(!constant_time_eq(b"", b"secret"));
+    }
+
+    #[test]
+    fn test_authorized() {
+        let (open, _rx) = state(None, 1);
+        assert!(open.authorized(&HeaderMap::new()));
+        assert!(open.authorized(&auth_headers("Bearer anything")));
+
+        let (secured, _rx) = state(Some("s3cret"), 1);
+        assert!(secured.authorized(&auth_headers("Bearer s3cret")));
+        assert!(!secured.authorized(&HeaderMap::new()));
+        assert!(!secured.authorized(&auth_headers("Bearer wrong")));
+        assert!(!secured.authorized(&auth_headers("Bearer s3cret2")));
+        assert!(!secured.authorized(&auth_headers("Bearer s3cre")));
+        assert!(!secured.authorized(&auth_headers("Basic s3cret")));
+        assert!(!secured.authorized(&auth_headers("s3cret")));
+    }
+
+    #[tokio::test]
+    async fn test_rejects_unauthorized_requests() {
+        let (state, _rx) = state(Some("s3cret"), 1);
+
+        let response = ingest(
+            State(state),
+            Path("events".to_string()),
+            auth_headers("Bearer wrong"),
+            Bytes::from_static(b"{\"value\": 1}"),
+        )
+        .await;
+
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_rejects_when_queue_is_full() {
+        let (state, mut rx) = state(None, 1);
+
+        // fill the queue without anything draining it
+        let (ack_tx, _ack_rx) = oneshot::channel();
+        state
+            .tx
+            .try_send((RecordBatch::new_empty(state.schema.schema.clone()), ack_tx))
+            .unwrap();
+
+        let response = ingest(
+            State(state.clone()),
+            Path("events".to_string()),
+            HeaderMap::new(),
+            Bytes::from_static(b"{\"value\": 1}"),
+        )
+        .await;
+
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(
+            response.headers().get(header::RETRY_AFTER).unwrap(),
+            RETRY_AFTER_SECS
+        );
+
+        // nothing was queued for the rejected request
+        assert!(rx.try_recv().is_ok());
+        assert!(rx.try_recv().is_err());
+    }
+}
@@ -0,0 +1,274 @@
+use arrow::array::{Array, ArrayRef, AsArray, RecordBatch};
+use arrow::datatypes::DataType;
+use arroyo_formats::de::{ArrowDeserializer, FieldValueType};
+use arroyo_operator::connector::LookupConnector;
+use arroyo_rpc::errors::DataflowError;
+use arroyo_rpc::{MetadataField, connector_err, retry};
+use arroyo_types::LOOKUP_KEY_INDEX_FIELD;
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::{StreamExt, stream};
+use reqwest::{Client, Method, StatusCode};
+use std::collections::HashMap;
+use std::time::Duration;
+use tracing::warn;
+
+const RETRY_BASE: Duration = Duration::from_millis(100);
+const RETRY_MAX_DELAY: Duration = Duration::from_secs(5);
+
+/// Looks up keys by making a request per key (or per batch of keys) against an HTTP endpoint
+pub struct HttpLookup {
+    pub(crate) requester: HttpRequester,
+    pub(crate) deserializer: ArrowDeserializer,
+    pub(crate) metadata_fields: Vec<MetadataField>,
+}
+
+pub(crate) struct HttpRequester {
+    pub(crate) client: Client,
+    pub(crate) method: Method,
+    pub(crate) endpoint: String,
+    pub(crate) body: Option<String>,
+    pub(crate) batch_size: Option<usize>,
+    pub(crate) max_concurrency: usize,
+    pub(crate) max_retries: u32,
+}
+
+struct RequestError {
+    retryable: bool,
+    message: String,
+}
+
+/// Replaces the `{key}` placeholder in a URL template with the URL-encoded key
+pub(crate) fn substitute_url(template: &str, key: &str) -> String {
+    let encoded: String = url::form_urlencoded::byte_serialize(key.as_bytes()).collect();
+    template.replace("{key}", &encoded.replace('+', "%20"))
+}
+
+async fn request(
+    client: &Client,
+    method: &Method,
+    url: &str,
+    body: Option<&str>,
+) -> Result<Option<Bytes>, RequestError> {
+    let mut req = client.request(method.clone(), url);
+    if let Some(body) = body {
+        req = req.body(body.to_string());
+    }
+
+    let resp = req.send().await.map_err(|e| RequestError {
+        retryable: true,
+        message: format!("request to {url} failed: {e}"),
+    })?;
+
+    let status = resp.status();
+    if status == StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+
+    if !status.is_success() {
+        let body = resp.text().await.unwrap_or_default();
+        return Err(RequestError {
+            retryable: status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS,
+            message: format!("request to {url} failed with status {status}: {body}"),
+        });
+    }
+
+    resp.bytes().await.map(Some).map_err(|e| RequestError {
+        retryable: true,
+        message: format!("failed to read response from {url}: {e}"),
+    })
+}
+
+async fn fetch_with_retries(
+    client: &Client,
+    method: &Method,
+    url: String,
+    body: Option<String>,
+    max_retries: u32,
+) -> Result<Option<Bytes>, DataflowError> {
+    retry!(
+        request(client, method, &url, body.as_deref()).await,
+        max_retries,
+        RETRY_BASE,
+        RETRY_MAX_DELAY,
+        |e| warn!("HTTP lookup failed, retrying: {}", e.message),
+        |e: &RequestError| e.retryable
+    )
+    .map_err(|e| {
+        if e.retryable {
+            connector_err!(External, WithBackoff, "{}", e.message)
+        } else {
+            connector_err!(User, NoRetry, "{}", e.message)
+        }
+    })
+}
+
+impl HttpRequester {
+    /// Fetches the response for each key, which is None for null keys and keys the server
+    /// doesn't know about
+    async fn fetch(&self, keys: &[Option<&str>]) -> Result<Vec<Option<Bytes>>, DataflowError> {
+        match self.batch_size {
+            Some(batch_size) => self.fetch_batched(keys, batch_size).await,
+            None => self.fetch_each(keys).await,
+        }
+    }
+
+    async fn fetch_each(&self, keys: &[Option<&str>]) -> Result<Vec<Option<Bytes>>, DataflowError> {
+        stream::iter(keys.iter().map(|key| async move {
+            let Some(key) = key else {
+                return Ok(None);
+            };
+
+            fetch_with_retries(
+                &self.client,
+                &self.method,
+                substitute_url(&self.endpoint, key),
+                self.body.as_ref().map(|b| b.replace("{key}", key)),
+                self.max_retries,
+            )
+            .await
+        }))
+        .buffered(self.max_concurrency)
+        .collect::<Vec<_>>()
+        .await
+        .into_iter()
+        .collect()
+    }
+
+    /// Sends a batch of keys as a JSON array, expecting back a JSON array containing the value
+    /// (or null) for each key, in order
+    async fn fetch_batch(&self, keys: &[&str]) -> Result<Vec<Option<Bytes>>, DataflowError> {
+        let body = serde_json::to_string(keys).unwrap();
+
+        let Some(resp) = fetch_with_retries(
+            &self.client,
+            &self.method,
+            self.endpoint.clone(),
+            Some(body),
+            self.max_retries,
+        )
+        .await?
+        else {
+            return Ok(vec![None; keys.len()]);
+        };
+
+        let values: Vec<serde_json::Value> = serde_json::from_slice(&resp).map_err(|e| {
+            connector_err!(
+                External,
+                NoRetry,
+                "batched HTTP lookup response was not a JSON array: {}",
+                e
+            )
+        })?;
+
+        if values.len() != keys.len() {
+            return Err(connector_err!(
+                External,
+                NoRetry,
+                "batched HTTP lookup returned {} values for {} keys",
+                values.len(),
+                keys.len()
+            ));
+        }
+
+        Ok(values
+            .into_iter()
+            .map(|v| (!v.is_null()).then(|| Bytes::from(serde_json::to_vec(&v).unwrap())))
+            .collect())
+    }
+
+    async fn fetch_batched(
+        &self,
+        keys: &[Option<&str>],
+        batch_size: usize,
+    ) -> Result<Vec<Option<Bytes>>, DataflowError> {
+        let present: Vec<(usize, &str)> = keys
+            .iter()
+            .enumerate()
+            .filter_map(|(i, k)| Some((i, (*k)?)))
+            .collect();
+
+        let chunks: Vec<Vec<&str>> = present
+            .chunks(batch_size)
+            .map(|c| c.iter().map(|(_, k)| *k).collect())
+            .collect();
+
+        let batches = stream::iter(chunks.iter().map(|chunk| self.fetch_batch(chunk)))
+            .buffered(self.max_concurrency)
+            .collect::<Vec<_>>()
+            .await
+            .into_iter()
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut results = vec![None; keys.len()];
+        for ((i, _), value) in present.iter().zip(batches.into_iter().flatten()) {
+            results[*i] = value;
+        }
+
+        Ok(results)
+    }
+}
+
+#[async_trait]
+impl LookupConnector for HttpLookup {
+    fn name(&self) -> String {
+        "HttpLookup".to_string()
+    }
+
+    async fn lookup(&mut self, keys: &[ArrayRef]) -> Option<Result<RecordBatch, DataflowError>> {
+        assert_eq!(keys.len(), 1, "HTTP lookup can only have a single key");
+        assert_eq!(
+            *keys[0].data_type(),
+            DataType::Utf8,
+            "HTTP lookup key must be a string"
+        );
+
+        let keys: Vec<_> = keys[0].as_string::<i32>().iter().collect();
+
+        let responses = match self.requester.fetch(&keys).await {
+            Ok(responses) => responses,
+            Err(e) => return Some(Err(e)),
+        };
+
+        let mut additional = HashMap::new();
+
+        for (idx, (response, key)) in responses.iter().zip(&keys).enumerate() {
+            additional.insert(
+                LOOKUP_KEY_INDEX_FIELD,
+                FieldValueType::UInt64(Some(idx as u64)),
+            );
+            for m in &self.metadata_fields {
+                additional.insert(
+                    m.field_name.as_str(),
+                    match m.key.as_str() {
+                        "key" => FieldValueType::String(*key),
+                        k => unreachable!("Invalid metadata key '{}'", k),
+                    },
+                );
+            }
+
+            let errors = match response {
+                Some(body) => {
+                    self.deserializer
+                        .deserialize_without_timestamp(body, Some(&additional))
+                        .await
+                }
+                None => {
+                    self.deserializer.deserialize_null(Some(&additional));
+                    vec![]
+                }
+            };
+
+            if let Some(error) = errors.into_iter().next() {
+                return Some(Err(error));
+            }
+        }
+
+        let (batch, mut errors) = self.deserializer.flush_buffer();
+        if let Some(error) = errors.pop() {
+            Some(Err(error))
+        } else {
+            batch.map(Ok)
+        }
+    }
+}
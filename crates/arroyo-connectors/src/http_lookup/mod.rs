@@ -0,0 +1,291 @@
+mod lookup;
+
+use crate::http_lookup::lookup::{HttpLookup, HttpRequester, substitute_url};
+use crate::{EmptyConfig, construct_http_client};
+use anyhow::{anyhow, bail};
+use arrow::datatypes::{DataType, Schema};
+use arroyo_formats::de::ArrowDeserializer;
+use arroyo_operator::connector::{Connection, Connector, LookupConnector, MetadataDef};
+use arroyo_operator::operator::ConstructedOperator;
+use arroyo_rpc::api_types::connections::{
+    ConnectionProfile, ConnectionSchema, ConnectionType, TestSourceMessage,
+};
+use arroyo_rpc::formats::Format;
+use arroyo_rpc::schema_resolver::FailingSchemaResolver;
+use arroyo_rpc::var_str::VarStr;
+use arroyo_rpc::{ConnectorOptions, OperatorConfig};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::mpsc::Sender;
+use typify::import_types;
+
+const TABLE_SCHEMA: &str = include_str!("./table.json");
+const ICON: &str = include_str!("../polling_http/http.svg");
+
+const DEFAULT_MAX_CONCURRENCY: usize = 8;
+const DEFAULT_MAX_RETRIES: u32 = 3;
+
+import_types!(
+    schema = "src/http_lookup/table.json",
+    convert = { {type = "string", format = "var-str"} = VarStr }
+);
+
+pub struct HttpLookupConnector {}
+
+impl HttpLookupTable {
+    fn headers(&self) -> anyhow::Result<Option<String>> {
+        self.headers.as_ref().map(|h| h.sub_env_vars()).transpose()
+    }
+
+    fn method(&self) -> reqwest::Method {
+        match self.method {
+            // batched keys are sent in the body, so those requests default to POST
+            None if self.batch_size.is_some() => reqwest::Method::POST,
+            None | Some(Method::Get) => reqwest::Method::GET,
+            Some(Method::Post) => reqwest::Method::POST,
+            Some(Method::Put) => reqwest::Method::PUT,
+        }
+    }
+}
+
+fn positive(name: &str, value: Option<i64>) -> anyhow::Result<Option<usize>> {
+    value
+        .map(|v| {
+            usize::try_from(v)
+                .ok()
+                .filter(|v| *v > 0)
+                .ok_or_else(|| anyhow!("'{}' must be a positive integer", name))
+        })
+        .transpose()
+}
+
+impl Connector for HttpLookupConnector {
+    type ProfileT = EmptyConfig;
+    type TableT = HttpLookupTable;
+
+    fn name(&self) -> &'static str {
+        "http_lookup"
+    }
+
+    fn metadata(&self) -> arroyo_rpc::api_types::connections::Connector {
+        arroyo_rpc::api_types::connections::Connector {
+            id: "http_lookup".to_string(),
+            name: "HTTP Lookup".to_string(),
+            icon: ICON.to_string(),
+            description: "Enrich streams with data fetched from REST APIs".to_string(),
+            enabled: true,
+            source: false,
+            sink: false,
+            testing: true,
+            hidden: false,
+            custom_schemas: true,
+            connection_config: None,
+            table_config: TABLE_SCHEMA.to_owned(),
+        }
+    }
+
+    fn metadata_defs(&self) -> &'static [MetadataDef] {
+        &[MetadataDef {
+            name: "key",
+            data_type: DataType::Utf8,
+        }]
+    }
+
+    fn table_type(&self, _: Self::ProfileT, _: Self::TableT) -> ConnectionType {
+        ConnectionType::Lookup
+    }
+
+    fn get_schema(
+        &self,
+        _: Self::ProfileT,
+        _: Self::TableT,
+        s: Option<&ConnectionSchema>,
+    ) -> Option<ConnectionSchema> {
+        s.cloned()
+    }
+
+    fn test(
+        &self,
+        _: &str,
+        _: Self::ProfileT,
+        table: Self::TableT,
+        _: Option<&ConnectionSchema>,
+        tx: Sender<TestSourceMessage>,
+    ) {
+        tokio::task::spawn(async move {
+            let message = match table
+                .headers()
+                .and_then(|h| construct_http_client(&substitute_url(&table.endpoint, "key"), h))
+            {
+                Ok(_) => TestSourceMessage::done("Successfully validated lookup table"),
+                Err(err) => TestSourceMessage::fail(format!("{}", err.root_cause())),
+            };
+
+            tx.send(message).await.unwrap();
+        });
+    }
+
+    fn from_options(
+        &self,
+        name: &str,
+        options: &mut ConnectorOptions,
+        schema: Option<&ConnectionSchema>,
+        _: Option<&ConnectionProfile>,
+    ) -> anyhow::Result<Connection> {
+        let typ = options.pull_str("type")?;
+        if typ != "lookup" {
+            bail!(
+                "'{}' is not a valid type; HTTP lookup tables can only be used as lookup tables, created with CREATE TEMPORARY TABLE",
+                typ
+            );
+        }
+
+        let method: Option<Method> = options
+            .pull_opt_str("method")?
+            .map(|s| s.to_uppercase().try_into())
+            .transpose()
+            .map_err(|_| anyhow!("invalid value for 'method'; must be one of GET, POST, or PUT"))?;
+
+        let table = HttpLookupTable {
+            endpoint: options.pull_str("endpoint")?,
+            method,
+            headers: options.pull_opt_str("headers")?.map(VarStr::new),
+            body: options.pull_opt_str("body")?,
+            batch_size: options.pull_opt_i64("batch_size")?,
+            max_concurrency: options.pull_opt_i64("max_concurrency")?,
+            max_retries: options.pull_opt_i64("max_retries")?,
+        };
+
+        self.from_config(None, name, EmptyConfig {}, table, schema)
+    }
+
+    fn from_config(
+        &self,
+        id: Option<i64>,
+        name: &str,
+        config: Self::ProfileT,
+        table: Self::TableT,
+        schema: Option<&ConnectionSchema>,
+    ) -> anyhow::Result<Connection> {
+        let schema = schema
+            .map(|s| s.to_owned())
+            .ok_or_else(|| anyhow!("no schema defined for HTTP lookup table"))?;
+
+        let format = schema
+            .format
+            .as_ref()
+            .map(|t| t.to_owned())
+            .ok_or_else(|| anyhow!("'format' must be set for HTTP lookup table"))?;
+
+        // lookups are made against the key metadata field, which is substituted into the request
+        for f in &schema.fields {
+            if schema.primary_keys.contains(&f.name)
+                && f.metadata_key.as_ref().map(|k| k != "key").unwrap_or(true)
+            {
+                bail!(
+                    "HTTP lookup tables must have a PRIMARY KEY field defined as \
+                    `field_name TEXT METADATA FROM 'key'`"
+                );
+            }
+        }
+
+        if table.batch_size.is_some() {
+            if !matches!(format, Format::Json(_)) {
+                bail!("'batch_size' can only be used with format 'json' for HTTP lookup tables");
+            }
+            if table.body.is_some() {
+                bail!("'body' cannot be set for a batched HTTP lookup table");
+            }
+            if table.endpoint.contains("{key}") {
+                bail!(
+                    "the endpoint of a batched HTTP lookup table cannot contain a {{key}} placeholder, \
+                    as the keys are sent in the request body"
+                );
+            }
+        } else if !table.endpoint.contains("{key}")
+            && !table.body.as_ref().is_some_and(|b| b.contains("{key}"))
+        {
+            bail!(
+                "the endpoint or body of an HTTP lookup table must contain a {{key}} placeholder, \
+                which is replaced by the lookup key"
+            );
+        }
+
+        positive("batch_size", table.batch_size)?;
+        positive("max_concurrency", table.max_concurrency)?;
+        if table.max_retries.is_some_and(|r| r < 0) {
+            bail!("'max_retries' must not be negative");
+        }
+
+        construct_http_client(&substitute_url(&table.endpoint, "key"), table.headers()?)?;
+
+        let description = format!("HttpLookup<{}>", table.endpoint);
+
+        let config = OperatorConfig {
+            connection: serde_json::to_value(config).unwrap(),
+            table: serde_json::to_value(table).unwrap(),
+            rate_limit: None,
+            format: Some(format),
+            bad_data: schema.bad_data.clone(),
+            framing: schema.framing.clone(),
+            metadata_fields: schema.metadata_fields(),
+            partition_watermarks: None,
+        };
+
+        Ok(Connection::new(
+            id,
+            self.name(),
+            name.to_string(),
+            ConnectionType::Lookup,
+            schema,
+            &config,
+            description,
+        ))
+    }
+
+    fn make_operator(
+        &self,
+        _: Self::ProfileT,
+        _: Self::TableT,
+        _: OperatorConfig,
+    ) -> anyhow::Result<ConstructedOperator> {
+        bail!("Cannot construct a lookup table as an operator");
+    }
+
+    fn make_lookup(
+        &self,
+        _: Self::ProfileT,
+        table: Self::TableT,
+        config: OperatorConfig,
+        schema: Arc<Schema>,
+    ) -> anyhow::Result<Box<dyn LookupConnector + Send>> {
+        let client =
+            construct_http_client(&substitute_url(&table.endpoint, "key"), table.headers()?)?;
+
+        Ok(Box::new(HttpLookup {
+            requester: HttpRequester {
+                client,
+                method: table.method(),
+                batch_size: positive("batch_size", table.batch_size)?,
+                max_concurrency: positive("max_concurrency", table.max_concurrency)?
+                    .unwrap_or(DEFAULT_MAX_CONCURRENCY),
+                max_retries: table
+                    .max_retries
+                    .map(|r| r.clamp(0, u32::MAX as i64) as u32)
+                    .unwrap_or(DEFAULT_MAX_RETRIES),
+                endpoint: table.endpoint,
+                body: table.body,
+            },
+            deserializer: ArrowDeserializer::for_lookup(
+                config
+                    .format
+                    .ok_or_else(|| anyhow!("HTTP lookup table must have a format"))?,
+                schema,
+                &config.metadata_fields,
+                config.bad_data.unwrap_or_default(),
+                Arc::new(FailingSchemaResolver::new()),
+            ),
+            metadata_fields: config.metadata_fields,
+        }))
+    }
+}
@@ -155,6 +155,7 @@ impl Connector for ImpulseConnector {
             bad_data: None,
             framing: None,
             metadata_fields: vec![],
+            partition_watermarks: None,
         };
 
         Ok(Connection::new(
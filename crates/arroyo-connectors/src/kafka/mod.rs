@@ -143,6 +143,8 @@ impl KafkaConnector {
                     },
                     timestamp_field: options.pull_opt_str("sink.timestamp_field")?,
                     key_field: options.pull_opt_str("sink.key_field")?,
+                    headers_field: options.pull_opt_str("sink.headers_field")?,
+                    topic_field: options.pull_opt_str("sink.topic_field")?,
                 }
             }
             _ => {
@@ -230,6 +232,7 @@ impl Connector for KafkaConnector {
             bad_data: schema.bad_data.clone(),
             framing: schema.framing.clone(),
             metadata_fields: schema.metadata_fields(),
+            partition_watermarks: None,
         };
 
         Ok(Connection::new(
@@ -322,6 +325,17 @@ impl Connector for KafkaConnector {
         }
     }
 
+    fn accepts_updating_input(&self, _: Self::ProfileT, table: Self::TableT) -> bool {
+        // retractions are written as tombstones, which need a key to apply to
+        matches!(
+            table.type_,
+            TableType::Sink {
+                key_field: Some(_),
+                ..
+            }
+        )
+    }
+
     fn metadata_defs(&self) -> &'static [MetadataDef] {
         &[
             MetadataDef {
@@ -427,6 +441,7 @@ impl Connector for KafkaConnector {
                         )
                         .unwrap(),
                         metadata_fields: config.metadata_fields,
+                        partition_watermarks: config.partition_watermarks,
                     },
                 )))
             }
@@ -434,6 +449,8 @@ impl Connector for KafkaConnector {
                 commit_mode,
                 key_field,
                 timestamp_field,
+                headers_field,
+                topic_field,
             } => Ok(ConstructedOperator::from_operator(Box::new(
                 KafkaSinkFunc {
                     bootstrap_servers: profile.bootstrap_servers.to_string(),
@@ -443,6 +460,11 @@ impl Connector for KafkaConnector {
                     timestamp_col: None,
                     key_field: key_field.clone(),
                     key_col: None,
+                    headers_field: headers_field.clone(),
+                    headers_col: None,
+                    topic_field: topic_field.clone(),
+                    topic_col: None,
+                    updating_meta_col: None,
                     write_futures: vec![],
                     client_config: client_configs(&profile, Some(table.clone()))?,
                     context: Context::new(Some(profile.clone())),
@@ -9,17 +9,19 @@ use std::collections::HashMap;
 use std::fmt::{Display, Formatter};
 use tracing::{error, warn};
 
+use rdkafka::message::{Header, OwnedHeaders};
 use rdkafka::producer::{DeliveryFuture, FutureRecord, Producer};
 use rdkafka::util::Timeout;
 
 use rdkafka::ClientConfig;
 
-use arrow::array::{Array, AsArray, RecordBatch};
+use arrow::array::{Array, AsArray, BooleanArray, RecordBatch, StructArray};
 use arrow::datatypes::{DataType, TimeUnit};
 use arroyo_formats::ser::ArrowSerializer;
 use arroyo_operator::context::{Collector, OperatorContext};
 use arroyo_operator::operator::{ArrowOperator, AsDisplayable, DisplayableOperator};
 use arroyo_rpc::df::ArroyoSchema;
+use arroyo_rpc::{UPDATING_META_FIELD, connector_err};
 use arroyo_types::CheckpointBarrier;
 use async_trait::async_trait;
 use prost::Message;
@@ -39,6 +41,12 @@ pub struct KafkaSinkFunc {
     pub timestamp_col: Option<usize>,
     pub key_field: Option<String>,
     pub key_col: Option<usize>,
+    pub headers_field: Option<String>,
+    pub headers_col: Option<usize>,
+    pub topic_field: Option<String>,
+    pub topic_col: Option<usize>,
+    // set when the input is an updating stream, in which case retractions are written as tombstones
+    pub updating_meta_col: Option<usize>,
     pub producer: Option<FutureProducer>,
     pub write_futures: Vec<DeliveryFuture>,
     pub client_config: HashMap<String, String>,
@@ -124,6 +132,58 @@ impl KafkaSinkFunc {
         }
     }
 
+    fn set_headers_col(&mut self, schema: &ArroyoSchema) {
+        if let Some(f) = &self.headers_field {
+            if let Ok(f) = schema.schema.field_with_name(f) {
+                if is_headers_type(f.data_type()) {
+                    self.headers_col = Some(schema.schema.index_of(f.name()).unwrap());
+                } else {
+                    warn!(
+                        "Kafka sink configured with headers_field '{f}', but it has type \
+                {}, not a map or struct of TEXT or BYTEA... ignoring",
+                        f.data_type()
+                    );
+                }
+            } else {
+                warn!(
+                    "Kafka sink configured with headers_field '{f}', but that \
+                does not appear in the schema... ignoring"
+                );
+            }
+        }
+    }
+
+    fn set_topic_col(&mut self, schema: &ArroyoSchema) {
+        if let Some(f) = &self.topic_field {
+            if let Ok(f) = schema.schema.field_with_name(f) {
+                if matches!(f.data_type(), DataType::Utf8) {
+                    self.topic_col = Some(schema.schema.index_of(f.name()).unwrap());
+                } else {
+                    warn!(
+                        "Kafka sink configured with topic_field '{f}', but it has type \
+                {}, not TEXT... ignoring",
+                        f.data_type()
+                    );
+                }
+            } else {
+                warn!(
+                    "Kafka sink configured with topic_field '{f}', but that \
+                does not appear in the schema... ignoring"
+                );
+            }
+        }
+    }
+
+    fn retracts(&self, batch: &RecordBatch) -> Option<BooleanArray> {
+        let meta: &StructArray = batch.column(self.updating_meta_col?).as_struct();
+        Some(
+            meta.column_by_name("is_retract")
+                .expect("meta struct must have is_retract")
+                .as_boolean()
+                .clone(),
+        )
+    }
+
     fn init_producer(&mut self, task_info: &TaskInfo) -> Result<()> {
         let mut client_config = ClientConfig::new();
         client_config.set("bootstrap.servers", &self.bootstrap_servers);
@@ -182,21 +242,30 @@ impl KafkaSinkFunc {
 
     async fn publish(
         &mut self,
+        topic: Option<&str>,
         ts: Option<i64>,
         k: Option<Vec<u8>>,
-        v: Vec<u8>,
+        v: Option<Vec<u8>>,
+        headers: Option<OwnedHeaders>,
         ctx: &mut OperatorContext,
     ) {
         let mut rec = {
-            let mut rec = FutureRecord::<Vec<u8>, Vec<u8>>::to(&self.topic);
+            let mut rec = FutureRecord::<Vec<u8>, Vec<u8>>::to(topic.unwrap_or(&self.topic));
             if let Some(ts) = ts {
                 rec = rec.timestamp(ts);
             }
             if let Some(k) = k.as_ref() {
                 rec = rec.key(k);
             }
+            if let Some(headers) = headers {
+                rec = rec.headers(headers);
+            }
 
-            rec.payload(&v)
+            // records without a payload are tombstones
+            if let Some(v) = v.as_ref() {
+                rec = rec.payload(v);
+            }
+            rec
         };
 
         loop {
@@ -244,6 +313,8 @@ impl ArrowOperator for KafkaSinkFunc {
                     AsDisplayable::Debug(&self.timestamp_field),
                 ),
                 ("key_field", AsDisplayable::Debug(&self.key_field)),
+                ("headers_field", AsDisplayable::Debug(&self.headers_field)),
+                ("topic_field", AsDisplayable::Debug(&self.topic_field)),
                 ("client_config", AsDisplayable::Debug(&self.client_config)),
             ],
         }
@@ -276,6 +347,17 @@ impl ArrowOperator for KafkaSinkFunc {
     async fn on_start(&mut self, ctx: &mut OperatorContext) -> DataflowResult<()> {
         self.set_timestamp_col(&ctx.in_schemas[0]);
         self.set_key_col(&ctx.in_schemas[0]);
+        self.set_headers_col(&ctx.in_schemas[0]);
+        self.set_topic_col(&ctx.in_schemas[0]);
+
+        self.updating_meta_col = ctx.in_schemas[0].schema.index_of(UPDATING_META_FIELD).ok();
+        if self.updating_meta_col.is_some() && self.key_col.is_none() {
+            return Err(connector_err!(
+                User,
+                NoRetry,
+                "Kafka sinks written to by updating queries must have a TEXT key_field, which retractions are written as tombstones for"
+            ));
+        }
 
         self.init_producer(&ctx.task_info)
             .expect("Producer creation failed");
@@ -298,8 +380,44 @@ impl ArrowOperator for KafkaSinkFunc {
             .downcast_ref::<arrow::array::TimestampNanosecondArray>();
 
         let keys = self.key_col.map(|i| batch.column(i).as_string::<i32>());
+        let topics = self.topic_col.map(|i| batch.column(i).as_string::<i32>());
+        let headers = self.headers_col.map(|i| batch.column(i));
+        let retracts = self.retracts(&batch);
+
+        // rows with a null topic are written to the sink's topic
+        let topic_at = |i: usize| topics.filter(|t| !t.is_null(i)).map(|t| t.value(i));
+        let key_at = |i: usize| keys.filter(|k| !k.is_null(i)).map(|k| k.value(i));
+
+        // a retraction followed by a new value for the same key in the same batch is an update,
+        // which doesn't need a tombstone as the new value replaces the old one
+        let mut last_append = HashMap::new();
+        if let Some(retracts) = &retracts {
+            for i in 0..batch.num_rows() {
+                if !retracts.value(i)
+                    && let Some(key) = key_at(i)
+                {
+                    last_append.insert((topic_at(i), key), i);
+                }
+            }
+        }
 
+        let mut null_key_retractions = 0;
         for (i, v) in values.enumerate() {
+            let topic = topic_at(i);
+            let key = key_at(i);
+            let is_retract = retracts.as_ref().is_some_and(|r| r.value(i));
+            if is_retract {
+                // a tombstone without a key wouldn't delete anything from a compacted topic
+                let Some(key) = key else {
+                    null_key_retractions += 1;
+                    continue;
+                };
+
+                if last_append.get(&(topic, key)).is_some_and(|j| *j > i) {
+                    continue;
+                }
+            }
+
             // kafka timestamp as unix millis
             let timestamp = timestamps.map(|ts| {
                 if ts.is_null(i) {
@@ -309,8 +427,17 @@ impl ArrowOperator for KafkaSinkFunc {
                 }
             });
             // TODO: this copy should be unnecessary but likely needs a custom trait impl
-            let key = keys.map(|k| k.value(i).as_bytes().to_vec());
-            self.publish(timestamp, key, v, ctx).await;
+            let key = key.map(|k| k.as_bytes().to_vec());
+            let headers = headers.and_then(|h| row_headers(h.as_ref(), i));
+            let value = (!is_retract).then_some(v);
+            self.publish(topic, timestamp, key, value, headers, ctx)
+                .await;
+        }
+
+        if null_key_retractions > 0 {
+            warn!(
+                "Kafka sink skipped {null_key_retractions} retractions with a null key, which can't be written as tombstones"
+            );
         }
         Ok(())
     }
@@ -402,3 +529,69 @@ impl ArrowOperator for KafkaSinkFunc {
         Ok(())
     }
 }
+
+fn is_header_value_type(data_type: &DataType) -> bool {
+    matches!(data_type, DataType::Utf8 | DataType::Binary)
+}
+
+fn is_headers_type(data_type: &DataType) -> bool {
+    match data_type {
+        DataType::Map(entries, _) => match entries.data_type() {
+            DataType::Struct(fields) => {
+                fields.len() == 2
+                    && matches!(fields[0].data_type(), DataType::Utf8)
+                    && is_header_value_type(fields[1].data_type())
+            }
+            _ => false,
+        },
+        DataType::Struct(fields) => fields.iter().all(|f| is_header_value_type(f.data_type())),
+        _ => false,
+    }
+}
+
+fn header_value(array: &dyn Array, i: usize) -> Option<&[u8]> {
+    if array.is_null(i) {
+        return None;
+    }
+
+    match array.data_type() {
+        DataType::Utf8 => Some(array.as_string::<i32>().value(i).as_bytes()),
+        DataType::Binary => Some(array.as_binary::<i32>().value(i)),
+        _ => unreachable!("header values must be TEXT or BYTEA"),
+    }
+}
+
+/// Builds the headers for row `i` from a map column (one header per entry) or a struct column
+/// (one header per field, named after it)
+fn row_headers(column: &dyn Array, i: usize) -> Option<OwnedHeaders> {
+    if column.is_null(i) {
+        return None;
+    }
+
+    let mut headers = OwnedHeaders::new();
+    match column.data_type() {
+        DataType::Map(..) => {
+            let entries = column.as_map().value(i);
+            let keys = entries.column(0).as_string::<i32>();
+            let values = entries.column(1);
+            for j in 0..entries.len() {
+                headers = headers.insert(Header {
+                    key: keys.value(j),
+                    value: header_value(values.as_ref(), j),
+                });
+            }
+        }
+        DataType::Struct(fields) => {
+            let columns = column.as_struct();
+            for (field, values) in fields.iter().zip(columns.columns()) {
+                headers = headers.insert(Header {
+                    key: field.name().as_str(),
+                    value: header_value(values.as_ref(), i),
+                });
+            }
+        }
+        _ => unreachable!("headers must be a map or struct"),
+    }
+
+    Some(headers)
+}
@@ -4,7 +4,10 @@ use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::{Duration, SystemTime};
 
-use arrow::array::{RecordBatch, UInt32Array};
+use arrow::array::{
+    BooleanArray, FixedSizeBinaryArray, MapBuilder, RecordBatch, StringArray, StringBuilder,
+    StructArray, UInt32Array,
+};
 use arrow::datatypes::Field;
 use arrow::datatypes::{DataType, Schema, SchemaRef};
 use arroyo_formats::ser::ArrowSerializer;
@@ -12,11 +15,13 @@ use arroyo_operator::context::OperatorContext;
 use arroyo_operator::operator::ArrowOperator;
 use arroyo_rpc::df::ArroyoSchema;
 use arroyo_rpc::formats::{Format, JsonFormat};
+use arroyo_rpc::{updating_meta_field, updating_meta_fields};
 use arroyo_types::CheckpointBarrier;
 use arroyo_types::*;
 use itertools::Itertools;
 use rdkafka::admin::{AdminClient, AdminOptions, NewTopic};
 use rdkafka::consumer::{Consumer, StreamConsumer};
+use rdkafka::message::{Headers, OwnedMessage};
 use rdkafka::producer::Producer;
 use rdkafka::{ClientConfig, Message};
 use serde::Deserialize;
@@ -46,6 +51,11 @@ struct TestData {
 
 impl KafkaTopicTester {
     async fn create_topic(&self, job_id: &str, num_partitions: i32) {
+        self.create_topic_named(&self.topic, job_id, num_partitions)
+            .await;
+    }
+
+    async fn create_topic_named(&self, topic: &str, job_id: &str, num_partitions: i32) {
         let admin_client: AdminClient<_> = ClientConfig::new()
             .set("bootstrap.servers", self.server.to_string())
             .set("enable.auto.commit", "false")
@@ -54,14 +64,14 @@ impl KafkaTopicTester {
             .create()
             .unwrap();
         admin_client
-            .delete_topics(&[&self.topic], &AdminOptions::new())
+            .delete_topics(&[topic], &AdminOptions::new())
             .await
             .expect("deletion should have worked");
         tokio::time::sleep(Duration::from_secs(1)).await;
         admin_client
             .create_topics(
                 [&NewTopic::new(
-                    &self.topic,
+                    topic,
                     num_partitions,
                     rdkafka::admin::TopicReplication::Fixed(1),
                 )],
@@ -72,6 +82,15 @@ impl KafkaTopicTester {
     }
 
     async fn get_sink_with_writes(&self) -> KafkaSinkWithWrites {
+        self.get_configured_sink(schema(), |_| {}).await
+    }
+
+    /// Creates a sink for input with `schema`, after `configure` has set its fields
+    async fn get_configured_sink(
+        &self,
+        schema: SchemaRef,
+        configure: impl FnOnce(&mut KafkaSinkFunc),
+    ) -> KafkaSinkWithWrites {
         let mut kafka = KafkaSinkFunc {
             topic: self.topic.to_string(),
             bootstrap_servers: self.server.to_string(),
@@ -85,7 +104,13 @@ impl KafkaTopicTester {
             context: Context::new(None),
             serializer: ArrowSerializer::new(Format::Json(JsonFormat::default())),
             key_col: None,
+            headers_field: None,
+            headers_col: None,
+            topic_field: None,
+            topic_col: None,
+            updating_meta_col: None,
         };
+        configure(&mut kafka);
 
         let (command_tx, _) = channel(128);
 
@@ -96,7 +121,7 @@ impl KafkaTopicTester {
             None,
             command_tx,
             1,
-            vec![Arc::new(ArroyoSchema::new_unkeyed(schema(), 0))],
+            vec![Arc::new(ArroyoSchema::new_unkeyed(schema, 0))],
             None,
             HashMap::new(),
         )
@@ -108,6 +133,11 @@ impl KafkaTopicTester {
     }
 
     fn get_consumer(&mut self, job_id: &str) -> StreamConsumer {
+        let topic = self.topic.clone();
+        self.get_consumer_for(job_id, &[&topic])
+    }
+
+    fn get_consumer_for(&mut self, job_id: &str, topics: &[&str]) -> StreamConsumer {
         let base_consumer: StreamConsumer = ClientConfig::new()
             .set("bootstrap.servers", self.server.to_string())
             .set("enable.auto.commit", "false")
@@ -118,7 +148,7 @@ impl KafkaTopicTester {
             .create()
             .expect("Consumer creation failed");
 
-        base_consumer.subscribe(&[&self.topic]).expect("success");
+        base_consumer.subscribe(topics).expect("success");
         base_consumer
     }
 }
@@ -133,11 +163,34 @@ async fn get_data(consumer: &mut StreamConsumer) -> String {
     String::from_utf8(payload.to_vec()).unwrap()
 }
 
+async fn get_message(consumer: &mut StreamConsumer) -> OwnedMessage {
+    consumer
+        .recv()
+        .await
+        .expect("shouldn't have errored")
+        .detach()
+}
+
 struct KafkaSinkWithWrites {
     sink: KafkaSinkFunc,
     ctx: OperatorContext,
 }
 
+impl KafkaSinkWithWrites {
+    async fn write(&mut self, batch: RecordBatch) {
+        self.sink
+            .process_batch(batch, &mut self.ctx, &mut DummyCollector {})
+            .await
+            .unwrap();
+        self.sink
+            .producer
+            .as_ref()
+            .unwrap()
+            .flush(Duration::from_secs(3))
+            .unwrap();
+    }
+}
+
 #[tokio::test]
 async fn test_kafka_checkpoint_flushes() {
     let mut kafka_topic_tester = KafkaTopicTester {
@@ -164,6 +217,7 @@ async fn test_kafka_checkpoint_flushes() {
         min_epoch: 0,
         timestamp: SystemTime::now(),
         then_stop: false,
+        unaligned: false,
     };
     sink_with_writes
         .sink
@@ -210,3 +264,229 @@ async fn test_kafka() {
         assert_eq!(message, result.value);
     }
 }
+
+#[tokio::test]
+async fn test_kafka_headers() {
+    let mut kafka_topic_tester = KafkaTopicTester {
+        topic: "arroyo-sink-headers".to_string(),
+        server: "0.0.0.0:9092".to_string(),
+    };
+    kafka_topic_tester.create_topic("headers", 1).await;
+
+    let mut headers = MapBuilder::new(None, StringBuilder::new(), StringBuilder::new());
+    for i in 0..3 {
+        if i == 2 {
+            headers.append(false).unwrap();
+        } else {
+            headers.keys().append_value("id");
+            headers.values().append_value(format!("id-{i}"));
+            headers.keys().append_value("empty");
+            headers.values().append_null();
+            headers.append(true).unwrap();
+        }
+    }
+    let headers = headers.finish();
+
+    let schema = Arc::new(Schema::new(vec![
+        Field::new("value", DataType::UInt32, false),
+        Field::new("headers", headers.data_type().clone(), true),
+    ]));
+
+    let mut sink_with_writes = kafka_topic_tester
+        .get_configured_sink(schema.clone(), |sink| {
+            sink.headers_field = Some("headers".to_string());
+        })
+        .await;
+    let mut consumer = kafka_topic_tester.get_consumer("headers");
+
+    let batch = RecordBatch::try_new(
+        schema,
+        vec![
+            Arc::new(UInt32Array::from_iter_values(0..3)),
+            Arc::new(headers),
+        ],
+    )
+    .unwrap();
+    sink_with_writes.write(batch).await;
+
+    for i in 0..2 {
+        let message = get_message(&mut consumer).await;
+        let headers = message.headers().expect("message should have headers");
+        let headers: Vec<_> = headers
+            .iter()
+            .map(|h| (h.key.to_string(), h.value.map(|v| v.to_vec())))
+            .collect();
+        assert_eq!(
+            headers,
+            vec![
+                ("id".to_string(), Some(format!("id-{i}").into_bytes())),
+                ("empty".to_string(), None)
+            ]
+        );
+    }
+
+    // a null map writes no headers
+    let message = get_message(&mut consumer).await;
+    assert!(message.headers().is_none_or(|h| h.count() == 0));
+}
+
+#[tokio::test]
+async fn test_kafka_topic_field() {
+    let mut kafka_topic_tester = KafkaTopicTester {
+        topic: "arroyo-sink-topic-default".to_string(),
+        server: "0.0.0.0:9092".to_string(),
+    };
+    let routed = ["arroyo-sink-topic-a", "arroyo-sink-topic-b"];
+
+    kafka_topic_tester.create_topic("topics", 1).await;
+    for topic in routed {
+        kafka_topic_tester
+            .create_topic_named(topic, "topics", 1)
+            .await;
+    }
+
+    let schema = Arc::new(Schema::new(vec![
+        Field::new("value", DataType::UInt32, false),
+        Field::new("topic", DataType::Utf8, true),
+    ]));
+
+    let mut sink_with_writes = kafka_topic_tester
+        .get_configured_sink(schema.clone(), |sink| {
+            sink.topic_field = Some("topic".to_string());
+        })
+        .await;
+    let mut consumer = kafka_topic_tester.get_consumer_for(
+        "topics",
+        &["arroyo-sink-topic-default", routed[0], routed[1]],
+    );
+
+    let topics = [
+        Some(routed[0]),
+        Some(routed[1]),
+        None,
+        Some(routed[0]),
+        None,
+    ];
+    let batch = RecordBatch::try_new(
+        schema,
+        vec![
+            Arc::new(UInt32Array::from_iter_values(0..topics.len() as u32)),
+            Arc::new(StringArray::from(topics.to_vec())),
+        ],
+    )
+    .unwrap();
+    sink_with_writes.write(batch).await;
+
+    let mut received = vec![];
+    for _ in 0..topics.len() {
+        let message = get_message(&mut consumer).await;
+        let data: TestData = serde_json::from_slice(message.payload().unwrap()).unwrap();
+        received.push((message.topic().to_string(), data.value));
+    }
+    received.sort();
+
+    assert_eq!(
+        received,
+        vec![
+            ("arroyo-sink-topic-a".to_string(), 0),
+            ("arroyo-sink-topic-a".to_string(), 3),
+            ("arroyo-sink-topic-b".to_string(), 1),
+            // rows with a null topic go to the sink's topic
+            ("arroyo-sink-topic-default".to_string(), 2),
+            ("arroyo-sink-topic-default".to_string(), 4),
+        ]
+    );
+}
+
+fn updating_batch(schema: &SchemaRef, rows: &[(Option<&str>, u32, bool)]) -> RecordBatch {
+    let meta = StructArray::new(
+        updating_meta_fields(),
+        vec![
+            Arc::new(BooleanArray::from_iter(
+                rows.iter().map(|(_, _, retract)| Some(*retract)),
+            )),
+            Arc::new(FixedSizeBinaryArray::new_null(16, rows.len())),
+        ],
+        None,
+    );
+
+    RecordBatch::try_new(
+        schema.clone(),
+        vec![
+            Arc::new(StringArray::from_iter(rows.iter().map(|(k, _, _)| *k))),
+            Arc::new(UInt32Array::from_iter_values(
+                rows.iter().map(|(_, v, _)| *v),
+            )),
+            Arc::new(meta),
+        ],
+    )
+    .unwrap()
+}
+
+#[tokio::test]
+async fn test_kafka_retractions() {
+    let mut kafka_topic_tester = KafkaTopicTester {
+        topic: "arroyo-sink-retractions".to_string(),
+        server: "0.0.0.0:9092".to_string(),
+    };
+    kafka_topic_tester.create_topic("retractions", 1).await;
+
+    let schema = Arc::new(Schema::new(vec![
+        Field::new("key", DataType::Utf8, true),
+        Field::new("value", DataType::UInt32, false),
+        updating_meta_field().as_ref().clone(),
+    ]));
+
+    let mut sink_with_writes = kafka_topic_tester
+        .get_configured_sink(schema.clone(), |sink| {
+            sink.key_field = Some("key".to_string());
+        })
+        .await;
+    let mut consumer = kafka_topic_tester.get_consumer("retractions");
+
+    sink_with_writes
+        .write(updating_batch(
+            &schema,
+            &[(Some("a"), 1, false), (Some("b"), 2, false)],
+        ))
+        .await;
+
+    sink_with_writes
+        .write(updating_batch(
+            &schema,
+            &[
+                // an update of a, which is written as just the new value
+                (Some("a"), 1, true),
+                (Some("a"), 3, false),
+                // a deletion of b, which is written as a tombstone
+                (Some("b"), 2, true),
+                // a retraction without a key is skipped
+                (None, 5, true),
+                (None, 6, false),
+            ],
+        ))
+        .await;
+
+    let mut received = vec![];
+    for _ in 0..5 {
+        let message = get_message(&mut consumer).await;
+        let key = message
+            .key()
+            .map(|k| String::from_utf8(k.to_vec()).unwrap());
+        let value = message
+            .payload()
+            .map(|p| serde_json::from_slice::<TestData>(p).unwrap().value);
+        received.push((key, value));
+    }
+
+    assert_eq!(
+        received,
+        vec![
+            (Some("a".to_string()), Some(1)),
+            (Some("b".to_string()), Some(2)),
+            (Some("a".to_string()), Some(3)),
+            (Some("b".to_string()), None),
+            (None, Some(6)),
+        ]
+    );
+}
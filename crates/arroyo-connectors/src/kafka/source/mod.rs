@@ -21,7 +21,9 @@ use arroyo_rpc::errors::DataflowResult;
 use arroyo_rpc::formats::{BadData, Format, Framing};
 use arroyo_rpc::grpc::rpc::TableConfig;
 use arroyo_rpc::schema_resolver::SchemaResolver;
-use arroyo_rpc::{ControlMessage, MetadataField, connector_err, grpc::rpc::StopMode};
+use arroyo_rpc::{
+    ControlMessage, MetadataField, PartitionWatermarkConfig, connector_err, grpc::rpc::StopMode,
+};
 use arroyo_types::*;
 
 use super::{Context, SourceOffset, StreamConsumer};
@@ -43,6 +45,7 @@ pub struct KafkaSourceFunc {
     pub context: Context,
     pub messages_per_second: NonZeroU32,
     pub metadata_fields: Vec<MetadataField>,
+    pub partition_watermarks: Option<PartitionWatermarkConfig>,
 }
 
 #[derive(Copy, Clone, Debug, Encode, Decode, PartialEq, PartialOrd)]
@@ -190,6 +193,23 @@ impl KafkaSourceFunc {
             );
         }
 
+        if let Some(config) = self.partition_watermarks.clone() {
+            collector.enable_partition_watermarks(config)?;
+            let assignment = consumer.assignment().map_err(|e| {
+                connector_err!(
+                    External,
+                    WithBackoff,
+                    "failed to get the partitions assigned to the Kafka consumer: {}",
+                    e
+                )
+            })?;
+            for tp in assignment.elements() {
+                collector.add_partition(&tp.partition().to_string());
+            }
+        }
+
+        let mut current_partition = None;
+
         let mut flush_ticker = tokio::time::interval(Duration::from_millis(50));
         flush_ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
 
@@ -221,6 +241,11 @@ impl KafkaSourceFunc {
                                     None
                                 };
 
+                                if current_partition != Some(msg.partition()) {
+                                    collector.set_partition(&msg.partition().to_string()).await?;
+                                    current_partition = Some(msg.partition());
+                                }
+
                                 collector.deserialize_slice(v, from_millis(timestamp.max(0) as u64), connector_metadata.as_ref()).await?;
 
                                 if collector.should_flush() {
@@ -240,6 +265,7 @@ impl KafkaSourceFunc {
                     if collector.should_flush() {
                         collector.flush_buffer().await?;
                     }
+                    collector.emit_partition_watermark().await;
                 }
                 control_message = ctx.control_rx.recv() => {
                     match control_message {
@@ -93,6 +93,7 @@ impl KafkaTopicTester {
             context: Context::new(None),
             messages_per_second: NonZeroU32::new(100).unwrap(),
             metadata_fields: vec![],
+            partition_watermarks: None,
         });
 
         let (to_control_tx, control_rx) = channel(128);
@@ -292,6 +293,7 @@ async fn test_kafka() {
         min_epoch: 0,
         timestamp: (SystemTime::now()),
         then_stop: false,
+        unaligned: false,
     });
     reader.to_control_tx.send(barrier).await.unwrap();
     let checkpoint_completed = reader.assert_control_checkpoint(1).await;
@@ -406,6 +408,7 @@ async fn test_kafka_with_metadata_fields() {
         context: Context::new(None),
         messages_per_second: NonZeroU32::new(100).unwrap(),
         metadata_fields,
+        partition_watermarks: None,
     };
 
     let (_to_control_tx, control_rx) = channel(128);
@@ -1,5 +1,7 @@
 use anyhow::{Result, anyhow, bail};
-use std::collections::HashMap;
+use aws_config::{BehaviorVersion, Region};
+use aws_sdk_kinesis::Client as KinesisClient;
+use std::collections::{HashMap, HashSet};
 use typify::import_types;
 
 use arroyo_formats::ser::ArrowSerializer;
@@ -22,9 +24,25 @@ import_types!(schema = "src/kinesis/table.json");
 
 mod sink;
 mod source;
+#[cfg(test)]
+mod test;
 
 pub struct KinesisConnector {}
 
+pub(crate) async fn kinesis_client(
+    aws_region: Option<&str>,
+    endpoint_url: Option<&str>,
+) -> KinesisClient {
+    let mut loader = aws_config::defaults(BehaviorVersion::v2026_01_12());
+    if let Some(region) = aws_region {
+        loader = loader.region(Region::new(region.to_string()));
+    }
+    if let Some(endpoint_url) = endpoint_url {
+        loader = loader.endpoint_url(endpoint_url);
+    }
+    KinesisClient::new(&loader.load().await)
+}
+
 impl Connector for KinesisConnector {
     type ProfileT = EmptyConfig;
 
@@ -84,11 +102,23 @@ impl Connector for KinesisConnector {
         table: Self::TableT,
         schema: Option<&ConnectionSchema>,
     ) -> anyhow::Result<arroyo_operator::connector::Connection> {
-        let (connection_type, description) = match table.type_ {
-            TableType::Source { .. } => (
-                ConnectionType::Source,
-                format!("KinesisSource<{}>", table.stream_name),
-            ),
+        let (connection_type, description) = match &table.type_ {
+            TableType::Source {
+                read_mode,
+                consumer_name,
+                ..
+            } => {
+                if consumer_name.is_some() && *read_mode != Some(SourceReadMode::EnhancedFanOut) {
+                    bail!(
+                        "'source.consumer_name' can only be set with 'source.read_mode' = 'enhanced_fan_out'"
+                    );
+                }
+
+                (
+                    ConnectionType::Source,
+                    format!("KinesisSource<{}>", table.stream_name),
+                )
+            }
             TableType::Sink { .. } => (
                 ConnectionType::Sink,
                 format!("KinesisSink<{}>", table.stream_name),
@@ -112,6 +142,7 @@ impl Connector for KinesisConnector {
             bad_data: schema.bad_data.clone(),
             framing: schema.framing.clone(),
             metadata_fields: schema.metadata_fields(),
+            partition_watermarks: None,
         };
 
         Ok(Connection::new(
@@ -142,6 +173,16 @@ impl Connector for KinesisConnector {
                         None | Some("latest") => SourceOffset::Latest,
                         Some(other) => bail!("invalid value for source.offset '{}'", other),
                     },
+                    read_mode: options
+                        .pull_opt_str("source.read_mode")?
+                        .map(|s| s.try_into())
+                        .transpose()
+                        .map_err(|_| {
+                            anyhow!(
+                                "invalid value for 'source.read_mode'; must be 'polling' or 'enhanced_fan_out'"
+                            )
+                        })?,
+                    consumer_name: options.pull_opt_str("source.consumer_name")?,
                 }
             }
             "sink" => {
@@ -164,6 +205,7 @@ impl Connector for KinesisConnector {
             stream_name: options.pull_str("stream_name")?,
             type_: table_type,
             aws_region: options.pull_opt_str("aws_region")?,
+            endpoint_url: options.pull_opt_str("endpoint_url")?,
         };
 
         Self::from_config(self, None, name, EmptyConfig {}, table, schema)
@@ -176,18 +218,32 @@ impl Connector for KinesisConnector {
         config: OperatorConfig,
     ) -> Result<ConstructedOperator> {
         match table.type_ {
-            TableType::Source { offset } => Ok(ConstructedOperator::from_source(Box::new(
+            TableType::Source {
+                offset,
+                read_mode,
+                consumer_name,
+            } => Ok(ConstructedOperator::from_source(Box::new(
                 KinesisSourceFunc {
                     stream_name: table.stream_name,
                     kinesis_client: None,
                     aws_region: table.aws_region,
+                    endpoint_url: table.endpoint_url,
                     offset,
+                    read_mode: read_mode.unwrap_or(SourceReadMode::Polling),
+                    consumer_name,
+                    consumer_arn: None,
                     shards: HashMap::new(),
+                    restored: HashMap::new(),
+                    listed: HashMap::new(),
+                    owners: HashMap::new(),
+                    finished: HashSet::new(),
+                    waiting: vec![],
                     format: config
                         .format
                         .ok_or_else(|| anyhow!("format required for kinesis source"))?,
                     framing: config.framing,
                     bad_data: config.bad_data,
+                    partition_watermarks: config.partition_watermarks,
                 },
             ))),
             TableType::Sink {
@@ -205,6 +261,7 @@ impl Connector for KinesisConnector {
                         client: None,
                         in_progress_batch: None,
                         aws_region: table.aws_region,
+                        endpoint_url: table.endpoint_url,
                         name: table.stream_name,
                         serializer: ArrowSerializer::new(
                             config
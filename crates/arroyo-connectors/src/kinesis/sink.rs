@@ -11,16 +11,18 @@ use arroyo_rpc::errors::DataflowResult;
 use arroyo_rpc::retry;
 use arroyo_types::CheckpointBarrier;
 use async_trait::async_trait;
-use aws_config::{BehaviorVersion, Region};
 use aws_sdk_kinesis::Client as KinesisClient;
 use aws_sdk_kinesis::primitives::Blob;
 use aws_sdk_kinesis::types::PutRecordsRequestEntry;
 use tracing::warn;
 use uuid::Uuid;
 
+use super::kinesis_client;
+
 pub struct KinesisSinkFunc {
     pub client: Option<Arc<KinesisClient>>,
     pub aws_region: Option<String>,
+    pub endpoint_url: Option<String>,
     pub in_progress_batch: Option<BatchRecordPreparer>,
     pub flush_config: FlushConfig,
     pub serializer: ArrowSerializer,
@@ -34,12 +36,9 @@ impl ArrowOperator for KinesisSinkFunc {
     }
 
     async fn on_start(&mut self, _ctx: &mut OperatorContext) -> DataflowResult<()> {
-        let mut loader = aws_config::defaults(BehaviorVersion::v2026_01_12());
-        if let Some(region) = &self.aws_region {
-            loader = loader.region(Region::new(region.clone()));
-        }
-
-        let client = Arc::new(KinesisClient::new(&loader.load().await));
+        let client = Arc::new(
+            kinesis_client(self.aws_region.as_deref(), self.endpoint_url.as_deref()).await,
+        );
         self.client = Some(client.clone());
 
         self.in_progress_batch = Some(BatchRecordPreparer::new(client, self.name.clone()));
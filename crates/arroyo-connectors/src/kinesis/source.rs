@@ -1,30 +1,33 @@
 use std::{
-    collections::{HashMap, hash_map::DefaultHasher},
-    fmt::Debug,
-    hash::{Hash, Hasher},
+    collections::{HashMap, HashSet},
+    fmt::{Debug, Formatter},
     pin::Pin,
     time::SystemTime,
 };
 
-use super::SourceOffset;
+use super::{SourceOffset, SourceReadMode, kinesis_client};
 use anyhow::{Context as AnyhowContext, Result, anyhow, bail};
 use arroyo_operator::SourceFinishType;
 use arroyo_operator::context::{SourceCollector, SourceContext};
 use arroyo_operator::operator::SourceOperator;
-use arroyo_rpc::errors::DataflowResult;
+use arroyo_rpc::errors::{DataflowResult, StateError};
 use arroyo_rpc::formats::{BadData, Format, Framing};
 use arroyo_rpc::grpc::rpc::TableConfig;
-use arroyo_rpc::{ControlMessage, grpc::rpc::StopMode};
-use arroyo_state::global_table_config;
+use arroyo_rpc::{ControlMessage, PartitionWatermarkConfig, grpc::rpc::StopMode};
+use arroyo_state::global_table_config_with_version;
+use arroyo_state::tables::MigratableState;
 use arroyo_state::tables::global_keyed_map::GlobalKeyedView;
 use arroyo_types::from_nanos;
 use async_trait::async_trait;
-use aws_config::{BehaviorVersion, Region};
 use aws_sdk_kinesis::Client as KinesisClient;
-use aws_sdk_kinesis::error::SdkError;
+use aws_sdk_kinesis::error::{DisplayErrorContext, SdkError};
 use aws_sdk_kinesis::operation::get_records::GetRecordsOutput;
 use aws_sdk_kinesis::operation::get_shard_iterator::builders::GetShardIteratorFluentBuilder;
-use aws_sdk_kinesis::types::{Shard, ShardIteratorType};
+use aws_sdk_kinesis::operation::subscribe_to_shard::SubscribeToShardOutput;
+use aws_sdk_kinesis::types::{
+    ConsumerStatus, Record, Shard, ShardIteratorType, StartingPosition, SubscribeToShardEvent,
+    SubscribeToShardEventStream,
+};
 use bincode::{Decode, Encode};
 use futures::{Future, StreamExt, stream::FuturesUnordered};
 use tokio::{
@@ -37,6 +40,7 @@ use tracing::{debug, info, warn};
 pub enum KinesisOffset {
     Earliest,
     Latest,
+    /// resumes after the record with this sequence number
     SequenceNumber(String),
     Timestamp(SystemTime),
 }
@@ -48,8 +52,33 @@ pub struct KinesisSourceFunc {
     pub bad_data: Option<BadData>,
     pub kinesis_client: Option<KinesisClient>,
     pub aws_region: Option<String>,
+    pub endpoint_url: Option<String>,
     pub shards: HashMap<String, ShardState>,
     pub offset: SourceOffset,
+    pub read_mode: SourceReadMode,
+    pub consumer_name: Option<String>,
+    pub consumer_arn: Option<String>,
+    /// the state of every shard in the checkpoint we restored from, across all subtasks, which
+    /// is taken from as shards are assigned to this subtask
+    pub restored: HashMap<String, ShardState>,
+    /// the shards in the stream as of the last sync, and whether they're closed to new records
+    pub listed: HashMap<String, bool>,
+    /// the subtask that reads each listed shard, decided when the shard is first listed
+    pub owners: HashMap<String, u32>,
+    /// shards that had been read to the end as of the checkpoint we restored from
+    pub finished: HashSet<String>,
+    /// shards assigned to this subtask that are waiting for their parents to be fully read
+    pub waiting: Vec<String>,
+    pub partition_watermarks: Option<PartitionWatermarkConfig>,
+}
+
+/// The shard state before shard lineage was recorded
+#[derive(Clone, Debug, Encode, Decode, PartialEq, PartialOrd)]
+pub struct ShardStateV1 {
+    stream_name: String,
+    shard_id: String,
+    offset: KinesisOffset,
+    closed: bool,
 }
 
 #[derive(Clone, Debug, Encode, Decode, PartialEq, PartialOrd)]
@@ -58,30 +87,98 @@ pub struct ShardState {
     shard_id: String,
     offset: KinesisOffset,
     closed: bool,
+    /// the shards this one was split or merged from, which are read to the end before it
+    parent_shard_ids: Vec<String>,
 }
 
-impl TryFrom<(String, Shard, SourceOffset)> for ShardState {
-    type Error = anyhow::Error;
-    fn try_from(
-        (stream_name, shard, source_offset): (String, Shard, SourceOffset),
-    ) -> Result<Self> {
-        let shard_id = shard.shard_id().to_string();
-        let offset = match source_offset {
-            SourceOffset::Earliest => KinesisOffset::Earliest,
-            SourceOffset::Latest => KinesisOffset::Latest,
-        };
+impl MigratableState for ShardState {
+    const VERSION: u32 = 1;
+    type PreviousVersion = ShardStateV1;
+
+    fn migrate(previous: ShardStateV1) -> Result<Self, StateError> {
+        // the lineage is filled in from the stream's shard listing once the shard is assigned
         Ok(Self {
-            stream_name,
-            shard_id,
-            offset,
-            closed: false,
+            stream_name: previous.stream_name,
+            shard_id: previous.shard_id,
+            offset: previous.offset,
+            closed: previous.closed,
+            parent_shard_ids: vec![],
         })
     }
 }
+
 type BoxedFuture<T> = Pin<Box<dyn Future<Output = T> + Send>>;
 
+fn parent_shard_ids(shard: &Shard) -> Vec<String> {
+    shard
+        .parent_shard_id()
+        .into_iter()
+        .chain(shard.adjacent_parent_shard_id())
+        .map(|s| s.to_string())
+        .collect()
+}
+
+/// Shards without a parent to follow are assigned to subtasks by where their hash key range
+/// starts, so that each subtask reads a contiguous part of the key space
+fn subtask_for_shard(starting_hash_key: &str, parallelism: u32) -> u32 {
+    let start: u128 = starting_hash_key.parse().unwrap_or(0);
+    (((start >> 64) * parallelism as u128) >> 64) as u32
+}
+
+/// Decides which subtask reads each newly-listed shard. A shard whose parent is listed and may
+/// not have been read to the end is read by the parent's subtask, which only starts it once it
+/// has finished the parent; other shards are assigned by their hash key. Decisions are kept for
+/// as long as a shard is listed, so that a shard doesn't move once its parent expires from the
+/// stream, and are only revisited on restore, when every subtask sees the same finished shards.
+///
+/// A merged shard follows its first parent, so its adjacent parent may be read by another
+/// subtask, in which case it's only known to be closed to new records before the child starts.
+fn assign_shards(
+    owners: &mut HashMap<String, u32>,
+    shards: &[Shard],
+    finished: &HashSet<String>,
+    parallelism: u32,
+) {
+    let by_id: HashMap<&str, &Shard> = shards.iter().map(|s| (s.shard_id(), s)).collect();
+    owners.retain(|shard_id, _| by_id.contains_key(shard_id.as_str()));
+
+    for shard in shards {
+        shard_owner(owners, &by_id, shard, finished, parallelism);
+    }
+}
+
+fn shard_owner(
+    owners: &mut HashMap<String, u32>,
+    by_id: &HashMap<&str, &Shard>,
+    shard: &Shard,
+    finished: &HashSet<String>,
+    parallelism: u32,
+) -> u32 {
+    if let Some(owner) = owners.get(shard.shard_id()) {
+        return *owner;
+    }
+
+    let owner = match shard
+        .parent_shard_id()
+        .filter(|parent| !finished.contains(*parent))
+        .and_then(|parent| by_id.get(parent))
+    {
+        Some(parent) => shard_owner(owners, by_id, parent, finished, parallelism),
+        None => subtask_for_shard(
+            shard
+                .hash_key_range()
+                .map(|r| r.starting_hash_key())
+                .unwrap_or("0"),
+            parallelism,
+        ),
+    };
+
+    owners.insert(shard.shard_id().to_string(), owner);
+    owner
+}
+
 impl ShardState {
-    fn new(stream_name: String, shard: Shard, source_offset: SourceOffset) -> Self {
+    fn new(stream_name: String, shard: &Shard, source_offset: SourceOffset) -> Self {
         Self {
             stream_name,
             shard_id: shard.shard_id().to_string(),
@@ -90,8 +187,10 @@ impl ShardState {
                 SourceOffset::Latest => KinesisOffset::Latest,
             },
             closed: false,
+            parent_shard_ids: parent_shard_ids(shard),
         }
     }
+
     fn get_update_shard_iterator_future(
         &self,
         kinesis_client: &KinesisClient,
@@ -109,7 +208,7 @@ impl ShardState {
                 shard_iterator_call.shard_iterator_type(ShardIteratorType::Latest)
             }
             KinesisOffset::SequenceNumber(sequence_number) => shard_iterator_call
-                .shard_iterator_type(ShardIteratorType::AtSequenceNumber)
+                .shard_iterator_type(ShardIteratorType::AfterSequenceNumber)
                 .starting_sequence_number(sequence_number.clone()),
             KinesisOffset::Timestamp(timestamp) => shard_iterator_call
                 .shard_iterator_type(ShardIteratorType::AtTimestamp)
@@ -127,6 +226,21 @@ impl ShardState {
             ))
         }))
     }
+
+    fn starting_position(&self) -> Result<StartingPosition> {
+        let builder = StartingPosition::builder();
+        let builder = match &self.offset {
+            KinesisOffset::Earliest => builder.r#type(ShardIteratorType::TrimHorizon),
+            KinesisOffset::Latest => builder.r#type(ShardIteratorType::Latest),
+            KinesisOffset::SequenceNumber(sequence_number) => builder
+                .r#type(ShardIteratorType::AfterSequenceNumber)
+                .sequence_number(sequence_number.clone()),
+            KinesisOffset::Timestamp(timestamp) => builder
+                .r#type(ShardIteratorType::AtTimestamp)
+                .timestamp((*timestamp).into()),
+        };
+        Ok(builder.build()?)
+    }
 }
 
 struct AsyncNamedResult<T: Debug> {
@@ -146,12 +260,23 @@ impl<T: Debug> AsyncNamedResult<T> {
     }
 }
 
+/// An enhanced fan-out subscription to a shard, which Kinesis ends after 5 minutes
+struct ShardSubscription(SubscribeToShardOutput);
+
+impl Debug for ShardSubscription {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ShardSubscription")
+    }
+}
+
 #[derive(Debug)]
 enum AsyncResult {
     // returns the new shard iterator id. Should always initialize a read after receiving this, if it is not None.
     ShardIteratorIdUpdate(Option<String>),
     GetRecords(GetRecordsOutput),
     NeedNewIterator,
+    // the next event of a subscription, or None if the subscription has ended and needs to be renewed
+    SubscriptionEvent(ShardSubscription, Option<SubscribeToShardEvent>),
 }
 
 #[async_trait]
@@ -161,7 +286,7 @@ impl SourceOperator for KinesisSourceFunc {
     }
 
     fn tables(&self) -> HashMap<String, TableConfig> {
-        global_table_config("k", "kinesis source state")
+        global_table_config_with_version("k", "kinesis source state", ShardState::VERSION)
     }
 
     async fn run(
@@ -176,44 +301,37 @@ impl SourceOperator for KinesisSourceFunc {
             &[],
         );
 
+        if let Some(config) = self.partition_watermarks.clone() {
+            collector.enable_partition_watermarks(config)?;
+        }
+
         self.run_int(ctx, collector).await
     }
 }
 
 impl KinesisSourceFunc {
-    /// Initializes the shards for the operator. First shards are read out of state,
-    /// then `sync_shards()` is called to find any new shards.
-    /// It returns a future for each shard to fetch the next shard iterator id.
+    /// Initializes the shards for the operator. The state of all shards is read out of state,
+    /// then `sync_shards()` is called to assign shards to this subtask.
+    /// It returns a future to start reading each shard that's ready to be read.
     async fn init_shards(
         &mut self,
         ctx: &mut SourceContext,
+        collector: &mut SourceCollector,
     ) -> anyhow::Result<Vec<BoxedFuture<AsyncNamedResult<AsyncResult>>>> {
-        let mut futures = Vec::new();
         let s: &mut GlobalKeyedView<String, ShardState> = ctx
             .table_manager
-            .get_global_keyed_state("k")
+            .get_global_keyed_state_migratable("k")
             .await
             .expect("failed to get state for kinesis source");
-        for (shard_id, shard_state) in s
-            .get_all()
+        self.restored = s.get_all().clone();
+        self.finished = self
+            .restored
             .values()
-            .map(|shard_state| (shard_state.shard_id.clone(), shard_state.clone()))
-            .filter(|(shard_id, _shard_state)| {
-                let mut hasher = DefaultHasher::new();
-                shard_id.hash(&mut hasher);
-                let shard_hash = hasher.finish() as u32;
-                shard_hash % ctx.task_info.parallelism == ctx.task_info.task_index
-            })
-        {
-            futures.push(
-                shard_state.get_update_shard_iterator_future(self.kinesis_client.as_ref().unwrap()),
-            );
-            self.shards.insert(shard_id, shard_state);
-        }
-        let new_futures = self.sync_shards(ctx).await?;
-        futures.extend(new_futures.into_iter());
+            .filter(|s| s.closed)
+            .map(|s| s.shard_id.clone())
+            .collect();
 
-        Ok(futures)
+        self.sync_shards(ctx, collector).await
     }
 
     async fn handle_async_result_split(
@@ -221,17 +339,86 @@ impl KinesisSourceFunc {
         shard_id: String,
         async_result: AsyncResult,
         collector: &mut SourceCollector,
-    ) -> DataflowResult<Option<BoxedFuture<AsyncNamedResult<AsyncResult>>>> {
-        match async_result {
+    ) -> DataflowResult<Vec<BoxedFuture<AsyncNamedResult<AsyncResult>>>> {
+        let mut next: Vec<_> = match async_result {
             AsyncResult::ShardIteratorIdUpdate(new_shard_iterator) => {
-                self.handle_shard_iterator_id_update(shard_id, new_shard_iterator)
+                self.handle_shard_iterator_id_update(shard_id.clone(), new_shard_iterator)
                     .await
             }
             AsyncResult::GetRecords(get_records) => {
-                self.handle_get_records(shard_id, get_records, collector)
+                self.handle_get_records(shard_id.clone(), get_records, collector)
                     .await
             }
-            AsyncResult::NeedNewIterator => self.handle_need_new_iterator(shard_id).await,
+            AsyncResult::NeedNewIterator => self.handle_need_new_iterator(shard_id.clone()).await,
+            AsyncResult::SubscriptionEvent(subscription, event) => {
+                self.handle_subscription_event(shard_id.clone(), subscription, event, collector)
+                    .await
+            }
+        }?
+        .into_iter()
+        .collect();
+
+        if self.shards.get(&shard_id).is_some_and(|s| s.closed) {
+            // a fully-read shard shouldn't hold back the watermark
+            collector.remove_partition(&shard_id).await?;
+
+            // and its children may now be read
+            next.extend(self.start_ready_shards(collector));
+        }
+
+        Ok(next)
+    }
+
+    /// Whether the parents of a shard have been read to the end, so that it can be read without
+    /// reordering the records of a key across a split or merge
+    fn parents_finished(&self, shard_id: &str) -> bool {
+        self.shards[shard_id]
+            .parent_shard_ids
+            .iter()
+            .all(|parent| match self.shards.get(parent) {
+                Some(parent) => parent.closed,
+                // parents are read by the same subtask as their children, except for the
+                // adjacent parent of a merge or a parent that was finished before we restored;
+                // the former is read by another subtask, whose progress we can't see, so we only
+                // wait for it to be closed to new records (or to have expired from the stream)
+                None => {
+                    self.finished.contains(parent)
+                        || self.listed.get(parent).copied().unwrap_or(true)
+                }
+            })
+    }
+
+    /// Starts reading the waiting shards whose parents have been read to the end
+    fn start_ready_shards(
+        &mut self,
+        collector: &mut SourceCollector,
+    ) -> Vec<BoxedFuture<AsyncNamedResult<AsyncResult>>> {
+        let (ready, waiting): (Vec<_>, Vec<_>) = std::mem::take(&mut self.waiting)
+            .into_iter()
+            .partition(|shard_id| self.parents_finished(shard_id));
+        self.waiting = waiting;
+
+        let mut futures = vec![];
+        for shard_id in ready {
+            if self.shards[&shard_id].closed {
+                continue;
+            }
+
+            debug!("starting to read kinesis shard {}", shard_id);
+            // register the shard with the collector, for per-shard watermarks
+            collector.add_partition(&shard_id);
+            futures.push(self.start_read_future(&shard_id));
+        }
+        futures
+    }
+
+    fn start_read_future(&self, shard_id: &str) -> BoxedFuture<AsyncNamedResult<AsyncResult>> {
+        let shard_state = &self.shards[shard_id];
+        match self.read_mode {
+            SourceReadMode::Polling => {
+                shard_state.get_update_shard_iterator_future(self.kinesis_client.as_ref().unwrap())
+            }
+            SourceReadMode::EnhancedFanOut => self.subscribe_future(shard_state),
         }
     }
 
@@ -309,7 +496,9 @@ impl KinesisSourceFunc {
             .last()
             .map(|record| record.sequence_number().to_owned());
 
-        let next_shard_iterator = self.process_records(get_records, collector).await?;
+        collector.set_partition(&shard_id).await?;
+        let next_shard_iterator = get_records.next_shard_iterator;
+        self.process_records(get_records.records, collector).await?;
         let shard_state = self.shards.get_mut(&shard_id).unwrap();
 
         if let Some(last_sequence_number) = last_sequence_number {
@@ -324,6 +513,7 @@ impl KinesisSourceFunc {
             }
         }
     }
+
     async fn handle_need_new_iterator(
         &mut self,
         shard_id: String,
@@ -334,28 +524,214 @@ impl KinesisSourceFunc {
         )))
     }
 
-    async fn init_client(&mut self) {
-        let mut loader = aws_config::defaults(BehaviorVersion::v2026_01_12());
-        if let Some(region) = &self.aws_region {
-            loader = loader.region(Region::new(region.clone()));
+    fn subscribe_future(
+        &self,
+        shard_state: &ShardState,
+    ) -> BoxedFuture<AsyncNamedResult<AsyncResult>> {
+        let client = self.kinesis_client.as_ref().unwrap().clone();
+        let consumer_arn = self
+            .consumer_arn
+            .clone()
+            .expect("consumer must be registered for enhanced fan-out");
+        let shard_id = shard_state.shard_id.clone();
+        let starting_position = shard_state.starting_position();
+
+        Box::pin(AsyncNamedResult::wrap_future(
+            shard_id.clone(),
+            Self::subscribe(client, consumer_arn, shard_id, starting_position),
+        ))
+    }
+
+    async fn subscribe(
+        kinesis_client: KinesisClient,
+        consumer_arn: String,
+        shard_id: String,
+        starting_position: Result<StartingPosition>,
+    ) -> Result<AsyncResult> {
+        let starting_position = starting_position?;
+        let mut retries = 0;
+        let subscription = loop {
+            let subscribe_call = kinesis_client
+                .subscribe_to_shard()
+                .consumer_arn(&consumer_arn)
+                .shard_id(&shard_id)
+                .starting_position(starting_position.clone());
+
+            match subscribe_call.send().await {
+                Ok(output) => break ShardSubscription(output),
+                Err(error) => match &error {
+                    // the previous subscription to this shard may not have been closed yet, and
+                    // each shard can only be subscribed to once a second
+                    SdkError::ServiceError(e)
+                        if e.err().is_resource_in_use_exception()
+                            || e.err().is_limit_exceeded_exception() =>
+                    {
+                        if retries == 10 {
+                            bail!(
+                                "failed to subscribe to shard {} after {} retries",
+                                shard_id,
+                                retries
+                            );
+                        }
+                        retries += 1;
+                        tokio::time::sleep(Duration::from_millis(500 * (1 << retries.min(4))))
+                            .await;
+                    }
+                    _ => return Err(anyhow!(error)),
+                },
+            }
+        };
+
+        Self::next_event(subscription).await
+    }
+
+    async fn next_event(mut subscription: ShardSubscription) -> Result<AsyncResult> {
+        loop {
+            match subscription.0.event_stream.recv().await {
+                Ok(Some(SubscribeToShardEventStream::SubscribeToShardEvent(event))) => {
+                    return Ok(AsyncResult::SubscriptionEvent(subscription, Some(event)));
+                }
+                Ok(Some(event)) => {
+                    debug!("ignoring unknown subscription event {:?}", event);
+                }
+                Ok(None) => {
+                    return Ok(AsyncResult::SubscriptionEvent(subscription, None));
+                }
+                Err(e) => {
+                    warn!(
+                        "enhanced fan-out subscription failed, resubscribing: {}",
+                        DisplayErrorContext(&e)
+                    );
+                    return Ok(AsyncResult::SubscriptionEvent(subscription, None));
+                }
+            }
         }
-        self.kinesis_client = Some(KinesisClient::new(&loader.load().await));
+    }
+
+    async fn handle_subscription_event(
+        &mut self,
+        shard_id: String,
+        subscription: ShardSubscription,
+        event: Option<SubscribeToShardEvent>,
+        collector: &mut SourceCollector,
+    ) -> DataflowResult<Option<BoxedFuture<AsyncNamedResult<AsyncResult>>>> {
+        let Some(event) = event else {
+            // resubscribe from where we got to
+            return Ok(Some(self.subscribe_future(&self.shards[&shard_id])));
+        };
+
+        // once a shard has been read to the end, the final event has no continuation and lists
+        // the shards it was split or merged into
+        let continuation = event.continuation_sequence_number().to_string();
+        let ended = continuation.is_empty() || !event.child_shards().is_empty();
+
+        collector.set_partition(&shard_id).await?;
+        self.process_records(event.records, collector).await?;
+        let shard_state = self.shards.get_mut(&shard_id).unwrap();
+
+        if !continuation.is_empty() {
+            shard_state.offset = KinesisOffset::SequenceNumber(continuation);
+        }
+
+        if ended {
+            shard_state.closed = true;
+            return Ok(None);
+        }
+
+        Ok(Some(Box::pin(AsyncNamedResult::wrap_future(
+            shard_id,
+            Self::next_event(subscription),
+        ))))
+    }
+
+    /// Registers the enhanced fan-out consumer for the stream, or finds it if it's already
+    /// registered, and waits for it to become active
+    async fn register_consumer(&self, consumer_name: &str) -> Result<String> {
+        let client = self.kinesis_client.as_ref().unwrap();
+        let summary = client
+            .describe_stream_summary()
+            .stream_name(&self.stream_name)
+            .send()
+            .await
+            .context("failed to describe stream")?;
+        let stream_arn = summary
+            .stream_description_summary()
+            .ok_or_else(|| anyhow!("no description returned for stream {}", self.stream_name))?
+            .stream_arn()
+            .to_string();
+
+        match client
+            .register_stream_consumer()
+            .stream_arn(&stream_arn)
+            .consumer_name(consumer_name)
+            .send()
+            .await
+        {
+            Ok(_) => info!(
+                "registered consumer {} for stream {}",
+                consumer_name, self.stream_name
+            ),
+            Err(error) => match &error {
+                // already registered, by another subtask or an earlier run of the pipeline
+                SdkError::ServiceError(e) if e.err().is_resource_in_use_exception() => {}
+                _ => return Err(anyhow!(error).context("failed to register stream consumer")),
+            },
+        }
+
+        for _ in 0..60 {
+            let description = client
+                .describe_stream_consumer()
+                .stream_arn(&stream_arn)
+                .consumer_name(consumer_name)
+                .send()
+                .await
+                .context("failed to describe stream consumer")?;
+
+            let consumer = description
+                .consumer_description()
+                .ok_or_else(|| anyhow!("no description returned for consumer {}", consumer_name))?;
+
+            if *consumer.consumer_status() == ConsumerStatus::Active {
+                return Ok(consumer.consumer_arn().to_string());
+            }
+
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+
+        bail!("stream consumer {} did not become active", consumer_name)
+    }
+
+    async fn init_client(&mut self, ctx: &SourceContext) -> Result<()> {
+        self.kinesis_client =
+            Some(kinesis_client(self.aws_region.as_deref(), self.endpoint_url.as_deref()).await);
+
+        if self.read_mode == SourceReadMode::EnhancedFanOut {
+            let consumer_name = self
+                .consumer_name
+                .clone()
+                .unwrap_or_else(|| format!("arroyo-{}", ctx.task_info.job_id));
+            self.consumer_arn = Some(self.register_consumer(&consumer_name).await?);
+        }
+
+        Ok(())
     }
 
     /// Runs the Kinesis source, handling incoming records and control messages.
     ///
     /// This method initializes the Kinesis client, initializes the shards, and enters a loop to handle incoming
     /// records and control messages. There are three prongs to the tokio select loop:
-    /// * A `FuturesUnordered` tha contains futures for reading off of shards.
-    /// * An interval that periodically polls for new shards, initializing their futures.
+    /// * A `FuturesUnordered` tha contains futures for reading off of shards, either by polling or
+    ///   from enhanced fan-out subscriptions.
+    /// * An interval that periodically polls for new shards, initializing their futures once
+    ///   their parents have been read.
     /// * Polling off of the control queue, to perform checkpointing and stop the operator.
     async fn run_int(
         &mut self,
         ctx: &mut SourceContext,
         collector: &mut SourceCollector,
     ) -> DataflowResult<SourceFinishType> {
-        self.init_client().await;
-        let starting_futures = self.init_shards(ctx).await?;
+        self.init_client(ctx).await?;
+        let starting_futures = self.init_shards(ctx, collector).await?;
         let mut futures = FuturesUnordered::new();
         futures.extend(starting_futures.into_iter());
 
@@ -366,16 +742,14 @@ impl KinesisSourceFunc {
             select! {
                 result = futures.select_next_some() => {
                     let shard_id = result.name;
-                    if let Some(future) = self.handle_async_result_split(shard_id,
-                        result.result?, collector).await? {
-                            futures.push(future);
-                        }
+                    futures.extend(self.handle_async_result_split(shard_id,
+                        result.result?, collector).await?);
                 },
                 _ = shard_poll_interval.tick() => {
                     if collector.should_flush() {
                         collector.flush_buffer().await?;
                     }
-                    match self.sync_shards(ctx).await {
+                    match self.sync_shards(ctx, collector).await {
                         Err(err) => {
                             warn!("failed to sync shards: {}", err);
                         },
@@ -383,6 +757,7 @@ impl KinesisSourceFunc {
                             futures.extend(new_futures.into_iter());
                         }
                      }
+                    collector.emit_partition_watermark().await;
                 }
                 control_message = ctx.control_rx.recv() => {
                     match control_message {
@@ -425,10 +800,9 @@ impl KinesisSourceFunc {
 
     async fn process_records(
         &mut self,
-        get_records_output: GetRecordsOutput,
+        records: Vec<Record>,
         collector: &mut SourceCollector,
-    ) -> DataflowResult<Option<String>> {
-        let records = get_records_output.records;
+    ) -> DataflowResult<()> {
         for record in records {
             let data = record.data.into_inner();
             let timestamp = record.approximate_arrival_timestamp.unwrap();
@@ -440,34 +814,65 @@ impl KinesisSourceFunc {
                 collector.flush_buffer().await?
             }
         }
-        Ok(get_records_output.next_shard_iterator)
+        Ok(())
     }
 
+    /// Lists the shards of the stream, assigning any new ones that belong to this subtask, and
+    /// returns futures to start reading those that are ready to be read
     async fn sync_shards(
         &mut self,
         ctx: &mut SourceContext,
+        collector: &mut SourceCollector,
     ) -> Result<Vec<BoxedFuture<AsyncNamedResult<AsyncResult>>>> {
-        let mut futures = Vec::new();
-        for shard in self.get_splits().await? {
-            // check hash
+        let shards = self.get_splits().await?;
+
+        // shards that appear after we've started reading were created by resharding, and are
+        // read from the start so that no records are missed
+        let offset = if self.listed.is_empty() && self.restored.is_empty() {
+            self.offset
+        } else {
+            SourceOffset::Earliest
+        };
+
+        self.listed = shards
+            .iter()
+            .map(|shard| {
+                let closed = shard
+                    .sequence_number_range()
+                    .is_some_and(|r| r.ending_sequence_number().is_some());
+                (shard.shard_id().to_string(), closed)
+            })
+            .collect();
+
+        assign_shards(
+            &mut self.owners,
+            &shards,
+            &self.finished,
+            ctx.task_info.parallelism,
+        );
+
+        for shard in shards {
             let shard_id = shard.shard_id().to_string();
-            let mut hasher = DefaultHasher::new();
-            shard_id.hash(&mut hasher);
-            let shard_hash = hasher.finish() as u32;
 
             if self.shards.contains_key(&shard_id)
-                || shard_hash % ctx.task_info.parallelism != ctx.task_info.task_index
+                || self.owners[&shard_id] != ctx.task_info.task_index
             {
                 continue;
             }
-            let shard_state = ShardState::new(self.stream_name.clone(), shard, self.offset);
 
-            futures.push(
-                shard_state.get_update_shard_iterator_future(self.kinesis_client.as_ref().unwrap()),
-            );
-            self.shards.insert(shard_id, shard_state);
+            let shard_state = match self.restored.remove(&shard_id) {
+                Some(mut shard_state) => {
+                    shard_state.parent_shard_ids = parent_shard_ids(&shard);
+                    shard_state
+                }
+                None => ShardState::new(self.stream_name.clone(), &shard, offset),
+            };
+
+            self.shards.insert(shard_id.clone(), shard_state);
+            self.waiting.push(shard_id);
         }
-        Ok(futures)
+
+        Ok(self.start_ready_shards(collector))
     }
 
     async fn get_splits(&mut self) -> Result<Vec<Shard>> {
@@ -497,3 +902,107 @@ impl KinesisSourceFunc {
         Ok(shard_collect)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aws_sdk_kinesis::types::{HashKeyRange, SequenceNumberRange};
+
+    #[test]
+    fn test_subtask_for_shard() {
+        let max = u128::MAX.to_string();
+        let half = (1u128 << 127).to_string();
+
+        assert_eq!(subtask_for_shard("0", 4), 0);
+        assert_eq!(subtask_for_shard(&half, 4), 2);
+        assert_eq!(subtask_for_shard(&max, 4), 3);
+        assert_eq!(subtask_for_shard(&max, 1), 0);
+    }
+
+    #[test]
+    fn test_split_shards_share_subtask_with_parent() {
+        // splitting the first of two shards keeps its lower half on the same subtask
+        let lower_child = "0";
+        let upper_child = (1u128 << 126).to_string();
+
+        assert_eq!(subtask_for_shard(lower_child, 2), 0);
+        assert_eq!(subtask_for_shard(&upper_child, 2), 0);
+        assert_eq!(subtask_for_shard(&upper_child, 4), 1);
+    }
+
+    fn shard(id: &str, start: u128, parent: Option<&str>, adjacent: Option<&str>) -> Shard {
+        Shard::builder()
+            .shard_id(id)
+            .set_parent_shard_id(parent.map(|s| s.to_string()))
+            .set_adjacent_parent_shard_id(adjacent.map(|s| s.to_string()))
+            .hash_key_range(
+                HashKeyRange::builder()
+                    .starting_hash_key(start.to_string())
+                    .ending_hash_key(u128::MAX.to_string())
+                    .build()
+                    .unwrap(),
+            )
+            .sequence_number_range(
+                SequenceNumberRange::builder()
+                    .starting_sequence_number("0")
+                    .build()
+                    .unwrap(),
+            )
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_assign_shards_follows_lineage() {
+        let half = 1u128 << 127;
+        let three_quarters = 3u128 << 126;
+
+        // a single shard split in two, whose upper half is split again, then merged back
+        let shards = vec![
+            shard("root", 0, None, None),
+            shard("lower", 0, Some("root"), None),
+            shard("upper", half, Some("root"), None),
+            shard("upper-upper", three_quarters, Some("upper"), None),
+            shard("merged", 0, Some("lower"), Some("upper-lower")),
+            shard("upper-lower", half, Some("upper"), None),
+        ];
+
+        let mut owners = HashMap::new();
+        assign_shards(&mut owners, &shards, &HashSet::new(), 4);
+        for shard in &shards {
+            assert_eq!(owners[shard.shard_id()], 0, "{}", shard.shard_id());
+        }
+
+        // once the root expires from the stream, its descendants stay where they are
+        assign_shards(&mut owners, &shards[1..], &HashSet::new(), 4);
+        assert!(!owners.contains_key("root"));
+        assert_eq!(owners["upper-upper"], 0);
+
+        // but are assigned by hash key on restore if their parents had been read to the end
+        let finished = ["root", "upper"].into_iter().map(String::from).collect();
+        let mut owners = HashMap::new();
+        assign_shards(&mut owners, &shards, &finished, 4);
+        assert_eq!(owners["upper"], 2);
+        assert_eq!(owners["upper-upper"], 3);
+        assert_eq!(owners["upper-lower"], 2);
+        assert_eq!(owners["merged"], 0);
+    }
+
+    #[test]
+    fn test_migrate_shard_state() {
+        let previous = ShardStateV1 {
+            stream_name: "stream".to_string(),
+            shard_id: "shardId-000000000001".to_string(),
+            offset: KinesisOffset::SequenceNumber(
+                "49590338271490256608559692538361571095921575989136588898".to_string(),
+            ),
+            closed: false,
+        };
+
+        let migrated = ShardState::migrate(previous.clone()).unwrap();
+        assert_eq!(migrated.shard_id, previous.shard_id);
+        assert_eq!(migrated.offset, previous.offset);
+        assert!(!migrated.closed);
+        assert!(migrated.parent_shard_ids.is_empty());
+    }
+}
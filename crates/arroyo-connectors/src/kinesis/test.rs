@@ -0,0 +1,312 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+
+use arrow::array::UInt64Array;
+use arrow::datatypes::{DataType, Field, Schema, TimeUnit};
+use arroyo_operator::context::{
+    ArrowCollector, BatchReceiver, OperatorContext, SourceCollector, SourceContext, batch_bounded,
+};
+use arroyo_operator::operator::SourceOperator;
+use arroyo_rpc::df::ArroyoSchema;
+use arroyo_rpc::formats::{Format, JsonFormat};
+use arroyo_rpc::{ControlMessage, ControlResp};
+use arroyo_types::{ArrowMessage, ChainInfo};
+use aws_sdk_kinesis::Client as KinesisClient;
+use aws_sdk_kinesis::primitives::Blob;
+use aws_sdk_kinesis::types::StreamStatus;
+use rand::random;
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc::{Receiver, Sender, channel};
+
+use super::source::KinesisSourceFunc;
+use super::{SourceOffset, SourceReadMode, kinesis_client};
+
+const REGION: &str = "us-east-1";
+
+/// The endpoint of a Kinesis emulator that supports enhanced fan-out, like LocalStack (usually at
+/// http://localhost:4566), from `KINESIS_ENDPOINT`; the tests that need one are skipped when
+/// that isn't set
+fn endpoint() -> Option<String> {
+    std::env::var("KINESIS_ENDPOINT").ok()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+struct TestData {
+    value: u64,
+}
+
+struct KinesisSourceWithReads {
+    to_control_tx: Sender<ControlMessage>,
+    #[allow(dead_code)]
+    from_control_rx: Receiver<ControlResp>,
+    data_recv: BatchReceiver,
+}
+
+impl KinesisSourceWithReads {
+    /// Reads values until `count` have been read or `timeout` passes without any data
+    async fn read_values(&mut self, count: usize, timeout: Duration) -> Vec<u64> {
+        let mut values = vec![];
+        while values.len() < count {
+            match tokio::time::timeout(timeout, self.data_recv.recv()).await {
+                Ok(Some(ArrowMessage::Data(record))) => {
+                    let a = record.columns()[1]
+                        .as_any()
+                        .downcast_ref::<UInt64Array>()
+                        .unwrap();
+                    values.extend(a.iter().map(|v| v.unwrap()));
+                }
+                Ok(Some(_)) => {}
+                Ok(None) => unreachable!("source stopped unexpectedly"),
+                Err(_) => break,
+            }
+        }
+        values
+    }
+
+    async fn stop(&self) {
+        self.to_control_tx
+            .send(ControlMessage::Stop {
+                mode: arroyo_rpc::grpc::rpc::StopMode::Immediate,
+            })
+            .await
+            .unwrap();
+    }
+}
+
+struct KinesisStreamTester {
+    stream_name: String,
+    endpoint: String,
+    client: KinesisClient,
+}
+
+impl KinesisStreamTester {
+    async fn new(endpoint: String, shards: i32) -> Self {
+        let stream_name = format!("arroyo-test-{}", random::<u64>());
+        let client = kinesis_client(Some(REGION), Some(&endpoint)).await;
+
+        client
+            .create_stream()
+            .stream_name(&stream_name)
+            .shard_count(shards)
+            .send()
+            .await
+            .unwrap_or_else(|e| panic!("failed to create stream at {endpoint}: {e:?}"));
+
+        let tester = Self {
+            stream_name,
+            endpoint,
+            client,
+        };
+        tester.wait_for_active().await;
+        tester
+    }
+
+    async fn wait_for_active(&self) {
+        for _ in 0..60 {
+            let summary = self
+                .client
+                .describe_stream_summary()
+                .stream_name(&self.stream_name)
+                .send()
+                .await
+                .unwrap();
+
+            if summary
+                .stream_description_summary()
+                .unwrap()
+                .stream_status()
+                == &StreamStatus::Active
+            {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(500)).await;
+        }
+        panic!("stream {} did not become active", self.stream_name);
+    }
+
+    async fn put(&self, values: impl Iterator<Item = u64>) {
+        for value in values {
+            self.client
+                .put_record()
+                .stream_name(&self.stream_name)
+                .partition_key("key")
+                .data(Blob::new(serde_json::to_vec(&TestData { value }).unwrap()))
+                .send()
+                .await
+                .unwrap();
+        }
+    }
+
+    /// Splits the only open shard of the stream in half
+    async fn split(&self) {
+        let shards = self
+            .client
+            .list_shards()
+            .stream_name(&self.stream_name)
+            .send()
+            .await
+            .unwrap();
+
+        let shard = shards
+            .shards()
+            .iter()
+            .find(|s| {
+                s.sequence_number_range()
+                    .is_some_and(|r| r.ending_sequence_number().is_none())
+            })
+            .unwrap();
+
+        self.client
+            .split_shard()
+            .stream_name(&self.stream_name)
+            .shard_to_split(shard.shard_id())
+            .new_starting_hash_key((1u128 << 127).to_string())
+            .send()
+            .await
+            .unwrap();
+
+        self.wait_for_active().await;
+    }
+
+    async fn delete(&self) {
+        self.client
+            .delete_stream()
+            .stream_name(&self.stream_name)
+            .enforce_consumer_deletion(true)
+            .send()
+            .await
+            .unwrap();
+    }
+
+    async fn get_source_with_reader(
+        &self,
+        read_mode: SourceReadMode,
+        task_index: u32,
+        parallelism: u32,
+    ) -> KinesisSourceWithReads {
+        let mut task_info = arroyo_types::get_test_task_info();
+        task_info.job_id = format!("kinesis-job-{}", self.stream_name);
+        task_info.task_index = task_index;
+        task_info.parallelism = parallelism;
+        let task_info = Arc::new(task_info);
+
+        let mut kinesis = KinesisSourceFunc {
+            stream_name: self.stream_name.clone(),
+            format: Format::Json(JsonFormat::default()),
+            framing: None,
+            bad_data: None,
+            kinesis_client: None,
+            aws_region: Some(REGION.to_string()),
+            endpoint_url: Some(self.endpoint.clone()),
+            shards: HashMap::new(),
+            offset: SourceOffset::Earliest,
+            read_mode,
+            consumer_name: None,
+            consumer_arn: None,
+            restored: HashMap::new(),
+            listed: HashMap::new(),
+            owners: HashMap::new(),
+            finished: HashSet::new(),
+            waiting: vec![],
+            partition_watermarks: None,
+        };
+
+        let (to_control_tx, control_rx) = channel(128);
+        let (command_tx, from_control_rx) = channel(128);
+        let (data_tx, recv) = batch_bounded(128);
+
+        let ctx = OperatorContext::new(
+            task_info.clone(),
+            None.as_ref(),
+            command_tx.clone(),
+            1,
+            vec![],
+            Some(Arc::new(ArroyoSchema::new_unkeyed(
+                Arc::new(Schema::new(vec![
+                    Field::new(
+                        "_timestamp",
+                        DataType::Timestamp(TimeUnit::Nanosecond, None),
+                        false,
+                    ),
+                    Field::new("value", DataType::UInt64, false),
+                ])),
+                0,
+            ))),
+            kinesis.tables(),
+        )
+        .await;
+
+        let chain_info = Arc::new(ChainInfo {
+            job_id: ctx.task_info.job_id.clone(),
+            node_id: ctx.task_info.node_id,
+            description: "kinesis source".to_string(),
+            task_index: ctx.task_info.task_index,
+        });
+
+        let mut ctx = SourceContext::from_operator(ctx, chain_info.clone(), control_rx);
+        let arrow_collector = ArrowCollector::new(
+            chain_info.clone(),
+            Some(ctx.out_schema.clone()),
+            vec![vec![data_tx]],
+        );
+        let mut collector = SourceCollector::new(
+            ctx.out_schema.clone(),
+            arrow_collector,
+            command_tx,
+            &task_info,
+        );
+
+        tokio::spawn(async move {
+            kinesis.on_start(&mut ctx).await.unwrap();
+            kinesis.run(&mut ctx, &mut collector).await.unwrap();
+        });
+
+        KinesisSourceWithReads {
+            to_control_tx,
+            from_control_rx,
+            data_recv: recv,
+        }
+    }
+}
+
+/// Writes to a shard before and after it's split, then reads the stream with two subtasks. The
+/// children of the split are read by the parent's subtask once it has finished the parent, so
+/// every record is read in order by the first subtask, whichever child the key hashes to.
+async fn test_read_across_split(read_mode: SourceReadMode) {
+    let Some(endpoint) = endpoint() else {
+        return;
+    };
+    let tester = KinesisStreamTester::new(endpoint, 1).await;
+
+    tester.put(0..10).await;
+    tester.split().await;
+    tester.put(10..20).await;
+
+    let mut first = tester.get_source_with_reader(read_mode, 0, 2).await;
+    let mut second = tester.get_source_with_reader(read_mode, 1, 2).await;
+
+    let values = first.read_values(20, Duration::from_secs(30)).await;
+    assert_eq!(values, (0..20).collect::<Vec<_>>());
+
+    assert!(
+        second
+            .read_values(1, Duration::from_secs(3))
+            .await
+            .is_empty()
+    );
+
+    first.stop().await;
+    second.stop().await;
+    tester.delete().await;
+}
+
+#[tokio::test]
+async fn test_kinesis_polling_split() {
+    test_read_across_split(SourceReadMode::Polling).await;
+}
+
+#[tokio::test]
+async fn test_kinesis_enhanced_fan_out_split() {
+    test_read_across_split(SourceReadMode::EnhancedFanOut).await;
+}
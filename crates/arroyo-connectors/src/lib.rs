@@ -20,6 +20,8 @@ pub mod blackhole;
 pub mod confluent;
 pub mod filesystem;
 pub mod fluvio;
+pub mod http_ingest;
+pub mod http_lookup;
 pub mod impulse;
 pub mod kafka;
 pub mod kinesis;
@@ -27,6 +29,8 @@ pub mod mqtt;
 pub mod nats;
 pub mod nexmark;
 pub mod polling_http;
+pub mod postgres;
+mod pre_commit;
 pub mod preview;
 pub mod rabbitmq;
 pub mod redis;
@@ -43,6 +47,8 @@ pub fn connectors() -> HashMap<&'static str, Box<dyn ErasedConnector>> {
         Box::new(filesystem::delta::DeltaLakeConnector {}),
         Box::new(filesystem::FileSystemConnector {}),
         Box::new(fluvio::FluvioConnector {}),
+        Box::new(http_ingest::HttpIngestConnector {}),
+        Box::new(http_lookup::HttpLookupConnector {}),
         Box::new(filesystem::iceberg::IcebergConnector {}),
         Box::new(impulse::ImpulseConnector {}),
         Box::new(kafka::KafkaConnector {}),
@@ -51,6 +57,7 @@ pub fn connectors() -> HashMap<&'static str, Box<dyn ErasedConnector>> {
         Box::new(nats::NatsConnector {}),
         Box::new(nexmark::NexmarkConnector {}),
         Box::new(polling_http::PollingHTTPConnector {}),
+        Box::new(postgres::PostgresConnector {}),
         Box::new(preview::PreviewConnector {}),
         Box::new(rabbitmq::RabbitmqConnector {}),
         Box::new(redis::RedisConnector {}),
@@ -5,6 +5,7 @@ use std::time::{Duration, SystemTime};
 
 use crate::mqtt::sink::MqttSinkFunc;
 use crate::mqtt::source::MqttSourceFunc;
+use crate::pre_commit::PreCommitBuffer;
 use anyhow::{anyhow, bail};
 use arrow::datatypes::DataType;
 use arroyo_formats::ser::ArrowSerializer;
@@ -18,6 +19,7 @@ use arroyo_rpc::{ConnectorOptions, OperatorConfig};
 use arroyo_types::to_nanos;
 use rumqttc::Outgoing;
 use rumqttc::mqttbytes::QoS;
+use rumqttc::v5;
 use rumqttc::{AsyncClient, Event as MqttEvent, EventLoop, Incoming, MqttOptions};
 use rustls::pki_types::{CertificateDer, PrivatePkcs8KeyDer};
 use rustls_native_certs::load_native_certs;
@@ -54,6 +56,16 @@ impl MqttTable {
             })
             .unwrap_or(QoS::AtMostOnce)
     }
+
+    /// The topic filters a source subscribes to, from the comma-separated `topic`
+    pub fn topic_filters(&self) -> Vec<String> {
+        self.topic
+            .split(',')
+            .map(|t| t.trim())
+            .filter(|t| !t.is_empty())
+            .map(|t| t.to_string())
+            .collect()
+    }
 }
 
 impl MqttConnector {
@@ -100,7 +112,9 @@ impl MqttConnector {
             .transpose()?;
 
         let table_type = match typ.as_str() {
-            "source" => TableType::Source {},
+            "source" => TableType::Source {
+                shared_group: options.pull_opt_str("source.shared_group")?,
+            },
             "sink" => TableType::Sink {
                 retain: options
                     .pull_opt_str("sink.retain")?
@@ -110,6 +124,11 @@ impl MqttConnector {
                     })
                     .transpose()?
                     .unwrap_or(false),
+                commit_mode: match options.pull_opt_str("sink.commit_mode")?.as_deref() {
+                    Some("at_least_once") | None => None,
+                    Some("exactly_once") => Some(CommitMode::ExactlyOnce),
+                    Some(other) => bail!("invalid value for sink.commit_mode '{}'", other),
+                },
             },
             _ => {
                 bail!("type must be one of 'source' or 'sink")
@@ -161,11 +180,36 @@ impl Connector for MqttConnector {
         table: MqttTable,
         schema: Option<&ConnectionSchema>,
     ) -> anyhow::Result<Connection> {
-        let (typ, desc) = match table.type_ {
-            TableType::Source { .. } => (
-                ConnectionType::Source,
-                format!("MqttSource<{}>", table.topic),
-            ),
+        let (typ, desc) = match &table.type_ {
+            TableType::Source { shared_group } => {
+                let filters = table.topic_filters();
+                if filters.is_empty() {
+                    bail!("'topic' must contain at least one topic filter");
+                }
+
+                for filter in &filters {
+                    if filter.starts_with("$share/") {
+                        bail!(
+                            "topic filter '{}' is a shared subscription; set 'source.shared_group' instead",
+                            filter
+                        );
+                    }
+                }
+
+                if let Some(group) = shared_group
+                    && (group.is_empty() || group.contains(['/', '+', '#']))
+                {
+                    bail!(
+                        "invalid 'source.shared_group' '{}'; it must be non-empty and cannot contain '/', '+' or '#'",
+                        group
+                    );
+                }
+
+                (
+                    ConnectionType::Source,
+                    format!("MqttSource<{}>", table.topic),
+                )
+            }
             TableType::Sink { .. } => (ConnectionType::Sink, format!("MqttSink<{}>", table.topic)),
         };
 
@@ -187,6 +231,7 @@ impl Connector for MqttConnector {
             bad_data: schema.bad_data.clone(),
             framing: schema.framing.clone(),
             metadata_fields: schema.metadata_fields(),
+            partition_watermarks: None,
         };
 
         Ok(Connection::new(
@@ -242,10 +287,24 @@ impl Connector for MqttConnector {
     }
 
     fn metadata_defs(&self) -> &'static [MetadataDef] {
-        &[MetadataDef {
-            name: "topic",
-            data_type: DataType::Utf8,
-        }]
+        &[
+            MetadataDef {
+                name: "topic",
+                data_type: DataType::Utf8,
+            },
+            MetadataDef {
+                name: "qos",
+                data_type: DataType::Int32,
+            },
+            MetadataDef {
+                name: "retain",
+                data_type: DataType::Boolean,
+            },
+            MetadataDef {
+                name: "user_properties",
+                data_type: DataType::Utf8,
+            },
+        ]
     }
 
     fn from_options(
@@ -275,41 +334,48 @@ impl Connector for MqttConnector {
         config: OperatorConfig,
     ) -> anyhow::Result<ConstructedOperator> {
         let qos = table.qos();
+        let topics = table.topic_filters();
         Ok(match table.type_ {
-            TableType::Source {} => ConstructedOperator::from_source(Box::new(MqttSourceFunc {
-                config: profile,
-                topic: table.topic,
-                qos,
-                format: config
-                    .format
-                    .ok_or_else(|| anyhow!("format is required for mqtt source"))?,
-                framing: config.framing,
-                bad_data: config.bad_data,
-                messages_per_second: NonZeroU32::new(
-                    config
-                        .rate_limit
-                        .map(|l| l.messages_per_second)
-                        .unwrap_or(u32::MAX),
-                )
-                .unwrap(),
-                subscribed: Arc::new(AtomicBool::new(false)),
-                metadata_fields: config.metadata_fields,
-            })),
-            TableType::Sink { retain } => {
-                ConstructedOperator::from_operator(Box::new(MqttSinkFunc {
+            TableType::Source { shared_group } => {
+                ConstructedOperator::from_source(Box::new(MqttSourceFunc {
                     config: profile,
+                    topics,
+                    shared_group,
                     qos,
-                    topic: table.topic,
-                    retain,
-                    serializer: ArrowSerializer::new(
+                    format: config
+                        .format
+                        .ok_or_else(|| anyhow!("format is required for mqtt source"))?,
+                    framing: config.framing,
+                    bad_data: config.bad_data,
+                    messages_per_second: NonZeroU32::new(
                         config
-                            .format
-                            .ok_or_else(|| anyhow!("format is required for mqtt sink"))?,
-                    ),
-                    stopped: Arc::new(AtomicBool::new(false)),
-                    client: None,
+                            .rate_limit
+                            .map(|l| l.messages_per_second)
+                            .unwrap_or(u32::MAX),
+                    )
+                    .unwrap(),
+                    subscribed: Arc::new(AtomicBool::new(false)),
+                    metadata_fields: config.metadata_fields,
                 }))
             }
+            TableType::Sink {
+                retain,
+                commit_mode,
+            } => ConstructedOperator::from_operator(Box::new(MqttSinkFunc {
+                config: profile,
+                qos,
+                topic: table.topic,
+                retain,
+                commit_mode: commit_mode.unwrap_or(CommitMode::AtLeastOnce),
+                serializer: ArrowSerializer::new(
+                    config
+                        .format
+                        .ok_or_else(|| anyhow!("format is required for mqtt sink"))?,
+                ),
+                stopped: Arc::new(AtomicBool::new(false)),
+                client: None,
+                pre_commits: PreCommitBuffer::default(),
+            })),
         })
     }
 }
@@ -330,9 +396,10 @@ async fn test_inner(
         0,
     )?;
 
+    let mut subscribe_only = false;
     let wait_for_incomming = match t {
         Some(t) => {
-            let topic = t.topic;
+            let topic = t.topic.clone();
             let qos = t
                 .qos
                 .map(|qos| match qos {
@@ -347,8 +414,18 @@ async fn test_inner(
                     .await?;
                 false
             } else {
-                client.subscribe(&topic, qos).await?;
-                client.publish(topic, qos, false, "test".as_bytes()).await?;
+                let filters = t.topic_filters();
+                for filter in &filters {
+                    client.subscribe(filter, qos).await?;
+                }
+
+                // we can only check that messages are received if there's a topic to publish to
+                match filters.into_iter().find(|f| !f.contains(['+', '#'])) {
+                    Some(topic) => {
+                        client.publish(topic, qos, false, "test".as_bytes()).await?;
+                    }
+                    None => subscribe_only = true,
+                }
                 true
             }
         }
@@ -367,6 +444,9 @@ async fn test_inner(
                     let _payload = String::from_utf8(p.payload.to_vec())?;
                     return Ok("Successfully subscribed".to_string());
                 }
+                MqttEvent::Incoming(Incoming::SubAck(_)) if subscribe_only => {
+                    return Ok("Successfully subscribed".to_string());
+                }
                 MqttEvent::Outgoing(Outgoing::Publish(_p)) => {
                     if !wait_for_incomming {
                         return Ok("Successfully published".to_string());
@@ -406,22 +486,91 @@ fn load_private_key<'a>(certificate: &str) -> anyhow::Result<PrivatePkcs8KeyDer<
     Ok(certs)
 }
 
+/// Creates a client id with the format: <client_prefix>_<job_id>_<operator_id>_<task_index>
+/// because the client id must be unique for each connection. Otherwise, the broker will only keep
+/// one active connection per client id
+fn client_id(c: &MqttConfig, job_id: &str, operator_id: &str, task_index: usize) -> String {
+    format!(
+        "{}_{}_{}_{}",
+        c.client_prefix.as_deref().unwrap_or("arroyo-mqtt"),
+        job_id,
+        operator_id,
+        task_index,
+    )
+}
+
+fn max_packet_size(c: &MqttConfig) -> anyhow::Result<Option<usize>> {
+    c.max_packet_size
+        .map(|max_packet_size| {
+            max_packet_size
+                .try_into()
+                .map_err(|_| anyhow!("max_packet_size value {} is too large for this {}-bit platform. Only values up to {} are supported.", max_packet_size, std::mem::size_of::<usize>() * 8, usize::MAX))
+        })
+        .transpose()
+}
+
+fn tls_transport(c: &MqttConfig) -> anyhow::Result<rumqttc::Transport> {
+    let mut root_cert_store = RootCertStore::empty();
+
+    if let Some(ca) = c.tls.as_ref().and_then(|tls| tls.ca.as_ref()) {
+        let ca = ca.sub_env_vars().map_err(|e| anyhow!("{}", e))?;
+        let certificates = load_certs(&ca)?;
+        for cert in certificates {
+            root_cert_store.add(cert).unwrap();
+        }
+    } else {
+        for cert in load_native_certs().expect("could not load platform certs") {
+            root_cert_store.add(cert).unwrap();
+        }
+    }
+
+    let builder = ClientConfig::builder().with_root_certificates(root_cert_store);
+
+    let tls_config = if let Some((Some(client_cert), Some(client_key))) = c
+        .tls
+        .as_ref()
+        .map(|tls| (tls.cert.as_ref(), tls.key.as_ref()))
+    {
+        let client_cert = client_cert.sub_env_vars().map_err(|e| anyhow!("{}", e))?;
+        let client_key = client_key.sub_env_vars().map_err(|e| anyhow!("{}", e))?;
+        let certs = load_certs(&client_cert)?;
+        let key = load_private_key(&client_key)?;
+
+        builder.with_client_auth_cert(certs, key.into())?
+    } else {
+        builder.with_no_client_auth()
+    };
+
+    Ok(rumqttc::Transport::tls_with_config(
+        rumqttc::TlsConfiguration::Rustls(Arc::new(tls_config)),
+    ))
+}
+
+fn credentials(c: &MqttConfig) -> anyhow::Result<Option<(String, String)>> {
+    let password = if let Some(password) = &c.password {
+        password.sub_env_vars().map_err(|e| anyhow!("{}", e))?
+    } else {
+        "".to_string()
+    };
+
+    c.username
+        .as_ref()
+        .map(|username| {
+            Ok((
+                username.sub_env_vars().map_err(|e| anyhow!("{}", e))?,
+                password,
+            ))
+        })
+        .transpose()
+}
+
 pub(crate) fn create_connection(
     c: &MqttConfig,
     job_id: &str,
     operator_id: &str,
     task_index: usize,
 ) -> anyhow::Result<(AsyncClient, EventLoop)> {
-    // It creates a client id with the format: <client_prefix>_<name>_<task_index>
-    // because the client id must be unique for each connection. Otherwise, the broker will only keep one active connection
-    // per client id
-    let client_id = format!(
-        "{}_{}_{}_{}",
-        c.client_prefix.as_deref().unwrap_or("arroyo-mqtt"),
-        job_id,
-        operator_id,
-        task_index,
-    );
+    let client_id = client_id(c, job_id, operator_id, task_index);
 
     let mut url = url::Url::parse(&c.url)?;
     let ssl = matches!(url.scheme(), "mqtts" | "ssl");
@@ -431,62 +580,57 @@ pub(crate) fn create_connection(
 
     options.set_keep_alive(Duration::from_secs(10));
 
-    if let Some(max_packet_size) = c.max_packet_size {
-        let max_packet_size: usize = max_packet_size
-            .try_into()
-            .map_err(|_| anyhow!("max_packet_size value {} is too large for this {}-bit platform. Only values up to {} are supported.", max_packet_size, std::mem::size_of::<usize>() * 8, usize::MAX))?;
+    if let Some(max_packet_size) = max_packet_size(c)? {
         options.set_max_packet_size(max_packet_size, max_packet_size);
     }
 
     if ssl {
-        let mut root_cert_store = RootCertStore::empty();
+        options.set_transport(tls_transport(c)?);
+    }
 
-        if let Some(ca) = c.tls.as_ref().and_then(|tls| tls.ca.as_ref()) {
-            let ca = ca.sub_env_vars().map_err(|e| anyhow!("{}", e))?;
-            let certificates = load_certs(&ca)?;
-            for cert in certificates {
-                root_cert_store.add(cert).unwrap();
-            }
-        } else {
-            for cert in load_native_certs().expect("could not load platform certs") {
-                root_cert_store.add(cert).unwrap();
-            }
-        }
+    if let Some((username, password)) = credentials(c)? {
+        options.set_credentials(username, password);
+    }
 
-        let builder = ClientConfig::builder().with_root_certificates(root_cert_store);
+    Ok(AsyncClient::new(options, 100))
+}
 
-        let tls_config = if let Some((Some(client_cert), Some(client_key))) = c
-            .tls
-            .as_ref()
-            .map(|tls| (tls.cert.as_ref(), tls.key.as_ref()))
-        {
-            let client_cert = client_cert.sub_env_vars().map_err(|e| anyhow!("{}", e))?;
-            let client_key = client_key.sub_env_vars().map_err(|e| anyhow!("{}", e))?;
-            let certs = load_certs(&client_cert)?;
-            let key = load_private_key(&client_key)?;
-
-            builder.with_client_auth_cert(certs, key.into())?
-        } else {
-            builder.with_no_client_auth()
-        };
+/// Creates an MQTT v5 connection, which sources only use when they need shared subscriptions or
+/// the user properties of messages, so that brokers that only speak v3.1.1 can still be read
+pub(crate) fn create_v5_connection(
+    c: &MqttConfig,
+    job_id: &str,
+    operator_id: &str,
+    task_index: usize,
+) -> anyhow::Result<(v5::AsyncClient, v5::EventLoop)> {
+    let client_id = client_id(c, job_id, operator_id, task_index);
+
+    let url = url::Url::parse(&c.url)?;
+    let ssl = match url.scheme() {
+        "mqtts" | "ssl" => true,
+        "mqtt" | "tcp" => false,
+        scheme => bail!("unsupported scheme '{}' for MQTT url", scheme),
+    };
+    let host = url
+        .host_str()
+        .ok_or_else(|| anyhow!("MQTT url '{}' has no host", c.url))?;
+    let port = url.port().unwrap_or(if ssl { 8883 } else { 1883 });
+
+    let mut options = v5::MqttOptions::new(client_id, host, port);
 
-        options.set_transport(rumqttc::Transport::tls_with_config(
-            rumqttc::TlsConfiguration::Rustls(Arc::new(tls_config)),
-        ));
+    options.set_keep_alive(Duration::from_secs(10));
+
+    if let Some(max_packet_size) = c.max_packet_size {
+        options.set_max_packet_size(Some(max_packet_size));
     }
 
-    let password = if let Some(password) = &c.password {
-        password.sub_env_vars().map_err(|e| anyhow!("{}", e))?
-    } else {
-        "".to_string()
-    };
+    if ssl {
+        options.set_transport(tls_transport(c)?);
+    }
 
-    if let Some(username) = &c.username {
-        options.set_credentials(
-            username.sub_env_vars().map_err(|e| anyhow!("{}", e))?,
-            password,
-        );
+    if let Some((username, password)) = credentials(c)? {
+        options.set_credentials(username, password);
     }
 
-    Ok(AsyncClient::new(options, 100))
+    Ok(v5::AsyncClient::new(options, 100))
 }
@@ -1,15 +1,20 @@
 use arrow::record_batch::RecordBatch;
 use async_trait::async_trait;
+use std::collections::HashMap;
 use std::sync::Arc;
 use std::sync::atomic::AtomicBool;
 use std::time::Duration;
+use tracing::warn;
 
-use crate::mqtt::MqttConfig;
+use crate::mqtt::{CommitMode, MqttConfig};
+use crate::pre_commit::PreCommitBuffer;
 use arroyo_formats::ser::ArrowSerializer;
 use arroyo_operator::context::{Collector, OperatorContext};
 use arroyo_operator::operator::ArrowOperator;
 use arroyo_rpc::errors::DataflowResult;
 use arroyo_rpc::formats::Format;
+use arroyo_rpc::grpc::rpc::TableConfig;
+use arroyo_types::CheckpointBarrier;
 use rumqttc::AsyncClient;
 use rumqttc::ConnectionError;
 use rumqttc::mqttbytes::QoS;
@@ -22,9 +27,12 @@ pub struct MqttSinkFunc {
     pub qos: QoS,
     pub topic: String,
     pub retain: bool,
+    pub commit_mode: CommitMode,
     pub serializer: ArrowSerializer,
     pub client: Option<AsyncClient>,
     pub stopped: Arc<AtomicBool>,
+    /// messages waiting for their checkpoint to commit, in exactly-once mode
+    pub(crate) pre_commits: PreCommitBuffer,
 }
 
 impl MqttSinkFunc {
@@ -34,9 +42,28 @@ impl MqttSinkFunc {
             qos,
             topic,
             retain,
+            commit_mode: CommitMode::AtLeastOnce,
             serializer: ArrowSerializer::new(format),
             client: None,
             stopped: Arc::new(AtomicBool::new(false)),
+            pre_commits: PreCommitBuffer::default(),
+        }
+    }
+
+    async fn publish(&mut self, v: Vec<u8>, ctx: &mut OperatorContext) {
+        match self
+            .client
+            .as_mut()
+            .unwrap()
+            .publish(&self.topic, self.qos, self.retain, v)
+            .await
+        {
+            Ok(_) => (),
+            Err(e) => {
+                ctx.report_error("Could not write to mqtt", format!("{e:?}"))
+                    .await;
+                panic!("Could not write to mqtt: {e:?}");
+            }
         }
     }
 }
@@ -46,7 +73,24 @@ impl ArrowOperator for MqttSinkFunc {
     fn name(&self) -> String {
         format!("mqtt-producer-{}", self.topic)
     }
+
+    fn tables(&self) -> HashMap<String, TableConfig> {
+        if self.is_committing() {
+            PreCommitBuffer::tables()
+        } else {
+            HashMap::new()
+        }
+    }
+
+    fn is_committing(&self) -> bool {
+        self.commit_mode == CommitMode::ExactlyOnce
+    }
+
     async fn on_start(&mut self, ctx: &mut OperatorContext) -> DataflowResult<()> {
+        if self.is_committing() {
+            self.pre_commits.restore(ctx).await;
+        }
+
         let mut attempts = 0;
         while attempts < 20 {
             match super::create_connection(
@@ -99,22 +143,51 @@ impl ArrowOperator for MqttSinkFunc {
         ctx: &mut OperatorContext,
         _: &mut dyn Collector,
     ) -> DataflowResult<()> {
-        for v in self.serializer.serialize(&batch) {
-            match self
-                .client
-                .as_mut()
-                .unwrap()
-                .publish(&self.topic, self.qos, self.retain, v)
-                .await
-            {
-                Ok(_) => (),
-                Err(e) => {
-                    ctx.report_error("Could not write to mqtt", format!("{e:?}"))
-                        .await;
-                    panic!("Could not write to mqtt: {e:?}");
-                }
+        if self.is_committing() {
+            self.pre_commits.push(self.serializer.serialize(&batch));
+            return Ok(());
+        }
+
+        let values: Vec<_> = self.serializer.serialize(&batch).collect();
+        for v in values {
+            self.publish(v, ctx).await;
+        }
+        Ok(())
+    }
+
+    async fn handle_checkpoint(
+        &mut self,
+        barrier: CheckpointBarrier,
+        ctx: &mut OperatorContext,
+        _: &mut dyn Collector,
+    ) -> DataflowResult<()> {
+        if !self.is_committing() {
+            return Ok(());
+        }
+
+        self.pre_commits.checkpoint(barrier.epoch, ctx).await;
+
+        Ok(())
+    }
+
+    async fn handle_commit(
+        &mut self,
+        epoch: u32,
+        _commit_data: &HashMap<String, HashMap<u32, Vec<u8>>>,
+        ctx: &mut OperatorContext,
+    ) -> DataflowResult<()> {
+        if !self.is_committing() {
+            warn!("received commit but consistency mode is not exactly once");
+            return Ok(());
+        }
+
+        for pre_commit in self.pre_commits.take_committable(epoch) {
+            for v in pre_commit.messages {
+                self.publish(v, ctx).await;
             }
         }
+
+        PreCommitBuffer::finish_commit(epoch, ctx).await;
         Ok(())
     }
 }
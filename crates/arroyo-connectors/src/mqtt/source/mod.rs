@@ -5,17 +5,18 @@ use arroyo_rpc::formats::{BadData, Format, Framing};
 use arroyo_rpc::{ControlMessage, MetadataField, grpc::rpc::StopMode};
 use arroyo_types::{SignalMessage, Watermark};
 use async_trait::async_trait;
+use bytes::Bytes;
 use governor::{Quota, RateLimiter as GovernorRateLimiter};
-use rumqttc::Outgoing;
 use rumqttc::mqttbytes::QoS;
-use rumqttc::{Event as MqttEvent, Incoming};
+use rumqttc::v5::mqttbytes::v5::Filter;
+use rumqttc::{Outgoing, SubscribeFilter, v5};
 use std::collections::HashMap;
 use std::num::NonZeroU32;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::{Duration, SystemTime};
 
-use crate::mqtt::{MqttConfig, create_connection};
+use crate::mqtt::{MqttConfig, create_connection, create_v5_connection};
 use arroyo_operator::SourceFinishType;
 use arroyo_operator::context::{SourceCollector, SourceContext};
 use arroyo_operator::operator::SourceOperator;
@@ -27,9 +28,108 @@ use tokio::time::MissedTickBehavior;
 #[cfg(test)]
 mod test;
 
+/// A connection with either protocol version; v5 is only used when a feature requires it
+enum Connection {
+    V3(rumqttc::AsyncClient, rumqttc::EventLoop),
+    V5(v5::AsyncClient, v5::EventLoop),
+}
+
+enum Polled {
+    Message(Message),
+    Subscribed,
+    Other,
+}
+
+struct Message {
+    topic: Bytes,
+    payload: Bytes,
+    qos: i32,
+    retain: bool,
+    /// the user properties as a JSON object, which are only sent over v5
+    user_properties: Option<String>,
+}
+
+impl Connection {
+    async fn subscribe(&self, filters: Vec<String>, qos: QoS) -> anyhow::Result<()> {
+        match self {
+            Connection::V3(client, _) => {
+                client
+                    .subscribe_many(filters.into_iter().map(|f| SubscribeFilter::new(f, qos)))
+                    .await?
+            }
+            Connection::V5(client, _) => {
+                let qos = v5_qos(qos);
+                client
+                    .subscribe_many(filters.into_iter().map(|f| Filter::new(f, qos)))
+                    .await?
+            }
+        }
+        Ok(())
+    }
+
+    async fn poll(&mut self) -> anyhow::Result<Polled> {
+        Ok(match self {
+            Connection::V3(_, eventloop) => match eventloop.poll().await? {
+                rumqttc::Event::Incoming(rumqttc::Incoming::Publish(p)) => {
+                    Polled::Message(Message {
+                        topic: Bytes::from(p.topic),
+                        payload: p.payload,
+                        qos: p.qos as i32,
+                        retain: p.retain,
+                        user_properties: None,
+                    })
+                }
+                rumqttc::Event::Outgoing(Outgoing::Subscribe(_)) => Polled::Subscribed,
+                _ => Polled::Other,
+            },
+            Connection::V5(_, eventloop) => match eventloop.poll().await? {
+                v5::Event::Incoming(v5::Incoming::Publish(p)) => {
+                    let user_properties = p
+                        .properties
+                        .as_ref()
+                        .filter(|props| !props.user_properties.is_empty())
+                        .map(|props| {
+                            // MQTT allows repeated keys, in which case the last value wins
+                            serde_json::Value::Object(
+                                props
+                                    .user_properties
+                                    .iter()
+                                    .map(|(k, v)| (k.clone(), serde_json::Value::String(v.clone())))
+                                    .collect(),
+                            )
+                            .to_string()
+                        });
+
+                    Polled::Message(Message {
+                        topic: p.topic,
+                        payload: p.payload,
+                        qos: p.qos as i32,
+                        retain: p.retain,
+                        user_properties,
+                    })
+                }
+                v5::Event::Outgoing(Outgoing::Subscribe(_)) => Polled::Subscribed,
+                _ => Polled::Other,
+            },
+        })
+    }
+}
+
+fn v5_qos(qos: QoS) -> v5::mqttbytes::QoS {
+    match qos {
+        QoS::AtMostOnce => v5::mqttbytes::QoS::AtMostOnce,
+        QoS::AtLeastOnce => v5::mqttbytes::QoS::AtLeastOnce,
+        QoS::ExactlyOnce => v5::mqttbytes::QoS::ExactlyOnce,
+    }
+}
+
 pub struct MqttSourceFunc {
     pub config: MqttConfig,
-    pub topic: String,
+    /// topic filters, which may contain wildcards
+    pub topics: Vec<String>,
+    /// if set, the topics are subscribed to as `$share/<group>/<topic>`, so that the broker
+    /// splits messages between the subtasks
+    pub shared_group: Option<String>,
     pub qos: QoS,
     pub format: Format,
     pub framing: Option<Framing>,
@@ -42,7 +142,7 @@ pub struct MqttSourceFunc {
 #[async_trait]
 impl SourceOperator for MqttSourceFunc {
     fn name(&self) -> String {
-        format!("mqtt-{}", self.topic)
+        format!("mqtt-{}", self.topics.join(","))
     }
 
     fn tables(&self) -> HashMap<String, TableConfig> {
@@ -62,7 +162,8 @@ impl SourceOperator for MqttSourceFunc {
 impl MqttSourceFunc {
     pub fn new(
         config: MqttConfig,
-        topic: String,
+        topics: Vec<String>,
+        shared_group: Option<String>,
         qos: QoS,
         format: Format,
         framing: Option<Framing>,
@@ -72,7 +173,8 @@ impl MqttSourceFunc {
     ) -> Self {
         Self {
             config,
-            topic,
+            topics,
+            shared_group,
             qos,
             format,
             framing,
@@ -87,6 +189,109 @@ impl MqttSourceFunc {
         self.subscribed.clone()
     }
 
+    /// Whether the source needs MQTT v5, which is only the case for shared subscriptions and the
+    /// `user_properties` metadata; otherwise it connects with v3.1.1, which all brokers support
+    fn uses_v5(&self) -> bool {
+        self.shared_group.is_some()
+            || self
+                .metadata_fields
+                .iter()
+                .any(|f| f.key == "user_properties")
+    }
+
+    fn filters(&self) -> Vec<String> {
+        self.topics
+            .iter()
+            .map(|topic| match &self.shared_group {
+                Some(group) => format!("$share/{group}/{topic}"),
+                None => topic.clone(),
+            })
+            .collect()
+    }
+
+    async fn subscribe(&self, connection: &Connection) -> DataflowResult<()> {
+        connection
+            .subscribe(self.filters(), self.qos)
+            .await
+            .map_err(|e| {
+                connector_err!(
+                    External,
+                    WithBackoff,
+                    "failed to subscribe to MQTT topics '{}': {}",
+                    self.topics.join(","),
+                    e
+                )
+            })
+    }
+
+    async fn handle_control_message(
+        &mut self,
+        ctx: &mut SourceContext,
+        collector: &mut SourceCollector,
+        msg: Option<ControlMessage>,
+    ) -> Option<SourceFinishType> {
+        match msg {
+            Some(ControlMessage::Checkpoint(c)) => {
+                tracing::debug!("starting checkpointing {}", ctx.task_info.task_index);
+                if self.start_checkpoint(c, ctx, collector).await {
+                    return Some(SourceFinishType::Immediate);
+                }
+            }
+            Some(ControlMessage::Stop { mode }) => {
+                tracing::info!("Stopping Mqtt source: {:?}", mode);
+
+                match mode {
+                    StopMode::Graceful => {
+                        return Some(SourceFinishType::Graceful);
+                    }
+                    StopMode::Immediate => {
+                        return Some(SourceFinishType::Immediate);
+                    }
+                }
+            }
+            Some(ControlMessage::Commit { .. }) => {
+                unreachable!("sources shouldn't receive commit messages");
+            }
+            Some(ControlMessage::LoadCompacted { compacted }) => {
+                ctx.load_compacted(compacted).await;
+            }
+            Some(ControlMessage::NoOp) => {}
+            None => {}
+        }
+        None
+    }
+
+    async fn handle_message(
+        &self,
+        p: Message,
+        collector: &mut SourceCollector,
+    ) -> DataflowResult<()> {
+        let topic = String::from_utf8_lossy(&p.topic).to_string();
+
+        let connector_metadata = if !self.metadata_fields.is_empty() {
+            let mut connector_metadata = HashMap::new();
+            for mf in &self.metadata_fields {
+                connector_metadata.insert(
+                    mf.field_name.as_str(),
+                    match mf.key.as_str() {
+                        "topic" => FieldValueType::String(Some(&topic)),
+                        "qos" => FieldValueType::Int32(Some(p.qos)),
+                        "retain" => FieldValueType::Boolean(Some(p.retain)),
+                        "user_properties" => FieldValueType::String(p.user_properties.as_deref()),
+                        k => unreachable!("invalid metadata key '{}' for mqtt", k),
+                    },
+                );
+            }
+            Some(connector_metadata)
+        } else {
+            None
+        };
+
+        collector
+            .deserialize_slice(&p.payload, SystemTime::now(), connector_metadata.as_ref())
+            .await
+    }
+
     async fn run_int(
         &mut self,
         ctx: &mut SourceContext,
@@ -99,78 +304,68 @@ impl MqttSourceFunc {
             &self.metadata_fields,
         );
 
-        if ctx.task_info.task_index > 0 {
+        // without a shared subscription, each subscriber receives every message, so only the
+        // first subtask can read
+        if self.shared_group.is_none() && ctx.task_info.task_index > 0 {
             tracing::warn!(
-                "Mqtt Consumer {}-{} can only be executed on a single worker... setting idle",
+                "Mqtt Consumer {}-{} can only be executed on a single worker without a shared subscription... setting idle",
                 ctx.task_info.operator_id,
                 ctx.task_info.task_index
             );
             collector
                 .broadcast(SignalMessage::Watermark(Watermark::Idle))
                 .await;
+
+            loop {
+                let msg = ctx.control_rx.recv().await;
+                if let Some(r) = self.handle_control_message(ctx, collector, msg).await {
+                    return Ok(r);
+                }
+            }
         }
 
-        let (client, mut eventloop) = create_connection(
-            &self.config,
-            &ctx.task_info.job_id,
-            &ctx.task_info.operator_id,
-            ctx.task_info.task_index as usize,
-        )
-        .context("creating connection")?;
+        let mut connection = if self.uses_v5() {
+            let (client, eventloop) = create_v5_connection(
+                &self.config,
+                &ctx.task_info.job_id,
+                &ctx.task_info.operator_id,
+                ctx.task_info.task_index as usize,
+            )
+            .context("creating connection")?;
+            Connection::V5(client, eventloop)
+        } else {
+            let (client, eventloop) = create_connection(
+                &self.config,
+                &ctx.task_info.job_id,
+                &ctx.task_info.operator_id,
+                ctx.task_info.task_index as usize,
+            )
+            .context("creating connection")?;
+            Connection::V3(client, eventloop)
+        };
 
-        client
-            .subscribe(self.topic.clone(), self.qos)
-            .await
-            .map_err(|e| {
-                connector_err!(
-                    External,
-                    WithBackoff,
-                    "failed to subscribe to MQTT topic '{}': {}",
-                    self.topic,
-                    e
-                )
-            })?;
+        self.subscribe(&connection).await?;
 
         let rate_limiter = GovernorRateLimiter::direct(Quota::per_second(self.messages_per_second));
 
-        let topic = self.topic.clone();
-        let qos = self.qos;
         let mut flush_ticker = tokio::time::interval(Duration::from_millis(50));
         flush_ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
 
         loop {
             select! {
-                event = eventloop.poll() => {
+                event = connection.poll() => {
                     match event {
-                        Ok(MqttEvent::Incoming(Incoming::Publish(p))) => {
-                            let topic = String::from_utf8_lossy(p.topic.as_bytes()).to_string();
-
-                            let connector_metadata = if !self.metadata_fields.is_empty() {
-                                let mut connector_metadata = HashMap::new();
-                                for mf in &self.metadata_fields {
-                                    connector_metadata.insert(mf.field_name.as_str(), match mf.key.as_str() {
-                                        "topic" => FieldValueType::String(Some(&topic)),
-                                        k => unreachable!("invalid metadata key '{}' for mqtt", k)
-                                    });
-                                }
-                                Some(connector_metadata)
-                            } else {
-                                None
-                            };
-
-                            collector.deserialize_slice(&p.payload, SystemTime::now(), connector_metadata.as_ref()).await?;
+                        Ok(Polled::Message(p)) => {
+                            self.handle_message(p, collector).await?;
                             rate_limiter.until_ready().await;
                         }
-                        Ok(MqttEvent::Outgoing(Outgoing::Subscribe(_))) => {
+                        Ok(Polled::Subscribed) => {
                             self.subscribed.store(true, Ordering::Relaxed);
                         }
-                        Ok(_) => (),
+                        Ok(Polled::Other) => (),
                         Err(err) => {
                             tracing::error!("Failed to poll mqtt eventloop: {}", err);
-                            client
-                                .subscribe(topic.clone(), qos)
-                                .await
-                                .map_err(|e| connector_err!(External, WithBackoff, "failed to resubscribe to MQTT topic '{}': {}", topic, e))?;
+                            self.subscribe(&connection).await?;
                         }
                     }
                 }
@@ -180,35 +375,8 @@ impl MqttSourceFunc {
                     }
                 }
                 control_message = ctx.control_rx.recv() => {
-                    match control_message {
-                        Some(ControlMessage::Checkpoint(c)) => {
-                            tracing::debug!("starting checkpointing {}", ctx.task_info.task_index);
-                            if self.start_checkpoint(c, ctx, collector).await {
-                                return Ok(SourceFinishType::Immediate);
-                            }
-                        },
-                        Some(ControlMessage::Stop { mode }) => {
-                            tracing::info!("Stopping Mqtt source: {:?}", mode);
-
-                            match mode {
-                                StopMode::Graceful => {
-                                    return Ok(SourceFinishType::Graceful);
-                                }
-                                StopMode::Immediate => {
-                                    return Ok(SourceFinishType::Immediate);
-                                }
-                            }
-                        }
-                        Some(ControlMessage::Commit { .. }) => {
-                            unreachable!("sources shouldn't receive commit messages");
-                        }
-                        Some(ControlMessage::LoadCompacted {compacted}) => {
-                            ctx.load_compacted(compacted).await;
-                        }
-                        Some(ControlMessage::NoOp) => {}
-                        None => {
-
-                        }
+                    if let Some(r) = self.handle_control_message(ctx, collector, control_message).await {
+                        return Ok(r);
                     }
                 }
             }
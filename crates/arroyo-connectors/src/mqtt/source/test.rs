@@ -13,7 +13,7 @@ use arroyo_operator::operator::SourceOperator;
 use arroyo_rpc::df::ArroyoSchema;
 use arroyo_rpc::formats::{Format, JsonFormat};
 use arroyo_rpc::var_str::VarStr;
-use arroyo_rpc::{ControlMessage, ControlResp};
+use arroyo_rpc::{ControlMessage, ControlResp, MetadataField};
 use arroyo_types::{ArrowMessage, ChainInfo, TaskInfo, to_nanos};
 use rand::random;
 use rumqttc::mqttbytes::QoS;
@@ -123,13 +123,18 @@ impl MqttTopicTester {
         client
     }
 
-    async fn get_source_with_reader(&self, task_info: TaskInfo) -> MqttSourceWithReads {
+    async fn get_source_with_reader(
+        &self,
+        task_info: TaskInfo,
+        shared_group: Option<String>,
+    ) -> MqttSourceWithReads {
         let config = self.get_config();
         let task_info = Arc::new(task_info);
 
         let mut mqtt = MqttSourceFunc::new(
             config,
-            self.topic.clone(),
+            vec![self.topic.clone()],
+            shared_group,
             QoS::AtLeastOnce,
             Format::Json(JsonFormat::default()),
             None,
@@ -198,10 +203,9 @@ impl MqttTopicTester {
     }
 }
 
-#[tokio::test]
-async fn test_mqtt() {
+async fn test_read(topic: &str, shared_group: Option<String>) {
     let mqtt_tester = MqttTopicTester {
-        topic: "mqtt-arroyo-test".to_string(),
+        topic: topic.to_string(),
         port: 1883,
         ca: None,
         cert: None,
@@ -213,7 +217,9 @@ async fn test_mqtt() {
     let mut task_info = arroyo_types::get_test_task_info();
     task_info.job_id = format!("mqtt-job-{}", random::<u64>());
 
-    let mut reader = mqtt_tester.get_source_with_reader(task_info.clone()).await;
+    let mut reader = mqtt_tester
+        .get_source_with_reader(task_info.clone(), shared_group)
+        .await;
 
     reader
         .wait_for_subscription(std::time::Duration::from_secs(5))
@@ -248,3 +254,84 @@ async fn test_mqtt() {
         .await
         .unwrap();
 }
+
+#[tokio::test]
+async fn test_mqtt() {
+    test_read("mqtt-arroyo-test", None).await;
+}
+
+#[tokio::test]
+async fn test_mqtt_shared_subscription() {
+    test_read("mqtt-arroyo-test-shared", Some("arroyo".to_string())).await;
+}
+
+#[test]
+fn test_shared_subscription_filters() {
+    let tester = MqttTopicTester {
+        topic: "unused".to_string(),
+        port: 1883,
+        ca: None,
+        cert: None,
+        key: None,
+        username: None,
+        password: None,
+    };
+
+    let mqtt = MqttSourceFunc::new(
+        tester.get_config(),
+        vec!["sensors/+/temperature".to_string(), "alerts/#".to_string()],
+        Some("arroyo".to_string()),
+        QoS::AtLeastOnce,
+        Format::Json(JsonFormat::default()),
+        None,
+        None,
+        10,
+        vec![],
+    );
+
+    assert!(mqtt.uses_v5());
+    assert_eq!(
+        mqtt.filters(),
+        vec![
+            "$share/arroyo/sensors/+/temperature".to_string(),
+            "$share/arroyo/alerts/#".to_string()
+        ]
+    );
+}
+
+#[test]
+fn test_uses_v5_only_when_needed() {
+    let tester = MqttTopicTester {
+        topic: "unused".to_string(),
+        port: 1883,
+        ca: None,
+        cert: None,
+        key: None,
+        username: None,
+        password: None,
+    };
+
+    let source = |metadata_fields| {
+        MqttSourceFunc::new(
+            tester.get_config(),
+            vec!["sensors/#".to_string()],
+            None,
+            QoS::AtLeastOnce,
+            Format::Json(JsonFormat::default()),
+            None,
+            None,
+            10,
+            metadata_fields,
+        )
+    };
+
+    let metadata = |key: &str| MetadataField {
+        field_name: key.to_string(),
+        key: key.to_string(),
+        data_type: None,
+    };
+
+    assert!(!source(vec![]).uses_v5());
+    assert!(!source(vec![metadata("topic"), metadata("qos"), metadata("retain")]).uses_v5());
+    assert!(source(vec![metadata("user_properties")]).uses_v5());
+}
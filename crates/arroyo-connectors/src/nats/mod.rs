@@ -1,5 +1,6 @@
 use crate::nats::sink::NatsSinkFunc;
 use crate::nats::source::NatsSourceFunc;
+use crate::pre_commit::PreCommitBuffer;
 use anyhow::anyhow;
 use anyhow::bail;
 use arroyo_formats::ser::ArrowSerializer;
@@ -131,7 +132,15 @@ impl NatsConnector {
                     Some(subject) => Some(SinkType::Subject(subject)),
                     None => bail!("`subject` must be set for sink"),
                 };
-                ConnectorType::Sink { sink_type }
+                let commit_mode = match options.pull_opt_str("sink.commit_mode")?.as_deref() {
+                    Some("at_least_once") | None => None,
+                    Some("exactly_once") => Some(CommitMode::ExactlyOnce),
+                    Some(other) => bail!("invalid value for sink.commit_mode '{}'", other),
+                };
+                ConnectorType::Sink {
+                    sink_type,
+                    commit_mode,
+                }
             }
             _ => bail!("Type must be one of 'source' or 'sink'"),
         };
@@ -270,6 +279,7 @@ impl Connector for NatsConnector {
             bad_data: schema.bad_data.clone(),
             framing: schema.framing.clone(),
             metadata_fields: schema.metadata_fields(),
+            partition_watermarks: None,
         };
 
         Ok(Connection::new(
@@ -334,24 +344,27 @@ impl Connector for NatsConnector {
                     .unwrap(),
                 }))
             }
-            ConnectorType::Sink { ref sink_type } => {
-                ConstructedOperator::from_operator(Box::new(NatsSinkFunc {
-                    sink_type: sink_type
-                        .clone()
-                        .ok_or_else(|| anyhow!("`sinkType` is required"))?,
-                    servers: profile
-                        .servers
-                        .sub_env_vars()
-                        .map_err(|e| e.context("servers"))?
-                        .clone(),
-                    connection: profile.clone(),
-                    table: table.clone(),
-                    publisher: None,
-                    serializer: ArrowSerializer::new(
-                        config.format.expect("Format must be set for NATS source"),
-                    ),
-                }))
-            }
+            ConnectorType::Sink {
+                ref sink_type,
+                ref commit_mode,
+            } => ConstructedOperator::from_operator(Box::new(NatsSinkFunc {
+                sink_type: sink_type
+                    .clone()
+                    .ok_or_else(|| anyhow!("`sinkType` is required"))?,
+                commit_mode: commit_mode.unwrap_or(CommitMode::AtLeastOnce),
+                servers: profile
+                    .servers
+                    .sub_env_vars()
+                    .map_err(|e| e.context("servers"))?
+                    .clone(),
+                connection: profile.clone(),
+                table: table.clone(),
+                publisher: None,
+                serializer: ArrowSerializer::new(
+                    config.format.expect("Format must be set for NATS source"),
+                ),
+                pre_commits: PreCommitBuffer::default(),
+            })),
         })
     }
 }
@@ -1,6 +1,7 @@
 use super::NatsConfig;
 use super::NatsTable;
-use super::{SinkType, get_nats_client};
+use super::{CommitMode, SinkType, get_nats_client};
+use crate::pre_commit::{PreCommit, PreCommitBuffer};
 use arrow::array::RecordBatch;
 use arroyo_formats::ser::ArrowSerializer;
 use arroyo_operator::context::{Collector, OperatorContext};
@@ -8,16 +9,85 @@ use arroyo_operator::operator::ArrowOperator;
 use arroyo_rpc::errors::DataflowResult;
 use arroyo_rpc::grpc::rpc::TableConfig;
 use arroyo_types::*;
+use async_nats::HeaderMap;
+use async_nats::header::NATS_MESSAGE_ID;
 use async_trait::async_trait;
 use std::collections::HashMap;
+use tracing::{debug, warn};
 
 pub struct NatsSinkFunc {
     pub sink_type: SinkType,
     pub servers: String,
     pub connection: NatsConfig,
     pub table: NatsTable,
+    pub commit_mode: CommitMode,
     pub publisher: Option<async_nats::Client>,
     pub serializer: ArrowSerializer,
+    /// messages waiting for their checkpoint to commit, in exactly-once mode
+    pub(crate) pre_commits: PreCommitBuffer,
+}
+
+impl NatsSinkFunc {
+    fn subject(&self) -> async_nats::Subject {
+        let SinkType::Subject(s) = &self.sink_type;
+        async_nats::Subject::from(s.clone())
+    }
+
+    /// Publishes the pre-committed messages through JetStream. Each message gets an id derived
+    /// from the job, subtask, epoch and its position within the epoch, so re-publishing the same
+    /// pre-commit (for example, after recovering from a checkpoint that was mid-commit) will be
+    /// deduplicated by the server.
+    async fn publish_pre_commit(
+        &self,
+        pre_commit: &PreCommit,
+        ctx: &OperatorContext,
+    ) -> anyhow::Result<()> {
+        let jetstream = async_nats::jetstream::new(
+            self.publisher
+                .clone()
+                .expect("Something went wrong while instantiating the publisher."),
+        );
+
+        let subject = self.subject();
+        let mut acks = Vec::with_capacity(pre_commit.messages.len());
+        for (i, msg) in pre_commit.messages.iter().enumerate() {
+            let mut headers = HeaderMap::new();
+            headers.insert(
+                NATS_MESSAGE_ID,
+                format!(
+                    "{}-{}-{}-{}-{}",
+                    ctx.task_info.job_id,
+                    ctx.task_info.operator_id,
+                    pre_commit.subtask,
+                    pre_commit.epoch,
+                    i
+                )
+                .as_str(),
+            );
+
+            acks.push(
+                jetstream
+                    .publish_with_headers(subject.clone(), headers, msg.clone().into())
+                    .await?,
+            );
+        }
+
+        let mut duplicates = 0;
+        for ack in acks {
+            if ack.await?.duplicate {
+                duplicates += 1;
+            }
+        }
+
+        if duplicates > 0 {
+            debug!(
+                "{} messages for epoch {} were already published",
+                duplicates, pre_commit.epoch
+            );
+        }
+
+        Ok(())
+    }
 }
 
 #[async_trait]
@@ -34,10 +104,18 @@ impl ArrowOperator for NatsSinkFunc {
     }
 
     fn tables(&self) -> HashMap<String, TableConfig> {
-        HashMap::new()
+        if self.is_committing() {
+            PreCommitBuffer::tables()
+        } else {
+            HashMap::new()
+        }
     }
 
-    async fn on_start(&mut self, _ctx: &mut OperatorContext) -> DataflowResult<()> {
+    fn is_committing(&self) -> bool {
+        self.commit_mode == CommitMode::ExactlyOnce
+    }
+
+    async fn on_start(&mut self, ctx: &mut OperatorContext) -> DataflowResult<()> {
         match get_nats_client(&self.connection).await {
             Ok(client) => {
                 self.publisher = Some(client);
@@ -46,15 +124,27 @@ impl ArrowOperator for NatsSinkFunc {
                 panic!("Failed to construct NATS publisher: {e:?}");
             }
         }
+
+        if self.is_committing() {
+            // restore any messages that were checkpointed but may not have been committed; if the
+            // restored checkpoint was mid-commit, the controller will send us the commit again
+            self.pre_commits.restore(ctx).await;
+        }
+
         Ok(())
     }
 
     async fn handle_checkpoint(
         &mut self,
-        _: CheckpointBarrier,
-        _ctx: &mut OperatorContext,
+        barrier: CheckpointBarrier,
+        ctx: &mut OperatorContext,
         _: &mut dyn Collector,
     ) -> DataflowResult<()> {
+        if self.is_committing() {
+            self.pre_commits.checkpoint(barrier.epoch, ctx).await;
+            return Ok(());
+        }
+
         // TODO: Implement checkpointing of in-progress data to avoid depending on
         // the downstream NATS availability to flush and checkpoint.
         let publisher = self
@@ -71,14 +161,41 @@ impl ArrowOperator for NatsSinkFunc {
         Ok(())
     }
 
+    async fn handle_commit(
+        &mut self,
+        epoch: u32,
+        _commit_data: &HashMap<String, HashMap<u32, Vec<u8>>>,
+        ctx: &mut OperatorContext,
+    ) -> DataflowResult<()> {
+        if !self.is_committing() {
+            warn!("received commit but consistency mode is not exactly once");
+            return Ok(());
+        }
+
+        for pre_commit in self.pre_commits.take_committable(epoch) {
+            if let Err(e) = self.publish_pre_commit(&pre_commit, ctx).await {
+                ctx.report_error("Failed to publish to NATS JetStream", format!("{e:?}"))
+                    .await;
+                panic!("Failed to publish to NATS JetStream: {e:?}");
+            }
+        }
+
+        PreCommitBuffer::finish_commit(epoch, ctx).await;
+        Ok(())
+    }
+
     async fn process_batch(
         &mut self,
         batch: RecordBatch,
         ctx: &mut OperatorContext,
         _: &mut dyn Collector,
     ) -> DataflowResult<()> {
-        let SinkType::Subject(s) = &self.sink_type;
-        let nats_subject = async_nats::Subject::from(s.clone());
+        if self.is_committing() {
+            self.pre_commits.push(self.serializer.serialize(&batch));
+            return Ok(());
+        }
+
+        let nats_subject = self.subject();
         for msg in self.serializer.serialize(&batch) {
             let publisher = self
                 .publisher
@@ -206,6 +206,7 @@ impl Connector for NexmarkConnector {
             bad_data: None,
             framing: None,
             metadata_fields: vec![],
+            partition_watermarks: None,
         };
 
         Ok(Connection::new(
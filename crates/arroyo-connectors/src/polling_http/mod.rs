@@ -1,9 +1,8 @@
 mod operator;
 
-use std::str::FromStr;
 use std::time::Duration;
 
-use anyhow::anyhow;
+use anyhow::{anyhow, bail};
 use arroyo_rpc::{ConnectorOptions, OperatorConfig, var_str::VarStr};
 use arroyo_types::string_to_map;
 use reqwest::{Client, Request};
@@ -18,12 +17,16 @@ use serde::{Deserialize, Serialize};
 
 use crate::{EmptyConfig, construct_http_client};
 
-use crate::polling_http::operator::{PollingHttpSourceFunc, PollingHttpSourceState};
+use crate::polling_http::operator::{
+    Pagination, PollingHttpSourceFunc, PollingHttpSourceState, render, validate_endpoint,
+};
 use arroyo_operator::connector::Connector;
 use arroyo_operator::operator::ConstructedOperator;
 
 const TABLE_SCHEMA: &str = include_str!("./table.json");
 const DEFAULT_POLLING_INTERVAL: Duration = Duration::from_secs(1);
+const DEFAULT_MAX_PAGES: u64 = 100;
+const DEFAULT_MAX_RETRIES: u64 = 3;
 
 import_types!(
     schema = "src/polling_http/table.json",
@@ -34,6 +37,33 @@ const ICON: &str = include_str!("./http.svg");
 pub struct PollingHTTPConnector {}
 
 impl PollingHTTPConnector {
+    fn pagination(table: &PollingHttpTable) -> anyhow::Result<Pagination> {
+        Ok(match table.pagination {
+            None | Some(PaginationMode::None) => Pagination::None,
+            Some(PaginationMode::LinkHeader) => Pagination::LinkHeader,
+            Some(PaginationMode::NextToken) => Pagination::NextToken {
+                pointer: table.pagination_token_pointer.clone().ok_or_else(|| {
+                    anyhow!("'pagination.token_pointer' must be set for next_token pagination")
+                })?,
+                param: table.pagination_param.clone().ok_or_else(|| {
+                    anyhow!("'pagination.param' must be set for next_token pagination")
+                })?,
+            },
+            Some(PaginationMode::Offset) => Pagination::Offset {
+                offset_param: table.pagination_param.clone().ok_or_else(|| {
+                    anyhow!("'pagination.param' must be set for offset pagination")
+                })?,
+                limit_param: table.pagination_limit_param.clone().ok_or_else(|| {
+                    anyhow!("'pagination.limit_param' must be set for offset pagination")
+                })?,
+                page_size: table.pagination_page_size.map(|n| n.get()).ok_or_else(|| {
+                    anyhow!("'pagination.page_size' must be set for offset pagination")
+                })?,
+                records_pointer: table.pagination_records_pointer.clone().unwrap_or_default(),
+            },
+        })
+    }
+
     fn construct_test_request(
         client: &Client,
         config: &PollingHttpTable,
@@ -45,11 +75,11 @@ impl PollingHTTPConnector {
                 Some(Method::Put) => reqwest::Method::PUT,
                 Some(Method::Patch) => reqwest::Method::PATCH,
             },
-            &config.endpoint,
+            render(&config.endpoint, config.cursor_initial.as_deref(), true),
         );
 
         if let Some(body) = &config.body {
-            req = req.body(body.clone());
+            req = req.body(render(body, config.cursor_initial.as_deref(), false));
         }
 
         let req = req
@@ -169,6 +199,12 @@ impl Connector for PollingHTTPConnector {
             .transpose()
             .map_err(|_| anyhow!("invalid value for 'emit_behavior'"))?;
 
+        let pagination: Option<PaginationMode> = options
+            .pull_opt_str("pagination")?
+            .map(|s| s.try_into())
+            .transpose()
+            .map_err(|_| anyhow!("invalid value for 'pagination'"))?;
+
         self.from_config(
             None,
             name,
@@ -180,6 +216,24 @@ impl Connector for PollingHTTPConnector {
                 body,
                 poll_interval_ms: interval,
                 emit_behavior,
+                pagination,
+                pagination_token_pointer: options.pull_opt_str("pagination.token_pointer")?,
+                pagination_param: options.pull_opt_str("pagination.param")?,
+                pagination_limit_param: options.pull_opt_str("pagination.limit_param")?,
+                pagination_page_size: options
+                    .pull_opt_u64("pagination.page_size")?
+                    .map(|t| t.try_into())
+                    .transpose()
+                    .map_err(|_| anyhow!("pagination.page_size must be greater than 0"))?,
+                pagination_records_pointer: options.pull_opt_str("pagination.records_pointer")?,
+                pagination_max_pages: options
+                    .pull_opt_u64("pagination.max_pages")?
+                    .map(|t| t.try_into())
+                    .transpose()
+                    .map_err(|_| anyhow!("pagination.max_pages must be greater than 0"))?,
+                cursor_pointer: options.pull_opt_str("cursor.pointer")?,
+                cursor_initial: options.pull_opt_str("cursor.initial")?,
+                max_retries: options.pull_opt_u64("max_retries")?,
             },
             schema,
         )
@@ -195,6 +249,18 @@ impl Connector for PollingHTTPConnector {
     ) -> anyhow::Result<arroyo_operator::connector::Connection> {
         let description = format!("PollingHTTPSource<{}>", table.endpoint);
 
+        validate_endpoint(&table.endpoint)?;
+        Self::pagination(&table)?;
+
+        let uses_cursor = table.endpoint.contains(operator::CURSOR_PLACEHOLDER)
+            || table
+                .body
+                .as_ref()
+                .is_some_and(|b| b.contains(operator::CURSOR_PLACEHOLDER));
+        if uses_cursor && table.cursor_pointer.is_none() && table.cursor_initial.is_none() {
+            bail!("'cursor.pointer' or 'cursor.initial' must be set to use {{cursor}}");
+        }
+
         if let Some(headers) = &table.headers {
             string_to_map(&headers.sub_env_vars()?, ':').ok_or_else(|| {
                 anyhow!(
@@ -222,6 +288,7 @@ impl Connector for PollingHTTPConnector {
             bad_data: schema.bad_data.clone(),
             framing: schema.framing.clone(),
             metadata_fields: vec![],
+            partition_watermarks: None,
         };
 
         Ok(Connection::new(
@@ -269,14 +336,22 @@ impl Connector for PollingHTTPConnector {
                     .timeout(Duration::from_secs(5))
                     .build()
                     .expect("could not construct http client"),
-                endpoint: url::Url::from_str(&table.endpoint).expect("invalid endpoint"),
+                pagination: Self::pagination(&table)?,
+                max_pages: table
+                    .pagination_max_pages
+                    .map(|n| n.get())
+                    .unwrap_or(DEFAULT_MAX_PAGES),
+                cursor_pointer: table.cursor_pointer,
+                cursor: table.cursor_initial,
+                max_retries: table.max_retries.unwrap_or(DEFAULT_MAX_RETRIES) as u32,
+                endpoint: table.endpoint,
                 method: match table.method {
                     None | Some(Method::Get) => reqwest::Method::GET,
                     Some(Method::Post) => reqwest::Method::POST,
                     Some(Method::Put) => reqwest::Method::PUT,
                     Some(Method::Patch) => reqwest::Method::PATCH,
                 },
-                body: table.body.map(|b| b.into()),
+                body: table.body,
                 polling_interval: table
                     .poll_interval_ms
                     .map(|d| Duration::from_millis(d as u64))
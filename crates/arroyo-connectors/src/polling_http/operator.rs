@@ -1,10 +1,12 @@
-use anyhow::bail;
+use anyhow::{anyhow, bail};
 use arroyo_rpc::{ControlMessage, connector_err};
 use arroyo_types::{SignalMessage, Watermark};
 use async_trait::async_trait;
 use bincode::{Decode, Encode};
 use bytes::Bytes;
 use futures::StreamExt;
+use reqwest::StatusCode;
+use reqwest::header::{HeaderMap, LINK, RETRY_AFTER};
 use std::borrow::Cow;
 use std::collections::HashMap;
 use std::time::Duration;
@@ -24,15 +26,57 @@ use arroyo_state::tables::global_keyed_map::GlobalKeyedView;
 use tracing::{debug, info, warn};
 
 const MAX_BODY_SIZE: usize = 5 * 1024 * 1024; // 5M ought to be enough for anybody
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+// the longest we'll wait when a server asks us to retry later
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(5 * 60);
+
+pub const CURSOR_PLACEHOLDER: &str = "{cursor}";
+
+/// How a poll follows further pages of results
+#[derive(Clone, Debug)]
+pub enum Pagination {
+    None,
+    /// follows the `rel="next"` URL of the Link header
+    LinkHeader,
+    /// sends the token at `pointer` in the response as the `param` query parameter
+    NextToken {
+        pointer: String,
+        param: String,
+    },
+    /// requests pages of `page_size` records with offset and limit query parameters, until a
+    /// page has fewer than `page_size` records
+    Offset {
+        offset_param: String,
+        limit_param: String,
+        page_size: u64,
+        records_pointer: String,
+    },
+}
+
+impl Pagination {
+    fn needs_json(&self) -> bool {
+        matches!(
+            self,
+            Pagination::NextToken { .. } | Pagination::Offset { .. }
+        )
+    }
+}
 
 pub struct PollingHttpSourceFunc {
     pub state: PollingHttpSourceState,
     pub client: reqwest::Client,
-    pub endpoint: url::Url,
+    /// the endpoint, which may contain a `{cursor}` placeholder
+    pub endpoint: String,
     pub method: reqwest::Method,
-    pub body: Option<Bytes>,
+    pub body: Option<String>,
     pub polling_interval: Duration,
     pub emit_behavior: EmitBehavior,
+    pub pagination: Pagination,
+    pub max_pages: u64,
+    pub cursor_pointer: Option<String>,
+    pub cursor: Option<String>,
+    pub max_retries: u32,
     pub format: Format,
     pub framing: Option<Framing>,
     pub bad_data: Option<BadData>,
@@ -43,6 +87,94 @@ pub struct PollingHttpSourceState {
     last_message: Option<Vec<u8>>,
 }
 
+// the cursor is stored in its own table, so that existing state remains readable
+#[derive(Clone, Debug, Encode, Decode, PartialEq, PartialOrd, Default)]
+pub struct PollingHttpCursorState {
+    cursor: Option<String>,
+}
+
+struct RequestError {
+    error: anyhow::Error,
+    retryable: bool,
+    retry_after: Option<Duration>,
+}
+
+/// The outcome of a request, once any retries are done
+enum Response {
+    Ok(HeaderMap, Vec<u8>),
+    Failed(anyhow::Error),
+    /// the source was stopped while waiting to retry
+    Finish(SourceFinishType),
+}
+
+impl<E: Into<anyhow::Error>> From<E> for RequestError {
+    fn from(error: E) -> Self {
+        // failures to connect or read the response are usually transient
+        Self {
+            error: error.into(),
+            retryable: true,
+            retry_after: None,
+        }
+    }
+}
+
+/// Parses a Retry-After header, which is either a number of seconds or an HTTP date
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    (date.with_timezone(&chrono::Utc) - chrono::Utc::now())
+        .to_std()
+        .ok()
+}
+
+/// Returns the `rel="next"` URL from a Link header, if there is one
+fn next_link(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get_all(LINK)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .find_map(|link| {
+            let mut parts = link.split(';');
+            let url = parts.next()?.trim().strip_prefix('<')?.strip_suffix('>')?;
+            parts
+                .any(|p| {
+                    p.trim()
+                        .strip_prefix("rel=")
+                        .is_some_and(|rel| rel.trim_matches('"').split(' ').any(|r| r == "next"))
+                })
+                .then(|| url.to_string())
+        })
+}
+
+fn with_query_param(url: &url::Url, name: &str, value: &str) -> url::Url {
+    let mut url = url.clone();
+    let pairs: Vec<(String, String)> = url
+        .query_pairs()
+        .filter(|(k, _)| k != name)
+        .map(|(k, v)| (k.into_owned(), v.into_owned()))
+        .collect();
+    url.query_pairs_mut()
+        .clear()
+        .extend_pairs(pairs)
+        .append_pair(name, value);
+    url
+}
+
+/// Renders a JSON value as it's substituted into requests
+fn json_to_string(value: &serde_json::Value) -> Option<String> {
+    match value {
+        serde_json::Value::Null => None,
+        serde_json::Value::String(s) if s.is_empty() => None,
+        serde_json::Value::String(s) => Some(s.clone()),
+        v => Some(v.to_string()),
+    }
+}
+
 #[async_trait]
 impl SourceOperator for PollingHttpSourceFunc {
     fn name(&self) -> String {
@@ -50,7 +182,12 @@ impl SourceOperator for PollingHttpSourceFunc {
     }
 
     fn tables(&self) -> HashMap<String, TableConfig> {
-        arroyo_state::global_table_config("s", "polling http source state")
+        let mut tables = arroyo_state::global_table_config("s", "polling http source state");
+        tables.extend(arroyo_state::global_table_config(
+            "c",
+            "polling http source cursor",
+        ));
+        tables
     }
 
     async fn on_start(&mut self, ctx: &mut SourceContext) -> DataflowResult<()> {
@@ -60,6 +197,16 @@ impl SourceOperator for PollingHttpSourceFunc {
         if let Some(state) = s.get(&()) {
             self.state = state.clone();
         }
+
+        let c: &mut GlobalKeyedView<(), PollingHttpCursorState> =
+            ctx.table_manager.get_global_keyed_state("c").await?;
+
+        if let Some(PollingHttpCursorState {
+            cursor: Some(cursor),
+        }) = c.get(&())
+        {
+            self.cursor = Some(cursor.clone());
+        }
         Ok(())
     }
 
@@ -89,6 +236,12 @@ impl PollingHttpSourceFunc {
                 let s = ctx.table_manager.get_global_keyed_state("s").await?;
                 s.insert((), state).await;
 
+                let c_state = PollingHttpCursorState {
+                    cursor: self.cursor.clone(),
+                };
+                let cursor = ctx.table_manager.get_global_keyed_state("c").await?;
+                cursor.insert((), c_state).await;
+
                 if self.start_checkpoint(c, ctx, collector).await {
                     return Ok(Some(SourceFinishType::Immediate));
                 }
@@ -116,23 +269,34 @@ impl PollingHttpSourceFunc {
         Ok(None)
     }
 
-    async fn request(&mut self) -> anyhow::Result<Vec<u8>> {
-        let mut request = self
-            .client
-            .request(self.method.clone(), self.endpoint.clone());
+    fn render(&self, template: &str, url_encode: bool) -> String {
+        render(template, self.cursor.as_deref(), url_encode)
+    }
+
+    async fn request(
+        &self,
+        url: &url::Url,
+        body: Option<&Bytes>,
+    ) -> Result<(HeaderMap, Vec<u8>), RequestError> {
+        let mut request = self.client.request(self.method.clone(), url.clone());
 
-        if let Some(body) = self.body.clone() {
-            request = request.body(body);
+        if let Some(body) = body {
+            request = request.body(body.clone());
         }
 
         let resp = self.client.execute(request.build()?).await?;
 
         if resp.status().is_success() {
+            let headers = resp.headers().clone();
             let content_len = resp.content_length().unwrap_or(0);
             if content_len > MAX_BODY_SIZE as u64 {
-                bail!(
-                    "content length sent by server exceeds maximum limit ({content_len} > {MAX_BODY_SIZE})"
-                );
+                return Err(RequestError {
+                    error: anyhow!(
+                        "content length sent by server exceeds maximum limit ({content_len} > {MAX_BODY_SIZE})"
+                    ),
+                    retryable: false,
+                    retry_after: None,
+                });
             }
 
             let mut buf = Vec::with_capacity(content_len as usize);
@@ -142,13 +306,18 @@ impl PollingHttpSourceFunc {
                 buf.extend_from_slice(&chunk?);
 
                 if buf.len() > MAX_BODY_SIZE {
-                    bail!("response body exceeds max length {MAX_BODY_SIZE}");
+                    return Err(RequestError {
+                        error: anyhow!("response body exceeds max length {MAX_BODY_SIZE}"),
+                        retryable: false,
+                        retry_after: None,
+                    });
                 }
             }
 
-            Ok(buf)
+            Ok((headers, buf))
         } else {
             let status = resp.status();
+            let retry_after = retry_after(resp.headers());
             let bytes = resp.bytes().await;
             let error_body = bytes
                 .as_ref()
@@ -157,15 +326,204 @@ impl PollingHttpSourceFunc {
 
             warn!(
                 "HTTP request to {} failed with {}: {}",
-                self.endpoint,
+                url,
                 status.as_u16(),
                 error_body
             );
 
-            bail!("http server responded with {}", status.as_u16());
+            Err(RequestError {
+                error: anyhow!("http server responded with {}", status.as_u16()),
+                retryable: status == StatusCode::TOO_MANY_REQUESTS
+                    || status == StatusCode::REQUEST_TIMEOUT
+                    || status.is_server_error(),
+                retry_after,
+            })
+        }
+    }
+
+    async fn request_with_retries(
+        &mut self,
+        url: &url::Url,
+        body: Option<&Bytes>,
+        ctx: &mut SourceContext,
+        collector: &mut SourceCollector,
+    ) -> DataflowResult<Response> {
+        let mut backoff = INITIAL_BACKOFF;
+        let mut attempts = 0;
+        loop {
+            match self.request(url, body).await {
+                Ok((headers, buf)) => return Ok(Response::Ok(headers, buf)),
+                Err(e) if e.retryable && attempts < self.max_retries => {
+                    let wait = e
+                        .retry_after
+                        .map(|d| d.min(MAX_RETRY_AFTER))
+                        .unwrap_or(backoff);
+                    debug!(
+                        "retrying HTTP request to {} in {:?} after error: {}",
+                        url, wait, e.error
+                    );
+                    if let Some(finish) = self.wait_to_retry(wait, ctx, collector).await? {
+                        return Ok(Response::Finish(finish));
+                    }
+                    backoff = (backoff * 2).min(MAX_BACKOFF);
+                    attempts += 1;
+                }
+                Err(e) => return Ok(Response::Failed(e.error)),
+            }
+        }
+    }
+
+    /// Waits for `wait` before retrying a request, handling control messages in the meantime so
+    /// that a long backoff doesn't hold up checkpoints or stops
+    async fn wait_to_retry(
+        &mut self,
+        wait: Duration,
+        ctx: &mut SourceContext,
+        collector: &mut SourceCollector,
+    ) -> DataflowResult<Option<SourceFinishType>> {
+        let sleep = tokio::time::sleep(wait);
+        tokio::pin!(sleep);
+
+        loop {
+            select! {
+                _ = &mut sleep => return Ok(None),
+                Some(control_message) = ctx.control_rx.recv() => {
+                    if let Some(r) = self
+                        .our_handle_control_message(ctx, collector, Some(control_message))
+                        .await?
+                    {
+                        return Ok(Some(r));
+                    }
+                }
+            }
         }
     }
 
+    /// Requests the pages of one poll, deserializing each as it arrives. If a request fails,
+    /// the error is reported and the poll ends; data from earlier pages has already been
+    /// emitted. Returns whether the source was stopped while waiting to retry a request.
+    async fn poll(
+        &mut self,
+        ctx: &mut SourceContext,
+        collector: &mut SourceCollector,
+    ) -> DataflowResult<Option<SourceFinishType>> {
+        let mut url = match url::Url::parse(&self.render(&self.endpoint, true)) {
+            Ok(url) => url,
+            Err(e) => {
+                ctx.report_nonfatal_error(connector_err!(
+                    User,
+                    WithBackoff,
+                    "invalid endpoint: {}",
+                    e
+                ))
+                .await;
+                return Ok(None);
+            }
+        };
+        let body: Option<Bytes> = self.body.as_ref().map(|b| self.render(b, false).into());
+
+        let mut offset = 0;
+        if let Pagination::Offset {
+            offset_param,
+            limit_param,
+            page_size,
+            ..
+        } = &self.pagination
+        {
+            url = with_query_param(&url, offset_param, "0");
+            url = with_query_param(&url, limit_param, &page_size.to_string());
+        }
+
+        let base_url = url.clone();
+
+        for page in 0..self.max_pages {
+            let (headers, buf) = match self
+                .request_with_retries(&url, body.as_ref(), ctx, collector)
+                .await?
+            {
+                Response::Ok(headers, buf) => (headers, buf),
+                Response::Failed(e) => {
+                    ctx.report_nonfatal_error(connector_err!(
+                        User,
+                        WithBackoff,
+                        "HTTP request failed: {}",
+                        e
+                    ))
+                    .await;
+                    return Ok(None);
+                }
+                Response::Finish(finish) => return Ok(Some(finish)),
+            };
+
+            if page == 0 {
+                if self.emit_behavior == EmitBehavior::Changed
+                    && Some(&buf) == self.state.last_message.as_ref()
+                {
+                    return Ok(None);
+                }
+                self.state.last_message = Some(buf.clone());
+            }
+
+            collector
+                .deserialize_slice(&buf, SystemTime::now(), None)
+                .await?;
+
+            if collector.should_flush() {
+                collector.flush_buffer().await?;
+            }
+
+            let json: Option<serde_json::Value> =
+                if self.pagination.needs_json() || self.cursor_pointer.is_some() {
+                    serde_json::from_slice(&buf).ok()
+                } else {
+                    None
+                };
+
+            if let (Some(pointer), Some(json)) = (&self.cursor_pointer, &json)
+                && let Some(cursor) = json.pointer(pointer).and_then(json_to_string)
+            {
+                self.cursor = Some(cursor);
+            }
+
+            let next = match &self.pagination {
+                Pagination::None => None,
+                Pagination::LinkHeader => next_link(&headers).and_then(|link| url.join(&link).ok()),
+                Pagination::NextToken { pointer, param } => json
+                    .as_ref()
+                    .and_then(|json| json.pointer(pointer))
+                    .and_then(json_to_string)
+                    .map(|token| with_query_param(&base_url, param, &token)),
+                Pagination::Offset {
+                    offset_param,
+                    page_size,
+                    records_pointer,
+                    ..
+                } => {
+                    let count = json
+                        .as_ref()
+                        .and_then(|json| json.pointer(records_pointer))
+                        .and_then(|records| records.as_array())
+                        .map(|records| records.len() as u64)
+                        .unwrap_or(0);
+                    offset += count;
+                    (count >= *page_size)
+                        .then(|| with_query_param(&base_url, offset_param, &offset.to_string()))
+                }
+            };
+
+            match next {
+                Some(next) => url = next,
+                None => return Ok(None),
+            }
+        }
+
+        warn!(
+            "stopped following pages from {} after {} pages",
+            self.endpoint, self.max_pages
+        );
+        Ok(None)
+    }
+
     async fn run_int(
         &mut self,
         ctx: &mut SourceContext,
@@ -186,23 +544,8 @@ impl PollingHttpSourceFunc {
             loop {
                 select! {
                     _ = timer.tick()  => {
-                        match self.request().await {
-                            Ok(buf) => {
-                                if self.emit_behavior == EmitBehavior::Changed && Some(&buf) == self.state.last_message.as_ref() {
-                                    continue;
-                                }
-
-                                collector.deserialize_slice(&buf, SystemTime::now(), None).await?;
-
-                                if collector.should_flush() {
-                                    collector.flush_buffer().await?;
-                                }
-
-                                self.state.last_message = Some(buf);
-                            }
-                            Err(e) => {
-                                ctx.report_nonfatal_error(connector_err!(User, WithBackoff, "HTTP request failed: {}", e)).await;
-                            }
+                        if let Some(r) = self.poll(ctx, collector).await? {
+                            return Ok(r);
                         }
                     }
                     control_message = ctx.control_rx.recv() => {
@@ -226,3 +569,127 @@ impl PollingHttpSourceFunc {
         }
     }
 }
+
+/// Substitutes the cursor into a template, URL-encoding it if the template is a URL, and
+/// otherwise escaping it for use inside a JSON string in a request body
+pub fn render(template: &str, cursor: Option<&str>, url_encode: bool) -> String {
+    let cursor = cursor.unwrap_or("");
+    if url_encode {
+        let encoded: String = url::form_urlencoded::byte_serialize(cursor.as_bytes()).collect();
+        template.replace(CURSOR_PLACEHOLDER, &encoded)
+    } else {
+        let quoted = serde_json::to_string(cursor).unwrap();
+        template.replace(CURSOR_PLACEHOLDER, &quoted[1..quoted.len() - 1])
+    }
+}
+
+/// Checks that the endpoint is a valid URL once the cursor has been substituted into it
+pub fn validate_endpoint(endpoint: &str) -> anyhow::Result<()> {
+    if let Err(e) = url::Url::parse(&render(endpoint, None, true)) {
+        bail!("invalid endpoint '{}': {}", endpoint, e);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    fn headers(name: reqwest::header::HeaderName, values: &[&str]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for v in values {
+            headers.append(name.clone(), HeaderValue::from_str(v).unwrap());
+        }
+        headers
+    }
+
+    #[test]
+    fn test_next_link() {
+        let h = headers(
+            LINK,
+            &[
+                r#"<https://api.example.com/items?page=1>; rel="prev", <https://api.example.com/items?page=3>; rel="next""#,
+            ],
+        );
+        assert_eq!(
+            next_link(&h).as_deref(),
+            Some("https://api.example.com/items?page=3")
+        );
+
+        // rel may hold several space-separated types, and links may span several headers
+        let h = headers(
+            LINK,
+            &[
+                "<https://api.example.com/first>; rel=first",
+                r#"<https://api.example.com/next>; title="more"; rel="next last""#,
+            ],
+        );
+        assert_eq!(
+            next_link(&h).as_deref(),
+            Some("https://api.example.com/next")
+        );
+
+        let h = headers(LINK, &[r#"<https://api.example.com/prev>; rel="prev""#]);
+        assert_eq!(next_link(&h), None);
+        assert_eq!(next_link(&HeaderMap::new()), None);
+    }
+
+    #[test]
+    fn test_retry_after() {
+        let h = headers(RETRY_AFTER, &["120"]);
+        assert_eq!(retry_after(&h), Some(Duration::from_secs(120)));
+
+        let date = (chrono::Utc::now() + chrono::Duration::seconds(30)).to_rfc2822();
+        let wait = retry_after(&headers(RETRY_AFTER, &[&date])).unwrap();
+        assert!(wait > Duration::from_secs(25) && wait <= Duration::from_secs(30));
+
+        // dates in the past and unparseable values are ignored
+        let date = (chrono::Utc::now() - chrono::Duration::seconds(30)).to_rfc2822();
+        assert_eq!(retry_after(&headers(RETRY_AFTER, &[&date])), None);
+        assert_eq!(retry_after(&headers(RETRY_AFTER, &["soon"])), None);
+        assert_eq!(retry_after(&HeaderMap::new()), None);
+    }
+
+    #[test]
+    fn test_with_query_param() {
+        let url = url::Url::parse("https://api.example.com/items?limit=10&cursor=abc").unwrap();
+
+        let url = with_query_param(&url, "cursor", "d&e=f");
+        let pairs: Vec<(String, String)> = url.query_pairs().into_owned().collect();
+        assert_eq!(
+            pairs,
+            vec![
+                ("limit".to_string(), "10".to_string()),
+                ("cursor".to_string(), "d&e=f".to_string()),
+            ]
+        );
+
+        let url = url::Url::parse("https://api.example.com/items").unwrap();
+        assert_eq!(
+            with_query_param(&url, "page", "2").as_str(),
+            "https://api.example.com/items?page=2"
+        );
+    }
+
+    #[test]
+    fn test_render() {
+        assert_eq!(
+            render(
+                "https://example.com/items?after={cursor}",
+                Some("a b&c"),
+                true
+            ),
+            "https://example.com/items?after=a+b%26c"
+        );
+        assert_eq!(
+            render("https://example.com/items?after={cursor}", None, true),
+            "https://example.com/items?after="
+        );
+
+        let body = render(r#"{"after": "{cursor}"}"#, Some(r#"say "hi" \ bye"#), false);
+        assert_eq!(body, r#"{"after": "say \"hi\" \\ bye"}"#);
+        let parsed: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(parsed["after"], r#"say "hi" \ bye"#);
+    }
+}
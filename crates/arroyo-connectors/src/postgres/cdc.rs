@@ -0,0 +1,1148 @@
+use crate::postgres::{
+    PostgresConfig, PostgresTable, connect, connect_replication, quote_identifier, quote_literal,
+    quote_table_name,
+};
+use anyhow::{anyhow, bail};
+use arrow::array::{ArrayRef, RecordBatch, StringArray, StructArray, TimestampNanosecondArray};
+use arrow::buffer::NullBuffer;
+use arrow::compute::cast;
+use arrow::datatypes::{DataType, Fields, Schema};
+use arroyo_operator::SourceFinishType;
+use arroyo_operator::context::{SourceCollector, SourceContext};
+use arroyo_operator::operator::SourceOperator;
+use arroyo_rpc::errors::{DataflowError, DataflowResult};
+use arroyo_rpc::grpc::rpc::{
+    GlobalKeyedTableConfig, StopMode, TableConfig, TableEnum, TaskCheckpointEventType,
+};
+use arroyo_rpc::{CheckpointEvent, ControlMessage, ControlResp, TIMESTAMP_FIELD, connector_err};
+use arroyo_types::{SignalMessage, Watermark, single_item_hash_map, to_nanos};
+use async_trait::async_trait;
+use bincode::{Decode, Encode};
+use futures::{StreamExt, pin_mut};
+use prost::Message as _;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::select;
+use tokio::time::MissedTickBehavior;
+use tokio_postgres::types::ToSql;
+use tokio_postgres::{Client, SimpleQueryMessage};
+use tracing::{debug, info};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+// the most changes read from the slot by a single poll; Postgres finishes the transaction it's in
+// when this is reached, so polls may read slightly more
+const MAX_CHANGES_PER_POLL: i32 = 65_536;
+const BATCH_SIZE: usize = 8192;
+const SNAPSHOT_FETCH_SIZE: usize = 10_000;
+// seconds between the unix epoch and the Postgres epoch (2000-01-01)
+const POSTGRES_EPOCH_OFFSET: u64 = 946_684_800;
+
+/// The initial snapshot of the table, which is read through a cursor
+struct Snapshot {
+    relation: Relation,
+    rows: usize,
+}
+
+/// What a poll of the slot found
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PollResult {
+    /// read all of the changes in the slot
+    CaughtUp,
+    /// stopped at the limit after emitting new changes; there may be more waiting
+    More,
+    /// stopped at the limit without reaching any new changes, so there's nothing more to read
+    /// until the slot is advanced
+    Blocked,
+}
+
+impl PollResult {
+    fn new(changes: i32, emitted: bool) -> Self {
+        if changes < MAX_CHANGES_PER_POLL {
+            PollResult::CaughtUp
+        } else if emitted {
+            PollResult::More
+        } else {
+            PollResult::Blocked
+        }
+    }
+}
+
+#[derive(Clone, Debug, Encode, Decode, PartialEq, Default)]
+pub struct PostgresCdcState {
+    /// the commit LSN of the last transaction that was emitted
+    commit_lsn: u64,
+    /// the end LSN of the last transaction that was emitted, which the slot can be advanced to
+    /// once a checkpoint including it has completed
+    end_lsn: u64,
+}
+
+/// Reads changes to a Postgres table with logical replication, using the `pgoutput` plugin.
+///
+/// When the pipeline first starts, a replication slot is created with an exported snapshot, which
+/// the existing rows of the table are read from. Changes are then read from the slot with
+/// `pg_logical_slot_peek_binary_changes`, and emitted as Debezium-style `before`/`after`/`op`
+/// rows, which the planner unrolls into an updating stream. As updates and deletes need the full
+/// old row to be retracted, the table must have `REPLICA IDENTITY FULL`.
+///
+/// Peeking doesn't consume changes, so the slot is only advanced past a transaction once a
+/// checkpoint including it has completed. The source's state is committed in two phases, so that
+/// it's sent a commit once every operator has finished the checkpoint, and the slot is advanced
+/// then (or immediately on restore). Until then, each poll re-reads (and skips) the changes it
+/// has already emitted. To bound that, each poll reads at most [`MAX_CHANGES_PER_POLL`] changes,
+/// polling again straight away if there may be more; once a poll only finds changes that were
+/// already emitted, polling pauses until the slot is advanced. That caps how far ahead of the
+/// slot the source can read: with a backlog, it reads about [`MAX_CHANGES_PER_POLL`] changes per
+/// checkpoint interval, so shorter intervals catch up faster.
+///
+/// The initial snapshot is read a chunk at a time, handling control messages in between, so the
+/// source can be stopped while it's being read. Checkpoints wait for it to finish, as it can't be
+/// resumed after a restart. Only the first subtask reads from the slot.
+pub struct PostgresCdcSourceFunc {
+    config: PostgresConfig,
+    table_name: String,
+    slot_name: String,
+    publication: String,
+    snapshot: bool,
+    state: PostgresCdcState,
+    /// the epoch and end LSN of the last checkpoint, which the slot is advanced to once it
+    /// commits
+    checkpointed: Option<(u32, u64)>,
+}
+
+fn db_err(context: &str, e: impl std::fmt::Display) -> DataflowError {
+    connector_err!(External, WithBackoff, "{}: {}", context, e)
+}
+
+fn format_lsn(lsn: u64) -> String {
+    format!("{:X}/{:X}", lsn >> 32, lsn & 0xFFFF_FFFF)
+}
+
+/// Postgres prints timestamp offsets without minutes (like `+00`), which Arrow can't parse
+fn normalize_timestamp(value: &str) -> String {
+    match value.rfind(['+', '-']) {
+        Some(i) if value.len() - i == 3 && value[..i].contains(':') => format!("{value}:00"),
+        _ => value.to_string(),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum TupleValue {
+    Null,
+    /// an unchanged TOASTed value, which isn't sent
+    Unchanged,
+    Text(String),
+}
+
+#[derive(Debug, Clone)]
+struct Relation {
+    columns: Vec<String>,
+}
+
+/// The messages of the pgoutput protocol (version 1) that we handle
+#[derive(Debug)]
+enum Message {
+    Begin {
+        final_lsn: u64,
+        timestamp: i64,
+    },
+    Commit {
+        commit_lsn: u64,
+        end_lsn: u64,
+    },
+    Relation {
+        id: u32,
+        relation: Relation,
+    },
+    Insert {
+        relation: u32,
+        new: Vec<TupleValue>,
+    },
+    Update {
+        relation: u32,
+        old: Option<Vec<TupleValue>>,
+        new: Vec<TupleValue>,
+    },
+    Delete {
+        relation: u32,
+        old: Vec<TupleValue>,
+    },
+    Truncate,
+    Other,
+}
+
+struct Reader<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> anyhow::Result<&'a [u8]> {
+        if self.buf.len() < n {
+            bail!("unexpected end of pgoutput message");
+        }
+        let (head, tail) = self.buf.split_at(n);
+        self.buf = tail;
+        Ok(head)
+    }
+
+    fn u8(&mut self) -> anyhow::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn i16(&mut self) -> anyhow::Result<i16> {
+        Ok(i16::from_be_bytes(self.take(2)?.try_into()?))
+    }
+
+    fn i32(&mut self) -> anyhow::Result<i32> {
+        Ok(i32::from_be_bytes(self.take(4)?.try_into()?))
+    }
+
+    fn u32(&mut self) -> anyhow::Result<u32> {
+        Ok(u32::from_be_bytes(self.take(4)?.try_into()?))
+    }
+
+    fn u64(&mut self) -> anyhow::Result<u64> {
+        Ok(u64::from_be_bytes(self.take(8)?.try_into()?))
+    }
+
+    fn i64(&mut self) -> anyhow::Result<i64> {
+        Ok(i64::from_be_bytes(self.take(8)?.try_into()?))
+    }
+
+    fn string(&mut self) -> anyhow::Result<String> {
+        let end = self
+            .buf
+            .iter()
+            .position(|b| *b == 0)
+            .ok_or_else(|| anyhow!("unterminated string in pgoutput message"))?;
+        let s = String::from_utf8(self.take(end)?.to_vec())?;
+        self.take(1)?;
+        Ok(s)
+    }
+
+    fn tuple(&mut self) -> anyhow::Result<Vec<TupleValue>> {
+        let n = self.i16()?;
+        (0..n)
+            .map(|_| match self.u8()? {
+                b'n' => Ok(TupleValue::Null),
+                b'u' => Ok(TupleValue::Unchanged),
+                b't' => {
+                    let len = self.i32()? as usize;
+                    Ok(TupleValue::Text(String::from_utf8(
+                        self.take(len)?.to_vec(),
+                    )?))
+                }
+                b => bail!("unsupported tuple value kind '{}'", b as char),
+            })
+            .collect()
+    }
+}
+
+fn parse_message(data: &[u8]) -> anyhow::Result<Message> {
+    let mut r = Reader { buf: data };
+    Ok(match r.u8()? {
+        b'B' => {
+            let final_lsn = r.u64()?;
+            let timestamp = r.i64()?;
+            Message::Begin {
+                final_lsn,
+                timestamp,
+            }
+        }
+        b'C' => {
+            let _flags = r.u8()?;
+            let commit_lsn = r.u64()?;
+            let end_lsn = r.u64()?;
+            Message::Commit {
+                commit_lsn,
+                end_lsn,
+            }
+        }
+        b'R' => {
+            let id = r.u32()?;
+            let _namespace = r.string()?;
+            let _name = r.string()?;
+            let _replica_identity = r.u8()?;
+            let n = r.i16()?;
+            let columns = (0..n)
+                .map(|_| {
+                    let _flags = r.u8()?;
+                    let name = r.string()?;
+                    let _type_oid = r.u32()?;
+                    let _type_modifier = r.i32()?;
+                    Ok(name)
+                })
+                .collect::<anyhow::Result<_>>()?;
+            Message::Relation {
+                id,
+                relation: Relation { columns },
+            }
+        }
+        b'I' => {
+            let relation = r.u32()?;
+            if r.u8()? != b'N' {
+                bail!("expected new tuple in insert message");
+            }
+            Message::Insert {
+                relation,
+                new: r.tuple()?,
+            }
+        }
+        b'U' => {
+            let relation = r.u32()?;
+            let (old, new) = match r.u8()? {
+                b'K' | b'O' => {
+                    let old = r.tuple()?;
+                    if r.u8()? != b'N' {
+                        bail!("expected new tuple in update message");
+                    }
+                    (Some(old), r.tuple()?)
+                }
+                b'N' => (None, r.tuple()?),
+                b => bail!("unexpected tuple type '{}' in update message", b as char),
+            };
+            Message::Update { relation, old, new }
+        }
+        b'D' => {
+            let relation = r.u32()?;
+            match r.u8()? {
+                b'K' | b'O' => {}
+                b => bail!("unexpected tuple type '{}' in delete message", b as char),
+            }
+            Message::Delete {
+                relation,
+                old: r.tuple()?,
+            }
+        }
+        b'T' => Message::Truncate,
+        _ => Message::Other,
+    })
+}
+
+type Row = Vec<Option<String>>;
+
+/// Accumulates changes into Debezium-style batches of `before`, `after` and `op`
+struct ChangeBuilder {
+    schema: Arc<Schema>,
+    fields: Fields,
+    before: Vec<Option<Row>>,
+    after: Vec<Option<Row>>,
+    ops: Vec<&'static str>,
+    timestamps: Vec<i64>,
+}
+
+impl ChangeBuilder {
+    fn new(schema: Arc<Schema>) -> anyhow::Result<Self> {
+        let fields = match schema.field_with_name("after")?.data_type() {
+            DataType::Struct(fields) => fields.clone(),
+            t => bail!("expected 'after' to be a struct, not {}", t),
+        };
+
+        Ok(Self {
+            schema,
+            fields,
+            before: vec![],
+            after: vec![],
+            ops: vec![],
+            timestamps: vec![],
+        })
+    }
+
+    fn len(&self) -> usize {
+        self.ops.len()
+    }
+
+    /// Maps a tuple onto the fields of the table, by the column names of its relation
+    fn row(&self, relation: &Relation, tuple: &[TupleValue]) -> Row {
+        self.fields
+            .iter()
+            .map(|f| {
+                let i = relation.columns.iter().position(|c| c == f.name())?;
+                match tuple.get(i)? {
+                    TupleValue::Text(s) => Some(s.clone()),
+                    TupleValue::Null | TupleValue::Unchanged => None,
+                }
+            })
+            .collect()
+    }
+
+    fn push(&mut self, op: &'static str, before: Option<Row>, after: Option<Row>, ts: i64) {
+        self.ops.push(op);
+        self.before.push(before);
+        self.after.push(after);
+        self.timestamps.push(ts);
+    }
+
+    fn struct_array(&self, rows: &[Option<Row>]) -> anyhow::Result<ArrayRef> {
+        let columns = self
+            .fields
+            .iter()
+            .enumerate()
+            .map(|(i, f)| {
+                let timestamp = matches!(f.data_type(), DataType::Timestamp(..));
+                let strings = StringArray::from_iter(rows.iter().map(|r| {
+                    let v = r.as_ref()?.get(i)?.as_ref()?;
+                    Some(if timestamp {
+                        normalize_timestamp(v)
+                    } else {
+                        v.clone()
+                    })
+                }));
+                cast(&strings, f.data_type()).map_err(|e| {
+                    anyhow!(
+                        "cannot convert Postgres column '{}' to {}: {}",
+                        f.name(),
+                        f.data_type(),
+                        e
+                    )
+                })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let nulls = NullBuffer::from_iter(rows.iter().map(|r| r.is_some()));
+        Ok(Arc::new(StructArray::try_new(
+            self.fields.clone(),
+            columns,
+            Some(nulls),
+        )?))
+    }
+
+    fn finish(&mut self) -> anyhow::Result<Option<RecordBatch>> {
+        if self.ops.is_empty() {
+            return Ok(None);
+        }
+
+        let before = self.struct_array(&self.before)?;
+        let after = self.struct_array(&self.after)?;
+        let op = Arc::new(StringArray::from(std::mem::take(&mut self.ops))) as ArrayRef;
+        let timestamps = Arc::new(TimestampNanosecondArray::from(std::mem::take(
+            &mut self.timestamps,
+        ))) as ArrayRef;
+        self.before.clear();
+        self.after.clear();
+
+        let columns = self
+            .schema
+            .fields()
+            .iter()
+            .map(|f| match f.name().as_str() {
+                "before" => Ok(before.clone()),
+                "after" => Ok(after.clone()),
+                "op" => Ok(op.clone()),
+                TIMESTAMP_FIELD => Ok(timestamps.clone()),
+                name => Err(anyhow!(
+                    "unexpected field '{}' in Postgres CDC schema",
+                    name
+                )),
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        Ok(Some(RecordBatch::try_new(self.schema.clone(), columns)?))
+    }
+}
+
+impl PostgresCdcSourceFunc {
+    pub(crate) fn new(config: PostgresConfig, table: PostgresTable) -> anyhow::Result<Self> {
+        let slot_name = table
+            .slot_name
+            .ok_or_else(|| anyhow!("slot_name is required for Postgres CDC sources"))?;
+
+        Ok(Self {
+            config,
+            table_name: table.table_name,
+            publication: table.publication.unwrap_or_else(|| slot_name.clone()),
+            slot_name,
+            snapshot: table.snapshot.unwrap_or(true),
+            state: PostgresCdcState::default(),
+            checkpointed: None,
+        })
+    }
+
+    async fn check_replica_identity(&self, client: &Client) -> DataflowResult<()> {
+        let row = client
+            .query_one(
+                "SELECT relreplident::text FROM pg_class WHERE oid = $1::text::regclass",
+                &[&self.table_name],
+            )
+            .await
+            .map_err(|e| {
+                connector_err!(
+                    User,
+                    NoRetry,
+                    "failed to find Postgres table {}: {}",
+                    self.table_name,
+                    e
+                )
+            })?;
+
+        let identity: String = row.get(0);
+        if identity != "f" {
+            return Err(connector_err!(
+                User,
+                NoRetry,
+                "Postgres table {} must have REPLICA IDENTITY FULL to be read with CDC, so that \
+                updates and deletes include the old row; run `ALTER TABLE {} REPLICA IDENTITY FULL`",
+                self.table_name,
+                quote_table_name(&self.table_name)
+            ));
+        }
+
+        Ok(())
+    }
+
+    async fn create_publication(&self, client: &Client) -> DataflowResult<()> {
+        let exists = client
+            .query_opt(
+                "SELECT 1 FROM pg_publication WHERE pubname = $1",
+                &[&self.publication],
+            )
+            .await
+            .map_err(|e| db_err("failed to look up Postgres publication", e))?
+            .is_some();
+
+        if !exists {
+            info!(
+                "creating publication {} for table {}",
+                self.publication, self.table_name
+            );
+            client
+                .batch_execute(&format!(
+                    "CREATE PUBLICATION {} FOR TABLE {}",
+                    quote_identifier(&self.publication),
+                    quote_table_name(&self.table_name)
+                ))
+                .await
+                .map_err(|e| {
+                    connector_err!(
+                        User,
+                        NoRetry,
+                        "failed to create publication {}: {}",
+                        self.publication,
+                        e
+                    )
+                })?;
+        }
+
+        Ok(())
+    }
+
+    /// (Re)creates the replication slot, and if configured to, opens a cursor over the existing
+    /// rows of the table in the snapshot it exports
+    async fn create_slot(
+        &mut self,
+        client: &Client,
+        builder: &ChangeBuilder,
+    ) -> DataflowResult<Option<Snapshot>> {
+        client
+            .execute(
+                "SELECT pg_drop_replication_slot(slot_name) FROM pg_replication_slots \
+                WHERE slot_name = $1",
+                &[&self.slot_name],
+            )
+            .await
+            .map_err(|e| db_err("failed to drop existing replication slot", e))?;
+
+        // the exported snapshot is only valid while the replication connection is open and idle
+        let replication = connect_replication(&self.config).await.map_err(|e| {
+            connector_err!(External, WithBackoff, source: e, "failed to connect to Postgres for replication")
+        })?;
+
+        let messages = replication
+            .simple_query(&format!(
+                "CREATE_REPLICATION_SLOT {} LOGICAL pgoutput EXPORT_SNAPSHOT",
+                quote_identifier(&self.slot_name)
+            ))
+            .await
+            .map_err(|e| {
+                connector_err!(
+                    User,
+                    NoRetry,
+                    "failed to create replication slot {} (is wal_level set to logical?): {}",
+                    self.slot_name,
+                    e
+                )
+            })?;
+
+        let Some(SimpleQueryMessage::Row(row)) = messages
+            .iter()
+            .find(|m| matches!(m, SimpleQueryMessage::Row(_)))
+        else {
+            return Err(connector_err!(
+                External,
+                NoRetry,
+                "CREATE_REPLICATION_SLOT returned no rows"
+            ));
+        };
+
+        let snapshot_name = row.get("snapshot_name").unwrap_or_default().to_string();
+        info!(
+            "created replication slot {} at {}",
+            self.slot_name,
+            row.get("consistent_point").unwrap_or_default()
+        );
+
+        if !self.snapshot {
+            return Ok(None);
+        }
+
+        // once imported into our transaction, the snapshot outlives the replication connection
+        self.start_snapshot(client, &snapshot_name, builder)
+            .await
+            .map(Some)
+    }
+
+    async fn start_snapshot(
+        &self,
+        client: &Client,
+        snapshot_name: &str,
+        builder: &ChangeBuilder,
+    ) -> DataflowResult<Snapshot> {
+        info!("reading snapshot of Postgres table {}", self.table_name);
+
+        let columns: Vec<_> = builder
+            .fields
+            .iter()
+            .map(|f| quote_identifier(f.name()))
+            .collect();
+        let relation = Relation {
+            columns: builder.fields.iter().map(|f| f.name().clone()).collect(),
+        };
+
+        client
+            .batch_execute(&format!(
+                "BEGIN ISOLATION LEVEL REPEATABLE READ; \
+                SET TRANSACTION SNAPSHOT {}; \
+                DECLARE arroyo_snapshot NO SCROLL CURSOR FOR SELECT {} FROM {}",
+                quote_literal(snapshot_name),
+                columns.join(", "),
+                quote_table_name(&self.table_name)
+            ))
+            .await
+            .map_err(|e| {
+                connector_err!(
+                    User,
+                    NoRetry,
+                    "failed to read snapshot of Postgres table {}: {}",
+                    self.table_name,
+                    e
+                )
+            })?;
+
+        Ok(Snapshot { relation, rows: 0 })
+    }
+
+    /// Reads the next chunk of the snapshot, returning whether it has been fully read
+    async fn fetch_snapshot(
+        &self,
+        client: &Client,
+        snapshot: &mut Snapshot,
+        collector: &mut SourceCollector,
+        builder: &mut ChangeBuilder,
+    ) -> DataflowResult<bool> {
+        let messages = client
+            .simple_query(&format!("FETCH {SNAPSHOT_FETCH_SIZE} FROM arroyo_snapshot"))
+            .await
+            .map_err(|e| db_err("failed to read snapshot from Postgres", e))?;
+
+        let mut fetched = 0;
+        let timestamp = to_nanos(SystemTime::now()) as i64;
+        for message in messages {
+            if let SimpleQueryMessage::Row(row) = message {
+                let tuple: Vec<_> = (0..row.len())
+                    .map(|i| match row.get(i) {
+                        Some(v) => TupleValue::Text(v.to_string()),
+                        None => TupleValue::Null,
+                    })
+                    .collect();
+                let after = builder.row(&snapshot.relation, &tuple);
+                builder.push("r", None, Some(after), timestamp);
+                fetched += 1;
+            }
+        }
+
+        self.flush(builder, collector).await?;
+
+        if fetched > 0 {
+            snapshot.rows += fetched;
+            return Ok(false);
+        }
+
+        client
+            .batch_execute("COMMIT")
+            .await
+            .map_err(|e| db_err("failed to finish snapshot", e))?;
+
+        info!(
+            "read {} rows from snapshot of Postgres table {}",
+            snapshot.rows, self.table_name
+        );
+        Ok(true)
+    }
+
+    /// Advances the slot past changes that are included in a completed checkpoint
+    async fn advance_slot(&self, client: &Client, lsn: u64) -> DataflowResult<()> {
+        if lsn == 0 {
+            return Ok(());
+        }
+
+        debug!("advancing slot {} to {}", self.slot_name, format_lsn(lsn));
+        let lsn = format_lsn(lsn);
+        let params: [&(dyn ToSql + Sync); 2] = [&self.slot_name, &lsn];
+        client
+            .execute(
+                "SELECT pg_replication_slot_advance(slot_name, $2::text::pg_lsn) \
+                FROM pg_replication_slots \
+                WHERE slot_name = $1 AND confirmed_flush_lsn < $2::text::pg_lsn",
+                &params,
+            )
+            .await
+            .map_err(|e| db_err("failed to advance replication slot", e))?;
+        Ok(())
+    }
+
+    /// Advances the slot once the checkpoint for `epoch` has committed, and tells the controller
+    /// we're done
+    async fn commit(
+        &mut self,
+        epoch: u32,
+        client: Option<&Client>,
+        ctx: &mut SourceContext,
+    ) -> DataflowResult<()> {
+        if let Some(client) = client
+            && let Some((_, lsn)) = self.checkpointed.take_if(|(e, _)| *e <= epoch)
+        {
+            self.advance_slot(client, lsn).await?;
+        }
+
+        ctx.control_tx
+            .send(ControlResp::CheckpointEvent(CheckpointEvent {
+                checkpoint_epoch: epoch,
+                node_id: ctx.task_info.node_id,
+                operator_id: ctx.task_info.operator_id.clone(),
+                subtask_index: ctx.task_info.task_index,
+                time: SystemTime::now(),
+                event_type: TaskCheckpointEventType::FinishedCommit,
+            }))
+            .await
+            .expect("sent commit event");
+        Ok(())
+    }
+
+    async fn flush(
+        &self,
+        builder: &mut ChangeBuilder,
+        collector: &mut SourceCollector,
+    ) -> DataflowResult<()> {
+        if let Some(batch) = builder
+            .finish()
+            .map_err(|e| connector_err!(User, NoRetry, "{}", e))?
+        {
+            collector.collect(batch).await?;
+        }
+        Ok(())
+    }
+
+    /// Reads up to [`MAX_CHANGES_PER_POLL`] changes from the slot, emitting those from
+    /// transactions after the last one we emitted
+    async fn poll(
+        &mut self,
+        client: &Client,
+        ctx: &mut SourceContext,
+        collector: &mut SourceCollector,
+        builder: &mut ChangeBuilder,
+    ) -> DataflowResult<PollResult> {
+        let params: [&(dyn ToSql + Sync); 3] =
+            [&self.slot_name, &MAX_CHANGES_PER_POLL, &self.publication];
+        let rows = client
+            .query_raw(
+                "SELECT data FROM pg_logical_slot_peek_binary_changes($1, NULL, $2, \
+                'proto_version', '1', 'publication_names', $3)",
+                params,
+            )
+            .await
+            .map_err(|e| db_err("failed to read changes from replication slot", e))?;
+        pin_mut!(rows);
+
+        let mut relations: HashMap<u32, Relation> = HashMap::new();
+        let mut skipping = false;
+        let mut timestamp = 0;
+        let mut changes = 0;
+        let last_commit_lsn = self.state.commit_lsn;
+
+        while let Some(row) = rows.next().await {
+            let row = row.map_err(|e| db_err("failed to read changes from replication slot", e))?;
+            changes += 1;
+            let data: &[u8] = row.get(0);
+            let message = parse_message(data).map_err(|e| {
+                connector_err!(External, NoRetry, "invalid pgoutput message: {}", e)
+            })?;
+
+            let relation = |id: u32| {
+                relations.get(&id).ok_or_else(|| {
+                    connector_err!(External, NoRetry, "change for unknown relation {}", id)
+                })
+            };
+
+            match message {
+                Message::Begin {
+                    final_lsn,
+                    timestamp: ts,
+                } => {
+                    // this transaction was emitted by an earlier poll
+                    skipping = final_lsn <= self.state.commit_lsn;
+                    let micros = Duration::from_micros(ts.max(0) as u64);
+                    timestamp =
+                        to_nanos(UNIX_EPOCH + Duration::from_secs(POSTGRES_EPOCH_OFFSET) + micros)
+                            as i64;
+                }
+                Message::Commit {
+                    commit_lsn,
+                    end_lsn,
+                } => {
+                    if !skipping {
+                        self.state.commit_lsn = commit_lsn;
+                        self.state.end_lsn = end_lsn;
+                    }
+                }
+                Message::Relation { id, relation } => {
+                    relations.insert(id, relation);
+                }
+                _ if skipping => {}
+                Message::Insert { relation: id, new } => {
+                    let after = builder.row(relation(id)?, &new);
+                    builder.push("c", None, Some(after), timestamp);
+                }
+                Message::Update {
+                    relation: id,
+                    old,
+                    new,
+                } => {
+                    let relation = relation(id)?;
+                    let old = old.ok_or_else(|| {
+                        connector_err!(
+                            User,
+                            NoRetry,
+                            "update to Postgres table {} has no old row; the table must have REPLICA IDENTITY FULL",
+                            self.table_name
+                        )
+                    })?;
+
+                    // unchanged TOASTed values aren't sent in the new row, but are in the old one
+                    let new: Vec<_> = new
+                        .into_iter()
+                        .zip(old.iter())
+                        .map(|(n, o)| match n {
+                            TupleValue::Unchanged => o.clone(),
+                            n => n,
+                        })
+                        .collect();
+
+                    let before = builder.row(relation, &old);
+                    let after = builder.row(relation, &new);
+                    builder.push("u", Some(before), Some(after), timestamp);
+                }
+                Message::Delete { relation: id, old } => {
+                    let before = builder.row(relation(id)?, &old);
+                    builder.push("d", Some(before), None, timestamp);
+                }
+                Message::Truncate => {
+                    ctx.report_nonfatal_error(connector_err!(
+                        User,
+                        NoRetry,
+                        "Postgres table {} was truncated; TRUNCATE isn't supported by CDC sources, so its rows have not been removed",
+                        self.table_name
+                    ))
+                    .await;
+                }
+                Message::Other => {}
+            }
+
+            if builder.len() >= BATCH_SIZE {
+                self.flush(builder, collector).await?;
+            }
+        }
+
+        self.flush(builder, collector).await?;
+
+        Ok(PollResult::new(
+            changes,
+            self.state.commit_lsn > last_commit_lsn,
+        ))
+    }
+
+    async fn handle_control_message(
+        &mut self,
+        msg: Option<ControlMessage>,
+        client: Option<&Client>,
+        ctx: &mut SourceContext,
+        collector: &mut SourceCollector,
+    ) -> DataflowResult<Option<SourceFinishType>> {
+        let Some(msg) = msg else {
+            return Ok(None);
+        };
+
+        match msg {
+            ControlMessage::Checkpoint(c) => {
+                debug!("starting checkpointing {}", ctx.task_info.task_index);
+                if client.is_some() {
+                    self.checkpointed = Some((c.epoch, self.state.end_lsn));
+
+                    ctx.table_manager
+                        .get_global_keyed_state("s")
+                        .await?
+                        .insert((), self.state.clone())
+                        .await;
+                }
+
+                if self.start_checkpoint(c, ctx, collector).await {
+                    // the controller waits for every subtask to commit the final checkpoint
+                    while let Some(msg) = ctx.control_rx.recv().await {
+                        if let ControlMessage::Commit { epoch, .. } = msg {
+                            self.commit(epoch, client, ctx).await?;
+                            break;
+                        }
+                    }
+                    return Ok(Some(SourceFinishType::Immediate));
+                }
+            }
+            ControlMessage::Stop { mode } => {
+                info!("Stopping Postgres CDC source: {:?}", mode);
+
+                match mode {
+                    StopMode::Graceful => {
+                        return Ok(Some(SourceFinishType::Graceful));
+                    }
+                    StopMode::Immediate => {
+                        return Ok(Some(SourceFinishType::Immediate));
+                    }
+                }
+            }
+            ControlMessage::Commit { epoch, .. } => {
+                self.commit(epoch, client, ctx).await?;
+            }
+            ControlMessage::LoadCompacted { compacted } => {
+                ctx.load_compacted(compacted).await;
+            }
+            ControlMessage::NoOp => {}
+        }
+        Ok(None)
+    }
+
+    async fn run_int(
+        &mut self,
+        ctx: &mut SourceContext,
+        collector: &mut SourceCollector,
+    ) -> DataflowResult<SourceFinishType> {
+        // a slot can only be read by one consumer, so only the first subtask reads it
+        if ctx.task_info.task_index != 0 {
+            collector
+                .broadcast(SignalMessage::Watermark(Watermark::Idle))
+                .await;
+            loop {
+                let msg = ctx.control_rx.recv().await;
+                if let Some(r) = self
+                    .handle_control_message(msg, None, ctx, collector)
+                    .await?
+                {
+                    return Ok(r);
+                }
+            }
+        }
+
+        let mut builder = ChangeBuilder::new(ctx.out_schema.schema.clone())
+            .map_err(|e| connector_err!(Internal, NoRetry, "{}", e))?;
+
+        let client = connect(&self.config).await.map_err(
+            |e| connector_err!(External, WithBackoff, source: e, "failed to connect to Postgres"),
+        )?;
+
+        self.check_replica_identity(&client).await?;
+
+        let restored: Option<PostgresCdcState> = ctx
+            .table_manager
+            .get_global_keyed_state::<(), PostgresCdcState>("s")
+            .await?
+            .get(&())
+            .cloned();
+
+        let mut snapshot = match restored {
+            Some(state) => {
+                // the restored checkpoint has completed, so we no longer need its changes
+                self.advance_slot(&client, state.end_lsn).await?;
+                self.state = state;
+                None
+            }
+            None => {
+                self.create_publication(&client).await?;
+                self.create_slot(&client, &builder).await?
+            }
+        };
+
+        // fetching from the snapshot cursor isn't cancel-safe, so rather than selecting over it,
+        // we check for control messages between chunks. A checkpoint is held until the snapshot
+        // has been read, as we couldn't resume it after restoring.
+        let mut held_checkpoint = None;
+        while let Some(s) = &mut snapshot {
+            match ctx.control_rx.try_recv() {
+                Ok(msg @ ControlMessage::Checkpoint(_)) => {
+                    held_checkpoint = Some(msg);
+                }
+                Ok(msg) => {
+                    if let Some(r) = self
+                        .handle_control_message(Some(msg), None, ctx, collector)
+                        .await?
+                    {
+                        return Ok(r);
+                    }
+                }
+                Err(_) => {}
+            }
+
+            if self
+                .fetch_snapshot(&client, s, collector, &mut builder)
+                .await?
+            {
+                snapshot = None;
+            }
+        }
+
+        if let Some(r) = self
+            .handle_control_message(held_checkpoint, Some(&client), ctx, collector)
+            .await?
+        {
+            return Ok(r);
+        }
+
+        let mut timer = tokio::time::interval(POLL_INTERVAL);
+        timer.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        // set once a poll finds only changes that were already emitted, until the next commit
+        // advances the slot past them
+        let mut blocked = false;
+
+        loop {
+            select! {
+                _ = timer.tick(), if !blocked => {
+                    match self.poll(&client, ctx, collector, &mut builder).await? {
+                        PollResult::CaughtUp => {}
+                        PollResult::More => timer.reset_immediately(),
+                        PollResult::Blocked => {
+                            debug!(
+                                "replication slot {} only has changes that were already emitted; \
+                                waiting for the next checkpoint to commit",
+                                self.slot_name
+                            );
+                            blocked = true;
+                        }
+                    }
+                }
+                control_message = ctx.control_rx.recv() => {
+                    if matches!(control_message, Some(ControlMessage::Commit { .. })) {
+                        blocked = false;
+                    }
+                    if let Some(r) = self
+                        .handle_control_message(control_message, Some(&client), ctx, collector)
+                        .await?
+                    {
+                        return Ok(r);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl SourceOperator for PostgresCdcSourceFunc {
+    fn name(&self) -> String {
+        format!("postgres-cdc-{}", self.table_name)
+    }
+
+    fn tables(&self) -> HashMap<String, TableConfig> {
+        // committed in two phases so that we're told when each checkpoint has completed
+        single_item_hash_map(
+            "s".to_string(),
+            TableConfig {
+                table_type: TableEnum::GlobalKeyValue.into(),
+                config: GlobalKeyedTableConfig {
+                    table_name: "s".to_string(),
+                    description: "postgres cdc source state".to_string(),
+                    uses_two_phase_commit: true,
+                }
+                .encode_to_vec(),
+                state_version: 0,
+            },
+        )
+    }
+
+    async fn run(
+        &mut self,
+        ctx: &mut SourceContext,
+        collector: &mut SourceCollector,
+    ) -> DataflowResult<SourceFinishType> {
+        self.run_int(ctx, collector).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_poll_result() {
+        assert_eq!(PollResult::new(0, false), PollResult::CaughtUp);
+        assert_eq!(PollResult::new(10, true), PollResult::CaughtUp);
+        assert_eq!(
+            PollResult::new(MAX_CHANGES_PER_POLL, true),
+            PollResult::More
+        );
+        // postgres finishes the transaction it's in, so may return more than the limit
+        assert_eq!(
+            PollResult::new(MAX_CHANGES_PER_POLL + 3, false),
+            PollResult::Blocked
+        );
+    }
+
+    #[test]
+    fn test_format_lsn() {
+        assert_eq!(format_lsn((0x16 << 32) | 0xB374D848), "16/B374D848");
+        assert_eq!(format_lsn(0x1A0), "0/1A0");
+    }
+
+    #[test]
+    fn test_normalize_timestamp() {
+        assert_eq!(
+            normalize_timestamp("2024-01-02 03:04:05.123+00"),
+            "2024-01-02 03:04:05.123+00:00"
+        );
+        assert_eq!(
+            normalize_timestamp("2024-01-02 03:04:05-07"),
+            "2024-01-02 03:04:05-07:00"
+        );
+        assert_eq!(
+            normalize_timestamp("2024-01-02 03:04:05"),
+            "2024-01-02 03:04:05"
+        );
+        assert_eq!(normalize_timestamp("2024-01-02"), "2024-01-02");
+    }
+
+    #[test]
+    fn test_parse_insert() {
+        let mut data = vec![b'I'];
+        data.extend(16384u32.to_be_bytes());
+        data.push(b'N');
+        data.extend(2i16.to_be_bytes());
+        data.push(b't');
+        data.extend(2i32.to_be_bytes());
+        data.extend(b"42");
+        data.push(b'n');
+
+        let Message::Insert { relation, new } = parse_message(&data).unwrap() else {
+            panic!("expected insert");
+        };
+        assert_eq!(relation, 16384);
+        assert_eq!(
+            new,
+            vec![TupleValue::Text("42".to_string()), TupleValue::Null]
+        );
+    }
+}
@@ -0,0 +1,315 @@
+use crate::postgres::{PostgresConfig, PostgresTable, connect, quote_identifier, quote_table_name};
+use anyhow::{anyhow, bail};
+use arrow::array::{
+    Array, ArrayRef, AsArray, BinaryArray, BooleanArray, Date32Array, Float32Array, Float64Array,
+    Int16Array, Int32Array, Int64Array, RecordBatch, StringArray, TimestampNanosecondArray,
+    UInt32Array, UInt64Array,
+};
+use arrow::compute::{cast, take};
+use arrow::datatypes::{DataType, Int16Type, Int32Type, Int64Type, Schema};
+use arroyo_operator::connector::LookupConnector;
+use arroyo_rpc::connector_err;
+use arroyo_rpc::errors::DataflowError;
+use arroyo_types::LOOKUP_KEY_INDEX_FIELD;
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio_postgres::types::{ToSql, Type};
+use tokio_postgres::{Client, Row, Statement};
+use uuid::Uuid;
+
+/// Looks up rows by primary key, batching all of the keys from an input batch into a single
+/// `WHERE pk = ANY($1)` query
+pub struct PostgresLookup {
+    config: PostgresConfig,
+    query: String,
+    client: Option<(Client, Statement)>,
+    schema: Arc<Schema>,
+    /// the index of the primary key among the queried columns
+    key_column: usize,
+}
+
+impl PostgresLookup {
+    pub(crate) fn new(
+        config: PostgresConfig,
+        table: PostgresTable,
+        schema: Arc<Schema>,
+    ) -> anyhow::Result<Self> {
+        let primary_key = table
+            .primary_key
+            .ok_or_else(|| anyhow!("Postgres lookup table has no primary key"))?;
+
+        let columns: Vec<_> = schema
+            .fields
+            .iter()
+            .filter(|f| f.name() != LOOKUP_KEY_INDEX_FIELD)
+            .map(|f| f.name().as_str())
+            .collect();
+
+        let key_column = columns
+            .iter()
+            .position(|c| *c == primary_key)
+            .ok_or_else(|| anyhow!("primary key '{}' is not a column of the table", primary_key))?;
+
+        let query = format!(
+            "SELECT {} FROM {} WHERE {} = ANY($1)",
+            columns
+                .iter()
+                .map(|c| quote_identifier(c))
+                .collect::<Vec<_>>()
+                .join(", "),
+            quote_table_name(&table.table_name),
+            quote_identifier(&primary_key)
+        );
+
+        Ok(Self {
+            config,
+            query,
+            client: None,
+            schema,
+            key_column,
+        })
+    }
+
+    async fn query(
+        &mut self,
+        keys: &ArrayRef,
+    ) -> Result<(Vec<Row>, Vec<Option<String>>, Vec<Type>), DataflowError> {
+        if self.client.is_none() {
+            let client = connect(&self.config).await.map_err(|e| {
+                connector_err!(External, WithBackoff, source: e, "failed to connect to Postgres")
+            })?;
+
+            let statement = client.prepare(&self.query).await.map_err(|e| {
+                connector_err!(
+                    User,
+                    NoRetry,
+                    "failed to prepare Postgres lookup query `{}`: {}",
+                    self.query,
+                    e
+                )
+            })?;
+
+            self.client = Some((client, statement));
+        }
+
+        let (client, statement) = self.client.as_ref().unwrap();
+
+        let (param, key_strings) = key_param(keys, &statement.params()[0])
+            .map_err(|e| connector_err!(User, NoRetry, "{}", e))?;
+
+        let column_types = statement
+            .columns()
+            .iter()
+            .map(|c| c.type_().clone())
+            .collect();
+
+        match client.query(statement, &[param.as_ref()]).await {
+            Ok(rows) => Ok((rows, key_strings, column_types)),
+            Err(e) => {
+                // reconnect on the next lookup
+                self.client = None;
+                Err(connector_err!(
+                    External,
+                    WithBackoff,
+                    "Postgres lookup query failed: {}",
+                    e
+                ))
+            }
+        }
+    }
+
+    async fn lookup_inner(&mut self, keys: &[ArrayRef]) -> Result<RecordBatch, DataflowError> {
+        if keys.len() != 1 {
+            return Err(connector_err!(
+                User,
+                NoRetry,
+                "Postgres lookups must be made against a single primary key column"
+            ));
+        }
+
+        let (rows, key_strings, column_types) = self.query(&keys[0]).await?;
+
+        let key_indices: HashMap<_, _> = key_strings
+            .iter()
+            .enumerate()
+            .filter_map(|(i, k)| Some((k.as_ref()?.as_str(), i)))
+            .collect();
+
+        let fields: Vec<_> = self
+            .schema
+            .fields
+            .iter()
+            .filter(|f| f.name() != LOOKUP_KEY_INDEX_FIELD)
+            .collect();
+
+        let columns = fields
+            .iter()
+            .zip(column_types.iter())
+            .enumerate()
+            .map(|(i, (field, pg_type))| {
+                let array = column_to_array(&rows, i, pg_type)?;
+                Ok(cast(&array, field.data_type()).map_err(|e| {
+                    anyhow!(
+                        "cannot convert Postgres column '{}' of type {} to {}: {}",
+                        field.name(),
+                        pg_type,
+                        field.data_type(),
+                        e
+                    )
+                })?)
+            })
+            .collect::<anyhow::Result<Vec<_>>>()
+            .map_err(|e| connector_err!(User, NoRetry, "{}", e))?;
+
+        // every key gets an output row, which is null if there's no matching row in the table
+        let mut indices = vec![None; keys[0].len()];
+        let found_keys = cast(&columns[self.key_column], &DataType::Utf8)?;
+        for (row, key) in found_keys.as_string::<i32>().iter().enumerate() {
+            if let Some(idx) = key.and_then(|k| key_indices.get(k)) {
+                indices[*idx] = Some(row as u32);
+            }
+        }
+        let indices = UInt32Array::from(indices);
+
+        let mut columns = columns.into_iter();
+        let output = self
+            .schema
+            .fields
+            .iter()
+            .map(|f| {
+                if f.name() == LOOKUP_KEY_INDEX_FIELD {
+                    Ok(
+                        Arc::new(UInt64Array::from_iter_values(0..indices.len() as u64))
+                            as ArrayRef,
+                    )
+                } else {
+                    take(&columns.next().unwrap(), &indices, None)
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(RecordBatch::try_new(self.schema.clone(), output)?)
+    }
+}
+
+/// Converts the lookup keys into a query parameter matching the type of the primary key column,
+/// along with the string form of each key, for matching rows back to keys
+#[allow(clippy::type_complexity)]
+fn key_param(
+    keys: &ArrayRef,
+    param_type: &Type,
+) -> anyhow::Result<(Box<dyn ToSql + Sync + Send>, Vec<Option<String>>)> {
+    macro_rules! int_param {
+        ($data_type:expr, $arrow_type:ty) => {{
+            let keys = cast(keys, &$data_type)?;
+            let values: Vec<_> = keys.as_primitive::<$arrow_type>().iter().collect();
+            let strings = values.iter().map(|v| v.map(|v| v.to_string())).collect();
+            (Box::new(values) as Box<dyn ToSql + Sync + Send>, strings)
+        }};
+    }
+
+    Ok(match *param_type {
+        Type::INT2_ARRAY => int_param!(DataType::Int16, Int16Type),
+        Type::INT4_ARRAY => int_param!(DataType::Int32, Int32Type),
+        Type::INT8_ARRAY => int_param!(DataType::Int64, Int64Type),
+        Type::TEXT_ARRAY | Type::VARCHAR_ARRAY | Type::BPCHAR_ARRAY => {
+            let keys = cast(keys, &DataType::Utf8)?;
+            let values: Vec<_> = keys
+                .as_string::<i32>()
+                .iter()
+                .map(|v| v.map(|v| v.to_string()))
+                .collect();
+            (
+                Box::new(values.clone()) as Box<dyn ToSql + Sync + Send>,
+                values,
+            )
+        }
+        Type::UUID_ARRAY => {
+            let keys = cast(keys, &DataType::Utf8)?;
+            let values: Vec<_> = keys
+                .as_string::<i32>()
+                .iter()
+                .map(|v| v.and_then(|v| Uuid::parse_str(v).ok()))
+                .collect();
+            let strings = values.iter().map(|v| v.map(|v| v.to_string())).collect();
+            (Box::new(values) as Box<dyn ToSql + Sync + Send>, strings)
+        }
+        ref t => bail!(
+            "unsupported type for Postgres lookup key: {}; keys must be integers, text, or uuids",
+            t
+        ),
+    })
+}
+
+/// Reads a column of the result rows into an array of the Arrow type that's closest to its
+/// Postgres type, which is then cast to the type in the table's schema
+fn column_to_array(rows: &[Row], idx: usize, pg_type: &Type) -> anyhow::Result<ArrayRef> {
+    macro_rules! column {
+        ($t:ty) => {
+            rows.iter()
+                .map(|r| r.try_get::<_, Option<$t>>(idx))
+                .collect::<Result<Vec<_>, _>>()?
+        };
+    }
+
+    Ok(match *pg_type {
+        Type::BOOL => Arc::new(BooleanArray::from(column!(bool))),
+        Type::INT2 => Arc::new(Int16Array::from(column!(i16))),
+        Type::INT4 => Arc::new(Int32Array::from(column!(i32))),
+        Type::INT8 => Arc::new(Int64Array::from(column!(i64))),
+        Type::FLOAT4 => Arc::new(Float32Array::from(column!(f32))),
+        Type::FLOAT8 => Arc::new(Float64Array::from(column!(f64))),
+        Type::NUMERIC => Arc::new(StringArray::from_iter(
+            column!(rust_decimal::Decimal)
+                .into_iter()
+                .map(|v| v.map(|v| v.to_string())),
+        )),
+        Type::TEXT | Type::VARCHAR | Type::BPCHAR | Type::NAME => {
+            Arc::new(StringArray::from(column!(&str)))
+        }
+        Type::JSON | Type::JSONB => Arc::new(StringArray::from_iter(
+            column!(serde_json::Value)
+                .into_iter()
+                .map(|v| v.map(|v| v.to_string())),
+        )),
+        Type::UUID => Arc::new(StringArray::from_iter(
+            column!(Uuid).into_iter().map(|v| v.map(|v| v.to_string())),
+        )),
+        Type::BYTEA => Arc::new(BinaryArray::from(column!(&[u8]))),
+        Type::TIMESTAMP => Arc::new(TimestampNanosecondArray::from(
+            column!(NaiveDateTime)
+                .into_iter()
+                .map(|v| v.and_then(|v| v.and_utc().timestamp_nanos_opt()))
+                .collect::<Vec<_>>(),
+        )),
+        Type::TIMESTAMPTZ => Arc::new(TimestampNanosecondArray::from(
+            column!(DateTime<Utc>)
+                .into_iter()
+                .map(|v| v.and_then(|v| v.timestamp_nanos_opt()))
+                .collect::<Vec<_>>(),
+        )),
+        Type::DATE => {
+            let epoch = NaiveDate::from_ymd_opt(1970, 1, 1).unwrap();
+            Arc::new(Date32Array::from(
+                column!(NaiveDate)
+                    .into_iter()
+                    .map(|v| v.map(|v| (v - epoch).num_days() as i32))
+                    .collect::<Vec<_>>(),
+            ))
+        }
+        ref t => bail!("unsupported Postgres type {} for lookup table column", t),
+    })
+}
+
+#[async_trait]
+impl LookupConnector for PostgresLookup {
+    fn name(&self) -> String {
+        "PostgresLookup".to_string()
+    }
+
+    async fn lookup(&mut self, keys: &[ArrayRef]) -> Option<Result<RecordBatch, DataflowError>> {
+        Some(self.lookup_inner(keys).await)
+    }
+}
@@ -0,0 +1,467 @@
+pub mod cdc;
+pub mod lookup;
+pub mod sink;
+
+use crate::postgres::cdc::PostgresCdcSourceFunc;
+use crate::postgres::lookup::PostgresLookup;
+use crate::postgres::sink::PostgresSinkFunc;
+use anyhow::{anyhow, bail};
+use arrow::datatypes::Schema;
+use arroyo_operator::connector::{Connection, Connector, LookupConnector};
+use arroyo_operator::operator::ConstructedOperator;
+use arroyo_rpc::api_types::connections::{
+    ConnectionProfile, ConnectionSchema, ConnectionType, TestSourceMessage,
+};
+use arroyo_rpc::var_str::VarStr;
+use arroyo_rpc::{ConnectorOptions, OperatorConfig};
+use native_tls::TlsConnector;
+use postgres_native_tls::MakeTlsConnector;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::oneshot::Receiver;
+use tokio_postgres::Client;
+use tracing::warn;
+use typify::import_types;
+
+pub struct PostgresConnector {}
+
+const CONFIG_SCHEMA: &str = include_str!("./profile.json");
+const TABLE_SCHEMA: &str = include_str!("./table.json");
+const ICON: &str = include_str!("./postgres.svg");
+
+import_types!(
+    schema = "src/postgres/profile.json",
+    convert = {
+        {type = "string", format = "var-str"} = VarStr
+    }
+);
+
+import_types!(schema = "src/postgres/table.json");
+
+pub(crate) async fn connect(config: &PostgresConfig) -> anyhow::Result<Client> {
+    connect_with(pg_config(config)?, config).await
+}
+
+/// Connects in logical replication mode, in which replication commands like
+/// CREATE_REPLICATION_SLOT can be run
+pub(crate) async fn connect_replication(config: &PostgresConfig) -> anyhow::Result<Client> {
+    let mut pg_config = pg_config(config)?;
+    pg_config.replication_mode(tokio_postgres::config::ReplicationMode::Logical);
+    connect_with(pg_config, config).await
+}
+
+fn pg_config(config: &PostgresConfig) -> anyhow::Result<tokio_postgres::Config> {
+    let mut pg_config = tokio_postgres::Config::new();
+    pg_config
+        .host(&config.host)
+        .port(
+            config
+                .port
+                .map(u16::try_from)
+                .transpose()
+                .map_err(|_| anyhow!("invalid port for Postgres"))?
+                .unwrap_or(5432),
+        )
+        .dbname(&config.database)
+        .ssl_mode(match config.ssl_mode {
+            Some(SslMode::Disable) => tokio_postgres::config::SslMode::Disable,
+            Some(SslMode::Prefer) | None => tokio_postgres::config::SslMode::Prefer,
+            Some(SslMode::Require) => tokio_postgres::config::SslMode::Require,
+        });
+
+    if let Some(username) = &config.username {
+        pg_config.user(username.sub_env_vars().map_err(|e| anyhow!("{}", e))?);
+    }
+
+    if let Some(password) = &config.password {
+        pg_config.password(password.sub_env_vars().map_err(|e| anyhow!("{}", e))?);
+    }
+
+    Ok(pg_config)
+}
+
+async fn connect_with(
+    pg_config: tokio_postgres::Config,
+    config: &PostgresConfig,
+) -> anyhow::Result<Client> {
+    let tls = MakeTlsConnector::new(
+        TlsConnector::new().map_err(|e| anyhow!("failed to construct TLS connector: {:?}", e))?,
+    );
+
+    let (client, connection) = pg_config
+        .connect(tls)
+        .await
+        .map_err(|e| anyhow!("failed to connect to Postgres at {}: {:?}", config.host, e))?;
+
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            warn!("Postgres connection closed with error: {:?}", e);
+        }
+    });
+
+    Ok(client)
+}
+
+/// Quotes a possibly schema-qualified table name for use in a query
+pub(crate) fn quote_table_name(table_name: &str) -> String {
+    table_name
+        .split('.')
+        .map(quote_identifier)
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+pub(crate) fn quote_identifier(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace('"', "\"\""))
+}
+
+pub(crate) fn quote_literal(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "''"))
+}
+
+async fn test_inner(
+    c: PostgresConfig,
+    table: Option<PostgresTable>,
+    tx: tokio::sync::mpsc::Sender<TestSourceMessage>,
+) -> anyhow::Result<String> {
+    tx.send(TestSourceMessage::info("Connecting to Postgres"))
+        .await
+        .unwrap();
+
+    let client = connect(&c).await?;
+
+    let Some(table) = table else {
+        client
+            .simple_query("SELECT 1")
+            .await
+            .map_err(|e| anyhow!("Failed to run query against Postgres: {:?}", e))?;
+
+        return Ok("Connected to Postgres successfully".to_string());
+    };
+
+    tx.send(TestSourceMessage::info(format!(
+        "Connected successfully, checking table {}",
+        table.table_name
+    )))
+    .await
+    .unwrap();
+
+    client
+        .simple_query(&format!(
+            "SELECT * FROM {} LIMIT 0",
+            quote_table_name(&table.table_name)
+        ))
+        .await
+        .map_err(|e| anyhow!("Failed to read table {}: {:?}", table.table_name, e))?;
+
+    Ok(format!("Table {} is readable", table.table_name))
+}
+
+impl Connector for PostgresConnector {
+    type ProfileT = PostgresConfig;
+    type TableT = PostgresTable;
+
+    fn name(&self) -> &'static str {
+        "postgres"
+    }
+
+    fn metadata(&self) -> arroyo_rpc::api_types::connections::Connector {
+        arroyo_rpc::api_types::connections::Connector {
+            id: "postgres".to_string(),
+            name: "Postgres".to_string(),
+            icon: ICON.to_string(),
+            description:
+                "Read changes from Postgres tables, enrich streams with rows looked up from them, or write results to them"
+                    .to_string(),
+            enabled: true,
+            source: true,
+            sink: true,
+            testing: true,
+            hidden: false,
+            custom_schemas: true,
+            connection_config: Some(CONFIG_SCHEMA.to_string()),
+            table_config: TABLE_SCHEMA.to_string(),
+        }
+    }
+
+    fn table_type(&self, _: Self::ProfileT, table: Self::TableT) -> ConnectionType {
+        match table.table_type {
+            Some(TableType::Sink) => ConnectionType::Sink,
+            Some(TableType::Source) => ConnectionType::Source,
+            Some(TableType::Lookup) | None => ConnectionType::Lookup,
+        }
+    }
+
+    fn accepts_updating_input(&self, _: Self::ProfileT, table: Self::TableT) -> bool {
+        // retractions are applied as deletes of their key, so only upsert sinks can take them
+        table.table_type == Some(TableType::Sink) && table.write_mode == Some(WriteMode::Upsert)
+    }
+
+    fn get_schema(
+        &self,
+        _: Self::ProfileT,
+        _: Self::TableT,
+        s: Option<&ConnectionSchema>,
+    ) -> Option<ConnectionSchema> {
+        s.cloned()
+    }
+
+    fn test_profile(&self, profile: Self::ProfileT) -> Option<Receiver<TestSourceMessage>> {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+
+        tokio::spawn(async move {
+            let (itx, _rx) = tokio::sync::mpsc::channel(8);
+            let message = match test_inner(profile, None, itx).await {
+                Ok(_) => TestSourceMessage::done("Successfully connected to Postgres"),
+                Err(e) => TestSourceMessage::fail(format!("Failed to connect to Postgres: {e:?}")),
+            };
+
+            tx.send(message).unwrap();
+        });
+
+        Some(rx)
+    }
+
+    fn test(
+        &self,
+        _: &str,
+        c: Self::ProfileT,
+        t: Self::TableT,
+        _: Option<&ConnectionSchema>,
+        tx: tokio::sync::mpsc::Sender<TestSourceMessage>,
+    ) {
+        tokio::task::spawn(async move {
+            let resp = match test_inner(c, Some(t), tx.clone()).await {
+                Ok(c) => TestSourceMessage::done(c),
+                Err(e) => TestSourceMessage::fail(e.to_string()),
+            };
+
+            tx.send(resp).await.unwrap();
+        });
+    }
+
+    fn from_options(
+        &self,
+        name: &str,
+        options: &mut ConnectorOptions,
+        s: Option<&ConnectionSchema>,
+        profile: Option<&ConnectionProfile>,
+    ) -> anyhow::Result<Connection> {
+        let connection_config = match profile {
+            Some(connection_profile) => {
+                serde_json::from_value(connection_profile.config.clone())
+                    .map_err(|e| anyhow!("Failed to parse connection config: {:?}", e))?
+            }
+            None => PostgresConfig {
+                host: options.pull_str("host")?,
+                port: options
+                    .pull_opt_u64("port")?
+                    .map(|p| p.try_into())
+                    .transpose()
+                    .map_err(|_| anyhow!("invalid value for port"))?,
+                database: options.pull_str("database")?,
+                username: options.pull_opt_str("username")?.map(VarStr::new),
+                password: options.pull_opt_str("password")?.map(VarStr::new),
+                ssl_mode: match options.pull_opt_str("ssl_mode")?.as_deref() {
+                    None => None,
+                    Some("disable") => Some(SslMode::Disable),
+                    Some("prefer") => Some(SslMode::Prefer),
+                    Some("require") => Some(SslMode::Require),
+                    Some(mode) => {
+                        bail!(
+                            "'{}' is not a valid value for ssl_mode; must be one of 'disable', 'prefer', or 'require'",
+                            mode
+                        );
+                    }
+                },
+            },
+        };
+
+        let table = match options.pull_str("type")?.as_str() {
+            "lookup" => PostgresTable {
+                table_name: options.pull_str("table_name")?,
+                primary_key: None,
+                table_type: Some(TableType::Lookup),
+                write_mode: None,
+                key_columns: vec![],
+                batch_size: None,
+                slot_name: None,
+                publication: None,
+                snapshot: None,
+                commit_mode: None,
+            },
+            "sink" => PostgresTable {
+                table_name: options.pull_str("table_name")?,
+                primary_key: None,
+                table_type: Some(TableType::Sink),
+                write_mode: options
+                    .pull_opt_str("sink.mode")?
+                    .map(|s| s.try_into())
+                    .transpose()
+                    .map_err(|_| {
+                        anyhow!("invalid value for 'sink.mode'; must be 'append' or 'upsert'")
+                    })?,
+                key_columns: vec![],
+                batch_size: options
+                    .pull_opt_u64("sink.batch_size")?
+                    .map(|t| t.try_into())
+                    .transpose()
+                    .map_err(|_| anyhow!("sink.batch_size must be greater than 0"))?,
+                slot_name: None,
+                publication: None,
+                snapshot: None,
+                commit_mode: options
+                    .pull_opt_str("sink.commit_mode")?
+                    .map(|s| s.try_into())
+                    .transpose()
+                    .map_err(|_| {
+                        anyhow!(
+                            "invalid value for 'sink.commit_mode'; must be 'at_least_once' or 'exactly_once'"
+                        )
+                    })?,
+            },
+            "source" => PostgresTable {
+                table_name: options.pull_str("table_name")?,
+                primary_key: None,
+                table_type: Some(TableType::Source),
+                write_mode: None,
+                key_columns: vec![],
+                batch_size: None,
+                slot_name: Some(options.pull_str("source.slot_name")?),
+                publication: options.pull_opt_str("source.publication")?,
+                snapshot: options.pull_opt_bool("source.snapshot")?,
+                commit_mode: None,
+            },
+            typ => bail!(
+                "'{}' is not a valid type; Postgres tables must be of type 'lookup' (created with CREATE TEMPORARY TABLE), 'sink', or 'source'",
+                typ
+            ),
+        };
+
+        self.from_config(None, name, connection_config, table, s)
+    }
+
+    fn from_config(
+        &self,
+        id: Option<i64>,
+        name: &str,
+        config: Self::ProfileT,
+        mut table: Self::TableT,
+        schema: Option<&ConnectionSchema>,
+    ) -> anyhow::Result<Connection> {
+        let schema = schema
+            .map(|s| s.to_owned())
+            .ok_or_else(|| anyhow!("No schema defined for Postgres connection"))?;
+
+        let connection_type = self.table_type(config.clone(), table.clone());
+
+        let description = if connection_type == ConnectionType::Source {
+            if schema.fields.iter().any(|f| f.metadata_key.is_some()) {
+                bail!("Postgres sources do not support metadata fields");
+            }
+
+            if !schema.format.as_ref().is_some_and(|f| f.is_updating()) {
+                bail!("Postgres sources must use the 'debezium_json' format");
+            }
+
+            if table.slot_name.is_none() {
+                bail!("Postgres sources must have a 'source.slot_name'");
+            }
+
+            "PostgresCdc".to_string()
+        } else if connection_type == ConnectionType::Sink {
+            if schema.fields.iter().any(|f| f.metadata_key.is_some()) {
+                bail!("Postgres sinks do not support metadata fields");
+            }
+
+            let mut key_columns: Vec<_> = schema.primary_keys.iter().cloned().collect();
+            key_columns.sort();
+
+            // with a primary key, the default is to upsert on it
+            let write_mode = *table.write_mode.get_or_insert(if key_columns.is_empty() {
+                WriteMode::Append
+            } else {
+                WriteMode::Upsert
+            });
+
+            if write_mode == WriteMode::Upsert && key_columns.is_empty() {
+                bail!("upsert Postgres sinks must have a PRIMARY KEY, which rows are upserted on");
+            }
+
+            table.key_columns = key_columns;
+            "PostgresSink".to_string()
+        } else {
+            let [primary_key] = schema.primary_keys.iter().collect::<Vec<_>>()[..] else {
+                bail!(
+                    "Postgres lookup tables must have exactly one PRIMARY KEY column, which lookups are made against"
+                );
+            };
+
+            if schema
+                .fields
+                .iter()
+                .any(|f| &f.name == primary_key && f.metadata_key.is_some())
+            {
+                bail!("the PRIMARY KEY of a Postgres lookup table must be a column of the table");
+            }
+
+            table.primary_key = Some(primary_key.clone());
+            "PostgresLookup".to_string()
+        };
+
+        let config = OperatorConfig {
+            connection: serde_json::to_value(config).unwrap(),
+            table: serde_json::to_value(table).unwrap(),
+            rate_limit: None,
+            // only sources read a format; lookups and sinks map columns onto the table directly
+            format: if connection_type == ConnectionType::Source {
+                schema.format.clone()
+            } else {
+                None
+            },
+            bad_data: schema.bad_data.clone(),
+            framing: None,
+            metadata_fields: vec![],
+            partition_watermarks: None,
+        };
+
+        Ok(Connection::new(
+            id,
+            self.name(),
+            name.to_string(),
+            connection_type,
+            schema,
+            &config,
+            description,
+        ))
+    }
+
+    fn make_operator(
+        &self,
+        profile: Self::ProfileT,
+        table: Self::TableT,
+        _: OperatorConfig,
+    ) -> anyhow::Result<ConstructedOperator> {
+        match table.table_type {
+            Some(TableType::Sink) => Ok(ConstructedOperator::from_operator(Box::new(
+                PostgresSinkFunc::new(profile, table)?,
+            ))),
+            Some(TableType::Source) => Ok(ConstructedOperator::from_source(Box::new(
+                PostgresCdcSourceFunc::new(profile, table)?,
+            ))),
+            Some(TableType::Lookup) | None => {
+                bail!("Cannot construct a lookup table as an operator")
+            }
+        }
+    }
+
+    fn make_lookup(
+        &self,
+        profile: Self::ProfileT,
+        table: Self::TableT,
+        _: OperatorConfig,
+        schema: Arc<Schema>,
+    ) -> anyhow::Result<Box<dyn LookupConnector + Send>> {
+        Ok(Box::new(PostgresLookup::new(profile, table, schema)?))
+    }
+}
@@ -0,0 +1,1175 @@
+use crate::postgres::{
+    CommitMode, PostgresConfig, PostgresTable, WriteMode, connect, quote_identifier, quote_literal,
+    quote_table_name,
+};
+use crate::pre_commit::PreCommitBuffer;
+use anyhow::{anyhow, bail};
+use arrow::array::{ArrayRef, AsArray, BooleanArray, RecordBatch, StructArray, UInt32Array};
+use arrow::compute::{cast, concat_batches, take};
+use arrow::datatypes::{
+    DataType, Date32Type, Float32Type, Float64Type, Int16Type, Int32Type, Int64Type, SchemaRef,
+    TimeUnit, TimestampNanosecondType,
+};
+use arrow::row::{RowConverter, SortField};
+use arroyo_operator::context::{Collector, OperatorContext};
+use arroyo_operator::operator::ArrowOperator;
+use arroyo_rpc::errors::{DataflowError, DataflowResult};
+use arroyo_rpc::grpc::rpc::TableConfig;
+use arroyo_rpc::{TIMESTAMP_FIELD, UPDATING_META_FIELD, connector_err};
+use arroyo_types::*;
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate};
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+use std::time::Duration;
+use tokio_postgres::error::SqlState;
+use tokio_postgres::types::{ToSql, Type};
+use tokio_postgres::{Client, Statement};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+const DEFAULT_BATCH_SIZE: usize = 1000;
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+struct Statements {
+    insert: Statement,
+    delete: Option<Statement>,
+}
+
+/// Writes rows to a Postgres table, either appending them or upserting them on the table's key
+/// columns. Retractions from updating inputs are applied as deletes of their keys.
+///
+/// Rows are buffered and written in batches, with each column sent as an array parameter to an
+/// `INSERT ... SELECT * FROM UNNEST(...)`. As only the final state of each key matters to an
+/// upsert, rows for the same key within a batch are collapsed to the last one.
+///
+/// In exactly-once mode, all writes between two checkpoints are made in a single transaction,
+/// which is prepared (with `PREPARE TRANSACTION`) when the checkpoint is taken and committed
+/// once it has completed. As `COMMIT PREPARED` can't run inside a transaction block, and the
+/// next checkpoint's transaction is already open by then, commits are made over a second
+/// connection. On restore, transactions prepared for checkpoints that didn't complete are
+/// rolled back, including those of subtasks that no longer exist after a rescale.
+pub struct PostgresSinkFunc {
+    config: PostgresConfig,
+    table_name: String,
+    write_mode: WriteMode,
+    key_columns: Vec<String>,
+    batch_size: usize,
+    commit_mode: CommitMode,
+    client: Option<Client>,
+    /// the connection prepared transactions are committed over
+    commit_client: Option<Client>,
+    statements: Option<Statements>,
+    /// the index in the input of each column written to the table, along with its type there
+    columns: Vec<(usize, Type)>,
+    /// the positions of the key columns within `columns`
+    key_indices: Vec<usize>,
+    updating_meta_index: Option<usize>,
+    buffer: Vec<RecordBatch>,
+    buffered_rows: usize,
+    /// the global identifiers of the transactions that have been prepared, and are waiting for
+    /// their checkpoint to commit
+    pre_commits: PreCommitBuffer,
+}
+
+fn db_err(context: &str, e: tokio_postgres::Error) -> DataflowError {
+    connector_err!(External, WithBackoff, "{}: {}", context, e)
+}
+
+impl PostgresSinkFunc {
+    pub(crate) fn new(config: PostgresConfig, table: PostgresTable) -> anyhow::Result<Self> {
+        let write_mode = table.write_mode.unwrap_or(WriteMode::Append);
+        if write_mode == WriteMode::Upsert && table.key_columns.is_empty() {
+            bail!("upsert Postgres sinks must have a PRIMARY KEY");
+        }
+
+        Ok(Self {
+            config,
+            table_name: table.table_name,
+            write_mode,
+            key_columns: table.key_columns,
+            batch_size: table
+                .batch_size
+                .map(|n| n.get() as usize)
+                .unwrap_or(DEFAULT_BATCH_SIZE),
+            commit_mode: table.commit_mode.unwrap_or(CommitMode::AtLeastOnce),
+            client: None,
+            commit_client: None,
+            statements: None,
+            columns: vec![],
+            key_indices: vec![],
+            updating_meta_index: None,
+            buffer: vec![],
+            buffered_rows: 0,
+            pre_commits: PreCommitBuffer::default(),
+        })
+    }
+
+    /// The prefix of the global identifiers of the transactions prepared by all subtasks of this
+    /// operator, which are followed by `{subtask}-{epoch}`
+    fn gid_prefix(task_info: &TaskInfo) -> String {
+        format!("arroyo-{}-{}-", task_info.job_id, task_info.operator_id)
+    }
+
+    fn gid(task_info: &TaskInfo, epoch: u32) -> String {
+        format!(
+            "{}{}-{}",
+            Self::gid_prefix(task_info),
+            task_info.task_index,
+            epoch
+        )
+    }
+
+    fn client(&self) -> &Client {
+        self.client
+            .as_ref()
+            .expect("Postgres sink is not connected")
+    }
+
+    async fn execute(&self, query: &str) -> DataflowResult<()> {
+        self.client()
+            .batch_execute(query)
+            .await
+            .map_err(|e| db_err(&format!("failed to execute `{query}` in Postgres"), e))
+    }
+
+    /// Looks up the types of the columns in the table, and prepares the statements we write with
+    async fn prepare(&mut self, names: &[String]) -> DataflowResult<()> {
+        let table = quote_table_name(&self.table_name);
+        let quoted: Vec<_> = names.iter().map(|c| quote_identifier(c)).collect();
+
+        let probe = self
+            .client()
+            .prepare(&format!(
+                "SELECT {} FROM {} LIMIT 0",
+                quoted.join(", "),
+                table
+            ))
+            .await
+            .map_err(|e| {
+                connector_err!(
+                    User,
+                    NoRetry,
+                    "failed to find columns {} in Postgres table {}: {}",
+                    quoted.join(", "),
+                    self.table_name,
+                    e
+                )
+            })?;
+
+        let types: Vec<Type> = probe.columns().iter().map(|c| c.type_().clone()).collect();
+        for (name, t) in names.iter().zip(&types) {
+            if !supported_type(t) {
+                return Err(connector_err!(
+                    User,
+                    NoRetry,
+                    "column '{}' of Postgres table {} has unsupported type {}",
+                    name,
+                    self.table_name,
+                    t
+                ));
+            }
+        }
+
+        let unnest = |columns: &[usize]| {
+            columns
+                .iter()
+                .enumerate()
+                .map(|(i, c)| format!("${}::{}[]", i + 1, types[*c].name()))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+
+        let all: Vec<usize> = (0..names.len()).collect();
+        let mut insert = format!(
+            "INSERT INTO {} ({}) SELECT * FROM UNNEST({})",
+            table,
+            quoted.join(", "),
+            unnest(&all)
+        );
+
+        let keys: Vec<_> = self
+            .key_indices
+            .iter()
+            .map(|i| quoted[*i].clone())
+            .collect();
+
+        if self.write_mode == WriteMode::Upsert {
+            let updates: Vec<_> = all
+                .iter()
+                .filter(|i| !self.key_indices.contains(i))
+                .map(|i| format!("{} = EXCLUDED.{}", quoted[*i], quoted[*i]))
+                .collect();
+
+            insert.push_str(&format!(" ON CONFLICT ({}) ", keys.join(", ")));
+            if updates.is_empty() {
+                insert.push_str("DO NOTHING");
+            } else {
+                insert.push_str(&format!("DO UPDATE SET {}", updates.join(", ")));
+            }
+        }
+
+        let delete = (self.write_mode == WriteMode::Upsert).then(|| {
+            format!(
+                "DELETE FROM {} WHERE ({}) IN (SELECT * FROM UNNEST({}))",
+                table,
+                keys.join(", "),
+                unnest(&self.key_indices)
+            )
+        });
+
+        let client = self.client();
+        let insert = client.prepare(&insert).await.map_err(|e| {
+            connector_err!(
+                User,
+                NoRetry,
+                "failed to prepare insert into Postgres table {}: {}",
+                self.table_name,
+                e
+            )
+        })?;
+
+        let delete = match delete {
+            Some(delete) => Some(client.prepare(&delete).await.map_err(|e| {
+                connector_err!(
+                    User,
+                    NoRetry,
+                    "failed to prepare delete from Postgres table {}: {}",
+                    self.table_name,
+                    e
+                )
+            })?),
+            None => None,
+        };
+
+        for (column, t) in self.columns.iter_mut().zip(types) {
+            column.1 = t;
+        }
+        self.statements = Some(Statements { insert, delete });
+        Ok(())
+    }
+
+    /// Rolls back transactions prepared for checkpoints that didn't complete by the subtasks
+    /// whose state this one has taken over
+    async fn roll_back_stale(&self, task_info: &TaskInfo) -> DataflowResult<()> {
+        let prefix = Self::gid_prefix(task_info);
+        let restored: HashSet<_> = self
+            .pre_commits
+            .restored()
+            .iter()
+            .flat_map(|p| &p.messages)
+            .map(|gid| String::from_utf8_lossy(gid))
+            .collect();
+
+        let rows = self
+            .client()
+            .query(
+                "SELECT gid FROM pg_prepared_xacts WHERE database = current_database()",
+                &[],
+            )
+            .await
+            .map_err(|e| db_err("failed to list prepared transactions in Postgres", e))?;
+
+        for row in rows {
+            let gid: String = row.get(0);
+            if owns_gid(&gid, &prefix, task_info.task_index, task_info.parallelism)
+                && !restored.contains(gid.as_str())
+            {
+                info!("rolling back stale prepared transaction {}", gid);
+                self.execute(&format!("ROLLBACK PREPARED {}", quote_literal(&gid)))
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Splits the buffered rows into those to insert and the keys to delete
+    fn plan_writes(&self, batch: &RecordBatch) -> DataflowResult<(Vec<u32>, Vec<u32>)> {
+        if self.write_mode == WriteMode::Append {
+            return Ok(((0..batch.num_rows() as u32).collect(), vec![]));
+        }
+
+        let retracts: Option<BooleanArray> = self.updating_meta_index.map(|i| {
+            let meta: &StructArray = batch.column(i).as_struct();
+            meta.column_by_name("is_retract")
+                .expect("meta struct must have is_retract")
+                .as_boolean()
+                .clone()
+        });
+
+        let key_arrays: Vec<ArrayRef> = self
+            .key_indices
+            .iter()
+            .map(|i| batch.column(self.columns[*i].0).clone())
+            .collect();
+
+        let converter = RowConverter::new(
+            key_arrays
+                .iter()
+                .map(|a| SortField::new(a.data_type().clone()))
+                .collect(),
+        )?;
+        let keys = converter.convert_columns(&key_arrays)?;
+
+        // only the last change to each key matters
+        let mut last = HashMap::new();
+        for (i, key) in keys.iter().enumerate() {
+            let retract = retracts.as_ref().is_some_and(|r| r.value(i));
+            last.insert(key, (i as u32, retract));
+        }
+
+        let mut upserts = vec![];
+        let mut deletes = vec![];
+        for (i, retract) in last.into_values() {
+            if retract {
+                deletes.push(i);
+            } else {
+                upserts.push(i);
+            }
+        }
+        upserts.sort_unstable();
+        deletes.sort_unstable();
+
+        Ok((upserts, deletes))
+    }
+
+    async fn write(
+        &self,
+        statement: &Statement,
+        batch: &RecordBatch,
+        indices: Vec<u32>,
+        columns: &[usize],
+    ) -> DataflowResult<()> {
+        if indices.is_empty() {
+            return Ok(());
+        }
+
+        let indices = UInt32Array::from(indices);
+        let params = columns
+            .iter()
+            .map(|c| {
+                let (idx, t) = &self.columns[*c];
+                column_param(&take(batch.column(*idx), &indices, None)?, t)
+            })
+            .collect::<anyhow::Result<Vec<_>>>()
+            .map_err(|e| {
+                connector_err!(
+                    User,
+                    NoRetry,
+                    "failed to convert rows for Postgres table {}: {}",
+                    self.table_name,
+                    e
+                )
+            })?;
+
+        let params: Vec<&(dyn ToSql + Sync)> = params
+            .iter()
+            .map(|p| p.as_ref() as &(dyn ToSql + Sync))
+            .collect();
+
+        self.client()
+            .execute(statement, &params)
+            .await
+            .map_err(|e| {
+                db_err(
+                    &format!("failed to write to Postgres table {}", self.table_name),
+                    e,
+                )
+            })?;
+
+        Ok(())
+    }
+
+    async fn flush(&mut self, schema: SchemaRef) -> DataflowResult<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+
+        let batch = concat_batches(&schema, &self.buffer)?;
+        self.buffer.clear();
+        self.buffered_rows = 0;
+
+        let (upserts, deletes) = self.plan_writes(&batch)?;
+        let statements = self.statements.as_ref().expect("statements not prepared");
+
+        let exactly_once = self.commit_mode == CommitMode::ExactlyOnce;
+        // in exactly-once mode, we're always in the checkpoint's transaction
+        if !exactly_once {
+            self.execute("BEGIN").await?;
+        }
+
+        // after collapsing, the deleted and upserted keys are disjoint, so order doesn't matter
+        if let Some(delete) = &statements.delete {
+            self.write(delete, &batch, deletes, &self.key_indices)
+                .await?;
+        }
+
+        let all: Vec<usize> = (0..self.columns.len()).collect();
+        self.write(&statements.insert, &batch, upserts, &all)
+            .await?;
+
+        if !exactly_once {
+            self.execute("COMMIT").await?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Whether a prepared transaction was prepared by a subtask whose state is restored by subtask
+/// `task_index`, which is the case for the same subtasks as in the restore of the "p" table
+fn owns_gid(gid: &str, prefix: &str, task_index: u32, parallelism: u32) -> bool {
+    gid.strip_prefix(prefix)
+        .and_then(|rest| rest.split_once('-'))
+        .filter(|(_, epoch)| epoch.parse::<u32>().is_ok())
+        .and_then(|(subtask, _)| subtask.parse::<u32>().ok())
+        .is_some_and(|subtask| subtask % parallelism == task_index)
+}
+
+fn supported_type(t: &Type) -> bool {
+    matches!(
+        *t,
+        Type::BOOL
+            | Type::INT2
+            | Type::INT4
+            | Type::INT8
+            | Type::FLOAT4
+            | Type::FLOAT8
+            | Type::NUMERIC
+            | Type::TEXT
+            | Type::VARCHAR
+            | Type::BPCHAR
+            | Type::NAME
+            | Type::JSON
+            | Type::JSONB
+            | Type::UUID
+            | Type::BYTEA
+            | Type::TIMESTAMP
+            | Type::TIMESTAMPTZ
+            | Type::DATE
+    )
+}
+
+/// Converts a column into an array parameter for a Postgres column of type `pg_type`
+fn column_param(array: &ArrayRef, pg_type: &Type) -> anyhow::Result<Box<dyn ToSql + Sync + Send>> {
+    macro_rules! primitive {
+        ($data_type:expr, $arrow_type:ty) => {{
+            let array = cast(array, &$data_type)?;
+            Box::new(
+                array
+                    .as_primitive::<$arrow_type>()
+                    .iter()
+                    .collect::<Vec<_>>(),
+            )
+        }};
+    }
+
+    macro_rules! strings {
+        ($f:expr) => {{
+            let array = cast(array, &DataType::Utf8)?;
+            array
+                .as_string::<i32>()
+                .iter()
+                .map(|v| v.map($f).transpose())
+                .collect::<anyhow::Result<Vec<_>>>()?
+        }};
+    }
+
+    let param: Box<dyn ToSql + Sync + Send> = match *pg_type {
+        Type::BOOL => Box::new(
+            cast(array, &DataType::Boolean)?
+                .as_boolean()
+                .iter()
+                .collect::<Vec<_>>(),
+        ),
+        Type::INT2 => primitive!(DataType::Int16, Int16Type),
+        Type::INT4 => primitive!(DataType::Int32, Int32Type),
+        Type::INT8 => primitive!(DataType::Int64, Int64Type),
+        Type::FLOAT4 => primitive!(DataType::Float32, Float32Type),
+        Type::FLOAT8 => primitive!(DataType::Float64, Float64Type),
+        Type::NUMERIC => Box::new(strings!(|v: &str| rust_decimal::Decimal::from_str(v)
+            .map_err(|e| anyhow!("invalid numeric '{}': {}", v, e)))),
+        Type::TEXT | Type::VARCHAR | Type::BPCHAR | Type::NAME => {
+            Box::new(strings!(|v: &str| anyhow::Ok(v.to_string())))
+        }
+        Type::JSON | Type::JSONB => Box::new(strings!(|v: &str| serde_json::from_str::<
+            serde_json::Value,
+        >(v)
+        .map_err(|e| anyhow!("invalid JSON '{}': {}", v, e)))),
+        Type::UUID => Box::new(strings!(|v: &str| Uuid::parse_str(v).map_err(|e| anyhow!(
+            "invalid UUID '{}': {}",
+            v,
+            e
+        )))),
+        Type::BYTEA => Box::new(
+            cast(array, &DataType::Binary)?
+                .as_binary::<i32>()
+                .iter()
+                .map(|v| v.map(|v| v.to_vec()))
+                .collect::<Vec<_>>(),
+        ),
+        Type::TIMESTAMP | Type::TIMESTAMPTZ => {
+            let array = cast(array, &DataType::Timestamp(TimeUnit::Nanosecond, None))?;
+            let values = array
+                .as_primitive::<TimestampNanosecondType>()
+                .iter()
+                .map(|v| v.map(DateTime::from_timestamp_nanos));
+            if *pg_type == Type::TIMESTAMP {
+                Box::new(values.map(|v| v.map(|v| v.naive_utc())).collect::<Vec<_>>())
+            } else {
+                Box::new(values.collect::<Vec<_>>())
+            }
+        }
+        Type::DATE => {
+            let epoch = NaiveDate::from_ymd_opt(1970, 1, 1).unwrap();
+            let array = cast(array, &DataType::Date32)?;
+            Box::new(
+                array
+                    .as_primitive::<Date32Type>()
+                    .iter()
+                    .map(|v| v.map(|v| epoch + chrono::TimeDelta::days(v as i64)))
+                    .collect::<Vec<_>>(),
+            )
+        }
+        ref t => bail!("unsupported Postgres type {} for sink column", t),
+    };
+
+    Ok(param)
+}
+
+#[async_trait]
+impl ArrowOperator for PostgresSinkFunc {
+    fn name(&self) -> String {
+        format!("postgres-sink-{}", self.table_name)
+    }
+
+    fn tables(&self) -> HashMap<String, TableConfig> {
+        if self.is_committing() {
+            PreCommitBuffer::tables()
+        } else {
+            HashMap::new()
+        }
+    }
+
+    fn is_committing(&self) -> bool {
+        self.commit_mode == CommitMode::ExactlyOnce
+    }
+
+    fn tick_interval(&self) -> Option<Duration> {
+        Some(FLUSH_INTERVAL)
+    }
+
+    async fn on_start(&mut self, ctx: &mut OperatorContext) -> DataflowResult<()> {
+        let schema = &ctx
+            .in_schemas
+            .first()
+            .expect("no in-schema for Postgres sink!")
+            .schema;
+
+        self.updating_meta_index = schema.index_of(UPDATING_META_FIELD).ok();
+        if self.updating_meta_index.is_some() && self.write_mode != WriteMode::Upsert {
+            return Err(connector_err!(
+                User,
+                NoRetry,
+                "Postgres sinks written to by updating queries must have a PRIMARY KEY, which retractions are deleted by"
+            ));
+        }
+
+        let mut names = vec![];
+        for (i, f) in schema.fields().iter().enumerate() {
+            if f.name() != TIMESTAMP_FIELD && f.name() != UPDATING_META_FIELD {
+                names.push(f.name().clone());
+                self.columns.push((i, Type::UNKNOWN));
+            }
+        }
+
+        self.key_indices = self
+            .key_columns
+            .iter()
+            .map(|k| {
+                names.iter().position(|n| n == k).ok_or_else(|| {
+                    connector_err!(
+                        User,
+                        NoRetry,
+                        "key column '{}' is not a column of the Postgres sink",
+                        k
+                    )
+                })
+            })
+            .collect::<Result<_, _>>()?;
+
+        let client = connect(&self.config).await.map_err(
+            |e| connector_err!(External, WithBackoff, source: e, "failed to connect to Postgres"),
+        )?;
+        self.client = Some(client);
+
+        self.prepare(&names).await?;
+
+        if self.is_committing() {
+            self.pre_commits.restore(ctx).await;
+            self.roll_back_stale(&ctx.task_info).await?;
+
+            let commit_client = connect(&self.config).await.map_err(
+                |e| connector_err!(External, WithBackoff, source: e, "failed to connect to Postgres"),
+            )?;
+            self.commit_client = Some(commit_client);
+
+            self.execute("BEGIN").await?;
+        }
+
+        Ok(())
+    }
+
+    async fn process_batch(
+        &mut self,
+        batch: RecordBatch,
+        ctx: &mut OperatorContext,
+        _: &mut dyn Collector,
+    ) -> DataflowResult<()> {
+        self.buffered_rows += batch.num_rows();
+        self.buffer.push(batch);
+
+        if self.buffered_rows >= self.batch_size {
+            self.flush(ctx.in_schemas[0].schema.clone()).await?;
+        }
+        Ok(())
+    }
+
+    async fn handle_tick(
+        &mut self,
+        _: u64,
+        ctx: &mut OperatorContext,
+        _: &mut dyn Collector,
+    ) -> DataflowResult<()> {
+        self.flush(ctx.in_schemas[0].schema.clone()).await
+    }
+
+    async fn handle_checkpoint(
+        &mut self,
+        barrier: CheckpointBarrier,
+        ctx: &mut OperatorContext,
+        _: &mut dyn Collector,
+    ) -> DataflowResult<()> {
+        self.flush(ctx.in_schemas[0].schema.clone()).await?;
+
+        if self.is_committing() {
+            let gid = Self::gid(&ctx.task_info, barrier.epoch);
+            self.execute(&format!("PREPARE TRANSACTION {}", quote_literal(&gid)))
+                .await?;
+
+            self.pre_commits.push([gid.into_bytes()]);
+            self.pre_commits.checkpoint(barrier.epoch, ctx).await;
+
+            self.execute("BEGIN").await?;
+        }
+
+        Ok(())
+    }
+
+    async fn handle_commit(
+        &mut self,
+        epoch: u32,
+        _commit_data: &HashMap<String, HashMap<u32, Vec<u8>>>,
+        ctx: &mut OperatorContext,
+    ) -> DataflowResult<()> {
+        if !self.is_committing() {
+            warn!("received commit but consistency mode is not exactly once");
+            return Ok(());
+        }
+
+        let commit_client = self
+            .commit_client
+            .as_ref()
+            .expect("Postgres sink is not connected");
+
+        for pre_commit in self.pre_commits.take_committable(epoch) {
+            for gid in pre_commit.messages {
+                let gid = String::from_utf8_lossy(&gid);
+                let query = format!("COMMIT PREPARED {}", quote_literal(&gid));
+                match commit_client.batch_execute(&query).await {
+                    Ok(()) => {}
+                    // a transaction restored from state may have been committed before we
+                    // restarted
+                    Err(e) if e.code() == Some(&SqlState::UNDEFINED_OBJECT) => {
+                        warn!(
+                            "prepared transaction {} no longer exists; assuming it was committed",
+                            gid
+                        );
+                    }
+                    Err(e) => {
+                        return Err(db_err(
+                            &format!("failed to commit prepared transaction {gid}"),
+                            e,
+                        ));
+                    }
+                }
+            }
+        }
+
+        PreCommitBuffer::finish_commit(epoch, ctx).await;
+        Ok(())
+    }
+
+    async fn on_close(
+        &mut self,
+        _: &Option<SignalMessage>,
+        ctx: &mut OperatorContext,
+        _: &mut dyn Collector,
+    ) -> DataflowResult<()> {
+        self.flush(ctx.in_schemas[0].schema.clone()).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::DummyCollector;
+    use arrow::array::{
+        Date32Array, Int32Array, Int64Array, StringArray, TimestampNanosecondArray,
+    };
+    use arrow::datatypes::{Field, Fields, Schema};
+    use arroyo_rpc::df::ArroyoSchema;
+    use arroyo_rpc::grpc::rpc::{CheckpointMetadata, OperatorCheckpointMetadata, OperatorMetadata};
+    use arroyo_rpc::{CheckpointCompleted, ControlResp};
+    use arroyo_state::tables::ErasedTable;
+    use arroyo_state::tables::global_keyed_map::GlobalKeyedTable;
+    use arroyo_state::{BackingStore, StateBackend};
+    use rand::random;
+    use std::sync::Arc;
+    use std::time::SystemTime;
+    use tokio::sync::mpsc::{Receiver, channel};
+
+    fn sink(write_mode: &str) -> PostgresSinkFunc {
+        let config = serde_json::from_value(serde_json::json!({
+            "host": "localhost",
+            "database": "postgres",
+        }))
+        .unwrap();
+        let table = serde_json::from_value(serde_json::json!({
+            "tableName": "t",
+            "writeMode": write_mode,
+            "keyColumns": ["id"],
+        }))
+        .unwrap();
+
+        let mut sink = PostgresSinkFunc::new(config, table).unwrap();
+        sink.columns = vec![(0, Type::INT8), (1, Type::TEXT)];
+        sink.key_indices = vec![0];
+        sink
+    }
+
+    fn updating_batch(rows: &[(i64, &str, bool)]) -> RecordBatch {
+        let meta_fields = Fields::from(vec![Field::new("is_retract", DataType::Boolean, false)]);
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int64, false),
+            Field::new("value", DataType::Utf8, false),
+            Field::new(
+                UPDATING_META_FIELD,
+                DataType::Struct(meta_fields.clone()),
+                false,
+            ),
+        ]));
+
+        let meta = StructArray::new(
+            meta_fields,
+            vec![Arc::new(BooleanArray::from_iter(
+                rows.iter().map(|(_, _, r)| Some(*r)),
+            ))],
+            None,
+        );
+
+        RecordBatch::try_new(
+            schema,
+            vec![
+                Arc::new(Int64Array::from_iter_values(
+                    rows.iter().map(|(k, _, _)| *k),
+                )),
+                Arc::new(StringArray::from_iter_values(
+                    rows.iter().map(|(_, v, _)| *v),
+                )),
+                Arc::new(meta),
+            ],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_plan_writes_collapses_keys() {
+        let mut sink = sink("upsert");
+        sink.updating_meta_index = Some(2);
+
+        let batch = updating_batch(&[
+            (1, "a", false),
+            (2, "b", false),
+            (1, "a", true),
+            (1, "c", false),
+            (2, "b", true),
+            (3, "d", false),
+            (3, "d", true),
+        ]);
+
+        // key 1 ends with the upsert at 3, while keys 2 and 3 end with retractions
+        assert_eq!(sink.plan_writes(&batch).unwrap(), (vec![3], vec![4, 6]));
+    }
+
+    #[test]
+    fn test_plan_writes_append() {
+        let sink = sink("append");
+        let batch = updating_batch(&[(1, "a", false), (1, "b", false)]);
+
+        assert_eq!(sink.plan_writes(&batch).unwrap(), (vec![0, 1], vec![]));
+    }
+
+    fn param(array: ArrayRef, pg_type: &Type) -> String {
+        format!("{:?}", column_param(&array, pg_type).unwrap())
+    }
+
+    #[test]
+    fn test_column_param_types() {
+        assert_eq!(
+            param(Arc::new(Int32Array::from(vec![Some(1), None])), &Type::INT8),
+            "[Some(1), None]"
+        );
+        assert_eq!(
+            param(Arc::new(Int64Array::from(vec![7])), &Type::INT2),
+            "[Some(7)]"
+        );
+        assert_eq!(
+            param(Arc::new(StringArray::from(vec!["1.50"])), &Type::NUMERIC),
+            "[Some(1.50)]"
+        );
+        assert_eq!(
+            param(
+                Arc::new(StringArray::from(vec![Some("{\"a\":1}"), None])),
+                &Type::JSONB
+            ),
+            "[Some(Object {\"a\": Number(1)}), None]"
+        );
+        assert_eq!(
+            param(
+                Arc::new(StringArray::from(vec![
+                    "67e55044-10b1-426f-9247-bb680e5fe0c8"
+                ])),
+                &Type::UUID
+            ),
+            "[Some(67e55044-10b1-426f-9247-bb680e5fe0c8)]"
+        );
+
+        let timestamps: ArrayRef = Arc::new(TimestampNanosecondArray::from(vec![
+            1_700_000_000_000_000_000,
+        ]));
+        assert_eq!(
+            param(timestamps.clone(), &Type::TIMESTAMP),
+            "[Some(2023-11-14T22:13:20)]"
+        );
+        assert_eq!(
+            param(timestamps, &Type::TIMESTAMPTZ),
+            "[Some(2023-11-14T22:13:20Z)]"
+        );
+
+        assert_eq!(
+            param(Arc::new(Date32Array::from(vec![19675])), &Type::DATE),
+            "[Some(2023-11-14)]"
+        );
+    }
+
+    #[test]
+    fn test_column_param_invalid_values() {
+        assert!(
+            column_param(
+                &(Arc::new(StringArray::from(vec!["x"])) as ArrayRef),
+                &Type::NUMERIC
+            )
+            .is_err()
+        );
+        assert!(
+            column_param(
+                &(Arc::new(StringArray::from(vec!["{"])) as ArrayRef),
+                &Type::JSON
+            )
+            .is_err()
+        );
+        assert!(
+            column_param(
+                &(Arc::new(Int64Array::from(vec![1])) as ArrayRef),
+                &Type::INTERVAL
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_owns_gid() {
+        let prefix = "arroyo-job-op-";
+
+        // scaled down from 3 subtasks to 2: subtask 0 takes over the transactions of 0 and 2
+        assert!(owns_gid("arroyo-job-op-0-5", prefix, 0, 2));
+        assert!(owns_gid("arroyo-job-op-2-5", prefix, 0, 2));
+        assert!(!owns_gid("arroyo-job-op-1-5", prefix, 0, 2));
+        assert!(owns_gid("arroyo-job-op-1-5", prefix, 1, 2));
+
+        // other operators and jobs are left alone
+        assert!(!owns_gid("arroyo-job-other-0-5", prefix, 0, 2));
+        assert!(!owns_gid("arroyo-job-op-x-5", prefix, 0, 2));
+        assert!(!owns_gid("arroyo-job-op-0-0-5", prefix, 0, 2));
+    }
+
+    /// A Postgres server with prepared transactions enabled, at the host in `POSTGRES_TEST_HOST`;
+    /// the tests that need one are skipped when that isn't set
+    fn test_config() -> Option<PostgresConfig> {
+        let host = std::env::var("POSTGRES_TEST_HOST").ok()?;
+        Some(
+            serde_json::from_value(serde_json::json!({
+                "host": host,
+                "database": "arroyo",
+                "username": "arroyo",
+                "password": "arroyo",
+                "sslMode": "disable",
+            }))
+            .unwrap(),
+        )
+    }
+
+    fn input_schema() -> Arc<ArroyoSchema> {
+        Arc::new(ArroyoSchema::from_fields(vec![
+            Field::new("id", DataType::Int64, false),
+            Field::new("value", DataType::Utf8, false),
+        ]))
+    }
+
+    fn batch(rows: &[(i64, &str)]) -> RecordBatch {
+        RecordBatch::try_new(
+            input_schema().schema.clone(),
+            vec![
+                Arc::new(Int64Array::from_iter_values(rows.iter().map(|(k, _)| *k))),
+                Arc::new(StringArray::from_iter_values(rows.iter().map(|(_, v)| *v))),
+                Arc::new(TimestampNanosecondArray::from_iter_values(
+                    rows.iter().map(|_| 0),
+                )),
+            ],
+        )
+        .unwrap()
+    }
+
+    fn barrier(epoch: u32) -> CheckpointBarrier {
+        CheckpointBarrier {
+            epoch,
+            min_epoch: 0,
+            timestamp: SystemTime::now(),
+            then_stop: false,
+            unaligned: false,
+        }
+    }
+
+    struct SinkWithWrites {
+        sink: PostgresSinkFunc,
+        ctx: OperatorContext,
+        control_rx: Receiver<ControlResp>,
+    }
+
+    impl SinkWithWrites {
+        async fn start(
+            config: &PostgresConfig,
+            table_name: &str,
+            task_info: &TaskInfo,
+            restore_from: Option<u32>,
+        ) -> Self {
+            let table = serde_json::from_value(serde_json::json!({
+                "tableName": table_name,
+                "tableType": "sink",
+                "writeMode": "upsert",
+                "keyColumns": ["id"],
+                "commitMode": "exactly_once",
+            }))
+            .unwrap();
+            let mut sink = PostgresSinkFunc::new(config.clone(), table).unwrap();
+
+            let checkpoint_metadata = restore_from.map(|epoch| CheckpointMetadata {
+                job_id: task_info.job_id.clone(),
+                epoch,
+                min_epoch: 1,
+                start_time: 0,
+                finish_time: 0,
+                operator_ids: vec![task_info.operator_id.clone()],
+            });
+
+            let (control_tx, control_rx) = channel(128);
+            let mut ctx = OperatorContext::new(
+                Arc::new(task_info.clone()),
+                checkpoint_metadata.as_ref(),
+                control_tx,
+                1,
+                vec![input_schema()],
+                None,
+                sink.tables(),
+            )
+            .await;
+
+            sink.on_start(&mut ctx).await.unwrap();
+
+            Self {
+                sink,
+                ctx,
+                control_rx,
+            }
+        }
+
+        async fn write(&mut self, rows: &[(i64, &str)]) {
+            self.sink
+                .process_batch(batch(rows), &mut self.ctx, &mut DummyCollector {})
+                .await
+                .unwrap();
+        }
+
+        /// Takes a checkpoint and writes it to the state backend, as the controller would once
+        /// every subtask had finished it
+        async fn checkpoint(&mut self, epoch: u32) {
+            self.sink
+                .handle_checkpoint(barrier(epoch), &mut self.ctx, &mut DummyCollector {})
+                .await
+                .unwrap();
+            self.ctx
+                .table_manager
+                .checkpoint(barrier(epoch), None)
+                .await;
+
+            let completed: CheckpointCompleted = loop {
+                if let ControlResp::CheckpointCompleted(c) = self.control_rx.recv().await.unwrap() {
+                    break c;
+                }
+            };
+
+            let metadata = completed.subtask_metadata;
+            let table_metadata = GlobalKeyedTable::merge_checkpoint_metadata(
+                metadata.table_configs["p"].clone(),
+                single_item_hash_map(0u32, metadata.table_metadata["p"].clone()),
+            )
+            .unwrap()
+            .unwrap();
+
+            let task_info = &self.ctx.task_info;
+            StateBackend::write_operator_checkpoint_metadata(OperatorCheckpointMetadata {
+                start_time: 0,
+                finish_time: 0,
+                table_checkpoint_metadata: single_item_hash_map("p", table_metadata),
+                table_configs: metadata.table_configs,
+                operator_metadata: Some(OperatorMetadata {
+                    job_id: task_info.job_id.clone(),
+                    operator_id: task_info.operator_id.clone(),
+                    epoch,
+                    min_watermark: None,
+                    max_watermark: None,
+                    parallelism: 1,
+                }),
+            })
+            .await
+            .unwrap();
+
+            StateBackend::write_checkpoint_metadata(CheckpointMetadata {
+                job_id: task_info.job_id.clone(),
+                epoch,
+                min_epoch: 1,
+                start_time: 0,
+                finish_time: 0,
+                operator_ids: vec![task_info.operator_id.clone()],
+            })
+            .await
+            .unwrap();
+        }
+
+        async fn commit(&mut self, epoch: u32) {
+            self.sink
+                .handle_commit(epoch, &HashMap::new(), &mut self.ctx)
+                .await
+                .unwrap();
+        }
+    }
+
+    async fn rows(client: &Client, table_name: &str) -> Vec<(i64, String)> {
+        client
+            .query(
+                &format!("SELECT id, value FROM {table_name} ORDER BY id"),
+                &[],
+            )
+            .await
+            .unwrap()
+            .iter()
+            .map(|r| (r.get(0), r.get(1)))
+            .collect()
+    }
+
+    fn expected(rows: &[(i64, &str)]) -> Vec<(i64, String)> {
+        rows.iter().map(|(k, v)| (*k, v.to_string())).collect()
+    }
+
+    #[tokio::test]
+    async fn test_exactly_once_checkpoint_commit_restore() {
+        let Some(config) = test_config() else {
+            return;
+        };
+
+        let table_name = format!("arroyo_sink_test_{}", random::<u32>());
+        let client = connect(&config).await.unwrap();
+        client
+            .batch_execute(&format!(
+                "CREATE TABLE {table_name} (id BIGINT PRIMARY KEY, value TEXT NOT NULL)"
+            ))
+            .await
+            .unwrap();
+
+        let mut task_info = get_test_task_info();
+        task_info.job_id = format!("postgres-job-{}", random::<u64>());
+        task_info.operator_id = "postgres_sink".to_string();
+
+        let mut sink = SinkWithWrites::start(&config, &table_name, &task_info, None).await;
+
+        // nothing is visible until the checkpoint commits
+        sink.write(&[(1, "a"), (2, "b")]).await;
+        sink.checkpoint(1).await;
+        assert!(rows(&client, &table_name).await.is_empty());
+
+        // while the next checkpoint's transaction is already open
+        sink.write(&[(2, "c")]).await;
+        sink.commit(1).await;
+        assert_eq!(
+            rows(&client, &table_name).await,
+            expected(&[(1, "a"), (2, "b")])
+        );
+
+        sink.write(&[(3, "d")]).await;
+        sink.checkpoint(2).await;
+        sink.write(&[(4, "e")]).await;
+
+        // fail before checkpoint 2 is committed, losing the writes made since
+        drop(sink);
+
+        let mut sink = SinkWithWrites::start(&config, &table_name, &task_info, Some(2)).await;
+        assert_eq!(
+            rows(&client, &table_name).await,
+            expected(&[(1, "a"), (2, "b")])
+        );
+
+        sink.commit(2).await;
+        assert_eq!(
+            rows(&client, &table_name).await,
+            expected(&[(1, "a"), (2, "c"), (3, "d")])
+        );
+
+        let prefix = PostgresSinkFunc::gid_prefix(&task_info);
+        let prepared = client
+            .query(
+                "SELECT gid FROM pg_prepared_xacts WHERE starts_with(gid, $1)",
+                &[&prefix],
+            )
+            .await
+            .unwrap();
+        assert!(prepared.is_empty(), "left prepared transactions behind");
+
+        drop(sink);
+        client
+            .batch_execute(&format!("DROP TABLE {table_name}"))
+            .await
+            .unwrap();
+    }
+}
@@ -0,0 +1,266 @@
+use std::collections::HashMap;
+use std::time::SystemTime;
+
+use arroyo_operator::context::OperatorContext;
+use arroyo_rpc::grpc::rpc::{GlobalKeyedTableConfig, TableConfig, TableEnum};
+use arroyo_rpc::{CheckpointEvent, ControlResp};
+use arroyo_state::tables::global_keyed_map::GlobalKeyedView;
+use arroyo_types::single_item_hash_map;
+use bincode::{Decode, Encode};
+use prost::Message;
+
+/// Messages from a single checkpoint of an exactly-once sink subtask, which are published once
+/// that checkpoint commits
+#[derive(Clone, Debug, Encode, Decode, PartialEq)]
+pub(crate) struct PreCommit {
+    pub epoch: u32,
+    pub subtask: u32,
+    pub messages: Vec<Vec<u8>>,
+}
+
+/// Holds back the messages of a sink that can't write transactionally until the checkpoint they
+/// were received before commits, storing them in the sink's state in between
+#[derive(Debug, Default)]
+pub(crate) struct PreCommitBuffer {
+    /// messages received since the last checkpoint
+    pending: Vec<Vec<u8>>,
+    /// checkpointed messages waiting for their epoch to commit
+    to_commit: Vec<PreCommit>,
+    /// messages restored from the checkpoint we started from, which still need to be published
+    /// if that checkpoint was interrupted while committing
+    restored: Vec<PreCommit>,
+}
+
+impl PreCommitBuffer {
+    pub fn tables() -> HashMap<String, TableConfig> {
+        single_item_hash_map(
+            "p".to_string(),
+            TableConfig {
+                table_type: TableEnum::GlobalKeyValue.into(),
+                config: GlobalKeyedTableConfig {
+                    table_name: "p".to_string(),
+                    description: "messages waiting for commit".to_string(),
+                    uses_two_phase_commit: true,
+                }
+                .encode_to_vec(),
+                state_version: 0,
+            },
+        )
+    }
+
+    pub fn push(&mut self, messages: impl IntoIterator<Item = Vec<u8>>) {
+        self.pending.extend(messages);
+    }
+
+    /// Restores the pre-commits of the subtasks that this one takes over from the checkpoint
+    /// we're starting from
+    pub async fn restore(&mut self, ctx: &mut OperatorContext) {
+        let state: &mut GlobalKeyedView<u32, Vec<PreCommit>> = ctx
+            .table_manager
+            .get_global_keyed_state("p")
+            .await
+            .expect("should be able to get table");
+
+        self.restore_from(
+            state.get_all(),
+            ctx.task_info.task_index,
+            ctx.task_info.parallelism,
+        );
+    }
+
+    fn restore_from(
+        &mut self,
+        state: &HashMap<u32, Vec<PreCommit>>,
+        task_index: u32,
+        parallelism: u32,
+    ) {
+        self.restored = state
+            .iter()
+            .filter(|(subtask, _)| **subtask % parallelism == task_index)
+            .flat_map(|(_, p)| p.iter().cloned())
+            .collect();
+    }
+
+    /// Moves the messages received since the last checkpoint into a pre-commit for this epoch,
+    /// and writes all uncommitted pre-commits to state
+    pub async fn checkpoint(&mut self, epoch: u32, ctx: &mut OperatorContext) {
+        self.checkpoint_pending(epoch, ctx.task_info.task_index);
+
+        let state: &mut GlobalKeyedView<u32, Vec<PreCommit>> = ctx
+            .table_manager
+            .get_global_keyed_state("p")
+            .await
+            .expect("should be able to get table");
+        state
+            .insert(ctx.task_info.task_index, self.uncommitted())
+            .await;
+    }
+
+    /// The pre-commits that haven't been published yet, including any restored ones whose commit
+    /// we haven't been sent yet; those need to survive a checkpoint taken in the meantime, or
+    /// they'd be lost if we fail again before committing them
+    fn uncommitted(&self) -> Vec<PreCommit> {
+        self.restored
+            .iter()
+            .chain(&self.to_commit)
+            .cloned()
+            .collect()
+    }
+
+    /// The pre-commits restored from the checkpoint we started from that haven't been committed
+    /// yet
+    pub fn restored(&self) -> &[PreCommit] {
+        &self.restored
+    }
+
+    fn checkpoint_pending(&mut self, epoch: u32, subtask: u32) {
+        self.to_commit.push(PreCommit {
+            epoch,
+            subtask,
+            messages: std::mem::take(&mut self.pending),
+        });
+    }
+
+    /// Takes the pre-commits that are ready to be published now that `epoch` has committed
+    pub fn take_committable(&mut self, epoch: u32) -> Vec<PreCommit> {
+        // the restored checkpoint always holds a pre-commit for its own epoch, along with those of
+        // any earlier epochs that hadn't committed when it was taken. If it was interrupted while
+        // committing, its commit is the first one we're sent, and all of those need publishing;
+        // otherwise that commit (and so, as commits are in order, the earlier ones) completed
+        // before the restart
+        let restored = std::mem::take(&mut self.restored);
+        let mut to_commit: Vec<_> = if restored.iter().map(|p| p.epoch).max() == Some(epoch) {
+            restored
+        } else {
+            vec![]
+        };
+
+        for pre_commit in std::mem::take(&mut self.to_commit) {
+            if pre_commit.epoch > epoch {
+                // not yet ready to be committed
+                self.to_commit.push(pre_commit);
+            } else {
+                to_commit.push(pre_commit);
+            }
+        }
+
+        to_commit.sort_by_key(|p| (p.epoch, p.subtask));
+        to_commit
+    }
+
+    /// Reports to the controller that this subtask has finished committing `epoch`
+    pub async fn finish_commit(epoch: u32, ctx: &mut OperatorContext) {
+        let checkpoint_event = ControlResp::CheckpointEvent(CheckpointEvent {
+            checkpoint_epoch: epoch,
+            node_id: ctx.task_info.node_id,
+            operator_id: ctx.task_info.operator_id.clone(),
+            subtask_index: ctx.task_info.task_index,
+            time: SystemTime::now(),
+            event_type: arroyo_rpc::grpc::rpc::TaskCheckpointEventType::FinishedCommit,
+        });
+        ctx.control_tx
+            .send(checkpoint_event)
+            .await
+            .expect("sent commit event");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pre_commit(epoch: u32, subtask: u32, message: &str) -> PreCommit {
+        PreCommit {
+            epoch,
+            subtask,
+            messages: vec![message.as_bytes().to_vec()],
+        }
+    }
+
+    fn epochs(pre_commits: &[PreCommit]) -> Vec<(u32, u32)> {
+        pre_commits.iter().map(|p| (p.epoch, p.subtask)).collect()
+    }
+
+    #[test]
+    fn test_commit_in_epoch_order() {
+        let mut buffer = PreCommitBuffer::default();
+        buffer.push([b"a".to_vec()]);
+        buffer.checkpoint_pending(1, 0);
+        buffer.push([b"b".to_vec()]);
+        buffer.checkpoint_pending(2, 0);
+        buffer.push([b"c".to_vec()]);
+
+        let committed = buffer.take_committable(1);
+        assert_eq!(epochs(&committed), vec![(1, 0)]);
+        assert_eq!(committed[0].messages, vec![b"a".to_vec()]);
+
+        buffer.checkpoint_pending(3, 0);
+        assert_eq!(epochs(&buffer.take_committable(3)), vec![(2, 0), (3, 0)]);
+        assert!(buffer.take_committable(4).is_empty());
+    }
+
+    #[test]
+    fn test_restore_interrupted_commit() {
+        // checkpoint 5 was taken while 4 was still waiting to commit, and neither committed
+        let state = HashMap::from([
+            (0, vec![pre_commit(4, 0, "a"), pre_commit(5, 0, "b")]),
+            (1, vec![pre_commit(5, 1, "c")]),
+            (2, vec![pre_commit(5, 2, "d")]),
+        ]);
+
+        // scaled down from 3 subtasks to 2
+        let mut buffer = PreCommitBuffer::default();
+        buffer.restore_from(&state, 0, 2);
+
+        assert_eq!(
+            epochs(&buffer.take_committable(5)),
+            vec![(4, 0), (5, 0), (5, 2)]
+        );
+
+        // restored messages are only published once
+        buffer.checkpoint_pending(6, 0);
+        assert_eq!(epochs(&buffer.take_committable(6)), vec![(6, 0)]);
+    }
+
+    #[test]
+    fn test_restore_completed_commit() {
+        let state = HashMap::from([(0, vec![pre_commit(4, 0, "a"), pre_commit(5, 0, "b")])]);
+
+        let mut buffer = PreCommitBuffer::default();
+        buffer.restore_from(&state, 0, 1);
+
+        // the first commit we're sent is for a later checkpoint, so 5 had already committed
+        buffer.checkpoint_pending(6, 0);
+        assert_eq!(epochs(&buffer.take_committable(6)), vec![(6, 0)]);
+    }
+
+    #[test]
+    fn test_restore_checkpoint_restore() {
+        // checkpoint 5 was interrupted while committing
+        let state = HashMap::from([
+            (0, vec![pre_commit(4, 0, "a"), pre_commit(5, 0, "b")]),
+            (1, vec![pre_commit(5, 1, "c")]),
+        ]);
+
+        // scaled down to a single subtask, which checkpoints before it's sent the commit for 5
+        let mut buffer = PreCommitBuffer::default();
+        buffer.restore_from(&state, 0, 1);
+        buffer.push([b"d".to_vec()]);
+        buffer.checkpoint_pending(6, 0);
+
+        let state = HashMap::from([(0, buffer.uncommitted())]);
+        assert_eq!(epochs(&state[&0]), vec![(4, 0), (5, 0), (5, 1), (6, 0)]);
+
+        // and then fails again, so we restore from 6, whose commit we're sent first
+        let mut buffer = PreCommitBuffer::default();
+        buffer.restore_from(&state, 0, 1);
+
+        let committed = buffer.take_committable(6);
+        assert_eq!(epochs(&committed), vec![(4, 0), (5, 0), (5, 1), (6, 0)]);
+        assert_eq!(committed[3].messages, vec![b"d".to_vec()]);
+
+        // once committed, they're no longer written to state
+        buffer.checkpoint_pending(7, 0);
+        assert_eq!(epochs(&buffer.uncommitted()), vec![(7, 0)]);
+    }
+}
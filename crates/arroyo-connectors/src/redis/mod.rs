@@ -1,8 +1,10 @@
 pub mod lookup;
 pub mod sink;
+pub mod source;
 
 use crate::redis::lookup::RedisLookup;
 use crate::redis::sink::{GeneralConnection, RedisSinkFunc};
+use crate::redis::source::{DEFAULT_VALUE_FIELD, RedisStreamSourceFunc};
 use anyhow::{anyhow, bail};
 use arrow::datatypes::{DataType, Schema};
 use arroyo_formats::de::ArrowDeserializer;
@@ -19,6 +21,7 @@ use redis::aio::ConnectionManager;
 use redis::cluster::ClusterClient;
 use redis::{Client, ConnectionInfo, IntoConnectionInfo};
 use serde::{Deserialize, Serialize};
+use std::num::NonZeroU32;
 use std::sync::Arc;
 use tokio::sync::oneshot::Receiver;
 use typify::import_types;
@@ -163,9 +166,9 @@ impl Connector for RedisConnector {
             id: "redis".to_string(),
             name: "Redis".to_string(),
             icon: ICON.to_string(),
-            description: "Write results to Redis".to_string(),
+            description: "Read from Redis Streams and write results to Redis".to_string(),
             enabled: true,
-            source: false,
+            source: true,
             sink: true,
             testing: false,
             hidden: false,
@@ -176,14 +179,28 @@ impl Connector for RedisConnector {
     }
 
     fn metadata_defs(&self) -> &'static [MetadataDef] {
-        &[MetadataDef {
-            name: "key",
-            data_type: DataType::Utf8,
-        }]
+        &[
+            MetadataDef {
+                name: "key",
+                data_type: DataType::Utf8,
+            },
+            MetadataDef {
+                name: "id",
+                data_type: DataType::Utf8,
+            },
+        ]
+    }
+
+    fn table_type(&self, _: Self::ProfileT, table: Self::TableT) -> ConnectionType {
+        match table.connector_type {
+            TableType::Source { .. } => ConnectionType::Source,
+            TableType::Sink { .. } => ConnectionType::Sink,
+            TableType::Lookup { .. } => ConnectionType::Lookup,
+        }
     }
 
-    fn table_type(&self, _: Self::ProfileT, _: Self::TableT) -> ConnectionType {
-        ConnectionType::Source
+    fn accepts_updating_input(&self, _: Self::ProfileT, table: Self::TableT) -> bool {
+        matches!(table.connector_type, TableType::Sink { .. })
     }
 
     fn get_schema(
@@ -299,6 +316,23 @@ impl Connector for RedisConnector {
         }
 
         let sink = match typ.as_str() {
+            "source" => TableType::Source {
+                source: StreamSource {
+                    stream_key: options.pull_str("source.stream_key")?,
+                    consumer_group: options.pull_opt_str("source.consumer_group")?,
+                    start_position: match options.pull_opt_str("source.start")?.as_deref() {
+                        Some("latest") | None => Some(StartPosition::Latest),
+                        Some("earliest") => Some(StartPosition::Earliest),
+                        Some(s) => {
+                            bail!(
+                                "'{}' is not a valid value for source.start; must be one of 'earliest' or 'latest'",
+                                s
+                            );
+                        }
+                    },
+                    value_field: options.pull_opt_str("source.value_field")?,
+                },
+            },
             "lookup" => {
                 // for look-up tables, we require that there's a primary key metadata field
                 for f in &schema.fields {
@@ -312,6 +346,14 @@ impl Connector for RedisConnector {
                     }
                 }
 
+                if schema
+                    .fields
+                    .iter()
+                    .any(|f| f.metadata_key.as_deref() == Some("id"))
+                {
+                    bail!("the 'id' metadata field is only available for Redis stream sources");
+                }
+
                 TableType::Lookup {
                     lookup: Default::default(),
                 }
@@ -364,6 +406,19 @@ impl Connector for RedisConnector {
                             .transpose()?,
                         hash_key_prefix: options.pull_str("target.key_prefix")?,
                     },
+                    "stream" => Target::StreamTable {
+                        stream_prefix: options.pull_str("target.key_prefix")?,
+                        stream_key_column: options
+                            .pull_opt_str("target.key_column")?
+                            .map(|name| validate_column(schema, name, "target.key_column"))
+                            .transpose()?,
+                        stream_max_length: options
+                            .pull_opt_u64("target.max_length")?
+                            .map(|t| t.try_into())
+                            .transpose()
+                            .map_err(|_| anyhow!("target.max_length must be greater than 0"))?,
+                        stream_value_field: options.pull_opt_str("target.value_field")?,
+                    },
                     s => {
                         bail!("'{}' is not a valid redis target", s);
                     }
@@ -372,7 +427,10 @@ impl Connector for RedisConnector {
                 TableType::Sink { target }
             }
             s => {
-                bail!("'{}' is not a valid type; must be `sink`", s);
+                bail!(
+                    "'{}' is not a valid type; must be one of `source`, `sink`, or `lookup`",
+                    s
+                );
             }
         };
 
@@ -408,6 +466,7 @@ impl Connector for RedisConnector {
         let _ = RedisClient::new(&config)?;
 
         let (connection_type, description) = match &table.connector_type {
+            TableType::Source { .. } => (ConnectionType::Source, "RedisStreamSource"),
             TableType::Sink { .. } => (ConnectionType::Sink, "RedisSink"),
             TableType::Lookup { .. } => (ConnectionType::Lookup, "RedisLookup"),
         };
@@ -420,6 +479,7 @@ impl Connector for RedisConnector {
             bad_data: schema.bad_data.clone(),
             framing: schema.framing.clone(),
             metadata_fields: schema.metadata_fields(),
+            partition_watermarks: None,
         };
 
         Ok(Connection::new(
@@ -442,6 +502,30 @@ impl Connector for RedisConnector {
         let client = RedisClient::new(&profile)?;
 
         match table.connector_type {
+            TableType::Source { source } => Ok(ConstructedOperator::from_source(Box::new(
+                RedisStreamSourceFunc {
+                    client,
+                    stream_key: source.stream_key,
+                    consumer_group: source.consumer_group,
+                    start_position: source.start_position.unwrap_or(StartPosition::Latest),
+                    value_field: source
+                        .value_field
+                        .unwrap_or_else(|| DEFAULT_VALUE_FIELD.to_string()),
+                    format: config
+                        .format
+                        .ok_or_else(|| anyhow!("format is required for redis source"))?,
+                    framing: config.framing,
+                    bad_data: config.bad_data,
+                    messages_per_second: NonZeroU32::new(
+                        config
+                            .rate_limit
+                            .map(|l| l.messages_per_second)
+                            .unwrap_or(u32::MAX),
+                    )
+                    .unwrap(),
+                    metadata_fields: config.metadata_fields,
+                },
+            ))),
             TableType::Sink { target } => {
                 let (tx, cmd_rx) = tokio::sync::mpsc::channel(128);
                 let (cmd_tx, rx) = tokio::sync::mpsc::channel(128);
@@ -458,6 +542,7 @@ impl Connector for RedisConnector {
                         rx,
                         key_index: None,
                         hash_index: None,
+                        updating_meta_index: None,
                     },
                 )))
             }
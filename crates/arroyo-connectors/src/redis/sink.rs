@@ -1,13 +1,16 @@
+use crate::redis::source::DEFAULT_VALUE_FIELD;
 use crate::redis::{ListOperation, RedisClient, Target};
-use arrow::array::{AsArray, RecordBatch};
+use arrow::array::{AsArray, BooleanArray, RecordBatch, StructArray};
 use arroyo_formats::ser::ArrowSerializer;
 use arroyo_operator::context::{Collector, ErrorReporter, OperatorContext};
 use arroyo_operator::operator::ArrowOperator;
 use arroyo_rpc::errors::DataflowResult;
+use arroyo_rpc::{UPDATING_META_FIELD, connector_err};
 use arroyo_types::CheckpointBarrier;
 use async_trait::async_trait;
 use redis::aio::{ConnectionLike, ConnectionManager};
 use redis::cluster_async::ClusterConnection;
+use redis::streams::StreamMaxlen;
 use redis::{Cmd, Pipeline, RedisFuture};
 use std::collections::HashSet;
 use std::time::{Duration, Instant};
@@ -29,6 +32,8 @@ pub struct RedisSinkFunc {
 
     pub key_index: Option<usize>,
     pub hash_index: Option<usize>,
+    // set when the input is an updating stream, in which case retractions delete their values
+    pub updating_meta_index: Option<usize>,
 }
 
 impl RedisSinkFunc {
@@ -41,13 +46,24 @@ impl RedisSinkFunc {
 
         key
     }
+
+    fn retracts(&self, batch: &RecordBatch) -> Option<BooleanArray> {
+        let meta: &StructArray = batch.column(self.updating_meta_index?).as_struct();
+        Some(
+            meta.column_by_name("is_retract")
+                .expect("meta struct must have is_retract")
+                .as_boolean()
+                .clone(),
+        )
+    }
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Clone, Debug)]
 enum RedisBehavior {
     Set { ttl: Option<usize> },
     Push { append: bool, max: Option<usize> },
     Hash,
+    Stream { max: Option<usize>, field: String },
 }
 
 pub enum RedisCmd {
@@ -62,6 +78,18 @@ pub enum RedisCmd {
         value: Vec<u8>,
     },
 
+    // removes a value previously written with `Data`
+    Del {
+        key: String,
+        value: Vec<u8>,
+    },
+
+    // removes a field previously written with `HData`
+    HDel {
+        key: String,
+        field: String,
+    },
+
     Flush(u32),
 }
 
@@ -133,11 +161,11 @@ impl RedisWriter {
                             Some(RedisCmd::Data { key, value }) => {
                                 self.size_estimate += key.len() + value.len();
 
-                                match self.behavior {
-                                    RedisBehavior::Set {ttl } => {
+                                match &self.behavior {
+                                    RedisBehavior::Set { ttl } => {
                                         // TODO: resolve duplicates before sending
                                         if let Some(ttl) = ttl {
-                                            self.pipeline.set_ex(key, value, ttl as u64);
+                                            self.pipeline.set_ex(key, value, *ttl as u64);
                                         } else {
                                             self.pipeline.set(key, value);
                                         }
@@ -147,7 +175,7 @@ impl RedisWriter {
                                             self.max_push_keys.insert(key.clone());
                                         }
 
-                                        if append {
+                                        if *append {
                                             self.pipeline.rpush(key, value);
                                         } else {
                                             self.pipeline.lpush(key, value);
@@ -156,6 +184,21 @@ impl RedisWriter {
                                     RedisBehavior::Hash => {
                                         unreachable!();
                                     }
+                                    RedisBehavior::Stream { max, field } => {
+                                        match max {
+                                            Some(max) => {
+                                                self.pipeline.xadd_maxlen(
+                                                    key,
+                                                    StreamMaxlen::Approx(*max),
+                                                    "*",
+                                                    &[(field, value)],
+                                                );
+                                            }
+                                            None => {
+                                                self.pipeline.xadd(key, "*", &[(field, value)]);
+                                            }
+                                        }
+                                    }
                                 }
                             }
                             Some(RedisCmd::HData { key, field, value }) => {
@@ -163,6 +206,28 @@ impl RedisWriter {
 
                                 self.pipeline.hset(key, field, value);
                             }
+                            Some(RedisCmd::Del { key, value }) => {
+                                self.size_estimate += key.len() + value.len();
+
+                                match &self.behavior {
+                                    RedisBehavior::Set { .. } => {
+                                        self.pipeline.del(key);
+                                    }
+                                    RedisBehavior::Push { append, .. } => {
+                                        // remove the oldest matching element, which is nearest
+                                        // the head of appended lists and the tail of prepended ones
+                                        self.pipeline.lrem(key, if *append { 1 } else { -1 }, value);
+                                    }
+                                    RedisBehavior::Hash | RedisBehavior::Stream { .. } => {
+                                        unreachable!();
+                                    }
+                                }
+                            }
+                            Some(RedisCmd::HDel { key, field }) => {
+                                self.size_estimate += key.len() + field.len();
+
+                                self.pipeline.hdel(key, field);
+                            }
                             Some(RedisCmd::Flush(i)) => {
                                 self.flush().await;
                                 if self.tx.send(i).await.is_err() {
@@ -242,6 +307,10 @@ impl ArrowOperator for RedisSinkFunc {
             | Target::HashTable {
                 hash_key_column: Some(key),
                 ..
+            }
+            | Target::StreamTable {
+                stream_key_column: Some(key),
+                ..
             } => {
                 self.key_index = Some(
                     ctx.in_schemas
@@ -269,6 +338,22 @@ impl ArrowOperator for RedisSinkFunc {
                 .unwrap_or_else(|_| panic!("hash field column ({hash_field_column}) does not exist in input schema for redis sink")));
         }
 
+        self.updating_meta_index = ctx
+            .in_schemas
+            .first()
+            .expect("no in-schema for redis sink!")
+            .schema
+            .index_of(UPDATING_META_FIELD)
+            .ok();
+
+        if self.updating_meta_index.is_some() && matches!(self.target, Target::StreamTable { .. }) {
+            return Err(connector_err!(
+                User,
+                NoRetry,
+                "Redis stream targets are append-only, and can't be written to by updating queries"
+            ));
+        }
+
         let mut attempts = 0;
         while attempts < 20 {
             match self.client.get_connection().await {
@@ -303,6 +388,16 @@ impl ArrowOperator for RedisSinkFunc {
                                 }
                             }
                             Target::HashTable { .. } => RedisBehavior::Hash,
+                            Target::StreamTable {
+                                stream_max_length,
+                                stream_value_field,
+                                ..
+                            } => RedisBehavior::Stream {
+                                max: stream_max_length.map(|x| x.get() as usize),
+                                field: stream_value_field
+                                    .clone()
+                                    .unwrap_or_else(|| DEFAULT_VALUE_FIELD.to_string()),
+                            },
                         },
                     }
                     .start();
@@ -326,18 +421,32 @@ impl ArrowOperator for RedisSinkFunc {
         _: &mut OperatorContext,
         _: &mut dyn Collector,
     ) -> DataflowResult<()> {
+        let retracts = self.retracts(&batch);
+
         for (i, value) in self.serializer.serialize(&batch).enumerate() {
+            let is_retract = retracts.as_ref().is_some_and(|r| r.value(i));
+
             match &self.target {
                 Target::StringTable { key_prefix, .. } => {
                     let key = self.make_key(key_prefix, &batch, i);
-                    self.tx
-                        .send(RedisCmd::Data { key, value })
-                        .await
-                        .expect("Redis writer panicked");
+                    let cmd = if is_retract {
+                        RedisCmd::Del { key, value }
+                    } else {
+                        RedisCmd::Data { key, value }
+                    };
+                    self.tx.send(cmd).await.expect("Redis writer panicked");
                 }
                 Target::ListTable { list_prefix, .. } => {
                     let key = self.make_key(list_prefix, &batch, i);
-
+                    let cmd = if is_retract {
+                        RedisCmd::Del { key, value }
+                    } else {
+                        RedisCmd::Data { key, value }
+                    };
+                    self.tx.send(cmd).await.expect("Redis writer panicked");
+                }
+                Target::StreamTable { stream_prefix, .. } => {
+                    let key = self.make_key(stream_prefix, &batch, i);
                     self.tx
                         .send(RedisCmd::Data { key, value })
                         .await
@@ -352,11 +461,12 @@ impl ArrowOperator for RedisSinkFunc {
                         .as_string::<i32>()
                         .value(i)
                         .to_string();
-
-                    self.tx
-                        .send(RedisCmd::HData { key, field, value })
-                        .await
-                        .expect("Redis writer panicked");
+                    let cmd = if is_retract {
+                        RedisCmd::HDel { key, field }
+                    } else {
+                        RedisCmd::HData { key, field, value }
+                    };
+                    self.tx.send(cmd).await.expect("Redis writer panicked");
                 }
             };
         }
@@ -0,0 +1,544 @@
+use crate::redis::sink::GeneralConnection;
+use crate::redis::{RedisClient, StartPosition};
+use arroyo_formats::de::FieldValueType;
+use arroyo_operator::SourceFinishType;
+use arroyo_operator::context::{SourceCollector, SourceContext};
+use arroyo_operator::operator::SourceOperator;
+use arroyo_rpc::errors::DataflowResult;
+use arroyo_rpc::formats::{BadData, Format, Framing};
+use arroyo_rpc::grpc::rpc::{StopMode, TableConfig};
+use arroyo_rpc::{ControlMessage, MetadataField, connector_err};
+use arroyo_types::from_millis;
+use async_trait::async_trait;
+use bincode::{Decode, Encode};
+use governor::{Quota, RateLimiter as GovernorRateLimiter};
+use redis::AsyncCommands;
+use redis::streams::{StreamId, StreamInfoConsumersReply, StreamReadOptions, StreamReadReply};
+use std::collections::{HashMap, VecDeque};
+use std::num::NonZeroU32;
+use std::time::SystemTime;
+use tracing::{debug, info};
+
+// how long each read waits for new entries before we check for control messages
+const BLOCK_MS: usize = 100;
+const READ_COUNT: usize = 1000;
+
+pub const DEFAULT_VALUE_FIELD: &str = "value";
+
+#[derive(Clone, Debug, Encode, Decode, PartialEq)]
+pub struct RedisStreamState {
+    // the ID of the last entry this subtask read before the checkpoint
+    last_id: String,
+}
+
+/// Reads a Redis Stream with XREADGROUP, as one consumer of a consumer group per subtask.
+///
+/// Entries stay in the consumer's pending entries list until a checkpoint including them has
+/// completed, and are acknowledged with XACK when the next checkpoint starts (as that only
+/// happens once the previous one has completed). On restore, pending entries after the
+/// checkpointed ID are read again, and those before it (which were checkpointed but not yet
+/// acknowledged) are acknowledged.
+///
+/// After a scale-down, each subtask also recovers the pending entries of the consumers of the
+/// removed subtasks whose state it has taken over, reading them as that consumer so that they're
+/// compared against that consumer's checkpointed ID.
+pub struct RedisStreamSourceFunc {
+    pub(crate) client: RedisClient,
+    pub stream_key: String,
+    pub consumer_group: Option<String>,
+    pub start_position: StartPosition,
+    pub value_field: String,
+    pub format: Format,
+    pub framing: Option<Framing>,
+    pub bad_data: Option<BadData>,
+    pub messages_per_second: NonZeroU32,
+    pub metadata_fields: Vec<MetadataField>,
+}
+
+/// Parses a stream entry ID (`<millis>-<sequence>`) into a comparable tuple
+fn parse_id(id: &str) -> Option<(u64, u64)> {
+    let (millis, seq) = id.split_once('-')?;
+    Some((millis.parse().ok()?, seq.parse().ok()?))
+}
+
+/// If `consumer` belongs to a subtask of this operator that no longer exists, and whose state
+/// has been restored by subtask `task_index`, returns that subtask's index
+fn removed_subtask(consumer: &str, prefix: &str, task_index: u32, parallelism: u32) -> Option<u32> {
+    let subtask: u32 = consumer.strip_prefix(prefix)?.parse().ok()?;
+    (subtask >= parallelism && subtask % parallelism == task_index).then_some(subtask)
+}
+
+/// The pending entries list of a consumer that's being read back after a restore
+struct Recovery {
+    consumer: String,
+    subtask: u32,
+    /// the ID of the last entry of the consumer that was included in the restored checkpoint
+    checkpointed_id: Option<(u64, u64)>,
+    next_id: String,
+}
+
+/// Tracks the IDs of entries that have been read until the checkpoint including them completes
+#[derive(Debug, Default)]
+struct Acks {
+    /// entries read since the last checkpoint
+    unacked: Vec<String>,
+    /// entries included in the last checkpoint, which may not have completed yet
+    checkpointed: Vec<String>,
+}
+
+impl Acks {
+    fn read(&mut self, id: String) {
+        self.unacked.push(id);
+    }
+
+    /// Called when a checkpoint starts, returning the entries that can now be acknowledged: as
+    /// checkpoints only start once the previous one has completed, those are the ones that
+    /// were included in the previous checkpoint
+    fn checkpoint(&mut self) -> Vec<String> {
+        std::mem::replace(&mut self.checkpointed, std::mem::take(&mut self.unacked))
+    }
+}
+
+#[async_trait]
+impl SourceOperator for RedisStreamSourceFunc {
+    fn name(&self) -> String {
+        format!("redis-stream-{}", self.stream_key)
+    }
+
+    fn tables(&self) -> HashMap<String, TableConfig> {
+        arroyo_state::global_table_config("r", "redis stream source state")
+    }
+
+    async fn run(
+        &mut self,
+        ctx: &mut SourceContext,
+        collector: &mut SourceCollector,
+    ) -> DataflowResult<SourceFinishType> {
+        self.run_int(ctx, collector).await
+    }
+}
+
+impl RedisStreamSourceFunc {
+    async fn create_group(
+        &self,
+        connection: &mut GeneralConnection,
+        group: &str,
+    ) -> DataflowResult<()> {
+        let start = match self.start_position {
+            StartPosition::Earliest => "0",
+            StartPosition::Latest => "$",
+        };
+
+        match connection
+            .xgroup_create_mkstream::<_, _, _, ()>(&self.stream_key, group, start)
+            .await
+        {
+            Ok(()) => {
+                info!(
+                    "created consumer group '{}' for stream '{}'",
+                    group, self.stream_key
+                );
+                Ok(())
+            }
+            // the group already exists, so we'll continue from its last-delivered ID
+            Err(e) if e.code() == Some("BUSYGROUP") => Ok(()),
+            Err(e) => Err(connector_err!(
+                External,
+                WithBackoff,
+                "failed to create consumer group '{}' for Redis stream '{}': {}",
+                group,
+                self.stream_key,
+                e
+            )),
+        }
+    }
+
+    async fn read(
+        &self,
+        connection: &mut GeneralConnection,
+        options: &StreamReadOptions,
+        id: &str,
+    ) -> DataflowResult<Vec<StreamId>> {
+        let reply: Option<StreamReadReply> = connection
+            .xread_options(&[&self.stream_key], &[id], options)
+            .await
+            .map_err(|e| {
+                connector_err!(
+                    External,
+                    WithBackoff,
+                    "failed to read from Redis stream '{}': {}",
+                    self.stream_key,
+                    e
+                )
+            })?;
+
+        Ok(reply
+            .into_iter()
+            .flat_map(|r| r.keys)
+            .flat_map(|k| k.ids)
+            .collect())
+    }
+
+    /// Finds the consumers of subtasks that were removed by a scale-down that this subtask is now
+    /// responsible for
+    async fn removed_consumers(
+        &self,
+        connection: &mut GeneralConnection,
+        group: &str,
+        prefix: &str,
+        ctx: &SourceContext,
+    ) -> DataflowResult<Vec<(String, u32)>> {
+        let reply: StreamInfoConsumersReply = connection
+            .xinfo_consumers(&self.stream_key, group)
+            .await
+            .map_err(|e| {
+                connector_err!(
+                    External,
+                    WithBackoff,
+                    "failed to list consumers of group '{}' of Redis stream '{}': {}",
+                    group,
+                    self.stream_key,
+                    e
+                )
+            })?;
+
+        let mut consumers: Vec<_> = reply
+            .consumers
+            .into_iter()
+            .filter_map(|c| {
+                let subtask = removed_subtask(
+                    &c.name,
+                    prefix,
+                    ctx.task_info.task_index,
+                    ctx.task_info.parallelism,
+                )?;
+                Some((c.name, subtask))
+            })
+            .collect();
+        consumers.sort_by_key(|(_, subtask)| *subtask);
+        Ok(consumers)
+    }
+
+    async fn delete_consumer(
+        &self,
+        connection: &mut GeneralConnection,
+        group: &str,
+        consumer: &str,
+    ) -> DataflowResult<()> {
+        connection
+            .xgroup_delconsumer::<_, _, _, ()>(&self.stream_key, group, consumer)
+            .await
+            .map_err(|e| {
+                connector_err!(
+                    External,
+                    WithBackoff,
+                    "failed to delete consumer '{}' of Redis stream '{}': {}",
+                    consumer,
+                    self.stream_key,
+                    e
+                )
+            })
+    }
+
+    async fn ack(
+        &self,
+        connection: &mut GeneralConnection,
+        group: &str,
+        ids: &[String],
+    ) -> DataflowResult<()> {
+        for chunk in ids.chunks(READ_COUNT) {
+            connection
+                .xack::<_, _, _, ()>(&self.stream_key, group, chunk)
+                .await
+                .map_err(|e| {
+                    connector_err!(
+                        External,
+                        WithBackoff,
+                        "failed to acknowledge entries of Redis stream '{}': {}",
+                        self.stream_key,
+                        e
+                    )
+                })?;
+        }
+        Ok(())
+    }
+
+    async fn collect_entry(
+        &self,
+        entry: &StreamId,
+        ctx: &mut SourceContext,
+        collector: &mut SourceCollector,
+    ) -> DataflowResult<()> {
+        let Some(value) = entry.get::<Vec<u8>>(&self.value_field) else {
+            ctx.report_nonfatal_error(connector_err!(
+                User,
+                NoRetry,
+                "entry {} of Redis stream '{}' has no field '{}'",
+                entry.id,
+                self.stream_key,
+                self.value_field
+            ))
+            .await;
+            return Ok(());
+        };
+
+        // entry IDs are prefixed by the time they were added, in milliseconds
+        let timestamp = parse_id(&entry.id)
+            .map(|(millis, _)| from_millis(millis))
+            .unwrap_or_else(SystemTime::now);
+
+        let connector_metadata = if !self.metadata_fields.is_empty() {
+            let mut connector_metadata = HashMap::new();
+            for f in &self.metadata_fields {
+                connector_metadata.insert(
+                    f.field_name.as_str(),
+                    match f.key.as_str() {
+                        "key" => FieldValueType::String(Some(&self.stream_key)),
+                        "id" => FieldValueType::String(Some(&entry.id)),
+                        k => unreachable!("invalid metadata key '{}' for redis", k),
+                    },
+                );
+            }
+            Some(connector_metadata)
+        } else {
+            None
+        };
+
+        collector
+            .deserialize_slice(&value, timestamp, connector_metadata.as_ref())
+            .await
+    }
+
+    async fn run_int(
+        &mut self,
+        ctx: &mut SourceContext,
+        collector: &mut SourceCollector,
+    ) -> DataflowResult<SourceFinishType> {
+        collector.initialize_deserializer(
+            self.format.clone(),
+            self.framing.clone(),
+            self.bad_data.clone(),
+            &self.metadata_fields,
+        );
+
+        let group = self.consumer_group.clone().unwrap_or_else(|| {
+            format!(
+                "arroyo-{}-{}",
+                ctx.task_info.job_id, ctx.task_info.operator_id
+            )
+        });
+        // consumer names are stable across restarts, so that we can recover our pending entries
+        let consumer_prefix = format!(
+            "arroyo-{}-{}-",
+            ctx.task_info.job_id, ctx.task_info.operator_id
+        );
+        let task_index = ctx.task_info.task_index;
+        let consumer = format!("{consumer_prefix}{task_index}");
+
+        let mut connection = self.client.get_connection().await.map_err(|e| {
+            connector_err!(External, WithBackoff, "failed to connect to Redis: {}", e)
+        })?;
+
+        self.create_group(&mut connection, &group).await?;
+
+        let removed = self
+            .removed_consumers(&mut connection, &group, &consumer_prefix, ctx)
+            .await?;
+
+        let state: HashMap<u32, RedisStreamState> = ctx
+            .table_manager
+            .get_global_keyed_state::<u32, RedisStreamState>("r")
+            .await?
+            .get_all()
+            .clone();
+        let restored_id = |subtask: u32| state.get(&subtask).and_then(|s| parse_id(&s.last_id));
+
+        // while recovering, we read the pending entries lists of our consumer and of any removed
+        // consumers we've taken over from the start, then switch to new entries (`>`) once
+        // they're exhausted
+        let mut recovering: VecDeque<_> = std::iter::once((consumer.clone(), task_index))
+            .chain(removed)
+            .map(|(consumer, subtask)| Recovery {
+                consumer,
+                subtask,
+                checkpointed_id: restored_id(subtask),
+                next_id: "0".to_string(),
+            })
+            .collect();
+
+        // the last entry read by each consumer whose entries we're reading, by subtask
+        let mut last_ids: HashMap<u32, String> = HashMap::new();
+        let mut acks = Acks::default();
+
+        let new_options = StreamReadOptions::default()
+            .group(&group, &consumer)
+            .count(READ_COUNT)
+            .block(BLOCK_MS);
+
+        let rate_limiter = GovernorRateLimiter::direct(Quota::per_second(self.messages_per_second));
+
+        loop {
+            let (subtask, checkpointed_id, entries) = match recovering.front_mut() {
+                Some(recovery) => {
+                    let options = StreamReadOptions::default()
+                        .group(&group, &recovery.consumer)
+                        .count(READ_COUNT);
+                    let entries = self
+                        .read(&mut connection, &options, &recovery.next_id)
+                        .await?;
+
+                    let read = (recovery.subtask, recovery.checkpointed_id, entries);
+                    match read.2.last() {
+                        Some(last) => recovery.next_id = last.id.clone(),
+                        None => {
+                            info!(
+                                "recovered pending entries for consumer '{}' of stream '{}'",
+                                recovery.consumer, self.stream_key
+                            );
+                            if recovery.subtask != task_index
+                                && !last_ids.contains_key(&recovery.subtask)
+                            {
+                                // the removed consumer had nothing left to read
+                                self.delete_consumer(&mut connection, &group, &recovery.consumer)
+                                    .await?;
+                            }
+                            recovering.pop_front();
+                        }
+                    }
+                    read
+                }
+                None => (
+                    task_index,
+                    None,
+                    self.read(&mut connection, &new_options, ">").await?,
+                ),
+            };
+
+            let mut already_checkpointed = vec![];
+            for entry in entries {
+                if let (Some(checkpointed), Some(id)) = (checkpointed_id, parse_id(&entry.id))
+                    && id <= checkpointed
+                {
+                    already_checkpointed.push(entry.id);
+                    continue;
+                }
+
+                self.collect_entry(&entry, ctx, collector).await?;
+                rate_limiter.until_ready().await;
+
+                last_ids.insert(subtask, entry.id.clone());
+                acks.read(entry.id);
+            }
+
+            if !already_checkpointed.is_empty() {
+                self.ack(&mut connection, &group, &already_checkpointed)
+                    .await?;
+            }
+
+            if collector.should_flush() {
+                collector.flush_buffer().await?;
+            }
+
+            match ctx.control_rx.try_recv() {
+                Ok(ControlMessage::Checkpoint(c)) => {
+                    debug!("starting checkpointing {}", ctx.task_info.task_index);
+                    let state = ctx.table_manager.get_global_keyed_state("r").await?;
+                    for (subtask, last_id) in &last_ids {
+                        state
+                            .insert(
+                                *subtask,
+                                RedisStreamState {
+                                    last_id: last_id.clone(),
+                                },
+                            )
+                            .await;
+                    }
+
+                    // the previous checkpoint has completed, as otherwise this one wouldn't have
+                    // started, so the entries it covers won't be needed again
+                    let completed = acks.checkpoint();
+
+                    if self.start_checkpoint(c, ctx, collector).await {
+                        return Ok(SourceFinishType::Immediate);
+                    }
+
+                    self.ack(&mut connection, &group, &completed).await?;
+                }
+                Ok(ControlMessage::Stop { mode }) => {
+                    info!("Stopping Redis stream source: {:?}", mode);
+
+                    match mode {
+                        StopMode::Graceful => {
+                            return Ok(SourceFinishType::Graceful);
+                        }
+                        StopMode::Immediate => {
+                            return Ok(SourceFinishType::Immediate);
+                        }
+                    }
+                }
+                Ok(ControlMessage::Commit { .. }) => {
+                    unreachable!("sources shouldn't receive commit messages");
+                }
+                Ok(ControlMessage::LoadCompacted { compacted }) => {
+                    ctx.load_compacted(compacted).await;
+                }
+                Ok(ControlMessage::NoOp) => {}
+                Err(_) => {
+                    // no messages
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_id() {
+        assert_eq!(parse_id("1526919030474-55"), Some((1526919030474, 55)));
+        assert_eq!(parse_id("0-0"), Some((0, 0)));
+        assert_eq!(parse_id("1526919030474"), None);
+        assert_eq!(parse_id("a-1"), None);
+
+        // sequence numbers compare numerically, not lexically
+        assert!(parse_id("5-10") > parse_id("5-9"));
+    }
+
+    #[test]
+    fn test_removed_subtask() {
+        let prefix = "arroyo-job-op-";
+
+        // scaled down from 5 subtasks to 2
+        assert_eq!(removed_subtask("arroyo-job-op-2", prefix, 0, 2), Some(2));
+        assert_eq!(removed_subtask("arroyo-job-op-4", prefix, 0, 2), Some(4));
+        assert_eq!(removed_subtask("arroyo-job-op-3", prefix, 1, 2), Some(3));
+        assert_eq!(removed_subtask("arroyo-job-op-3", prefix, 0, 2), None);
+
+        // consumers of subtasks that still exist are left to them
+        assert_eq!(removed_subtask("arroyo-job-op-0", prefix, 0, 2), None);
+        assert_eq!(removed_subtask("arroyo-job-op-1", prefix, 1, 2), None);
+
+        // as are those of other operators and other clients of the group
+        assert_eq!(removed_subtask("arroyo-job-other-2", prefix, 0, 2), None);
+        assert_eq!(removed_subtask("some-consumer", prefix, 0, 2), None);
+    }
+
+    #[test]
+    fn test_ack_after_checkpoint_completes() {
+        let mut acks = Acks::default();
+        acks.read("1-0".to_string());
+        acks.read("2-0".to_string());
+
+        // nothing has been checkpointed when the first checkpoint starts
+        assert!(acks.checkpoint().is_empty());
+
+        acks.read("3-0".to_string());
+
+        // the first checkpoint completed before the second started
+        assert_eq!(acks.checkpoint(), vec!["1-0", "2-0"]);
+        assert_eq!(acks.checkpoint(), vec!["3-0"]);
+        assert!(acks.checkpoint().is_empty());
+    }
+}
@@ -103,6 +103,7 @@ impl Connector for SingleFileConnector {
             bad_data: schema.bad_data.clone(),
             framing: schema.framing.clone(),
             metadata_fields: vec![],
+            partition_watermarks: None,
         };
 
         Ok(Connection::new(
@@ -106,6 +106,7 @@ impl Connector for StdoutConnector {
             bad_data: schema.bad_data.clone(),
             framing: schema.framing.clone(),
             metadata_fields: vec![],
+            partition_watermarks: None,
         };
 
         Ok(Connection::new(
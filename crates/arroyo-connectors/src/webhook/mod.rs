@@ -1,9 +1,10 @@
 mod operator;
 
 use std::sync::Arc;
-use std::time::SystemTime;
+use std::time::{Duration, SystemTime};
 
-use anyhow::anyhow;
+use anyhow::{anyhow, bail};
+use arroyo_rpc::formats::Format;
 use arroyo_rpc::{ConnectorOptions, OperatorConfig};
 
 use arroyo_formats::ser::ArrowSerializer;
@@ -21,7 +22,7 @@ use typify::import_types;
 
 use crate::{EmptyConfig, construct_http_client};
 
-use crate::webhook::operator::WebhookSinkFunc;
+use crate::webhook::operator::{BodyTemplate, Signer, WebhookSinkFunc};
 use arroyo_operator::connector::Connector;
 use arroyo_operator::operator::ConstructedOperator;
 
@@ -30,7 +31,9 @@ const TABLE_SCHEMA: &str = include_str!("./table.json");
 import_types!(schema = "src/webhook/table.json", convert = { {type = "string", format = "var-str"} = VarStr });
 const ICON: &str = include_str!("./webhook.svg");
 
-const MAX_INFLIGHT: u32 = 50;
+const DEFAULT_MAX_INFLIGHT: u32 = 50;
+const DEFAULT_BATCH_TIMEOUT: Duration = Duration::from_secs(1);
+const DEFAULT_SIGNATURE_HEADER: &str = "X-Signature-256";
 
 pub struct WebhookConnector {}
 
@@ -154,6 +157,23 @@ impl Connector for WebhookConnector {
             .map(|t| t.to_owned())
             .ok_or_else(|| anyhow!("'format' must be set for webhook connection"))?;
 
+        if !matches!(format, Format::Json(_)) {
+            if table.body_template.is_some() {
+                bail!("'body_template' requires the JSON format");
+            }
+            if table.batch_size.is_some_and(|n| n.get() > 1)
+                && !matches!(table.batch_format, Some(BatchFormat::Ndjson))
+            {
+                bail!(
+                    "batching into a JSON array requires the JSON format; use batch_format = 'ndjson'"
+                );
+            }
+        }
+
+        if table.signature_header.is_some() && table.signing_secret.is_none() {
+            bail!("'signature_header' requires 'signing_secret' to be set");
+        }
+
         let config = OperatorConfig {
             connection: serde_json::to_value(config).unwrap(),
             table: serde_json::to_value(table).unwrap(),
@@ -162,6 +182,7 @@ impl Connector for WebhookConnector {
             bad_data: schema.bad_data.clone(),
             framing: schema.framing.clone(),
             metadata_fields: schema.metadata_fields(),
+            partition_watermarks: None,
         };
 
         Ok(Connection::new(
@@ -186,9 +207,34 @@ impl Connector for WebhookConnector {
 
         let headers = options.pull_opt_str("headers")?.map(VarStr::new);
 
+        let batch_format: Option<BatchFormat> = options
+            .pull_opt_str("batch_format")?
+            .map(|s| s.try_into())
+            .transpose()
+            .map_err(|_| anyhow!("invalid value for 'batch_format'"))?;
+
         let table = WebhookTable {
             endpoint: VarStr::new(endpoint),
             headers,
+            batch_size: options
+                .pull_opt_u64("batch_size")?
+                .map(|t| t.try_into())
+                .transpose()
+                .map_err(|_| anyhow!("batch_size must be greater than 0"))?,
+            batch_timeout_ms: options
+                .pull_opt_u64("batch_timeout_ms")?
+                .map(|t| t.try_into())
+                .transpose()
+                .map_err(|_| anyhow!("batch_timeout_ms must be greater than 0"))?,
+            batch_format,
+            max_inflight: options
+                .pull_opt_u64("max_inflight")?
+                .map(|t| t.try_into())
+                .transpose()
+                .map_err(|_| anyhow!("max_inflight must be greater than 0"))?,
+            body_template: options.pull_opt_str("body_template")?,
+            signing_secret: options.pull_opt_str("signing_secret")?.map(VarStr::new),
+            signature_header: options.pull_opt_str("signature_header")?,
         };
 
         let client = construct_http_client(
@@ -211,6 +257,22 @@ impl Connector for WebhookConnector {
         config: OperatorConfig,
     ) -> anyhow::Result<ConstructedOperator> {
         let url = table.endpoint.sub_env_vars()?;
+        let max_inflight = table
+            .max_inflight
+            .map(|n| n.get() as u32)
+            .unwrap_or(DEFAULT_MAX_INFLIGHT);
+        let signer = table
+            .signing_secret
+            .map(|secret| -> anyhow::Result<Signer> {
+                Ok(Signer {
+                    secret: secret.sub_env_vars()?.into_bytes(),
+                    header: table
+                        .signature_header
+                        .unwrap_or_else(|| DEFAULT_SIGNATURE_HEADER.to_string()),
+                })
+            })
+            .transpose()?;
+
         Ok(ConstructedOperator::from_operator(Box::new(
             WebhookSinkFunc {
                 url: Arc::new(url.clone()),
@@ -222,13 +284,26 @@ impl Connector for WebhookConnector {
                         .map(|s| s.sub_env_vars())
                         .transpose()?,
                 )?,
-                semaphore: Arc::new(Semaphore::new(MAX_INFLIGHT as usize)),
+                semaphore: Arc::new(Semaphore::new(max_inflight as usize)),
+                max_inflight,
                 serializer: ArrowSerializer::new(
                     config
                         .format
                         .expect("No format configured for webhook sink"),
                 ),
                 last_reported_error_at: Arc::new(Mutex::new(SystemTime::UNIX_EPOCH)),
+                batch_size: table.batch_size.map(|n| n.get() as usize).unwrap_or(1),
+                batch_timeout: table
+                    .batch_timeout_ms
+                    .map(|n| Duration::from_millis(n.get()))
+                    .unwrap_or(DEFAULT_BATCH_TIMEOUT),
+                batch_format: table.batch_format.unwrap_or(BatchFormat::JsonArray),
+                template: table.body_template.map(BodyTemplate::new),
+                signer,
+                bad_data: config.bad_data.unwrap_or_default(),
+                failure: Arc::new(std::sync::Mutex::new(None)),
+                buffer: vec![],
+                buffer_started: None,
             },
         )))
     }
@@ -1,31 +1,304 @@
 use arrow::array::RecordBatch;
 use async_trait::async_trait;
+use hmac::{Hmac, Mac};
+use regex::Regex;
+use reqwest::StatusCode;
+use reqwest::header::RETRY_AFTER;
+use sha2::Sha256;
 use std::collections::HashMap;
+use std::time::Instant;
 use std::{
     sync::Arc,
     time::{Duration, SystemTime},
 };
 
-use arroyo_types::CheckpointBarrier;
+use arroyo_types::{CheckpointBarrier, SignalMessage};
 
 use tokio::sync::{Mutex, Semaphore};
 use tracing::warn;
 
-use crate::webhook::MAX_INFLIGHT;
+use crate::webhook::BatchFormat;
 use arroyo_formats::ser::ArrowSerializer;
 use arroyo_operator::context::{Collector, OperatorContext};
 use arroyo_operator::operator::ArrowOperator;
-use arroyo_rpc::ControlResp;
 use arroyo_rpc::errors::DataflowResult;
+use arroyo_rpc::formats::BadData;
 use arroyo_rpc::grpc::rpc::TableConfig;
+use arroyo_rpc::{ControlResp, connector_err};
 use arroyo_state::global_table_config;
 
+/// A body with `{{field}}` placeholders, which are filled in from each JSON-serialized record
+pub struct BodyTemplate {
+    template: String,
+    placeholder: Regex,
+}
+
+impl BodyTemplate {
+    pub fn new(template: String) -> Self {
+        Self {
+            template,
+            placeholder: Regex::new(r"\{\{\s*([^{}\s]+)\s*\}\}").unwrap(),
+        }
+    }
+
+    /// Renders the template for a record; fields may be nested, as in `{{a.b}}`, and missing
+    /// fields are rendered as empty
+    fn render(&self, record: &[u8]) -> Result<Vec<u8>, serde_json::Error> {
+        let record: serde_json::Value = serde_json::from_slice(record)?;
+        let rendered = self
+            .placeholder
+            .replace_all(&self.template, |c: &regex::Captures| {
+                let value = c[1]
+                    .split('.')
+                    .try_fold(&record, |value, field| value.get(field));
+
+                match value {
+                    None | Some(serde_json::Value::Null) => String::new(),
+                    Some(serde_json::Value::String(s)) => {
+                        let quoted = serde_json::to_string(s).unwrap();
+                        quoted[1..quoted.len() - 1].to_string()
+                    }
+                    Some(v) => v.to_string(),
+                }
+            });
+        Ok(rendered.into_owned().into_bytes())
+    }
+}
+
+#[derive(Clone)]
+pub struct Signer {
+    pub secret: Vec<u8>,
+    pub header: String,
+}
+
+impl Signer {
+    fn sign(&self, body: &[u8]) -> String {
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(&self.secret).expect("HMAC can take a key of any size");
+        mac.update(body);
+        let digest: String = mac
+            .finalize()
+            .into_bytes()
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect();
+        format!("sha256={digest}")
+    }
+}
+
 pub struct WebhookSinkFunc {
     pub url: Arc<String>,
     pub semaphore: Arc<Semaphore>,
+    pub max_inflight: u32,
     pub client: reqwest::Client,
     pub serializer: ArrowSerializer,
     pub last_reported_error_at: Arc<Mutex<SystemTime>>,
+    pub batch_size: usize,
+    pub batch_timeout: Duration,
+    pub batch_format: BatchFormat,
+    pub template: Option<BodyTemplate>,
+    pub signer: Option<Signer>,
+    pub bad_data: BadData,
+    /// set by a request task when a rejected request should fail the sink
+    pub failure: Arc<std::sync::Mutex<Option<String>>>,
+    pub buffer: Vec<Vec<u8>>,
+    pub buffer_started: Option<Instant>,
+}
+
+/// What a request task needs to send a body and report on its progress
+#[derive(Clone)]
+struct RequestContext {
+    client: reqwest::Client,
+    url: Arc<String>,
+    signer: Option<Signer>,
+    bad_data: BadData,
+    failure: Arc<std::sync::Mutex<Option<String>>>,
+    control_tx: tokio::sync::mpsc::Sender<ControlResp>,
+    error_lock: Arc<Mutex<SystemTime>>,
+    operator_id: String,
+    task_index: usize,
+    node_id: u32,
+}
+
+impl RequestContext {
+    /// Reports an error to the controller, at most once a second
+    async fn report(&self, message: String, details: String) {
+        if let Ok(mut last_reported) = self.error_lock.try_lock()
+            && last_reported.elapsed().unwrap_or_default() > Duration::from_secs(1)
+        {
+            warn!("{}: {}", message, details);
+
+            self.control_tx
+                .send(ControlResp::Error {
+                    node_id: self.node_id,
+                    operator_id: self.operator_id.clone(),
+                    task_index: self.task_index,
+                    message,
+                    details,
+                })
+                .await
+                .unwrap();
+
+            *last_reported = SystemTime::now();
+        }
+    }
+
+    async fn send(&self, body: bytes::Bytes) {
+        let signature = self.signer.as_ref().map(|s| (&s.header, s.sign(&body)));
+
+        let mut retries = 0;
+        loop {
+            let mut req = self.client.post(&*self.url).body(body.clone());
+            if let Some((header, signature)) = &signature {
+                req = req.header(header.as_str(), signature);
+            }
+            let req = req.build().expect("failed to build request");
+
+            let (details, retry_after) = match self.client.execute(req).await {
+                Ok(resp) if resp.status().is_success() => return,
+                Ok(resp)
+                    if resp.status().is_client_error()
+                        && resp.status() != StatusCode::REQUEST_TIMEOUT
+                        && resp.status() != StatusCode::TOO_MANY_REQUESTS =>
+                {
+                    // the server won't accept this request no matter how many times we send it
+                    let status = resp.status().as_u16();
+                    let body = resp.text().await.unwrap_or_default();
+                    let details = format!("server rejected request with {status}: {body}");
+                    match self.bad_data {
+                        BadData::Drop {} => {
+                            self.report("webhook request dropped".to_string(), details)
+                                .await;
+                        }
+                        BadData::Fail {} => {
+                            self.failure.lock().unwrap().get_or_insert(details);
+                        }
+                    }
+                    return;
+                }
+                Ok(resp) => {
+                    let retry_after = resp
+                        .headers()
+                        .get(RETRY_AFTER)
+                        .and_then(|v| v.to_str().ok())
+                        .and_then(|v| v.trim().parse().ok())
+                        .map(Duration::from_secs);
+                    (
+                        format!(
+                            "server responded with error code: {}",
+                            resp.status().as_u16()
+                        ),
+                        retry_after,
+                    )
+                }
+                Err(e) => (e.to_string(), None),
+            };
+
+            self.report(format!("webhook failed (retry {retries})"), details)
+                .await;
+
+            retries += 1;
+
+            tokio::time::sleep(
+                retry_after.unwrap_or_else(|| {
+                    Duration::from_millis((50 * (1 << retries.min(10))).min(5_000))
+                }),
+            )
+            .await
+        }
+    }
+}
+
+impl WebhookSinkFunc {
+    fn batching(&self) -> bool {
+        self.batch_size > 1
+    }
+
+    fn request_context(&self, ctx: &OperatorContext) -> RequestContext {
+        RequestContext {
+            client: self.client.clone(),
+            url: self.url.clone(),
+            signer: self.signer.clone(),
+            bad_data: self.bad_data.clone(),
+            failure: self.failure.clone(),
+            control_tx: ctx.control_tx.clone(),
+            error_lock: self.last_reported_error_at.clone(),
+            operator_id: ctx.task_info.operator_id.clone(),
+            task_index: ctx.task_info.task_index as usize,
+            node_id: ctx.task_info.node_id,
+        }
+    }
+
+    /// Returns an error if a request was rejected and the bad data policy is to fail
+    fn check_failure(&self) -> DataflowResult<()> {
+        if let Some(details) = self.failure.lock().unwrap().take() {
+            return Err(connector_err!(
+                User,
+                NoRetry,
+                "webhook request failed: {}",
+                details
+            ));
+        }
+        Ok(())
+    }
+
+    fn batch_body(&self, records: Vec<Vec<u8>>) -> Vec<u8> {
+        match self.batch_format {
+            BatchFormat::JsonArray => {
+                let mut body = vec![b'['];
+                body.extend(records.join(&b','));
+                body.push(b']');
+                body
+            }
+            BatchFormat::Ndjson => {
+                let mut body = records.join(&b'\n');
+                body.push(b'\n');
+                body
+            }
+        }
+    }
+
+    async fn send(&self, body: Vec<u8>, ctx: &OperatorContext) {
+        let permit = self
+            .semaphore
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("websink semaphore closed");
+
+        let request = self.request_context(ctx);
+
+        tokio::task::spawn(async move {
+            // move the permit into the task
+            let _permit = permit;
+            request.send(body.into()).await;
+        });
+    }
+
+    async fn flush(&mut self, ctx: &OperatorContext) {
+        self.buffer_started = None;
+        if self.buffer.is_empty() {
+            return;
+        }
+
+        let records = std::mem::take(&mut self.buffer);
+        let body = self.batch_body(records);
+        self.send(body, ctx).await;
+    }
+
+    /// Waits for all in-flight requests to finish
+    async fn drain(&mut self, ctx: &OperatorContext) -> DataflowResult<()> {
+        self.flush(ctx).await;
+
+        // wait to acquire all of the permits (effectively blocking until all inflight requests are done)
+        let _permits = self
+            .semaphore
+            .acquire_many(self.max_inflight)
+            .await
+            .unwrap();
+
+        self.check_failure()
+    }
 }
 
 #[async_trait]
@@ -38,96 +311,274 @@ impl ArrowOperator for WebhookSinkFunc {
         global_table_config("s", "webhook sink state")
     }
 
+    fn tick_interval(&self) -> Option<Duration> {
+        // a batch may start just after a tick, so tick often enough that it isn't held for
+        // much longer than the timeout
+        self.batching()
+            .then(|| (self.batch_timeout / 10).max(Duration::from_millis(1)))
+    }
+
     async fn process_batch(
         &mut self,
         record: RecordBatch,
         ctx: &mut OperatorContext,
         _: &mut dyn Collector,
     ) -> DataflowResult<()> {
+        self.check_failure()?;
+
         for body in self.serializer.serialize(&record) {
-            let permit = self
-                .semaphore
-                .clone()
-                .acquire_owned()
-                .await
-                .expect("websink semaphore closed");
-
-            let body: bytes::Bytes = body.into();
-
-            let client = self.client.clone();
-            let control_tx = ctx.control_tx.clone();
-            let error_lock = self.last_reported_error_at.clone();
-            let url = self.url.clone();
-
-            // these are just used for (potential) error reporting and we don't need to clone them
-            let operator_id = ctx.task_info.operator_id.clone();
-            let task_index = ctx.task_info.task_index as usize;
-            let node_id = ctx.task_info.node_id;
-
-            tokio::task::spawn(async move {
-                // move the permit into the task
-                let _permit = permit;
-                let mut retries = 0;
-                loop {
-                    let req = client
-                        .post(&*url)
-                        .body(body.clone())
-                        .build()
-                        .expect("failed to build request");
-
-                    match client.execute(req).await {
-                        Ok(_) => break,
-                        Err(e) => {
-                            if let Ok(mut last_reported) = error_lock.try_lock()
-                                && last_reported.elapsed().unwrap_or_default()
-                                    > Duration::from_secs(1)
-                            {
-                                warn!("websink request failed: {:?}", e);
-
-                                let details = if let Some(status) = e.status() {
-                                    format!("server responded with error code: {}", status.as_u16())
-                                } else {
-                                    e.to_string()
-                                };
-
-                                control_tx
-                                    .send(ControlResp::Error {
-                                        node_id,
-                                        operator_id: operator_id.clone(),
-                                        task_index,
-                                        message: format!("webhook failed (retry {retries})"),
-                                        details,
-                                    })
-                                    .await
-                                    .unwrap();
-
-                                *last_reported = SystemTime::now();
-                            }
-
-                            retries += 1;
-
-                            tokio::time::sleep(Duration::from_millis(
-                                (50 * (1 << retries)).min(5_000),
-                            ))
-                            .await
-                        }
-                    }
-                }
-            });
+            let body = match &self.template {
+                Some(template) => template.render(&body).map_err(|e| {
+                    connector_err!(
+                        Internal,
+                        NoRetry,
+                        "failed to read serialized record for body template: {}",
+                        e
+                    )
+                })?,
+                None => body,
+            };
+
+            if !self.batching() {
+                self.send(body, ctx).await;
+                continue;
+            }
+
+            self.buffer.push(body);
+            self.buffer_started.get_or_insert_with(Instant::now);
+            if self.buffer.len() >= self.batch_size {
+                self.flush(ctx).await;
+            }
         }
         Ok(())
     }
 
+    async fn handle_tick(
+        &mut self,
+        _: u64,
+        ctx: &mut OperatorContext,
+        _: &mut dyn Collector,
+    ) -> DataflowResult<()> {
+        if self
+            .buffer_started
+            .is_some_and(|started| started.elapsed() >= self.batch_timeout)
+        {
+            self.flush(ctx).await;
+        }
+        self.check_failure()
+    }
+
     async fn handle_checkpoint(
         &mut self,
         _: CheckpointBarrier,
-        _ctx: &mut OperatorContext,
+        ctx: &mut OperatorContext,
         _: &mut dyn Collector,
     ) -> DataflowResult<()> {
-        // wait to acquire all of the permits (effectively blocking until all inflight requests are done)
-        let _permits = self.semaphore.acquire_many(MAX_INFLIGHT).await.unwrap();
-
         // TODO: instead of blocking checkpoints on in-progress (or failing) requests, we should store them to state
-        Ok(())
+        self.drain(ctx).await
+    }
+
+    async fn on_close(
+        &mut self,
+        _: &Option<SignalMessage>,
+        ctx: &mut OperatorContext,
+        _: &mut dyn Collector,
+    ) -> DataflowResult<()> {
+        self.drain(ctx).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arroyo_rpc::formats::{Format, JsonFormat};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tokio::sync::mpsc::{Receiver, channel};
+
+    fn sink(batch_size: usize, batch_format: BatchFormat) -> WebhookSinkFunc {
+        WebhookSinkFunc {
+            url: Arc::new("http://localhost".to_string()),
+            semaphore: Arc::new(Semaphore::new(1)),
+            max_inflight: 1,
+            client: reqwest::Client::new(),
+            serializer: ArrowSerializer::new(Format::Json(JsonFormat::default())),
+            last_reported_error_at: Arc::new(Mutex::new(SystemTime::UNIX_EPOCH)),
+            batch_size,
+            batch_timeout: Duration::from_secs(1),
+            batch_format,
+            template: None,
+            signer: None,
+            bad_data: BadData::default(),
+            failure: Arc::new(std::sync::Mutex::new(None)),
+            buffer: vec![],
+            buffer_started: None,
+        }
+    }
+
+    fn render(template: &str, record: &str) -> String {
+        String::from_utf8(
+            BodyTemplate::new(template.to_string())
+                .render(record.as_bytes())
+                .unwrap(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_render_template() {
+        let record = r#"{"id": 7, "name": "say \"hi\"", "address": {"city": "Oslo", "zip": null}, "tags": ["a"]}"#;
+
+        assert_eq!(
+            render(r#"{"id": {{id}}, "name": "{{ name }}"}"#, record),
+            r#"{"id": 7, "name": "say \"hi\""}"#
+        );
+
+        // nested fields, and values that aren't strings
+        assert_eq!(
+            render("{{address.city}} {{  tags }} {{address}}", record),
+            r#"Oslo ["a"] {"city":"Oslo","zip":null}"#
+        );
+
+        // missing and null fields are empty
+        assert_eq!(
+            render(
+                "[{{missing}}][{{address.zip}}][{{address.city.name}}]",
+                record
+            ),
+            "[][][]"
+        );
+
+        // braces that aren't placeholders are left alone
+        assert_eq!(render("{ {{id}} }", record), "{ 7 }");
+
+        assert!(
+            BodyTemplate::new("{{id}}".to_string())
+                .render(b"not json")
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_signer() {
+        // test case 2 from RFC 4231
+        let signer = Signer {
+            secret: b"Jefe".to_vec(),
+            header: "X-Signature".to_string(),
+        };
+
+        assert_eq!(
+            signer.sign(b"what do ya want for nothing?"),
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn test_batch_body() {
+        let records = || vec![br#"{"a":1}"#.to_vec(), br#"{"a":2}"#.to_vec()];
+
+        assert_eq!(
+            sink(10, BatchFormat::JsonArray).batch_body(records()),
+            br#"[{"a":1},{"a":2}]"#
+        );
+        assert_eq!(
+            sink(10, BatchFormat::Ndjson).batch_body(records()),
+            b"{\"a\":1}\n{\"a\":2}\n"
+        );
+    }
+
+    #[test]
+    fn test_tick_interval() {
+        assert_eq!(sink(1, BatchFormat::JsonArray).tick_interval(), None);
+        assert_eq!(
+            sink(10, BatchFormat::JsonArray).tick_interval(),
+            Some(Duration::from_millis(100))
+        );
+    }
+
+    /// Serves each of `statuses` in turn, repeating the last, and counts the requests it gets
+    async fn server(statuses: Vec<u16>) -> (String, Arc<AtomicUsize>) {
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = requests.clone();
+
+        let app = axum::Router::new().route(
+            "/",
+            axum::routing::post(move || {
+                let i = counter.fetch_add(1, Ordering::SeqCst);
+                let status = statuses[i.min(statuses.len() - 1)];
+                async move {
+                    (
+                        axum::http::StatusCode::from_u16(status).unwrap(),
+                        [("retry-after", "0")],
+                        "rejected",
+                    )
+                }
+            }),
+        );
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        (format!("http://{address}/"), requests)
+    }
+
+    fn request_context(url: String, bad_data: BadData) -> (RequestContext, Receiver<ControlResp>) {
+        let (control_tx, control_rx) = channel(16);
+        let request = RequestContext {
+            client: reqwest::Client::new(),
+            url: Arc::new(url),
+            signer: None,
+            bad_data,
+            failure: Arc::new(std::sync::Mutex::new(None)),
+            control_tx,
+            error_lock: Arc::new(Mutex::new(SystemTime::UNIX_EPOCH)),
+            operator_id: "webhook".to_string(),
+            task_index: 0,
+            node_id: 1,
+        };
+
+        (request, control_rx)
+    }
+
+    #[tokio::test]
+    async fn test_rejected_requests_are_dropped() {
+        let (url, requests) = server(vec![400]).await;
+        let (request, mut control_rx) = request_context(url, BadData::Drop {});
+
+        request.send("{}".into()).await;
+
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+        assert!(request.failure.lock().unwrap().is_none());
+        let Ok(ControlResp::Error {
+            message, details, ..
+        }) = control_rx.try_recv()
+        else {
+            panic!("expected the dropped request to be reported");
+        };
+        assert_eq!(message, "webhook request dropped");
+        assert!(details.contains("400"), "{details}");
+    }
+
+    #[tokio::test]
+    async fn test_rejected_requests_fail() {
+        let (url, requests) = server(vec![404]).await;
+        let (request, _control_rx) = request_context(url, BadData::Fail {});
+
+        request.send("{}".into()).await;
+
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+        let failure = request.failure.lock().unwrap().clone().unwrap();
+        assert!(failure.contains("404"), "{failure}");
+    }
+
+    #[tokio::test]
+    async fn test_timeouts_and_rate_limits_are_retried() {
+        let (url, requests) = server(vec![408, 429, 500, 200]).await;
+        let (request, _control_rx) = request_context(url, BadData::Fail {});
+
+        request.send("{}".into()).await;
+
+        assert_eq!(requests.load(Ordering::SeqCst), 4);
+        assert!(request.failure.lock().unwrap().is_none());
     }
 }
@@ -1,4 +1,4 @@
---! all_jobs : Job(ttl_micros?, state?, start_time?, finish_time?, tasks?, failure_message?, failure_domain?, run_id?, pipeline_path?, wasm_path?, ignore_state_before_epoch?)
+--! all_jobs : Job(ttl_micros?, state?, start_time?, finish_time?, tasks?, failure_message?, failure_domain?, run_id?, pipeline_path?, wasm_path?, ignore_state_before_epoch?, worker_overrides?)
 SELECT
     c.id as id,
     c.organization_id as org_id,
@@ -21,7 +21,8 @@ SELECT
     c.restart_nonce as config_restart_nonce,
     s.restart_nonce as status_restart_nonce,
     restart_mode,
-    ignore_state_before_epoch
+    ignore_state_before_epoch,
+    worker_overrides
 FROM job_configs c
 INNER JOIN job_statuses s ON c.id = s.id;
 
@@ -93,6 +94,12 @@ WHERE job_id = :job_id AND (state = 'ready' or state = 'committing')
 ORDER BY epoch DESC
 LIMIT 1;
 
+--! oldest_checkpoint_since : (epoch?)
+SELECT MIN(epoch) as epoch
+FROM checkpoints
+WHERE job_id = :job_id AND finish_time >= :since
+    AND state != 'failed' AND state != 'inprogress';
+
 --! create_job_log_message
 INSERT INTO job_log_messages (pub_id, job_id, operator_id, task_index, log_level, message, details, error_domain, retry_hint)
 VALUES (:pub_id, :job_id, :operator_id, :task_index, :log_level, :message, :details, :error_domain, :retry_hint);
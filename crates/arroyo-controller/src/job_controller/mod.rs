@@ -8,8 +8,9 @@ use std::{
 use crate::types::public::StopMode as SqlStopMode;
 use anyhow::bail;
 use arroyo_rpc::grpc::rpc::{
-    CheckpointReq, CommitReq, JobFinishedReq, LabelPair, LoadCompactedDataReq, MetricsReq,
-    StopExecutionReq, StopMode, TaskCheckpointEventType, worker_grpc_client::WorkerGrpcClient,
+    AlignWatermarksReq, CheckpointReq, CommitReq, JobFinishedReq, LabelPair, LoadCompactedDataReq,
+    MetricsReq, StopExecutionReq, StopMode, SubtaskRef, TaskCheckpointEventType,
+    worker_grpc_client::WorkerGrpcClient,
 };
 use arroyo_state::{BackingStore, StateBackend};
 use arroyo_types::{WorkerId, to_micros};
@@ -19,6 +20,7 @@ use rand::{Rng, rng};
 use time::OffsetDateTime;
 
 use crate::job_controller::job_metrics::{JobMetrics, get_metric_name};
+use crate::job_controller::watermark_alignment::WatermarkAlignment;
 use crate::types::public::CheckpointState as DbCheckpointState;
 use crate::{JobConfig, JobMessage, RunningMessage, TaskFailedEvent, queries::controller_queries};
 use arroyo_datastream::logical::LogicalProgram;
@@ -31,13 +33,24 @@ use arroyo_state::checkpoint_state::CheckpointState;
 use arroyo_state::committing_state::CommittingState;
 use arroyo_state::parquet::ParquetBackend;
 use futures::future::try_join_all;
+use lazy_static::lazy_static;
+use prometheus::{GaugeVec, register_gauge_vec};
 use tokio::{sync::mpsc::Receiver, task::JoinHandle};
 use tonic::{Request, transport::Channel};
 use tracing::{debug, error, info, warn};
 
 pub mod job_metrics;
+mod watermark_alignment;
+
+lazy_static! {
+    static ref WATERMARK_SKEW: GaugeVec = register_gauge_vec!(
+        "arroyo_controller_watermark_skew_seconds",
+        "difference between the fastest and slowest source watermarks for jobs with watermark alignment",
+        &["job_id"]
+    )
+    .unwrap();
+}
 
-const CHECKPOINTS_TO_KEEP: u32 = 4;
 const CHECKPOINT_ROWS_TO_KEEP: u32 = 100;
 const COMPACT_EVERY: u32 = 2;
 
@@ -108,6 +121,7 @@ pub struct RunningJobModel {
     metrics: JobMetrics,
     metric_update_task: Option<JoinHandle<()>>,
     last_updated_metrics: Instant,
+    watermark_alignment: WatermarkAlignment,
 
     // checkpoint-wide events
     pub checkpoint_spans: Vec<JobCheckpointSpan>,
@@ -193,6 +207,58 @@ impl RunningJobModel {
         Ok(())
     }
 
+    async fn align_watermarks(
+        &mut self,
+        node_id: u32,
+        subtask_index: u32,
+        watermark: Option<SystemTime>,
+        max_drift: Duration,
+    ) {
+        let paused = self
+            .watermark_alignment
+            .update(node_id, subtask_index, watermark, max_drift)
+            .cloned();
+
+        WATERMARK_SKEW
+            .with_label_values(&[self.job_id.as_str()])
+            .set(self.watermark_alignment.skew().as_secs_f64());
+
+        let Some(paused) = paused else {
+            return;
+        };
+
+        debug!(
+            message = "updating watermark alignment",
+            job_id = *self.job_id,
+            paused = paused.len()
+        );
+
+        let req = AlignWatermarksReq {
+            paused: paused
+                .into_iter()
+                .map(|(node_id, subtask_index)| SubtaskRef {
+                    node_id,
+                    subtask_index,
+                })
+                .collect(),
+        };
+
+        for worker in self.workers.values_mut() {
+            if let Err(e) = worker
+                .connect
+                .align_watermarks(Request::new(req.clone()))
+                .await
+            {
+                warn!(
+                    message = "Failed to send watermark alignment to worker",
+                    job_id = *self.job_id,
+                    worker_id = worker.id.0,
+                    error = format!("{:?}", e),
+                );
+            }
+        }
+    }
+
     pub async fn handle_message(
         &mut self,
         msg: RunningMessage,
@@ -312,6 +378,15 @@ impl RunningJobModel {
                     );
                 }
             }
+            RunningMessage::WatermarkReport {
+                node_id,
+                subtask_index,
+                watermark,
+                max_drift,
+            } => {
+                self.align_watermarks(node_id, subtask_index, watermark, max_drift)
+                    .await;
+            }
             RunningMessage::WorkerFinished { worker_id } => {
                 if let Some(worker) = self.workers.get_mut(&worker_id) {
                     worker.state = WorkerState::Stopped;
@@ -543,13 +618,11 @@ impl RunningJobModel {
     }
 
     pub fn cleanup_needed(&self) -> Option<u32> {
-        if self.epoch - self.min_epoch > CHECKPOINTS_TO_KEEP
-            && self.epoch.is_multiple_of(COMPACT_EVERY)
-        {
-            Some(self.epoch - CHECKPOINTS_TO_KEEP)
-        } else {
-            None
-        }
+        cleanup_epoch(
+            self.epoch,
+            self.min_epoch,
+            config().pipeline.checkpoint_retention.keep_last,
+        )
     }
 
     pub fn worker_timedout(&self) -> bool {
@@ -682,6 +755,7 @@ impl JobController {
                 metrics,
                 metric_update_task: None,
                 last_updated_metrics: Instant::now(),
+                watermark_alignment: WatermarkAlignment::default(),
                 program,
                 checkpoint_spans: vec![],
             },
@@ -959,6 +1033,32 @@ impl JobController {
         let cur_epoch = self.model.epoch;
 
         tokio::spawn(async move {
+            // don't clean up checkpoints that are still within the retention period
+            let mut new_min = new_min;
+            if let Some(keep_for) = &config().pipeline.checkpoint_retention.keep_for {
+                let since = OffsetDateTime::now_utc() - **keep_for;
+                let oldest = controller_queries::fetch_oldest_checkpoint_since(
+                    &db.client().await?,
+                    &*job_id,
+                    &since,
+                )
+                .await?
+                .into_iter()
+                .next()
+                .and_then(|r| r.epoch);
+
+                new_min = retained_min_epoch(new_min, oldest.map(|e| e as u32));
+            }
+
+            if new_min <= min_epoch {
+                debug!(
+                    message = "all checkpoints are within the retention period",
+                    job_id = *job_id,
+                    min_epoch
+                );
+                return Ok(min_epoch);
+            }
+
             let checkpoint = StateBackend::load_checkpoint_metadata(&job_id, cur_epoch).await?;
 
             controller_queries::execute_mark_compacting(
@@ -999,3 +1099,49 @@ impl JobController {
         })
     }
 }
+
+/// The epoch that checkpoints before can be cleaned up from, once a job whose oldest checkpoint
+/// is `min_epoch` has completed `epoch`, keeping the latest `keep_last`
+fn cleanup_epoch(epoch: u32, min_epoch: u32, keep_last: u32) -> Option<u32> {
+    let keep_last = keep_last.max(1);
+    (epoch - min_epoch > keep_last && epoch.is_multiple_of(COMPACT_EVERY))
+        .then(|| epoch - keep_last)
+}
+
+/// Holds back the epoch that checkpoints are cleaned up from so that those that finished within
+/// the `keep_for` retention period, the oldest of which is `oldest_retained`, are kept
+fn retained_min_epoch(new_min: u32, oldest_retained: Option<u32>) -> u32 {
+    oldest_retained.map_or(new_min, |oldest| new_min.min(oldest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cleanup_epoch_keeps_last() {
+        // nothing to clean up until there are more than `keep_last` checkpoints
+        assert_eq!(cleanup_epoch(4, 1, 4), None);
+        assert_eq!(cleanup_epoch(6, 1, 4), Some(2));
+
+        // only cleans up every COMPACT_EVERY epochs
+        assert_eq!(cleanup_epoch(7, 1, 4), None);
+        assert_eq!(cleanup_epoch(8, 1, 4), Some(4));
+
+        assert_eq!(cleanup_epoch(20, 1, 10), Some(10));
+
+        // the latest checkpoint is always kept
+        assert_eq!(cleanup_epoch(4, 1, 0), Some(3));
+    }
+
+    #[test]
+    fn test_retained_min_epoch_keeps_for() {
+        assert_eq!(retained_min_epoch(10, None), 10);
+
+        // checkpoint 7 finished within the retention period
+        assert_eq!(retained_min_epoch(10, Some(7)), 7);
+
+        // all checkpoints in the retention period are already being kept
+        assert_eq!(retained_min_epoch(10, Some(12)), 10);
+    }
+}
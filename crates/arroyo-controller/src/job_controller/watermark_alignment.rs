@@ -0,0 +1,98 @@
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, SystemTime};
+
+/// Tracks the watermarks of the watermark generators that have alignment enabled, and determines
+/// which of them have gotten so far ahead of the slowest that their sources should be paused
+#[derive(Debug, Default)]
+pub struct WatermarkAlignment {
+    // (node_id, subtask) -> (watermark, max drift); the watermark is None for idle subtasks
+    watermarks: HashMap<(u32, u32), (Option<SystemTime>, Duration)>,
+    paused: HashSet<(u32, u32)>,
+}
+
+impl WatermarkAlignment {
+    /// Records a reported watermark, returning the new set of subtasks that should be paused if
+    /// it has changed
+    pub fn update(
+        &mut self,
+        node_id: u32,
+        subtask_index: u32,
+        watermark: Option<SystemTime>,
+        max_drift: Duration,
+    ) -> Option<&HashSet<(u32, u32)>> {
+        self.watermarks
+            .insert((node_id, subtask_index), (watermark, max_drift));
+
+        let paused = match self.min_watermark() {
+            Some(min) => self
+                .watermarks
+                .iter()
+                .filter(|(_, (watermark, max_drift))| {
+                    watermark.is_some_and(|w| w > min + *max_drift)
+                })
+                .map(|(k, _)| *k)
+                .collect(),
+            None => HashSet::new(),
+        };
+
+        if paused != self.paused {
+            self.paused = paused;
+            Some(&self.paused)
+        } else {
+            None
+        }
+    }
+
+    fn min_watermark(&self) -> Option<SystemTime> {
+        self.watermarks.values().filter_map(|(w, _)| *w).min()
+    }
+
+    /// The difference between the fastest and slowest non-idle watermarks
+    pub fn skew(&self) -> Duration {
+        let watermarks = self.watermarks.values().filter_map(|(w, _)| *w);
+        match (watermarks.clone().min(), watermarks.max()) {
+            (Some(min), Some(max)) => max.duration_since(min).unwrap_or_default(),
+            _ => Duration::ZERO,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(secs: u64) -> Option<SystemTime> {
+        Some(SystemTime::UNIX_EPOCH + Duration::from_secs(secs))
+    }
+
+    #[test]
+    fn test_pauses_subtasks_ahead_of_slowest() {
+        let drift = Duration::from_secs(10);
+        let mut alignment = WatermarkAlignment::default();
+
+        assert_eq!(alignment.update(1, 0, at(100), drift), None);
+        assert_eq!(alignment.update(1, 1, at(105), drift), None);
+
+        let paused = alignment.update(1, 2, at(120), drift).unwrap();
+        assert_eq!(paused, &HashSet::from([(1, 2)]));
+        assert_eq!(alignment.skew(), Duration::from_secs(20));
+
+        // the slowest subtask catches up, so the fastest one can resume
+        let paused = alignment.update(1, 0, at(112), drift).unwrap();
+        assert!(paused.is_empty());
+    }
+
+    #[test]
+    fn test_idle_subtasks_are_ignored() {
+        let drift = Duration::from_secs(10);
+        let mut alignment = WatermarkAlignment::default();
+
+        alignment.update(1, 0, at(100), drift);
+        assert!(alignment.update(2, 0, at(200), drift).is_some());
+
+        // once the slow subtask goes idle, nothing is held back
+        let paused = alignment.update(1, 0, None, drift).unwrap();
+        assert!(paused.is_empty());
+        assert_eq!(alignment.skew(), Duration::ZERO);
+    }
+}
@@ -5,6 +5,7 @@
 #![allow(clippy::needless_lifetimes)]
 
 use anyhow::Result;
+use arroyo_rpc::api_types::pipelines::WorkerOverrides;
 use arroyo_rpc::config::config;
 use arroyo_rpc::grpc::rpc::controller_grpc_server::{ControllerGrpc, ControllerGrpcServer};
 use arroyo_rpc::grpc::rpc::{
@@ -17,7 +18,7 @@ use arroyo_rpc::grpc::rpc::{
 };
 use arroyo_rpc::grpc::rpc::{
     NonfatalErrorReq, SinkDataReq, SinkDataResp, TaskCheckpointEventReq, TaskCheckpointEventResp,
-    WorkerErrorRes,
+    WatermarkReportReq, WatermarkReportResp, WorkerErrorRes,
 };
 use arroyo_rpc::public_ids::{IdTypes, generate_id};
 use arroyo_rpc::{config, errors};
@@ -71,6 +72,7 @@ pub struct JobConfig {
     restart_nonce: i32,
     restart_mode: RestartMode,
     ignore_state_before_epoch: Option<i32>,
+    worker_overrides: Option<WorkerOverrides>,
 }
 
 #[derive(Clone, Debug)]
@@ -155,6 +157,12 @@ pub enum RunningMessage {
     WorkerFinished {
         worker_id: WorkerId,
     },
+    WatermarkReport {
+        node_id: u32,
+        subtask_index: u32,
+        watermark: Option<SystemTime>,
+        max_drift: Duration,
+    },
 }
 
 #[derive(Debug)]
@@ -480,6 +488,26 @@ impl ControllerGrpc for ControllerServer {
         }
     }
 
+    async fn report_watermark(
+        &self,
+        request: Request<WatermarkReportReq>,
+    ) -> Result<Response<WatermarkReportResp>, Status> {
+        let req = request.into_inner();
+
+        self.send_to_job_queue(
+            &req.job_id,
+            JobMessage::RunningMessage(RunningMessage::WatermarkReport {
+                node_id: req.node_id,
+                subtask_index: req.subtask_index,
+                watermark: req.watermark.map(from_micros),
+                max_drift: Duration::from_micros(req.max_drift_micros),
+            }),
+        )
+        .await?;
+
+        Ok(Response::new(WatermarkReportResp {}))
+    }
+
     async fn job_metrics(
         &self,
         request: Request<JobMetricsReq>,
@@ -611,6 +639,17 @@ impl ControllerServer {
                         restart_nonce: p.config_restart_nonce,
                         restart_mode: p.restart_mode,
                         ignore_state_before_epoch: p.ignore_state_before_epoch,
+                        worker_overrides: p.worker_overrides.and_then(|v| {
+                            serde_json::from_value(v)
+                                .map_err(|e| {
+                                    warn!(
+                                        message = "invalid worker overrides for job",
+                                        job_id = *id,
+                                        error = ?e
+                                    );
+                                })
+                                .ok()
+                        }),
                     };
 
                     let mut jobs = jobs.lock().await;
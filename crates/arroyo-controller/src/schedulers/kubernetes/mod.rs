@@ -1,13 +1,12 @@
-mod quantities;
-
-use crate::schedulers::kubernetes::quantities::QuantityParser;
 use crate::schedulers::{Scheduler, SchedulerError, StartPipelineReq};
 use anyhow::bail;
+use arroyo_rpc::api_types::pipelines::WorkerOverrides;
 use arroyo_rpc::config::{KubernetesSchedulerConfig, ResourceMode, config};
 use arroyo_rpc::grpc::rpc::{HeartbeatNodeReq, RegisterNodeReq, WorkerFinishedReq};
+use arroyo_rpc::quantities::QuantityParser;
 use arroyo_types::{JOB_ID_ENV, RUN_ID_ENV, WorkerId};
 use async_trait::async_trait;
-use k8s_openapi::api::core::v1::Pod;
+use k8s_openapi::api::core::v1::{Pod, ResourceRequirements};
 use k8s_openapi::apimachinery::pkg::api::resource::Quantity;
 use kube::api::{DeleteParams, ListParams};
 use kube::{Api, Client};
@@ -38,47 +37,147 @@ impl KubernetesScheduler {
         Self { client, config }
     }
 
-    fn make_pod(&self, req: &StartPipelineReq, number: usize, slots: usize) -> Pod {
+    fn make_resources(
+        &self,
+        overrides: Option<&WorkerOverrides>,
+        number: usize,
+        slots: usize,
+    ) -> ResourceRequirements {
         let c = &self.config;
+        let mut r = c.worker.resources.clone();
+
+        // overrides are validated by the API when the pipeline is created, so we just ignore any
+        // that fail to parse here
+        let cpu_override = overrides
+            .and_then(|o| o.cpu_per_slot())
+            .and_then(|q| q.parse_cpu().ok());
+        let memory_override = overrides
+            .and_then(|o| o.memory_per_slot())
+            .and_then(|q| q.parse_memory().ok());
+
+        let per_slot = matches!(c.resource_mode, ResourceMode::PerSlot);
 
-        let resources = match c.resource_mode {
-            ResourceMode::PerSlot => {
-                let mut r = c.worker.resources.clone();
-
-                for (name, rs) in [("limits", &mut r.limits), ("requests", &mut r.requests)] {
-                    if let Some(l) = rs {
-                        if let Some(cpu) = l.get_mut("cpu") {
-                            let v = cpu.parse_cpu().unwrap_or_else(|e| {
-                                if number == 0 {
-                                    warn!("Invalid value '{}' for \
-                                kubernetes_scheduler.worker.resources.{}.cpu: {}; defaulting to 900m", cpu.0, name, e);
-                                }
-                                Quantity("900m".to_string()).parse_cpu().unwrap()
-                            });
-
-                            *cpu = Quantity((v * slots as i64).to_canonical());
-                        }
-
-                        if let Some(mem) = l.get_mut("memory") {
-                            let v = mem.parse_memory().unwrap_or_else(|e| {
-                                if number == 0 {
-                                    warn!("Invalid value '{}' for \
-                                kubernetes_scheduler.worker.resources.{}.memory: {}; defaulting to 500Mi", mem.0, name, e);
-                                }
-                                Quantity("500Mi".to_string()).parse_memory().unwrap()
-                            });
-
-                            *mem = Quantity((v * slots as i64).to_canonical());
-                        }
+        if r.requests.is_none() && (cpu_override.is_some() || memory_override.is_some()) {
+            r.requests = Some(Default::default());
+        }
+
+        for (name, rs) in [("limits", &mut r.limits), ("requests", &mut r.requests)] {
+            let Some(l) = rs else {
+                continue;
+            };
+
+            // per-pipeline overrides are always specified per slot, and replace the configured
+            // value in the requests and in any limit that is set
+            if let Some(cpu) = cpu_override {
+                if name == "requests" || l.contains_key("cpu") {
+                    l.insert(
+                        "cpu".to_string(),
+                        Quantity((cpu * slots as i64).to_canonical()),
+                    );
+                }
+            } else if per_slot && let Some(cpu) = l.get_mut("cpu") {
+                let v = cpu.parse_cpu().unwrap_or_else(|e| {
+                    if number == 0 {
+                        warn!(
+                            "Invalid value '{}' for \
+                    kubernetes_scheduler.worker.resources.{}.cpu: {}; defaulting to 900m",
+                            cpu.0, name, e
+                        );
                     }
+                    Quantity("900m".to_string()).parse_cpu().unwrap()
+                });
+
+                *cpu = Quantity((v * slots as i64).to_canonical());
+            }
+
+            if let Some(mem) = memory_override {
+                if name == "requests" || l.contains_key("memory") {
+                    l.insert(
+                        "memory".to_string(),
+                        Quantity((mem * slots as i64).to_canonical()),
+                    );
                 }
+            } else if per_slot && let Some(mem) = l.get_mut("memory") {
+                let v = mem.parse_memory().unwrap_or_else(|e| {
+                    if number == 0 {
+                        warn!(
+                            "Invalid value '{}' for \
+                    kubernetes_scheduler.worker.resources.{}.memory: {}; defaulting to 500Mi",
+                            mem.0, name, e
+                        );
+                    }
+                    Quantity("500Mi".to_string()).parse_memory().unwrap()
+                });
 
-                r
+                *mem = Quantity((v * slots as i64).to_canonical());
             }
-            ResourceMode::PerPod => c.worker.resources.clone(),
-        };
+        }
+
+        r
+    }
+
+    fn slots_per_pod(&self, req: &StartPipelineReq) -> usize {
+        req.worker_overrides
+            .as_ref()
+            .and_then(|o| o.slots_per_pod)
+            .unwrap_or(self.config.worker.task_slots)
+            .max(1) as usize
+    }
+
+    fn make_pod(&self, req: &StartPipelineReq, number: usize, slots: usize) -> Pod {
+        let c = &self.config;
+        let overrides = req.worker_overrides.as_ref();
+
+        let resources = self.make_resources(overrides, number, slots);
 
         let mut labels = c.worker.labels.clone();
+        let mut annotations = c.worker.annotations.clone();
+        let mut node_selector = c.worker.node_selector.clone();
+        let mut tolerations = c.worker.tolerations.clone();
+        let mut volumes = c.worker.volumes.clone();
+        let mut volume_mounts = c.worker.volume_mounts.clone();
+        let mut affinity = None;
+        let mut extra_env = vec![];
+
+        if let Some(o) = overrides {
+            // the overrides are validated on pipeline creation, so failures here are unexpected
+            let log_invalid = |field: &str, e: anyhow::Error| {
+                if number == 0 {
+                    warn!(
+                        message = "ignoring invalid worker override",
+                        job_id = *req.job_id,
+                        field,
+                        error = ?e
+                    );
+                }
+            };
+
+            labels.extend(o.labels.clone());
+            annotations.extend(o.annotations.clone());
+            node_selector.extend(o.node_selector.clone());
+
+            match o.tolerations() {
+                Ok(t) => tolerations.extend(t),
+                Err(e) => log_invalid("tolerations", e),
+            }
+            match o.volumes() {
+                Ok(v) => volumes.extend(v),
+                Err(e) => log_invalid("volumes", e),
+            }
+            match o.volume_mounts() {
+                Ok(v) => volume_mounts.extend(v),
+                Err(e) => log_invalid("volume_mounts", e),
+            }
+            match o.env() {
+                Ok(v) => extra_env = v,
+                Err(e) => log_invalid("env", e),
+            }
+            match o.affinity() {
+                Ok(a) => affinity = a,
+                Err(e) => log_invalid("affinity", e),
+            }
+        }
+
         labels.insert(CLUSTER_LABEL.to_string(), c.worker.name());
         labels.insert(JOB_ID_LABEL.to_string(), (*req.job_id).clone());
         labels.insert(RUN_ID_LABEL.to_string(), format!("{}", req.run_id));
@@ -135,7 +234,13 @@ impl KubernetesScheduler {
             }));
         }
 
-        for var in &config().kubernetes_scheduler.worker.env {
+        for var in config()
+            .kubernetes_scheduler
+            .worker
+            .env
+            .iter()
+            .chain(extra_env.iter())
+        {
             env.as_array_mut()
                 .unwrap()
                 .push(serde_json::to_value(var).unwrap());
@@ -157,11 +262,11 @@ impl KubernetesScheduler {
                 "name": pod_name,
                 "namespace": c.namespace,
                 "labels": labels,
-                "annotations": c.worker.annotations,
+                "annotations": annotations,
                 "ownerReferences": owner,
             },
             "spec": {
-                "volumes": c.worker.volumes,
+                "volumes": volumes,
                 "restartPolicy": "Never",
                 "imagePullSecrets": c.worker.image_pull_secrets,
                 "containers": [
@@ -182,12 +287,13 @@ impl KubernetesScheduler {
                             }
                         ],
                         "env": env,
-                        "volumeMounts": c.worker.volume_mounts,
+                        "volumeMounts": volume_mounts,
                     }
                 ],
                 "serviceAccountName": c.worker.service_account_name,
-                "nodeSelector": c.worker.node_selector,
-                "tolerations": c.worker.tolerations,
+                "nodeSelector": node_selector,
+                "tolerations": tolerations,
+                "affinity": affinity,
             }
         }))
         .unwrap()
@@ -199,10 +305,9 @@ impl Scheduler for KubernetesScheduler {
     async fn start_workers(&self, req: StartPipelineReq) -> Result<(), SchedulerError> {
         let api: Api<Pod> = Api::default_namespaced(self.client.as_ref().unwrap().clone());
 
-        let replicas = (req.slots as f32 / config().kubernetes_scheduler.worker.task_slots as f32)
-            .ceil() as usize;
+        let max_slots_per_pod = self.slots_per_pod(&req);
 
-        let max_slots_per_pod = config().kubernetes_scheduler.worker.task_slots as usize;
+        let replicas = (req.slots as f32 / max_slots_per_pod as f32).ceil() as usize;
         let mut slots_scheduled = 0;
         let mut pods = vec![];
         for i in 0..replicas {
@@ -323,6 +428,7 @@ mod test {
 
     use crate::schedulers::StartPipelineReq;
     use crate::schedulers::kubernetes::KubernetesScheduler;
+    use arroyo_rpc::api_types::pipelines::WorkerOverrides;
 
     #[test]
     fn test_resource_creation() {
@@ -335,6 +441,7 @@ mod test {
             run_id: 1,
             slots: 8,
             env_vars: Default::default(),
+            worker_overrides: None,
         };
 
         let mut config = config().kubernetes_scheduler.clone();
@@ -371,4 +478,91 @@ mod test {
             // test that we don't panic when creating the replicaset
             .make_pod(&req, 3, 4);
     }
+
+    #[test]
+    fn test_worker_overrides() {
+        let overrides: WorkerOverrides = serde_json::from_value(json!({
+            "cpu_per_slot": "2",
+            "memory_per_slot": "4Gi",
+            "slots_per_pod": 2,
+            "node_selector": {"node-pool": "highmem"},
+            "tolerations": [{
+                "key": "dedicated",
+                "operator": "Equal",
+                "value": "joins",
+                "effect": "NoSchedule"
+            }],
+            "labels": {"team": "analytics"},
+            "env": [{"name": "EXTRA_VAR", "value": "1"}]
+        }))
+        .unwrap();
+        overrides.validate().unwrap();
+
+        let req = StartPipelineReq {
+            name: "test_pipeline".to_string(),
+            program: LogicalProgram::default(),
+            wasm_path: "file:///wasm".to_string(),
+            job_id: Arc::new("job123".to_string()),
+            hash: "12123123h".to_string(),
+            run_id: 1,
+            slots: 8,
+            env_vars: Default::default(),
+            worker_overrides: Some(overrides),
+        };
+
+        let scheduler =
+            KubernetesScheduler::with_config(None, config().kubernetes_scheduler.clone());
+        assert_eq!(scheduler.slots_per_pod(&req), 2);
+
+        let pod = scheduler.make_pod(&req, 0, 2);
+
+        let metadata = pod.metadata;
+        assert_eq!(metadata.labels.unwrap().get("team").unwrap(), "analytics");
+
+        let spec = pod.spec.unwrap();
+        assert_eq!(
+            spec.node_selector.unwrap().get("node-pool").unwrap(),
+            "highmem"
+        );
+        assert!(
+            spec.tolerations
+                .unwrap()
+                .iter()
+                .any(|t| t.key.as_deref() == Some("dedicated"))
+        );
+
+        let container = &spec.containers[0];
+        let requests = container
+            .resources
+            .as_ref()
+            .unwrap()
+            .requests
+            .as_ref()
+            .unwrap();
+        assert_eq!(requests.get("cpu").unwrap().0, "4");
+        assert_eq!(requests.get("memory").unwrap().0, "8Gi");
+        assert!(
+            container
+                .env
+                .as_ref()
+                .unwrap()
+                .iter()
+                .any(|e| e.name == "EXTRA_VAR")
+        );
+    }
+
+    #[test]
+    fn test_invalid_worker_overrides() {
+        let overrides = WorkerOverrides {
+            memory_per_slot: Some("4Gx".to_string()),
+            ..Default::default()
+        };
+        assert!(overrides.validate().is_err());
+
+        let overrides = WorkerOverrides {
+            slots_per_pod: Some(0),
+            ..Default::default()
+        };
+        assert!(overrides.validate().is_err());
+    }
 }
@@ -1,5 +1,6 @@
 use anyhow::bail;
 use arroyo_datastream::logical::LogicalProgram;
+use arroyo_rpc::api_types::pipelines::WorkerOverrides;
 use arroyo_rpc::config::config;
 use arroyo_rpc::connect_grpc;
 use arroyo_rpc::grpc::rpc::node_grpc_client::NodeGrpcClient;
@@ -95,6 +96,7 @@ pub struct StartPipelineReq {
     pub run_id: u64,
     pub slots: usize,
     pub env_vars: HashMap<String, String>,
+    pub worker_overrides: Option<WorkerOverrides>,
 }
 
 #[async_trait::async_trait]
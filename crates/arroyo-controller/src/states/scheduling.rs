@@ -212,6 +212,7 @@ impl Scheduling {
                     )]
                     .into_iter()
                     .collect(),
+                    worker_overrides: ctx.config.worker_overrides.clone(),
                 })
                 .await
             {
@@ -53,10 +53,22 @@ pub struct WasmUDF {
 
 #[derive(Clone, Encode, Decode, Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd)]
 pub enum WindowType {
-    Tumbling { width: Duration },
-    Sliding { width: Duration, slide: Duration },
+    Tumbling {
+        width: Duration,
+    },
+    Sliding {
+        width: Duration,
+        slide: Duration,
+    },
     Instant,
-    Session { gap: Duration },
+    Session {
+        gap: Duration,
+    },
+    Cumulating {
+        offset: Duration,
+        step: Duration,
+        size: Duration,
+    },
 }
 
 fn format_duration(duration: Duration) -> String {
@@ -97,6 +109,15 @@ impl Debug for WindowType {
             Self::Session { gap } => {
                 write!(f, "SessionWindow({})", format_duration(*gap))
             }
+            Self::Cumulating { offset, step, size } => {
+                write!(
+                    f,
+                    "CumulatingWindow(offset: {}, step: {}, size: {})",
+                    format_duration(*offset),
+                    format_duration(*step),
+                    format_duration(*size)
+                )
+            }
         }
     }
 }
@@ -33,11 +33,17 @@ pub enum OperatorName {
     AsyncUdf,
     Join,
     InstantJoin,
+    IntervalJoin,
     LookupJoin,
+    TemporalJoin,
+    MatchRecognize,
+    Deduplicate,
     WindowFunction,
     TumblingWindowAggregate,
     SlidingWindowAggregate,
+    CumulatingWindowAggregate,
     SessionWindowAggregate,
+    LateRecords,
     UpdatingAggregate,
     ConnectorSource,
     ConnectorSink,
@@ -294,6 +300,7 @@ pub struct PythonUdfConfig {
 pub struct ProgramConfig {
     pub udf_dylibs: HashMap<String, DylibUdfConfig>,
     pub python_udfs: HashMap<String, PythonUdfConfig>,
+    pub unaligned_checkpoints: bool,
 }
 
 #[derive(Clone, Debug, Default)]
@@ -407,17 +414,25 @@ impl LogicalProgram {
                     | OperatorName::Projection => continue,
                     OperatorName::Join => "join-with-expiration".to_string(),
                     OperatorName::InstantJoin => "windowed-join".to_string(),
+                    OperatorName::IntervalJoin => "interval-join".to_string(),
                     OperatorName::WindowFunction => "sql-window-function".to_string(),
                     OperatorName::LookupJoin => "lookup-join".to_string(),
+                    OperatorName::TemporalJoin => "temporal-join".to_string(),
+                    OperatorName::MatchRecognize => "match-recognize".to_string(),
+                    OperatorName::Deduplicate => "deduplicate".to_string(),
                     OperatorName::TumblingWindowAggregate => {
                         "sql-tumbling-window-aggregate".to_string()
                     }
                     OperatorName::SlidingWindowAggregate => {
                         "sql-sliding-window-aggregate".to_string()
                     }
+                    OperatorName::CumulatingWindowAggregate => {
+                        "sql-cumulating-window-aggregate".to_string()
+                    }
                     OperatorName::SessionWindowAggregate => {
                         "sql-session-window-aggregate".to_string()
                     }
+                    OperatorName::LateRecords => "sql-late-records".to_string(),
                     OperatorName::UpdatingAggregate => "sql-updating-aggregate".to_string(),
                     OperatorName::ConnectorSource => {
                         let Ok(connector_op) = ConnectorOp::decode(&t.operator_config[..]) else {
@@ -497,6 +512,7 @@ impl TryFrom<ArrowProgram> for LogicalProgram {
             .unwrap_or_else(|| ArrowProgramConfig {
                 udf_dylibs: HashMap::new(),
                 python_udfs: HashMap::new(),
+                unaligned_checkpoints: false,
             })
             .into();
 
@@ -607,6 +623,7 @@ impl From<ProgramConfig> for ArrowProgramConfig {
                 .into_iter()
                 .map(|(k, v)| (k, v.into()))
                 .collect(),
+            unaligned_checkpoints: from.unaligned_checkpoints,
         }
     }
 }
@@ -624,6 +641,7 @@ impl From<ArrowProgramConfig> for ProgramConfig {
                 .into_iter()
                 .map(|(k, v)| (k, v.into()))
                 .collect(),
+            unaligned_checkpoints: from.unaligned_checkpoints,
         }
     }
 }
@@ -5,8 +5,8 @@ use arrow::array::{Int32Builder, Int64Builder};
 use arrow::compute::kernels;
 use arrow::json::reader::{FailureKind, JsonType, ValidationError};
 use arrow_array::builder::{
-    ArrayBuilder, BinaryBuilder, GenericByteBuilder, StringBuilder, TimestampNanosecondBuilder,
-    UInt64Builder, make_builder,
+    ArrayBuilder, BinaryBuilder, BooleanBuilder, GenericByteBuilder, StringBuilder,
+    TimestampNanosecondBuilder, UInt64Builder, make_builder,
 };
 use arrow_array::types::GenericBinaryType;
 use arrow_array::{ArrayRef, BooleanArray, RecordBatch};
@@ -32,6 +32,7 @@ pub enum FieldValueType<'a> {
     Int32(Option<i32>),
     String(Option<&'a str>),
     Bytes(Option<&'a [u8]>),
+    Boolean(Option<bool>),
 }
 
 struct ContextBuffer {
@@ -544,6 +545,7 @@ impl ArrowDeserializer {
                         FieldValueType::UInt64(_) => Box::new(UInt64Builder::new()),
                         FieldValueType::String(_) => Box::new(StringBuilder::new()),
                         FieldValueType::Bytes(_) => Box::new(BinaryBuilder::new()),
+                        FieldValueType::Boolean(_) => Box::new(BooleanBuilder::new()),
                     };
                     builders.insert(key.to_string(), builder);
                 }
@@ -795,6 +797,9 @@ fn add_additional_fields(
         FieldValueType::Bytes(v) => {
             append_repeated_value!(builder, BinaryBuilder, v, count);
         }
+        FieldValueType::Boolean(v) => {
+            append_repeated_value!(builder, BooleanBuilder, v, count);
+        }
     }
 }
 
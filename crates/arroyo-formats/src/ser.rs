@@ -7,11 +7,11 @@ use arrow_array::{Array, RecordBatch, StructArray};
 use arrow_json::EncoderOptions;
 use arrow_json::writer::make_encoder;
 use arrow_schema::{ArrowError, DataType, Field};
-use arroyo_rpc::TIMESTAMP_FIELD;
 use arroyo_rpc::formats::{
     AvroFormat, DecimalEncoding, Format, JsonFormat, RawBytesFormat, RawStringFormat,
     TimestampFormat,
 };
+use arroyo_rpc::{TIMESTAMP_FIELD, UPDATING_META_FIELD};
 use serde_json::Value;
 use std::sync::Arc;
 
@@ -73,7 +73,7 @@ impl ArrowSerializer {
             .fields
             .iter()
             .enumerate()
-            .filter(|(_, f)| f.name() != TIMESTAMP_FIELD)
+            .filter(|(_, f)| f.name() != TIMESTAMP_FIELD && f.name() != UPDATING_META_FIELD)
             .map(|(i, _)| i)
             .collect()
     }
@@ -3,7 +3,7 @@ use std::sync::{Arc, OnceLock, RwLock};
 
 use arroyo_types::{
     BATCHES_RECV, BATCHES_SENT, BYTES_RECV, BYTES_SENT, ChainInfo, DESERIALIZATION_ERRORS,
-    MESSAGES_RECV, MESSAGES_SENT,
+    LATE_RECORDS_DROPPED, MESSAGES_RECV, MESSAGES_SENT,
 };
 use lazy_static::lazy_static;
 use prometheus::{
@@ -94,6 +94,12 @@ lazy_static! {
         &TASK_METRIC_LABELS
     )
     .unwrap();
+    pub static ref LATE_RECORDS_DROPPED_COUNTER: IntCounterVec = register_int_counter_vec!(
+        LATE_RECORDS_DROPPED,
+        "Count of records dropped for arriving after the allowed lateness",
+        &TASK_METRIC_LABELS
+    )
+    .unwrap();
 }
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
@@ -105,10 +111,11 @@ pub enum TaskCounters {
     BytesReceived,
     BytesSent,
     DeserializationErrors,
+    LateRecordsDropped,
 }
 
 impl TaskCounters {
-    pub fn variants() -> [TaskCounters; 7] {
+    pub fn variants() -> [TaskCounters; 8] {
         use TaskCounters::*;
 
         [
@@ -119,6 +126,7 @@ impl TaskCounters {
             BytesReceived,
             BytesSent,
             DeserializationErrors,
+            LateRecordsDropped,
         ]
     }
 }
@@ -134,6 +142,7 @@ impl TaskCounters {
             TaskCounters::BytesReceived => &BYTES_RECEIVED_COUNTER,
             TaskCounters::BytesSent => &BYTES_SENT_COUNTER,
             TaskCounters::DeserializationErrors => &DESERIALIZATION_ERRORS_COUNTER,
+            TaskCounters::LateRecordsDropped => &LATE_RECORDS_DROPPED_COUNTER,
         }
     }
 
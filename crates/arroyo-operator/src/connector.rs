@@ -92,6 +92,13 @@ pub trait Connector: Send {
 
     fn table_type(&self, config: Self::ProfileT, table: Self::TableT) -> ConnectionType;
 
+    /// Whether this table can be written to directly by an updating query, applying retractions
+    /// itself rather than receiving them as Debezium envelopes
+    #[allow(unused)]
+    fn accepts_updating_input(&self, config: Self::ProfileT, table: Self::TableT) -> bool {
+        false
+    }
+
     #[allow(unused)]
     fn register_udfs(&self, registry: &mut dyn FunctionRegistry) -> anyhow::Result<()> {
         Ok(())
@@ -189,6 +196,12 @@ pub trait ErasedConnector: Send {
         table: &serde_json::Value,
     ) -> Result<ConnectionType, serde_json::Error>;
 
+    fn accepts_updating_input(
+        &self,
+        config: &serde_json::Value,
+        table: &serde_json::Value,
+    ) -> Result<bool, serde_json::Error>;
+
     fn config_description(&self, s: &serde_json::Value) -> Result<String, serde_json::Error>;
 
     fn register_udfs(&self, registry: &mut dyn FunctionRegistry) -> anyhow::Result<()>;
@@ -283,6 +296,14 @@ impl<C: Connector> ErasedConnector for C {
         Ok(self.table_type(self.parse_config(config)?, self.parse_table(table)?))
     }
 
+    fn accepts_updating_input(
+        &self,
+        config: &serde_json::Value,
+        table: &serde_json::Value,
+    ) -> Result<bool, serde_json::Error> {
+        Ok(self.accepts_updating_input(self.parse_config(config)?, self.parse_table(table)?))
+    }
+
     fn get_schema(
         &self,
         config: &serde_json::Value,
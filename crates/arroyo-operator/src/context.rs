@@ -1,7 +1,7 @@
 use crate::{RateLimiter, server_for_hash_array};
-use arrow::array::{Array, PrimitiveArray, RecordBatch};
-use arrow::compute::{partition, sort_to_indices, take};
-use arrow::datatypes::UInt64Type;
+use arrow::array::{Array, AsArray, PrimitiveArray, RecordBatch};
+use arrow::compute::{max, partition, sort_to_indices, take};
+use arrow::datatypes::{TimestampNanosecondType, UInt64Type};
 use arroyo_formats::de::{ArrowDeserializer, FieldValueType};
 use arroyo_metrics::{QueueGauges, TaskCounters, register_queue_gauge};
 use arroyo_rpc::config::config;
@@ -10,26 +10,33 @@ use arroyo_rpc::errors::{DataflowError, DataflowResult};
 use arroyo_rpc::formats::{BadData, Format, Framing};
 use arroyo_rpc::grpc::rpc::{CheckpointMetadata, TableConfig, TaskCheckpointEventType};
 use arroyo_rpc::schema_resolver::SchemaResolver;
-use arroyo_rpc::{CompactionResult, ControlMessage, ControlResp, MetadataField, get_hasher};
+use arroyo_rpc::{
+    CompactionResult, ControlMessage, ControlResp, MetadataField, PartitionWatermarkConfig,
+    connector_err, get_hasher,
+};
 use arroyo_state::tables::table_manager::TableManager;
 use arroyo_types::{
-    ArrowMessage, ChainInfo, CheckpointBarrier, SignalMessage, TaskInfo, Watermark,
+    ArrowMessage, ChainInfo, CheckpointBarrier, SignalMessage, TaskInfo, Watermark, from_nanos,
 };
 use async_trait::async_trait;
 use datafusion::common::hash_utils;
 use rand::Rng;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::mem::size_of_val;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
-use std::time::SystemTime;
-use tokio::sync::Notify;
+use std::time::{Duration, Instant, SystemTime};
 use tokio::sync::mpsc::error::SendError;
-use tokio::sync::mpsc::{Receiver, Sender, UnboundedReceiver, UnboundedSender, unbounded_channel};
+use tokio::sync::mpsc::{
+    Receiver, Sender, UnboundedReceiver, UnboundedSender, WeakSender, unbounded_channel,
+};
+use tokio::sync::{Notify, watch};
 use tracing::{trace, warn};
 
 pub type QueueItem = ArrowMessage;
 
+const PAUSE_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
 pub struct WatermarkHolder {
     // This is the last watermark with an actual value; this helps us keep track of the watermark we're at even
     // if we're currently idle
@@ -110,10 +117,25 @@ fn message_bytes(item: &QueueItem) -> u64 {
     }
 }
 
+#[inline]
+fn is_unaligned_barrier(item: &QueueItem) -> bool {
+    matches!(item, QueueItem::Signal(SignalMessage::Barrier(b)) if b.unaligned)
+}
+
 impl BatchSender {
     pub async fn send(&self, item: QueueItem) -> Result<(), SendError<QueueItem>> {
         // Ensure that every message is sendable, even if it's bigger than our max size
         let count = message_count(&item, self.size);
+
+        // unaligned barriers don't wait for space, as they need to reach the receiver while it is
+        // still working through the data queued ahead of them
+        if is_unaligned_barrier(&item) {
+            self.queued_messages.fetch_add(count, Ordering::SeqCst);
+            self.queued_bytes
+                .fetch_add(message_bytes(&item), Ordering::AcqRel);
+            return self.tx.send(item);
+        }
+
         loop {
             if self.tx.is_closed() {
                 return Err(SendError(item));
@@ -172,6 +194,9 @@ impl BatchSender {
 pub struct BatchReceiver {
     size: u32,
     rx: UnboundedReceiver<QueueItem>,
+    // items taken off the channel while looking for an unaligned barrier, which still count
+    // against the queue size until they are returned
+    buffered: VecDeque<QueueItem>,
     queued_messages: Arc<AtomicU32>,
     queued_bytes: Arc<AtomicU64>,
     notify: Arc<Notify>,
@@ -179,16 +204,63 @@ pub struct BatchReceiver {
 
 impl BatchReceiver {
     pub async fn recv(&mut self) -> Option<QueueItem> {
-        let item = self.rx.recv().await;
+        let item = match self.buffered.pop_front() {
+            Some(item) => Some(item),
+            None => self.rx.recv().await,
+        };
         if let Some(item) = &item {
-            let count = message_count(item, self.size);
-            self.queued_messages.fetch_sub(count, Ordering::SeqCst);
-            self.queued_bytes
-                .fetch_sub(message_bytes(item), Ordering::AcqRel);
-            self.notify.notify_waiters();
+            self.release(item);
         }
         item
     }
+
+    /// Like [`BatchReceiver::recv`], except that an unaligned checkpoint barrier that is queued
+    /// behind data is returned ahead of that data, along with the batches it overtook. Those
+    /// batches are still returned by later calls, in their original order; the caller is
+    /// responsible for storing them in the checkpoint the barrier triggers.
+    pub async fn recv_overtaking(&mut self) -> Option<(QueueItem, Vec<RecordBatch>)> {
+        while let Ok(item) = self.rx.try_recv() {
+            self.buffered.push_back(item);
+        }
+
+        // barriers may overtake data and watermarks, but not other signals
+        let barrier_idx = self
+            .buffered
+            .iter()
+            .position(|item| {
+                !matches!(
+                    item,
+                    QueueItem::Data(_) | QueueItem::Signal(SignalMessage::Watermark(_))
+                )
+            })
+            .filter(|idx| *idx > 0 && is_unaligned_barrier(&self.buffered[*idx]));
+
+        if let Some(idx) = barrier_idx {
+            let barrier = self.buffered.remove(idx).unwrap();
+            self.release(&barrier);
+
+            let overtaken = self
+                .buffered
+                .range(..idx)
+                .filter_map(|item| match item {
+                    QueueItem::Data(batch) => Some(batch.clone()),
+                    QueueItem::Signal(_) => None,
+                })
+                .collect();
+
+            return Some((barrier, overtaken));
+        }
+
+        self.recv().await.map(|item| (item, vec![]))
+    }
+
+    fn release(&self, item: &QueueItem) {
+        let count = message_count(item, self.size);
+        self.queued_messages.fetch_sub(count, Ordering::SeqCst);
+        self.queued_bytes
+            .fetch_sub(message_bytes(item), Ordering::AcqRel);
+        self.notify.notify_waiters();
+    }
 }
 
 pub fn batch_bounded(size: u32) -> (BatchSender, BatchReceiver) {
@@ -207,6 +279,7 @@ pub fn batch_bounded(size: u32) -> (BatchSender, BatchReceiver) {
         BatchReceiver {
             size,
             rx,
+            buffered: VecDeque::new(),
             notify,
             queued_bytes,
             queued_messages,
@@ -260,6 +333,183 @@ impl SourceContext {
     }
 }
 
+/// Lets the worker pause a source that has gotten too far ahead of the slowest source in the
+/// pipeline, for watermark alignment. A paused source blocks when it tries to emit data, which
+/// stops it from reading further.
+#[derive(Clone)]
+pub struct SourcePauseHandle {
+    paused: Arc<watch::Sender<bool>>,
+    // the source's own control queue, so that paused sources can still handle control messages
+    control_tx: WeakSender<ControlMessage>,
+}
+
+impl SourcePauseHandle {
+    pub fn new(control_tx: &Sender<ControlMessage>) -> Self {
+        Self {
+            paused: Arc::new(watch::Sender::new(false)),
+            control_tx: control_tx.downgrade(),
+        }
+    }
+
+    pub fn set_paused(&self, paused: bool) {
+        self.paused
+            .send_if_modified(|p| std::mem::replace(p, paused) != paused);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        *self.paused.borrow()
+    }
+
+    fn control_pending(&self) -> bool {
+        self.control_tx
+            .upgrade()
+            .is_none_or(|tx| tx.capacity() < tx.max_capacity())
+    }
+
+    /// Waits until the source is unpaused, or until there's a control message (like a checkpoint
+    /// barrier) that the source needs to handle
+    async fn wait(&self) {
+        let mut paused = self.paused.subscribe();
+        while *paused.borrow_and_update() && !self.control_pending() {
+            match tokio::time::timeout(PAUSE_CHECK_INTERVAL, paused.changed()).await {
+                Ok(Err(_)) => return,
+                Ok(Ok(())) | Err(_) => {}
+            }
+        }
+    }
+}
+
+/// Tracks the event-time watermark of each partition (or shard) that a source reads from. The
+/// source's watermark is the minimum over the partitions that aren't idle, so that data
+/// interleaved from partitions that are at different points in time doesn't need to be covered by
+/// the watermark delay.
+struct PartitionWatermarks {
+    event_time_index: usize,
+    idle_time: Option<Duration>,
+    partitions: HashMap<String, PartitionWatermark>,
+    current: Option<String>,
+    last_emitted: Option<Watermark>,
+}
+
+struct PartitionWatermark {
+    max_event_time: Option<SystemTime>,
+    last_event: Instant,
+}
+
+impl PartitionWatermarks {
+    fn new(config: PartitionWatermarkConfig, schema: &ArroyoSchema) -> DataflowResult<Self> {
+        let event_time_index = schema
+            .schema
+            .index_of(&config.event_time_field)
+            .map_err(|_| {
+                connector_err!(
+                    User,
+                    NoRetry,
+                    "event time field '{}' for partition watermarks is not in the source schema",
+                    config.event_time_field
+                )
+            })?;
+
+        Ok(Self {
+            event_time_index,
+            idle_time: config.idle_time_micros.map(Duration::from_micros),
+            partitions: HashMap::new(),
+            current: None,
+            last_emitted: None,
+        })
+    }
+
+    fn add_partition(&mut self, partition: &str) {
+        self.partitions
+            .entry(partition.to_string())
+            .or_insert_with(|| PartitionWatermark {
+                max_event_time: None,
+                last_event: Instant::now(),
+            });
+    }
+
+    fn remove_partition(&mut self, partition: &str) {
+        self.partitions.remove(partition);
+        if self.current.as_deref() == Some(partition) {
+            self.current = None;
+        }
+    }
+
+    /// Updates the watermark of the current partition from a batch of its records
+    fn observe(&mut self, batch: &RecordBatch) -> DataflowResult<()> {
+        let Some(current) = &self.current else {
+            return Ok(());
+        };
+
+        let event_times = batch
+            .column(self.event_time_index)
+            .as_primitive_opt::<TimestampNanosecondType>()
+            .ok_or_else(|| DataflowError::InternalOperatorError {
+                error: "invalid event time column",
+                message: format!(
+                    "expected the event time column to be a timestamp, but it was {}",
+                    batch.column(self.event_time_index).data_type()
+                ),
+            })?;
+
+        let partition = self.partitions.get_mut(current).unwrap();
+        partition.last_event = Instant::now();
+        if let Some(nanos) = max(event_times) {
+            let event_time = from_nanos(nanos.max(0) as u128);
+            partition.max_event_time = Some(
+                partition
+                    .max_event_time
+                    .map_or(event_time, |t| t.max(event_time)),
+            );
+        }
+
+        Ok(())
+    }
+
+    fn is_idle(&self, partition: &PartitionWatermark, now: Instant) -> bool {
+        self.idle_time
+            .is_some_and(|idle_time| now.duration_since(partition.last_event) > idle_time)
+    }
+
+    fn watermark(&self, now: Instant) -> Option<Watermark> {
+        // a partition that hasn't received any data yet holds back the watermark (as None sorts
+        // before any time) until it becomes idle
+        match self
+            .partitions
+            .values()
+            .filter(|p| !self.is_idle(p, now))
+            .map(|p| p.max_event_time)
+            .min()
+        {
+            None => Some(Watermark::Idle),
+            Some(min) => min.map(Watermark::EventTime),
+        }
+    }
+
+    /// Returns the watermark that the source should emit, if it has advanced since the last one
+    /// or all of the partitions have become idle
+    fn next_watermark(&mut self, now: Instant) -> Option<Watermark> {
+        if self.partitions.is_empty() {
+            return None;
+        }
+
+        let watermark = self.watermark(now)?;
+
+        let advanced = match (self.last_emitted, watermark) {
+            (Some(Watermark::EventTime(last)), Watermark::EventTime(t)) => t > last,
+            (Some(Watermark::Idle), Watermark::Idle) => false,
+            _ => true,
+        };
+
+        if advanced {
+            self.last_emitted = Some(watermark);
+            Some(watermark)
+        } else {
+            None
+        }
+    }
+}
+
 pub struct SourceCollector {
     deserializer: Option<ArrowDeserializer>,
     buffered_error: Option<DataflowError>,
@@ -269,6 +519,8 @@ pub struct SourceCollector {
     control_tx: Sender<ControlResp>,
     task_info: Arc<TaskInfo>,
     connection_id: Option<String>,
+    pause: Option<SourcePauseHandle>,
+    partition_watermarks: Option<PartitionWatermarks>,
 }
 
 impl SourceCollector {
@@ -287,6 +539,92 @@ impl SourceCollector {
             buffered_error: None,
             error_rate_limiter: RateLimiter::new(),
             connection_id: None,
+            pause: None,
+            partition_watermarks: None,
+        }
+    }
+
+    pub fn set_pause_handle(&mut self, pause: SourcePauseHandle) {
+        self.pause = Some(pause);
+    }
+
+    async fn wait_if_paused(&self) {
+        if let Some(pause) = &self.pause {
+            pause.wait().await;
+        }
+    }
+
+    /// Enables per-partition watermarks, which the source drives by calling `set_partition` as
+    /// it reads from each of its partitions
+    pub fn enable_partition_watermarks(
+        &mut self,
+        config: PartitionWatermarkConfig,
+    ) -> DataflowResult<()> {
+        self.partition_watermarks = Some(PartitionWatermarks::new(config, &self.out_schema)?);
+        Ok(())
+    }
+
+    /// Registers a partition that this source reads from; until it receives data (or becomes
+    /// idle) it holds back the watermark
+    pub fn add_partition(&mut self, partition: &str) {
+        if let Some(watermarks) = &mut self.partition_watermarks {
+            watermarks.add_partition(partition);
+        }
+    }
+
+    /// Stops tracking a partition, for example once a shard has been fully read
+    pub async fn remove_partition(&mut self, partition: &str) -> DataflowResult<()> {
+        if self
+            .partition_watermarks
+            .as_ref()
+            .is_some_and(|w| w.current.as_deref() == Some(partition))
+        {
+            self.flush_buffer().await?;
+        }
+
+        if let Some(watermarks) = &mut self.partition_watermarks {
+            watermarks.remove_partition(partition);
+        }
+
+        self.emit_partition_watermark().await;
+        Ok(())
+    }
+
+    /// Marks subsequently deserialized records as belonging to `partition`. When per-partition
+    /// watermarks are enabled, the records buffered for the previous partition are flushed first,
+    /// so that every batch comes from a single partition.
+    pub async fn set_partition(&mut self, partition: &str) -> DataflowResult<()> {
+        let Some(watermarks) = &self.partition_watermarks else {
+            return Ok(());
+        };
+
+        if watermarks.current.as_deref() == Some(partition) {
+            return Ok(());
+        }
+
+        self.flush_buffer().await?;
+
+        let watermarks = self.partition_watermarks.as_mut().unwrap();
+        watermarks.add_partition(partition);
+        watermarks.current = Some(partition.to_string());
+        Ok(())
+    }
+
+    /// Emits the source's watermark if it has advanced, or if all of its partitions have become
+    /// idle; sources should call this periodically so that idleness is detected
+    pub async fn emit_partition_watermark(&mut self) {
+        if let Some(watermark) = self
+            .partition_watermarks
+            .as_mut()
+            .and_then(|w| w.next_watermark(Instant::now()))
+        {
+            trace!(
+                "emitting partition watermark {:?} for {}",
+                watermark, self.task_info
+            );
+            self.collector
+                .broadcast(SignalMessage::Watermark(watermark))
+                .await;
         }
     }
 
@@ -295,6 +633,7 @@ impl SourceCollector {
     }
 
     pub async fn collect(&mut self, record: RecordBatch) -> DataflowResult<()> {
+        self.wait_if_paused().await;
         self.collector.collect(record).await
     }
 
@@ -415,7 +754,13 @@ impl SourceCollector {
             }
 
             if let Some(batch) = batch {
+                if let Some(watermarks) = &mut self.partition_watermarks {
+                    watermarks.observe(&batch)?;
+                }
+
+                self.wait_if_paused().await;
                 self.collector.collect(batch).await?;
+                self.emit_partition_watermark().await;
             }
         }
 
@@ -462,6 +807,8 @@ pub struct OperatorContext {
     pub out_schema: Option<Arc<ArroyoSchema>>,
     pub table_manager: TableManager,
     pub error_reporter: ErrorReporter,
+    /// the chain this operator runs in, which labels its task metrics
+    pub chain_info: Arc<ChainInfo>,
 }
 
 #[derive(Clone)]
@@ -501,7 +848,7 @@ pub struct ArrowCollector {
     tx_queue_bytes_gauges: QueueGauges,
 }
 
-fn repartition<'a>(
+pub(crate) fn repartition<'a>(
     record: &'a RecordBatch,
     keys: Option<&'a Vec<usize>>,
     qs: usize,
@@ -691,6 +1038,13 @@ impl OperatorContext {
                 .await
                 .expect("should be able to create TableManager");
 
+        let chain_info = Arc::new(ChainInfo {
+            job_id: task_info.job_id.clone(),
+            node_id: task_info.node_id,
+            description: task_info.operator_name.clone(),
+            task_index: task_info.task_index,
+        });
+
         Self {
             task_info: task_info.clone(),
             control_tx: control_tx.clone(),
@@ -705,6 +1059,7 @@ impl OperatorContext {
                 tx: control_tx,
                 task_info,
             },
+            chain_info,
         }
     }
 
@@ -762,6 +1117,94 @@ mod tests {
         assert_eq!(w.watermark(), Some(Watermark::Idle));
     }
 
+    #[test]
+    fn test_partition_watermarks_missing_field() {
+        let schema = ArroyoSchema::new_unkeyed(
+            Arc::new(Schema::new(vec![Field::new(
+                "_timestamp",
+                DataType::Timestamp(TimeUnit::Nanosecond, None),
+                false,
+            )])),
+            0,
+        );
+
+        let err = PartitionWatermarks::new(
+            PartitionWatermarkConfig {
+                event_time_field: "event_time".to_string(),
+                idle_time_micros: None,
+            },
+            &schema,
+        )
+        .err()
+        .unwrap();
+        assert!(err.to_string().contains("'event_time'"), "{err}");
+    }
+
+    #[test]
+    fn test_partition_watermarks() {
+        let schema = ArroyoSchema::new_unkeyed(
+            Arc::new(Schema::new(vec![Field::new(
+                "_timestamp",
+                DataType::Timestamp(TimeUnit::Nanosecond, None),
+                false,
+            )])),
+            0,
+        );
+
+        let batch = |secs: &[u64]| {
+            RecordBatch::try_new(
+                schema.schema.clone(),
+                vec![Arc::new(TimestampNanosecondArray::from(
+                    secs.iter()
+                        .map(|s| Duration::from_secs(*s).as_nanos() as i64)
+                        .collect::<Vec<_>>(),
+                ))],
+            )
+            .unwrap()
+        };
+
+        let at =
+            |secs: u64| Watermark::EventTime(SystemTime::UNIX_EPOCH + Duration::from_secs(secs));
+
+        let mut w = PartitionWatermarks::new(
+            PartitionWatermarkConfig {
+                event_time_field: "_timestamp".to_string(),
+                idle_time_micros: Some(10_000_000),
+            },
+            &schema,
+        )
+        .unwrap();
+
+        w.add_partition("0");
+        w.add_partition("1");
+
+        w.current = Some("0".to_string());
+        w.observe(&batch(&[50, 30])).unwrap();
+
+        // partition 1 hasn't received any data yet, so it holds back the watermark
+        let now = Instant::now();
+        assert_eq!(w.next_watermark(now), None);
+
+        w.current = Some("1".to_string());
+        w.observe(&batch(&[20])).unwrap();
+        assert_eq!(w.next_watermark(now), Some(at(20)));
+
+        // records that are out of order within a partition don't move its watermark backwards
+        w.observe(&batch(&[10])).unwrap();
+        assert_eq!(w.next_watermark(now), None);
+
+        // once partition 1 goes idle, partition 0 determines the watermark
+        let later = now + Duration::from_secs(15);
+        w.partitions.get_mut("0").unwrap().last_event = later;
+        assert_eq!(w.next_watermark(later), Some(at(50)));
+
+        // and once all of them are idle, so is the source
+        assert_eq!(
+            w.next_watermark(later + Duration::from_secs(15)),
+            Some(Watermark::Idle)
+        );
+    }
+
     #[tokio::test]
     async fn test_shuffles() {
         let timestamp = SystemTime::now();
@@ -879,6 +1322,52 @@ mod tests {
         assert_eq!(tx.capacity(), 8);
     }
 
+    #[tokio::test]
+    async fn test_unaligned_barrier_overtakes_data() {
+        let (tx, mut rx) = batch_bounded(8);
+        let msg = RecordBatch::try_new(
+            Arc::new(Schema::new(vec![Field::new("x", DataType::Int64, false)])),
+            vec![Arc::new(Int64Array::from(vec![1, 2, 3, 4]))],
+        )
+        .unwrap();
+
+        let barrier = CheckpointBarrier {
+            epoch: 1,
+            min_epoch: 0,
+            timestamp: SystemTime::now(),
+            then_stop: false,
+            unaligned: true,
+        };
+
+        tx.send(ArrowMessage::Data(msg.clone())).await.unwrap();
+        tx.send(ArrowMessage::Data(msg.clone())).await.unwrap();
+
+        // the queue is full, but the barrier should still be sent
+        tokio::time::timeout(
+            std::time::Duration::from_secs(1),
+            tx.send(ArrowMessage::Signal(SignalMessage::Barrier(barrier))),
+        )
+        .await
+        .expect("barrier should not wait for capacity")
+        .unwrap();
+
+        let (item, overtaken) = rx.recv_overtaking().await.unwrap();
+        assert_eq!(item, ArrowMessage::Signal(SignalMessage::Barrier(barrier)));
+        assert_eq!(overtaken, vec![msg.clone(), msg.clone()]);
+
+        // the overtaken data is still delivered, and still counts against the queue
+        assert_eq!(tx.capacity(), 0);
+
+        let (item, overtaken) = rx.recv_overtaking().await.unwrap();
+        assert_eq!(item, ArrowMessage::Data(msg.clone()));
+        assert!(overtaken.is_empty());
+
+        let (item, _) = rx.recv_overtaking().await.unwrap();
+        assert_eq!(item, ArrowMessage::Data(msg));
+
+        assert_eq!(tx.capacity(), 8);
+    }
+
     #[tokio::test]
     async fn test_panic_propagation() {
         let (tx, mut rx) = batch_bounded(8);
@@ -1,6 +1,6 @@
 use crate::context::{
     ArrowCollector, BatchReceiver, BatchSender, Collector, OperatorContext, SourceCollector,
-    SourceContext, send_checkpoint_event,
+    SourceContext, SourcePauseHandle, repartition, send_checkpoint_event,
 };
 use crate::inq_reader::InQReader;
 use crate::udfs::{ArroyoUdaf, UdafArg};
@@ -9,12 +9,16 @@ use anyhow::{anyhow, bail};
 use arrow::array::RecordBatch;
 use arrow::datatypes::DataType;
 use arrow::datatypes::Schema;
+use arrow::ipc::reader::StreamReader;
+use arrow::ipc::writer::StreamWriter;
 use arroyo_datastream::logical::{DylibUdfConfig, PythonUdfConfig};
 use arroyo_metrics::TaskCounters;
 use arroyo_rpc::df::ArroyoSchema;
-use arroyo_rpc::errors::DataflowResult;
+use arroyo_rpc::errors::{DataflowError, DataflowResult};
 use arroyo_rpc::grpc::rpc::{TableConfig, TaskCheckpointEventType};
 use arroyo_rpc::{ControlMessage, ControlResp};
+use arroyo_state::global_table_config;
+use arroyo_state::tables::global_keyed_map::GlobalKeyedView;
 use arroyo_state::tables::table_manager::TableManager;
 use arroyo_storage::StorageProvider;
 use arroyo_types::{
@@ -24,6 +28,7 @@ use arroyo_udf_host::parse::inner_type;
 use arroyo_udf_host::{ContainerOrLocal, LocalUdf, SyncUdfDylib, UdfDylib, UdfInterface};
 use arroyo_udf_python::PythonUDF;
 use async_trait::async_trait;
+use bincode::{Decode, Encode};
 use datafusion::common::{DataFusionError, Result as DFResult};
 use datafusion::execution::FunctionRegistry;
 use datafusion::logical_expr::expr_rewriter::FunctionRewrite;
@@ -65,6 +70,7 @@ pub trait OperatorConstructor: Send {
 pub struct SourceNode {
     pub operator: Box<dyn SourceOperator + Send>,
     pub context: OperatorContext,
+    pub pause: Option<SourcePauseHandle>,
 }
 
 pub enum ConstructedOperator {
@@ -161,6 +167,10 @@ impl OperatorNode {
                     &source_context.task_info,
                 );
 
+                if let Some(pause) = s.pause {
+                    collector.set_pause_handle(pause);
+                }
+
                 s.operator.on_start(&mut source_context).await?;
 
                 ready.wait().await;
@@ -190,6 +200,10 @@ impl OperatorNode {
                 }
             }
             OperatorNode::Chained(mut o) => {
+                for (_, ctx) in o.iter_mut() {
+                    ctx.chain_info = chain_info.clone();
+                }
+
                 let result = operator_run_behavior(
                     &mut o,
                     in_qs,
@@ -461,10 +475,104 @@ where
     }
 }
 
+/// Table used by the head of a chain to store data overtaken by unaligned checkpoint barriers
+pub const IN_FLIGHT_TABLE: &str = "__in_flight";
+
+pub fn in_flight_table_config() -> HashMap<String, TableConfig> {
+    global_table_config(
+        IN_FLIGHT_TABLE,
+        "data overtaken by unaligned checkpoint barriers",
+    )
+}
+
+/// A batch that was queued behind an unaligned checkpoint barrier, stored so that it can be
+/// replayed when restoring from that checkpoint
+#[derive(Debug, Clone, Encode, Decode)]
+pub struct InFlightBatch {
+    pub input: u32,
+    pub in_partitions: u32,
+    /// the batch, in the arrow IPC stream format
+    pub data: Vec<u8>,
+}
+
+impl InFlightBatch {
+    fn new(input: usize, in_partitions: usize, batch: &RecordBatch) -> anyhow::Result<Self> {
+        let mut writer = StreamWriter::try_new(Vec::new(), &batch.schema())?;
+        writer.write(batch)?;
+        writer.finish()?;
+
+        Ok(Self {
+            input: input as u32,
+            in_partitions: in_partitions as u32,
+            data: writer.into_inner()?,
+        })
+    }
+
+    fn batch(&self) -> anyhow::Result<RecordBatch> {
+        StreamReader::try_new(self.data.as_slice(), None)?
+            .next()
+            .ok_or_else(|| anyhow!("in-flight data is empty"))?
+            .map_err(|e| e.into())
+    }
+}
+
+/// Selects the in-flight batches stored by the subtasks of the previous run that this subtask is
+/// now responsible for, as `(input, in_partitions, batch)`. Batches from keyed inputs are
+/// re-partitioned by key, as the parallelism may have changed since they were stored; others are
+/// replayed by the subtask that takes over the one that stored them.
+fn in_flight_for_subtask(
+    stored: &HashMap<u32, Vec<InFlightBatch>>,
+    in_schemas: &[Arc<ArroyoSchema>],
+    task_index: u32,
+    parallelism: u32,
+) -> anyhow::Result<Vec<(u32, u32, RecordBatch)>> {
+    let mut subtasks: Vec<_> = stored.keys().copied().collect();
+    subtasks.sort();
+
+    let mut result = vec![];
+    for subtask in subtasks {
+        for b in &stored[&subtask] {
+            let logical_input =
+                (b.input as usize * in_schemas.len()) / (b.in_partitions.max(1) as usize);
+            let keys = in_schemas
+                .get(logical_input)
+                .and_then(|s| s.routing_keys())
+                .filter(|keys| !keys.is_empty());
+
+            let batch = match keys {
+                Some(keys) => {
+                    let batch = b.batch()?;
+                    repartition(&batch, Some(keys), parallelism as usize)
+                        .find(|(server, _)| *server == task_index as usize)
+                        .map(|(_, batch)| batch)
+                }
+                None if subtask % parallelism == task_index => Some(b.batch()?),
+                None => None,
+            };
+
+            if let Some(batch) = batch {
+                result.push((b.input, b.in_partitions, batch));
+            }
+        }
+    }
+
+    Ok(result)
+}
+
 pub struct ChainedOperator {
     pub operator: Box<dyn ArrowOperator>,
     pub context: OperatorContext,
     pub next: Option<Box<ChainedOperator>>,
+    /// whether this is the head of a chain in a pipeline with unaligned checkpoints enabled.
+    ///
+    /// Barriers overtake the data queued on their input, and the first to arrive snapshots our
+    /// state and is forwarded right away, without blocking any inputs. The checkpoint stays open,
+    /// storing the data that arrives on the other inputs, until their barriers arrive too.
+    /// Stopping checkpoints are still aligned, as we shut down after them.
+    pub unaligned_checkpoints: bool,
+    in_flight: Vec<InFlightBatch>,
+    /// the epoch of the unaligned checkpoint that is open, if any
+    open_checkpoint: Option<u32>,
 }
 
 impl ChainedOperator {
@@ -473,6 +581,9 @@ impl ChainedOperator {
             operator,
             context,
             next: None,
+            unaligned_checkpoints: false,
+            in_flight: vec![],
+            open_checkpoint: None,
         }
     }
 }
@@ -605,6 +716,59 @@ impl ChainedOperator {
         Ok(())
     }
 
+    /// Processes the data that was overtaken by the barrier of the checkpoint we restored from,
+    /// which must happen before any new data is read from our inputs
+    async fn replay_in_flight(
+        &mut self,
+        in_partitions: usize,
+        final_collector: &mut ArrowCollector,
+    ) -> DataflowResult<()> {
+        if !self.unaligned_checkpoints {
+            return Ok(());
+        }
+
+        let task_index = self.context.task_info.task_index;
+        let parallelism = self.context.task_info.parallelism;
+        let state: &mut GlobalKeyedView<u32, Vec<InFlightBatch>> = self
+            .context
+            .table_manager
+            .get_global_keyed_state(IN_FLIGHT_TABLE)
+            .await
+            .expect("in-flight table should be registered");
+
+        let in_flight = in_flight_for_subtask(
+            state.get_all(),
+            &self.context.in_schemas,
+            task_index,
+            parallelism,
+        )
+        .map_err(|e| {
+            DataflowError::InternalOperatorError {
+                error: "failed to decode in-flight data",
+                message: e.to_string(),
+            }
+            .with_operator(self.context.task_info.operator_id.clone())
+        })?;
+
+        if !in_flight.is_empty() {
+            info!(
+                "replaying {} in-flight batches for {}",
+                in_flight.len(),
+                self.context.task_info
+            );
+        }
+
+        for (input, stored_partitions, batch) in in_flight {
+            // if our parallelism has changed, map the input onto the same position in our
+            // current inputs so multi-input operators see it on the right side
+            let idx = (input as usize * in_partitions) / (stored_partitions.max(1) as usize);
+            self.process_batch_index(idx, in_partitions, batch, final_collector)
+                .await?;
+        }
+
+        Ok(())
+    }
+
     async fn process_batch_index<'a, 'b>(
         &'a mut self,
         index: usize,
@@ -662,6 +826,7 @@ impl ChainedOperator {
         &mut self,
         idx: usize,
         message: &SignalMessage,
+        overtaken: Vec<RecordBatch>,
         counter: &mut CheckpointCounter,
         closed: &mut HashSet<usize>,
         in_partitions: usize,
@@ -687,6 +852,29 @@ impl ChainedOperator {
                         .unwrap();
                 }
 
+                self.store_in_flight(idx, in_partitions, &overtaken)?;
+
+                if self.unaligned_checkpoints && !t.then_stop && in_partitions > 1 {
+                    if counter.all_clear() {
+                        debug!("Starting unaligned checkpoint {chain_info}");
+
+                        self.start_unaligned_checkpoint(t, control_tx, collector)
+                            .await
+                            .map_err(|e| {
+                                e.with_operator(self.context.task_info.operator_id.clone())
+                            })?;
+
+                        collector.broadcast(SignalMessage::Barrier(*t)).await;
+                    }
+
+                    if counter.mark(idx, t) {
+                        debug!("Finishing unaligned checkpoint {chain_info}");
+                        self.finish_unaligned_checkpoint().await;
+                    }
+
+                    return Ok(ControlOutcome::Continue);
+                }
+
                 if counter.mark(idx, t) {
                     debug!("Checkpointing {chain_info}");
 
@@ -730,6 +918,40 @@ impl ChainedOperator {
         Ok(ControlOutcome::Continue)
     }
 
+    fn store_in_flight(
+        &mut self,
+        idx: usize,
+        in_partitions: usize,
+        batches: &[RecordBatch],
+    ) -> DataflowResult<()> {
+        for batch in batches {
+            self.in_flight
+                .push(InFlightBatch::new(idx, in_partitions, batch).map_err(|e| {
+                    DataflowError::InternalOperatorError {
+                        error: "failed to encode in-flight data",
+                        message: e.to_string(),
+                    }
+                    .with_operator(self.context.task_info.operator_id.clone())
+                })?);
+        }
+        Ok(())
+    }
+
+    /// Stores a batch that arrived on an input ahead of the barrier of the open unaligned
+    /// checkpoint. It has to be replayed when restoring, as our snapshot was taken without it.
+    fn record_in_flight(
+        &mut self,
+        idx: usize,
+        in_partitions: usize,
+        batch: &RecordBatch,
+        counter: &CheckpointCounter,
+    ) -> DataflowResult<()> {
+        if self.open_checkpoint.is_some() && !counter.is_blocked(idx) {
+            self.store_in_flight(idx, in_partitions, std::slice::from_ref(batch))?;
+        }
+        Ok(())
+    }
+
     async fn handle_watermark(
         &mut self,
         watermark: Watermark,
@@ -844,6 +1066,20 @@ impl ChainedOperator {
 
         call_with_collector!(self, final_collector, handle_checkpoint, *t);
 
+        if self.unaligned_checkpoints {
+            // we always write an entry, so that in-flight data from previous checkpoints is
+            // not replayed
+            let in_flight = std::mem::take(&mut self.in_flight);
+            let task_index = self.context.task_info.task_index;
+            self.context
+                .table_manager
+                .get_global_keyed_state(IN_FLIGHT_TABLE)
+                .await
+                .expect("in-flight table should be registered")
+                .insert(task_index, in_flight)
+                .await;
+        }
+
         send_checkpoint_event(
             control_tx,
             &self.context.task_info,
@@ -870,6 +1106,71 @@ impl ChainedOperator {
         Ok(())
     }
 
+    /// Snapshots the chain on the first barrier of an unaligned checkpoint. The head's checkpoint
+    /// stays open until [`ChainedOperator::finish_unaligned_checkpoint`], so that the in-flight
+    /// data from the inputs that haven't delivered the barrier yet can be stored with it.
+    async fn start_unaligned_checkpoint(
+        &mut self,
+        t: &CheckpointBarrier,
+        control_tx: &Sender<ControlResp>,
+        final_collector: &mut ArrowCollector,
+    ) -> DataflowResult<()> {
+        send_checkpoint_event(
+            control_tx,
+            &self.context.task_info,
+            *t,
+            TaskCheckpointEventType::StartedCheckpointing,
+        )
+        .await;
+
+        call_with_collector!(self, final_collector, handle_checkpoint, *t);
+
+        send_checkpoint_event(
+            control_tx,
+            &self.context.task_info,
+            *t,
+            TaskCheckpointEventType::FinishedOperatorSetup,
+        )
+        .await;
+
+        let last_watermark = self.context.watermarks.last_present_watermark();
+        self.context
+            .table_manager
+            .start_checkpoint(*t, last_watermark)
+            .await;
+        self.open_checkpoint = Some(t.epoch);
+
+        send_checkpoint_event(
+            control_tx,
+            &self.context.task_info,
+            *t,
+            TaskCheckpointEventType::FinishedSync,
+        )
+        .await;
+
+        if let Some(next) = &mut self.next {
+            Box::pin(next.run_checkpoint(t, control_tx, final_collector)).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Stores the in-flight data once the barrier of the open unaligned checkpoint has arrived on
+    /// all of our inputs, which completes our part of the checkpoint
+    async fn finish_unaligned_checkpoint(&mut self) {
+        let in_flight = std::mem::take(&mut self.in_flight);
+        let task_index = self.context.task_info.task_index;
+        self.context
+            .table_manager
+            .get_global_keyed_state(IN_FLIGHT_TABLE)
+            .await
+            .expect("in-flight table should be registered")
+            .insert_into_checkpoint(task_index, in_flight)
+            .await;
+        self.context.table_manager.finish_checkpoint().await;
+        self.open_checkpoint = None;
+    }
+
     async fn handle_tick(
         &mut self,
         tick: u64,
@@ -960,10 +1261,22 @@ async fn operator_run_behavior(
     let mut sel = InQReader::new();
     let in_partitions = in_qs.len();
 
+    this.replay_in_flight(in_partitions, collector).await?;
+
+    let unaligned_checkpoints = this.unaligned_checkpoints;
     for (i, q) in in_qs.iter_mut().enumerate() {
         let stream = async_stream::stream! {
-          while let Some(item) = q.recv().await {
-            yield(i, item);
+          loop {
+            // scanning the queue for barriers has a cost, so only do it when barriers may overtake
+            let next = if unaligned_checkpoints {
+                q.recv_overtaking().await
+            } else {
+                q.recv().await.map(|item| (item, vec![]))
+            };
+            let Some((item, overtaken)) = next else {
+                break;
+            };
+            yield(i, item, overtaken);
           }
         };
         sel.push(Box::pin(stream));
@@ -990,7 +1303,7 @@ async fn operator_run_behavior(
 
             p = sel.next(), if !shutdown_after_commit => {
                 match p {
-                    Some(((idx, message), s)) => {
+                    Some(((idx, message, overtaken), s)) => {
                         let local_idx = idx;
 
                         trace!("[{}] Handling message {}-{}, {:?}",
@@ -1001,6 +1314,7 @@ async fn operator_run_behavior(
                                 TaskCounters::BatchesReceived.for_task(chain_info, |c| c.inc());
                                 TaskCounters::MessagesReceived.for_task(chain_info, |c| c.inc_by(record.num_rows() as u64));
                                 TaskCounters::BytesReceived.for_task(chain_info, |c| c.inc_by(record.get_array_memory_size() as u64));
+                                this.record_in_flight(idx, in_partitions, &record, &counter)?;
                                 this.process_batch_index(idx, in_partitions, record, collector)
                                     .instrument(tracing::trace_span!("handle_fn",
                                         name,
@@ -1009,7 +1323,7 @@ async fn operator_run_behavior(
                                 ).await?;
                             }
                             ArrowMessage::Signal(signal) => {
-                                match this.handle_control_message(idx, &signal, &mut counter, &mut closed, in_partitions,
+                                match this.handle_control_message(idx, &signal, overtaken, &mut counter, &mut closed, in_partitions,
                                     &control_tx, chain_info, collector).await? {
                                     ControlOutcome::Continue => {}
                                     ControlOutcome::Stop => {
@@ -1032,7 +1346,8 @@ async fn operator_run_behavior(
                             }
                         }
 
-                        if counter.is_blocked(idx){
+                        // inputs aren't blocked while an unaligned checkpoint is open
+                        if counter.is_blocked(idx) && this.open_checkpoint.is_none() {
                             blocked.push(s);
                         } else {
                             if counter.all_clear() && !blocked.is_empty(){
@@ -1503,3 +1818,237 @@ impl FunctionRegistry for Registry {
         vec![]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::batch_bounded;
+    use arrow::array::{Int64Array, TimestampNanosecondArray};
+    use arrow::datatypes::{Field, TimeUnit};
+    use arroyo_types::get_test_task_info;
+    use tokio::sync::mpsc::channel;
+
+    fn schema() -> Arc<Schema> {
+        Arc::new(Schema::new(vec![
+            Field::new("key", DataType::Int64, false),
+            Field::new(
+                "_timestamp",
+                DataType::Timestamp(TimeUnit::Nanosecond, None),
+                false,
+            ),
+        ]))
+    }
+
+    fn batch(keys: std::ops::Range<i64>) -> RecordBatch {
+        let len = keys.end - keys.start;
+        RecordBatch::try_new(
+            schema(),
+            vec![
+                Arc::new(Int64Array::from_iter_values(keys)),
+                Arc::new(TimestampNanosecondArray::from_iter_values(0..len)),
+            ],
+        )
+        .unwrap()
+    }
+
+    fn stored(batches: Vec<(u32, RecordBatch)>) -> HashMap<u32, Vec<InFlightBatch>> {
+        let mut stored: HashMap<u32, Vec<InFlightBatch>> = HashMap::new();
+        for (subtask, b) in batches {
+            stored
+                .entry(subtask)
+                .or_default()
+                .push(InFlightBatch::new(0, 1, &b).unwrap());
+        }
+        stored
+    }
+
+    #[test]
+    fn test_restore_keyed_in_flight_at_new_parallelism() {
+        let in_schemas = vec![Arc::new(ArroyoSchema::new_keyed(schema(), 1, vec![0]))];
+        // stored by two subtasks, restored by three
+        let stored = stored(vec![(0, batch(0..100)), (1, batch(100..250))]);
+
+        let keys = vec![0];
+        let mut total = 0;
+        for task_index in 0..3 {
+            let in_flight = in_flight_for_subtask(&stored, &in_schemas, task_index, 3).unwrap();
+            for (input, in_partitions, batch) in in_flight {
+                assert_eq!((input, in_partitions), (0, 1));
+                total += batch.num_rows();
+
+                // every row is replayed on the subtask its key is routed to
+                for (server, _) in repartition(&batch, Some(&keys), 3) {
+                    assert_eq!(server, task_index as usize);
+                }
+            }
+        }
+
+        assert_eq!(total, 250);
+    }
+
+    #[test]
+    fn test_restore_unkeyed_in_flight_at_new_parallelism() {
+        let in_schemas = vec![Arc::new(ArroyoSchema::new_unkeyed(schema(), 1))];
+        let stored = stored(vec![
+            (0, batch(0..10)),
+            (1, batch(10..20)),
+            (2, batch(20..30)),
+        ]);
+
+        let rows = |task_index| -> Vec<usize> {
+            in_flight_for_subtask(&stored, &in_schemas, task_index, 2)
+                .unwrap()
+                .iter()
+                .map(|(_, _, b)| b.num_rows())
+                .collect()
+        };
+
+        // subtask 0 takes over the data of the old subtasks 0 and 2
+        assert_eq!(rows(0), vec![10, 10]);
+        assert_eq!(rows(1), vec![10]);
+    }
+
+    struct PassThrough;
+
+    #[async_trait]
+    impl ArrowOperator for PassThrough {
+        fn name(&self) -> String {
+            "pass_through".to_string()
+        }
+
+        async fn process_batch(
+            &mut self,
+            batch: RecordBatch,
+            _: &mut OperatorContext,
+            collector: &mut dyn Collector,
+        ) -> DataflowResult<()> {
+            collector.collect(batch).await
+        }
+    }
+
+    async fn recv(rx: &mut BatchReceiver) -> ArrowMessage {
+        tokio::time::timeout(Duration::from_secs(5), rx.recv())
+            .await
+            .expect("should receive a message")
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_unaligned_barrier_forwarded_with_stalled_input() {
+        let task_info = Arc::new(TaskInfo {
+            job_id: format!("unaligned-{}", rand::random::<u32>()),
+            ..get_test_task_info()
+        });
+        let in_schema = Arc::new(ArroyoSchema::new_unkeyed(schema(), 1));
+        let (control_tx, mut control_rx) = channel(128);
+        let ctx = OperatorContext::new(
+            task_info,
+            None,
+            control_tx.clone(),
+            2,
+            vec![in_schema.clone(), in_schema.clone()],
+            Some(in_schema.clone()),
+            in_flight_table_config(),
+        )
+        .await;
+
+        let (tx0, rx0) = batch_bounded(1024);
+        let (tx1, rx1) = batch_bounded(1024);
+        let (out_tx, mut out_rx) = batch_bounded(1024);
+
+        let mut op = ChainedOperator::new(Box::new(PassThrough), ctx);
+        op.unaligned_checkpoints = true;
+        let mut collector = ArrowCollector::new(
+            op.context.chain_info.clone(),
+            Some(in_schema),
+            vec![vec![out_tx]],
+        );
+        let barrier = CheckpointBarrier {
+            epoch: 1,
+            min_epoch: 0,
+            timestamp: SystemTime::now(),
+            then_stop: false,
+            unaligned: true,
+        };
+
+        // the barrier is queued behind data on input 0, while input 1 is stalled
+        tx0.send(ArrowMessage::Data(batch(0..4))).await.unwrap();
+        tx0.send(ArrowMessage::Signal(SignalMessage::Barrier(barrier)))
+            .await
+            .unwrap();
+
+        let (_controller_tx, controller_rx) = channel(16);
+        let run = tokio::spawn(async move {
+            let mut in_qs = vec![rx0, rx1];
+            let result = operator_run_behavior(
+                &mut op,
+                &mut in_qs,
+                control_tx,
+                controller_rx,
+                &mut collector,
+                Arc::new(Barrier::new(1)),
+            )
+            .await;
+            (op, result)
+        });
+
+        // the barrier is forwarded ahead of the data it overtook, without waiting for input 1
+        assert_eq!(
+            recv(&mut out_rx).await,
+            ArrowMessage::Signal(SignalMessage::Barrier(barrier))
+        );
+        assert_eq!(recv(&mut out_rx).await, ArrowMessage::Data(batch(0..4)));
+
+        // input 1 isn't blocked, and its data ahead of the barrier is processed as it arrives
+        tx1.send(ArrowMessage::Data(batch(10..13))).await.unwrap();
+        assert_eq!(recv(&mut out_rx).await, ArrowMessage::Data(batch(10..13)));
+
+        // our part of the checkpoint isn't complete until the barrier arrives on input 1 too
+        while let Ok(resp) = control_rx.try_recv() {
+            assert!(!matches!(resp, ControlResp::CheckpointCompleted(_)));
+        }
+        tx1.send(ArrowMessage::Signal(SignalMessage::Barrier(barrier)))
+            .await
+            .unwrap();
+        loop {
+            let resp = tokio::time::timeout(Duration::from_secs(5), control_rx.recv())
+                .await
+                .expect("checkpoint should complete")
+                .unwrap();
+            if let ControlResp::CheckpointCompleted(c) = resp {
+                assert_eq!(c.checkpoint_epoch, 1);
+                assert!(
+                    c.subtask_metadata
+                        .table_metadata
+                        .contains_key(IN_FLIGHT_TABLE)
+                );
+                break;
+            }
+        }
+
+        tx0.send(ArrowMessage::Signal(SignalMessage::Stop))
+            .await
+            .unwrap();
+        tx1.send(ArrowMessage::Signal(SignalMessage::Stop))
+            .await
+            .unwrap();
+        let (mut op, result) = run.await.unwrap();
+        assert_eq!(result.unwrap(), Some(SignalMessage::Stop));
+
+        // the barrier was only forwarded once
+        assert_eq!(out_rx.recv().await, None);
+
+        // the checkpoint stores the data from both inputs that we processed after snapshotting
+        let in_flight: &mut GlobalKeyedView<u32, Vec<InFlightBatch>> = op
+            .context
+            .table_manager
+            .get_global_keyed_state(IN_FLIGHT_TABLE)
+            .await
+            .unwrap();
+        let stored: Vec<_> = in_flight.get_all()[&0]
+            .iter()
+            .map(|b| (b.input, b.batch().unwrap()))
+            .collect();
+        assert_eq!(stored, vec![(0, batch(0..4)), (1, batch(10..13))]);
+    }
+}
@@ -10,14 +10,16 @@ use arroyo_rpc::{
     TIMESTAMP_FIELD,
     df::{ArroyoSchema, ArroyoSchemaRef},
     grpc::api::{
-        SessionWindowAggregateOperator, SlidingWindowAggregateOperator,
-        TumblingWindowAggregateOperator,
+        CumulatingWindowAggregateOperator, SessionWindowAggregateOperator,
+        SlidingWindowAggregateOperator, TumblingWindowAggregateOperator,
     },
+    updating_meta_field,
 };
 use datafusion::common::{
     Column, DFSchema, DFSchemaRef, Result, ScalarValue, internal_err, plan_err,
 };
 use datafusion::error::DataFusionError;
+use datafusion::functions::datetime::date_bin;
 use datafusion::logical_expr;
 use datafusion::logical_expr::{
     Aggregate, BinaryExpr, Expr, Extension, LogicalPlan, UserDefinedLogicalNodeCore,
@@ -47,25 +49,50 @@ pub(crate) struct AggregateExtension {
     pub(crate) schema: DFSchemaRef,
     pub(crate) key_fields: Vec<usize>,
     pub(crate) final_calculation: LogicalPlan,
+    pub(crate) allowed_lateness: Duration,
 }
 
-multifield_partial_ord!(AggregateExtension, aggregate, key_fields, final_calculation);
+multifield_partial_ord!(
+    AggregateExtension,
+    aggregate,
+    key_fields,
+    final_calculation,
+    allowed_lateness
+);
 
 impl AggregateExtension {
+    /// With an allowed lateness, windows emit on the watermark and then retract and update their
+    /// results for late records, so the output is updating.
     pub fn new(
         window_behavior: WindowBehavior,
         aggregate: LogicalPlan,
         key_fields: Vec<usize>,
+        allowed_lateness: Duration,
     ) -> Self {
         let final_calculation =
             Self::final_projection(&aggregate, window_behavior.clone()).unwrap();
 
+        let schema = if allowed_lateness.is_zero() {
+            final_calculation.schema().clone()
+        } else {
+            let mut fields = fields_with_qualifiers(final_calculation.schema());
+            fields.push((None, updating_meta_field()).into());
+            Arc::new(
+                schema_from_df_fields_with_metadata(
+                    &fields,
+                    final_calculation.schema().metadata().clone(),
+                )
+                .unwrap(),
+            )
+        };
+
         Self {
             window_behavior,
             aggregate,
-            schema: final_calculation.schema().clone(),
+            schema,
             key_fields,
             final_calculation,
+            allowed_lateness,
         }
     }
 
@@ -104,6 +131,7 @@ impl AggregateExtension {
             partial_aggregation_plan: partial_aggregation_plan.encode_to_vec(),
             final_aggregation_plan: finish_plan.encode_to_vec(),
             final_projection: Some(final_physical_plan_node.encode_to_vec()),
+            allowed_lateness_micros: self.allowed_lateness.as_micros() as u64,
         };
 
         Ok(LogicalNode::single(
@@ -154,7 +182,7 @@ impl AggregateExtension {
             partial_aggregation_plan: partial_aggregation_plan.encode_to_vec(),
             final_aggregation_plan: finish_plan.encode_to_vec(),
             final_projection: final_physical_plan_node.encode_to_vec(),
-            // TODO add final aggregation.
+            allowed_lateness_micros: self.allowed_lateness.as_micros() as u64,
         };
 
         Ok(LogicalNode::single(
@@ -167,6 +195,61 @@ impl AggregateExtension {
         ))
     }
 
+    pub fn cumulating_window_config(
+        &self,
+        planner: &Planner,
+        index: usize,
+        input_schema: DFSchemaRef,
+        offset: Duration,
+        step: Duration,
+        size: Duration,
+    ) -> Result<LogicalNode> {
+        // partial aggregates are computed per step, and each emission merges those from the start
+        // of the enclosing window
+        let binning_function_proto = planner.binning_function_proto(step, input_schema.clone())?;
+
+        let SplitPlanOutput {
+            partial_aggregation_plan,
+            partial_schema,
+            finish_plan,
+        } = planner.split_physical_plan(self.key_fields.clone(), &self.aggregate, true)?;
+
+        let final_physical_plan = planner.sync_plan(&self.final_calculation)?;
+        let final_physical_plan_node = PhysicalPlanNode::try_from_physical_plan(
+            final_physical_plan,
+            &ArroyoPhysicalExtensionCodec::default(),
+        )?;
+
+        let config = CumulatingWindowAggregateOperator {
+            name: format!("CumulatingWindow<{size:?}>"),
+            step_micros: step.as_micros() as u64,
+            size_micros: size.as_micros() as u64,
+            offset_micros: offset.as_micros() as u64,
+            binning_function: binning_function_proto.encode_to_vec(),
+            input_schema: Some(
+                ArroyoSchema::from_schema_keys(
+                    Arc::new(input_schema.as_ref().into()),
+                    self.key_fields.clone(),
+                )?
+                .into(),
+            ),
+            partial_schema: Some(partial_schema.into()),
+            partial_aggregation_plan: partial_aggregation_plan.encode_to_vec(),
+            final_aggregation_plan: finish_plan.encode_to_vec(),
+            final_projection: final_physical_plan_node.encode_to_vec(),
+            allowed_lateness_micros: self.allowed_lateness.as_micros() as u64,
+        };
+
+        Ok(LogicalNode::single(
+            index as u32,
+            format!("cumulating_window_{index}"),
+            OperatorName::CumulatingWindowAggregate,
+            config.encode_to_vec(),
+            "cumulating window".to_string(),
+            1,
+        ))
+    }
+
     pub fn session_window_config(
         &self,
         planner: &Planner,
@@ -218,6 +301,7 @@ impl AggregateExtension {
             unkeyed_aggregate_schema: None,
             partial_aggregation_plan: vec![],
             final_aggregation_plan: physical_plan_node.encode_to_vec(),
+            allowed_lateness_micros: self.allowed_lateness.as_micros() as u64,
         };
 
         Ok(LogicalNode::single(
@@ -276,6 +360,7 @@ impl AggregateExtension {
             partial_aggregation_plan: partial_aggregation_plan.encode_to_vec(),
             final_aggregation_plan: finish_plan.encode_to_vec(),
             final_projection,
+            allowed_lateness_micros: self.allowed_lateness.as_micros() as u64,
         };
 
         Ok(LogicalNode::single(
@@ -308,6 +393,9 @@ impl AggregateExtension {
             .iter()
             .map(|field| Expr::Column(field.qualified_column()))
             .collect();
+        // for cumulating windows, _timestamp is the start of the last step, and the window starts at
+        // the beginning of the enclosing window of `size`, counted from `offset` past the epoch
+        let mut cumulating_size = None;
         let (window_field, window_index, width, is_nested) = match window_behavior {
             WindowBehavior::InData => return Ok(timestamp_append),
             WindowBehavior::FromOperator {
@@ -319,6 +407,10 @@ impl AggregateExtension {
                 WindowType::Tumbling { width, .. } | WindowType::Sliding { width, .. } => {
                     (window_field, window_index, width, is_nested)
                 }
+                WindowType::Cumulating { offset, step, size } => {
+                    cumulating_size = Some((offset, size));
+                    (window_field, window_index, step, is_nested)
+                }
                 WindowType::Session { .. } => {
                     return Ok(LogicalPlan::Extension(Extension {
                         node: Arc::new(WindowAppendExtension::new(
@@ -343,11 +435,29 @@ impl AggregateExtension {
             Column::new(timestamp_field.qualifier().cloned(), timestamp_field.name());
         aggregate_fields.insert(window_index, window_field.clone());
 
+        let window_start =
+            match cumulating_size {
+                Some((offset, size)) => date_bin().call(vec![
+                    Expr::Literal(
+                        ScalarValue::IntervalMonthDayNano(Some(
+                            IntervalMonthDayNanoType::make_value(0, 0, size.as_nanos() as i64),
+                        )),
+                        None,
+                    ),
+                    Expr::Column(timestamp_column.clone()),
+                    Expr::Literal(
+                        ScalarValue::TimestampNanosecond(Some(offset.as_nanos() as i64), None),
+                        None,
+                    ),
+                ]),
+                None => Expr::Column(timestamp_column.clone()),
+            };
+
         let window_expression = Expr::ScalarFunction(ScalarFunction {
             func: window(),
             args: vec![
-                // copy bin_start as first argument
-                Expr::Column(timestamp_column.clone()),
+                // bin_start, or the start of the enclosing window for cumulating windows
+                window_start,
                 // add width interval to _timestamp for bin end
                 Expr::BinaryExpr(BinaryExpr {
                     left: Box::new(Expr::Column(timestamp_column.clone())),
@@ -490,6 +600,7 @@ impl UserDefinedLogicalNodeCore for AggregateExtension {
             self.window_behavior.clone(),
             inputs[0].clone(),
             self.key_fields.clone(),
+            self.allowed_lateness,
         ))
     }
 }
@@ -532,6 +643,15 @@ impl ArroyoExtension for AggregateExtension {
                             *width,
                             *slide,
                         )?,
+                        WindowType::Cumulating { offset, step, size } => self
+                            .cumulating_window_config(
+                                planner,
+                                index,
+                                input_df_schema,
+                                *offset,
+                                *step,
+                                *size,
+                            )?,
                         WindowType::Instant => {
                             return plan_err!(
                                 "instant window not supported in aggregate extension"
@@ -23,7 +23,7 @@ pub struct DebeziumUnrollingExtension {
     input: LogicalPlan,
     schema: DFSchemaRef,
     pub primary_keys: Vec<usize>,
-    primary_key_names: Arc<Vec<String>>,
+    pub(crate) primary_key_names: Arc<Vec<String>>,
 }
 
 multifield_partial_ord!(
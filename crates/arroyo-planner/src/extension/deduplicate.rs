@@ -0,0 +1,149 @@
+use crate::builder::{NamedNode, Planner};
+use crate::extension::key_calculation::KeyCalculationExtension;
+use crate::extension::{ArroyoExtension, NodeWithIncomingEdges};
+use crate::{fields_with_qualifiers, multifield_partial_ord, schema_from_df_fields_with_metadata};
+use arroyo_datastream::logical::{LogicalEdge, LogicalEdgeType, LogicalNode, OperatorName};
+use arroyo_rpc::df::{ArroyoSchema, ArroyoSchemaRef};
+use arroyo_rpc::grpc::api::DeduplicateOperator;
+use datafusion::common::{DFSchemaRef, Result, internal_err, plan_err};
+use datafusion::logical_expr::{Expr, Extension, LogicalPlan, UserDefinedLogicalNodeCore};
+use prost::Message;
+use std::fmt::Formatter;
+use std::sync::Arc;
+use std::time::Duration;
+
+pub(crate) const DEDUPLICATE_NODE_NAME: &str = "DeduplicateNode";
+
+/// Emits only the first row (by event time) for each value of the key, remembering keys for
+/// the TTL after their first row. It's planned from `ROW_NUMBER() OVER (PARTITION BY ... ORDER BY
+/// <event time>) = 1` over an unkeyed input, which is keyed by the partition expressions once the
+/// input has been rewritten.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct DeduplicateExtension {
+    pub(crate) input: LogicalPlan,
+    pub(crate) partition_by: Vec<Expr>,
+    pub(crate) keyed: bool,
+    pub(crate) schema: DFSchemaRef,
+    pub(crate) ttl: Duration,
+}
+
+multifield_partial_ord!(DeduplicateExtension, input, partition_by, keyed, ttl);
+
+impl DeduplicateExtension {
+    pub(crate) fn new(input: LogicalPlan, partition_by: Vec<Expr>, ttl: Duration) -> Self {
+        Self {
+            schema: input.schema().clone(),
+            input,
+            partition_by,
+            keyed: false,
+            ttl,
+        }
+    }
+
+    /// Replaces the input with its keyed version, which has the partition columns first
+    pub(crate) fn with_keyed_input(&self, input: KeyCalculationExtension) -> Result<Self> {
+        let schema = Arc::new(schema_from_df_fields_with_metadata(
+            &fields_with_qualifiers(&input.schema)[self.partition_by.len()..],
+            input.schema.metadata().clone(),
+        )?);
+
+        Ok(Self {
+            input: LogicalPlan::Extension(Extension {
+                node: Arc::new(input),
+            }),
+            partition_by: self.partition_by.clone(),
+            keyed: true,
+            schema,
+            ttl: self.ttl,
+        })
+    }
+}
+
+impl ArroyoExtension for DeduplicateExtension {
+    fn node_name(&self) -> Option<NamedNode> {
+        None
+    }
+
+    fn plan_node(
+        &self,
+        _planner: &Planner,
+        index: usize,
+        input_schemas: Vec<ArroyoSchemaRef>,
+    ) -> Result<NodeWithIncomingEdges> {
+        if input_schemas.len() != 1 {
+            return plan_err!("deduplication should have exactly one input");
+        }
+        if !self.keyed {
+            return internal_err!("deduplication input was never keyed");
+        }
+        let input_schema = input_schemas[0].clone();
+
+        let config = DeduplicateOperator {
+            name: format!("deduplicate_{index}"),
+            input_schema: Some(input_schema.as_ref().clone().into()),
+            ttl_micros: self.ttl.as_micros() as u64,
+        };
+
+        let logical_node = LogicalNode::single(
+            index as u32,
+            format!("deduplicate_{index}"),
+            OperatorName::Deduplicate,
+            config.encode_to_vec(),
+            "deduplicate".to_string(),
+            1,
+        );
+
+        let edge = LogicalEdge::project_all(LogicalEdgeType::Shuffle, (*input_schema).clone());
+        Ok(NodeWithIncomingEdges {
+            node: logical_node,
+            edges: vec![edge],
+        })
+    }
+
+    fn output_schema(&self) -> ArroyoSchema {
+        ArroyoSchema::from_schema_unkeyed(self.schema.inner().clone()).unwrap()
+    }
+}
+
+impl UserDefinedLogicalNodeCore for DeduplicateExtension {
+    fn name(&self) -> &str {
+        DEDUPLICATE_NODE_NAME
+    }
+
+    fn inputs(&self) -> Vec<&LogicalPlan> {
+        vec![&self.input]
+    }
+
+    fn schema(&self) -> &DFSchemaRef {
+        &self.schema
+    }
+
+    fn expressions(&self) -> Vec<Expr> {
+        vec![]
+    }
+
+    fn fmt_for_explain(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(f, "DeduplicateExtension: {}", self.schema)
+    }
+
+    fn with_exprs_and_inputs(&self, _exprs: Vec<Expr>, inputs: Vec<LogicalPlan>) -> Result<Self> {
+        let [input] = &inputs[..] else {
+            return internal_err!("deduplication must have one input");
+        };
+
+        // the input changes when it's rewritten, before it's keyed
+        let schema = if self.keyed {
+            self.schema.clone()
+        } else {
+            input.schema().clone()
+        };
+
+        Ok(Self {
+            input: input.clone(),
+            partition_by: self.partition_by.clone(),
+            keyed: self.keyed,
+            schema,
+            ttl: self.ttl,
+        })
+    }
+}
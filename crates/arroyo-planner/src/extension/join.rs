@@ -14,10 +14,18 @@ use std::time::Duration;
 
 pub(crate) const JOIN_NODE_NAME: &str = "JoinNode";
 
+/// The bounds of `right._timestamp - left._timestamp` for rows matched by an interval join
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd)]
+pub(crate) struct IntervalBounds {
+    pub(crate) lower_micros: i64,
+    pub(crate) upper_micros: i64,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd)]
 pub struct JoinExtension {
     pub(crate) rewritten_join: LogicalPlan,
     pub(crate) is_instant: bool,
+    pub(crate) interval: Option<IntervalBounds>,
     pub(crate) ttl: Option<Duration>,
 }
 
@@ -46,6 +54,8 @@ impl ArroyoExtension for JoinExtension {
 
         let operator_name = if self.is_instant {
             OperatorName::InstantJoin
+        } else if self.interval.is_some() {
+            OperatorName::IntervalJoin
         } else {
             OperatorName::Join
         };
@@ -57,6 +67,8 @@ impl ArroyoExtension for JoinExtension {
             output_schema: Some(self.output_schema().into()),
             join_plan: physical_plan_node.encode_to_vec(),
             ttl_micros: self.ttl.map(|t| t.as_micros() as u64),
+            lower_bound_micros: self.interval.map(|i| i.lower_micros),
+            upper_bound_micros: self.interval.map(|i| i.upper_micros),
         };
 
         let logical_node = LogicalNode::single(
@@ -108,6 +120,7 @@ impl UserDefinedLogicalNodeCore for JoinExtension {
         Ok(Self {
             rewritten_join: inputs[0].clone(),
             is_instant: self.is_instant,
+            interval: self.interval,
             ttl: self.ttl,
         })
     }
@@ -0,0 +1,147 @@
+use std::fmt::Formatter;
+use std::sync::Arc;
+use std::time::Duration;
+
+use arroyo_datastream::logical::{LogicalEdge, LogicalEdgeType, LogicalNode, OperatorName};
+use arroyo_rpc::df::{ArroyoSchema, ArroyoSchemaRef};
+use arroyo_rpc::grpc::api::LateRecordsOperator;
+use datafusion::common::{DFSchema, DFSchemaRef, Result, TableReference, internal_err, plan_err};
+use datafusion::logical_expr::{Expr, LogicalPlan, UserDefinedLogicalNodeCore};
+use prost::Message;
+
+use crate::builder::{NamedNode, Planner};
+use crate::multifield_partial_ord;
+
+use super::{ArroyoExtension, NodeWithIncomingEdges};
+
+pub(crate) const LATE_RECORDS_EXTENSION_NAME: &str = "LateRecordsExtension";
+
+/// Plans `late_records(...)`: takes the watermarked stream of a source and emits only the
+/// records that a window with bins of `bin_width` drops, because the watermark has passed the bin
+/// they fall into by more than the allowed lateness.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct LateRecordsExtension {
+    pub(crate) input: LogicalPlan,
+    pub(crate) qualifier: TableReference,
+    pub(crate) schema: DFSchemaRef,
+    pub(crate) allowed_lateness: Duration,
+    pub(crate) bin_width: Duration,
+}
+
+multifield_partial_ord!(
+    LateRecordsExtension,
+    input,
+    qualifier,
+    allowed_lateness,
+    bin_width
+);
+
+impl LateRecordsExtension {
+    /// `qualifier` is the name of the table function scan, which the rest of the query uses to
+    /// refer to our fields
+    pub(crate) fn new(
+        input: LogicalPlan,
+        qualifier: TableReference,
+        allowed_lateness: Duration,
+        bin_width: Duration,
+    ) -> Result<Self> {
+        let schema = Arc::new(DFSchema::try_from_qualified_schema(
+            qualifier.clone(),
+            input.schema().as_arrow(),
+        )?);
+
+        Ok(Self {
+            input,
+            qualifier,
+            schema,
+            allowed_lateness,
+            bin_width,
+        })
+    }
+}
+
+impl UserDefinedLogicalNodeCore for LateRecordsExtension {
+    fn name(&self) -> &str {
+        LATE_RECORDS_EXTENSION_NAME
+    }
+
+    fn inputs(&self) -> Vec<&LogicalPlan> {
+        vec![&self.input]
+    }
+
+    fn schema(&self) -> &DFSchemaRef {
+        &self.schema
+    }
+
+    fn expressions(&self) -> Vec<Expr> {
+        vec![]
+    }
+
+    fn fmt_for_explain(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "LateRecordsExtension({}): allowed_lateness: {:?}, bin_width: {:?}",
+            self.qualifier, self.allowed_lateness, self.bin_width
+        )
+    }
+
+    fn with_exprs_and_inputs(&self, _exprs: Vec<Expr>, inputs: Vec<LogicalPlan>) -> Result<Self> {
+        if inputs.len() != 1 {
+            return internal_err!("input size inconsistent");
+        }
+
+        Self::new(
+            inputs[0].clone(),
+            self.qualifier.clone(),
+            self.allowed_lateness,
+            self.bin_width,
+        )
+    }
+}
+
+impl ArroyoExtension for LateRecordsExtension {
+    fn node_name(&self) -> Option<NamedNode> {
+        None
+    }
+
+    fn plan_node(
+        &self,
+        _planner: &Planner,
+        index: usize,
+        input_schemas: Vec<ArroyoSchemaRef>,
+    ) -> Result<NodeWithIncomingEdges> {
+        if input_schemas.len() != 1 {
+            return plan_err!("LateRecordsExtension should have exactly one input");
+        }
+
+        let config = LateRecordsOperator {
+            name: format!("late_records({})", self.qualifier),
+            input_schema: Some(input_schemas[0].as_ref().clone().into()),
+            allowed_lateness_micros: self.allowed_lateness.as_micros() as u64,
+            bin_width_micros: self.bin_width.as_micros() as u64,
+        };
+
+        let node = LogicalNode::single(
+            index as u32,
+            format!("late_records_{index}"),
+            OperatorName::LateRecords,
+            config.encode_to_vec(),
+            config.name.clone(),
+            1,
+        );
+
+        // shuffle so that, like the windows downstream of the source, we see the minimum
+        // watermark across all of its subtasks
+        let edge =
+            LogicalEdge::project_all(LogicalEdgeType::Shuffle, input_schemas[0].as_ref().clone());
+
+        Ok(NodeWithIncomingEdges {
+            node,
+            edges: vec![edge],
+        })
+    }
+
+    fn output_schema(&self) -> ArroyoSchema {
+        ArroyoSchema::from_schema_unkeyed(Arc::new(self.schema.as_ref().into())).unwrap()
+    }
+}
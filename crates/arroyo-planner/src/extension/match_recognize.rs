@@ -0,0 +1,224 @@
+use crate::builder::{NamedNode, Planner};
+use crate::extension::{ArroyoExtension, NodeWithIncomingEdges};
+use crate::multifield_partial_ord;
+use arroyo_datastream::logical::{LogicalEdge, LogicalEdgeType, LogicalNode, OperatorName};
+use arroyo_rpc::df::{ArroyoSchema, ArroyoSchemaRef};
+use arroyo_rpc::grpc::api::{
+    MatchAfterSkip, MatchMeasureAggregate, MatchMeasureInput, MatchNfaState, MatchNfaTransition,
+    MatchRecognizeOperator, MatchSymbol,
+};
+use datafusion::common::{DFSchema, DFSchemaRef, Result, internal_err, plan_err};
+use datafusion::logical_expr::{Expr, LogicalPlan, UserDefinedLogicalNodeCore};
+use prost::Message;
+use std::fmt::Formatter;
+use std::time::Duration;
+
+pub(crate) const MATCH_RECOGNIZE_NODE_NAME: &str = "MatchRecognizeNode";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd)]
+pub(crate) enum MeasureAggregate {
+    First,
+    Last,
+    Count,
+    Sum,
+    Min,
+    Max,
+    Avg,
+}
+
+impl From<MeasureAggregate> for MatchMeasureAggregate {
+    fn from(value: MeasureAggregate) -> Self {
+        match value {
+            MeasureAggregate::First => MatchMeasureAggregate::MeasureFirst,
+            MeasureAggregate::Last => MatchMeasureAggregate::MeasureLast,
+            MeasureAggregate::Count => MatchMeasureAggregate::MeasureCount,
+            MeasureAggregate::Sum => MatchMeasureAggregate::MeasureSum,
+            MeasureAggregate::Min => MatchMeasureAggregate::MeasureMin,
+            MeasureAggregate::Max => MatchMeasureAggregate::MeasureMax,
+            MeasureAggregate::Avg => MatchMeasureAggregate::MeasureAvg,
+        }
+    }
+}
+
+/// A value computed over the rows of a match (optionally only those mapped to one symbol),
+/// which the MEASURES are evaluated against
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd)]
+pub(crate) struct MeasureInput {
+    pub(crate) symbol: Option<usize>,
+    pub(crate) column: Option<usize>,
+    pub(crate) aggregate: MeasureAggregate,
+}
+
+/// A state of the pattern's NFA, with transitions as (symbol, target state)
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, PartialOrd)]
+pub(crate) struct NfaState {
+    pub(crate) transitions: Vec<(usize, usize)>,
+    pub(crate) accepting: bool,
+}
+
+/// Row pattern recognition (`MATCH_RECOGNIZE`) over an input keyed by its PARTITION BY
+/// columns. Each symbol of the pattern has an optional condition over the input, and the pattern
+/// itself is compiled into an NFA without epsilon transitions, starting from state 0.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct MatchRecognizeExtension {
+    pub(crate) input: LogicalPlan,
+    pub(crate) schema: DFSchemaRef,
+    pub(crate) symbols: Vec<String>,
+    pub(crate) conditions: Vec<Option<Expr>>,
+    pub(crate) states: Vec<NfaState>,
+    pub(crate) measure_inputs: Vec<MeasureInput>,
+    pub(crate) measure_input_schema: DFSchemaRef,
+    pub(crate) measures: Vec<Expr>,
+    pub(crate) within: Option<Duration>,
+    pub(crate) skip_to_next_row: bool,
+    pub(crate) ttl: Duration,
+}
+
+multifield_partial_ord!(
+    MatchRecognizeExtension,
+    input,
+    symbols,
+    conditions,
+    states,
+    measure_inputs,
+    measures,
+    within,
+    skip_to_next_row,
+    ttl
+);
+
+impl ArroyoExtension for MatchRecognizeExtension {
+    fn node_name(&self) -> Option<NamedNode> {
+        None
+    }
+
+    fn plan_node(
+        &self,
+        planner: &Planner,
+        index: usize,
+        input_schemas: Vec<ArroyoSchemaRef>,
+    ) -> Result<NodeWithIncomingEdges> {
+        if input_schemas.len() != 1 {
+            return plan_err!("MATCH_RECOGNIZE should have exactly one input");
+        }
+        let input_schema = input_schemas[0].clone();
+        let input_df_schema = DFSchema::try_from(input_schema.schema.as_ref().clone())?;
+
+        let symbols = self
+            .symbols
+            .iter()
+            .zip(&self.conditions)
+            .map(|(name, condition)| {
+                Ok(MatchSymbol {
+                    name: name.clone(),
+                    condition: condition
+                        .as_ref()
+                        .map(|c| planner.serialize_as_physical_expr(c, &input_df_schema))
+                        .transpose()?,
+                })
+            })
+            .collect::<Result<_>>()?;
+
+        let measures = self
+            .measures
+            .iter()
+            .map(|m| planner.serialize_as_physical_expr(m, &self.measure_input_schema))
+            .collect::<Result<_>>()?;
+
+        let config = MatchRecognizeOperator {
+            name: format!("match_recognize_{index}"),
+            input_schema: Some(input_schema.as_ref().clone().into()),
+            output_schema: Some(self.output_schema().into()),
+            symbols,
+            states: self
+                .states
+                .iter()
+                .map(|state| MatchNfaState {
+                    transitions: state
+                        .transitions
+                        .iter()
+                        .map(|(symbol, target)| MatchNfaTransition {
+                            symbol: *symbol as u32,
+                            target: *target as u32,
+                        })
+                        .collect(),
+                    accepting: state.accepting,
+                })
+                .collect(),
+            measure_inputs: self
+                .measure_inputs
+                .iter()
+                .map(|input| MatchMeasureInput {
+                    symbol: input.symbol.map(|s| s as u32),
+                    column: input.column.map(|c| c as u32),
+                    aggregate: MatchMeasureAggregate::from(input.aggregate) as i32,
+                })
+                .collect(),
+            measures,
+            within_micros: self.within.map(|w| w.as_micros() as u64),
+            after_match_skip: if self.skip_to_next_row {
+                MatchAfterSkip::SkipToNextRow
+            } else {
+                MatchAfterSkip::SkipPastLastRow
+            } as i32,
+            ttl_micros: self.ttl.as_micros() as u64,
+        };
+
+        let logical_node = LogicalNode::single(
+            index as u32,
+            format!("match_recognize_{index}"),
+            OperatorName::MatchRecognize,
+            config.encode_to_vec(),
+            "match_recognize".to_string(),
+            1,
+        );
+
+        let edge = LogicalEdge::project_all(LogicalEdgeType::Shuffle, (*input_schema).clone());
+        Ok(NodeWithIncomingEdges {
+            node: logical_node,
+            edges: vec![edge],
+        })
+    }
+
+    fn output_schema(&self) -> ArroyoSchema {
+        ArroyoSchema::from_schema_unkeyed(self.schema.inner().clone()).unwrap()
+    }
+}
+
+impl UserDefinedLogicalNodeCore for MatchRecognizeExtension {
+    fn name(&self) -> &str {
+        MATCH_RECOGNIZE_NODE_NAME
+    }
+
+    fn inputs(&self) -> Vec<&LogicalPlan> {
+        vec![&self.input]
+    }
+
+    fn schema(&self) -> &DFSchemaRef {
+        &self.schema
+    }
+
+    fn expressions(&self) -> Vec<Expr> {
+        vec![]
+    }
+
+    fn fmt_for_explain(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "MatchRecognizeExtension({}): {}",
+            self.symbols.join(", "),
+            self.schema
+        )
+    }
+
+    fn with_exprs_and_inputs(&self, _exprs: Vec<Expr>, inputs: Vec<LogicalPlan>) -> Result<Self> {
+        let [input] = &inputs[..] else {
+            return internal_err!("MATCH_RECOGNIZE must have one input");
+        };
+
+        Ok(Self {
+            input: input.clone(),
+            ..self.clone()
+        })
+    }
+}
@@ -26,21 +26,29 @@ use self::{
     window_fn::WindowFunctionExtension,
 };
 use crate::builder::{NamedNode, Planner};
+use crate::extension::late_records::LateRecordsExtension;
 use crate::extension::lookup::LookupJoin;
 use crate::extension::projection::ProjectionExtension;
 use crate::schemas::{add_timestamp_field, has_timestamp_field};
 use crate::{ASYNC_RESULT_FIELD, DFField, fields_with_qualifiers, schema_from_df_fields};
+use deduplicate::DeduplicateExtension;
 use join::JoinExtension;
+use match_recognize::MatchRecognizeExtension;
+use temporal_join::TemporalJoinExtension;
 
 pub(crate) mod aggregate;
 pub(crate) mod debezium;
+pub(crate) mod deduplicate;
 pub(crate) mod join;
 pub(crate) mod key_calculation;
+pub(crate) mod late_records;
 pub(crate) mod lookup;
+pub(crate) mod match_recognize;
 pub(crate) mod projection;
 pub(crate) mod remote_table;
 pub(crate) mod sink;
 pub(crate) mod table_source;
+pub(crate) mod temporal_join;
 pub(crate) mod updating_aggregate;
 pub(crate) mod watermark_node;
 pub(crate) mod window_fn;
@@ -91,7 +99,11 @@ impl<'a> TryFrom<&'a dyn UserDefinedLogicalNode> for &'a dyn ArroyoExtension {
             .or_else(|_| try_from_t::<DebeziumUnrollingExtension>(node))
             .or_else(|_| try_from_t::<UpdatingAggregateExtension>(node))
             .or_else(|_| try_from_t::<LookupJoin>(node))
+            .or_else(|_| try_from_t::<TemporalJoinExtension>(node))
+            .or_else(|_| try_from_t::<MatchRecognizeExtension>(node))
+            .or_else(|_| try_from_t::<DeduplicateExtension>(node))
             .or_else(|_| try_from_t::<ProjectionExtension>(node))
+            .or_else(|_| try_from_t::<LateRecordsExtension>(node))
             .map_err(|_| DataFusionError::Plan(format!("unexpected node: {}", node.name())))
     }
 }
@@ -55,6 +55,7 @@ impl SinkExtension {
                         }));
                         schema = input.schema().clone();
                     }
+                    (true, false) if connector_table.accepts_updating_input() => {}
                     (true, false) => {
                         return plan_err!(
                             "input is updating, but sink is not configured as an updating sink (hint: use `format = 'debezium_json'`)"
@@ -0,0 +1,121 @@
+use crate::builder::{NamedNode, Planner};
+use crate::extension::{ArroyoExtension, NodeWithIncomingEdges};
+use crate::multifield_partial_ord;
+use arroyo_datastream::logical::{LogicalEdge, LogicalEdgeType, LogicalNode, OperatorName};
+use arroyo_rpc::df::{ArroyoSchema, ArroyoSchemaRef};
+use arroyo_rpc::grpc::api::TemporalJoinOperator;
+use datafusion::common::{DFSchemaRef, JoinType, Result, internal_err, plan_err};
+use datafusion::logical_expr::{Expr, LogicalPlan, UserDefinedLogicalNodeCore};
+use prost::Message;
+use std::fmt::Formatter;
+use std::time::Duration;
+
+pub(crate) const TEMPORAL_JOIN_NODE_NAME: &str = "TemporalJoinNode";
+
+/// A join against a versioned table (`FOR SYSTEM_TIME AS OF`), in which each row of the left
+/// side is joined with the version of the right side that was valid at its event time. Both
+/// inputs are keyed by the join condition.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct TemporalJoinExtension {
+    pub(crate) left: LogicalPlan,
+    pub(crate) right: LogicalPlan,
+    pub(crate) schema: DFSchemaRef,
+    pub(crate) join_type: JoinType,
+    pub(crate) ttl: Duration,
+}
+
+multifield_partial_ord!(TemporalJoinExtension, left, right, ttl);
+
+impl ArroyoExtension for TemporalJoinExtension {
+    fn node_name(&self) -> Option<NamedNode> {
+        None
+    }
+
+    fn plan_node(
+        &self,
+        _planner: &Planner,
+        index: usize,
+        input_schemas: Vec<ArroyoSchemaRef>,
+    ) -> Result<NodeWithIncomingEdges> {
+        if input_schemas.len() != 2 {
+            return plan_err!("temporal join should have exactly two inputs");
+        }
+        let left_schema = input_schemas[0].clone();
+        let right_schema = input_schemas[1].clone();
+
+        let config = TemporalJoinOperator {
+            name: format!("temporal_join_{index}"),
+            left_schema: Some(left_schema.as_ref().clone().into()),
+            right_schema: Some(right_schema.as_ref().clone().into()),
+            output_schema: Some(self.output_schema().into()),
+            join_type: match self.join_type {
+                JoinType::Inner => arroyo_rpc::grpc::api::JoinType::Inner as i32,
+                JoinType::Left => arroyo_rpc::grpc::api::JoinType::Left as i32,
+                j => {
+                    return plan_err!(
+                        "unsupported join type '{j}' for temporal join; only inner and left joins are supported"
+                    );
+                }
+            },
+            ttl_micros: self.ttl.as_micros() as u64,
+        };
+
+        let logical_node = LogicalNode::single(
+            index as u32,
+            format!("temporal_join_{index}"),
+            OperatorName::TemporalJoin,
+            config.encode_to_vec(),
+            "temporal join".to_string(),
+            1,
+        );
+
+        let left_edge =
+            LogicalEdge::project_all(LogicalEdgeType::LeftJoin, left_schema.as_ref().clone());
+        let right_edge =
+            LogicalEdge::project_all(LogicalEdgeType::RightJoin, right_schema.as_ref().clone());
+        Ok(NodeWithIncomingEdges {
+            node: logical_node,
+            edges: vec![left_edge, right_edge],
+        })
+    }
+
+    fn output_schema(&self) -> ArroyoSchema {
+        ArroyoSchema::from_schema_unkeyed(self.schema.inner().clone()).unwrap()
+    }
+}
+
+impl UserDefinedLogicalNodeCore for TemporalJoinExtension {
+    fn name(&self) -> &str {
+        TEMPORAL_JOIN_NODE_NAME
+    }
+
+    fn inputs(&self) -> Vec<&LogicalPlan> {
+        vec![&self.left, &self.right]
+    }
+
+    fn schema(&self) -> &DFSchemaRef {
+        &self.schema
+    }
+
+    fn expressions(&self) -> Vec<Expr> {
+        vec![]
+    }
+
+    fn fmt_for_explain(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(f, "TemporalJoinExtension: {}", self.schema)
+    }
+
+    fn with_exprs_and_inputs(&self, _exprs: Vec<Expr>, inputs: Vec<LogicalPlan>) -> Result<Self> {
+        let [left, right] = &inputs[..] else {
+            return internal_err!("temporal join must have two inputs");
+        };
+
+        Ok(Self {
+            left: left.clone(),
+            right: right.clone(),
+            schema: self.schema.clone(),
+            join_type: self.join_type,
+            ttl: self.ttl,
+        })
+    }
+}
@@ -13,6 +13,7 @@ use datafusion_proto::physical_plan::to_proto::serialize_physical_expr;
 use prost::Message;
 use std::fmt::Formatter;
 use std::sync::Arc;
+use std::time::Duration;
 
 pub(crate) const WATERMARK_NODE_NAME: &str = "WatermarkNode";
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -22,6 +23,8 @@ pub struct WatermarkNode {
     pub watermark_expression: Expr,
     pub schema: DFSchemaRef,
     timestamp_index: usize,
+    alignment_max_drift: Option<Duration>,
+    per_partition: bool,
 }
 
 multifield_partial_ord!(
@@ -29,7 +32,9 @@ multifield_partial_ord!(
     input,
     qualifier,
     watermark_expression,
-    timestamp_index
+    timestamp_index,
+    alignment_max_drift,
+    per_partition
 );
 
 impl UserDefinedLogicalNodeCore for WatermarkNode {
@@ -72,6 +77,8 @@ impl UserDefinedLogicalNodeCore for WatermarkNode {
             watermark_expression: exprs.into_iter().next().unwrap(),
             schema: self.schema.clone(),
             timestamp_index,
+            alignment_max_drift: self.alignment_max_drift,
+            per_partition: self.per_partition,
         })
     }
 }
@@ -98,6 +105,8 @@ impl ArroyoExtension for WatermarkNode {
                 idle_time_micros: None,
                 expression: expression.encode_to_vec(),
                 input_schema: Some(self.arroyo_schema().into()),
+                alignment_max_drift_micros: self.alignment_max_drift.map(|d| d.as_micros() as u64),
+                per_partition: self.per_partition,
             }
             .encode_to_vec(),
             "watermark".to_string(),
@@ -121,6 +130,8 @@ impl WatermarkNode {
         input: LogicalPlan,
         qualifier: TableReference,
         watermark_expression: Expr,
+        alignment_max_drift: Option<Duration>,
+        per_partition: bool,
     ) -> Result<Self> {
         let schema = add_timestamp_field(input.schema().clone(), Some(qualifier.clone()))?;
         let timestamp_index = schema
@@ -132,6 +143,8 @@ impl WatermarkNode {
             watermark_expression,
             schema,
             timestamp_index,
+            alignment_max_drift,
+            per_partition,
         })
     }
     pub(crate) fn arroyo_schema(&self) -> ArroyoSchema {
@@ -39,7 +39,7 @@ use datafusion::logical_expr::{
 
 use datafusion::logical_expr::{AggregateUDF, TableSource};
 use extension::ArroyoExtension;
-use logical::LogicalBatchInput;
+use logical::{LateRecordsSource, LogicalBatchInput};
 
 use rewriters::SinkInputRewriter;
 use schemas::window_arrow_struct;
@@ -47,14 +47,18 @@ use tables::{Insert, Table};
 
 use crate::builder::PlanToGraphVisitor;
 use crate::extension::sink::SinkExtension;
-use crate::plan::ArroyoRewriter;
+use crate::plan::{
+    ArroyoRewriter, DeduplicationRewriter, extract_temporal_joins, plan_match_recognize,
+    rewrite_within_clauses,
+};
 use arroyo_datastream::logical::{DylibUdfConfig, ProgramConfig, PythonUdfConfig};
-use arroyo_rpc::api_types::connections::ConnectionProfile;
+use arroyo_rpc::api_types::connections::{ConnectionProfile, ConnectionType};
 use datafusion::common::DataFusionError;
 use std::collections::HashSet;
 use std::fmt::{Debug, Formatter};
 
 use crate::functions::{is_json_union, serialize_outgoing_json};
+pub use functions::MultiHashFunction;
 use crate::rewriters::{SourceMetadataVisitor, TimeWindowUdfChecker, UnnestRewriter};
 
 use crate::extension::key_calculation::{KeyCalculationExtension, KeysOrExprs};
@@ -98,12 +102,16 @@ pub struct CompiledSql {
 #[derive(Clone)]
 pub struct PlanningOptions {
     ttl: Duration,
+    allowed_lateness: Duration,
+    deduplication_ttl: Duration,
 }
 
 impl Default for PlanningOptions {
     fn default() -> Self {
         Self {
             ttl: Duration::from_secs(24 * 60 * 60),
+            allowed_lateness: Duration::ZERO,
+            deduplication_ttl: Duration::from_secs(24 * 60 * 60),
         }
     }
 }
@@ -223,6 +231,18 @@ impl ArroyoSchemaProvider {
             ))
             .unwrap();
 
+        registry
+            .register_udf(PlaceholderUdf::with_return(
+                "cumulate",
+                vec![
+                    DataType::Interval(datatypes::IntervalUnit::MonthDayNano),
+                    DataType::Interval(datatypes::IntervalUnit::MonthDayNano),
+                    DataType::Interval(datatypes::IntervalUnit::MonthDayNano),
+                ],
+                window_arrow_struct(),
+            ))
+            .unwrap();
+
         registry
             .register_udf(PlaceholderUdf::with_return(
                 "tumble",
@@ -437,6 +457,62 @@ impl ContextProvider for ArroyoSchemaProvider {
         Ok(create_table(name.to_string(), schema))
     }
 
+    fn get_table_function_source(
+        &self,
+        name: &str,
+        args: Vec<Expr>,
+    ) -> datafusion::common::Result<Arc<dyn TableSource>> {
+        if name != "late_records" {
+            return plan_err!("unknown table function '{name}'");
+        }
+
+        let [Expr::Literal(ScalarValue::Utf8(Some(source)), _), window] = args.as_slice() else {
+            return plan_err!(
+                "late_records expects a string literal naming a source table and the window whose late records to emit, like late_records('orders', tumble(INTERVAL '1' MINUTE))"
+            );
+        };
+
+        // records are late for a window once the bin they fall into has closed
+        let bin_width = match find_window(window)? {
+            Some(WindowType::Tumbling { width }) => width,
+            Some(WindowType::Sliding { slide, .. }) => slide,
+            Some(WindowType::Cumulating { step, .. }) => step,
+            Some(WindowType::Session { .. }) => Duration::ZERO,
+            Some(WindowType::Instant) | None => {
+                return plan_err!(
+                    "late_records: expected a tumble, hop, cumulate or session window, not {window}"
+                );
+            }
+        };
+
+        let Some(table) = self.get_table(source) else {
+            return plan_err!("late_records: table '{source}' not found");
+        };
+
+        let Table::ConnectorTable(connector_table) = table else {
+            return plan_err!("late_records: '{source}' is not a source table");
+        };
+
+        if connector_table.connection_type != ConnectionType::Source {
+            return plan_err!("late_records: '{source}' is not a source table");
+        }
+
+        if connector_table.is_updating() {
+            return plan_err!("late_records: updating table '{source}' has no late records");
+        }
+
+        let schema = Arc::new(Schema::new_with_metadata(
+            table.get_fields(),
+            HashMap::new(),
+        ));
+
+        Ok(Arc::new(LateRecordsSource {
+            source: source.clone(),
+            bin_width,
+            schema,
+        }))
+    }
+
     fn get_function_meta(&self, name: &str) -> Option<Arc<ScalarUDF>> {
         self.functions.get(name).cloned()
     }
@@ -631,6 +707,35 @@ fn find_window(expression: &Expr) -> Result<Option<WindowType>> {
                 let gap = get_duration(&args[0])?;
                 Ok(Some(WindowType::Session { gap }))
             }
+            "cumulate" => {
+                if args.len() != 3 {
+                    unreachable!("wrong number of arguments for cumulate(), expected three");
+                }
+                let offset = get_duration(&args[0])?;
+                let step = get_duration(&args[1])?;
+                let size = get_duration(&args[2])?;
+                if step.is_zero() || !size.as_nanos().is_multiple_of(step.as_nanos()) {
+                    return plan_err!(
+                        "cumulate() size {:?} must be a non-zero multiple of step {:?}",
+                        size,
+                        step
+                    );
+                }
+                if offset >= size || !offset.as_nanos().is_multiple_of(step.as_nanos()) {
+                    return plan_err!(
+                        "cumulate() offset {:?} must be a multiple of step {:?} less than size {:?}",
+                        offset,
+                        step,
+                        size
+                    );
+                }
+                if step == size {
+                    // a cumulate window with a single step is a tumble window
+                    Ok(Some(WindowType::Tumbling { width: size }))
+                } else {
+                    Ok(Some(WindowType::Cumulating { offset, step, size }))
+                }
+            }
             _ => Ok(None),
         },
         Expr::Alias(logical_expr::expr::Alias { expr, .. }) => find_window(expr),
@@ -649,6 +754,8 @@ pub fn rewrite_plan(
     schema_provider: &ArroyoSchemaProvider,
 ) -> Result<LogicalPlan> {
     let rewritten_plan = plan
+        .rewrite_with_subqueries(&mut DeduplicationRewriter { schema_provider })?
+        .data
         .rewrite_with_subqueries(&mut ArroyoRewriter { schema_provider })?
         .data
         .rewrite_with_subqueries(&mut UnnestRewriter {})?;
@@ -763,18 +870,24 @@ fn try_handle_set_variable(
             return plan_err!("invalid syntax for `SET` call");
         };
 
-        if opt.to_string() != "updating_ttl" {
-            return plan_err!(
-                "invalid option '{}'; supported options are 'updating_ttl'",
-                opt
-            );
-        }
+        let option = opt.to_string();
+        let target = match option.as_str() {
+            "updating_ttl" => &mut schema_provider.planning_options.ttl,
+            "allowed_lateness" => &mut schema_provider.planning_options.allowed_lateness,
+            "deduplication_ttl" => &mut schema_provider.planning_options.deduplication_ttl,
+            _ => {
+                return plan_err!(
+                    "invalid option '{}'; supported options are 'updating_ttl', 'allowed_lateness', and 'deduplication_ttl'",
+                    opt
+                );
+            }
+        };
 
         if value.len() != 1 {
-            return plan_err!("invalid `SET updating_ttl` call; expected exactly one expression");
+            return plan_err!("invalid `SET {option}` call; expected exactly one expression");
         }
 
-        schema_provider.planning_options.ttl = duration_from_sql(value[0].clone())?;
+        *target = duration_from_sql(value[0].clone())?;
 
         return Ok(true);
     }
@@ -783,7 +896,11 @@ fn try_handle_set_variable(
 }
 
 pub(crate) fn parse_sql(sql: &str) -> Result<Vec<Statement>, ParserError> {
-    Parser::parse_sql(&ArroyoDialect {}, sql)
+    let dialect = ArroyoDialect {};
+    match rewrite_within_clauses(&dialect, sql)? {
+        Some(tokens) => Parser::new(&dialect).with_tokens(tokens).parse_statements(),
+        None => Parser::parse_sql(&dialect, sql),
+    }
 }
 
 pub async fn parse_and_get_arrow_program(
@@ -810,11 +927,14 @@ pub async fn parse_and_get_arrow_program(
         .build();
 
     let mut inserts = vec![];
-    for statement in parse_sql(&query)? {
+    for mut statement in parse_sql(&query)? {
         if try_handle_set_variable(&statement, &mut schema_provider)? {
             continue;
         }
 
+        extract_temporal_joins(&mut statement, &schema_provider)?;
+        plan_match_recognize(&mut statement, &mut schema_provider)?;
+
         if let Some(table) = Table::try_from_statement(&statement, &schema_provider)? {
             schema_provider.insert_table(table);
         } else {
@@ -928,6 +1048,7 @@ pub async fn parse_and_get_arrow_program(
         ProgramConfig {
             udf_dylibs: schema_provider.dylib_udfs.clone(),
             python_udfs: schema_provider.python_udfs.clone(),
+            unaligned_checkpoints: false,
         },
     );
 
@@ -1,9 +1,10 @@
+use std::time::Duration;
 use std::{any::Any, sync::Arc};
 
 use arrow_schema::SchemaRef;
 use datafusion::catalog::Session;
 use datafusion::common::Result as DFResult;
-use datafusion::logical_expr::{Expr, TableType};
+use datafusion::logical_expr::{Expr, TableSource, TableType};
 use datafusion::{datasource::TableProvider, physical_plan::ExecutionPlan};
 use serde::{Deserialize, Serialize};
 
@@ -52,3 +53,22 @@ impl TableProvider for LogicalBatchInput {
         )))
     }
 }
+
+/// The table source for a `late_records(...)` scan, which reads the records of the named source
+/// table that a window with bins of `bin_width` drops as late
+#[derive(Debug, Clone)]
+pub struct LateRecordsSource {
+    pub source: String,
+    pub bin_width: Duration,
+    pub schema: SchemaRef,
+}
+
+impl TableSource for LateRecordsSource {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+}
@@ -6,7 +6,7 @@ use crate::{
     ArroyoSchemaProvider, DFField, WindowBehavior, fields_with_qualifiers, find_window,
     schema_from_df_fields_with_metadata,
 };
-use arroyo_rpc::TIMESTAMP_FIELD;
+use arroyo_rpc::{TIMESTAMP_FIELD, UPDATING_META_FIELD};
 use datafusion::common::tree_node::{Transformed, TreeNodeRewriter};
 use datafusion::common::{DFSchema, DataFusionError, Result, not_impl_err, plan_err};
 use datafusion::functions_aggregate::expr_fn::max;
@@ -187,6 +187,14 @@ impl TreeNodeRewriter for AggregateRewriter<'_> {
                                 "can't reinvoke session window in nested aggregates. Need to pass the window struct up from the source query."
                             );
                         }
+                        if matches!(
+                            input_window,
+                            arroyo_datastream::WindowType::Cumulating { .. }
+                        ) {
+                            return plan_err!(
+                                "can't reinvoke cumulate window in nested aggregates. Need to pass the window struct up from the source query."
+                            );
+                        }
                         group_expr.remove(window_index);
                         key_fields.remove(window_index);
                         let window_field = schema.qualified_field(window_index).into();
@@ -225,6 +233,13 @@ impl TreeNodeRewriter for AggregateRewriter<'_> {
             }
         };
 
+        if input
+            .schema()
+            .has_column_with_unqualified_name(UPDATING_META_FIELD)
+        {
+            return plan_err!("windowed aggregates are not supported over updating inputs");
+        }
+
         let key_count = key_fields.len();
         key_fields.extend(fields_with_qualifiers(input.schema()));
 
@@ -284,6 +299,7 @@ impl TreeNodeRewriter for AggregateRewriter<'_> {
             window_behavior,
             LogicalPlan::Aggregate(rewritten_aggregate),
             (0..key_count).collect(),
+            self.schema_provider.planning_options.allowed_lateness,
         );
         let final_plan = LogicalPlan::Extension(Extension {
             node: Arc::new(aggregate_extension),
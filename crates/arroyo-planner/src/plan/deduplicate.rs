@@ -0,0 +1,263 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use arrow_schema::DataType;
+use arroyo_rpc::{TIMESTAMP_FIELD, UPDATING_META_FIELD};
+use datafusion::common::tree_node::{Transformed, TreeNode, TreeNodeRecursion, TreeNodeRewriter};
+use datafusion::common::{Column, Result, ScalarValue, plan_err};
+use datafusion::logical_expr::expr::{Alias, WindowFunction};
+use datafusion::logical_expr::utils::{conjunction, split_conjunction};
+use datafusion::logical_expr::{
+    BinaryExpr, Expr, Extension, Filter, LogicalPlan, Operator, Projection, Window, lit,
+};
+
+use super::join::event_time_columns;
+use crate::ArroyoSchemaProvider;
+use crate::extension::deduplicate::DeduplicateExtension;
+use crate::extension::key_calculation::{KeyCalculationExtension, KeysOrExprs};
+use crate::fields_with_qualifiers;
+use crate::tables::Table;
+
+/// Plans `ROW_NUMBER() OVER (PARTITION BY ... ORDER BY <event time>) = 1` as an append-only
+/// [`DeduplicateExtension`], rather than as a window function (which requires windowed input).
+/// This runs before the [`super::ArroyoRewriter`], as the filter determines how the window
+/// function is planned.
+pub(crate) struct DeduplicationRewriter<'a> {
+    pub(crate) schema_provider: &'a ArroyoSchemaProvider,
+}
+
+fn unwrap_alias(expr: &Expr) -> &Expr {
+    match expr {
+        Expr::Alias(Alias { expr, .. }) => unwrap_alias(expr),
+        _ => expr,
+    }
+}
+
+fn is_one(expr: &Expr) -> bool {
+    matches!(expr, Expr::Literal(v, _)
+        if v.cast_to(&DataType::Int64).ok() == Some(ScalarValue::Int64(Some(1))))
+}
+
+fn as_column(expr: &Expr) -> Option<&Column> {
+    match expr {
+        Expr::Column(column) => Some(column),
+        Expr::Cast(cast) => as_column(&cast.expr),
+        Expr::TryCast(cast) => as_column(&cast.expr),
+        _ => None,
+    }
+}
+
+/// If the predicate restricts a column to the first row, returns that column
+fn first_row_column(predicate: &Expr) -> Option<&Column> {
+    let Expr::BinaryExpr(BinaryExpr { left, op, right }) = predicate else {
+        return None;
+    };
+
+    match op {
+        Operator::Eq if is_one(right) => as_column(left),
+        Operator::Eq if is_one(left) => as_column(right),
+        Operator::LtEq if is_one(right) => as_column(left),
+        Operator::GtEq if is_one(left) => as_column(right),
+        _ => None,
+    }
+}
+
+/// Follows the column down through projections and aliases to the ROW_NUMBER() that computes
+/// it, if any
+fn find_row_number(plan: &LogicalPlan, column: &Column) -> Option<Window> {
+    match plan {
+        LogicalPlan::Projection(projection) => {
+            let index = projection.schema.index_of_column(column).ok()?;
+            let Expr::Column(column) = unwrap_alias(&projection.expr[index]) else {
+                return None;
+            };
+            find_row_number(&projection.input, column)
+        }
+        LogicalPlan::SubqueryAlias(alias) => {
+            let index = alias.schema.index_of_column(column).ok()?;
+            let column = Column::from(alias.input.schema().qualified_field(index));
+            find_row_number(&alias.input, &column)
+        }
+        LogicalPlan::Window(window) => {
+            let index = window.schema.index_of_column(column).ok()?;
+            let window_index = index.checked_sub(window.input.schema().fields().len())?;
+            let Expr::WindowFunction(function) = unwrap_alias(&window.window_expr[window_index])
+            else {
+                return None;
+            };
+            (function.fun.name() == "row_number").then(|| window.clone())
+        }
+        _ => None,
+    }
+}
+
+impl DeduplicationRewriter<'_> {
+    /// The columns of the (not yet rewritten) plan that hold its event time
+    fn event_time_columns(&self, plan: &LogicalPlan) -> Result<HashSet<String>> {
+        let mut tables = vec![];
+        plan.apply(|p| {
+            if let LogicalPlan::TableScan(scan) = p {
+                tables.push(scan.table_name.table().to_string());
+            }
+            Ok(TreeNodeRecursion::Continue)
+        })?;
+
+        let mut columns = HashSet::from([TIMESTAMP_FIELD.to_string()]);
+        if let [table] = tables.as_slice() {
+            match self.schema_provider.get_table(table) {
+                Some(Table::ConnectorTable(table)) => {
+                    columns.extend(table.event_time_field.clone());
+                }
+                Some(Table::TableFromQuery { logical_plan, .. }) => {
+                    columns.extend(event_time_columns(logical_plan)?);
+                }
+                _ => {}
+            }
+        }
+        Ok(columns)
+    }
+
+    /// Returns the partition expressions if the window is a deduplication, i.e., it's ordered by
+    /// event time
+    fn deduplication_keys(&self, window: &Window) -> Result<Option<Vec<Expr>>> {
+        let [window_expr] = window.window_expr.as_slice() else {
+            return Ok(None);
+        };
+        let Expr::WindowFunction(function) = unwrap_alias(window_expr) else {
+            return Ok(None);
+        };
+        let WindowFunction { params, .. } = function.as_ref();
+
+        let [order] = params.order_by.as_slice() else {
+            return Ok(None);
+        };
+        let Expr::Column(order_column) = &order.expr else {
+            return Ok(None);
+        };
+        if !self
+            .event_time_columns(&window.input)?
+            .contains(&order_column.name)
+        {
+            return Ok(None);
+        }
+
+        if !order.asc {
+            return plan_err!(
+                "deduplication keeps the first row for each key, so ROW_NUMBER() must be ordered by ascending event time"
+            );
+        }
+        if params.partition_by.is_empty() {
+            return plan_err!("deduplication with ROW_NUMBER() requires a PARTITION BY clause");
+        }
+
+        Ok(Some(params.partition_by.clone()))
+    }
+}
+
+impl TreeNodeRewriter for DeduplicationRewriter<'_> {
+    type Node = LogicalPlan;
+
+    fn f_up(&mut self, node: Self::Node) -> Result<Transformed<Self::Node>> {
+        let LogicalPlan::Filter(filter) = node else {
+            return Ok(Transformed::no(node));
+        };
+
+        let mut conjuncts: Vec<_> = split_conjunction(&filter.predicate)
+            .into_iter()
+            .cloned()
+            .collect();
+
+        for i in 0..conjuncts.len() {
+            let Some(window) =
+                first_row_column(&conjuncts[i]).and_then(|c| find_row_number(&filter.input, c))
+            else {
+                continue;
+            };
+            let Some(partition_by) = self.deduplication_keys(&window)? else {
+                continue;
+            };
+
+            // every row that's emitted is the first of its key, so the row number is always 1
+            let mut expressions: Vec<_> = fields_with_qualifiers(window.input.schema())
+                .iter()
+                .map(|field| Expr::Column(field.qualified_column()))
+                .collect();
+            let (qualifier, field) = window
+                .schema
+                .qualified_field(window.input.schema().fields().len());
+            expressions.push(lit(1u64).alias_qualified(qualifier.cloned(), field.name()));
+
+            let deduplicate = LogicalPlan::Extension(Extension {
+                node: Arc::new(DeduplicateExtension::new(
+                    window.input.as_ref().clone(),
+                    partition_by,
+                    self.schema_provider.planning_options.deduplication_ttl,
+                )),
+            });
+            let replacement =
+                LogicalPlan::Projection(Projection::try_new(expressions, Arc::new(deduplicate))?);
+
+            let mut replacement = Some(replacement);
+            let input = filter
+                .input
+                .as_ref()
+                .clone()
+                .transform_down(|node| match node {
+                    LogicalPlan::Window(w) if w == window => Ok(Transformed::new(
+                        replacement.take().unwrap(),
+                        true,
+                        TreeNodeRecursion::Jump,
+                    )),
+                    node => Ok(Transformed::no(node)),
+                })?
+                .data;
+
+            conjuncts.remove(i);
+            return Ok(Transformed::yes(match conjunction(conjuncts) {
+                Some(predicate) => {
+                    LogicalPlan::Filter(Filter::try_new(predicate, Arc::new(input))?)
+                }
+                None => input,
+            }));
+        }
+
+        Ok(Transformed::no(LogicalPlan::Filter(filter)))
+    }
+}
+
+/// Keys the (rewritten) input of a deduplication by its partition expressions
+pub(super) fn key_deduplication(
+    deduplicate: &DeduplicateExtension,
+) -> Result<Transformed<LogicalPlan>> {
+    if deduplicate
+        .input
+        .schema()
+        .has_column_with_unqualified_name(UPDATING_META_FIELD)
+    {
+        return plan_err!("deduplication is not supported over updating inputs");
+    }
+
+    let mut key_expressions: Vec<_> = deduplicate
+        .partition_by
+        .iter()
+        .enumerate()
+        .map(|(i, expr)| expr.clone().alias(format!("_key_{i}")))
+        .collect();
+    key_expressions.extend(
+        fields_with_qualifiers(deduplicate.input.schema())
+            .iter()
+            .map(|field| Expr::Column(field.qualified_column())),
+    );
+
+    let key_calculation = KeyCalculationExtension::new(
+        LogicalPlan::Projection(Projection::try_new(
+            key_expressions,
+            Arc::new(deduplicate.input.clone()),
+        )?),
+        KeysOrExprs::Keys((0..deduplicate.partition_by.len()).collect()),
+    );
+
+    Ok(Transformed::yes(LogicalPlan::Extension(Extension {
+        node: Arc::new(deduplicate.with_keyed_input(key_calculation)?),
+    })))
+}
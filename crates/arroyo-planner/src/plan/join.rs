@@ -1,12 +1,18 @@
-use crate::extension::join::JoinExtension;
+use crate::extension::debezium::DebeziumUnrollingExtension;
+use crate::extension::join::{IntervalBounds, JoinExtension};
 use crate::extension::key_calculation::KeyCalculationExtension;
 use crate::extension::lookup::{LookupJoin, LookupSource};
+use crate::extension::table_source::TableSourceExtension;
+use crate::extension::temporal_join::TemporalJoinExtension;
 use crate::plan::WindowDetectingVisitor;
 use crate::schemas::add_timestamp_field;
-use crate::tables::ConnectorTable;
-use crate::{ArroyoSchemaProvider, fields_with_qualifiers, schema_from_df_fields_with_metadata};
+use crate::tables::{ConnectorTable, Table};
+use crate::{
+    ArroyoSchemaProvider, fields_with_qualifiers, get_duration, parse_sql,
+    schema_from_df_fields_with_metadata,
+};
 use arroyo_datastream::WindowType;
-use arroyo_rpc::UPDATING_META_FIELD;
+use arroyo_rpc::{TIMESTAMP_FIELD, UPDATING_META_FIELD};
 use datafusion::common::tree_node::{
     Transformed, TreeNode, TreeNodeRecursion, TreeNodeRewriter, TreeNodeVisitor,
 };
@@ -15,12 +21,23 @@ use datafusion::common::{
     not_impl_err, plan_err,
 };
 use datafusion::logical_expr;
-use datafusion::logical_expr::expr::Alias;
+use datafusion::logical_expr::expr::{Alias, Between};
+use datafusion::logical_expr::utils::split_conjunction;
 use datafusion::logical_expr::{
-    BinaryExpr, Case, Expr, Extension, Join, LogicalPlan, Projection, build_join_schema,
+    BinaryExpr, Case, Expr, Extension, Join, LogicalPlan, Operator, Projection, build_join_schema,
 };
 use datafusion::prelude::coalesce;
+use datafusion::sql::sqlparser::ast::{
+    Expr as SqlExpr, Ident, ObjectName, Query, SetExpr, Statement, TableFactor, TableVersion,
+    TableWithJoins,
+};
+
+/// The alias given to relations joined `FOR SYSTEM_TIME AS OF`, which are wrapped in a subquery
+/// reading from them under this alias so that the join can be planned as a temporal join
+const TEMPORAL_RELATION_ALIAS: &str = "__arroyo_temporal";
 use datafusion::sql::unparser::expr_to_sql;
+use std::collections::HashSet;
+use std::iter::once;
 use std::sync::Arc;
 
 pub(crate) struct JoinRewriter<'a> {
@@ -310,6 +327,288 @@ fn maybe_plan_lookup_join(join: &Join) -> Result<Option<LogicalPlan>> {
     })))
 }
 
+/// Finds the primary keys of the updating table that a versioned table is read from
+fn versioned_table_keys(plan: &LogicalPlan) -> Result<Option<Arc<Vec<String>>>> {
+    let mut keys = None;
+    plan.apply(|p| {
+        if let LogicalPlan::Extension(e) = p
+            && let Some(d) = e.node.as_any().downcast_ref::<DebeziumUnrollingExtension>()
+        {
+            keys = Some(d.primary_key_names.clone());
+            return Ok(TreeNodeRecursion::Stop);
+        }
+        Ok(TreeNodeRecursion::Continue)
+    })?;
+    Ok(keys)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum JoinSide {
+    Left,
+    Right,
+}
+
+/// The names of the columns of a join input that hold its event time: `_timestamp`, and the
+/// event time field of its source if it reads from exactly one
+pub(super) fn event_time_columns(plan: &LogicalPlan) -> Result<HashSet<String>> {
+    let mut sources = vec![];
+    plan.apply(|p| {
+        if let LogicalPlan::Extension(e) = p
+            && let Some(source) = e.node.as_any().downcast_ref::<TableSourceExtension>()
+        {
+            sources.push(source.table.event_time_field.clone());
+        }
+        Ok(TreeNodeRecursion::Continue)
+    })?;
+
+    let mut columns = HashSet::from([TIMESTAMP_FIELD.to_string()]);
+    if let [Some(event_time_field)] = sources.as_slice() {
+        columns.insert(event_time_field.clone());
+    }
+    Ok(columns)
+}
+
+struct IntervalBoundsExtractor<'a> {
+    left: &'a LogicalPlan,
+    right: &'a LogicalPlan,
+    left_times: HashSet<String>,
+    right_times: HashSet<String>,
+    lower: Option<i64>,
+    upper: Option<i64>,
+}
+
+impl<'a> IntervalBoundsExtractor<'a> {
+    fn new(left: &'a LogicalPlan, right: &'a LogicalPlan) -> Result<Self> {
+        Ok(Self {
+            left,
+            right,
+            left_times: event_time_columns(left)?,
+            right_times: event_time_columns(right)?,
+            lower: None,
+            upper: None,
+        })
+    }
+
+    /// Parses an expression of the form `time +/- interval...`, where `time` is the event time
+    /// of one of the inputs, into the side and the offset in microseconds
+    fn time_offset(&self, expr: &Expr) -> Option<(JoinSide, i64)> {
+        match expr {
+            Expr::Column(c) => {
+                if self.left.schema().has_column(c) && self.left_times.contains(&c.name) {
+                    Some((JoinSide::Left, 0))
+                } else if self.right.schema().has_column(c) && self.right_times.contains(&c.name) {
+                    Some((JoinSide::Right, 0))
+                } else {
+                    None
+                }
+            }
+            Expr::BinaryExpr(BinaryExpr { left, op, right }) => {
+                let interval = get_duration(right).ok()?.as_micros() as i64;
+                let (side, offset) = self.time_offset(left)?;
+                match op {
+                    Operator::Plus => Some((side, offset + interval)),
+                    Operator::Minus => Some((side, offset - interval)),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Records the bound implied by `left op right`, if it compares the event times of the two
+    /// sides
+    fn add_comparison(&mut self, left: &Expr, op: Operator, right: &Expr) {
+        let (Some((left_side, left_offset)), Some((right_side, right_offset))) =
+            (self.time_offset(left), self.time_offset(right))
+        else {
+            return;
+        };
+
+        // normalize to `right._timestamp - left._timestamp op bound`; strict comparisons are
+        // treated as inclusive, which is conservative for expiration while the join filter
+        // still applies the exact condition
+        let (op, bound) = match (left_side, right_side) {
+            (JoinSide::Right, JoinSide::Left) => (op, right_offset - left_offset),
+            (JoinSide::Left, JoinSide::Right) => match op.swap() {
+                Some(op) => (op, left_offset - right_offset),
+                None => return,
+            },
+            _ => return,
+        };
+
+        match op {
+            Operator::Gt | Operator::GtEq => {
+                self.lower = Some(self.lower.map_or(bound, |l| l.max(bound)));
+            }
+            Operator::Lt | Operator::LtEq => {
+                self.upper = Some(self.upper.map_or(bound, |u| u.min(bound)));
+            }
+            _ => {}
+        }
+    }
+
+    fn add_conjunct(&mut self, expr: &Expr) {
+        match expr {
+            Expr::BinaryExpr(BinaryExpr { left, op, right }) => {
+                self.add_comparison(left, *op, right);
+            }
+            Expr::Between(Between {
+                expr,
+                negated: false,
+                low,
+                high,
+            }) => {
+                self.add_comparison(expr, Operator::GtEq, low);
+                self.add_comparison(expr, Operator::LtEq, high);
+            }
+            _ => {}
+        }
+    }
+
+    /// Finds the bounds on `right._timestamp - left._timestamp` imposed by the join filter, if
+    /// it bounds them from both sides
+    fn extract(mut self, filter: &Expr) -> Result<Option<IntervalBounds>> {
+        for conjunct in split_conjunction(filter) {
+            self.add_conjunct(conjunct);
+        }
+
+        let (Some(lower), Some(upper)) = (self.lower, self.upper) else {
+            return Ok(None);
+        };
+
+        if lower > upper {
+            return plan_err!(
+                "the time bounds of the interval join are empty; the lower bound ({}µs) is after the upper bound ({}µs)",
+                lower,
+                upper
+            );
+        }
+
+        Ok(Some(IntervalBounds {
+            lower_micros: lower,
+            upper_micros: upper,
+        }))
+    }
+}
+
+/// Whether a join input is a relation that was joined `FOR SYSTEM_TIME AS OF`, as marked by
+/// `extract_temporal_joins`
+fn is_temporal_relation(plan: &LogicalPlan) -> bool {
+    match plan {
+        LogicalPlan::SubqueryAlias(alias) if alias.alias.table() == TEMPORAL_RELATION_ALIAS => true,
+        LogicalPlan::SubqueryAlias(_) | LogicalPlan::Projection(_) | LogicalPlan::Filter(_) => plan
+            .inputs()
+            .first()
+            .is_some_and(|input| is_temporal_relation(input)),
+        _ => false,
+    }
+}
+
+impl JoinRewriter<'_> {
+    fn maybe_plan_temporal_join(&self, join: &Join) -> Result<Option<LogicalPlan>> {
+        if !is_temporal_relation(&join.right) {
+            return Ok(None);
+        }
+
+        let Some(qualifier) = join.right.schema().iter().next().and_then(|(q, _)| q) else {
+            return plan_err!("the versioned table of a temporal join must be a named relation");
+        };
+
+        match join.join_type {
+            JoinType::Inner | JoinType::Left => {}
+            t => {
+                return plan_err!(
+                    "{} join is not supported for temporal joins; must be a left or inner join",
+                    t
+                );
+            }
+        }
+
+        if join.filter.is_some() {
+            return plan_err!(
+                "filter join conditions are not supported for temporal joins; must have an equality condition"
+            );
+        }
+
+        if WindowDetectingVisitor::get_window(&join.left)?.is_some()
+            || WindowDetectingVisitor::get_window(&join.right)?.is_some()
+        {
+            return plan_err!("temporal joins cannot be performed over windowed inputs");
+        }
+
+        if join
+            .left
+            .schema()
+            .has_column_with_unqualified_name(UPDATING_META_FIELD)
+        {
+            return plan_err!("the left side of a temporal join cannot be an updating table");
+        }
+
+        let Some(primary_keys) = versioned_table_keys(&join.right)? else {
+            return plan_err!(
+                "the versioned table `{}` in a temporal join must be an updating table with a PRIMARY KEY, such as a Debezium source",
+                qualifier
+            );
+        };
+
+        let right_keys = join
+            .on
+            .iter()
+            .map(|(_, r)| match r {
+                Expr::Column(c) => Ok(c.name.as_str()),
+                e => plan_err!(
+                    "invalid right-side condition for temporal join: `{}`; only column references are supported",
+                    expr_to_sql(e)
+                        .map(|e| e.to_string())
+                        .unwrap_or_else(|_| e.to_string())
+                ),
+            })
+            .collect::<Result<HashSet<_>>>()?;
+
+        if right_keys != primary_keys.iter().map(|k| k.as_str()).collect() {
+            return plan_err!(
+                "the condition of a temporal join must match the PRIMARY KEY of the versioned table `{}` ({}) against the left side",
+                qualifier,
+                primary_keys.join(", ")
+            );
+        }
+
+        let (left_expressions, right_expressions): (Vec<_>, Vec<_>) =
+            join.on.iter().cloned().unzip();
+        let left = Self::create_join_key_plan(join.left.clone(), left_expressions, "left")?;
+        let right = Self::create_join_key_plan(join.right.clone(), right_expressions, "right")?;
+
+        // the output has the fields of both sides, without the updating metadata of the
+        // versioned table, and with the timestamp of the left side
+        let fields = fields_with_qualifiers(&join.schema);
+        let timestamp = fields[..join.left.schema().fields().len()]
+            .iter()
+            .find(|f| f.name() == TIMESTAMP_FIELD)
+            .cloned()
+            .ok_or_else(|| {
+                DataFusionError::Plan("left side of join has no timestamp field".to_string())
+            })?;
+        let mut output_fields: Vec<_> = fields
+            .into_iter()
+            .filter(|f| f.name() != TIMESTAMP_FIELD && f.name() != UPDATING_META_FIELD)
+            .collect();
+        output_fields.push(timestamp);
+
+        Ok(Some(LogicalPlan::Extension(Extension {
+            node: Arc::new(TemporalJoinExtension {
+                left,
+                right,
+                schema: Arc::new(schema_from_df_fields_with_metadata(
+                    &output_fields,
+                    join.schema.metadata().clone(),
+                )?),
+                join_type: join.join_type,
+                ttl: self.schema_provider.planning_options.ttl,
+            }),
+        })))
+    }
+}
+
 impl TreeNodeRewriter for JoinRewriter<'_> {
     type Node = LogicalPlan;
 
@@ -322,6 +621,10 @@ impl TreeNodeRewriter for JoinRewriter<'_> {
             return Ok(Transformed::yes(plan));
         }
 
+        if let Some(plan) = self.maybe_plan_temporal_join(&join)? {
+            return Ok(Transformed::yes(plan));
+        }
+
         let is_instant = Self::check_join_windowing(&join)?;
 
         let Join {
@@ -343,6 +646,15 @@ impl TreeNodeRewriter for JoinRewriter<'_> {
             return not_impl_err!("Updating joins must include an equijoin condition");
         }
 
+        // inner joins whose filter bounds the event times of the two sides relative to each
+        // other can expire their state by the watermark, rather than relying on a TTL
+        let interval = match (&filter, join_type) {
+            (Some(filter), JoinType::Inner) if !is_instant => {
+                IntervalBoundsExtractor::new(&left, &right)?.extract(filter)?
+            }
+            _ => None,
+        };
+
         let (left_expressions, right_expressions): (Vec<_>, Vec<_>) =
             on.clone().into_iter().unzip();
 
@@ -368,8 +680,10 @@ impl TreeNodeRewriter for JoinRewriter<'_> {
         let join_extension = JoinExtension {
             rewritten_join: final_logical_plan,
             is_instant,
-            // only non-instant (updating) joins have a TTL
-            ttl: (!is_instant).then_some(self.schema_provider.planning_options.ttl),
+            interval,
+            // only updating joins have a TTL
+            ttl: (!is_instant && interval.is_none())
+                .then_some(self.schema_provider.planning_options.ttl),
         };
 
         Ok(Transformed::yes(LogicalPlan::Extension(Extension {
@@ -377,3 +691,214 @@ impl TreeNodeRewriter for JoinRewriter<'_> {
         })))
     }
 }
+
+/// Strips `FOR SYSTEM_TIME AS OF` clauses from a statement, marking the relations they were
+/// applied to so that the joins against them can be planned as temporal joins. Each relation is
+/// replaced by a subquery that reads from it under `TEMPORAL_RELATION_ALIAS`, which keeps the
+/// marker with that join, rather than with every use of the table.
+pub(crate) fn extract_temporal_joins(
+    statement: &mut Statement,
+    schema_provider: &ArroyoSchemaProvider,
+) -> Result<()> {
+    let mut extractor = TemporalJoinExtractor { schema_provider };
+
+    match statement {
+        Statement::Query(query) => extractor.query(query)?,
+        Statement::Insert(insert) => {
+            if let Some(source) = &mut insert.source {
+                extractor.query(source)?;
+            }
+        }
+        Statement::CreateView { query, .. } => extractor.query(query)?,
+        Statement::CreateTable(create) => {
+            if let Some(query) = &mut create.query {
+                extractor.query(query)?;
+            }
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+pub(super) fn normalize_ident(ident: &Ident) -> String {
+    if ident.quote_style.is_some() {
+        ident.value.clone()
+    } else {
+        ident.value.to_lowercase()
+    }
+}
+
+fn normalize_table_name(name: &ObjectName) -> String {
+    let name = name.to_string();
+    let last = name.rsplit('.').next().unwrap_or(&name);
+    match last.strip_prefix('"').and_then(|s| s.strip_suffix('"')) {
+        Some(quoted) => quoted.to_string(),
+        None => last.to_lowercase(),
+    }
+}
+
+/// Returns the name a relation is referred to by in the query, along with the table it reads
+/// from if it's a table
+fn relation_name(factor: &TableFactor) -> Option<(String, Option<String>)> {
+    match factor {
+        TableFactor::Table { name, alias, .. } => Some((
+            alias
+                .as_ref()
+                .map(|a| normalize_ident(&a.name))
+                .unwrap_or_else(|| normalize_table_name(name)),
+            Some(name.to_string()),
+        )),
+        TableFactor::Derived {
+            alias: Some(alias), ..
+        } => Some((normalize_ident(&alias.name), None)),
+        _ => None,
+    }
+}
+
+/// Wraps a table joined `FOR SYSTEM_TIME AS OF` in a subquery that reads from it under
+/// `TEMPORAL_RELATION_ALIAS`, aliased to the name the table is referred to by in the query
+fn mark_temporal_relation(factor: &TableFactor) -> Result<TableFactor> {
+    let TableFactor::Table { name, alias, .. } = factor else {
+        return plan_err!("FOR SYSTEM_TIME AS OF can only be applied to tables");
+    };
+
+    let alias = match alias {
+        Some(alias) => alias.to_string(),
+        None => name
+            .0
+            .last()
+            .map(|part| part.to_string())
+            .unwrap_or_else(|| name.to_string()),
+    };
+
+    let statements = parse_sql(&format!(
+        "SELECT * FROM (SELECT * FROM {name} AS {TEMPORAL_RELATION_ALIAS}) AS {alias}"
+    ))?;
+    let Statement::Query(query) = &statements[0] else {
+        unreachable!()
+    };
+    let SetExpr::Select(select) = query.body.as_ref() else {
+        unreachable!()
+    };
+    Ok(select.from[0].relation.clone())
+}
+
+struct TemporalJoinExtractor<'a> {
+    schema_provider: &'a ArroyoSchemaProvider,
+}
+
+impl TemporalJoinExtractor<'_> {
+    fn query(&mut self, query: &mut Query) -> Result<()> {
+        if let Some(with) = &mut query.with {
+            for cte in &mut with.cte_tables {
+                self.query(&mut cte.query)?;
+            }
+        }
+
+        self.set_expr(&mut query.body)
+    }
+
+    fn set_expr(&mut self, expr: &mut SetExpr) -> Result<()> {
+        match expr {
+            SetExpr::Select(select) => {
+                for table in &mut select.from {
+                    self.table_with_joins(table)?;
+                }
+            }
+            SetExpr::Query(query) => self.query(query)?,
+            SetExpr::SetOperation { left, right, .. } => {
+                self.set_expr(left)?;
+                self.set_expr(right)?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn table_with_joins(&mut self, table: &mut TableWithJoins) -> Result<()> {
+        if self.table_factor(&mut table.relation)?.is_some() {
+            return plan_err!("FOR SYSTEM_TIME AS OF can only be used on the right side of a join");
+        }
+
+        let relations: Vec<_> = once(&table.relation)
+            .chain(table.joins.iter().map(|j| &j.relation))
+            .map(relation_name)
+            .collect();
+
+        for (i, join) in table.joins.iter_mut().enumerate() {
+            if let Some(as_of) = self.table_factor(&mut join.relation)? {
+                self.validate_as_of(&as_of, &relations[..=i])?;
+                join.relation = mark_temporal_relation(&join.relation)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Removes the version clause from a table, returning its expression if there was one
+    fn table_factor(&mut self, factor: &mut TableFactor) -> Result<Option<SqlExpr>> {
+        match factor {
+            TableFactor::Table { version, .. } => match version.take() {
+                Some(TableVersion::ForSystemTimeAsOf(expr)) => return Ok(Some(expr)),
+                other => *version = other,
+            },
+            TableFactor::Derived { subquery, .. } => self.query(subquery)?,
+            TableFactor::NestedJoin {
+                table_with_joins, ..
+            } => self.table_with_joins(table_with_joins)?,
+            _ => {}
+        }
+        Ok(None)
+    }
+
+    /// Versions are looked up by the event time of the left side, so the AS OF expression must
+    /// refer to its event time field
+    fn validate_as_of(
+        &self,
+        as_of: &SqlExpr,
+        preceding: &[Option<(String, Option<String>)>],
+    ) -> Result<()> {
+        let (relation, column) = match as_of {
+            SqlExpr::Identifier(ident) => (None, normalize_ident(ident)),
+            SqlExpr::CompoundIdentifier(idents) if idents.len() == 2 => (
+                Some(normalize_ident(&idents[0])),
+                normalize_ident(&idents[1]),
+            ),
+            _ => {
+                return plan_err!(
+                    "FOR SYSTEM_TIME AS OF must reference the event time column of the left side of the join, found `{}`",
+                    as_of
+                );
+            }
+        };
+
+        // without a qualifier we can't tell which relation the column belongs to
+        if relation.is_none() && preceding.len() > 1 {
+            return Ok(());
+        }
+
+        for (name, table) in preceding
+            .iter()
+            .flatten()
+            .filter(|(name, _)| relation.as_ref().is_none_or(|r| r == name))
+        {
+            if let Some(Table::ConnectorTable(t)) = table
+                .as_ref()
+                .and_then(|t| self.schema_provider.get_table(t.as_str()))
+            {
+                let time_field = t.event_time_field.as_deref().unwrap_or(TIMESTAMP_FIELD);
+                if column != time_field && column != TIMESTAMP_FIELD {
+                    return plan_err!(
+                        "temporal joins look up versions by the event time of the left side; FOR SYSTEM_TIME AS OF must reference `{}.{}`, not `{}`",
+                        name,
+                        time_field,
+                        as_of
+                    );
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
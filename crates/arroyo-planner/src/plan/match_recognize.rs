@@ -0,0 +1,793 @@
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::ops::ControlFlow;
+use std::sync::Arc;
+
+use arrow_schema::{DataType, Field, Schema};
+use arroyo_rpc::{UPDATING_META_FIELD, duration_from_sql};
+use datafusion::common::tree_node::TreeNode;
+use datafusion::common::{
+    Column, DFSchema, DataFusionError, Result, not_impl_err, plan_datafusion_err, plan_err,
+};
+use datafusion::logical_expr::{Expr, ExprSchemable, Extension, LogicalPlan, Projection};
+use datafusion::optimizer::analyzer::type_coercion::TypeCoercionRewriter;
+use datafusion::sql::planner::{PlannerContext, SqlToRel};
+use datafusion::sql::sqlparser::ast::{
+    AfterMatchSkip, Expr as SqlExpr, Function, FunctionArg, FunctionArgExpr, FunctionArguments,
+    Ident, MatchRecognizePattern, MatchRecognizeSymbol, RepetitionQuantifier, RowsPerMatch,
+    SetExpr, Statement, TableFactor, VisitMut, VisitorMut,
+};
+use datafusion::sql::sqlparser::dialect::Dialect;
+use datafusion::sql::sqlparser::keywords::Keyword;
+use datafusion::sql::sqlparser::parser::ParserError;
+use datafusion::sql::sqlparser::tokenizer::{Token, Tokenizer, Whitespace};
+
+use super::join::{event_time_columns, normalize_ident};
+use crate::extension::key_calculation::{KeyCalculationExtension, KeysOrExprs};
+use crate::extension::match_recognize::{
+    MatchRecognizeExtension, MeasureAggregate, MeasureInput, NfaState,
+};
+use crate::schemas::add_timestamp_field;
+use crate::tables::{Table, produce_optimized_plan};
+use crate::{ArroyoSchemaProvider, fields_with_qualifiers, parse_sql, rewrite_plan};
+
+/// The symbol that a `WITHIN` clause is moved to in the DEFINE clause, see
+/// [`rewrite_within_clauses`]
+const WITHIN_SYMBOL: &str = "__arroyo_within";
+const MEASURE_INPUT_PREFIX: &str = "__measure_input_";
+const MAX_NFA_STATES: usize = 1024;
+
+fn is_keyword(token: &Token, keyword: Keyword) -> bool {
+    matches!(token, Token::Word(w) if w.keyword == keyword)
+}
+
+fn next_token(tokens: &[Token], from: usize) -> Option<usize> {
+    tokens[from..]
+        .iter()
+        .position(|t| !matches!(t, Token::Whitespace(_)))
+        .map(|p| p + from)
+}
+
+/// sqlparser doesn't support the `WITHIN <interval>` clause that may follow the PATTERN of a
+/// MATCH_RECOGNIZE, so it's moved into the DEFINE clause as the definition of a reserved symbol,
+/// from which it's extracted again during planning. Returns the rewritten tokens if there were
+/// any such clauses; other uses of WITHIN, like `WITHIN GROUP`, are left alone.
+pub(crate) fn rewrite_within_clauses(
+    dialect: &dyn Dialect,
+    sql: &str,
+) -> Result<Option<Vec<Token>>, ParserError> {
+    let lowercase = sql.to_lowercase();
+    if !lowercase.contains("within") || !lowercase.contains("match_recognize") {
+        return Ok(None);
+    }
+
+    let mut tokens = Tokenizer::new(dialect, sql).tokenize()?;
+    let mut rewritten = false;
+    let mut in_match_recognize = false;
+    let mut i = 0;
+    while i < tokens.len() {
+        if is_keyword(&tokens[i], Keyword::MATCH_RECOGNIZE) {
+            in_match_recognize = true;
+        }
+        if !in_match_recognize || !is_keyword(&tokens[i], Keyword::PATTERN) {
+            i += 1;
+            continue;
+        }
+        let Some(open) = next_token(&tokens, i + 1).filter(|j| tokens[*j] == Token::LParen) else {
+            i += 1;
+            continue;
+        };
+        in_match_recognize = false;
+
+        let mut depth = 0;
+        let mut close = None;
+        for (j, token) in tokens.iter().enumerate().skip(open) {
+            match token {
+                Token::LParen => depth += 1,
+                Token::RParen => {
+                    depth -= 1;
+                    if depth == 0 {
+                        close = Some(j);
+                        break;
+                    }
+                }
+                _ => {}
+            }
+        }
+        let Some(close) = close else {
+            break;
+        };
+
+        let Some(within) =
+            next_token(&tokens, close + 1).filter(|j| is_keyword(&tokens[*j], Keyword::WITHIN))
+        else {
+            i = close + 1;
+            continue;
+        };
+        let Some(define) = tokens[within..]
+            .iter()
+            .position(|t| is_keyword(t, Keyword::DEFINE))
+            .map(|p| p + within)
+        else {
+            return Err(ParserError::ParserError(
+                "Expected DEFINE after the WITHIN clause of MATCH_RECOGNIZE".to_string(),
+            ));
+        };
+
+        // drop WITHIN, keeping the interval; DEFINE then moves to `within`
+        let interval: Vec<_> = tokens.drain(within..define).skip(1).collect();
+        let mut definition = vec![
+            Token::Whitespace(Whitespace::Space),
+            Token::make_word(WITHIN_SYMBOL, None),
+            Token::Whitespace(Whitespace::Space),
+            Token::make_keyword("AS"),
+        ];
+        definition.extend(interval);
+        definition.push(Token::Comma);
+        tokens.splice(within + 1..within + 1, definition);
+
+        rewritten = true;
+        i = within + 1;
+    }
+
+    Ok(rewritten.then_some(tokens))
+}
+
+/// Plans each MATCH_RECOGNIZE in the statement, which DataFusion doesn't support, into a
+/// [`MatchRecognizeExtension`] registered as a table, and replaces it with a reference to that
+/// table.
+pub(crate) fn plan_match_recognize(
+    statement: &mut Statement,
+    schema_provider: &mut ArroyoSchemaProvider,
+) -> Result<()> {
+    match statement.visit(&mut MatchRecognizePlanner { schema_provider }) {
+        ControlFlow::Break(e) => Err(e),
+        ControlFlow::Continue(()) => Ok(()),
+    }
+}
+
+struct MatchRecognizePlanner<'a> {
+    schema_provider: &'a mut ArroyoSchemaProvider,
+}
+
+impl VisitorMut for MatchRecognizePlanner<'_> {
+    type Break = DataFusionError;
+
+    fn post_visit_table_factor(
+        &mut self,
+        table_factor: &mut TableFactor,
+    ) -> ControlFlow<Self::Break> {
+        if matches!(table_factor, TableFactor::MatchRecognize { .. }) {
+            match self.plan(table_factor) {
+                Ok(relation) => *table_factor = relation,
+                Err(e) => return ControlFlow::Break(e),
+            }
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+fn column_index(schema: &DFSchema, ident: &Ident) -> Result<usize> {
+    let name = normalize_ident(ident);
+    schema
+        .fields()
+        .iter()
+        .position(|f| f.name() == &name)
+        .ok_or_else(|| plan_datafusion_err!("no column '{name}' in the input of MATCH_RECOGNIZE"))
+}
+
+impl MatchRecognizePlanner<'_> {
+    fn plan(&mut self, table_factor: &TableFactor) -> Result<TableFactor> {
+        let TableFactor::MatchRecognize {
+            table,
+            partition_by,
+            order_by,
+            measures,
+            rows_per_match,
+            after_match_skip,
+            pattern,
+            symbols: definitions,
+            alias,
+        } = table_factor
+        else {
+            unreachable!()
+        };
+
+        if matches!(rows_per_match, Some(RowsPerMatch::AllRows(_))) {
+            return not_impl_err!(
+                "ALL ROWS PER MATCH is not supported in MATCH_RECOGNIZE; use ONE ROW PER MATCH"
+            );
+        }
+        let skip_to_next_row = match after_match_skip {
+            None | Some(AfterMatchSkip::PastLastRow) => false,
+            Some(AfterMatchSkip::ToNextRow) => true,
+            Some(skip) => {
+                return not_impl_err!(
+                    "AFTER MATCH {skip} is not supported; only SKIP PAST LAST ROW and SKIP TO NEXT ROW are"
+                );
+            }
+        };
+
+        let statements = parse_sql(&format!("SELECT * FROM {table}"))?;
+        let input = produce_optimized_plan(&statements[0], self.schema_provider)?;
+        let input = rewrite_plan(input, self.schema_provider)?;
+        if input
+            .schema()
+            .has_column_with_unqualified_name(UPDATING_META_FIELD)
+        {
+            return plan_err!("MATCH_RECOGNIZE is not supported over updating inputs");
+        }
+
+        let partition_columns = partition_by
+            .iter()
+            .map(|expr| match expr {
+                SqlExpr::Identifier(ident) => column_index(input.schema(), ident),
+                _ => {
+                    plan_err!("PARTITION BY in MATCH_RECOGNIZE only supports columns, not '{expr}'")
+                }
+            })
+            .collect::<Result<Vec<_>>>()?;
+        if partition_columns.is_empty() {
+            return plan_err!("MATCH_RECOGNIZE requires a PARTITION BY clause");
+        }
+
+        let event_times = event_time_columns(&input)?;
+        match order_by.as_slice() {
+            [order]
+                if matches!(&order.expr, SqlExpr::Identifier(ident)
+                if event_times.contains(&normalize_ident(ident))) => {}
+            _ => {
+                return plan_err!(
+                    "MATCH_RECOGNIZE must be ordered by the event time of its input, one of {}",
+                    event_times.iter().cloned().collect::<Vec<_>>().join(", ")
+                );
+            }
+        }
+
+        let mut symbols = vec![];
+        collect_symbols(pattern, &mut symbols)?;
+
+        let mut within = None;
+        let mut symbol_definitions = HashMap::new();
+        for definition in definitions {
+            if definition.symbol.value == WITHIN_SYMBOL {
+                within = Some(duration_from_sql(definition.definition.clone())?);
+                continue;
+            }
+            let symbol = normalize_ident(&definition.symbol);
+            if !symbols.contains(&symbol) {
+                return plan_err!("symbol '{symbol}' is defined but doesn't appear in the PATTERN");
+            }
+            if symbol_definitions
+                .insert(symbol.clone(), &definition.definition)
+                .is_some()
+            {
+                return plan_err!("symbol '{symbol}' is defined more than once");
+            }
+        }
+
+        // key the input by the partition columns
+        let key_count = partition_columns.len();
+        let mut key_expressions: Vec<_> = partition_columns
+            .iter()
+            .enumerate()
+            .map(|(i, column)| {
+                Expr::Column(Column::from(input.schema().qualified_field(*column)))
+                    .alias(format!("_key_{i}"))
+            })
+            .collect();
+        key_expressions.extend(
+            fields_with_qualifiers(input.schema())
+                .iter()
+                .map(|field| Expr::Column(field.qualified_column())),
+        );
+        let key_plan = LogicalPlan::Extension(Extension {
+            node: Arc::new(KeyCalculationExtension::new(
+                LogicalPlan::Projection(Projection::try_new(
+                    key_expressions,
+                    Arc::new(input.clone()),
+                )?),
+                KeysOrExprs::Keys((0..key_count).collect()),
+            )),
+        });
+        let keyed_schema = DFSchema::try_from(key_plan.schema().as_arrow().clone())?;
+
+        let sql_to_rel = SqlToRel::new(&*self.schema_provider);
+
+        let conditions = symbols
+            .iter()
+            .map(|symbol| {
+                let Some(definition) = symbol_definitions.get(symbol) else {
+                    return Ok(None);
+                };
+                let mut definition = (*definition).clone();
+                if let ControlFlow::Break(e) = definition.visit(&mut DefineRewriter {
+                    symbol,
+                    symbols: &symbols,
+                }) {
+                    return Err(e);
+                }
+                let condition = sql_to_rel
+                    .sql_to_expr(definition, &keyed_schema, &mut PlannerContext::new())?
+                    .rewrite(&mut TypeCoercionRewriter::new(&keyed_schema))?
+                    .data;
+                if condition.get_type(&keyed_schema)? != DataType::Boolean {
+                    return plan_err!("the definition of symbol '{symbol}' must be a boolean");
+                }
+                Ok(Some(condition))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut measure_rewriter = MeasureRewriter {
+            symbols: &symbols,
+            schema: &keyed_schema,
+            inputs: vec![],
+            fields: vec![],
+        };
+        let mut measure_exprs = vec![];
+        for measure in measures {
+            let mut expr = measure.expr.clone();
+            if let ControlFlow::Break(e) = expr.visit(&mut measure_rewriter) {
+                return Err(e);
+            }
+            measure_exprs.push((normalize_ident(&measure.alias), expr));
+        }
+        let MeasureRewriter { inputs, fields, .. } = measure_rewriter;
+        let measure_input_schema = Arc::new(DFSchema::try_from(Schema::new(fields))?);
+
+        let mut output_fields: Vec<Field> = partition_columns
+            .iter()
+            .map(|column| input.schema().field(*column).clone())
+            .collect();
+        let mut planned_measures = vec![];
+        for (name, expr) in measure_exprs {
+            let measure = sql_to_rel
+                .sql_to_expr(expr, &measure_input_schema, &mut PlannerContext::new())?
+                .rewrite(&mut TypeCoercionRewriter::new(&measure_input_schema))?
+                .data;
+            output_fields.push(Field::new(
+                &name,
+                measure.get_type(&measure_input_schema)?,
+                true,
+            ));
+            planned_measures.push(measure);
+        }
+        let schema = add_timestamp_field(
+            Arc::new(DFSchema::try_from(Schema::new(output_fields))?),
+            None,
+        )?;
+
+        let extension = MatchRecognizeExtension {
+            input: key_plan,
+            schema,
+            conditions,
+            states: compile_pattern(pattern, &symbols)?,
+            symbols,
+            measure_inputs: inputs,
+            measure_input_schema,
+            measures: planned_measures,
+            within,
+            skip_to_next_row,
+            ttl: self.schema_provider.planning_options.ttl,
+        };
+
+        let mut n = 0;
+        while self
+            .schema_provider
+            .get_table(format!("__match_recognize_{n}"))
+            .is_some()
+        {
+            n += 1;
+        }
+        let name = format!("__match_recognize_{n}");
+        self.schema_provider.insert_table(Table::TableFromQuery {
+            name: name.clone(),
+            logical_plan: LogicalPlan::Extension(Extension {
+                node: Arc::new(extension),
+            }),
+        });
+
+        let alias = alias
+            .as_ref()
+            .map(|alias| format!(" AS {alias}"))
+            .unwrap_or_default();
+        let statements = parse_sql(&format!("SELECT * FROM {name}{alias}"))?;
+        let Statement::Query(query) = &statements[0] else {
+            unreachable!()
+        };
+        let SetExpr::Select(select) = query.body.as_ref() else {
+            unreachable!()
+        };
+        Ok(select.from[0].relation.clone())
+    }
+}
+
+/// Collects the symbols of the pattern, in order of first appearance
+fn collect_symbols(pattern: &MatchRecognizePattern, symbols: &mut Vec<String>) -> Result<()> {
+    match pattern {
+        MatchRecognizePattern::Symbol(MatchRecognizeSymbol::Named(ident)) => {
+            let symbol = normalize_ident(ident);
+            if !symbols.contains(&symbol) {
+                symbols.push(symbol);
+            }
+        }
+        MatchRecognizePattern::Symbol(symbol) => {
+            return not_impl_err!("'{symbol}' is not supported in MATCH_RECOGNIZE patterns");
+        }
+        MatchRecognizePattern::Exclude(_) | MatchRecognizePattern::Permute(_) => {
+            return not_impl_err!("'{pattern}' is not supported in MATCH_RECOGNIZE patterns");
+        }
+        MatchRecognizePattern::Concat(patterns) | MatchRecognizePattern::Alternation(patterns) => {
+            for pattern in patterns {
+                collect_symbols(pattern, symbols)?;
+            }
+        }
+        MatchRecognizePattern::Group(pattern) | MatchRecognizePattern::Repetition(pattern, _) => {
+            collect_symbols(pattern, symbols)?
+        }
+    }
+    Ok(())
+}
+
+/// Rewrites a DEFINE condition to be evaluated against a single input row, stripping the
+/// qualifier of the symbol being defined
+struct DefineRewriter<'a> {
+    symbol: &'a str,
+    symbols: &'a [String],
+}
+
+impl VisitorMut for DefineRewriter<'_> {
+    type Break = DataFusionError;
+
+    fn pre_visit_expr(&mut self, expr: &mut SqlExpr) -> ControlFlow<Self::Break> {
+        match expr {
+            SqlExpr::CompoundIdentifier(idents) if idents.len() == 2 => {
+                let qualifier = normalize_ident(&idents[0]);
+                if qualifier == self.symbol {
+                    *expr = SqlExpr::Identifier(idents[1].clone());
+                } else if self.symbols.contains(&qualifier) {
+                    return ControlFlow::Break(plan_datafusion_err!(
+                        "the definition of symbol '{}' can't reference symbol '{qualifier}'; \
+                        conditions may only refer to the row being matched",
+                        self.symbol
+                    ));
+                }
+            }
+            SqlExpr::Function(f) => {
+                let name = f.name.to_string().to_lowercase();
+                if matches!(
+                    name.as_str(),
+                    "prev" | "next" | "first" | "last" | "classifier" | "match_number"
+                ) {
+                    return ControlFlow::Break(plan_datafusion_err!(
+                        "{} is not supported in the DEFINE clause of MATCH_RECOGNIZE",
+                        name.to_uppercase()
+                    ));
+                }
+            }
+            _ => {}
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+/// Replaces the references to the rows of a match in a measure with references to
+/// [`MeasureInput`]s, computed by the operator for each match
+struct MeasureRewriter<'a> {
+    symbols: &'a [String],
+    schema: &'a DFSchema,
+    inputs: Vec<MeasureInput>,
+    fields: Vec<Field>,
+}
+
+impl MeasureRewriter<'_> {
+    fn symbol(&self, ident: &Ident) -> Result<usize> {
+        let symbol = normalize_ident(ident);
+        self.symbols
+            .iter()
+            .position(|s| *s == symbol)
+            .ok_or_else(|| plan_datafusion_err!("unknown pattern symbol '{symbol}' in MEASURES"))
+    }
+
+    fn reference(&self, idents: &[Ident]) -> Result<(Option<usize>, usize)> {
+        match idents {
+            [column] => Ok((None, column_index(self.schema, column)?)),
+            [symbol, column] => Ok((
+                Some(self.symbol(symbol)?),
+                column_index(self.schema, column)?,
+            )),
+            _ => plan_err!(
+                "references in MEASURES must be columns, optionally qualified by a pattern symbol"
+            ),
+        }
+    }
+
+    fn function_input(
+        &self,
+        aggregate: MeasureAggregate,
+        f: &Function,
+    ) -> Result<(Option<usize>, Option<usize>)> {
+        let name = f.name.to_string().to_uppercase();
+        let FunctionArguments::List(list) = &f.args else {
+            return plan_err!("{name} in MEASURES requires an argument");
+        };
+        let [FunctionArg::Unnamed(arg)] = list.args.as_slice() else {
+            return plan_err!("{name} in MEASURES takes exactly one argument");
+        };
+
+        let (symbol, column) = match arg {
+            FunctionArgExpr::Wildcard => (None, None),
+            FunctionArgExpr::QualifiedWildcard(symbol) => {
+                (Some(self.symbol(&Ident::new(symbol.to_string()))?), None)
+            }
+            FunctionArgExpr::Expr(SqlExpr::Identifier(ident)) => {
+                let (symbol, column) = self.reference(std::slice::from_ref(ident))?;
+                (symbol, Some(column))
+            }
+            FunctionArgExpr::Expr(SqlExpr::CompoundIdentifier(idents)) => {
+                let (symbol, column) = self.reference(idents)?;
+                (symbol, Some(column))
+            }
+            _ => {
+                return plan_err!(
+                    "the argument to {name} in MEASURES must be a column, optionally qualified by a pattern symbol"
+                );
+            }
+        };
+
+        if column.is_none() && aggregate != MeasureAggregate::Count {
+            return plan_err!("{name}(*) is not supported in MEASURES");
+        }
+        Ok((symbol, column))
+    }
+
+    fn add_input(&mut self, input: MeasureInput) -> Result<String> {
+        if let Some(i) = self.inputs.iter().position(|i| *i == input) {
+            return Ok(self.fields[i].name().clone());
+        }
+
+        let input_type = input
+            .column
+            .map(|c| self.schema.field(c).data_type().clone());
+        let data_type = match (input.aggregate, input_type) {
+            (MeasureAggregate::Count, _) => DataType::Int64,
+            (MeasureAggregate::Sum, Some(t)) if t.is_signed_integer() => DataType::Int64,
+            (MeasureAggregate::Sum, Some(t)) if t.is_unsigned_integer() => DataType::UInt64,
+            (MeasureAggregate::Sum | MeasureAggregate::Avg, Some(t)) if t.is_numeric() => {
+                DataType::Float64
+            }
+            (MeasureAggregate::Sum | MeasureAggregate::Avg, Some(t)) => {
+                return plan_err!(
+                    "{:?} in MEASURES requires a numeric column, not {t}",
+                    input.aggregate
+                );
+            }
+            (_, Some(t)) => t,
+            (_, None) => unreachable!("only COUNT may omit its column"),
+        };
+
+        let name = format!("{MEASURE_INPUT_PREFIX}{}", self.inputs.len());
+        self.fields.push(Field::new(&name, data_type, true));
+        self.inputs.push(input);
+        Ok(name)
+    }
+}
+
+impl VisitorMut for MeasureRewriter<'_> {
+    type Break = DataFusionError;
+
+    fn pre_visit_expr(&mut self, expr: &mut SqlExpr) -> ControlFlow<Self::Break> {
+        let input = match expr {
+            SqlExpr::Function(f) => {
+                let aggregate = match f.name.to_string().to_lowercase().as_str() {
+                    "first" => MeasureAggregate::First,
+                    "last" => MeasureAggregate::Last,
+                    "count" => MeasureAggregate::Count,
+                    "sum" => MeasureAggregate::Sum,
+                    "min" => MeasureAggregate::Min,
+                    "max" => MeasureAggregate::Max,
+                    "avg" => MeasureAggregate::Avg,
+                    _ => return ControlFlow::Continue(()),
+                };
+                self.function_input(aggregate, f)
+                    .map(|(symbol, column)| MeasureInput {
+                        symbol,
+                        column,
+                        aggregate,
+                    })
+            }
+            // a bare reference is to the last row of the match (mapped to the symbol, if any)
+            SqlExpr::CompoundIdentifier(idents) => {
+                self.reference(idents).map(|(symbol, column)| MeasureInput {
+                    symbol,
+                    column: Some(column),
+                    aggregate: MeasureAggregate::Last,
+                })
+            }
+            SqlExpr::Identifier(ident) if !ident.value.starts_with(MEASURE_INPUT_PREFIX) => self
+                .reference(std::slice::from_ref(ident))
+                .map(|(symbol, column)| MeasureInput {
+                    symbol,
+                    column: Some(column),
+                    aggregate: MeasureAggregate::Last,
+                }),
+            _ => return ControlFlow::Continue(()),
+        };
+
+        match input.and_then(|input| self.add_input(input)) {
+            Ok(name) => {
+                *expr = SqlExpr::Identifier(Ident::new(name));
+                ControlFlow::Continue(())
+            }
+            Err(e) => ControlFlow::Break(e),
+        }
+    }
+}
+
+#[derive(Default)]
+struct NfaBuilder {
+    epsilons: Vec<Vec<usize>>,
+    transitions: Vec<Vec<(usize, usize)>>,
+}
+
+impl NfaBuilder {
+    fn add_state(&mut self) -> Result<usize> {
+        if self.epsilons.len() >= MAX_NFA_STATES {
+            return plan_err!("the MATCH_RECOGNIZE pattern is too large");
+        }
+        self.epsilons.push(vec![]);
+        self.transitions.push(vec![]);
+        Ok(self.epsilons.len() - 1)
+    }
+
+    /// Compiles the pattern into a fragment of the NFA, returning its start and end states
+    fn compile(
+        &mut self,
+        pattern: &MatchRecognizePattern,
+        symbols: &[String],
+    ) -> Result<(usize, usize)> {
+        let start = self.add_state()?;
+        let end = match pattern {
+            MatchRecognizePattern::Symbol(MatchRecognizeSymbol::Named(ident)) => {
+                let symbol = normalize_ident(ident);
+                let end = self.add_state()?;
+                let symbol = symbols.iter().position(|s| *s == symbol).unwrap();
+                self.transitions[start].push((symbol, end));
+                end
+            }
+            MatchRecognizePattern::Group(pattern) => {
+                let (s, e) = self.compile(pattern, symbols)?;
+                self.epsilons[start].push(s);
+                e
+            }
+            MatchRecognizePattern::Concat(patterns) => {
+                let mut current = start;
+                for pattern in patterns {
+                    let (s, e) = self.compile(pattern, symbols)?;
+                    self.epsilons[current].push(s);
+                    current = e;
+                }
+                current
+            }
+            MatchRecognizePattern::Alternation(patterns) => {
+                let end = self.add_state()?;
+                for pattern in patterns {
+                    let (s, e) = self.compile(pattern, symbols)?;
+                    self.epsilons[start].push(s);
+                    self.epsilons[e].push(end);
+                }
+                end
+            }
+            MatchRecognizePattern::Repetition(pattern, quantifier) => {
+                let (min, max) = match quantifier {
+                    RepetitionQuantifier::ZeroOrMore => (0, None),
+                    RepetitionQuantifier::OneOrMore => (1, None),
+                    RepetitionQuantifier::AtMostOne => (0, Some(1)),
+                    RepetitionQuantifier::Exactly(n) => (*n, Some(*n)),
+                    RepetitionQuantifier::AtLeast(n) => (*n, None),
+                    RepetitionQuantifier::AtMost(n) => (0, Some(*n)),
+                    RepetitionQuantifier::Range(min, max) => (*min, Some(*max)),
+                };
+                if max.is_some_and(|max| max < min) {
+                    return plan_err!("invalid quantifier {quantifier} in MATCH_RECOGNIZE pattern");
+                }
+
+                let mut current = start;
+                for _ in 0..min {
+                    let (s, e) = self.compile(pattern, symbols)?;
+                    self.epsilons[current].push(s);
+                    current = e;
+                }
+
+                let end = self.add_state()?;
+                match max {
+                    None => {
+                        let hub = self.add_state()?;
+                        let (s, e) = self.compile(pattern, symbols)?;
+                        self.epsilons[current].push(hub);
+                        self.epsilons[hub].push(s);
+                        self.epsilons[e].push(hub);
+                        self.epsilons[hub].push(end);
+                    }
+                    Some(max) => {
+                        for _ in min..max {
+                            let (s, e) = self.compile(pattern, symbols)?;
+                            self.epsilons[current].push(end);
+                            self.epsilons[current].push(s);
+                            current = e;
+                        }
+                        self.epsilons[current].push(end);
+                    }
+                }
+                end
+            }
+            MatchRecognizePattern::Symbol(_)
+            | MatchRecognizePattern::Exclude(_)
+            | MatchRecognizePattern::Permute(_) => {
+                return not_impl_err!("'{pattern}' is not supported in MATCH_RECOGNIZE patterns");
+            }
+        };
+        Ok((start, end))
+    }
+
+    fn closure(&self, state: usize) -> BTreeSet<usize> {
+        let mut closure = BTreeSet::from([state]);
+        let mut queue = vec![state];
+        while let Some(s) = queue.pop() {
+            for next in &self.epsilons[s] {
+                if closure.insert(*next) {
+                    queue.push(*next);
+                }
+            }
+        }
+        closure
+    }
+}
+
+/// Compiles the pattern into an NFA without epsilon transitions, whose start state is 0
+fn compile_pattern(pattern: &MatchRecognizePattern, symbols: &[String]) -> Result<Vec<NfaState>> {
+    let mut builder = NfaBuilder::default();
+    let (start, end) = builder.compile(pattern, symbols)?;
+
+    let eliminated = |state: usize| {
+        let closure = builder.closure(state);
+        let mut transitions: Vec<_> = closure
+            .iter()
+            .flat_map(|s| builder.transitions[*s].iter().copied())
+            .collect();
+        transitions.sort();
+        transitions.dedup();
+        (transitions, closure.contains(&end))
+    };
+
+    // renumber the states reachable from the start
+    let mut ids = HashMap::from([(start, 0)]);
+    let mut states = vec![];
+    let mut queue = VecDeque::from([start]);
+    while let Some(state) = queue.pop_front() {
+        let (transitions, accepting) = eliminated(state);
+        let transitions = transitions
+            .into_iter()
+            .map(|(symbol, target)| {
+                let next = ids.len();
+                let id = *ids.entry(target).or_insert_with(|| {
+                    queue.push_back(target);
+                    next
+                });
+                (symbol, id)
+            })
+            .collect();
+        states.push(NfaState {
+            transitions,
+            accepting,
+        });
+    }
+
+    if states[0].accepting {
+        return plan_err!(
+            "MATCH_RECOGNIZE patterns that can match no rows are not supported; \
+            the pattern must require at least one row"
+        );
+    }
+
+    Ok(states)
+}
@@ -11,7 +11,11 @@ use aggregate::AggregateRewriter;
 use datafusion::logical_expr::{
     Aggregate, Expr, Extension, Filter, LogicalPlan, SubqueryAlias, expr::Alias,
 };
+pub(crate) use deduplicate::DeduplicationRewriter;
+use deduplicate::key_deduplication;
 use join::JoinRewriter;
+pub(crate) use join::extract_temporal_joins;
+pub(crate) use match_recognize::{plan_match_recognize, rewrite_within_clauses};
 
 use self::window_fn::WindowFunctionRewriter;
 use crate::rewriters::TimeWindowNullCheckRemover;
@@ -19,6 +23,7 @@ use crate::{
     ArroyoSchemaProvider, DFField, WindowBehavior,
     extension::{
         aggregate::{AGGREGATE_EXTENSION_NAME, AggregateExtension},
+        deduplicate::DeduplicateExtension,
         join::JOIN_NODE_NAME,
     },
     fields_with_qualifiers, find_window,
@@ -33,7 +38,9 @@ use crate::{
 };
 
 mod aggregate;
+mod deduplicate;
 mod join;
+mod match_recognize;
 mod window_fn;
 
 #[derive(Debug, Default)]
@@ -403,7 +410,16 @@ impl TreeNodeRewriter for ArroyoRewriter<'_> {
             LogicalPlan::Analyze(_) => {
                 return plan_err!("ANALYZE is not supported ({})", node.display());
             }
-            LogicalPlan::Extension(_) => {}
+            LogicalPlan::Extension(ref extension) => {
+                if let Some(deduplicate) = extension
+                    .node
+                    .as_any()
+                    .downcast_ref::<DeduplicateExtension>()
+                    && !deduplicate.keyed
+                {
+                    return key_deduplication(deduplicate);
+                }
+            }
             LogicalPlan::Distinct(_) => {}
             LogicalPlan::Dml(_) => {}
             LogicalPlan::Ddl(_) => {}
@@ -1,10 +1,12 @@
 use crate::builder::NamedNode;
 use crate::extension::ArroyoExtension;
 use crate::extension::debezium::DebeziumUnrollingExtension;
+use crate::extension::late_records::LateRecordsExtension;
 use crate::extension::remote_table::RemoteTableExtension;
 use crate::extension::sink::SinkExtension;
 use crate::extension::table_source::TableSourceExtension;
 use crate::extension::watermark_node::WatermarkNode;
+use crate::logical::LateRecordsSource;
 use crate::schemas::add_timestamp_field;
 use crate::tables::ConnectorTable;
 use crate::tables::FieldSpec;
@@ -208,6 +210,8 @@ impl SourceRewriter<'_> {
             remote,
             table_scan.table_name.clone(),
             Self::watermark_expression(table)?,
+            table.watermark_alignment_max_drift,
+            table.watermark_per_partition,
         )
         .map_err(|err| {
             DataFusionError::Internal(format!("failed to create watermark expression: {err}"))
@@ -231,6 +235,39 @@ impl SourceRewriter<'_> {
         })))
     }
 
+    /// Plans `late_records('source', window)` by reading the source as a normal query would
+    /// (sharing its watermark node with any other readers) and keeping only the records that the
+    /// window drops as late
+    fn mutate_late_records(
+        &self,
+        table_scan: &TableScan,
+        late_records: &LateRecordsSource,
+    ) -> DFResult<Transformed<LogicalPlan>> {
+        let source = late_records.source.as_str();
+        let Some(Table::ConnectorTable(table)) = self.schema_provider.get_table(source) else {
+            return plan_err!("late_records: '{source}' is not a source table");
+        };
+
+        let source_scan = TableScan::try_new(
+            TableReference::bare(source),
+            table_scan.source.clone(),
+            table_scan.projection.clone(),
+            vec![],
+            None,
+        )?;
+
+        let watermarked = self.mutate_connector_table(&source_scan, table)?.data;
+
+        Ok(Transformed::yes(LogicalPlan::Extension(Extension {
+            node: Arc::new(LateRecordsExtension::new(
+                watermarked,
+                table_scan.table_name.clone(),
+                self.schema_provider.planning_options.allowed_lateness,
+                late_records.bin_width,
+            )?),
+        })))
+    }
+
     fn mutate_table_from_query(
         &self,
         table_scan: &TableScan,
@@ -281,6 +318,14 @@ impl TreeNodeRewriter for SourceRewriter<'_> {
             return Ok(Transformed::no(node));
         };
 
+        if let Some(late_records) = table_scan
+            .source
+            .as_any()
+            .downcast_ref::<LateRecordsSource>()
+        {
+            return self.mutate_late_records(&table_scan, late_records);
+        }
+
         let table_name = table_scan.table_name.table();
         let table = self
             .schema_provider
@@ -650,7 +695,7 @@ pub struct TimeWindowUdfChecker {}
 pub fn is_time_window(expr: &Expr) -> Option<&str> {
     if let Expr::ScalarFunction(ScalarFunction { func, args: _ }) = expr {
         match func.name() {
-            "tumble" | "hop" | "session" => {
+            "tumble" | "hop" | "session" | "cumulate" => {
                 return Some(func.name());
             }
             _ => {}
@@ -10,12 +10,12 @@ use arrow_schema::{DataType, Field, FieldRef, Schema};
 use arroyo_connectors::connector_for_type;
 use arroyo_datastream::default_sink;
 use arroyo_operator::connector::Connection;
-use arroyo_rpc::ConnectorOptions;
 use arroyo_rpc::api_types::connections::{
     ConnectionProfile, ConnectionSchema, ConnectionType, SourceField,
 };
 use arroyo_rpc::formats::{BadData, Format, Framing, JsonCompression, JsonFormat};
 use arroyo_rpc::grpc::api::ConnectorOp;
+use arroyo_rpc::{ConnectorOptions, OperatorConfig, PartitionWatermarkConfig, TIMESTAMP_FIELD};
 use arroyo_types::ArroyoExtensionType;
 use datafusion::common::tree_node::{TreeNode, TreeNodeRecursion, TreeNodeVisitor};
 use datafusion::common::{Column, DataFusionError, plan_err};
@@ -76,6 +76,8 @@ pub struct ConnectorTable {
     pub event_time_field: Option<String>,
     pub watermark_field: Option<String>,
     pub idle_time: Option<Duration>,
+    pub watermark_alignment_max_drift: Option<Duration>,
+    pub watermark_per_partition: bool,
     pub primary_keys: Arc<Vec<String>>,
     pub inferred_fields: Option<Vec<FieldRef>>,
     pub partition_exprs: Arc<Option<Vec<Expr>>>,
@@ -97,6 +99,8 @@ multifield_partial_ord!(
     event_time_field,
     watermark_field,
     idle_time,
+    watermark_alignment_max_drift,
+    watermark_per_partition,
     primary_keys
 );
 
@@ -136,7 +140,7 @@ impl From<Field> for FieldSpec {
     }
 }
 
-fn produce_optimized_plan(
+pub(crate) fn produce_optimized_plan(
     statement: &Statement,
     schema_provider: &ArroyoSchemaProvider,
 ) -> Result<LogicalPlan> {
@@ -210,6 +214,8 @@ impl From<Connection> for ConnectorTable {
             event_time_field: None,
             watermark_field: None,
             idle_time: DEFAULT_IDLE_TIME,
+            watermark_alignment_max_drift: None,
+            watermark_per_partition: false,
             primary_keys: Arc::new(vec![]),
             partition_exprs: Arc::new(value.partition_exprs),
             inferred_fields: None,
@@ -267,6 +273,17 @@ impl ConnectorTable {
             DataFusionError::Plan(format!("Unknown connector '{connector_name}'"))
         })?;
 
+        // Postgres CDC sources produce Debezium-style changes, so default to reading them as such
+        if connector_name == "postgres" && !options.contains_key("format") {
+            let typ = options.pull_opt_str("type")?;
+            if typ.as_deref() == Some("source") {
+                options.insert_str("format", "debezium_json")?;
+            }
+            if let Some(typ) = typ {
+                options.insert_str("type", typ)?;
+            }
+        }
+
         let format = Format::from_opts(options)
             .map_err(|e| DataFusionError::Plan(format!("invalid format: '{e}'")))?;
 
@@ -422,6 +439,21 @@ impl ConnectorTable {
             .filter(|t| *t > 0)
             .map(|t| Duration::from_micros(t as u64));
 
+        table.watermark_alignment_max_drift =
+            options.pull_opt_duration("watermark.alignment.max_drift")?;
+        if table.watermark_alignment_max_drift.is_some()
+            && table.connection_type != ConnectionType::Source
+        {
+            return plan_err!("'watermark.alignment.max_drift' can only be set for source tables");
+        }
+
+        if options
+            .pull_opt_bool("watermark.per_partition")?
+            .unwrap_or(false)
+        {
+            table.enable_partition_watermarks()?;
+        }
+
         table.lookup_cache_max_bytes = options.pull_opt_u64("lookup.cache.max_bytes")?;
 
         table.lookup_cache_ttl = options.pull_opt_duration("lookup.cache.ttl")?;
@@ -446,6 +478,49 @@ impl ConnectorTable {
         Ok(table)
     }
 
+    /// Configures the source to track a watermark for each of the partitions it reads from,
+    /// based on the event time field
+    fn enable_partition_watermarks(&mut self) -> Result<()> {
+        if self.connection_type != ConnectionType::Source {
+            return plan_err!("'watermark.per_partition' can only be set for source tables");
+        }
+
+        if !matches!(self.connector.as_str(), "kafka" | "kinesis") {
+            return plan_err!(
+                "'watermark.per_partition' is only supported for Kafka and Kinesis sources"
+            );
+        }
+
+        let event_time_field = match &self.event_time_field {
+            Some(event_time_field) => {
+                if self
+                    .fields
+                    .iter()
+                    .any(|f| f.field().name() == event_time_field && f.is_virtual())
+                {
+                    return plan_err!(
+                        "per-partition watermarks require the event time field `{}` to be read \
+                        from the source, but it is a virtual field",
+                        event_time_field
+                    );
+                }
+                event_time_field.clone()
+            }
+            None => TIMESTAMP_FIELD.to_string(),
+        };
+
+        let mut config: OperatorConfig = serde_json::from_str(&self.config)
+            .map_err(|e| plan_datafusion_err!("invalid config for table {}: {}", self.name, e))?;
+        config.partition_watermarks = Some(PartitionWatermarkConfig {
+            event_time_field,
+            idle_time_micros: self.idle_time.map(|t| t.as_micros() as u64),
+        });
+        self.config = serde_json::to_string(&config).unwrap();
+        self.watermark_per_partition = true;
+
+        Ok(())
+    }
+
     fn has_virtual_fields(&self) -> bool {
         self.fields.iter().any(|f| f.is_virtual())
     }
@@ -457,6 +532,27 @@ impl ConnectorTable {
             .unwrap_or(false)
     }
 
+    /// Whether this table can be written to directly by an updating query, applying retractions
+    /// itself rather than receiving them as Debezium envelopes
+    pub(crate) fn accepts_updating_input(&self) -> bool {
+        if self.connection_type != ConnectionType::Sink {
+            return false;
+        }
+
+        let Some(connector) = connector_for_type(&self.connector) else {
+            return false;
+        };
+
+        serde_json::from_str::<OperatorConfig>(&self.config)
+            .ok()
+            .and_then(|c| {
+                connector
+                    .accepts_updating_input(&c.connection, &c.table)
+                    .ok()
+            })
+            .unwrap_or(false)
+    }
+
     fn timestamp_override(&self) -> Result<Option<Expr>> {
         if let Some(field_name) = &self.event_time_field {
             if self.is_updating() {
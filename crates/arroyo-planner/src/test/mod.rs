@@ -7,9 +7,13 @@ use arroyo_connectors::{
 };
 use arroyo_operator::connector::Connector;
 use arroyo_udf_host::parse::NullableType;
+use sqlparser::dialect::ArroyoDialect;
+use sqlparser::parser::Parser;
 use test_log::test;
 
-use crate::{ArroyoSchemaProvider, SqlConfig, parse_and_get_program};
+use crate::{
+    ArroyoSchemaProvider, SqlConfig, parse_and_get_program, parse_sql, rewrite_within_clauses,
+};
 
 fn get_test_schema_provider() -> ArroyoSchemaProvider {
     let mut schema_provider = ArroyoSchemaProvider::new();
@@ -58,3 +62,37 @@ async fn test_udf() {
         .await
         .unwrap();
 }
+
+#[test]
+fn test_within_group_is_not_rewritten() {
+    let dialect = ArroyoDialect {};
+
+    let sql = "SELECT approx_percentile_cont(0.5) WITHIN GROUP (ORDER BY pattern) \
+        FROM prices GROUP BY symbol";
+    assert!(rewrite_within_clauses(&dialect, sql).unwrap().is_none());
+    assert_eq!(
+        parse_sql(sql).unwrap(),
+        Parser::parse_sql(&dialect, sql).unwrap()
+    );
+
+    // a MATCH_RECOGNIZE elsewhere in the query only has its own WITHIN rewritten
+    let sql = "SELECT symbol, approx_percentile_cont(0.5) WITHIN GROUP (ORDER BY end_price) \
+        FROM prices MATCH_RECOGNIZE ( \
+            PARTITION BY symbol ORDER BY trade_time \
+            MEASURES LAST(B.price) AS end_price \
+            PATTERN (A B+) WITHIN INTERVAL '10' MINUTE \
+            DEFINE B AS B.price > 10 \
+        ) AS m GROUP BY symbol";
+    let statements = parse_sql(sql).unwrap();
+    assert_eq!(statements.len(), 1);
+    let rendered = statements[0].to_string();
+    assert!(
+        rendered.contains("WITHIN GROUP (ORDER BY end_price)"),
+        "{rendered}"
+    );
+    assert!(
+        rendered.contains("__arroyo_within AS INTERVAL '10' MINUTE"),
+        "{rendered}"
+    );
+    assert!(!rendered.contains("B+) WITHIN"), "{rendered}");
+}
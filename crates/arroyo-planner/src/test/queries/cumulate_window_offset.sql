@@ -0,0 +1,17 @@
+CREATE TABLE nexmark WITH (
+    connector = 'nexmark',
+    event_rate = '10'
+);
+
+SELECT
+    bid.auction as auction,
+    cumulate(interval '8 hour', interval '1 minute', interval '1 day') as window,
+    count(*) as count,
+    max(bid.price) as max_price
+FROM
+    nexmark
+where
+    bid is not null
+GROUP BY
+    1,
+    2
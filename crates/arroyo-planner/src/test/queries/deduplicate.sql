@@ -0,0 +1,32 @@
+CREATE TABLE webhook_events (
+    event_id TEXT,
+    payload TEXT,
+    received_at TIMESTAMP,
+    WATERMARK FOR received_at AS received_at - INTERVAL '5' SECOND
+) WITH (
+    connector = 'kafka',
+    bootstrap_servers = 'localhost:9092',
+    type = 'source',
+    topic = 'webhook_events',
+    format = 'json'
+);
+
+CREATE TABLE unique_events (
+    event_id TEXT,
+    payload TEXT,
+    received_at TIMESTAMP
+) WITH (
+    connector = 'kafka',
+    bootstrap_servers = 'localhost:9092',
+    type = 'sink',
+    topic = 'unique_events',
+    format = 'json'
+);
+
+SET deduplication_ttl = INTERVAL '1' HOUR;
+
+INSERT INTO unique_events
+SELECT event_id, payload, received_at FROM (
+    SELECT *, ROW_NUMBER() OVER (PARTITION BY event_id ORDER BY received_at) AS row_num
+    FROM webhook_events
+) WHERE row_num = 1;
@@ -0,0 +1,16 @@
+--fail=Error during planning: cumulate() offset 90s must be a multiple of step 60s less than size 600s
+CREATE TABLE nexmark WITH (
+    connector = 'nexmark',
+    event_rate = '10'
+);
+
+SELECT
+    bid.auction as auction,
+    cumulate(interval '90 second', interval '1 minute', interval '10 minute') as window,
+    count(*) as count
+FROM
+    nexmark
+where
+    bid is not null
+GROUP BY
+1,2
@@ -0,0 +1,16 @@
+--fail=Error during planning: cumulate() size 600s must be a non-zero multiple of step 180s
+CREATE TABLE nexmark WITH (
+    connector = 'nexmark',
+    event_rate = '10'
+);
+
+SELECT
+    bid.auction as auction,
+    cumulate(interval '0 minute', interval '3 minute', interval '10 minute') as window,
+    count(*) as count
+FROM
+    nexmark
+where
+    bid is not null
+GROUP BY
+1,2
@@ -0,0 +1,18 @@
+--fail=ROW_NUMBER() must be ordered by ascending event time
+CREATE TABLE webhook_events (
+    event_id TEXT,
+    payload TEXT,
+    received_at TIMESTAMP,
+    WATERMARK FOR received_at AS received_at - INTERVAL '5' SECOND
+) WITH (
+    connector = 'kafka',
+    bootstrap_servers = 'localhost:9092',
+    type = 'source',
+    topic = 'webhook_events',
+    format = 'json'
+);
+
+SELECT event_id, payload FROM (
+    SELECT *, ROW_NUMBER() OVER (PARTITION BY event_id ORDER BY received_at DESC) AS row_num
+    FROM webhook_events
+) WHERE row_num = 1;
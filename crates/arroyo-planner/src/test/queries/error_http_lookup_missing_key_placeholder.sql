@@ -0,0 +1,25 @@
+--fail=the endpoint or body of an HTTP lookup table must contain a {key} placeholder
+CREATE TABLE events (
+    event_id TEXT,
+    customer_id TEXT
+) WITH (
+    connector = 'kafka',
+    topic = 'events',
+    type = 'source',
+    format = 'json',
+    bootstrap_servers = 'broker:9092'
+);
+
+create temporary table customers (
+    customer_id TEXT METADATA FROM 'key' PRIMARY KEY,
+    customer_name TEXT
+) with (
+    connector = 'http_lookup',
+    endpoint = 'https://api.example.com/customers',
+    format = 'json'
+);
+
+SELECT e.event_id, c.customer_name
+FROM events e
+LEFT JOIN customers c
+ON e.customer_id = c.customer_id;
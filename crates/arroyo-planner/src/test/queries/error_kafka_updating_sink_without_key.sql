@@ -0,0 +1,27 @@
+--fail=input is updating, but sink is not configured as an updating sink
+CREATE TABLE orders (
+    customer_id TEXT NOT NULL,
+    amount BIGINT
+) WITH (
+    connector = 'kafka',
+    bootstrap_servers = 'localhost:9092',
+    type = 'source',
+    topic = 'orders',
+    format = 'json'
+);
+
+CREATE TABLE totals (
+    customer_id TEXT NOT NULL,
+    total BIGINT
+) WITH (
+    connector = 'kafka',
+    bootstrap_servers = 'localhost:9092',
+    type = 'sink',
+    topic = 'totals',
+    format = 'json'
+);
+
+INSERT INTO totals
+SELECT customer_id, sum(amount) as total
+FROM orders
+GROUP BY customer_id;
@@ -0,0 +1,26 @@
+--fail=can't reference symbol 'a'
+CREATE TABLE prices (
+    symbol TEXT,
+    price DOUBLE,
+    trade_time TIMESTAMP,
+    WATERMARK FOR trade_time AS trade_time - INTERVAL '5' SECOND
+) WITH (
+    connector = 'kafka',
+    bootstrap_servers = 'localhost:9092',
+    type = 'source',
+    topic = 'prices',
+    format = 'json'
+);
+
+SELECT *
+FROM prices
+MATCH_RECOGNIZE (
+    PARTITION BY symbol
+    ORDER BY trade_time
+    MEASURES
+        FIRST(A.price) AS start_price,
+        LAST(B.price) AS end_price
+    PATTERN (A B+)
+    DEFINE
+        B AS B.price > A.price
+);
@@ -0,0 +1,13 @@
+--fail=invalid 'source.shared_group'
+CREATE TABLE readings (
+    value DOUBLE
+) WITH (
+    connector = 'mqtt',
+    url = 'tcp://localhost:1883',
+    type = 'source',
+    topic = 'sensors/#',
+    'source.shared_group' = 'group/a',
+    format = 'json'
+);
+
+SELECT * FROM readings;
@@ -0,0 +1,17 @@
+--fail=per-partition watermarks require the event time field `event_time` to be read from the source, but it is a virtual field
+CREATE TABLE orders (
+  customer_id INT,
+  order_id INT,
+  date_string TEXT,
+  event_time TIMESTAMP GENERATED ALWAYS AS (CAST(date_string as TIMESTAMP)) STORED,
+  watermark FOR event_time
+) WITH (
+  connector = 'kafka',
+  format = 'json',
+  type = 'source',
+  bootstrap_servers = 'localhost:9092',
+  topic = 'orders',
+  'watermark.per_partition' = true
+);
+
+SELECT count(*) FROM orders;
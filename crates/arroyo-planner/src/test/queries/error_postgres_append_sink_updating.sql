@@ -0,0 +1,28 @@
+--fail=input is updating, but sink is not configured as an updating sink
+CREATE TABLE orders (
+    customer_id TEXT NOT NULL,
+    amount BIGINT
+) WITH (
+    connector = 'kafka',
+    bootstrap_servers = 'localhost:9092',
+    type = 'source',
+    topic = 'orders',
+    format = 'json'
+);
+
+CREATE TABLE totals (
+    customer_id TEXT PRIMARY KEY,
+    total BIGINT
+) WITH (
+    connector = 'postgres',
+    host = 'localhost',
+    database = 'analytics',
+    type = 'sink',
+    table_name = 'customer_totals',
+    'sink.mode' = 'append'
+);
+
+INSERT INTO totals
+SELECT customer_id, sum(amount) as total
+FROM orders
+GROUP BY customer_id;
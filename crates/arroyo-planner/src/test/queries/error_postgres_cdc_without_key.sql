@@ -0,0 +1,15 @@
+--fail=Debezium source must have at least one PRIMARY KEY field
+CREATE TABLE orders (
+    id BIGINT,
+    amount BIGINT
+) WITH (
+    connector = 'postgres',
+    host = 'localhost',
+    database = 'shop',
+    type = 'source',
+    table_name = 'orders',
+    'source.slot_name' = 'arroyo_orders',
+    'source.snapshot' = 'false'
+);
+
+SELECT count(*) FROM orders;
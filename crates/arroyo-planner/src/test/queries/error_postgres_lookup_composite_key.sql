@@ -0,0 +1,20 @@
+--fail=Postgres lookup tables must have exactly one PRIMARY KEY column
+create table impulse with (
+    connector = 'impulse',
+    event_rate = '2'
+);
+
+create temporary table prices (
+    region TEXT PRIMARY KEY,
+    sku TEXT PRIMARY KEY,
+    price FLOAT
+) with (
+    connector = 'postgres',
+    host = 'localhost',
+    database = 'inventory',
+    table_name = 'prices'
+);
+
+select A.counter, B.price
+from impulse A inner join prices B
+on cast(A.counter as TEXT) = B.sku;
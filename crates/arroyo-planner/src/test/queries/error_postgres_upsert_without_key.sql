@@ -0,0 +1,25 @@
+--fail=must have a PRIMARY KEY
+CREATE TABLE events (
+    customer_id TEXT,
+    event_type TEXT
+) WITH (
+    connector = 'kafka',
+    bootstrap_servers = 'localhost:9092',
+    type = 'source',
+    topic = 'events',
+    format = 'json'
+);
+
+CREATE TABLE latest (
+    customer_id TEXT,
+    event_type TEXT
+) WITH (
+    connector = 'postgres',
+    host = 'localhost',
+    database = 'analytics',
+    type = 'sink',
+    table_name = 'latest_events',
+    'sink.mode' = 'upsert'
+);
+
+INSERT INTO latest SELECT customer_id, event_type FROM events;
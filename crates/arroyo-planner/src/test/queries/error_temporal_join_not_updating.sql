@@ -0,0 +1,30 @@
+--fail=must be an updating table with a PRIMARY KEY
+CREATE TABLE orders (
+    order_id TEXT,
+    currency TEXT,
+    amount DOUBLE,
+    order_time TIMESTAMP,
+    WATERMARK FOR order_time AS order_time - INTERVAL '5' SECOND
+) WITH (
+    connector = 'kafka',
+    bootstrap_servers = 'localhost:9092',
+    type = 'source',
+    topic = 'orders',
+    format = 'json'
+);
+
+CREATE TABLE currency_rates (
+    currency TEXT,
+    rate DOUBLE
+) WITH (
+    connector = 'kafka',
+    bootstrap_servers = 'localhost:9092',
+    type = 'source',
+    topic = 'rates',
+    format = 'json'
+);
+
+SELECT o.order_id, o.amount * r.rate AS converted
+FROM orders o
+JOIN currency_rates FOR SYSTEM_TIME AS OF o.order_time AS r
+ON o.currency = r.currency;
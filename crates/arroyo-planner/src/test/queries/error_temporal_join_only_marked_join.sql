@@ -0,0 +1,36 @@
+--fail=can't handle updating right side of join
+CREATE TABLE orders (
+    order_id TEXT,
+    currency TEXT,
+    amount DOUBLE,
+    order_time TIMESTAMP,
+    WATERMARK FOR order_time AS order_time - INTERVAL '5' SECOND
+) WITH (
+    connector = 'kafka',
+    bootstrap_servers = 'localhost:9092',
+    type = 'source',
+    topic = 'orders',
+    format = 'json'
+);
+
+CREATE TABLE currency_rates (
+    currency TEXT PRIMARY KEY,
+    rate DOUBLE
+) WITH (
+    connector = 'kafka',
+    bootstrap_servers = 'localhost:9092',
+    type = 'source',
+    topic = 'rates',
+    format = 'debezium_json'
+);
+
+SELECT o.order_id, o.amount * r.rate AS converted
+FROM orders o
+JOIN currency_rates FOR SYSTEM_TIME AS OF o.order_time AS r
+ON o.currency = r.currency;
+
+-- joining the versioned table again without FOR SYSTEM_TIME AS OF isn't a temporal join
+SELECT o.order_id, r.rate
+FROM orders o
+JOIN currency_rates r
+ON o.currency = r.currency;
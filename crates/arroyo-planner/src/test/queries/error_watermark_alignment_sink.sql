@@ -0,0 +1,18 @@
+--fail='watermark.alignment.max_drift' can only be set for source tables
+CREATE TABLE impulse WITH (
+  connector = 'impulse',
+  event_rate = '10'
+);
+
+CREATE TABLE output (
+  counter BIGINT UNSIGNED
+) WITH (
+  connector = 'kafka',
+  format = 'json',
+  type = 'sink',
+  bootstrap_servers = 'localhost:9092',
+  topic = 'output',
+  'watermark.alignment.max_drift' = interval '30 seconds'
+);
+
+INSERT INTO output SELECT counter FROM impulse;
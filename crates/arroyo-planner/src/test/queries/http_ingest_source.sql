@@ -0,0 +1,15 @@
+CREATE TABLE clicks (
+    user_id TEXT,
+    url TEXT
+) WITH (
+    connector = 'http_ingest',
+    port = '8090',
+    bearer_token = 'secret-token',
+    format = 'json',
+    framing = 'newline',
+    bad_data = 'drop'
+);
+
+SELECT url, count(*) as views
+FROM clicks
+GROUP BY url, tumble(interval '1 minute');
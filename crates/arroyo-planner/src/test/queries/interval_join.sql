@@ -0,0 +1,30 @@
+CREATE TABLE impressions (
+    ad_id BIGINT,
+    impression_time TIMESTAMP,
+    WATERMARK FOR impression_time AS impression_time - INTERVAL '5' SECOND
+) WITH (
+    connector = 'kafka',
+    bootstrap_servers = 'localhost:9092',
+    type = 'source',
+    topic = 'impressions',
+    format = 'json'
+);
+
+CREATE TABLE clicks (
+    ad_id BIGINT,
+    click_time TIMESTAMP,
+    WATERMARK FOR click_time AS click_time - INTERVAL '5' SECOND
+) WITH (
+    connector = 'kafka',
+    bootstrap_servers = 'localhost:9092',
+    type = 'source',
+    topic = 'clicks',
+    format = 'json'
+);
+
+SELECT i.ad_id, i.impression_time, c.click_time
+FROM impressions i
+JOIN clicks c
+ON i.ad_id = c.ad_id
+AND c.click_time BETWEEN i.impression_time - INTERVAL '5' MINUTE
+    AND i.impression_time + INTERVAL '1' MINUTE;
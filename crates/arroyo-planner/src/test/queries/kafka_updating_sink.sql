@@ -0,0 +1,32 @@
+CREATE TABLE orders (
+    customer_id TEXT NOT NULL,
+    region TEXT,
+    amount BIGINT
+) WITH (
+    connector = 'kafka',
+    bootstrap_servers = 'localhost:9092',
+    type = 'source',
+    topic = 'orders',
+    format = 'json'
+);
+
+CREATE TABLE totals (
+    customer_id TEXT NOT NULL,
+    topic TEXT,
+    headers STRUCT<region TEXT>,
+    total BIGINT
+) WITH (
+    connector = 'kafka',
+    bootstrap_servers = 'localhost:9092',
+    type = 'sink',
+    topic = 'totals',
+    format = 'json',
+    'sink.key_field' = 'customer_id',
+    'sink.topic_field' = 'topic',
+    'sink.headers_field' = 'headers'
+);
+
+INSERT INTO totals
+SELECT customer_id, concat('totals_', region), named_struct('region', region), sum(amount) as total
+FROM orders
+GROUP BY customer_id, region;
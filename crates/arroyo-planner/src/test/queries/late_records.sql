@@ -0,0 +1,42 @@
+CREATE TABLE cars (
+	timestamp TIMESTAMP,
+	driver_id BIGINT,
+	event_type TEXT,
+	location TEXT,
+	WATERMARK FOR timestamp AS timestamp - INTERVAL '5' SECOND
+) WITH (
+	connector = 'single_file',
+	path = 'cars.json',
+	format = 'json',
+	type = 'source'
+);
+
+CREATE TABLE driver_counts (
+	driver_id BIGINT,
+	count BIGINT
+) WITH (
+	connector = 'single_file',
+	path = 'driver_counts.json',
+	format = 'debezium_json',
+	type = 'sink'
+);
+
+CREATE TABLE late_cars (
+	timestamp TIMESTAMP,
+	driver_id BIGINT,
+	event_type TEXT,
+	location TEXT
+) WITH (
+	connector = 'single_file',
+	path = 'late_cars.json',
+	format = 'json',
+	type = 'sink'
+);
+
+SET allowed_lateness = INTERVAL '1' MINUTE;
+
+INSERT INTO driver_counts
+SELECT driver_id, count(*) FROM cars
+GROUP BY driver_id, tumble(INTERVAL '10' SECOND);
+
+INSERT INTO late_cars SELECT * FROM late_records('cars', tumble(INTERVAL '10' SECOND));
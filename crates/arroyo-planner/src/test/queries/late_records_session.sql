@@ -0,0 +1,29 @@
+CREATE TABLE cars (
+	timestamp TIMESTAMP,
+	driver_id BIGINT,
+	event_type TEXT,
+	location TEXT,
+	WATERMARK FOR timestamp AS timestamp - INTERVAL '5' SECOND
+) WITH (
+	connector = 'single_file',
+	path = 'cars.json',
+	format = 'json',
+	type = 'source'
+);
+
+CREATE TABLE late_cars (
+	timestamp TIMESTAMP,
+	driver_id BIGINT,
+	event_type TEXT,
+	location TEXT
+) WITH (
+	connector = 'single_file',
+	path = 'late_cars.json',
+	format = 'json',
+	type = 'sink'
+);
+
+SET allowed_lateness = INTERVAL '30' SECOND;
+
+INSERT INTO late_cars SELECT * FROM late_records('cars', session(INTERVAL '1' MINUTE));
+INSERT INTO late_cars SELECT * FROM late_records('cars', hop(INTERVAL '5' SECOND, INTERVAL '1' MINUTE));
@@ -0,0 +1,31 @@
+CREATE TABLE events (
+    event_id TEXT,
+    timestamp TIMESTAMP,
+    customer_id TEXT,
+    event_type TEXT
+) WITH (
+    connector = 'kafka',
+    topic = 'events',
+    type = 'source',
+    format = 'json',
+    bootstrap_servers = 'broker:9092'
+);
+
+create temporary table customers (
+    customer_id TEXT METADATA FROM 'key' PRIMARY KEY,
+    customer_name TEXT,
+    plan TEXT
+) with (
+    connector = 'http_lookup',
+    endpoint = 'https://api.example.com/customers/{key}',
+    headers = 'Authorization: Bearer {{ API_TOKEN }}',
+    format = 'json',
+    max_concurrency = 16,
+    'lookup.cache.max_bytes' = 1000000,
+    'lookup.cache.ttl' = interval '1' minute
+);
+
+SELECT e.event_id, e.timestamp, e.customer_id, c.customer_name, c.plan
+FROM events e
+LEFT JOIN customers c
+ON e.customer_id = c.customer_id;
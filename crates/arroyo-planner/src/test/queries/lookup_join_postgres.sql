@@ -0,0 +1,33 @@
+CREATE TABLE events (
+    event_id TEXT,
+    timestamp TIMESTAMP,
+    customer_id BIGINT,
+    event_type TEXT
+) WITH (
+    connector = 'kafka',
+    topic = 'events',
+    type = 'source',
+    format = 'json',
+    bootstrap_servers = 'broker:9092'
+);
+
+create temporary table customers (
+    id BIGINT PRIMARY KEY,
+    customer_name TEXT,
+    plan TEXT,
+    signed_up TIMESTAMP
+) with (
+    connector = 'postgres',
+    host = 'localhost',
+    database = 'crm',
+    username = 'arroyo',
+    password = '{{ POSTGRES_PASSWORD }}',
+    table_name = 'public.customers',
+    'lookup.cache.max_bytes' = 1000000,
+    'lookup.cache.ttl' = interval '30' second
+);
+
+SELECT  e.event_id,  e.timestamp,  e.event_type, c.customer_name, c.plan
+FROM  events e
+LEFT JOIN customers c
+ON e.customer_id = c.id;
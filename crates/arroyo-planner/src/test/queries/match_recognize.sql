@@ -0,0 +1,44 @@
+CREATE TABLE logins (
+    user_id TEXT,
+    ip TEXT,
+    success BOOLEAN,
+    login_time TIMESTAMP,
+    WATERMARK FOR login_time AS login_time - INTERVAL '5' SECOND
+) WITH (
+    connector = 'kafka',
+    bootstrap_servers = 'localhost:9092',
+    type = 'source',
+    topic = 'logins',
+    format = 'json'
+);
+
+CREATE TABLE suspicious_logins (
+    user_id TEXT,
+    failures BIGINT,
+    first_failure TIMESTAMP,
+    success_ip TEXT
+) WITH (
+    connector = 'kafka',
+    bootstrap_servers = 'localhost:9092',
+    type = 'sink',
+    topic = 'suspicious_logins',
+    format = 'json'
+);
+
+INSERT INTO suspicious_logins
+SELECT user_id, failures, first_failure, success_ip
+FROM logins
+MATCH_RECOGNIZE (
+    PARTITION BY user_id
+    ORDER BY login_time
+    MEASURES
+        COUNT(F.*) AS failures,
+        FIRST(F.login_time) AS first_failure,
+        S.ip AS success_ip
+    ONE ROW PER MATCH
+    AFTER MATCH SKIP PAST LAST ROW
+    PATTERN (F{3,} S) WITHIN INTERVAL '10' MINUTE
+    DEFINE
+        F AS NOT F.success,
+        S AS S.success
+) AS m;
@@ -0,0 +1,20 @@
+CREATE TABLE readings (
+    device_id TEXT,
+    value DOUBLE,
+    topic TEXT METADATA FROM 'topic',
+    qos INT METADATA FROM 'qos',
+    retained BOOLEAN METADATA FROM 'retain',
+    props TEXT METADATA FROM 'user_properties'
+) WITH (
+    connector = 'mqtt',
+    url = 'tcp://localhost:1883',
+    type = 'source',
+    topic = 'sensors/+/temperature, sensors/+/humidity',
+    'source.shared_group' = 'arroyo',
+    format = 'json'
+);
+
+SELECT topic, avg(value) as avg_value
+FROM readings
+WHERE NOT retained
+GROUP BY topic, tumble(interval '1 minute');
@@ -0,0 +1,17 @@
+CREATE TABLE orders (
+  customer_id INT,
+  order_id INT,
+  timestamp TIMESTAMP NOT NULL,
+  watermark FOR timestamp as timestamp - INTERVAL '1 second'
+) WITH (
+  connector = 'kafka',
+  format = 'json',
+  type = 'source',
+  bootstrap_servers = 'localhost:9092',
+  topic = 'orders',
+  'watermark.per_partition' = true
+);
+
+SELECT tumble(interval '1 minute') as window, customer_id, count(*) as orders
+FROM orders
+GROUP BY 1, 2;
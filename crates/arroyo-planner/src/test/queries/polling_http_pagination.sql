@@ -0,0 +1,17 @@
+create table events (
+    id TEXT,
+    value BIGINT
+) with (
+    connector = 'polling_http',
+    endpoint = 'https://example.com/events?since={cursor}',
+    format = 'json',
+    pagination = 'next_token',
+    'pagination.token_pointer' = '/meta/next_page_token',
+    'pagination.param' = 'page_token',
+    'pagination.max_pages' = '10',
+    'cursor.pointer' = '/meta/last_seen',
+    'cursor.initial' = '0',
+    max_retries = '5'
+);
+
+select * from events;
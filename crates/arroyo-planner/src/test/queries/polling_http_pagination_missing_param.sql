@@ -0,0 +1,12 @@
+--fail=must be set for offset pagination
+create table events (
+    id TEXT
+) with (
+    connector = 'polling_http',
+    endpoint = 'https://example.com/events',
+    format = 'json',
+    pagination = 'offset',
+    'pagination.page_size' = '100'
+);
+
+select * from events;
@@ -0,0 +1,27 @@
+CREATE TABLE events (
+    event_id TEXT,
+    customer_id BIGINT,
+    event_type TEXT
+) WITH (
+    connector = 'kafka',
+    bootstrap_servers = 'localhost:9092',
+    type = 'source',
+    topic = 'events',
+    format = 'json'
+);
+
+CREATE TABLE event_log (
+    event_id TEXT,
+    customer_id BIGINT,
+    event_type TEXT
+) WITH (
+    connector = 'postgres',
+    host = 'localhost',
+    database = 'analytics',
+    type = 'sink',
+    table_name = 'event_log',
+    'sink.mode' = 'append'
+);
+
+INSERT INTO event_log
+SELECT event_id, customer_id, event_type FROM events;
@@ -0,0 +1,30 @@
+CREATE TABLE orders (
+    id BIGINT PRIMARY KEY,
+    customer_id TEXT,
+    amount BIGINT,
+    placed_at TIMESTAMP
+) WITH (
+    connector = 'postgres',
+    host = 'localhost',
+    database = 'shop',
+    username = 'arroyo',
+    type = 'source',
+    table_name = 'public.orders',
+    'source.slot_name' = 'arroyo_orders'
+);
+
+CREATE TABLE totals (
+    customer_id TEXT PRIMARY KEY,
+    total BIGINT
+) WITH (
+    connector = 'postgres',
+    host = 'localhost',
+    database = 'analytics',
+    type = 'sink',
+    table_name = 'customer_totals'
+);
+
+INSERT INTO totals
+SELECT customer_id, sum(amount) as total
+FROM orders
+GROUP BY customer_id;
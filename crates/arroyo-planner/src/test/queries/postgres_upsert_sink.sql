@@ -0,0 +1,29 @@
+CREATE TABLE orders (
+    customer_id TEXT NOT NULL,
+    amount BIGINT
+) WITH (
+    connector = 'kafka',
+    bootstrap_servers = 'localhost:9092',
+    type = 'source',
+    topic = 'orders',
+    format = 'json'
+);
+
+CREATE TABLE totals (
+    customer_id TEXT PRIMARY KEY,
+    total BIGINT
+) WITH (
+    connector = 'postgres',
+    host = 'localhost',
+    database = 'analytics',
+    username = 'arroyo',
+    type = 'sink',
+    table_name = 'public.customer_totals',
+    'sink.commit_mode' = 'exactly_once',
+    'sink.batch_size' = '500'
+);
+
+INSERT INTO totals
+SELECT customer_id, sum(amount) as total
+FROM orders
+GROUP BY customer_id;
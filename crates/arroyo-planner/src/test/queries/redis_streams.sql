@@ -0,0 +1,32 @@
+CREATE TABLE events (
+    entry_id TEXT METADATA FROM 'id',
+    user_id TEXT NOT NULL,
+    action TEXT
+) WITH (
+    connector = 'redis',
+    address = 'redis://localhost:6379',
+    type = 'source',
+    format = 'json',
+    'source.stream_key' = 'events',
+    'source.consumer_group' = 'analytics',
+    'source.start' = 'earliest'
+);
+
+CREATE TABLE actions (
+    user_id TEXT NOT NULL,
+    action TEXT
+) WITH (
+    connector = 'redis',
+    address = 'redis://localhost:6379',
+    type = 'sink',
+    format = 'json',
+    target = 'stream',
+    'target.key_prefix' = 'actions:',
+    'target.key_column' = 'user_id',
+    'target.max_length' = '10000'
+);
+
+INSERT INTO actions
+SELECT user_id, action
+FROM events
+WHERE entry_id IS NOT NULL;
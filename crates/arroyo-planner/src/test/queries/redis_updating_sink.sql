@@ -0,0 +1,28 @@
+CREATE TABLE orders (
+    customer_id TEXT NOT NULL,
+    amount BIGINT
+) WITH (
+    connector = 'kafka',
+    bootstrap_servers = 'localhost:9092',
+    type = 'source',
+    topic = 'orders',
+    format = 'json'
+);
+
+CREATE TABLE totals (
+    customer_id TEXT NOT NULL,
+    total BIGINT
+) WITH (
+    connector = 'redis',
+    address = 'redis://localhost:6379',
+    type = 'sink',
+    format = 'json',
+    target = 'hash',
+    'target.key_prefix' = 'totals',
+    'target.field_column' = 'customer_id'
+);
+
+INSERT INTO totals
+SELECT customer_id, sum(amount) as total
+FROM orders
+GROUP BY customer_id;
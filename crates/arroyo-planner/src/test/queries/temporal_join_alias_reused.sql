@@ -0,0 +1,46 @@
+CREATE TABLE orders (
+    order_id TEXT,
+    currency TEXT,
+    amount DOUBLE,
+    order_time TIMESTAMP,
+    WATERMARK FOR order_time AS order_time - INTERVAL '5' SECOND
+) WITH (
+    connector = 'kafka',
+    bootstrap_servers = 'localhost:9092',
+    type = 'source',
+    topic = 'orders',
+    format = 'json'
+);
+
+CREATE TABLE currency_rates (
+    currency TEXT PRIMARY KEY,
+    rate DOUBLE
+) WITH (
+    connector = 'kafka',
+    bootstrap_servers = 'localhost:9092',
+    type = 'source',
+    topic = 'rates',
+    format = 'debezium_json'
+);
+
+CREATE TABLE refunds (
+    order_id TEXT,
+    reason TEXT
+) WITH (
+    connector = 'kafka',
+    bootstrap_servers = 'localhost:9092',
+    type = 'source',
+    topic = 'refunds',
+    format = 'json'
+);
+
+SELECT o.order_id, o.amount * r.rate AS converted
+FROM orders o
+JOIN currency_rates FOR SYSTEM_TIME AS OF o.order_time AS r
+ON o.currency = r.currency;
+
+-- the same alias in a join without FOR SYSTEM_TIME AS OF is planned as a regular join
+SELECT o.order_id, r.reason
+FROM orders o
+JOIN refunds r
+ON o.order_id = r.order_id;
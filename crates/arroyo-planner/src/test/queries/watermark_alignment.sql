@@ -0,0 +1,35 @@
+CREATE TABLE orders (
+  customer_id INT,
+  order_id INT,
+  timestamp TIMESTAMP NOT NULL,
+  watermark FOR timestamp as timestamp - INTERVAL '5 seconds'
+) WITH (
+  connector = 'kafka',
+  format = 'json',
+  type = 'source',
+  bootstrap_servers = 'localhost:9092',
+  topic = 'orders',
+  'watermark.alignment.max_drift' = interval '30 seconds'
+);
+
+CREATE TABLE payments (
+  order_id INT,
+  amount FLOAT,
+  timestamp TIMESTAMP NOT NULL,
+  watermark FOR timestamp
+) WITH (
+  connector = 'kafka',
+  format = 'json',
+  type = 'source',
+  bootstrap_servers = 'localhost:9092',
+  topic = 'payments',
+  'watermark.alignment.max_drift' = interval '30 seconds'
+);
+
+SELECT tumble(interval '1 minute') as window, count(*) as events
+FROM (
+  SELECT order_id FROM orders
+  UNION ALL
+  SELECT order_id FROM payments
+)
+GROUP BY 1;
@@ -0,0 +1,28 @@
+create table orders (
+    order_id TEXT,
+    customer TEXT,
+    amount DOUBLE
+) with (
+    connector = 'sse',
+    endpoint = 'https://example.com/orders',
+    format = 'json'
+);
+
+create table notifications (
+    order_id TEXT,
+    customer TEXT,
+    amount DOUBLE
+) with (
+    connector = 'webhook',
+    endpoint = 'https://example.com/hooks',
+    format = 'json',
+    batch_size = '100',
+    batch_timeout_ms = '500',
+    batch_format = 'json_array',
+    max_inflight = '4',
+    body_template = '{"text": "order {{order_id}} from {{customer}} for {{amount}}"}',
+    signing_secret = 'secret',
+    bad_data = 'drop'
+);
+
+insert into notifications select * from orders;
@@ -0,0 +1,19 @@
+--fail=requires the JSON format
+create table orders (
+    order_id TEXT
+) with (
+    connector = 'sse',
+    endpoint = 'https://example.com/orders',
+    format = 'json'
+);
+
+create table notifications (
+    value TEXT
+) with (
+    connector = 'webhook',
+    endpoint = 'https://example.com/hooks',
+    format = 'raw_string',
+    body_template = '{"text": "{{value}}"}'
+);
+
+insert into notifications select order_id from orders;
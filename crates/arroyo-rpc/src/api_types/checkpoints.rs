@@ -1,7 +1,7 @@
 use arroyo_types::to_micros;
 use serde::{Deserialize, Serialize};
 use std::time::SystemTime;
-use utoipa::ToSchema;
+use utoipa::{IntoParams, ToSchema};
 
 #[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
 #[serde(rename_all = "snake_case")]
@@ -40,6 +40,73 @@ pub struct OperatorCheckpointGroup {
     pub subtasks: Vec<SubtaskCheckpointGroup>,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct CheckpointGcPost {
+    /// If set, reports the files that would be deleted without deleting them
+    pub dry_run: Option<bool>,
+    /// If set, also deletes the checkpoints of jobs that aren't in this cluster's database,
+    /// which is only safe if no other cluster shares its checkpoint storage
+    pub delete_orphans: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct CheckpointGcResult {
+    pub dry_run: bool,
+    /// Checkpoint files that are no longer referenced by any job
+    pub files: Vec<String>,
+    pub bytes: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct StateTable {
+    pub name: String,
+    pub table_type: StateTableType,
+    pub description: String,
+    /// Number of Parquet files referenced by this table in the checkpoint
+    pub files: u32,
+    pub bytes: u64,
+}
+
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, Eq, PartialEq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum StateTableType {
+    GlobalKeyed,
+    ExpiringKeyedTime,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct StateTableCollection {
+    pub data: Vec<StateTable>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, IntoParams, ToSchema)]
+#[into_params(parameter_in = Query)]
+#[serde(rename_all = "snake_case")]
+pub struct StateTableQueryParams {
+    /// Only return rows whose key matches. Single-column keys are matched against the value
+    /// itself, multi-column keys against a JSON array of the key values
+    pub key: Option<String>,
+    pub offset: Option<u64>,
+    pub limit: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct StateTableRows {
+    pub table: StateTable,
+    pub key_fields: Vec<String>,
+    /// Rows that would be restored from the checkpoint, encoded as JSON objects: expired rows
+    /// and superseded generations are dropped. Binary columns (like the bincode-encoded keys and
+    /// values of global keyed tables) are hex-encoded.
+    pub rows: Vec<serde_json::Value>,
+    pub offset: u64,
+    pub has_more: bool,
+}
+
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub enum JobCheckpointEventType {
     Checkpointing,
@@ -1,7 +1,13 @@
 use crate::api_types::udfs::Udf;
 use crate::errors::ErrorDomain;
 use crate::grpc as grpc_proto;
+use crate::quantities::QuantityParser;
+use anyhow::{anyhow, bail};
+use k8s_openapi::api::core::v1::{Affinity, EnvVar, Toleration, Volume, VolumeMount};
+use k8s_openapi::apimachinery::pkg::api::resource::Quantity;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use utoipa::ToSchema;
 
 #[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
@@ -26,6 +32,121 @@ pub struct PipelinePost {
     pub udfs: Option<Vec<Udf>>,
     pub parallelism: u64,
     pub checkpoint_interval_micros: Option<u64>,
+    pub worker_overrides: Option<WorkerOverrides>,
+    /// If set, checkpoint barriers skip ahead of queued data, which is stored as part of the
+    /// checkpoint; this keeps checkpoints fast when the pipeline is backpressured. Only data
+    /// already queued at a subtask is overtaken, and each input still stops being read once its
+    /// barrier arrives until barriers have arrived on all other inputs
+    pub unaligned_checkpoints: Option<bool>,
+}
+
+/// Per-pipeline overrides for the worker pods created by the Kubernetes scheduler. Values
+/// here are applied on top of the global `kubernetes-scheduler.worker` config.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct WorkerOverrides {
+    /// CPU for each task slot, as a Kubernetes quantity (e.g., "900m")
+    pub cpu_per_slot: Option<String>,
+    /// Memory for each task slot, as a Kubernetes quantity (e.g., "2Gi")
+    pub memory_per_slot: Option<String>,
+    pub slots_per_pod: Option<u32>,
+    #[serde(default)]
+    pub node_selector: BTreeMap<String, String>,
+    #[serde(default)]
+    #[schema(value_type = Vec<Object>)]
+    pub tolerations: Vec<serde_json::Value>,
+    #[schema(value_type = Option<Object>)]
+    pub affinity: Option<serde_json::Value>,
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
+    #[serde(default)]
+    pub annotations: BTreeMap<String, String>,
+    #[serde(default)]
+    #[schema(value_type = Vec<Object>)]
+    pub env: Vec<serde_json::Value>,
+    #[serde(default)]
+    #[schema(value_type = Vec<Object>)]
+    pub volumes: Vec<serde_json::Value>,
+    #[serde(default)]
+    #[schema(value_type = Vec<Object>)]
+    pub volume_mounts: Vec<serde_json::Value>,
+}
+
+impl WorkerOverrides {
+    pub fn cpu_per_slot(&self) -> Option<Quantity> {
+        self.cpu_per_slot.clone().map(Quantity)
+    }
+
+    pub fn memory_per_slot(&self) -> Option<Quantity> {
+        self.memory_per_slot.clone().map(Quantity)
+    }
+
+    pub fn tolerations(&self) -> anyhow::Result<Vec<Toleration>> {
+        parse_k8s_list(&self.tolerations, "tolerations")
+    }
+
+    pub fn affinity(&self) -> anyhow::Result<Option<Affinity>> {
+        self.affinity
+            .as_ref()
+            .map(|a| {
+                serde_json::from_value(a.clone()).map_err(|e| anyhow!("invalid affinity: {e}"))
+            })
+            .transpose()
+    }
+
+    pub fn env(&self) -> anyhow::Result<Vec<EnvVar>> {
+        parse_k8s_list(&self.env, "env")
+    }
+
+    pub fn volumes(&self) -> anyhow::Result<Vec<Volume>> {
+        parse_k8s_list(&self.volumes, "volumes")
+    }
+
+    pub fn volume_mounts(&self) -> anyhow::Result<Vec<VolumeMount>> {
+        parse_k8s_list(&self.volume_mounts, "volume_mounts")
+    }
+
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if let Some(cpu) = self.cpu_per_slot() {
+            let v = cpu
+                .parse_cpu()
+                .map_err(|e| anyhow!("invalid cpu_per_slot '{}': {e}", cpu.0))?;
+            if v.value() <= 0 {
+                bail!("cpu_per_slot must be positive");
+            }
+        }
+
+        if let Some(memory) = self.memory_per_slot() {
+            let v = memory
+                .parse_memory()
+                .map_err(|e| anyhow!("invalid memory_per_slot '{}': {e}", memory.0))?;
+            if v.value() <= 0 {
+                bail!("memory_per_slot must be positive");
+            }
+        }
+
+        if self.slots_per_pod == Some(0) {
+            bail!("slots_per_pod must be at least 1");
+        }
+
+        self.tolerations()?;
+        self.affinity()?;
+        self.env()?;
+        self.volumes()?;
+        self.volume_mounts()?;
+
+        Ok(())
+    }
+}
+
+fn parse_k8s_list<T: DeserializeOwned>(
+    values: &[serde_json::Value],
+    field: &str,
+) -> anyhow::Result<Vec<T>> {
+    values
+        .iter()
+        .map(|v| serde_json::from_value(v.clone()).map_err(|e| anyhow!("invalid {field}: {e}")))
+        .collect()
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
@@ -371,6 +371,21 @@ pub struct CompactionConfig {
     pub checkpoints_to_compact: u32,
 }
 
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct CheckpointRetentionConfig {
+    /// The number of most recent checkpoints to keep for each job
+    pub keep_last: u32,
+
+    /// If set, checkpoints that finished within this duration are also kept
+    #[serde(default)]
+    pub keep_for: Option<HumanReadableDuration>,
+
+    /// Garbage collection will not delete checkpoint files newer than this, as they may belong
+    /// to checkpoints that are still in progress
+    pub gc_min_age: HumanReadableDuration,
+}
+
 #[derive(Debug, Deserialize, Serialize, Clone)]
 #[serde(rename_all = "kebab-case", deny_unknown_fields)]
 pub struct CompilerConfig {
@@ -530,6 +545,8 @@ pub struct PipelineConfig {
     pub chaining: ChainingConfig,
 
     pub compaction: CompactionConfig,
+
+    pub checkpoint_retention: CheckpointRetentionConfig,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
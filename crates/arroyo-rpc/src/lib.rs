@@ -1,6 +1,7 @@
 pub mod api_types;
 pub mod formats;
 pub mod public_ids;
+pub mod quantities;
 pub mod schema_resolver;
 pub mod var_str;
 
@@ -242,6 +243,14 @@ pub enum ControlResp {
         message: String,
         details: String,
     },
+    /// Reported periodically by watermark generators that have alignment enabled; the watermark
+    /// is None if the subtask is idle (or finished)
+    WatermarkReport {
+        node_id: u32,
+        task_index: usize,
+        watermark: Option<SystemTime>,
+        max_drift: Duration,
+    },
 }
 
 pub struct FileAuthInterceptor {
@@ -278,6 +287,16 @@ pub struct RateLimit {
     pub messages_per_second: u32,
 }
 
+/// Configures a source that reads from several partitions (like Kafka partitions or Kinesis
+/// shards) to track a watermark for each of them, emitting the minimum as its own watermark
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PartitionWatermarkConfig {
+    /// the field of the source's output that holds the event time of each record
+    pub event_time_field: String,
+    /// partitions that haven't received data for this long don't hold back the watermark
+    pub idle_time_micros: Option<u64>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct MetadataField {
     pub field_name: String,
@@ -296,6 +315,8 @@ pub struct OperatorConfig {
     pub rate_limit: Option<RateLimit>,
     #[serde(default)]
     pub metadata_fields: Vec<MetadataField>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub partition_watermarks: Option<PartitionWatermarkConfig>,
 }
 
 impl Default for OperatorConfig {
@@ -308,6 +329,7 @@ impl Default for OperatorConfig {
             framing: None,
             rate_limit: None,
             metadata_fields: vec![],
+            partition_watermarks: None,
         }
     }
 }
@@ -88,13 +88,13 @@ impl MemoryUnit {
     }
 }
 
+#[derive(Copy, Clone)]
 pub enum ParsedQuantity {
     Cpu(i64),
     Memory(i128),
 }
 
 impl ParsedQuantity {
-    #[allow(unused)]
     pub fn value(&self) -> i128 {
         match self {
             ParsedQuantity::Cpu(i) => *i as i128,
@@ -84,8 +84,10 @@ async fn run_smoketest(path: &Path) {
             .collect::<Vec<_>>()
     });
 
+    let unaligned = query.starts_with("--unaligned");
+
     match (
-        correctness_run_codegen(test_name, query.clone(), pk.as_deref(), 20).await,
+        correctness_run_codegen(test_name, query.clone(), pk.as_deref(), unaligned, 20).await,
         fail,
     ) {
         (Ok(_), false) => {
@@ -113,6 +115,7 @@ struct SmokeTestContext<'a> {
     engine: &'a RunningEngine,
     control_rx: &'a mut Receiver<ControlResp>,
     program: Arc<LogicalProgram>,
+    unaligned: bool,
 }
 
 async fn checkpoint(ctx: &mut SmokeTestContext<'_>, epoch: u32) {
@@ -132,6 +135,7 @@ async fn checkpoint(ctx: &mut SmokeTestContext<'_>, epoch: u32) {
         min_epoch: 0,
         timestamp: SystemTime::now(),
         then_stop: false,
+        unaligned: ctx.unaligned,
     };
 
     for source in ctx.engine.source_controls() {
@@ -303,6 +307,7 @@ async fn run_and_checkpoint(
     tasks_per_operator: HashMap<String, usize>,
     control_rx: &mut Receiver<ControlResp>,
     checkpoint_interval: i32,
+    unaligned: bool,
 ) {
     let engine = Engine::for_local(program, job_id.to_string())
         .await
@@ -322,6 +327,7 @@ async fn run_and_checkpoint(
         engine: &running_engine,
         control_rx,
         program: logical_program,
+        unaligned,
     };
 
     // trigger a couple checkpoints
@@ -382,6 +388,7 @@ async fn run_pipeline_and_assert_outputs(
     golden_output_location: String,
     udfs: &[LocalUdf],
     primary_keys: Option<&[&str]>,
+    unaligned: bool,
 ) {
     // remove output_location before running the pipeline
     if std::path::Path::new(&output_location).exists() {
@@ -393,7 +400,15 @@ async fn run_pipeline_and_assert_outputs(
     let (control_tx, mut control_rx) = channel(128);
     run_completely(
         job_id,
-        Program::local_from_logical(job_id.to_string(), &graph, udfs, None, control_tx).await,
+        Program::local_from_logical(
+            job_id.to_string(),
+            &graph,
+            udfs,
+            None,
+            unaligned,
+            control_tx,
+        )
+        .await,
         output_location.clone(),
         golden_output_location.clone(),
         primary_keys,
@@ -412,17 +427,27 @@ async fn run_pipeline_and_assert_outputs(
     println!("Run and checkpoint");
     run_and_checkpoint(
         Arc::new(job_id.to_string()),
-        Program::local_from_logical(job_id.to_string(), &graph, udfs, None, control_tx).await,
+        Program::local_from_logical(
+            job_id.to_string(),
+            &graph,
+            udfs,
+            None,
+            unaligned,
+            control_tx,
+        )
+        .await,
         Arc::new(LogicalProgram::new(
             graph.clone(),
             ProgramConfig {
                 udf_dylibs: Default::default(),
                 python_udfs: Default::default(),
+                unaligned_checkpoints: unaligned,
             },
         )),
         tasks_per_operator(&graph),
         &mut control_rx,
         checkpoint_interval,
+        unaligned,
     )
     .await;
 
@@ -435,7 +460,15 @@ async fn run_pipeline_and_assert_outputs(
     println!("Finish from checkpoint");
     finish_from_checkpoint(
         job_id,
-        Program::local_from_logical(job_id.to_string(), &graph, udfs, Some(3), control_tx).await,
+        Program::local_from_logical(
+            job_id.to_string(),
+            &graph,
+            udfs,
+            Some(3),
+            unaligned,
+            control_tx,
+        )
+        .await,
         &mut control_rx,
     )
     .await;
@@ -667,6 +700,7 @@ pub async fn correctness_run_codegen(
     test_name: impl Into<String>,
     query: impl Into<String>,
     primary_keys: Option<&[&str]>,
+    unaligned_checkpoints: bool,
     checkpoint_interval: i32,
 ) -> Result<()> {
     let test_name = test_name.into();
@@ -709,6 +743,7 @@ pub async fn correctness_run_codegen(
         golden_output_location,
         &udfs,
         primary_keys,
+        unaligned_checkpoints,
     )
     .await;
     Ok(())
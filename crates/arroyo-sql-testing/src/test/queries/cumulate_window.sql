@@ -0,0 +1,35 @@
+CREATE TABLE orders (
+  order_time TIMESTAMP,
+  region TEXT,
+  amount BIGINT
+) WITH (
+  connector = 'single_file',
+  path = '$input_dir/orders.json',
+  format = 'json',
+  type = 'source',
+  event_time_field = 'order_time'
+);
+
+CREATE TABLE revenue_so_far (
+  region TEXT,
+  window_start TIMESTAMP,
+  window_end TIMESTAMP,
+  orders BIGINT,
+  revenue BIGINT
+) WITH (
+  connector = 'single_file',
+  path = '$output_path',
+  format = 'json',
+  type = 'sink'
+);
+
+-- the hour so far, starting on the half hour and refreshed every ten minutes
+INSERT INTO revenue_so_far
+SELECT region, window.start, window.end, orders, revenue FROM (
+  SELECT region,
+    cumulate(INTERVAL '30' MINUTE, INTERVAL '10' MINUTE, INTERVAL '1' HOUR) as window,
+    COUNT(*) as orders,
+    SUM(amount) as revenue
+  FROM orders
+  GROUP BY 1, 2
+);
@@ -0,0 +1,30 @@
+CREATE TABLE webhook_events (
+  event_id TEXT,
+  payload TEXT,
+  received_at TIMESTAMP NOT NULL,
+  WATERMARK FOR received_at
+) WITH (
+  connector = 'single_file',
+  path = '$input_dir/webhook_events.json',
+  format = 'json',
+  type = 'source'
+);
+
+CREATE TABLE unique_events (
+  event_id TEXT,
+  payload TEXT,
+  received_at TIMESTAMP
+) WITH (
+  connector = 'single_file',
+  path = '$output_path',
+  format = 'json',
+  type = 'sink'
+);
+
+SET deduplication_ttl = INTERVAL '1' MINUTE;
+
+INSERT INTO unique_events
+SELECT event_id, payload, received_at FROM (
+  SELECT *, ROW_NUMBER() OVER (PARTITION BY event_id ORDER BY received_at) AS row_num
+  FROM webhook_events
+) WHERE row_num = 1;
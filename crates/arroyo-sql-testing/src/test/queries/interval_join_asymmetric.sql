@@ -0,0 +1,40 @@
+CREATE TABLE impressions (
+  ad_id BIGINT,
+  impression_time TIMESTAMP NOT NULL,
+  WATERMARK FOR impression_time
+) WITH (
+  connector = 'single_file',
+  path = '$input_dir/impressions.json',
+  format = 'json',
+  type = 'source'
+);
+
+CREATE TABLE clicks (
+  ad_id BIGINT,
+  click_time TIMESTAMP NOT NULL,
+  WATERMARK FOR click_time
+) WITH (
+  connector = 'single_file',
+  path = '$input_dir/clicks.json',
+  format = 'json',
+  type = 'source'
+);
+
+CREATE TABLE attributed_clicks (
+  ad_id BIGINT,
+  impression_time TIMESTAMP,
+  click_time TIMESTAMP
+) WITH (
+  connector = 'single_file',
+  path = '$output_path',
+  format = 'json',
+  type = 'sink'
+);
+
+INSERT INTO attributed_clicks
+SELECT i.ad_id, i.impression_time, c.click_time
+FROM impressions i
+JOIN clicks c
+ON i.ad_id = c.ad_id
+AND c.click_time BETWEEN i.impression_time - INTERVAL '1' MINUTE
+    AND i.impression_time + INTERVAL '5' MINUTE;
@@ -0,0 +1,46 @@
+CREATE TABLE logins (
+  user_id TEXT,
+  status TEXT,
+  login_time TIMESTAMP NOT NULL,
+  WATERMARK FOR login_time
+) WITH (
+  connector = 'single_file',
+  path = '$input_dir/logins.json',
+  format = 'json',
+  type = 'source'
+);
+
+CREATE TABLE recovered_logins (
+  user_id TEXT,
+  warnings BIGINT,
+  failures BIGINT,
+  successes BIGINT,
+  first_failure TIMESTAMP,
+  last_success TIMESTAMP
+) WITH (
+  connector = 'single_file',
+  path = '$output_path',
+  format = 'json',
+  type = 'sink'
+);
+
+INSERT INTO recovered_logins
+SELECT user_id, warnings, failures, successes, first_failure, last_success
+FROM logins
+MATCH_RECOGNIZE (
+  PARTITION BY user_id
+  ORDER BY login_time
+  MEASURES
+    COUNT(W.*) AS warnings,
+    COUNT(F.*) AS failures,
+    COUNT(S.*) AS successes,
+    FIRST(F.login_time) AS first_failure,
+    LAST(S.login_time) AS last_success
+  ONE ROW PER MATCH
+  AFTER MATCH SKIP PAST LAST ROW
+  PATTERN (W? F{2,4} S+) WITHIN INTERVAL '3' MINUTE
+  DEFINE
+    W AS W.status = 'warn',
+    F AS F.status = 'fail',
+    S AS S.status = 'ok'
+) AS m;
@@ -0,0 +1,889 @@
+//! Read-only access to the contents of checkpointed state, used by the state inspection API
+//! and the `arroyo state inspect` command.
+
+use crate::parquet::{base_path, metadata_path, operator_path};
+use crate::tables::ErasedTable;
+use crate::tables::expiring_time_key_map::ExpiringTimeKeyTable;
+use crate::tables::global_keyed_map::GlobalKeyedTable;
+use arrow::compute::filter_record_batch;
+use arrow::compute::kernels::cmp::gt_eq;
+use arrow::json::ArrayWriter;
+use arrow_array::{RecordBatch, TimestampNanosecondArray};
+use arroyo_rpc::api_types::checkpoints::{StateTable, StateTableRows, StateTableType};
+use arroyo_rpc::df::ArroyoSchema;
+use arroyo_rpc::errors::StateError;
+use arroyo_rpc::grpc::rpc::{
+    CheckpointMetadata, ExpiringKeyedTimeTableConfig, GlobalKeyedTableConfig,
+    OperatorCheckpointMetadata, TableCheckpointMetadata, TableConfig, TableEnum,
+};
+use arroyo_storage::StorageProvider;
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use prost::Message;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+
+/// The maximum number of rows returned by a single call to [`read_table`]
+pub const MAX_ROWS: usize = 1000;
+
+async fn load_operator_metadata(
+    storage: &StorageProvider,
+    job_id: &str,
+    epoch: u32,
+    operator_id: &str,
+) -> Result<Option<OperatorCheckpointMetadata>, StateError> {
+    storage
+        .get_if_present(metadata_path(&operator_path(job_id, epoch, operator_id)).as_str())
+        .await?
+        .map(|data| Ok(OperatorCheckpointMetadata::decode(&data[..])?))
+        .transpose()
+}
+
+/// A table within an operator checkpoint, along with the files that make it up
+struct CheckpointedTable {
+    name: String,
+    table_type: StateTableType,
+    description: String,
+    /// the columns that make up the key, if the table is keyed
+    key_fields: Vec<String>,
+    /// the number of leading columns to display; the remainder are internal bookkeeping
+    columns: Option<usize>,
+    /// the timestamp column and the time (in nanos) before which rows have expired, for tables
+    /// that expire their data; expired rows are dropped when the table is restored
+    expiration: Option<(usize, i64)>,
+    /// whether rows are versioned by a `_generation` column, in which case only the latest
+    /// generation of each key is restored
+    generational: bool,
+    files: Vec<String>,
+}
+
+impl CheckpointedTable {
+    fn new(
+        name: &str,
+        config: &TableConfig,
+        metadata: &TableCheckpointMetadata,
+        watermark_micros: Option<u64>,
+    ) -> Result<Self, StateError> {
+        let other = |error: String| StateError::Other {
+            table: name.to_string(),
+            error,
+        };
+
+        let mut expiration = None;
+        let mut generational = false;
+
+        let (table_type, description, key_fields, columns, files) = match config.table_type() {
+            TableEnum::MissingTableType => {
+                return Err(other("table has no table type".to_string()));
+            }
+            TableEnum::GlobalKeyValue => {
+                let table_config = GlobalKeyedTableConfig::decode(&config.config[..])?;
+                (
+                    StateTableType::GlobalKeyed,
+                    table_config.description,
+                    vec!["key".to_string()],
+                    None,
+                    GlobalKeyedTable::files_to_keep(config.clone(), metadata.clone())?,
+                )
+            }
+            TableEnum::ExpiringKeyedTimeTable => {
+                let table_config = ExpiringKeyedTimeTableConfig::decode(&config.config[..])?;
+                let schema: ArroyoSchema = table_config
+                    .schema
+                    .ok_or_else(|| other("missing schema".to_string()))?
+                    .try_into()
+                    .map_err(|e| other(format!("schema conversion error: {e:?}")))?;
+
+                let key_fields = schema
+                    .storage_keys()
+                    .map(|keys| {
+                        keys.iter()
+                            .map(|i| schema.schema.field(*i).name().clone())
+                            .collect()
+                    })
+                    .unwrap_or_default();
+
+                expiration = watermark_micros.map(|watermark| {
+                    let cutoff = watermark.saturating_sub(table_config.retention_micros);
+                    (schema.timestamp_index, cutoff as i64 * 1000)
+                });
+                generational = table_config.generational;
+
+                (
+                    StateTableType::ExpiringKeyedTime,
+                    table_config.description,
+                    key_fields,
+                    // generational tables store the `_generation` after the schema's columns
+                    Some(schema.schema.fields().len() + generational as usize),
+                    ExpiringTimeKeyTable::files_to_keep(config.clone(), metadata.clone())?,
+                )
+            }
+        };
+
+        let mut files: Vec<_> = files.into_iter().collect();
+        files.sort();
+
+        Ok(Self {
+            name: name.to_string(),
+            table_type,
+            description,
+            key_fields,
+            columns,
+            expiration,
+            generational,
+            files,
+        })
+    }
+
+    async fn summarize(&self, storage: &StorageProvider) -> Result<StateTable, StateError> {
+        let mut bytes = 0;
+        for file in &self.files {
+            bytes += storage.head(file.as_str()).await?.size;
+        }
+
+        Ok(StateTable {
+            name: self.name.clone(),
+            table_type: self.table_type,
+            description: self.description.clone(),
+            files: self.files.len() as u32,
+            bytes,
+        })
+    }
+
+    fn key(&self, row: &Value) -> Vec<Value> {
+        self.key_fields
+            .iter()
+            .map(|f| row.get(f).cloned().unwrap_or(Value::Null))
+            .collect()
+    }
+
+    fn key_matches(&self, row: &Value, key: &str) -> bool {
+        let values = self.key(row);
+
+        match values.as_slice() {
+            [Value::String(s)] => s == key,
+            [v] => v.to_string() == key,
+            _ => Value::Array(values).to_string() == key,
+        }
+    }
+
+    fn to_rows(&self, batch: &RecordBatch) -> Result<Vec<Value>, StateError> {
+        let batch = match self.expiration {
+            Some((timestamp_index, expired_before)) => {
+                let live = gt_eq(
+                    batch.column(timestamp_index),
+                    &TimestampNanosecondArray::new_scalar(expired_before),
+                )?;
+                filter_record_batch(batch, &live)?
+            }
+            None => batch.clone(),
+        };
+
+        let batch = match self.columns {
+            Some(n) => batch.project(&(0..n.min(batch.num_columns())).collect::<Vec<_>>())?,
+            None => batch.clone(),
+        };
+
+        let mut writer = ArrayWriter::new(vec![]);
+        writer.write(&batch)?;
+        writer.finish()?;
+
+        serde_json::from_slice(&writer.into_inner()).map_err(|e| StateError::Other {
+            table: self.name.clone(),
+            error: format!("failed to convert rows to JSON: {e}"),
+        })
+    }
+}
+
+async fn checkpointed_tables(
+    storage: &StorageProvider,
+    job_id: &str,
+    epoch: u32,
+    operator_id: &str,
+) -> Result<Option<Vec<CheckpointedTable>>, StateError> {
+    let Some(metadata) = load_operator_metadata(storage, job_id, epoch, operator_id).await? else {
+        return Ok(None);
+    };
+
+    let watermark = metadata
+        .operator_metadata
+        .as_ref()
+        .and_then(|m| m.min_watermark);
+
+    let mut tables = metadata
+        .table_checkpoint_metadata
+        .iter()
+        .map(|(name, table_metadata)| {
+            let config = metadata
+                .table_configs
+                .get(name)
+                .ok_or_else(|| StateError::Other {
+                    table: name.clone(),
+                    error: "missing table config".to_string(),
+                })?;
+            CheckpointedTable::new(name, config, table_metadata, watermark)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    tables.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(Some(tables))
+}
+
+/// Lists the epochs of every checkpoint in storage that has finished writing its metadata,
+/// by job
+pub async fn list_checkpoints(
+    storage: &StorageProvider,
+) -> Result<BTreeMap<String, Vec<u32>>, StateError> {
+    let mut checkpoints: BTreeMap<String, Vec<u32>> = BTreeMap::new();
+    for object in storage.list_objects("").await? {
+        let parts: Vec<_> = object.location.parts().collect();
+        if let [job_id, checkpoints_dir, checkpoint, metadata] = &parts[..]
+            && checkpoints_dir.as_ref() == "checkpoints"
+            && metadata.as_ref() == "metadata"
+            && let Some(epoch) = checkpoint
+                .as_ref()
+                .strip_prefix("checkpoint-")
+                .and_then(|e| e.parse().ok())
+        {
+            checkpoints
+                .entry(job_id.as_ref().to_string())
+                .or_default()
+                .push(epoch);
+        }
+    }
+
+    for epochs in checkpoints.values_mut() {
+        epochs.sort();
+    }
+
+    Ok(checkpoints)
+}
+
+/// Lists the operators in a checkpoint, or None if there's no checkpoint for that epoch
+pub async fn list_operators(
+    storage: &StorageProvider,
+    job_id: &str,
+    epoch: u32,
+) -> Result<Option<Vec<String>>, StateError> {
+    let Some(data) = storage
+        .get_if_present(metadata_path(&base_path(job_id, epoch)).as_str())
+        .await?
+    else {
+        return Ok(None);
+    };
+
+    let mut operators = CheckpointMetadata::decode(&data[..])?.operator_ids;
+    operators.sort();
+    Ok(Some(operators))
+}
+
+/// Lists the tables in an operator's checkpoint for the given epoch, along with their sizes.
+/// Returns None if the operator has no checkpoint for that epoch.
+pub async fn list_tables(
+    storage: &StorageProvider,
+    job_id: &str,
+    epoch: u32,
+    operator_id: &str,
+) -> Result<Option<Vec<StateTable>>, StateError> {
+    let Some(tables) = checkpointed_tables(storage, job_id, epoch, operator_id).await? else {
+        return Ok(None);
+    };
+
+    let mut result = vec![];
+    for table in tables {
+        result.push(table.summarize(storage).await?);
+    }
+    Ok(Some(result))
+}
+
+/// Reads up to `limit` rows of a checkpointed table, skipping the first `offset` rows that
+/// match `key` (or all rows, if no key is given). Returns None if the table doesn't exist in the
+/// operator's checkpoint for that epoch.
+///
+/// Rows are filtered the way they are when the table is restored: rows that have expired as of
+/// the checkpoint's watermark are dropped, as are rows of generational tables that have been
+/// superseded by a later generation of the same key.
+#[allow(clippy::too_many_arguments)]
+pub async fn read_table(
+    storage: &StorageProvider,
+    job_id: &str,
+    epoch: u32,
+    operator_id: &str,
+    table: &str,
+    key: Option<&str>,
+    offset: usize,
+    limit: usize,
+) -> Result<Option<StateTableRows>, StateError> {
+    let limit = limit.min(MAX_ROWS);
+
+    let Some(table) = checkpointed_tables(storage, job_id, epoch, operator_id)
+        .await?
+        .and_then(|tables| tables.into_iter().find(|t| t.name == table))
+    else {
+        return Ok(None);
+    };
+
+    let mut skipped = 0;
+    let mut rows = vec![];
+    let mut has_more = false;
+
+    // returns false once the page is full
+    let mut page = |row: Value| {
+        if skipped < offset {
+            skipped += 1;
+        } else if rows.len() < limit {
+            rows.push(row);
+        } else {
+            has_more = true;
+            return false;
+        }
+        true
+    };
+
+    // for generational tables we need to see every row of a key before we know which one is
+    // live, so they're collected before paging; for others we can stop once the page is full
+    let mut latest: Vec<Option<Value>> = vec![];
+    let mut latest_by_key: HashMap<String, (u64, usize)> = HashMap::new();
+
+    'files: for file in &table.files {
+        let contents = storage.get(file.as_str()).await?;
+        for batch in ParquetRecordBatchReaderBuilder::try_new(contents)?.build()? {
+            for row in table.to_rows(&batch?)? {
+                if key.is_some_and(|key| !table.key_matches(&row, key)) {
+                    continue;
+                }
+
+                if !table.generational {
+                    if !page(row) {
+                        break 'files;
+                    }
+                    continue;
+                }
+
+                let generation = row.get("_generation").and_then(Value::as_u64).unwrap_or(0);
+                let row_key = Value::Array(table.key(&row)).to_string();
+                let previous = latest_by_key.get(&row_key).copied();
+                if previous.is_some_and(|(g, _)| g > generation) {
+                    continue;
+                }
+                if let Some((_, i)) = previous {
+                    latest[i] = None;
+                }
+                latest_by_key.insert(row_key, (generation, latest.len()));
+                latest.push(Some(row));
+            }
+        }
+    }
+
+    for row in latest.into_iter().flatten() {
+        if !page(row) {
+            break;
+        }
+    }
+
+    Ok(Some(StateTableRows {
+        table: table.summarize(storage).await?,
+        key_fields: table.key_fields,
+        rows,
+        offset: offset as u64,
+        has_more,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schemas::SchemaWithHashAndOperation;
+    use arrow_array::{
+        BinaryArray, Int64Array, StringArray, TimestampNanosecondArray, UInt64Array,
+    };
+    use arroyo_rpc::grpc::rpc::{
+        ExpiringKeyedTimeTableCheckpointMetadata, GlobalKeyedTableTaskCheckpointMetadata,
+        OperatorMetadata, ParquetTimeFile,
+    };
+    use arroyo_types::to_nanos;
+    use bincode::config;
+    use parquet::arrow::ArrowWriter;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use std::time::SystemTime;
+
+    const JOB_ID: &str = "job_1";
+    const OPERATOR_ID: &str = "operator_1";
+    const USERS: [&str; 3] = ["a", "b", "c"];
+
+    async fn storage() -> StorageProvider {
+        let dir = std::env::temp_dir().join(format!(
+            "arroyo-inspect-{}-{}",
+            std::process::id(),
+            to_nanos(SystemTime::now())
+        ));
+        StorageProvider::for_url(&format!("file://{}", dir.to_string_lossy()))
+            .await
+            .unwrap()
+    }
+
+    fn to_parquet(batch: &RecordBatch) -> Vec<u8> {
+        let mut data = vec![];
+        let mut writer = ArrowWriter::try_new(&mut data, batch.schema(), None).unwrap();
+        writer.write(batch).unwrap();
+        writer.close().unwrap();
+        data
+    }
+
+    fn counts_schema() -> ArroyoSchema {
+        ArroyoSchema::new_keyed(
+            Arc::new(arrow_schema::Schema::new(vec![
+                arrow_schema::Field::new("user_id", arrow_schema::DataType::Utf8, false),
+                arrow_schema::Field::new("count", arrow_schema::DataType::Int64, false),
+                arrow_schema::Field::new(
+                    "_timestamp",
+                    arrow_schema::DataType::Timestamp(arrow_schema::TimeUnit::Nanosecond, None),
+                    false,
+                ),
+            ])),
+            2,
+            vec![0],
+        )
+    }
+
+    /// A file of an expiring time key table, with the counts `start..end` assigned to users in
+    /// turn
+    fn counts_file(start: i64, end: i64) -> Vec<u8> {
+        let schema = SchemaWithHashAndOperation::new(Arc::new(counts_schema()), false);
+        let n = (end - start) as usize;
+        let batch = RecordBatch::try_new(
+            schema.state_schema().schema.clone(),
+            vec![
+                Arc::new(StringArray::from_iter_values(
+                    (start..end).map(|i| USERS[i as usize % USERS.len()]),
+                )),
+                Arc::new(Int64Array::from_iter_values(start..end)),
+                Arc::new(TimestampNanosecondArray::from_iter_values(start..end)),
+                Arc::new(UInt64Array::from_iter_values(
+                    (start..end).map(|i| i as u64),
+                )),
+                Arc::new(BinaryArray::from_iter_values(std::iter::repeat_n(b"", n))),
+            ],
+        )
+        .unwrap();
+        to_parquet(&batch)
+    }
+
+    /// A file of a generational expiring time key table, with rows of
+    /// `(user_id, count, timestamp, generation)`
+    fn generations_file(rows: &[(&str, i64, i64, u64)]) -> Vec<u8> {
+        let schema = SchemaWithHashAndOperation::new(Arc::new(counts_schema()), true);
+        let batch = RecordBatch::try_new(
+            schema.state_schema().schema.clone(),
+            vec![
+                Arc::new(StringArray::from_iter_values(rows.iter().map(|r| r.0))),
+                Arc::new(Int64Array::from_iter_values(rows.iter().map(|r| r.1))),
+                Arc::new(TimestampNanosecondArray::from_iter_values(
+                    rows.iter().map(|r| r.2),
+                )),
+                Arc::new(UInt64Array::from_iter_values(rows.iter().map(|r| r.3))),
+                Arc::new(UInt64Array::from_iter_values(rows.iter().map(|_| 0))),
+                Arc::new(BinaryArray::from_iter_values(std::iter::repeat_n(
+                    b"",
+                    rows.len(),
+                ))),
+            ],
+        )
+        .unwrap();
+        to_parquet(&batch)
+    }
+
+    fn encode<T: bincode::Encode>(value: T) -> Vec<u8> {
+        bincode::encode_to_vec(value, config::standard()).unwrap()
+    }
+
+    fn offsets_file() -> Vec<u8> {
+        let batch = RecordBatch::try_new(
+            Arc::new(arrow_schema::Schema::new(vec![
+                arrow_schema::Field::new("key", arrow_schema::DataType::Binary, false),
+                arrow_schema::Field::new("value", arrow_schema::DataType::Binary, false),
+            ])),
+            vec![
+                Arc::new(BinaryArray::from_iter_values([encode(0u32), encode(1u32)])),
+                Arc::new(BinaryArray::from_iter_values([
+                    encode(10u64),
+                    encode(20u64),
+                ])),
+            ],
+        )
+        .unwrap();
+        to_parquet(&batch)
+    }
+
+    /// Writes a checkpoint of an operator with a global keyed table `o` of two offsets, an
+    /// expiring time key table `t` of 1500 counts, split across two files, and a generational
+    /// table `g` whose rows are superseded or expired as of the checkpoint's 1µs watermark
+    async fn write_checkpoint(storage: &StorageProvider, job_id: &str, epoch: u32) {
+        let path = operator_path(job_id, epoch, OPERATOR_ID);
+
+        let counts_files = vec![format!("{path}/table-t-000"), format!("{path}/table-t-001")];
+        storage
+            .put(counts_files[0].as_str(), counts_file(0, 900))
+            .await
+            .unwrap();
+        storage
+            .put(counts_files[1].as_str(), counts_file(900, 1500))
+            .await
+            .unwrap();
+
+        let generations_files = vec![format!("{path}/table-g-000"), format!("{path}/table-g-001")];
+        storage
+            .put(
+                generations_files[0].as_str(),
+                generations_file(&[("a", 1, 2000, 1), ("b", 2, 2000, 2), ("c", 3, 500, 3)]),
+            )
+            .await
+            .unwrap();
+        storage
+            .put(
+                generations_files[1].as_str(),
+                generations_file(&[("a", 4, 3000, 4), ("b", 5, 3000, 1)]),
+            )
+            .await
+            .unwrap();
+
+        let offsets_file_name = format!("{path}/table-o-000");
+        storage
+            .put(offsets_file_name.as_str(), offsets_file())
+            .await
+            .unwrap();
+
+        let mut table_configs = HashMap::new();
+        let mut table_checkpoint_metadata = HashMap::new();
+
+        table_configs.insert(
+            "t".to_string(),
+            TableConfig {
+                table_type: TableEnum::ExpiringKeyedTimeTable as i32,
+                config: ExpiringKeyedTimeTableConfig {
+                    table_name: "t".to_string(),
+                    description: "counts".to_string(),
+                    retention_micros: 1,
+                    generational: false,
+                    schema: Some(counts_schema().into()),
+                }
+                .encode_to_vec(),
+                state_version: 0,
+            },
+        );
+        table_checkpoint_metadata.insert(
+            "t".to_string(),
+            TableCheckpointMetadata {
+                table_type: TableEnum::ExpiringKeyedTimeTable as i32,
+                data: ExpiringKeyedTimeTableCheckpointMetadata {
+                    files: counts_files
+                        .into_iter()
+                        .map(|file| ParquetTimeFile {
+                            epoch,
+                            file,
+                            min_routing_key: 0,
+                            max_routing_key: u64::MAX,
+                            max_timestamp_micros: 0,
+                            generation: 0,
+                        })
+                        .collect(),
+                }
+                .encode_to_vec(),
+            },
+        );
+
+        table_configs.insert(
+            "g".to_string(),
+            TableConfig {
+                table_type: TableEnum::ExpiringKeyedTimeTable as i32,
+                config: ExpiringKeyedTimeTableConfig {
+                    table_name: "g".to_string(),
+                    description: "latest counts".to_string(),
+                    retention_micros: 0,
+                    generational: true,
+                    schema: Some(counts_schema().into()),
+                }
+                .encode_to_vec(),
+                state_version: 0,
+            },
+        );
+        table_checkpoint_metadata.insert(
+            "g".to_string(),
+            TableCheckpointMetadata {
+                table_type: TableEnum::ExpiringKeyedTimeTable as i32,
+                data: ExpiringKeyedTimeTableCheckpointMetadata {
+                    files: generations_files
+                        .into_iter()
+                        .map(|file| ParquetTimeFile {
+                            epoch,
+                            file,
+                            min_routing_key: 0,
+                            max_routing_key: u64::MAX,
+                            max_timestamp_micros: 3,
+                            generation: 0,
+                        })
+                        .collect(),
+                }
+                .encode_to_vec(),
+            },
+        );
+
+        table_configs.extend(crate::global_table_config("o", "offsets"));
+        table_checkpoint_metadata.insert(
+            "o".to_string(),
+            TableCheckpointMetadata {
+                table_type: TableEnum::GlobalKeyValue as i32,
+                data: GlobalKeyedTableTaskCheckpointMetadata {
+                    files: vec![offsets_file_name],
+                    commit_data_by_subtask: HashMap::new(),
+                }
+                .encode_to_vec(),
+            },
+        );
+
+        storage
+            .put(
+                metadata_path(&path).as_str(),
+                OperatorCheckpointMetadata {
+                    operator_metadata: Some(OperatorMetadata {
+                        job_id: job_id.to_string(),
+                        operator_id: OPERATOR_ID.to_string(),
+                        epoch,
+                        min_watermark: Some(1),
+                        max_watermark: Some(1),
+                        parallelism: 2,
+                    }),
+                    start_time: 0,
+                    finish_time: 0,
+                    table_checkpoint_metadata,
+                    table_configs,
+                }
+                .encode_to_vec(),
+            )
+            .await
+            .unwrap();
+
+        storage
+            .put(
+                metadata_path(&base_path(job_id, epoch)).as_str(),
+                CheckpointMetadata {
+                    job_id: job_id.to_string(),
+                    epoch,
+                    min_epoch: 1,
+                    start_time: 0,
+                    finish_time: 0,
+                    operator_ids: vec![OPERATOR_ID.to_string()],
+                }
+                .encode_to_vec(),
+            )
+            .await
+            .unwrap();
+    }
+
+    fn counts(rows: &StateTableRows) -> Vec<i64> {
+        rows.rows
+            .iter()
+            .map(|row| row["count"].as_i64().unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_list_checkpoints() {
+        let storage = storage().await;
+        write_checkpoint(&storage, "job_1", 1).await;
+        write_checkpoint(&storage, "job_1", 3).await;
+        write_checkpoint(&storage, "job_2", 2).await;
+
+        // a checkpoint that hasn't finished writing its metadata isn't listed
+        storage
+            .put(
+                format!("{}/table-t-000", operator_path("job_1", 4, OPERATOR_ID)).as_str(),
+                counts_file(0, 10),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            list_checkpoints(&storage).await.unwrap(),
+            BTreeMap::from([
+                ("job_1".to_string(), vec![1, 3]),
+                ("job_2".to_string(), vec![2]),
+            ])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_list_operators() {
+        let storage = storage().await;
+        write_checkpoint(&storage, JOB_ID, 1).await;
+
+        assert_eq!(
+            list_operators(&storage, JOB_ID, 1).await.unwrap(),
+            Some(vec![OPERATOR_ID.to_string()])
+        );
+        assert_eq!(list_operators(&storage, JOB_ID, 2).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_list_tables() {
+        let storage = storage().await;
+        write_checkpoint(&storage, JOB_ID, 1).await;
+
+        let tables = list_tables(&storage, JOB_ID, 1, OPERATOR_ID)
+            .await
+            .unwrap()
+            .unwrap();
+
+        let summary: Vec<_> = tables
+            .iter()
+            .map(|t| {
+                (
+                    t.name.as_str(),
+                    t.table_type,
+                    t.description.as_str(),
+                    t.files,
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("g", StateTableType::ExpiringKeyedTime, "latest counts", 2),
+                ("o", StateTableType::GlobalKeyed, "offsets", 1),
+                ("t", StateTableType::ExpiringKeyedTime, "counts", 2),
+            ]
+        );
+        assert!(tables.iter().all(|t| t.bytes > 0));
+
+        assert!(
+            list_tables(&storage, JOB_ID, 1, "operator_2")
+                .await
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            list_tables(&storage, JOB_ID, 2, OPERATOR_ID)
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_read_table() {
+        let storage = storage().await;
+        write_checkpoint(&storage, JOB_ID, 1).await;
+
+        let rows = read_table(&storage, JOB_ID, 1, OPERATOR_ID, "t", None, 0, 10)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(rows.key_fields, vec!["user_id".to_string()]);
+        assert_eq!(counts(&rows), (0..10).collect::<Vec<_>>());
+        assert!(rows.has_more);
+        assert_eq!(rows.table.files, 2);
+
+        // internal columns like the key hash aren't returned
+        let mut columns: Vec<_> = rows.rows[0].as_object().unwrap().keys().cloned().collect();
+        columns.sort();
+        assert_eq!(columns, vec!["_timestamp", "count", "user_id"]);
+
+        // pages continue across files
+        let rows = read_table(&storage, JOB_ID, 1, OPERATOR_ID, "t", None, 895, 10)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(counts(&rows), (895..905).collect::<Vec<_>>());
+        assert_eq!(rows.offset, 895);
+
+        // the offset counts rows matching the key
+        let rows = read_table(&storage, JOB_ID, 1, OPERATOR_ID, "t", Some("b"), 2, 3)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(counts(&rows), vec![7, 10, 13]);
+        assert!(rows.has_more);
+
+        let rows = read_table(&storage, JOB_ID, 1, OPERATOR_ID, "t", Some("b"), 498, 10)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(counts(&rows), vec![1495, 1498]);
+        assert!(!rows.has_more);
+
+        // binary keys of global keyed tables are matched by their hex encoding
+        let key: String = encode(1u32).iter().map(|b| format!("{b:02x}")).collect();
+        let rows = read_table(&storage, JOB_ID, 1, OPERATOR_ID, "o", Some(&key), 0, 10)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(rows.key_fields, vec!["key".to_string()]);
+        assert_eq!(rows.rows.len(), 1);
+
+        assert!(
+            read_table(&storage, JOB_ID, 1, OPERATOR_ID, "missing", None, 0, 10)
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_read_table_applies_expiration_and_generations() {
+        let storage = storage().await;
+        write_checkpoint(&storage, JOB_ID, 1).await;
+
+        // c's only row is before the watermark, and b's row in the second file is of an older
+        // generation than the one in the first
+        let rows = read_table(&storage, JOB_ID, 1, OPERATOR_ID, "g", None, 0, 10)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(counts(&rows), vec![2, 4]);
+        assert!(!rows.has_more);
+        assert_eq!(rows.rows[1]["user_id"], "a");
+        assert_eq!(rows.rows[1]["_generation"], 4);
+
+        let rows = read_table(&storage, JOB_ID, 1, OPERATOR_ID, "g", Some("a"), 0, 10)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(counts(&rows), vec![4]);
+
+        let rows = read_table(&storage, JOB_ID, 1, OPERATOR_ID, "g", None, 1, 10)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(counts(&rows), vec![4]);
+
+        assert!(
+            read_table(&storage, JOB_ID, 1, OPERATOR_ID, "g", Some("c"), 0, 10)
+                .await
+                .unwrap()
+                .unwrap()
+                .rows
+                .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_read_table_is_capped_at_max_rows() {
+        let storage = storage().await;
+        write_checkpoint(&storage, JOB_ID, 1).await;
+
+        let rows = read_table(&storage, JOB_ID, 1, OPERATOR_ID, "t", None, 0, 5000)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(rows.rows.len(), MAX_ROWS);
+        assert_eq!(counts(&rows), (0..MAX_ROWS as i64).collect::<Vec<_>>());
+        assert!(rows.has_more);
+
+        let rows = read_table(&storage, JOB_ID, 1, OPERATOR_ID, "t", None, MAX_ROWS, 5000)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(rows.rows.len(), 500);
+        assert!(!rows.has_more);
+    }
+}
@@ -22,6 +22,7 @@ use std::time::{Duration, SystemTime};
 
 pub mod checkpoint_state;
 pub mod committing_state;
+pub mod inspect;
 mod metrics;
 pub mod parquet;
 pub(crate) mod schemas;
@@ -33,8 +34,19 @@ pub const FULL_KEY_RANGE: RangeInclusive<u64> = 0..=u64::MAX;
 #[derive(Debug)]
 pub enum StateMessage {
     Checkpoint(CheckpointMessage),
+    /// Starts a checkpoint that stays open for [`StateMessage::CheckpointData`] until
+    /// [`StateMessage::FinishCheckpoint`]; other data sent meanwhile goes to the next checkpoint
+    StartCheckpoint(CheckpointMessage),
+    CheckpointData {
+        table: String,
+        data: TableData,
+    },
+    FinishCheckpoint,
     Compaction(HashMap<String, TableCheckpointMetadata>),
-    TableData { table: String, data: TableData },
+    TableData {
+        table: String,
+        data: TableData,
+    },
 }
 #[derive(Debug)]
 pub struct CheckpointMessage {
@@ -181,7 +193,7 @@ pub fn hash_key<K: Hash>(key: &K) -> u64 {
 static STORAGE_PROVIDER: tokio::sync::OnceCell<Arc<StorageProvider>> =
     tokio::sync::OnceCell::const_new();
 
-pub(crate) async fn get_storage_provider() -> Result<&'static Arc<StorageProvider>, StateError> {
+pub async fn get_storage_provider() -> Result<&'static Arc<StorageProvider>, StateError> {
     // TODO: this should be encoded in the config so that the controller doesn't need
     // to be synchronized with the workers
 
@@ -2,12 +2,14 @@ use crate::tables::expiring_time_key_map::ExpiringTimeKeyTable;
 use crate::tables::global_keyed_map::GlobalKeyedTable;
 use crate::tables::{CompactionConfig, ErasedTable};
 use crate::{BackingStore, get_storage_provider};
+use arroyo_rpc::api_types::checkpoints::CheckpointGcResult;
 use arroyo_rpc::errors::StateError;
 use arroyo_rpc::grpc::rpc::{
     CheckpointMetadata, OperatorCheckpointMetadata, TableCheckpointMetadata,
 };
 use futures::StreamExt;
 use futures::stream::FuturesUnordered;
+use object_store::ObjectMeta;
 
 use arroyo_rpc::config::config;
 use arroyo_rpc::grpc::rpc;
@@ -23,15 +25,15 @@ pub const GENERATIONS_TO_COMPACT: u32 = 1; // only compact generation 0 files
 
 pub struct ParquetBackend;
 
-fn base_path(job_id: &str, epoch: u32) -> String {
+pub(crate) fn base_path(job_id: &str, epoch: u32) -> String {
     format!("{job_id}/checkpoints/checkpoint-{epoch:0>7}")
 }
 
-fn metadata_path(path: &str) -> String {
+pub(crate) fn metadata_path(path: &str) -> String {
     format!("{path}/metadata")
 }
 
-fn operator_path(job_id: &str, epoch: u32, operator: &str) -> String {
+pub(crate) fn operator_path(job_id: &str, epoch: u32, operator: &str) -> String {
     format!("{}/operator-{}", base_path(job_id, epoch), operator)
 }
 
@@ -300,6 +302,192 @@ impl ParquetBackend {
 
         Ok(operator_id)
     }
+
+    /// Returns every data file referenced by an operator's checkpoint
+    fn referenced_files(
+        operator_metadata: &OperatorCheckpointMetadata,
+    ) -> Result<HashSet<String>, StateError> {
+        let mut files = HashSet::new();
+        for (table_name, metadata) in &operator_metadata.table_checkpoint_metadata {
+            let Some(table_config) = operator_metadata.table_configs.get(table_name) else {
+                warn!("missing table config for table {}", table_name);
+                continue;
+            };
+
+            files.extend(match table_config.table_type() {
+                rpc::TableEnum::MissingTableType => {
+                    warn!("found table without table type: {:?}", table_name);
+                    HashSet::new()
+                }
+                rpc::TableEnum::GlobalKeyValue => {
+                    GlobalKeyedTable::files_to_keep(table_config.clone(), metadata.clone())?
+                }
+                rpc::TableEnum::ExpiringKeyedTimeTable => {
+                    ExpiringTimeKeyTable::files_to_keep(table_config.clone(), metadata.clone())?
+                }
+            });
+        }
+
+        Ok(files)
+    }
+
+    /// Deletes checkpoint files that aren't needed by any job. This cleans up after failed
+    /// checkpoints and interrupted compactions, which aren't handled by the normal per-job
+    /// cleanup. `jobs` should contain every job known to the database; the files of job ids that
+    /// aren't in it are only deleted if `delete_orphans` is set, as the storage may be shared
+    /// with other clusters.
+    pub async fn collect_garbage(
+        jobs: &HashMap<String, JobCheckpoints>,
+        dry_run: bool,
+        delete_orphans: bool,
+    ) -> Result<CheckpointGcResult, StateError> {
+        let storage_client = get_storage_provider().await?;
+        let min_age = *config().pipeline.checkpoint_retention.gc_min_age;
+        let cutoff = SystemTime::now() - min_age;
+
+        // group the checkpoint files in storage by job and epoch
+        let mut by_job: HashMap<String, Vec<(u32, ObjectMeta)>> = HashMap::new();
+        for object in storage_client.list_objects("").await? {
+            let Some((job_id, epoch)) = checkpoint_file_epoch(&object.location) else {
+                continue;
+            };
+
+            by_job.entry(job_id).or_default().push((epoch, object));
+        }
+
+        let mut result = CheckpointGcResult {
+            dry_run,
+            ..Default::default()
+        };
+
+        for (job_id, objects) in by_job {
+            let retained = match jobs.get(&job_id) {
+                None if !delete_orphans => {
+                    debug!(
+                        message = "skipping checkpoint files of unknown job",
+                        job_id,
+                        files = objects.len()
+                    );
+                    continue;
+                }
+                // the job doesn't exist, so none of its files are needed
+                None => Retained::default(),
+                Some(job) => match Self::retained_files(&job_id, job).await? {
+                    Some(retained) => retained,
+                    // nothing has completed yet, so we can't tell what's in use
+                    None => continue,
+                },
+            };
+
+            for (epoch, object) in objects {
+                if !retained.is_garbage(epoch, &object, cutoff) {
+                    continue;
+                }
+
+                let path = object.location.to_string();
+                if !dry_run {
+                    storage_client.delete_if_present(path.as_str()).await?;
+                }
+                result.bytes += object.size;
+                result.files.push(path);
+            }
+        }
+
+        result.files.sort();
+
+        info!(
+            message = "Finished checkpoint garbage collection",
+            dry_run,
+            files = result.files.len(),
+            bytes = result.bytes
+        );
+
+        Ok(result)
+    }
+
+    /// Finds the files used by the checkpoints a job retains, or returns None if we can't tell
+    /// which those are
+    async fn retained_files(
+        job_id: &str,
+        job: &JobCheckpoints,
+    ) -> Result<Option<Retained>, StateError> {
+        let Some(epoch) = job.epoch else {
+            return Ok((!job.running).then(Retained::default));
+        };
+
+        let storage_client = get_storage_provider().await?;
+        let mut keep = HashSet::new();
+        for e in job.min_epoch.min(epoch)..=epoch {
+            let path = metadata_path(&base_path(job_id, e));
+            let Some(data) = storage_client.get_if_present(path.as_str()).await? else {
+                continue;
+            };
+            keep.insert(path);
+
+            for operator_id in CheckpointMetadata::decode(&data[..])?.operator_ids {
+                if let Some(operator_metadata) =
+                    Self::load_operator_metadata(job_id, &operator_id, e).await?
+                {
+                    keep.insert(metadata_path(&operator_path(job_id, e, &operator_id)));
+                    keep.extend(Self::referenced_files(&operator_metadata)?);
+                }
+            }
+        }
+
+        Ok(Some(Retained {
+            keep,
+            // running jobs may be writing newer checkpoints
+            keep_after: job.running.then_some(epoch),
+        }))
+    }
+}
+
+/// Parses the job id and epoch out of the path of a file in a checkpoint directory, like
+/// `{job_id}/checkpoints/checkpoint-{epoch}/...`
+fn checkpoint_file_epoch(path: &object_store::path::Path) -> Option<(String, u32)> {
+    let mut parts = path.parts();
+    let job_id = parts.next()?;
+    if parts.next()?.as_ref() != "checkpoints" {
+        return None;
+    }
+    let epoch = parts
+        .next()?
+        .as_ref()
+        .strip_prefix("checkpoint-")?
+        .parse()
+        .ok()?;
+
+    Some((job_id.as_ref().to_string(), epoch))
+}
+
+/// The checkpoint files of a job that garbage collection must not delete
+#[derive(Debug, Default)]
+struct Retained {
+    /// files referenced by the checkpoints the job retains
+    keep: HashSet<String>,
+    /// epochs after this may still be being written
+    keep_after: Option<u32>,
+}
+
+impl Retained {
+    /// Files newer than `cutoff` are never garbage, as they may belong to a checkpoint that's
+    /// still in progress
+    fn is_garbage(&self, epoch: u32, object: &ObjectMeta, cutoff: SystemTime) -> bool {
+        !self.keep.contains(object.location.as_ref())
+            && !self.keep_after.is_some_and(|e| epoch > e)
+            && SystemTime::from(object.last_modified) <= cutoff
+    }
+}
+
+/// What the database knows about a job's checkpoints, used by garbage collection
+#[derive(Debug, Clone)]
+pub struct JobCheckpoints {
+    /// The latest successful checkpoint
+    pub epoch: Option<u32>,
+    /// The oldest epoch the latest successful checkpoint depends on
+    pub min_epoch: u32,
+    /// Whether the job may still be writing checkpoints
+    pub running: bool,
 }
 
 #[derive(Debug)]
@@ -326,3 +514,103 @@ impl ParquetStats {
         self.max_routing_key = self.max_routing_key.max(other.max_routing_key);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use object_store::path::Path;
+    use std::time::Duration;
+
+    fn object(path: &str, age: Duration) -> ObjectMeta {
+        ObjectMeta {
+            location: Path::from(path),
+            last_modified: (SystemTime::now() - age).into(),
+            size: 10,
+            e_tag: None,
+            version: None,
+        }
+    }
+
+    #[test]
+    fn test_checkpoint_file_epoch() {
+        assert_eq!(
+            checkpoint_file_epoch(&Path::from(
+                "job_1/checkpoints/checkpoint-0000007/operator-1/table-a-000"
+            )),
+            Some(("job_1".to_string(), 7))
+        );
+        assert_eq!(
+            checkpoint_file_epoch(&Path::from("job_1/checkpoints/checkpoint-12/metadata")),
+            Some(("job_1".to_string(), 12))
+        );
+
+        assert_eq!(
+            checkpoint_file_epoch(&Path::from("job_1/artifacts/checkpoint-1")),
+            None
+        );
+        assert_eq!(
+            checkpoint_file_epoch(&Path::from("job_1/checkpoints/compacted-1")),
+            None
+        );
+        assert_eq!(checkpoint_file_epoch(&Path::from("job_1")), None);
+    }
+
+    #[test]
+    fn test_retained_files_are_not_garbage() {
+        let cutoff = SystemTime::now() - Duration::from_secs(60 * 60);
+        let old = Duration::from_secs(2 * 60 * 60);
+
+        let retained = Retained {
+            keep: HashSet::from(["job_1/checkpoints/checkpoint-5/metadata".to_string()]),
+            keep_after: None,
+        };
+
+        assert!(!retained.is_garbage(
+            5,
+            &object("job_1/checkpoints/checkpoint-5/metadata", old),
+            cutoff
+        ));
+        assert!(retained.is_garbage(
+            5,
+            &object("job_1/checkpoints/checkpoint-5/operator-1/table-a-000", old),
+            cutoff
+        ));
+        assert!(retained.is_garbage(
+            6,
+            &object("job_1/checkpoints/checkpoint-6/metadata", old),
+            cutoff
+        ));
+
+        // files that may belong to an in-progress checkpoint are kept
+        assert!(!retained.is_garbage(
+            6,
+            &object(
+                "job_1/checkpoints/checkpoint-6/metadata",
+                Duration::from_secs(60)
+            ),
+            cutoff
+        ));
+    }
+
+    #[test]
+    fn test_running_jobs_keep_newer_epochs() {
+        let cutoff = SystemTime::now() - Duration::from_secs(60 * 60);
+        let old = Duration::from_secs(2 * 60 * 60);
+
+        let retained = Retained {
+            keep: HashSet::new(),
+            keep_after: Some(5),
+        };
+
+        assert!(retained.is_garbage(
+            5,
+            &object("job_1/checkpoints/checkpoint-5/metadata", old),
+            cutoff
+        ));
+        assert!(!retained.is_garbage(
+            6,
+            &object("job_1/checkpoints/checkpoint-6/metadata", old),
+            cutoff
+        ));
+    }
+}
@@ -469,6 +469,22 @@ impl<K: Key, V: Data> GlobalKeyedView<K, V> {
         self.data.insert(key, value);
     }
 
+    /// Like [`GlobalKeyedView::insert`], but writes to the checkpoint opened by
+    /// [`TableManager::start_checkpoint`](crate::tables::table_manager::TableManager::start_checkpoint)
+    pub async fn insert_into_checkpoint(&mut self, key: K, value: V) {
+        self.state_tx
+            .send(StateMessage::CheckpointData {
+                table: self.table_name.clone(),
+                data: TableData::KeyedData {
+                    key: bincode::encode_to_vec(&key, config::standard()).unwrap(),
+                    value: bincode::encode_to_vec(&value, config::standard()).unwrap(),
+                },
+            })
+            .await
+            .unwrap();
+        self.data.insert(key, value);
+    }
+
     pub fn get_all(&self) -> &HashMap<K, V> {
         &self.data
     }
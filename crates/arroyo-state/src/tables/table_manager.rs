@@ -1,6 +1,10 @@
 use std::any::Any;
 
-use std::{collections::HashMap, sync::Arc, time::SystemTime};
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Arc,
+    time::SystemTime,
+};
 
 use anyhow::{Result, anyhow, bail};
 use arroyo_rpc::CompactionResult;
@@ -61,6 +65,8 @@ pub struct BackendFlusher {
     table_checkpointers: HashMap<String, Box<dyn ErasedCheckpointer>>,
     current_epoch: u32,
     last_epoch_checkpoints: HashMap<String, TableSubtaskCheckpointMetadata>,
+    // messages for the next epoch that arrived while a started checkpoint was still open
+    deferred: VecDeque<StateMessage>,
 }
 
 impl BackendFlusher {
@@ -103,28 +109,57 @@ impl BackendFlusher {
         }
         self.last_epoch_checkpoints.clear();
         let mut compacted_tables = None;
+        let mut started_checkpoint = None;
 
         // accumulate writes in the RecordBatchBuilders until we get a checkpoint
         while checkpoint_epoch.is_none() {
-            tokio::select! {
-                op = self.queue.recv() => {
-                    match op {
-                        Some(StateMessage::Checkpoint(checkpoint)) => {
-                            checkpoint_epoch = Some(checkpoint);
-                        }
-                        Some(StateMessage::Compaction(compacted_tables_message)) => {
-                            compacted_tables = Some(compacted_tables_message);
-                        }
-                        Some(StateMessage::TableData { table, data }) => {
-                            self.table_checkpointers
-                                .get_mut(&table).expect("checkpointer should be there")
-                                .insert_data(data).await?
-                        },
-                        None => {
-                            debug!("Parquet flusher closed");
-                            return Ok(false);
-                        }
-                    }
+            // messages deferred by the last checkpoint come first, unless we're deferring again
+            let op = match self.deferred.pop_front() {
+                Some(op) if started_checkpoint.is_none() => Some(op),
+                Some(op) => {
+                    self.deferred.push_front(op);
+                    self.queue.recv().await
+                }
+                None => self.queue.recv().await,
+            };
+
+            match op {
+                Some(StateMessage::Checkpoint(checkpoint)) => {
+                    checkpoint_epoch = Some(checkpoint);
+                }
+                Some(StateMessage::StartCheckpoint(checkpoint)) => {
+                    started_checkpoint = Some(checkpoint);
+                }
+                Some(StateMessage::FinishCheckpoint) => {
+                    checkpoint_epoch = Some(
+                        started_checkpoint
+                            .take()
+                            .ok_or_else(|| anyhow!("finished a checkpoint that wasn't started"))?,
+                    );
+                }
+                Some(StateMessage::CheckpointData { table, data }) => {
+                    self.table_checkpointers
+                        .get_mut(&table)
+                        .expect("checkpointer should be there")
+                        .insert_data(data)
+                        .await?
+                }
+                Some(op) if started_checkpoint.is_some() => {
+                    self.deferred.push_back(op);
+                }
+                Some(StateMessage::Compaction(compacted_tables_message)) => {
+                    compacted_tables = Some(compacted_tables_message);
+                }
+                Some(StateMessage::TableData { table, data }) => {
+                    self.table_checkpointers
+                        .get_mut(&table)
+                        .expect("checkpointer should be there")
+                        .insert_data(data)
+                        .await?
+                }
+                None => {
+                    debug!("Parquet flusher closed");
+                    return Ok(false);
                 }
             }
         }
@@ -222,6 +257,7 @@ impl BackendWriter {
             current_epoch,
             table_checkpointers: HashMap::new(),
             last_epoch_checkpoints,
+            deferred: VecDeque::new(),
         })
         .start();
 
@@ -367,6 +403,41 @@ impl TableManager {
         }
     }
 
+    /// Starts a checkpoint of everything written so far, which stays open until
+    /// [`TableManager::finish_checkpoint`]. Meanwhile, writes go to the next checkpoint, except for
+    /// those made with [`GlobalKeyedView::insert_into_checkpoint`]. Unaligned checkpoints use this
+    /// to store the data that arrives on their inputs after they've snapshotted, but ahead of
+    /// their barrier.
+    pub async fn start_checkpoint(
+        &mut self,
+        barrier: CheckpointBarrier,
+        watermark: Option<SystemTime>,
+    ) {
+        assert!(
+            !barrier.then_stop,
+            "stopping checkpoints must be finished right away"
+        );
+
+        self.writer
+            .sender
+            .send(StateMessage::StartCheckpoint(CheckpointMessage {
+                epoch: barrier.epoch,
+                time: barrier.timestamp,
+                watermark,
+                then_stop: false,
+            }))
+            .await
+            .expect("should be able to send checkpoint");
+    }
+
+    pub async fn finish_checkpoint(&mut self) {
+        self.writer
+            .sender
+            .send(StateMessage::FinishCheckpoint)
+            .await
+            .expect("should be able to send checkpoint");
+    }
+
     pub async fn load_compacted(&mut self, compacted: &CompactionResult) {
         assert_eq!(
             compacted.operator_id, self.task_info.operator_id,
@@ -737,6 +737,31 @@ impl StorageProvider {
         Ok(list)
     }
 
+    /// Lists every object under `prefix`, recursively. Unlike [`StorageProvider::list`], the
+    /// returned locations are relative to this provider, so they can be passed back into
+    /// its other methods.
+    pub async fn list_objects(
+        &self,
+        prefix: impl Into<Path>,
+    ) -> Result<Vec<ObjectMeta>, StorageError> {
+        let prefix = prefix.into();
+        let key_part_count = self
+            .config
+            .key()
+            .map(|key| key.parts().count())
+            .unwrap_or_default();
+
+        let mut objects = vec![];
+        let mut list = self.object_store.list(Some(&self.qualify_path(&prefix)));
+        while let Some(meta) = list.next().await {
+            let mut meta = meta?;
+            meta.location = meta.location.parts().skip(key_part_count).collect();
+            objects.push(meta);
+        }
+
+        Ok(objects)
+    }
+
     pub async fn get(&self, path: impl Into<Path>) -> Result<Bytes, StorageError> {
         let path = path.into();
         let bytes = self
@@ -379,6 +379,7 @@ pub const BATCHES_SENT: &str = "arroyo_worker_batches_sent";
 pub const TX_QUEUE_SIZE: &str = "arroyo_worker_tx_queue_size";
 pub const TX_QUEUE_REM: &str = "arroyo_worker_tx_queue_rem";
 pub const DESERIALIZATION_ERRORS: &str = "arroyo_worker_deserialization_errors";
+pub const LATE_RECORDS_DROPPED: &str = "arroyo_worker_late_records_dropped";
 
 #[derive(Debug, Copy, Clone, Encode, Decode, PartialEq, Eq)]
 pub struct CheckpointBarrier {
@@ -386,6 +387,9 @@ pub struct CheckpointBarrier {
     pub min_epoch: u32,
     pub timestamp: SystemTime,
     pub then_stop: bool,
+    /// if set, the barrier may overtake data that is queued ahead of it; that data is stored as
+    /// part of the checkpoint instead of being processed before it
+    pub unaligned: bool,
 }
 
 pub struct DisplayAsSql<'a>(pub &'a DataType);
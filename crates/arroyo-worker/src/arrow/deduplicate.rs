@@ -0,0 +1,345 @@
+use arrow::compute::{
+    concat_batches, filter_record_batch, partition, sort_to_indices, take_record_batch,
+};
+use arrow::row::{OwnedRow, RowConverter, Rows, SortField};
+use arrow_array::cast::AsArray;
+use arrow_array::types::TimestampNanosecondType;
+use arrow_array::{BooleanArray, RecordBatch, UInt32Array};
+use arrow_schema::Schema;
+use arroyo_operator::context::{Collector, OperatorContext};
+use arroyo_operator::operator::{
+    ArrowOperator, AsDisplayable, ConstructedOperator, DisplayableOperator, OperatorConstructor,
+    Registry,
+};
+use arroyo_rpc::df::{ArroyoSchema, ArroyoSchemaRef};
+use arroyo_rpc::errors::DataflowResult;
+use arroyo_rpc::grpc::{api, rpc::TableConfig};
+use arroyo_state::timestamp_table_config;
+use arroyo_types::{CheckpointBarrier, Watermark, from_nanos, to_nanos};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+/// Emits the first row (by event time) of each key, dropping the rest.
+///
+/// Rows are buffered until the watermark passes them, at which point no earlier row can arrive
+/// for their key. Keys are remembered for the TTL after the time of their first row; a row for a
+/// key that has already been emitted is dropped as soon as it arrives.
+pub struct Deduplicate {
+    input_schema: ArroyoSchemaRef,
+    seen_schema: ArroyoSchemaRef,
+    ttl: Duration,
+    key_converter: RowConverter,
+    // the time of the first row of each key that's been emitted
+    seen: HashMap<OwnedRow, i64>,
+}
+
+impl Deduplicate {
+    fn key_rows(&self, batch: &RecordBatch) -> anyhow::Result<Rows> {
+        let columns: Vec<_> = self
+            .input_schema
+            .storage_keys()
+            .expect("deduplication input must be keyed")
+            .iter()
+            .map(|i| batch.column(*i).clone())
+            .collect();
+        Ok(self.key_converter.convert_columns(&columns)?)
+    }
+
+    async fn buffer_batch(
+        &mut self,
+        batch: RecordBatch,
+        ctx: &mut OperatorContext,
+    ) -> anyhow::Result<()> {
+        let timestamp_index = self.input_schema.timestamp_index;
+
+        // drop the rows of keys that have already been emitted with an earlier row
+        let keys = self.key_rows(&batch)?;
+        let timestamps = batch
+            .column(timestamp_index)
+            .as_primitive::<TimestampNanosecondType>();
+        let unseen: BooleanArray = (0..batch.num_rows())
+            .map(|i| {
+                Some(
+                    self.seen
+                        .get(&keys.row(i).owned())
+                        .is_none_or(|first| *first > timestamps.value(i)),
+                )
+            })
+            .collect();
+        let batch = filter_record_batch(&batch, &unseen)?;
+        if batch.num_rows() == 0 {
+            return Ok(());
+        }
+
+        let indices = sort_to_indices(batch.column(timestamp_index), None, None)?;
+        let sorted = take_record_batch(&batch, &indices)?;
+        let timestamps = sorted
+            .column(timestamp_index)
+            .as_primitive::<TimestampNanosecondType>();
+
+        let watermark = ctx.last_present_watermark();
+        let table = ctx
+            .table_manager
+            .get_expiring_time_key_table("input", watermark)
+            .await?;
+
+        for range in partition(&[sorted.column(timestamp_index).clone()])?.ranges() {
+            table.insert(
+                from_nanos(timestamps.value(range.start) as u128),
+                sorted.slice(range.start, range.end - range.start),
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Returns the first row of each key in the batch that hasn't been seen before
+    fn first_rows(&mut self, batch: RecordBatch) -> anyhow::Result<Option<RecordBatch>> {
+        let sorted = self.input_schema.sort(batch, true)?;
+        let keys = self.key_rows(&sorted)?;
+        let timestamps = sorted
+            .column(self.input_schema.timestamp_index)
+            .as_primitive::<TimestampNanosecondType>();
+
+        let mut indices = vec![];
+        for range in self.input_schema.partition(&sorted, false)? {
+            let key = keys.row(range.start).owned();
+            if self.seen.contains_key(&key) {
+                continue;
+            }
+            self.seen.insert(key, timestamps.value(range.start));
+            indices.push(range.start as u32);
+        }
+
+        if indices.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(take_record_batch(
+            &sorted,
+            &UInt32Array::from(indices),
+        )?))
+    }
+
+    /// The time before which keys are forgotten, as of the watermark
+    fn ttl_cutoff(&self, watermark: Option<SystemTime>) -> i64 {
+        watermark
+            .and_then(|w| w.checked_sub(self.ttl))
+            .map(|cutoff| to_nanos(cutoff) as i64)
+            .unwrap_or(i64::MIN)
+    }
+
+    async fn restore_seen(&mut self, ctx: &mut OperatorContext) -> anyhow::Result<()> {
+        let watermark = ctx.last_present_watermark();
+        let table = ctx
+            .table_manager
+            .get_expiring_time_key_table("seen", watermark)
+            .await?;
+
+        // the table keeps each batch of keys for the TTL after its latest row, so keys that had
+        // already expired are dropped here as they would have been before the checkpoint
+        let cutoff = self.ttl_cutoff(watermark);
+        let mut seen = HashMap::new();
+        for (_, batches) in table.all_batches_for_watermark(watermark) {
+            for batch in batches {
+                let keys = self
+                    .key_converter
+                    .convert_columns(&batch.columns()[..batch.num_columns() - 1])?;
+                let timestamps = batch
+                    .columns()
+                    .last()
+                    .unwrap()
+                    .as_primitive::<TimestampNanosecondType>();
+                for i in 0..batch.num_rows() {
+                    if timestamps.value(i) >= cutoff {
+                        seen.insert(keys.row(i).owned(), timestamps.value(i));
+                    }
+                }
+            }
+        }
+        self.seen = seen;
+
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl ArrowOperator for Deduplicate {
+    fn name(&self) -> String {
+        "Deduplicate".to_string()
+    }
+
+    fn display(&self) -> DisplayableOperator<'_> {
+        DisplayableOperator {
+            name: Cow::Borrowed("Deduplicate"),
+            fields: vec![("ttl", AsDisplayable::Debug(&self.ttl))],
+        }
+    }
+
+    async fn on_start(&mut self, ctx: &mut OperatorContext) -> DataflowResult<()> {
+        self.restore_seen(ctx).await?;
+        Ok(())
+    }
+
+    async fn process_batch(
+        &mut self,
+        batch: RecordBatch,
+        ctx: &mut OperatorContext,
+        _: &mut dyn Collector,
+    ) -> DataflowResult<()> {
+        self.buffer_batch(batch, ctx).await?;
+        Ok(())
+    }
+
+    async fn handle_watermark(
+        &mut self,
+        watermark: Watermark,
+        ctx: &mut OperatorContext,
+        collector: &mut dyn Collector,
+    ) -> DataflowResult<Option<Watermark>> {
+        let Some(watermark) = ctx.last_present_watermark() else {
+            return Ok(Some(watermark));
+        };
+
+        let table = ctx
+            .table_manager
+            .get_expiring_time_key_table("input", Some(watermark))
+            .await?;
+
+        let mut ready = vec![];
+        while let Some(time) = table.get_min_time()
+            && time < watermark
+        {
+            ready.extend(table.expire_timestamp(time));
+        }
+
+        if !ready.is_empty()
+            && let Some(first_rows) =
+                self.first_rows(concat_batches(&self.input_schema.schema, &ready)?)?
+        {
+            // remember the newly-seen keys, as of the time of their first row
+            let mut seen_columns: Vec<_> = self
+                .input_schema
+                .storage_keys()
+                .unwrap()
+                .iter()
+                .map(|i| first_rows.column(*i).clone())
+                .collect();
+            seen_columns.push(first_rows.column(self.input_schema.timestamp_index).clone());
+            let seen_batch = RecordBatch::try_new(self.seen_schema.schema.clone(), seen_columns)?;
+            let max_time = seen_batch
+                .columns()
+                .last()
+                .unwrap()
+                .as_primitive::<TimestampNanosecondType>()
+                .iter()
+                .flatten()
+                .max()
+                .unwrap();
+            ctx.table_manager
+                .get_expiring_time_key_table("seen", Some(watermark))
+                .await?
+                .insert(from_nanos(max_time as u128), seen_batch);
+
+            collector
+                .collect(self.input_schema.unkeyed_batch(&first_rows)?)
+                .await?;
+        }
+
+        let cutoff = self.ttl_cutoff(Some(watermark));
+        self.seen.retain(|_, first| *first >= cutoff);
+
+        Ok(Some(Watermark::EventTime(watermark)))
+    }
+
+    async fn handle_checkpoint(
+        &mut self,
+        _: CheckpointBarrier,
+        ctx: &mut OperatorContext,
+        _: &mut dyn Collector,
+    ) -> DataflowResult<()> {
+        let watermark = ctx.last_present_watermark();
+        ctx.table_manager
+            .get_expiring_time_key_table("input", watermark)
+            .await?
+            .flush(watermark)
+            .await?;
+        ctx.table_manager
+            .get_expiring_time_key_table("seen", watermark)
+            .await?
+            .flush(watermark)
+            .await?;
+        Ok(())
+    }
+
+    fn tables(&self) -> HashMap<String, TableConfig> {
+        let mut tables = HashMap::new();
+        tables.insert(
+            "input".to_string(),
+            timestamp_table_config(
+                "input",
+                "buffered deduplication input",
+                Duration::ZERO,
+                false,
+                self.input_schema.as_ref().clone(),
+            ),
+        );
+        tables.insert(
+            "seen".to_string(),
+            timestamp_table_config(
+                "seen",
+                "keys that have been emitted",
+                self.ttl,
+                false,
+                self.seen_schema.as_ref().clone(),
+            ),
+        );
+        tables
+    }
+}
+
+pub struct DeduplicateConstructor;
+impl OperatorConstructor for DeduplicateConstructor {
+    type ConfigT = api::DeduplicateOperator;
+    fn with_config(
+        &self,
+        config: Self::ConfigT,
+        _: Arc<Registry>,
+    ) -> anyhow::Result<ConstructedOperator> {
+        let input_schema: ArroyoSchemaRef = Arc::new(config.input_schema.unwrap().try_into()?);
+        let keys = input_schema
+            .storage_keys()
+            .expect("deduplication input must be keyed")
+            .clone();
+
+        let mut seen_fields: Vec<_> = keys
+            .iter()
+            .map(|i| input_schema.schema.field(*i).clone())
+            .collect();
+        seen_fields.push(
+            input_schema
+                .schema
+                .field(input_schema.timestamp_index)
+                .clone(),
+        );
+        let seen_schema = Arc::new(ArroyoSchema::new_keyed(
+            Arc::new(Schema::new(seen_fields)),
+            keys.len(),
+            (0..keys.len()).collect(),
+        ));
+
+        Ok(ConstructedOperator::from_operator(Box::new(Deduplicate {
+            key_converter: RowConverter::new(
+                keys.iter()
+                    .map(|i| SortField::new(input_schema.schema.field(*i).data_type().clone()))
+                    .collect(),
+            )?,
+            input_schema,
+            seen_schema,
+            ttl: Duration::from_micros(config.ttl_micros),
+            seen: HashMap::new(),
+        })))
+    }
+}
@@ -0,0 +1,487 @@
+use arrow::compute::kernels::cmp::gt_eq;
+use arrow::compute::{cast, concat_batches, filter_record_batch, max, min};
+use arrow::row::{OwnedRow, RowConverter, Rows, SortField};
+use arrow_array::cast::AsArray;
+use arrow_array::types::TimestampNanosecondType;
+use arrow_array::{RecordBatch, TimestampNanosecondArray};
+use arrow_schema::DataType;
+use arroyo_operator::context::{Collector, OperatorContext};
+use arroyo_operator::operator::{
+    ArrowOperator, AsDisplayable, ConstructedOperator, DisplayableOperator, OperatorConstructor,
+    Registry,
+};
+use arroyo_planner::physical::{ArroyoPhysicalExtensionCodec, DecodingContext};
+use arroyo_rpc::df::{ArroyoSchema, ArroyoSchemaRef};
+use arroyo_rpc::errors::DataflowResult;
+use arroyo_rpc::grpc::{api, rpc::TableConfig};
+use arroyo_state::timestamp_table_config;
+use arroyo_types::{CheckpointBarrier, Watermark, from_nanos, to_nanos};
+use datafusion::execution::context::SessionContext;
+use datafusion::execution::runtime_env::RuntimeEnvBuilder;
+use datafusion::physical_plan::ExecutionPlan;
+use datafusion_proto::{physical_plan::AsExecutionPlan, protobuf::PhysicalPlanNode};
+use futures::StreamExt;
+use prost::Message;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Side {
+    Left,
+    Right,
+}
+
+impl Side {
+    fn table_name(&self) -> &'static str {
+        match self {
+            Side::Left => "left",
+            Side::Right => "right",
+        }
+    }
+}
+
+/// The buffered rows of one side of the join, indexed by key
+#[derive(Default)]
+struct SideState {
+    batches: HashMap<OwnedRow, Vec<RecordBatch>>,
+    // keys by the earliest time at which one of their rows can no longer be matched
+    expirations: BTreeMap<i64, HashSet<OwnedRow>>,
+}
+
+impl SideState {
+    fn insert(&mut self, key: OwnedRow, batch: RecordBatch, expires_at: i64) {
+        self.expirations
+            .entry(expires_at)
+            .or_default()
+            .insert(key.clone());
+        self.batches.entry(key).or_default().push(batch);
+    }
+
+    fn get(&self, key: &OwnedRow) -> &[RecordBatch] {
+        self.batches
+            .get(key)
+            .map(|b| b.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Drops every row whose timestamp plus `retention` is before the watermark
+    fn expire(
+        &mut self,
+        watermark: i64,
+        retention: i64,
+        timestamp_index: usize,
+    ) -> anyhow::Result<()> {
+        let cutoff = TimestampNanosecondArray::new_scalar(watermark.saturating_sub(retention));
+
+        while let Some(entry) = self.expirations.first_entry()
+            && *entry.key() < watermark
+        {
+            for key in entry.remove() {
+                let Some(batches) = self.batches.remove(&key) else {
+                    continue;
+                };
+
+                for batch in batches {
+                    let timestamps = batch
+                        .column(timestamp_index)
+                        .as_primitive::<TimestampNanosecondType>();
+                    let batch = filter_record_batch(&batch, &gt_eq(timestamps, &cutoff)?)?;
+                    if batch.num_rows() > 0 {
+                        let (start, _) = time_range(&batch, timestamp_index);
+                        self.insert(key.clone(), batch, start.saturating_add(retention));
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn time_range(batch: &RecordBatch, timestamp_index: usize) -> (i64, i64) {
+    let timestamps = batch
+        .column(timestamp_index)
+        .as_primitive::<TimestampNanosecondType>();
+    (
+        min(timestamps).expect("batch must not be empty"),
+        max(timestamps).expect("batch must not be empty"),
+    )
+}
+
+/// Joins two streams on a key, where the timestamp of each right row must fall within a fixed
+/// interval around the timestamp of the left row, i.e.
+/// `left._timestamp + lower_bound <= right._timestamp <= left._timestamp + upper_bound`.
+///
+/// Each row is joined against the buffered rows of the other side as it arrives, so the output
+/// is append-only. Rows are kept only as long as a row from the other side that's not yet behind
+/// the watermark could still fall within the bounds: left rows until the watermark passes
+/// `_timestamp + upper_bound`, and right rows until it passes `_timestamp - lower_bound`.
+pub struct IntervalJoin {
+    left_input_schema: ArroyoSchemaRef,
+    right_input_schema: ArroyoSchemaRef,
+    lower_bound_micros: i64,
+    upper_bound_micros: i64,
+    // how long after their timestamps rows from each side are retained, in nanos; these are
+    // negative when the interval doesn't include the timestamp of the row itself
+    left_retention: i64,
+    right_retention: i64,
+    key_types: Vec<DataType>,
+    key_converter: RowConverter,
+    left_state: SideState,
+    right_state: SideState,
+    left_passer: Arc<RwLock<Option<RecordBatch>>>,
+    right_passer: Arc<RwLock<Option<RecordBatch>>>,
+    join_execution_plan: Arc<dyn ExecutionPlan>,
+}
+
+impl IntervalJoin {
+    fn input_schema(&self, side: Side) -> &ArroyoSchemaRef {
+        match side {
+            Side::Left => &self.left_input_schema,
+            Side::Right => &self.right_input_schema,
+        }
+    }
+
+    fn retention(&self, side: Side) -> i64 {
+        match side {
+            Side::Left => self.left_retention,
+            Side::Right => self.right_retention,
+        }
+    }
+
+    fn key_rows(&self, batch: &RecordBatch, schema: &ArroyoSchema) -> anyhow::Result<Rows> {
+        let columns = schema
+            .storage_keys()
+            .expect("interval join inputs must be keyed")
+            .iter()
+            .zip(&self.key_types)
+            .map(|(i, t)| cast(batch.column(*i), t))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(self.key_converter.convert_columns(&columns)?)
+    }
+
+    fn split_by_key(
+        &self,
+        side: Side,
+        batch: RecordBatch,
+    ) -> anyhow::Result<Vec<(OwnedRow, RecordBatch)>> {
+        let schema = self.input_schema(side);
+        let sorted = schema.sort(batch, false)?;
+        let keys = self.key_rows(&sorted, schema)?;
+
+        Ok(schema
+            .partition(&sorted, false)?
+            .into_iter()
+            .map(|range| {
+                (
+                    keys.row(range.start).owned(),
+                    sorted.slice(range.start, range.end - range.start),
+                )
+            })
+            .collect())
+    }
+
+    async fn process_side(
+        &mut self,
+        side: Side,
+        batch: RecordBatch,
+        ctx: &mut OperatorContext,
+        collector: &mut dyn Collector,
+    ) -> DataflowResult<()> {
+        let timestamp_index = self.input_schema(side).timestamp_index;
+        let retention = self.retention(side);
+        let partitions = self.split_by_key(side, batch.clone())?;
+
+        let watermark = ctx.last_present_watermark();
+        let watermark_nanos = watermark.map(|w| to_nanos(w) as i64).unwrap_or(i64::MIN);
+        let table = ctx
+            .table_manager
+            .get_expiring_time_key_table(side.table_name(), watermark)
+            .await?;
+
+        let (state, other_state) = match side {
+            Side::Left => (&mut self.left_state, &self.right_state),
+            Side::Right => (&mut self.right_state, &self.left_state),
+        };
+
+        let mut matches = vec![];
+        for (key, batch) in partitions {
+            matches.extend(other_state.get(&key).iter().cloned());
+
+            // rows that can't match any future row from the other side don't need to be kept
+            let (start, end) = time_range(&batch, timestamp_index);
+            if end.saturating_add(retention) >= watermark_nanos {
+                table.insert(
+                    from_nanos(end.saturating_add(retention).max(0) as u128),
+                    batch.clone(),
+                );
+                state.insert(key, batch, start.saturating_add(retention));
+            }
+        }
+
+        if matches.is_empty() {
+            return Ok(());
+        }
+
+        let batch = self.input_schema(side).unkeyed_batch(&batch)?;
+        match side {
+            Side::Left => {
+                let right = concat_batches(&self.right_input_schema.schema, &matches)?;
+                let right = self.right_input_schema.unkeyed_batch(&right)?;
+                self.compute_pair(batch, right, collector).await
+            }
+            Side::Right => {
+                let left = concat_batches(&self.left_input_schema.schema, &matches)?;
+                let left = self.left_input_schema.unkeyed_batch(&left)?;
+                self.compute_pair(left, batch, collector).await
+            }
+        }
+    }
+
+    async fn compute_pair(
+        &mut self,
+        left: RecordBatch,
+        right: RecordBatch,
+        collector: &mut dyn Collector,
+    ) -> DataflowResult<()> {
+        {
+            self.right_passer.write().unwrap().replace(right);
+            self.left_passer.write().unwrap().replace(left);
+        }
+        self.join_execution_plan.reset().unwrap();
+        let mut records = self
+            .join_execution_plan
+            .execute(0, SessionContext::new().task_ctx())
+            .expect("successfully computed?");
+        while let Some(batch) = records.next().await {
+            collector.collect(batch?).await?;
+        }
+
+        Ok(())
+    }
+
+    fn expire(&mut self, watermark: i64) -> anyhow::Result<()> {
+        self.left_state.expire(
+            watermark,
+            self.left_retention,
+            self.left_input_schema.timestamp_index,
+        )?;
+        self.right_state.expire(
+            watermark,
+            self.right_retention,
+            self.right_input_schema.timestamp_index,
+        )
+    }
+}
+
+#[async_trait::async_trait]
+impl ArrowOperator for IntervalJoin {
+    fn name(&self) -> String {
+        "IntervalJoin".to_string()
+    }
+
+    fn display(&self) -> DisplayableOperator<'_> {
+        DisplayableOperator {
+            name: Cow::Borrowed("IntervalJoin"),
+            fields: vec![
+                (
+                    "lower_bound_micros",
+                    AsDisplayable::Debug(&self.lower_bound_micros),
+                ),
+                (
+                    "upper_bound_micros",
+                    AsDisplayable::Debug(&self.upper_bound_micros),
+                ),
+                (
+                    "join_execution_plan",
+                    self.join_execution_plan.as_ref().into(),
+                ),
+            ],
+        }
+    }
+
+    async fn on_start(&mut self, ctx: &mut OperatorContext) -> DataflowResult<()> {
+        let watermark = ctx.last_present_watermark();
+        for side in [Side::Left, Side::Right] {
+            let batches: Vec<_> = ctx
+                .table_manager
+                .get_expiring_time_key_table(side.table_name(), watermark)
+                .await?
+                .all_batches_for_watermark(watermark)
+                .flat_map(|(_, batches)| batches.clone())
+                .collect();
+
+            let timestamp_index = self.input_schema(side).timestamp_index;
+            let retention = self.retention(side);
+            for batch in batches {
+                for (key, batch) in self.split_by_key(side, batch)? {
+                    let (start, _) = time_range(&batch, timestamp_index);
+                    let state = match side {
+                        Side::Left => &mut self.left_state,
+                        Side::Right => &mut self.right_state,
+                    };
+                    state.insert(key, batch, start.saturating_add(retention));
+                }
+            }
+        }
+
+        if let Some(watermark) = watermark {
+            self.expire(to_nanos(watermark) as i64)?;
+        }
+
+        Ok(())
+    }
+
+    async fn process_batch(
+        &mut self,
+        _: RecordBatch,
+        _: &mut OperatorContext,
+        _: &mut dyn Collector,
+    ) -> DataflowResult<()> {
+        unreachable!();
+    }
+
+    async fn process_batch_index(
+        &mut self,
+        index: usize,
+        total_inputs: usize,
+        record_batch: RecordBatch,
+        ctx: &mut OperatorContext,
+        collector: &mut dyn Collector,
+    ) -> DataflowResult<()> {
+        let side = match index / (total_inputs / 2) {
+            0 => Side::Left,
+            1 => Side::Right,
+            _ => unreachable!(),
+        };
+        self.process_side(side, record_batch, ctx, collector).await
+    }
+
+    async fn handle_watermark(
+        &mut self,
+        watermark: Watermark,
+        ctx: &mut OperatorContext,
+        _: &mut dyn Collector,
+    ) -> DataflowResult<Option<Watermark>> {
+        let Some(watermark) = ctx.last_present_watermark() else {
+            return Ok(Some(watermark));
+        };
+
+        self.expire(to_nanos(watermark) as i64)?;
+
+        // every output row has the later timestamp of its pair, so it can't be behind the
+        // watermark unless one of its inputs was
+        Ok(Some(Watermark::EventTime(watermark)))
+    }
+
+    async fn handle_checkpoint(
+        &mut self,
+        _: CheckpointBarrier,
+        ctx: &mut OperatorContext,
+        _: &mut dyn Collector,
+    ) -> DataflowResult<()> {
+        let watermark = ctx.last_present_watermark();
+        for side in [Side::Left, Side::Right] {
+            ctx.table_manager
+                .get_expiring_time_key_table(side.table_name(), watermark)
+                .await?
+                .flush(watermark)
+                .await?;
+        }
+        Ok(())
+    }
+
+    fn tables(&self) -> HashMap<String, TableConfig> {
+        // rows are stored by the time they can be dropped, rather than by their own timestamps
+        let mut tables = HashMap::new();
+        tables.insert(
+            "left".to_string(),
+            timestamp_table_config(
+                "left",
+                "left join data",
+                Duration::ZERO,
+                false,
+                self.left_input_schema.as_ref().clone(),
+            ),
+        );
+        tables.insert(
+            "right".to_string(),
+            timestamp_table_config(
+                "right",
+                "right join data",
+                Duration::ZERO,
+                false,
+                self.right_input_schema.as_ref().clone(),
+            ),
+        );
+        tables
+    }
+}
+
+pub struct IntervalJoinConstructor;
+impl OperatorConstructor for IntervalJoinConstructor {
+    type ConfigT = api::JoinOperator;
+    fn with_config(
+        &self,
+        config: Self::ConfigT,
+        registry: Arc<Registry>,
+    ) -> anyhow::Result<ConstructedOperator> {
+        let left_passer = Arc::new(RwLock::new(None));
+        let right_passer = Arc::new(RwLock::new(None));
+
+        let codec = ArroyoPhysicalExtensionCodec {
+            context: DecodingContext::LockedJoinPair {
+                left: left_passer.clone(),
+                right: right_passer.clone(),
+            },
+        };
+        let join_physical_plan_node = PhysicalPlanNode::decode(&mut config.join_plan.as_slice())?;
+        let join_execution_plan = join_physical_plan_node.try_into_physical_plan(
+            registry.as_ref(),
+            &RuntimeEnvBuilder::new().build()?,
+            &codec,
+        )?;
+
+        let left_input_schema: ArroyoSchemaRef = Arc::new(config.left_schema.unwrap().try_into()?);
+        let right_input_schema: ArroyoSchemaRef =
+            Arc::new(config.right_schema.unwrap().try_into()?);
+
+        let lower_bound = config
+            .lower_bound_micros
+            .expect("lower bound must be set for interval join");
+        let upper_bound = config
+            .upper_bound_micros
+            .expect("upper bound must be set for interval join");
+
+        let key_types: Vec<_> = left_input_schema
+            .storage_keys()
+            .expect("left side of interval join must be keyed")
+            .iter()
+            .map(|i| left_input_schema.schema.field(*i).data_type().clone())
+            .collect();
+
+        Ok(ConstructedOperator::from_operator(Box::new(IntervalJoin {
+            key_converter: RowConverter::new(
+                key_types
+                    .iter()
+                    .map(|t| SortField::new(t.clone()))
+                    .collect(),
+            )?,
+            key_types,
+            left_input_schema,
+            right_input_schema,
+            lower_bound_micros: lower_bound,
+            upper_bound_micros: upper_bound,
+            left_retention: upper_bound.saturating_mul(1000),
+            right_retention: lower_bound.saturating_mul(-1000),
+            left_state: SideState::default(),
+            right_state: SideState::default(),
+            left_passer,
+            right_passer,
+            join_execution_plan,
+        })))
+    }
+}
@@ -0,0 +1,440 @@
+use anyhow::anyhow;
+use arrow::compute::{filter_record_batch, kernels::boolean::not, kernels::cmp::lt};
+use arrow_array::{
+    Array, ArrayRef, BooleanArray, RecordBatch, StructArray, TimestampNanosecondArray,
+};
+use arrow_schema::{ArrowError, Schema};
+use arroyo_metrics::TaskCounters;
+use arroyo_operator::context::{Collector, OperatorContext};
+use arroyo_operator::operator::{
+    ArrowOperator, AsDisplayable, ConstructedOperator, DisplayableOperator, OperatorConstructor,
+    Registry,
+};
+use arroyo_planner::MultiHashFunction;
+use arroyo_rpc::df::ArroyoSchema;
+use arroyo_rpc::errors::DataflowResult;
+use arroyo_rpc::grpc::api::LateRecordsOperator;
+use arroyo_rpc::{UPDATING_META_FIELD, updating_meta_field, updating_meta_fields};
+use arroyo_types::{from_nanos, to_nanos};
+use async_trait::async_trait;
+use datafusion::logical_expr::ColumnarValue;
+use std::borrow::Cow;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+/// Holds a watermark back by the allowed lateness. Windows emit on the watermark itself, but keep
+/// the state of the windows they've closed until this watermark passes them, so that late records
+/// can still update their results.
+pub(crate) fn lateness_watermark(watermark: SystemTime, allowed_lateness: Duration) -> SystemTime {
+    watermark
+        .checked_sub(allowed_lateness)
+        .unwrap_or(SystemTime::UNIX_EPOCH)
+}
+
+/// The time before which records are late for a window with bins of `bin_width`: the bin they
+/// fall into has passed the lateness watermark, and its state is gone. Session (and instant)
+/// windows have no fixed bins and pass zero, so their records are late as soon as they're behind
+/// that watermark.
+pub(crate) fn late_cutoff(
+    watermark: SystemTime,
+    allowed_lateness: Duration,
+    bin_width: Duration,
+) -> SystemTime {
+    let watermark = lateness_watermark(watermark, allowed_lateness);
+    if bin_width.is_zero() {
+        return watermark;
+    }
+
+    let nanos = to_nanos(watermark);
+    from_nanos(nanos - nanos % bin_width.as_nanos())
+}
+
+/// Which of `timestamps` are late for a window with bins of `bin_width` (see [`late_cutoff`]).
+/// Both the windows and `late_records(...)` decide lateness with this.
+pub(crate) fn late_rows(
+    timestamps: &TimestampNanosecondArray,
+    watermark: SystemTime,
+    allowed_lateness: Duration,
+    bin_width: Duration,
+) -> Result<BooleanArray, ArrowError> {
+    let cutoff = TimestampNanosecondArray::new_scalar(to_nanos(late_cutoff(
+        watermark,
+        allowed_lateness,
+        bin_width,
+    )) as i64);
+    lt(timestamps, &cutoff)
+}
+
+/// Drops the rows of `batch` that are late for a window with bins of `bin_width`, counting them
+/// in the late records metric
+pub(crate) fn drop_late_rows(
+    ctx: &OperatorContext,
+    batch: RecordBatch,
+    timestamp_index: usize,
+    allowed_lateness: Duration,
+    bin_width: Duration,
+) -> DataflowResult<RecordBatch> {
+    // nothing is late until we've seen a watermark
+    let Some(watermark) = ctx.last_present_watermark() else {
+        return Ok(batch);
+    };
+
+    let late = late_rows(
+        timestamp_column(&batch, timestamp_index)?,
+        watermark,
+        allowed_lateness,
+        bin_width,
+    )?;
+    let dropped = late.true_count();
+    if dropped == 0 {
+        return Ok(batch);
+    }
+
+    TaskCounters::LateRecordsDropped.for_task(&ctx.chain_info, |c| c.inc_by(dropped as u64));
+    Ok(filter_record_batch(&batch, &not(&late)?)?)
+}
+
+fn timestamp_column(
+    batch: &RecordBatch,
+    timestamp_index: usize,
+) -> DataflowResult<&TimestampNanosecondArray> {
+    Ok(batch
+        .column(timestamp_index)
+        .as_any()
+        .downcast_ref::<TimestampNanosecondArray>()
+        .ok_or_else(|| anyhow!("expected timestamp column"))?)
+}
+
+/// Ids for the results of a window, from their group-by keys and a bound (the start or end) that
+/// identifies the window each belongs to. A window updated by late records retracts its previous
+/// results by these ids.
+pub(crate) fn window_result_ids(keys: &[ArrayRef], windows: ArrayRef) -> DataflowResult<ArrayRef> {
+    let num_rows = windows.len();
+    let mut columns: Vec<_> = keys.iter().cloned().map(ColumnarValue::Array).collect();
+    columns.push(ColumnarValue::Array(windows));
+    Ok(MultiHashFunction::default()
+        .invoke(&columns)?
+        .into_array(num_rows)?)
+}
+
+/// The `_updating_meta` column marking window results with the given ids as created or retracted
+pub(crate) fn updating_meta(ids: ArrayRef, is_retract: bool) -> ArrayRef {
+    Arc::new(StructArray::new(
+        updating_meta_fields(),
+        vec![
+            Arc::new(BooleanArray::from(vec![is_retract; ids.len()])),
+            ids,
+        ],
+        None,
+    ))
+}
+
+/// Appends the `_updating_meta` column to the results of a window, which is updating when it
+/// allows late records
+pub(crate) fn with_updating_meta(
+    batch: &RecordBatch,
+    ids: ArrayRef,
+) -> DataflowResult<RecordBatch> {
+    let schema = batch.schema();
+    let mut fields = schema.fields().to_vec();
+    fields.push(updating_meta_field());
+    let mut columns = batch.columns().to_vec();
+    columns.push(updating_meta(ids, false));
+
+    Ok(RecordBatch::try_new(
+        Arc::new(Schema::new_with_metadata(fields, schema.metadata().clone())),
+        columns,
+    )?)
+}
+
+/// Turns previously emitted window results into their retractions
+pub(crate) fn retract(batch: &RecordBatch) -> DataflowResult<RecordBatch> {
+    let index = batch.schema().index_of(UPDATING_META_FIELD)?;
+    let meta = batch
+        .column(index)
+        .as_any()
+        .downcast_ref::<StructArray>()
+        .ok_or_else(|| anyhow!("expected {UPDATING_META_FIELD} to be a struct"))?;
+
+    let mut columns = batch.columns().to_vec();
+    columns[index] = updating_meta(meta.column(1).clone(), true);
+    Ok(RecordBatch::try_new(batch.schema(), columns)?)
+}
+
+/// Emits the records of a source that a window with bins of `bin_width` drops as late. Backs the
+/// `late_records(...)` table function.
+pub struct LateRecordsFunc {
+    input_schema: ArroyoSchema,
+    allowed_lateness: Duration,
+    bin_width: Duration,
+}
+
+pub struct LateRecordsConstructor;
+
+impl OperatorConstructor for LateRecordsConstructor {
+    type ConfigT = LateRecordsOperator;
+    fn with_config(
+        &self,
+        config: Self::ConfigT,
+        _: Arc<Registry>,
+    ) -> anyhow::Result<ConstructedOperator> {
+        let input_schema = config
+            .input_schema
+            .ok_or_else(|| anyhow!("requires input schema"))?
+            .try_into()?;
+
+        Ok(ConstructedOperator::from_operator(Box::new(
+            LateRecordsFunc {
+                input_schema,
+                allowed_lateness: Duration::from_micros(config.allowed_lateness_micros),
+                bin_width: Duration::from_micros(config.bin_width_micros),
+            },
+        )))
+    }
+}
+
+#[async_trait]
+impl ArrowOperator for LateRecordsFunc {
+    fn name(&self) -> String {
+        "late_records".to_string()
+    }
+
+    fn display(&self) -> DisplayableOperator<'_> {
+        DisplayableOperator {
+            name: Cow::Borrowed("LateRecordsFunc"),
+            fields: vec![
+                (
+                    "allowed_lateness",
+                    AsDisplayable::Debug(&self.allowed_lateness),
+                ),
+                ("bin_width", AsDisplayable::Debug(&self.bin_width)),
+            ],
+        }
+    }
+
+    async fn process_batch(
+        &mut self,
+        batch: RecordBatch,
+        ctx: &mut OperatorContext,
+        collector: &mut dyn Collector,
+    ) -> DataflowResult<()> {
+        // nothing is late until we've seen a watermark
+        let Some(watermark) = ctx.last_present_watermark() else {
+            return Ok(());
+        };
+
+        let late_rows = late_rows(
+            timestamp_column(&batch, self.input_schema.timestamp_index)?,
+            watermark,
+            self.allowed_lateness,
+            self.bin_width,
+        )?;
+        let late = filter_record_batch(&batch, &late_rows)?;
+
+        if late.num_rows() > 0 {
+            collector.collect(late).await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow_array::Int64Array;
+    use arrow_schema::{DataType, Field, Schema, TimeUnit};
+    use arroyo_rpc::ControlResp;
+    use arroyo_types::{Watermark, get_test_task_info};
+    use std::collections::HashMap;
+    use tokio::sync::mpsc::channel;
+
+    fn secs(s: u64) -> SystemTime {
+        SystemTime::UNIX_EPOCH + Duration::from_secs(s)
+    }
+
+    #[derive(Default)]
+    struct VecCollector {
+        batches: Vec<RecordBatch>,
+    }
+
+    #[async_trait]
+    impl Collector for VecCollector {
+        async fn collect(&mut self, batch: RecordBatch) -> DataflowResult<()> {
+            self.batches.push(batch);
+            Ok(())
+        }
+
+        async fn broadcast_watermark(&mut self, _: Watermark) -> DataflowResult<()> {
+            unreachable!()
+        }
+    }
+
+    #[test]
+    fn test_late_cutoff() {
+        let lateness = Duration::from_secs(5);
+
+        // tumbling windows of 10s close the bin [90, 100) once the watermark passes 105
+        assert_eq!(
+            late_cutoff(secs(100), lateness, Duration::from_secs(10)),
+            secs(90)
+        );
+        assert_eq!(
+            late_cutoff(secs(105), lateness, Duration::from_secs(10)),
+            secs(100)
+        );
+
+        // sessions drop anything behind the lateness watermark
+        assert_eq!(late_cutoff(secs(100), lateness, Duration::ZERO), secs(95));
+
+        // lateness larger than the watermark holds everything back
+        assert_eq!(
+            late_cutoff(secs(3), lateness, Duration::from_secs(10)),
+            SystemTime::UNIX_EPOCH
+        );
+    }
+
+    async fn collect_late_rows(
+        bin_width: Duration,
+        watermark: SystemTime,
+        timestamps: &[u64],
+    ) -> Vec<i64> {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new(
+                "_timestamp",
+                DataType::Timestamp(TimeUnit::Nanosecond, None),
+                false,
+            ),
+            Field::new("value", DataType::Int64, false),
+        ]));
+        let input_schema = ArroyoSchema::new_unkeyed(schema.clone(), 0);
+
+        let (control_tx, _control_rx) = channel::<ControlResp>(16);
+        let mut ctx = OperatorContext::new(
+            Arc::new(get_test_task_info()),
+            None,
+            control_tx,
+            1,
+            vec![Arc::new(input_schema.clone())],
+            None,
+            HashMap::new(),
+        )
+        .await;
+
+        let mut func = LateRecordsFunc {
+            input_schema,
+            allowed_lateness: Duration::from_secs(5),
+            bin_width,
+        };
+        let mut collector = VecCollector::default();
+
+        let batch = RecordBatch::try_new(
+            schema,
+            vec![
+                Arc::new(TimestampNanosecondArray::from_iter_values(
+                    timestamps.iter().map(|t| to_nanos(secs(*t)) as i64),
+                )),
+                Arc::new(Int64Array::from_iter_values(
+                    timestamps.iter().map(|t| *t as i64),
+                )),
+            ],
+        )
+        .unwrap();
+
+        // nothing is late before the first watermark
+        func.process_batch(batch.clone(), &mut ctx, &mut collector)
+            .await
+            .unwrap();
+        assert!(collector.batches.is_empty());
+
+        ctx.watermarks.set(0, Watermark::EventTime(watermark));
+        func.process_batch(batch, &mut ctx, &mut collector)
+            .await
+            .unwrap();
+
+        collector
+            .batches
+            .iter()
+            .flat_map(|b| {
+                b.column(1)
+                    .as_any()
+                    .downcast_ref::<Int64Array>()
+                    .unwrap()
+                    .values()
+                    .to_vec()
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_late_records_match_window_drops() {
+        let timestamps = [85, 92, 97, 101];
+
+        // a 10s tumbling window still updates its bin [90, 100) with 92, as it keeps the bin's
+        // state until the watermark passes 105, even though 92 is more than the allowed lateness
+        // behind
+        assert_eq!(
+            collect_late_rows(Duration::from_secs(10), secs(100), &timestamps).await,
+            vec![85]
+        );
+
+        // a hop window sliding by 5s has already dropped its bins [85, 90) and [90, 95)
+        assert_eq!(
+            collect_late_rows(Duration::from_secs(5), secs(100), &timestamps).await,
+            vec![85, 92]
+        );
+
+        // sessions drop everything behind the lateness watermark
+        assert_eq!(
+            collect_late_rows(Duration::ZERO, secs(101), &timestamps).await,
+            vec![85, 92]
+        );
+    }
+
+    #[test]
+    fn test_retract_window_results() {
+        let schema = Arc::new(Schema::new(vec![Field::new(
+            "count",
+            DataType::Int64,
+            false,
+        )]));
+        let batch =
+            RecordBatch::try_new(schema, vec![Arc::new(Int64Array::from(vec![1, 2]))]).unwrap();
+        let ids = window_result_ids(
+            &[Arc::new(Int64Array::from(vec![7, 8]))],
+            Arc::new(TimestampNanosecondArray::from(vec![0, 0])),
+        )
+        .unwrap();
+
+        let created = with_updating_meta(&batch, ids).unwrap();
+        let retracted = retract(&created).unwrap();
+
+        let updating_meta = |batch: &RecordBatch| {
+            let meta = batch
+                .column_by_name(UPDATING_META_FIELD)
+                .unwrap()
+                .as_any()
+                .downcast_ref::<StructArray>()
+                .unwrap()
+                .clone();
+            (
+                meta.column(0)
+                    .as_any()
+                    .downcast_ref::<BooleanArray>()
+                    .unwrap()
+                    .iter()
+                    .map(Option::unwrap)
+                    .collect::<Vec<_>>(),
+                meta.column(1).clone(),
+            )
+        };
+        let (created_retracts, created_ids) = updating_meta(&created);
+        let (retracted_retracts, retracted_ids) = updating_meta(&retracted);
+
+        assert_eq!(created_retracts, vec![false, false]);
+        assert_eq!(retracted_retracts, vec![true, true]);
+        // retractions carry the ids of the results they replace, and keys tell results apart
+        assert_eq!(created_ids, retracted_ids);
+        assert_ne!(created_ids.slice(0, 1), created_ids.slice(1, 1));
+        assert_eq!(created.column(0), retracted.column(0));
+    }
+}
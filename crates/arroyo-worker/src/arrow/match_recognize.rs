@@ -0,0 +1,861 @@
+use arrow::compute::{
+    cast, concat_batches, partition, sort_to_indices, sum, take, take_record_batch,
+};
+use arrow::row::{OwnedRow, RowConverter, SortField};
+use arrow_array::cast::AsArray;
+use arrow_array::types::{Float64Type, Int64Type, TimestampNanosecondType, UInt64Type};
+use arrow_array::{
+    Array, ArrayRef, BinaryArray, BooleanArray, RecordBatch, RecordBatchOptions,
+    TimestampNanosecondArray, UInt32Array, UInt64Array,
+};
+use arrow_schema::{DataType, Field, Schema, SchemaRef, TimeUnit};
+use arroyo_operator::context::{Collector, OperatorContext};
+use arroyo_operator::operator::{
+    ArrowOperator, AsDisplayable, ConstructedOperator, DisplayableOperator, OperatorConstructor,
+    Registry,
+};
+use arroyo_rpc::TIMESTAMP_FIELD;
+use arroyo_rpc::df::{ArroyoSchema, ArroyoSchemaRef};
+use arroyo_rpc::errors::DataflowResult;
+use arroyo_rpc::grpc::{api, rpc::TableConfig};
+use arroyo_state::timestamp_table_config;
+use arroyo_types::{CheckpointBarrier, Watermark, from_nanos, to_nanos};
+use bincode::{Decode, Encode, config};
+use datafusion::common::ScalarValue;
+use datafusion::physical_expr::PhysicalExpr;
+use datafusion_proto::physical_plan::DefaultPhysicalExtensionCodec;
+use datafusion_proto::physical_plan::from_proto::parse_physical_expr;
+use datafusion_proto::protobuf::PhysicalExprNode;
+use futures::StreamExt;
+use prost::Message;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+
+struct NfaState {
+    // (symbol, target state)
+    transitions: Vec<(usize, usize)>,
+    accepting: bool,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum MeasureAggregate {
+    First,
+    Last,
+    Count,
+    Sum,
+    Min,
+    Max,
+    Avg,
+}
+
+struct MeasureInput {
+    symbol: Option<u32>,
+    column: Option<usize>,
+    aggregate: MeasureAggregate,
+    data_type: DataType,
+}
+
+/// A path through the NFA, with the symbol that each of its rows was mapped to
+#[derive(Debug, Clone, Encode, Decode)]
+struct Path {
+    state: u32,
+    symbols: Vec<u32>,
+}
+
+/// An attempt to match the pattern starting from a particular row. The attempt is finished
+/// once none of its paths can be extended any further.
+#[derive(Debug, Clone, Encode, Decode)]
+struct Attempt {
+    start: u64,
+    start_time: i64,
+    paths: Vec<Path>,
+    // the symbols of the longest match found so far
+    best: Option<Vec<u32>>,
+}
+
+impl Attempt {
+    fn finished(&self) -> bool {
+        self.paths.is_empty()
+    }
+}
+
+/// The state of a single partition: the rows that may still be part of a match, and the
+/// in-progress attempts, ordered by their first row
+#[derive(Debug, Default, Encode, Decode)]
+struct PartitionState {
+    // the sequence number of the first buffered row
+    first_seq: u64,
+    // the encoded rows, starting from the first row of the earliest attempt
+    rows: Vec<Vec<u8>>,
+    attempts: Vec<Attempt>,
+    last_time: i64,
+}
+
+impl PartitionState {
+    fn is_empty(&self) -> bool {
+        self.attempts.is_empty()
+    }
+}
+
+struct Match {
+    rows: Vec<Vec<u8>>,
+    symbols: Vec<u32>,
+}
+
+/// Row pattern recognition (`MATCH_RECOGNIZE`) over a keyed input.
+///
+/// Rows are buffered until the watermark passes them, so that each partition is processed in
+/// event-time order. Every row starts a new attempt to match the pattern, which is advanced
+/// through the NFA by each subsequent row; an attempt produces its longest match once it can't
+/// be extended (because no transition matches, the WITHIN interval has passed, or the TTL has
+/// expired). Matches are emitted in order of their first row, and with AFTER MATCH SKIP PAST
+/// LAST ROW, attempts that overlap an emitted match are discarded.
+pub struct MatchRecognize {
+    input_schema: ArroyoSchemaRef,
+    state_schema: ArroyoSchemaRef,
+    symbols: Vec<String>,
+    conditions: Vec<Option<Arc<dyn PhysicalExpr>>>,
+    states: Vec<NfaState>,
+    measure_inputs: Vec<MeasureInput>,
+    measure_input_schema: SchemaRef,
+    measures: Vec<Arc<dyn PhysicalExpr>>,
+    within: Option<Duration>,
+    skip_to_next_row: bool,
+    ttl: Duration,
+    key_converter: RowConverter,
+    row_converter: RowConverter,
+    partitions: HashMap<OwnedRow, PartitionState>,
+    updated_keys: HashSet<OwnedRow>,
+    generation: u64,
+}
+
+impl MatchRecognize {
+    fn key_columns(&self, batch: &RecordBatch) -> Vec<ArrayRef> {
+        self.input_schema
+            .storage_keys()
+            .expect("match_recognize input must be keyed")
+            .iter()
+            .map(|i| batch.column(*i).clone())
+            .collect()
+    }
+
+    async fn buffer_batch(
+        &mut self,
+        batch: RecordBatch,
+        ctx: &mut OperatorContext,
+    ) -> anyhow::Result<()> {
+        let timestamp_index = self.input_schema.timestamp_index;
+
+        let indices = sort_to_indices(batch.column(timestamp_index), None, None)?;
+        let sorted = take_record_batch(&batch, &indices)?;
+        let watermark = ctx.last_present_watermark();
+
+        // rows behind the watermark would come after rows that have already been run through
+        // the pattern, so they can't be matched in order and are dropped as late
+        let first_on_time = match watermark {
+            Some(watermark) => sorted
+                .column(timestamp_index)
+                .as_primitive::<TimestampNanosecondType>()
+                .values()
+                .partition_point(|t| *t < to_nanos(watermark) as i64),
+            None => 0,
+        };
+        let sorted = sorted.slice(first_on_time, sorted.num_rows() - first_on_time);
+        let timestamps = sorted
+            .column(timestamp_index)
+            .as_primitive::<TimestampNanosecondType>();
+
+        let table = ctx
+            .table_manager
+            .get_expiring_time_key_table("input", watermark)
+            .await?;
+
+        for range in partition(&[sorted.column(timestamp_index).clone()])?.ranges() {
+            table.insert(
+                from_nanos(timestamps.value(range.start) as u128),
+                sorted.slice(range.start, range.end - range.start),
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Runs the rows of the batch, which must be sorted by key and timestamp, through the
+    /// attempts of their partitions
+    fn process_sorted(
+        &mut self,
+        batch: &RecordBatch,
+        matches: &mut Vec<Match>,
+    ) -> anyhow::Result<()> {
+        let keys = self
+            .key_converter
+            .convert_columns(&self.key_columns(batch))?;
+        let rows = self.row_converter.convert_columns(batch.columns())?;
+        let timestamps = batch
+            .column(self.input_schema.timestamp_index)
+            .as_primitive::<TimestampNanosecondType>();
+
+        let conditions = self
+            .conditions
+            .iter()
+            .map(|condition| {
+                condition
+                    .as_ref()
+                    .map(|c| -> anyhow::Result<BooleanArray> {
+                        Ok(c.evaluate(batch)?
+                            .into_array(batch.num_rows())?
+                            .as_boolean()
+                            .clone())
+                    })
+                    .transpose()
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        for range in self.input_schema.partition(batch, false)? {
+            let key = keys.row(range.start).owned();
+            let mut state = self.partitions.remove(&key).unwrap_or_default();
+
+            for i in range {
+                let matched: Vec<_> = conditions
+                    .iter()
+                    .map(|c| c.as_ref().is_none_or(|c| c.is_valid(i) && c.value(i)))
+                    .collect();
+                self.advance(
+                    &mut state,
+                    timestamps.value(i),
+                    rows.row(i).as_ref().to_vec(),
+                    &matched,
+                    matches,
+                );
+            }
+
+            self.updated_keys.insert(key.clone());
+            self.partitions.insert(key, state);
+        }
+
+        Ok(())
+    }
+
+    fn advance(
+        &self,
+        state: &mut PartitionState,
+        timestamp: i64,
+        row: Vec<u8>,
+        matched: &[bool],
+        matches: &mut Vec<Match>,
+    ) {
+        let seq = state.first_seq + state.rows.len() as u64;
+        state.rows.push(row);
+        state.last_time = timestamp;
+
+        // attempts that this row is outside the WITHIN interval of can't be extended by it
+        if let Some(within) = self.within {
+            for attempt in &mut state.attempts {
+                if timestamp - attempt.start_time > within.as_nanos() as i64 {
+                    attempt.paths.clear();
+                }
+            }
+        }
+
+        state.attempts.push(Attempt {
+            start: seq,
+            start_time: timestamp,
+            paths: vec![Path {
+                state: 0,
+                symbols: vec![],
+            }],
+            best: None,
+        });
+
+        for attempt in state.attempts.iter_mut().filter(|a| !a.finished()) {
+            let mut next: Vec<Path> = vec![];
+            for path in &attempt.paths {
+                for (symbol, target) in &self.states[path.state as usize].transitions {
+                    // paths that reach the same state are equivalent from here on, so only the
+                    // first is kept
+                    if !matched[*symbol] || next.iter().any(|p| p.state as usize == *target) {
+                        continue;
+                    }
+                    let mut symbols = path.symbols.clone();
+                    symbols.push(*symbol as u32);
+                    next.push(Path {
+                        state: *target as u32,
+                        symbols,
+                    });
+                }
+            }
+
+            if let Some(path) = next
+                .iter()
+                .find(|p| self.states[p.state as usize].accepting)
+            {
+                attempt.best = Some(path.symbols.clone());
+            }
+
+            // paths in states without any transitions can't be extended any further
+            next.retain(|p| !self.states[p.state as usize].transitions.is_empty());
+            attempt.paths = next;
+        }
+
+        self.resolve(state, matches);
+    }
+
+    /// Emits the matches of the finished attempts that aren't preceded by an unfinished one,
+    /// and drops the rows that can no longer be part of a match
+    fn resolve(&self, state: &mut PartitionState, matches: &mut Vec<Match>) {
+        state.attempts.retain(|a| !a.finished() || a.best.is_some());
+
+        while state.attempts.first().is_some_and(|a| a.finished()) {
+            let attempt = state.attempts.remove(0);
+            let symbols = attempt.best.expect("finished attempts have a match");
+            let offset = (attempt.start - state.first_seq) as usize;
+            let end = attempt.start + symbols.len() as u64;
+
+            matches.push(Match {
+                rows: state.rows[offset..offset + symbols.len()].to_vec(),
+                symbols,
+            });
+
+            if !self.skip_to_next_row {
+                state.attempts.retain(|a| a.start >= end);
+            }
+        }
+
+        let next_seq = state.first_seq + state.rows.len() as u64;
+        let first_needed = state.attempts.first().map(|a| a.start).unwrap_or(next_seq);
+        state
+            .rows
+            .drain(..(first_needed - state.first_seq) as usize);
+        state.first_seq = first_needed;
+    }
+
+    /// Finishes the attempts that can't be extended by any future row, because the watermark
+    /// has passed their WITHIN interval or they've been idle for longer than the TTL
+    fn expire(&mut self, watermark: i64, matches: &mut Vec<Match>) {
+        let ttl_cutoff = watermark - self.ttl.as_nanos() as i64;
+        let within_cutoff = self.within.map(|w| watermark - w.as_nanos() as i64);
+
+        let mut partitions = std::mem::take(&mut self.partitions);
+        for (key, state) in partitions.iter_mut() {
+            let mut expired = false;
+            for attempt in state.attempts.iter_mut().filter(|a| !a.finished()) {
+                if attempt.start_time < ttl_cutoff
+                    || within_cutoff.is_some_and(|cutoff| attempt.start_time < cutoff)
+                {
+                    attempt.paths.clear();
+                    expired = true;
+                }
+            }
+
+            if expired {
+                self.resolve(state, matches);
+                self.updated_keys.insert(key.clone());
+            }
+        }
+        self.partitions = partitions;
+    }
+
+    fn measure_input(
+        &self,
+        input: &MeasureInput,
+        columns: &[ArrayRef],
+        offset: usize,
+        symbols: &[u32],
+    ) -> anyhow::Result<ScalarValue> {
+        let indices: Vec<_> = symbols
+            .iter()
+            .enumerate()
+            .filter(|(_, s)| input.symbol.is_none_or(|symbol| **s == symbol))
+            .map(|(i, _)| (offset + i) as u32)
+            .collect();
+
+        let Some(column) = input.column else {
+            return Ok(ScalarValue::Int64(Some(indices.len() as i64)));
+        };
+        let values = take(&columns[column], &UInt32Array::from(indices), None)?;
+
+        Ok(match input.aggregate {
+            MeasureAggregate::First if !values.is_empty() => {
+                ScalarValue::try_from_array(&values, 0)?
+            }
+            MeasureAggregate::Last if !values.is_empty() => {
+                ScalarValue::try_from_array(&values, values.len() - 1)?
+            }
+            MeasureAggregate::First | MeasureAggregate::Last => {
+                ScalarValue::try_from(&input.data_type)?
+            }
+            MeasureAggregate::Count => {
+                ScalarValue::Int64(Some((values.len() - values.null_count()) as i64))
+            }
+            MeasureAggregate::Sum => {
+                let values = cast(&values, &input.data_type)?;
+                match &input.data_type {
+                    DataType::Int64 => ScalarValue::Int64(sum(values.as_primitive::<Int64Type>())),
+                    DataType::UInt64 => {
+                        ScalarValue::UInt64(sum(values.as_primitive::<UInt64Type>()))
+                    }
+                    _ => ScalarValue::Float64(sum(values.as_primitive::<Float64Type>())),
+                }
+            }
+            MeasureAggregate::Avg => {
+                let values = cast(&values, &DataType::Float64)?;
+                let values = values.as_primitive::<Float64Type>();
+                let count = values.len() - values.null_count();
+                ScalarValue::Float64(sum(values).map(|s| s / count as f64))
+            }
+            MeasureAggregate::Min | MeasureAggregate::Max => {
+                let mut result = ScalarValue::try_from(&input.data_type)?;
+                for i in 0..values.len() {
+                    if values.is_null(i) {
+                        continue;
+                    }
+                    let value = ScalarValue::try_from_array(&values, i)?;
+                    if result.is_null()
+                        || (input.aggregate == MeasureAggregate::Min && value < result)
+                        || (input.aggregate == MeasureAggregate::Max && value > result)
+                    {
+                        result = value;
+                    }
+                }
+                result
+            }
+        })
+    }
+
+    fn build_output(
+        &self,
+        matches: Vec<Match>,
+        out_schema: &ArroyoSchema,
+    ) -> anyhow::Result<Option<RecordBatch>> {
+        if matches.is_empty() {
+            return Ok(None);
+        }
+
+        let parser = self.row_converter.parser();
+        let columns = self.row_converter.convert_rows(
+            matches
+                .iter()
+                .flat_map(|m| m.rows.iter().map(|r| parser.parse(r))),
+        )?;
+
+        let mut offsets = vec![];
+        let mut last_indices = vec![];
+        let mut offset = 0;
+        for m in &matches {
+            offsets.push(offset);
+            offset += m.rows.len();
+            last_indices.push((offset - 1) as u32);
+        }
+
+        let measure_inputs = self
+            .measure_inputs
+            .iter()
+            .map(|input| {
+                let values = matches
+                    .iter()
+                    .zip(&offsets)
+                    .map(|(m, offset)| self.measure_input(input, &columns, *offset, &m.symbols))
+                    .collect::<anyhow::Result<Vec<_>>>()?;
+                Ok(ScalarValue::iter_to_array(values)?)
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let measure_inputs = RecordBatch::try_new_with_options(
+            self.measure_input_schema.clone(),
+            measure_inputs,
+            &RecordBatchOptions::new().with_row_count(Some(matches.len())),
+        )?;
+
+        // every row of a match has the same key, and the match takes the time of its last row
+        let last_indices = UInt32Array::from(last_indices);
+        let mut output = vec![];
+        for i in self.input_schema.storage_keys().unwrap() {
+            output.push(take(&columns[*i], &last_indices, None)?);
+        }
+        for measure in &self.measures {
+            output.push(
+                measure
+                    .evaluate(&measure_inputs)?
+                    .into_array(matches.len())?,
+            );
+        }
+        output.push(take(
+            &columns[self.input_schema.timestamp_index],
+            &last_indices,
+            None,
+        )?);
+
+        let output = output
+            .into_iter()
+            .zip(out_schema.schema.fields())
+            .map(|(column, field)| cast(&column, field.data_type()))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Some(RecordBatch::try_new(
+            out_schema.schema.clone(),
+            output,
+        )?))
+    }
+
+    async fn restore_partitions(&mut self, ctx: &mut OperatorContext) -> anyhow::Result<()> {
+        let table = ctx
+            .table_manager
+            .get_uncached_key_value_view("partitions")
+            .await?;
+        let key_count = self.input_schema.storage_keys().unwrap().len();
+
+        // only the latest generation written for each key is current
+        let mut restored: HashMap<OwnedRow, (u64, Option<PartitionState>)> = HashMap::new();
+        let mut stream = Box::pin(table.get_all());
+        while let Some(batch) = stream.next().await {
+            let batch = batch?;
+            if batch.num_rows() == 0 {
+                continue;
+            }
+
+            let keys = self
+                .key_converter
+                .convert_columns(&batch.columns()[..key_count])?;
+            let states = batch.column(key_count + 1).as_binary::<i32>();
+            let generations = batch.columns().last().unwrap().as_primitive::<UInt64Type>();
+
+            for i in 0..batch.num_rows() {
+                let generation = generations.value(i);
+                self.generation = self.generation.max(generation + 1);
+
+                let (current, state) = restored.entry(keys.row(i).owned()).or_default();
+                if *current > generation {
+                    continue;
+                }
+                *current = generation;
+
+                // an empty state is written when a partition has nothing left in progress
+                let bytes = states.value(i);
+                *state = if bytes.is_empty() {
+                    None
+                } else {
+                    Some(bincode::decode_from_slice(bytes, config::standard())?.0)
+                };
+            }
+        }
+        drop(stream);
+
+        self.partitions = restored
+            .into_iter()
+            .filter_map(|(key, (_, state))| Some((key, state?)))
+            .collect();
+
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl ArrowOperator for MatchRecognize {
+    fn name(&self) -> String {
+        "MatchRecognize".to_string()
+    }
+
+    fn display(&self) -> DisplayableOperator<'_> {
+        DisplayableOperator {
+            name: Cow::Borrowed("MatchRecognize"),
+            fields: vec![
+                ("symbols", AsDisplayable::Debug(&self.symbols)),
+                ("within", AsDisplayable::Debug(&self.within)),
+                (
+                    "skip_to_next_row",
+                    AsDisplayable::Debug(&self.skip_to_next_row),
+                ),
+                ("ttl", AsDisplayable::Debug(&self.ttl)),
+            ],
+        }
+    }
+
+    async fn on_start(&mut self, ctx: &mut OperatorContext) -> DataflowResult<()> {
+        self.restore_partitions(ctx).await?;
+        Ok(())
+    }
+
+    async fn process_batch(
+        &mut self,
+        batch: RecordBatch,
+        ctx: &mut OperatorContext,
+        _: &mut dyn Collector,
+    ) -> DataflowResult<()> {
+        self.buffer_batch(batch, ctx).await?;
+        Ok(())
+    }
+
+    async fn handle_watermark(
+        &mut self,
+        watermark: Watermark,
+        ctx: &mut OperatorContext,
+        collector: &mut dyn Collector,
+    ) -> DataflowResult<Option<Watermark>> {
+        let Some(watermark) = ctx.last_present_watermark() else {
+            return Ok(Some(watermark));
+        };
+
+        let table = ctx
+            .table_manager
+            .get_expiring_time_key_table("input", Some(watermark))
+            .await?;
+
+        let mut ready = vec![];
+        while let Some(time) = table.get_min_time()
+            && time < watermark
+        {
+            ready.extend(table.expire_timestamp(time));
+        }
+
+        let mut matches = vec![];
+        if !ready.is_empty() {
+            let batch = concat_batches(&self.input_schema.schema, &ready)?;
+            let batch = self.input_schema.sort(batch, true)?;
+            self.process_sorted(&batch, &mut matches)?;
+        }
+
+        self.expire(to_nanos(watermark) as i64, &mut matches);
+
+        if let Some(batch) = self.build_output(matches, ctx.out_schema.as_ref().unwrap())? {
+            collector.collect(batch).await?;
+        }
+
+        Ok(Some(Watermark::EventTime(watermark)))
+    }
+
+    async fn handle_checkpoint(
+        &mut self,
+        _: CheckpointBarrier,
+        ctx: &mut OperatorContext,
+        _: &mut dyn Collector,
+    ) -> DataflowResult<()> {
+        let watermark = ctx.last_present_watermark();
+        ctx.table_manager
+            .get_expiring_time_key_table("input", watermark)
+            .await?
+            .flush(watermark)
+            .await?;
+
+        if self.updated_keys.is_empty() {
+            return Ok(());
+        }
+
+        // write out the state of every partition that's changed, as a new generation
+        let keys: Vec<_> = self.updated_keys.drain().collect();
+        let mut columns = self
+            .key_converter
+            .convert_rows(keys.iter().map(|k| k.row()))?;
+        let mut timestamps = vec![];
+        let mut states = vec![];
+        for key in &keys {
+            let state = &self.partitions[key];
+            timestamps.push(state.last_time);
+            states.push(if state.is_empty() {
+                vec![]
+            } else {
+                bincode::encode_to_vec(state, config::standard())
+                    .expect("partition state should be encodable")
+            });
+        }
+        columns.push(Arc::new(TimestampNanosecondArray::from(timestamps)));
+        columns.push(Arc::new(BinaryArray::from_iter_values(states)));
+        columns.push(Arc::new(UInt64Array::from(vec![
+            self.generation;
+            keys.len()
+        ])));
+        self.generation += 1;
+
+        ctx.table_manager
+            .get_uncached_key_value_view("partitions")
+            .await?
+            .insert_batch(columns)
+            .await?;
+
+        // the empty states have been written out, so don't need to be kept any longer
+        self.partitions.retain(|_, state| !state.is_empty());
+
+        Ok(())
+    }
+
+    fn tables(&self) -> HashMap<String, TableConfig> {
+        let mut tables = HashMap::new();
+        tables.insert(
+            "input".to_string(),
+            timestamp_table_config(
+                "input",
+                "buffered match_recognize input",
+                Duration::ZERO,
+                false,
+                self.input_schema.as_ref().clone(),
+            ),
+        );
+        tables.insert(
+            "partitions".to_string(),
+            timestamp_table_config(
+                "partitions",
+                "in-progress matches for each partition",
+                self.ttl,
+                true,
+                self.state_schema.as_ref().clone(),
+            ),
+        );
+        tables
+    }
+}
+
+fn measure_input_type(aggregate: MeasureAggregate, column_type: Option<&DataType>) -> DataType {
+    match (aggregate, column_type) {
+        (MeasureAggregate::Count, _) => DataType::Int64,
+        (MeasureAggregate::Sum, Some(t)) if t.is_signed_integer() => DataType::Int64,
+        (MeasureAggregate::Sum, Some(t)) if t.is_unsigned_integer() => DataType::UInt64,
+        (MeasureAggregate::Sum | MeasureAggregate::Avg, _) => DataType::Float64,
+        (_, Some(t)) => t.clone(),
+        (_, None) => unreachable!("only COUNT may omit its column"),
+    }
+}
+
+pub struct MatchRecognizeConstructor;
+impl OperatorConstructor for MatchRecognizeConstructor {
+    type ConfigT = api::MatchRecognizeOperator;
+    fn with_config(
+        &self,
+        config: Self::ConfigT,
+        registry: Arc<Registry>,
+    ) -> anyhow::Result<ConstructedOperator> {
+        let input_schema: ArroyoSchemaRef = Arc::new(config.input_schema.unwrap().try_into()?);
+        let keys = input_schema
+            .storage_keys()
+            .expect("match_recognize input must be keyed")
+            .clone();
+
+        let parse_expr = |bytes: &[u8], schema: &Schema| -> anyhow::Result<_> {
+            Ok(parse_physical_expr(
+                &PhysicalExprNode::decode(bytes)?,
+                registry.as_ref(),
+                schema,
+                &DefaultPhysicalExtensionCodec {},
+            )?)
+        };
+
+        let (symbols, conditions) = config
+            .symbols
+            .iter()
+            .map(|symbol| {
+                Ok((
+                    symbol.name.clone(),
+                    symbol
+                        .condition
+                        .as_ref()
+                        .map(|c| parse_expr(c, input_schema.schema.as_ref()))
+                        .transpose()?,
+                ))
+            })
+            .collect::<anyhow::Result<(Vec<_>, Vec<_>)>>()?;
+
+        let measure_inputs: Vec<_> = config
+            .measure_inputs
+            .iter()
+            .map(|input| {
+                let aggregate = match input.aggregate() {
+                    api::MatchMeasureAggregate::MeasureFirst => MeasureAggregate::First,
+                    api::MatchMeasureAggregate::MeasureLast => MeasureAggregate::Last,
+                    api::MatchMeasureAggregate::MeasureCount => MeasureAggregate::Count,
+                    api::MatchMeasureAggregate::MeasureSum => MeasureAggregate::Sum,
+                    api::MatchMeasureAggregate::MeasureMin => MeasureAggregate::Min,
+                    api::MatchMeasureAggregate::MeasureMax => MeasureAggregate::Max,
+                    api::MatchMeasureAggregate::MeasureAvg => MeasureAggregate::Avg,
+                };
+                let column = input.column.map(|c| c as usize);
+                MeasureInput {
+                    symbol: input.symbol,
+                    column,
+                    aggregate,
+                    data_type: measure_input_type(
+                        aggregate,
+                        column.map(|c| input_schema.schema.field(c).data_type()),
+                    ),
+                }
+            })
+            .collect();
+
+        let measure_input_schema = Arc::new(Schema::new(
+            measure_inputs
+                .iter()
+                .enumerate()
+                .map(|(i, input)| {
+                    Field::new(
+                        format!("__measure_input_{i}"),
+                        input.data_type.clone(),
+                        true,
+                    )
+                })
+                .collect::<Vec<_>>(),
+        ));
+
+        let measures = config
+            .measures
+            .iter()
+            .map(|m| parse_expr(m, measure_input_schema.as_ref()))
+            .collect::<anyhow::Result<_>>()?;
+
+        let mut state_fields: Vec<_> = keys
+            .iter()
+            .map(|i| input_schema.schema.field(*i).clone())
+            .collect();
+        state_fields.push(Field::new(
+            TIMESTAMP_FIELD,
+            DataType::Timestamp(TimeUnit::Nanosecond, None),
+            false,
+        ));
+        state_fields.push(Field::new("state", DataType::Binary, false));
+        let state_schema = Arc::new(ArroyoSchema::new_keyed(
+            Arc::new(Schema::new(state_fields)),
+            keys.len(),
+            (0..keys.len()).collect(),
+        ));
+
+        Ok(ConstructedOperator::from_operator(Box::new(
+            MatchRecognize {
+                key_converter: RowConverter::new(
+                    keys.iter()
+                        .map(|i| SortField::new(input_schema.schema.field(*i).data_type().clone()))
+                        .collect(),
+                )?,
+                row_converter: RowConverter::new(
+                    input_schema
+                        .schema
+                        .fields()
+                        .iter()
+                        .map(|f| SortField::new(f.data_type().clone()))
+                        .collect(),
+                )?,
+                input_schema,
+                state_schema,
+                symbols,
+                conditions,
+                states: config
+                    .states
+                    .iter()
+                    .map(|state| NfaState {
+                        transitions: state
+                            .transitions
+                            .iter()
+                            .map(|t| (t.symbol as usize, t.target as usize))
+                            .collect(),
+                        accepting: state.accepting,
+                    })
+                    .collect(),
+                measure_inputs,
+                measure_input_schema,
+                measures,
+                within: config.within_micros.map(Duration::from_micros),
+                skip_to_next_row: config.after_match_skip() == api::MatchAfterSkip::SkipToNextRow,
+                ttl: Duration::from_micros(config.ttl_micros),
+                partitions: HashMap::new(),
+                updated_keys: HashSet::new(),
+                generation: 0,
+            },
+        )))
+    }
+}
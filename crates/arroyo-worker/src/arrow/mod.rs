@@ -33,13 +33,18 @@ use std::sync::Arc;
 use std::sync::RwLock;
 
 pub mod async_udf;
+pub mod deduplicate;
 pub mod incremental_aggregator;
 pub mod instant_join;
+pub mod interval_join;
 pub mod join_with_expiration;
+pub mod late_records;
 pub mod lookup_join;
+pub mod match_recognize;
 pub mod session_aggregating_window;
 pub mod sliding_aggregating_window;
 pub(crate) mod sync;
+pub mod temporal_join;
 pub mod tumbling_aggregating_window;
 mod updating_cache;
 pub mod watermark_generator;
@@ -45,6 +45,10 @@ use std::time::Duration;
 use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender, unbounded_channel};
 use tokio_stream::StreamExt;
 use tracing::{debug, warn};
+
+use super::late_records::{
+    drop_late_rows, lateness_watermark, retract, updating_meta, window_result_ids,
+};
 // TODO: advance futures outside of method calls.
 
 pub struct SessionAggregatingWindowFunc {
@@ -58,6 +62,14 @@ pub struct SessionAggregatingWindowFunc {
 const GLOBAL_KEY: Vec<u8> = vec![];
 
 impl SessionAggregatingWindowFunc {
+    /// The watermark that the input rows are retained until. Sessions are emitted on the watermark,
+    /// but with an allowed lateness closed sessions are kept until it has passed them by that much,
+    /// so that late records can reopen them.
+    fn state_watermark(&self, ctx: &OperatorContext) -> Option<SystemTime> {
+        ctx.last_present_watermark()
+            .map(|watermark| lateness_watermark(watermark, self.config.allowed_lateness))
+    }
+
     fn should_advance(&self, watermark: SystemTime) -> bool {
         let result = self
             .keys_by_next_watermark_action
@@ -165,12 +177,14 @@ impl SessionAggregatingWindowFunc {
             .map(|(start_time, _keys)| *start_time)
     }
 
+    /// Adds sorted input to the sessions of its keys. Late rows reopen the closed sessions of their
+    /// key, and the results they change are returned as (retracted, created).
     #[allow(clippy::single_range_in_vec_init)]
     async fn add_at_watermark(
         &mut self,
         sorted_batch: RecordBatch,
         watermark: Option<SystemTime>,
-    ) -> Result<()> {
+    ) -> Result<(KeyedSessionResults, KeyedSessionResults)> {
         let partition = if !self.config.input_schema_ref.has_routing_keys() {
             // if we don't have keys, we can just partition by the whole batch.
             vec![0..sorted_batch.num_rows()]
@@ -202,6 +216,8 @@ impl SessionAggregatingWindowFunc {
             .map(|keys| keys.len())
             .unwrap_or(0);
 
+        let mut retracted = vec![];
+        let mut created = vec![];
         for range in partition {
             let key_batch = sorted_batch.slice(range.start, range.end - range.start);
 
@@ -215,14 +231,25 @@ impl SessionAggregatingWindowFunc {
                     .to_vec()
             };
 
-            let key_computation =
-                self.key_computations
-                    .entry(row.clone())
-                    .or_insert_with(|| KeyComputingHolder {
-                        session_window_config: self.config.clone(),
-                        active_session: None,
-                        batches_by_start_time: BTreeMap::new(),
-                    });
+            if let Some(watermark) = watermark
+                && start_time_for_sorted_batch(&key_batch, &self.config.input_schema_ref)
+                    < watermark
+            {
+                let (key_retracted, key_created) =
+                    self.reopen(row.clone(), key_batch, watermark).await?;
+                if !key_retracted.is_empty() {
+                    retracted.push((row.clone(), key_retracted));
+                }
+                if !key_created.is_empty() {
+                    created.push((row, key_created));
+                }
+                continue;
+            }
+
+            let key_computation = self
+                .key_computations
+                .entry(row.clone())
+                .or_insert_with(|| KeyComputingHolder::new(self.config.clone()));
 
             let initial_next_watermark_action = key_computation.next_watermark_action();
             let initial_data_start = key_computation.earliest_data();
@@ -275,7 +302,75 @@ impl SessionAggregatingWindowFunc {
                 }
             }
         }
-        Ok(())
+        Ok((retracted, created))
+    }
+
+    /// Recomputes the sessions of a key with late rows, which are behind the watermark and so may
+    /// change, merge or add sessions that have already been emitted. Only the rows of sessions
+    /// that late records can still reach are retained, so this replays them along with the
+    /// key's open data and returns the results that were (retracted, created).
+    async fn reopen(
+        &mut self,
+        row: Vec<u8>,
+        late_batch: RecordBatch,
+        watermark: SystemTime,
+    ) -> Result<(Vec<SessionWindowResult>, Vec<SessionWindowResult>)> {
+        let (previous, mut batches) = match self.key_computations.remove(&row) {
+            Some(key_computation) => {
+                if let Some(next_watermark_action) = key_computation.next_watermark_action() {
+                    self.keys_by_next_watermark_action
+                        .get_mut(&next_watermark_action)
+                        .expect("should have key")
+                        .remove(&row);
+                }
+                if let Some(data_start) = key_computation.earliest_data() {
+                    self.keys_by_start_time
+                        .get_mut(&data_start)
+                        .expect("should have key")
+                        .remove(&row);
+                }
+                key_computation.into_batches()
+            }
+            None => (vec![], vec![]),
+        };
+        batches.push(late_batch);
+
+        let mut key_computation = KeyComputingHolder::new(self.config.clone());
+        for batch in batches {
+            let start_time = start_time_for_sorted_batch(&batch, &self.config.input_schema_ref);
+            key_computation
+                .batches_by_start_time
+                .entry(start_time)
+                .or_default()
+                .push(batch);
+        }
+        let current = key_computation.watermark_update(watermark).await?;
+
+        if let (Some(next_watermark_action), Some(data_start)) = (
+            key_computation.next_watermark_action(),
+            key_computation.earliest_data(),
+        ) {
+            self.keys_by_next_watermark_action
+                .entry(next_watermark_action)
+                .or_default()
+                .insert(row.clone());
+            self.keys_by_start_time
+                .entry(data_start)
+                .or_default()
+                .insert(row.clone());
+            self.key_computations.insert(row, key_computation);
+        }
+
+        let retracted = previous
+            .iter()
+            .filter(|result| !current.iter().any(|other| other.same_result(result)))
+            .cloned()
+            .collect();
+        let created = current
+            .into_iter()
+            .filter(|result| !previous.iter().any(|other| other.same_result(result)))
+            .collect();
+        Ok((retracted, created))
     }
 
     fn sort_columns(&self, batch: &RecordBatch) -> Vec<SortColumn> {
@@ -367,10 +462,21 @@ impl SessionAggregatingWindowFunc {
             vec![Arc::new(window_start_array), Arc::new(window_end_array)],
             None,
         )?;
+        let ids = if self.config.is_updating() {
+            Some(window_result_ids(
+                &key_columns,
+                window_struct_array.column(0).clone(),
+            )?)
+        } else {
+            None
+        };
         let mut columns = key_columns;
         columns.insert(self.config.window_index, Arc::new(window_struct_array));
         columns.extend_from_slice(merged_batch.columns());
         columns.push(Arc::new(timestamp_array));
+        if let Some(ids) = ids {
+            columns.push(updating_meta(ids, false));
+        }
         RecordBatch::try_new(
             ctx.out_schema.as_ref().unwrap().schema.clone(),
             columns.clone(),
@@ -384,6 +490,7 @@ impl SessionAggregatingWindowFunc {
 
 struct SessionWindowConfig {
     gap: Duration,
+    allowed_lateness: Duration,
     input_schema_ref: ArroyoSchemaRef,
     window_field: FieldRef,
     window_index: usize,
@@ -394,6 +501,15 @@ struct SessionWindowConfig {
     receiver: Arc<RwLock<Option<UnboundedReceiver<RecordBatch>>>>,
 }
 
+impl SessionWindowConfig {
+    /// Whether sessions can be updated by late records after they're emitted
+    fn is_updating(&self) -> bool {
+        !self.allowed_lateness.is_zero()
+    }
+}
+
+type KeyedSessionResults = Vec<(Vec<u8>, Vec<SessionWindowResult>)>;
+
 struct ActiveSession {
     // the data start time for this session
     data_start: SystemTime,
@@ -401,6 +517,8 @@ struct ActiveSession {
     sender: Option<UnboundedSender<RecordBatch>>,
     // the next batch's execution plan
     result_stream: SendableRecordBatchStream,
+    // the session's input, retained when late records may reopen it
+    batches: Option<Vec<RecordBatch>>,
 }
 
 impl ActiveSession {
@@ -408,6 +526,7 @@ impl ActiveSession {
         aggregation_plan: Arc<dyn ExecutionPlan>,
         initial_timestamp: SystemTime,
         sender: UnboundedSender<RecordBatch>,
+        retain_batches: bool,
     ) -> Result<Self> {
         aggregation_plan.reset()?;
         let result_exec = aggregation_plan.execute(0, SessionContext::new().task_ctx())?;
@@ -416,8 +535,17 @@ impl ActiveSession {
             data_end: initial_timestamp,
             sender: Some(sender),
             result_stream: result_exec,
+            batches: retain_batches.then(Vec::new),
         })
     }
+
+    fn send(&mut self, batch: RecordBatch) -> Result<()> {
+        if let Some(batches) = &mut self.batches {
+            batches.push(batch.clone());
+        }
+        self.sender.as_ref().unwrap().send(batch)?;
+        Ok(())
+    }
     // Add all data in the batch that is within gap of the current session interval,
     // updating gap as more data is added.
     // The batch is sorted and it will never be the case that the start of batch is less than data_start - gap.
@@ -441,7 +569,7 @@ impl ActiveSession {
             // add it to the current session and update the gap
             self.data_end = self.data_end.max(from_nanos(end as u128));
             self.data_start = self.data_start.min(from_nanos(start as u128));
-            self.sender.as_ref().unwrap().send(batch)?;
+            self.send(batch)?;
             return Ok(None);
         }
 
@@ -480,10 +608,10 @@ impl ActiveSession {
         if index == batch.num_rows() {
             // all data in the batch is within the current session interval
             // we've already updated the gap, so we can just add it to the current session
-            self.sender.as_ref().unwrap().send(batch)?;
+            self.send(batch)?;
             return Ok(None);
         }
-        self.sender.as_ref().unwrap().send(batch.slice(0, index))?;
+        self.send(batch.slice(0, index))?;
 
         let batch = batch.slice(index, batch.num_rows() - index);
         let start_time = from_nanos(timestamp_column.value(index) as u128);
@@ -496,6 +624,7 @@ impl ActiveSession {
             // drop the active session sender
             self.sender.take();
         }
+        let batches = self.batches.take().unwrap_or_default();
         let result_batches: Vec<_> = self
             .result_stream
             .map(|batch| Ok(batch?))
@@ -518,15 +647,26 @@ impl ActiveSession {
             window_start: self.data_start,
             window_end: self.data_end + gap,
             batch,
+            batches,
         })
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 struct SessionWindowResult {
     window_start: SystemTime,
     window_end: SystemTime,
     batch: RecordBatch,
+    // the input of the session, retained when late records may reopen it
+    batches: Vec<RecordBatch>,
+}
+
+impl SessionWindowResult {
+    fn same_result(&self, other: &SessionWindowResult) -> bool {
+        self.window_start == other.window_start
+            && self.window_end == other.window_end
+            && self.batch == other.batch
+    }
 }
 
 struct KeyComputingHolder {
@@ -536,11 +676,22 @@ struct KeyComputingHolder {
     // buffered batches that may not be in the current session.
     // For now checkpointing happens on incoming batches, but in the future we can checkpoint partial aggregates.
     batches_by_start_time: BTreeMap<SystemTime, Vec<RecordBatch>>,
+    // emitted sessions that late records can still reopen, when there's an allowed lateness.
+    closed_sessions: Vec<SessionWindowResult>,
 }
 
 impl KeyComputingHolder {
+    fn new(session_window_config: Arc<SessionWindowConfig>) -> Self {
+        Self {
+            session_window_config,
+            active_session: None,
+            batches_by_start_time: BTreeMap::new(),
+            closed_sessions: vec![],
+        }
+    }
+
     fn next_watermark_action(&self) -> Option<SystemTime> {
-        match self.active_session {
+        let next_open_action = match self.active_session {
             Some(ref active_session) => {
                 Some(active_session.data_end + self.session_window_config.gap)
             }
@@ -548,7 +699,13 @@ impl KeyComputingHolder {
                 .batches_by_start_time
                 .first_key_value()
                 .map(|(start_time, _batches)| *start_time - self.session_window_config.gap),
-        }
+        };
+        // closed sessions are dropped once late records can't reach them
+        self.closed_sessions
+            .iter()
+            .map(|session| session.window_end + self.session_window_config.allowed_lateness)
+            .chain(next_open_action)
+            .min()
     }
     /* This method is for advancing the state machine when the watermark is incremented.
       The operator code is responsible for making sure it is called on all appropriate KeyComputingHolders.
@@ -598,12 +755,19 @@ impl KeyComputingHolder {
                         self.session_window_config.final_physical_exec.clone(),
                         *initial_timestamp,
                         sender,
+                        self.session_window_config.is_updating(),
                     )
                     .await?,
                 );
                 self.fill_active_session()?;
             }
         }
+        if self.session_window_config.is_updating() {
+            self.closed_sessions.extend(results.iter().cloned());
+            let cutoff = lateness_watermark(watermark, self.session_window_config.allowed_lateness);
+            self.closed_sessions
+                .retain(|session| session.window_end > cutoff);
+        }
         Ok(results)
     }
 
@@ -679,17 +843,38 @@ impl KeyComputingHolder {
     }
 
     fn is_empty(&self) -> bool {
-        self.active_session.is_none() && self.batches_by_start_time.is_empty()
+        self.active_session.is_none()
+            && self.batches_by_start_time.is_empty()
+            && self.closed_sessions.is_empty()
     }
 
     fn earliest_data(&self) -> Option<SystemTime> {
-        match self.active_session {
+        let earliest_open_data = match self.active_session {
             Some(ref active_session) => Some(active_session.data_start),
             None => self
                 .batches_by_start_time
                 .first_key_value()
                 .map(|(start_time, _batches)| *start_time),
+        };
+        self.closed_sessions
+            .iter()
+            .map(|session| session.window_start)
+            .chain(earliest_open_data)
+            .min()
+    }
+
+    /// Takes apart the holder into its closed sessions and all of the input it has retained.
+    fn into_batches(self) -> (Vec<SessionWindowResult>, Vec<RecordBatch>) {
+        let mut batches: Vec<_> = self
+            .closed_sessions
+            .iter()
+            .flat_map(|session| session.batches.iter().cloned())
+            .collect();
+        if let Some(mut active_session) = self.active_session {
+            batches.extend(active_session.batches.take().unwrap_or_default());
         }
+        batches.extend(self.batches_by_start_time.into_values().flatten());
+        (self.closed_sessions, batches)
     }
 }
 
@@ -755,6 +940,7 @@ impl OperatorConstructor for SessionAggregatingWindowConstructor {
 
         let config = SessionWindowConfig {
             gap: Duration::from_micros(config.gap_micros),
+            allowed_lateness: Duration::from_micros(config.allowed_lateness_micros),
             window_field,
             window_index: config.window_index as usize,
             input_schema_ref: Arc::new(input_schema),
@@ -789,6 +975,10 @@ impl ArrowOperator for SessionAggregatingWindowFunc {
                     "gap",
                     format!("{} seconds", self.config.gap.as_secs()).into(),
                 ),
+                (
+                    "allowed_lateness",
+                    AsDisplayable::Debug(&self.config.allowed_lateness),
+                ),
                 (
                     "window_field",
                     self.config.window_field.name().as_str().into(),
@@ -839,8 +1029,10 @@ impl ArrowOperator for SessionAggregatingWindowFunc {
             return Ok(());
         };
 
+        // with an allowed lateness, these are the closed sessions that were already emitted and
+        // that late records can still reopen
         let evicted_results = self.results_at_watermark(watermark).await?;
-        if !evicted_results.is_empty() {
+        if !evicted_results.is_empty() && !self.config.is_updating() {
             warn!(
                 "evicted {} results when restoring from state.",
                 evicted_results.len()
@@ -849,28 +1041,21 @@ impl ArrowOperator for SessionAggregatingWindowFunc {
         Ok(())
     }
 
-    // TODO: filter out late data
     async fn process_batch(
         &mut self,
         batch: RecordBatch,
         ctx: &mut OperatorContext,
-        _: &mut dyn Collector,
+        collector: &mut dyn Collector,
     ) -> DataflowResult<()> {
         debug!("received batch {:?}", batch);
         let current_watermark = ctx.last_present_watermark();
-        let batch = if let Some(watermark) = current_watermark {
-            // filter out late data
-            let timestamp_column = batch
-                .column(self.config.input_schema_ref.timestamp_index)
-                .as_any()
-                .downcast_ref::<TimestampNanosecondArray>()
-                .ok_or_else(|| anyhow!("expected timestamp column"))?;
-            let watermark_scalar = TimestampNanosecondArray::new_scalar(to_nanos(watermark) as i64);
-            let on_time = gt_eq(timestamp_column, &watermark_scalar)?;
-            filter_record_batch(&batch, &on_time)?
-        } else {
-            batch
-        };
+        let batch = drop_late_rows(
+            ctx,
+            batch,
+            self.config.input_schema_ref.timestamp_index,
+            self.config.allowed_lateness,
+            Duration::ZERO,
+        )?;
         if batch.num_rows() == 0 {
             warn!("fully filtered out a batch");
             return Ok(());
@@ -881,7 +1066,7 @@ impl ArrowOperator for SessionAggregatingWindowFunc {
         // TODO: pre-aggregate data before sending to state backend.
         let table = ctx
             .table_manager
-            .get_expiring_time_key_table("s", current_watermark)
+            .get_expiring_time_key_table("s", self.state_watermark(ctx))
             .await?;
 
         let max_timestamp = max(sorted
@@ -893,7 +1078,20 @@ impl ArrowOperator for SessionAggregatingWindowFunc {
 
         table.insert(from_nanos(max_timestamp as u128), sorted.clone());
 
-        self.add_at_watermark(sorted, current_watermark).await?;
+        let (retracted, created) = self.add_at_watermark(sorted, current_watermark).await?;
+        // late records update sessions that have already been emitted
+        let mut updates = vec![];
+        if !retracted.is_empty() {
+            updates.push(retract(&self.to_record_batch(retracted, ctx)?)?);
+        }
+        if !created.is_empty() {
+            updates.push(self.to_record_batch(created, ctx)?);
+        }
+        if !updates.is_empty() {
+            collector
+                .collect(concat_batches(&updates[0].schema(), &updates)?)
+                .await?;
+        }
         Ok(())
     }
 
@@ -913,7 +1111,7 @@ impl ArrowOperator for SessionAggregatingWindowFunc {
         ctx: &mut OperatorContext,
         _: &mut dyn Collector,
     ) -> DataflowResult<()> {
-        let watermark = ctx.last_present_watermark();
+        let watermark = self.state_watermark(ctx);
         let table = ctx
             .table_manager
             .get_expiring_time_key_table("s", watermark)
@@ -1,5 +1,5 @@
 use anyhow::{Result, anyhow, bail};
-use arrow::compute::{partition, sort_to_indices, take};
+use arrow::compute::{concat_batches, partition, sort_to_indices, take};
 use arrow_array::{Array, PrimitiveArray, RecordBatch, types::TimestampNanosecondType};
 use arrow_schema::SchemaRef;
 use arroyo_operator::{
@@ -22,6 +22,9 @@ use std::{
 
 use futures::stream::FuturesUnordered;
 
+use super::late_records::{
+    drop_late_rows, late_cutoff, lateness_watermark, retract, window_result_ids, with_updating_meta,
+};
 use super::sync::streams::KeyedCloneableStreamFuture;
 use arroyo_operator::context::Collector;
 use arroyo_operator::operator::{AsDisplayable, DisplayableOperator, Registry};
@@ -44,6 +47,14 @@ use tracing::info;
 pub struct SlidingAggregatingWindowFunc<K: Copy> {
     slide: Duration,
     width: Duration,
+    // cumulating windows pin the start of each window to a multiple of the width past the
+    // offset, so every slide emits the aggregate from that start up to the end of the slide
+    cumulating: bool,
+    offset: Duration,
+    allowed_lateness: Duration,
+    timestamp_index: usize,
+    // the number of group-by keys, which lead the finished aggregates
+    key_count: usize,
     binning_function: Arc<dyn PhysicalExpr>,
     partial_aggregation_plan: Arc<dyn ExecutionPlan>,
     partial_schema: ArroyoSchema,
@@ -58,6 +69,9 @@ pub struct SlidingAggregatingWindowFunc<K: Copy> {
     projection_input_schema: SchemaRef,
     final_projection: Arc<dyn ExecutionPlan>,
     state: SlidingWindowState,
+    // when updating, the results emitted for each window by its end, which an update for late
+    // records retracts
+    emitted: BTreeMap<SystemTime, RecordBatch>,
 }
 
 #[allow(clippy::enum_variant_names)]
@@ -96,6 +110,31 @@ impl<K: Copy> SlidingAggregatingWindowFunc<K> {
 
         from_nanos(nanos)
     }
+
+    /// The start of the window that ends at `bin_end`
+    fn window_start(&self, bin_end: SystemTime) -> SystemTime {
+        if self.cumulating {
+            let nanos = to_nanos(bin_end - self.slide);
+            let width = self.width.as_nanos();
+            from_nanos(nanos - (nanos + width - self.offset.as_nanos()) % width)
+        } else {
+            bin_end - self.width
+        }
+    }
+
+    /// The watermark that the state of closed bins expires on. Windows are emitted as soon as the
+    /// watermark passes them, but their bins are kept until this one, which trails it by the
+    /// allowed lateness, so that late records within the bound can still update their results.
+    fn state_watermark(&self, ctx: &OperatorContext) -> Option<SystemTime> {
+        ctx.last_present_watermark()
+            .map(|watermark| lateness_watermark(watermark, self.allowed_lateness))
+    }
+
+    /// With an allowed lateness, results are updating, and late records retract and replace the
+    /// results of the windows they fall into
+    fn is_updating(&self) -> bool {
+        !self.allowed_lateness.is_zero()
+    }
 }
 
 impl SlidingAggregatingWindowFunc<SystemTime> {
@@ -122,78 +161,116 @@ impl SlidingAggregatingWindowFunc<SystemTime> {
             SlidingWindowState::OnlyBufferedData { earliest_bin_time } => earliest_bin_time,
             SlidingWindowState::InMemoryData { next_window_start } => next_window_start,
         };
+        let bin_end = bin_start + self.slide;
+        // bins from this one on can still get late records
+        let cutoff = ctx
+            .last_present_watermark()
+            .map(|watermark| late_cutoff(watermark, self.allowed_lateness, self.slide))
+            .unwrap_or(bin_end);
         let partial_table = ctx
             .table_manager
-            .get_expiring_time_key_table("t", ctx.last_present_watermark())
+            .get_expiring_time_key_table("t", self.state_watermark(ctx))
             .await?;
 
-        let bin_end = bin_start + self.slide;
-        partial_table.flush(Some(bin_end)).await?;
+        partial_table
+            .flush(Some(lateness_watermark(bin_end, self.allowed_lateness)))
+            .await?;
 
         if let Some(mut bin_exec) = self.execs.remove(&bin_start) {
             // If there are any active computations, finish them and write them to state.
-            if let Some(mut active_exec) = bin_exec.active_exec.take() {
-                {
-                    bin_exec.sender.take();
-                }
-                let bucket_nanos = to_nanos(bin_start) as i64;
-                while let (_bin, Some((batch, new_exec))) = active_exec.await {
-                    active_exec = new_exec;
-                    let batch = batch.expect("should be able to compute batch");
-
-                    let bin_start_scalar =
-                        ScalarValue::TimestampNanosecond(Some(bucket_nanos), None);
-                    let timestamp_array = bin_start_scalar.to_array_of_size(batch.num_rows())?;
-                    let mut columns = batch.columns().to_vec();
-                    columns.push(timestamp_array);
-                    let state_batch =
-                        RecordBatch::try_new(self.partial_schema.schema.clone(), columns)?;
-                    partial_table.insert(bin_start, state_batch);
-                    bin_exec.finished_batches.push(batch);
-                }
+            for batch in bin_exec.finish_exec().await? {
+                partial_table.insert(bin_start, self.state_batch(&batch, bin_start)?);
             }
             for batch in bin_exec.finished_batches {
                 self.tiered_record_batches.insert(batch, bin_start)?;
             }
         }
+        // the earliest bin that's still needed by the next window, or by the update of a window
+        // that late records can still land in
+        let retained_start = self.window_start(bin_end.min(cutoff) + self.slide);
         partial_table.flush_timestamp(bin_end).await;
-        partial_table.expire_timestamp(bin_end - self.width + self.slide);
-        let interval_start = bin_end - self.width;
-        let interval_end = bin_end;
+        partial_table.expire_timestamp(retained_start);
+
+        let results = self.compute_window(bin_end).await?;
+        self.tiered_record_batches.delete_before(retained_start)?;
+        self.state = self.next_state(bin_end);
+
+        for batch in results.iter().filter(|batch| batch.num_rows() > 0) {
+            collector.collect(batch.clone()).await?;
+        }
+        if self.is_updating()
+            && let Some(batch) = results.into_iter().next()
+        {
+            self.emitted.insert(bin_end, batch);
+        }
+
+        Ok(())
+    }
+
+    /// The state once the windows up to `next_window_start` have been emitted: we keep advancing
+    /// while the next window has data in memory, and otherwise skip ahead to the earliest bin
+    /// that's still computing
+    fn next_state(&self, next_window_start: SystemTime) -> SlidingWindowState {
+        let next_window_end = next_window_start + self.slide;
+        if self
+            .tiered_record_batches
+            .has_data_between(self.window_start(next_window_end), next_window_end)
+        {
+            SlidingWindowState::InMemoryData { next_window_start }
+        } else {
+            match self.execs.keys().next() {
+                Some(bin) => SlidingWindowState::OnlyBufferedData {
+                    earliest_bin_time: *bin,
+                },
+                None => SlidingWindowState::NoData,
+            }
+        }
+    }
+
+    /// Computes the results of the window ending at `window_end` from the bins in memory. When
+    /// updating, these are a single batch carrying the ids that a later update retracts them by.
+    async fn compute_window(&self, window_end: SystemTime) -> Result<Vec<RecordBatch>> {
+        let interval_start = self.window_start(window_end);
         {
             let mut batches = self.final_batches_passer.write().unwrap();
             *batches = self
                 .tiered_record_batches
-                .batches_for_interval(interval_start, interval_end)?;
+                .batches_for_interval(interval_start, window_end)?;
         }
         self.finish_execution_plan.reset()?;
         let mut final_exec = self
             .finish_execution_plan
             .execute(0, SessionContext::new().task_ctx())?;
-        self.tiered_record_batches
-            .delete_before(bin_end + self.slide - self.width)?;
-
-        self.state = if self.tiered_record_batches.is_empty() {
-            match partial_table.get_min_time() {
-                Some(min_time) => SlidingWindowState::OnlyBufferedData {
-                    earliest_bin_time: self.bin_start(min_time),
-                },
-                None => SlidingWindowState::NoData,
-            }
+        // the final projection of a cumulating window derives the window from the last bin
+        let projection_timestamp = if self.cumulating {
+            window_end - self.slide
         } else {
-            SlidingWindowState::InMemoryData {
-                next_window_start: bin_end,
-            }
+            interval_start
         };
         let mut aggregate_results = Vec::new();
         while let Some(batch) = final_exec.next().await {
             let with_timestamp = Self::add_bin_start_as_timestamp(
                 &batch?,
-                interval_start,
+                projection_timestamp,
                 self.projection_input_schema.clone(),
             )?;
             aggregate_results.push(with_timestamp);
         }
+
+        // ids come from the group-by keys before the final projection moves them around, and the
+        // window end, as cumulating windows share their starts
+        let ids = if self.is_updating() {
+            let batch = concat_batches(&self.projection_input_schema, &aggregate_results)?;
+            let window_ends =
+                ScalarValue::TimestampNanosecond(Some(to_nanos(window_end) as i64), None)
+                    .to_array_of_size(batch.num_rows())?;
+            let ids = window_result_ids(&batch.columns()[..self.key_count], window_ends)?;
+            aggregate_results = vec![batch];
+            Some(ids)
+        } else {
+            None
+        };
+
         {
             let mut batches = self.final_batches_passer.write().unwrap();
             *batches = aggregate_results;
@@ -202,12 +279,79 @@ impl SlidingAggregatingWindowFunc<SystemTime> {
         let mut final_projection_exec = self
             .final_projection
             .execute(0, SessionContext::new().task_ctx())?;
+        let mut results = vec![];
         while let Some(batch) = final_projection_exec.next().await {
-            collector.collect(batch?).await?;
+            results.push(batch?);
         }
 
+        match ids {
+            Some(ids) => Ok(vec![with_updating_meta(
+                &concat_batches(&self.final_projection.schema(), &results)?,
+                ids,
+            )?]),
+            None => Ok(results),
+        }
+    }
+
+    /// Folds late records sent to a bin before `closed`, whose windows have started emitting, into
+    /// the bins in memory, and emits the retractions of the results of the emitted windows that
+    /// contain it together with the updated ones
+    async fn update_closed_bin(
+        &mut self,
+        bin_start: SystemTime,
+        closed: SystemTime,
+        ctx: &mut OperatorContext,
+        collector: &mut dyn Collector,
+    ) -> DataflowResult<()> {
+        let state_watermark = self.state_watermark(ctx);
+        let partial_table = ctx
+            .table_manager
+            .get_expiring_time_key_table("t", state_watermark)
+            .await?;
+
+        let mut bin_exec = self.execs.remove(&bin_start).expect("just sent to it");
+        for batch in bin_exec.finish_exec().await? {
+            partial_table.insert(bin_start, self.state_batch(&batch, bin_start)?);
+        }
+        for batch in bin_exec.finished_batches {
+            self.tiered_record_batches.insert(batch, bin_start)?;
+        }
+
+        let mut batches = vec![];
+        let mut window_end = bin_start + self.slide;
+        while window_end <= closed && self.window_start(window_end) <= bin_start {
+            let Some(current) = self.compute_window(window_end).await?.pop() else {
+                unreachable!("updating results are a single batch")
+            };
+            if let Some(previous) = self.emitted.remove(&window_end) {
+                batches.push(retract(&previous)?);
+            }
+            batches.push(current.clone());
+            self.emitted.insert(window_end, current);
+            window_end += self.slide;
+        }
+
+        // the windows that haven't closed yet pick the bin up from memory
+        if self.window_start(closed + self.slide) <= bin_start
+            && !matches!(self.state, SlidingWindowState::InMemoryData { .. })
+        {
+            self.state = SlidingWindowState::InMemoryData {
+                next_window_start: closed,
+            };
+        }
+
+        if let Some(first) = batches.first() {
+            collector
+                .collect(concat_batches(&first.schema(), &batches)?)
+                .await?;
+        }
         Ok(())
     }
+
+    fn state_batch(&self, batch: &RecordBatch, bin_start: SystemTime) -> Result<RecordBatch> {
+        Self::add_bin_start_as_timestamp(batch, bin_start, self.partial_schema.schema.clone())
+    }
+
     // TODO: don't repeat this
     fn add_bin_start_as_timestamp(
         batch: &RecordBatch,
@@ -260,8 +404,17 @@ impl RecordBatchTier {
             self.panes.push_back(pane);
             return Ok(());
         }
-        // if the bin_start is before start_time, error out
-        let start_time = self.start_time.unwrap();
+        // late records can land before the start time, which moves it back
+        let mut start_time = self.start_time.unwrap();
+        if bin_start < start_time {
+            let missing =
+                (start_time.duration_since(bin_start)?.as_nanos() / self.width.as_nanos()) as usize;
+            for _ in 0..missing {
+                self.panes.push_front(RecordBatchPane::default());
+            }
+            start_time = bin_start;
+            self.start_time = Some(start_time);
+        }
         let bin_index =
             (bin_start.duration_since(start_time)?.as_nanos() / self.width.as_nanos()) as usize;
         while self.panes.len() <= bin_index {
@@ -418,11 +571,16 @@ impl TieredRecordBatchHolder {
         Ok(())
     }
 
-    fn is_empty(&self) -> bool {
-        self.tiers[0]
-            .panes
-            .iter()
-            .all(|entry| entry.batches.is_empty())
+    /// Whether any of the bins in [start, end) have data
+    fn has_data_between(&self, start: SystemTime, end: SystemTime) -> bool {
+        let tier = &self.tiers[0];
+        let Some(start_time) = tier.start_time else {
+            return false;
+        };
+        tier.panes.iter().enumerate().any(|(i, pane)| {
+            let bin = start_time + tier.width * i as u32;
+            start <= bin && bin < end && !pane.batches.is_empty()
+        })
     }
 }
 
@@ -442,6 +600,22 @@ impl<K: Copy> Default for BinComputingHolder<K> {
     }
 }
 
+impl<K: Copy> BinComputingHolder<K> {
+    /// Finishes the bin's partial aggregation, returning the partial aggregates it produced
+    async fn finish_exec(&mut self) -> DataflowResult<Vec<RecordBatch>> {
+        self.sender.take();
+        let mut batches = vec![];
+        if let Some(mut active_exec) = self.active_exec.take() {
+            while let (_bin, Some((batch, next_exec))) = active_exec.await {
+                active_exec = next_exec;
+                batches.push(batch?);
+            }
+        }
+        self.finished_batches.extend(batches.iter().cloned());
+        Ok(batches)
+    }
+}
+
 type NextBatchFuture<K> = KeyedCloneableStreamFuture<K, SendableRecordBatchStream>;
 
 pub struct SlidingAggregatingWindowConstructor;
@@ -452,12 +626,60 @@ impl OperatorConstructor for SlidingAggregatingWindowConstructor {
         &self,
         config: Self::ConfigT,
         registry: Arc<Registry>,
+    ) -> anyhow::Result<ConstructedOperator> {
+        Self::construct(config, registry, None)
+    }
+}
+
+/// Cumulating windows are sliding windows whose slide is the step and whose width is the max
+/// window size, with each window starting at the beginning of the enclosing max window, which
+/// starts `offset` past a multiple of the max size
+pub struct CumulatingAggregatingWindowConstructor;
+
+impl OperatorConstructor for CumulatingAggregatingWindowConstructor {
+    type ConfigT = api::CumulatingWindowAggregateOperator;
+    fn with_config(
+        &self,
+        config: Self::ConfigT,
+        registry: Arc<Registry>,
+    ) -> anyhow::Result<ConstructedOperator> {
+        let offset_micros = config.offset_micros;
+        let config = api::SlidingWindowAggregateOperator {
+            name: config.name,
+            width_micros: config.size_micros,
+            slide_micros: config.step_micros,
+            binning_function: config.binning_function,
+            input_schema: config.input_schema,
+            partial_schema: config.partial_schema,
+            partial_aggregation_plan: config.partial_aggregation_plan,
+            final_aggregation_plan: config.final_aggregation_plan,
+            final_projection: config.final_projection,
+            allowed_lateness_micros: config.allowed_lateness_micros,
+        };
+
+        SlidingAggregatingWindowConstructor::construct(
+            config,
+            registry,
+            Some(Duration::from_micros(offset_micros)),
+        )
+    }
+}
+
+impl SlidingAggregatingWindowConstructor {
+    fn construct(
+        config: api::SlidingWindowAggregateOperator,
+        registry: Arc<Registry>,
+        cumulating_offset: Option<Duration>,
     ) -> anyhow::Result<ConstructedOperator> {
         let width = Duration::from_micros(config.width_micros);
         let input_schema: ArroyoSchema = config
             .input_schema
             .ok_or_else(|| anyhow!("missing input schema"))?
             .try_into()?;
+        let key_count = input_schema
+            .storage_keys()
+            .map(Vec::len)
+            .unwrap_or_default();
         let slide = Duration::from_micros(config.slide_micros);
         let binning_function = PhysicalExprNode::decode(&mut config.binning_function.as_slice())?;
         let binning_function = parse_physical_expr(
@@ -508,6 +730,11 @@ impl OperatorConstructor for SlidingAggregatingWindowConstructor {
             SlidingAggregatingWindowFunc {
                 slide,
                 width,
+                cumulating: cumulating_offset.is_some(),
+                offset: cumulating_offset.unwrap_or_default(),
+                allowed_lateness: Duration::from_micros(config.allowed_lateness_micros),
+                timestamp_index: input_schema.timestamp_index,
+                key_count,
                 binning_function,
                 partial_aggregation_plan,
                 partial_schema,
@@ -522,6 +749,7 @@ impl OperatorConstructor for SlidingAggregatingWindowConstructor {
                 projection_input_schema: final_projection.children()[0].schema().clone(),
                 final_projection,
                 state: SlidingWindowState::NoData,
+                emitted: BTreeMap::new(),
             },
         )))
     }
@@ -531,7 +759,11 @@ impl OperatorConstructor for SlidingAggregatingWindowConstructor {
 
 impl ArrowOperator for SlidingAggregatingWindowFunc<SystemTime> {
     fn name(&self) -> String {
-        "sliding_window".to_string()
+        if self.cumulating {
+            "cumulating_window".to_string()
+        } else {
+            "sliding_window".to_string()
+        }
     }
 
     fn display(&self) -> DisplayableOperator<'_> {
@@ -540,6 +772,12 @@ impl ArrowOperator for SlidingAggregatingWindowFunc<SystemTime> {
             fields: vec![
                 ("slide", AsDisplayable::Debug(&self.slide)),
                 ("width", AsDisplayable::Debug(&self.width)),
+                ("cumulating", AsDisplayable::Debug(&self.cumulating)),
+                ("offset", AsDisplayable::Debug(&self.offset)),
+                (
+                    "allowed_lateness",
+                    AsDisplayable::Debug(&self.allowed_lateness),
+                ),
                 (
                     "partial_aggregation_plan",
                     self.partial_aggregation_plan.as_ref().into(),
@@ -555,13 +793,14 @@ impl ArrowOperator for SlidingAggregatingWindowFunc<SystemTime> {
 
     async fn on_start(&mut self, ctx: &mut OperatorContext) -> DataflowResult<()> {
         let watermark = ctx.last_present_watermark();
+        let state_watermark = self.state_watermark(ctx);
         let table = ctx
             .table_manager
-            .get_expiring_time_key_table("t", watermark)
+            .get_expiring_time_key_table("t", state_watermark)
             .await?;
         // bins before the watermark should be put into the TieredRecordBatchHolder, those after in the exec.
         let watermark_bin = self.bin_start(watermark.unwrap_or(SystemTime::UNIX_EPOCH));
-        for (timestamp, batches) in table.all_batches_for_watermark(watermark) {
+        for (timestamp, batches) in table.all_batches_for_watermark(state_watermark) {
             let bin = self.bin_start(*timestamp);
             if bin < watermark_bin {
                 for batch in batches {
@@ -577,30 +816,46 @@ impl ArrowOperator for SlidingAggregatingWindowFunc<SystemTime> {
                 .for_each(|batch| holder.finished_batches.push(batch.clone()));
         }
 
-        if self.tiered_record_batches.is_empty() {
-            match table.get_min_time() {
-                Some(min_time) => {
-                    self.state = SlidingWindowState::OnlyBufferedData {
-                        earliest_bin_time: self.bin_start(min_time),
-                    }
+        // windows that closed before we restarted have already emitted their results, which we
+        // recompute so that late records can retract them
+        if let Some(watermark) = watermark
+            && self.is_updating()
+        {
+            let mut window_end =
+                late_cutoff(watermark, self.allowed_lateness, self.slide) + self.slide;
+            while window_end <= watermark_bin {
+                if self
+                    .tiered_record_batches
+                    .has_data_between(self.window_start(window_end), window_end)
+                    && let Some(batch) = self.compute_window(window_end).await?.pop()
+                {
+                    self.emitted.insert(window_end, batch);
                 }
-                None => self.state = SlidingWindowState::NoData,
+                window_end += self.slide;
             }
-        } else {
-            self.state = SlidingWindowState::InMemoryData {
-                next_window_start: watermark_bin,
-            };
         }
+
+        self.state = self.next_state(watermark_bin);
         Ok(())
     }
 
-    // TODO: filter out late data
     async fn process_batch(
         &mut self,
         batch: RecordBatch,
         ctx: &mut OperatorContext,
-        _: &mut dyn Collector,
+        collector: &mut dyn Collector,
     ) -> DataflowResult<()> {
+        let batch = drop_late_rows(
+            ctx,
+            batch,
+            self.timestamp_index,
+            self.allowed_lateness,
+            self.slide,
+        )?;
+        if batch.num_rows() == 0 {
+            return Ok(());
+        }
+
         let bin = self
             .binning_function
             .evaluate(&batch)
@@ -622,29 +877,31 @@ impl ArrowOperator for SlidingAggregatingWindowFunc<SystemTime> {
             .downcast_ref::<PrimitiveArray<TimestampNanosecondType>>()
             .unwrap();
 
+        let closed = ctx
+            .last_present_watermark()
+            .map(|watermark| self.bin_start(watermark));
+
         for range in partition.ranges() {
             // the binning function already rounded down to the bin start.
             let bin_start = from_nanos(typed_bin.value(range.start) as u128);
-
-            let watermark = ctx.last_present_watermark();
-
-            if watermark.is_some() && bin_start < self.bin_start(watermark.unwrap()) {
-                continue;
-            }
-
-            self.state = match self.state {
-                SlidingWindowState::NoData => SlidingWindowState::OnlyBufferedData {
-                    earliest_bin_time: bin_start,
-                },
-                SlidingWindowState::OnlyBufferedData { earliest_bin_time } => {
-                    SlidingWindowState::OnlyBufferedData {
-                        earliest_bin_time: earliest_bin_time.min(bin_start),
+            let is_late = closed.is_some_and(|closed| bin_start < closed);
+
+            // windows over late records have been emitted already, so they're updated below
+            if !is_late {
+                self.state = match self.state {
+                    SlidingWindowState::NoData => SlidingWindowState::OnlyBufferedData {
+                        earliest_bin_time: bin_start,
+                    },
+                    SlidingWindowState::OnlyBufferedData { earliest_bin_time } => {
+                        SlidingWindowState::OnlyBufferedData {
+                            earliest_bin_time: earliest_bin_time.min(bin_start),
+                        }
                     }
-                }
-                SlidingWindowState::InMemoryData { next_window_start } => {
-                    SlidingWindowState::InMemoryData { next_window_start }
-                }
-            };
+                    SlidingWindowState::InMemoryData { next_window_start } => {
+                        SlidingWindowState::InMemoryData { next_window_start }
+                    }
+                };
+            }
             let bin_batch = sorted.slice(range.start, range.end - range.start);
             let bin_exec = self.execs.entry(bin_start).or_default();
             if bin_exec.active_exec.is_none() {
@@ -669,6 +926,13 @@ impl ArrowOperator for SlidingAggregatingWindowFunc<SystemTime> {
                 .expect("just set this")
                 .send(bin_batch)
                 .unwrap();
+
+            if let Some(closed) = closed
+                && is_late
+            {
+                self.update_closed_bin(bin_start, closed, ctx, collector)
+                    .await?;
+            }
         }
         Ok(())
     }
@@ -687,6 +951,10 @@ impl ArrowOperator for SlidingAggregatingWindowFunc<SystemTime> {
             self.advance(ctx, collector).await?;
         }
 
+        // windows that late records can no longer land in won't be updated again
+        let cutoff = late_cutoff(last_watermark, self.allowed_lateness, self.slide);
+        self.emitted = self.emitted.split_off(&(cutoff + self.slide));
+
         Ok(Some(watermark))
     }
 
@@ -699,7 +967,9 @@ impl ArrowOperator for SlidingAggregatingWindowFunc<SystemTime> {
         let watermark = ctx
             .watermark()
             .and_then(|watermark: Watermark| match watermark {
-                Watermark::EventTime(watermark) => Some(watermark),
+                Watermark::EventTime(watermark) => {
+                    Some(lateness_watermark(watermark, self.allowed_lateness))
+                }
                 Watermark::Idle => None,
             });
         let table = ctx
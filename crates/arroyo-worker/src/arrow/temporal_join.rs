@@ -0,0 +1,872 @@
+use arrow::compute::kernels::boolean::or;
+use arrow::compute::kernels::zip::zip;
+use arrow::compute::{cast, partition, sort_to_indices, take, take_record_batch};
+use arrow::row::{OwnedRow, RowConverter, Rows, SortField};
+use arrow_array::cast::AsArray;
+use arrow_array::types::{TimestampNanosecondType, UInt64Type};
+use arrow_array::{
+    Array, ArrayRef, BooleanArray, RecordBatch, StructArray, TimestampNanosecondArray, UInt32Array,
+    UInt64Array, new_null_array,
+};
+use arrow_schema::DataType;
+use arroyo_operator::context::{Collector, OperatorContext};
+use arroyo_operator::operator::{
+    ArrowOperator, AsDisplayable, ConstructedOperator, DisplayableOperator, OperatorConstructor,
+    Registry,
+};
+use arroyo_rpc::UPDATING_META_FIELD;
+use arroyo_rpc::df::{ArroyoSchema, ArroyoSchemaRef};
+use arroyo_rpc::errors::DataflowResult;
+use arroyo_rpc::grpc::{api, rpc::TableConfig};
+use arroyo_state::timestamp_table_config;
+use arroyo_types::{CheckpointBarrier, Watermark, from_nanos, to_nanos};
+use futures::StreamExt;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum TemporalJoinType {
+    Inner,
+    Left,
+}
+
+struct Version {
+    // the full right-side row, including the key, timestamp, and updating metadata columns
+    row: OwnedRow,
+    deleted: bool,
+}
+
+/// Joins a stream against a versioned (updating) table, as of the event time of each row.
+///
+/// Rows from the left side are buffered until the watermark passes them, at which point every
+/// version they could match has arrived; each is then joined with the version of its key that was
+/// valid at its timestamp. Versions that have been superseded by one before the watermark can no
+/// longer be matched and are garbage-collected, while the latest version of each key is kept
+/// until it's deleted, however old it is. As versions are stored in a table that expires rows
+/// older than the TTL, a version that's been current for longer than that is moved up to the
+/// watermark and written out again.
+pub struct TemporalJoin {
+    left_input_schema: ArroyoSchemaRef,
+    right_input_schema: ArroyoSchemaRef,
+    join_type: TemporalJoinType,
+    ttl: Duration,
+    key_types: Vec<DataType>,
+    key_converter: RowConverter,
+    right_converter: RowConverter,
+    // indices of the right input columns that are included in the output
+    right_output_indices: Vec<usize>,
+    versions: HashMap<OwnedRow, BTreeMap<SystemTime, Version>>,
+    updated_keys: HashSet<OwnedRow>,
+    // the last row of each key whose versions have all been dropped since the last checkpoint,
+    // so that a deletion can be written in their place
+    tombstones: HashMap<OwnedRow, OwnedRow>,
+    generation: u64,
+}
+
+fn get_retracts(batch: &RecordBatch) -> Option<&BooleanArray> {
+    let meta = batch.column_by_name(UPDATING_META_FIELD)?;
+    let meta: &StructArray = meta.as_struct();
+    Some(
+        meta.column_by_name("is_retract")
+            .expect("meta struct must have is_retract")
+            .as_boolean(),
+    )
+}
+
+impl TemporalJoin {
+    fn new(
+        left_input_schema: ArroyoSchemaRef,
+        right_input_schema: ArroyoSchemaRef,
+        join_type: TemporalJoinType,
+        ttl: Duration,
+    ) -> anyhow::Result<Self> {
+        let right_keys = right_input_schema
+            .storage_keys()
+            .expect("right side of temporal join must be keyed")
+            .clone();
+
+        let key_types: Vec<_> = right_keys
+            .iter()
+            .map(|i| right_input_schema.schema.field(*i).data_type().clone())
+            .collect();
+
+        let right_output_indices = right_input_schema
+            .schema
+            .fields()
+            .iter()
+            .enumerate()
+            .filter(|(i, f)| {
+                !right_keys.contains(i)
+                    && *i != right_input_schema.timestamp_index
+                    && f.name() != UPDATING_META_FIELD
+            })
+            .map(|(i, _)| i)
+            .collect();
+
+        Ok(Self {
+            key_converter: RowConverter::new(
+                key_types
+                    .iter()
+                    .map(|t| SortField::new(t.clone()))
+                    .collect(),
+            )?,
+            right_converter: RowConverter::new(
+                right_input_schema
+                    .schema
+                    .fields()
+                    .iter()
+                    .map(|f| SortField::new(f.data_type().clone()))
+                    .collect(),
+            )?,
+            key_types,
+            right_output_indices,
+            left_input_schema,
+            right_input_schema,
+            join_type,
+            ttl,
+            versions: HashMap::new(),
+            updated_keys: HashSet::new(),
+            tombstones: HashMap::new(),
+            generation: 0,
+        })
+    }
+
+    fn key_rows(&self, batch: &RecordBatch, schema: &ArroyoSchema) -> anyhow::Result<Rows> {
+        let columns = schema
+            .storage_keys()
+            .expect("temporal join inputs must be keyed")
+            .iter()
+            .zip(&self.key_types)
+            .map(|(i, t)| cast(batch.column(*i), t))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(self.key_converter.convert_columns(&columns)?)
+    }
+
+    fn insert_version(&mut self, key: OwnedRow, time: SystemTime, version: Version) {
+        let versions = self.versions.entry(key.clone()).or_default();
+
+        // an update is a retraction of the old row and an append of the new one at the same
+        // time, so the append takes precedence regardless of the order they arrive in
+        if version.deleted && versions.get(&time).is_some_and(|v| !v.deleted) {
+            return;
+        }
+
+        versions.insert(time, version);
+        self.tombstones.remove(&key);
+        self.updated_keys.insert(key);
+    }
+
+    fn process_right(&mut self, batch: RecordBatch) -> anyhow::Result<()> {
+        let keys = self.key_rows(&batch, &self.right_input_schema)?;
+        let rows = self.right_converter.convert_columns(batch.columns())?;
+        let timestamps = batch
+            .column(self.right_input_schema.timestamp_index)
+            .as_primitive::<TimestampNanosecondType>();
+        let retracts = get_retracts(&batch);
+
+        for i in 0..batch.num_rows() {
+            self.insert_version(
+                keys.row(i).owned(),
+                from_nanos(timestamps.value(i) as u128),
+                Version {
+                    row: rows.row(i).owned(),
+                    deleted: retracts.is_some_and(|r| r.value(i)),
+                },
+            );
+        }
+
+        Ok(())
+    }
+
+    async fn process_left(
+        &mut self,
+        batch: RecordBatch,
+        ctx: &mut OperatorContext,
+    ) -> anyhow::Result<()> {
+        let timestamp_index = self.left_input_schema.timestamp_index;
+
+        // buffer the rows by timestamp, so that each can be released as soon as the watermark
+        // passes it
+        let indices = sort_to_indices(batch.column(timestamp_index), None, None)?;
+        let sorted = take_record_batch(&batch, &indices)?;
+        let timestamps = sorted
+            .column(timestamp_index)
+            .as_primitive::<TimestampNanosecondType>();
+
+        let watermark = ctx.last_present_watermark();
+        let table = ctx
+            .table_manager
+            .get_expiring_time_key_table("left", watermark)
+            .await?;
+
+        for range in partition(&[sorted.column(timestamp_index).clone()])?.ranges() {
+            table.insert(
+                from_nanos(timestamps.value(range.start) as u128),
+                sorted.slice(range.start, range.end - range.start),
+            );
+        }
+
+        Ok(())
+    }
+
+    fn version_at(&self, key: &OwnedRow, time: SystemTime) -> Option<&OwnedRow> {
+        self.versions
+            .get(key)?
+            .range(..=time)
+            .next_back()
+            .filter(|(_, v)| !v.deleted)
+            .map(|(_, v)| &v.row)
+    }
+
+    fn join_batch(
+        &self,
+        batch: &RecordBatch,
+        out_schema: &ArroyoSchema,
+    ) -> anyhow::Result<Option<RecordBatch>> {
+        let keys = self.key_rows(batch, &self.left_input_schema)?;
+        let timestamps = batch
+            .column(self.left_input_schema.timestamp_index)
+            .as_primitive::<TimestampNanosecondType>();
+
+        let mut right_rows = self.right_converter.empty_rows(batch.num_rows(), 0);
+        let mut left_indices = vec![];
+        let mut right_indices = vec![];
+
+        for i in 0..batch.num_rows() {
+            match self.version_at(
+                &keys.row(i).owned(),
+                from_nanos(timestamps.value(i) as u128),
+            ) {
+                Some(row) => {
+                    left_indices.push(i as u32);
+                    right_indices.push(Some(right_rows.num_rows() as u32));
+                    right_rows.push(row.row());
+                }
+                None if self.join_type == TemporalJoinType::Left => {
+                    left_indices.push(i as u32);
+                    right_indices.push(None);
+                }
+                None => {}
+            }
+        }
+
+        if left_indices.is_empty() {
+            return Ok(None);
+        }
+
+        let left = take_record_batch(batch, &UInt32Array::from(left_indices))?;
+        let right_indices = UInt32Array::from(right_indices);
+        let right_columns = self.right_converter.convert_rows(right_rows.iter())?;
+
+        let left_keys = self.left_input_schema.storage_keys().unwrap();
+        let mut columns: Vec<ArrayRef> = (0..left.num_columns())
+            .filter(|i| !left_keys.contains(i) && *i != self.left_input_schema.timestamp_index)
+            .map(|i| left.column(i).clone())
+            .collect();
+
+        for i in &self.right_output_indices {
+            columns.push(if right_rows.num_rows() == 0 {
+                new_null_array(right_columns[*i].data_type(), right_indices.len())
+            } else {
+                take(&right_columns[*i], &right_indices, None)?
+            });
+        }
+
+        columns.push(left.column(self.left_input_schema.timestamp_index).clone());
+
+        Ok(Some(RecordBatch::try_new(
+            out_schema.schema.clone(),
+            columns,
+        )?))
+    }
+
+    /// Drops the versions that can no longer be matched by any buffered or future row
+    fn expire_versions(&mut self, watermark: SystemTime) {
+        let cutoff = watermark
+            .checked_sub(self.ttl)
+            .unwrap_or(SystemTime::UNIX_EPOCH);
+
+        let updated_keys = &mut self.updated_keys;
+        let tombstones = &mut self.tombstones;
+        self.versions.retain(|key, versions| {
+            let len = versions.len();
+            let mut moved = false;
+
+            // the key is dropped entirely once its latest version is a deletion that's been
+            // passed by the watermark
+            let tombstone = versions
+                .last_key_value()
+                .filter(|(time, v)| **time <= watermark && v.deleted)
+                .map(|(_, v)| v.row.clone());
+
+            // every row that has yet to be joined is at or after the watermark, so the latest
+            // version before it is the oldest one that may still be needed
+            if let Some(time) = versions.range(..=watermark).next_back().map(|(t, _)| *t) {
+                *versions = versions.split_off(&time);
+                if versions.first_key_value().is_some_and(|(_, v)| v.deleted) {
+                    versions.pop_first();
+                }
+
+                // no other version is before the watermark, so moving this one up to it doesn't
+                // change which rows it matches, but keeps it from being expired from state
+                if time < cutoff
+                    && let Some(version) = versions.remove(&time)
+                {
+                    versions.insert(watermark, version);
+                    moved = true;
+                }
+            }
+
+            if versions.len() != len || moved {
+                updated_keys.insert(key.clone());
+            }
+
+            if let Some(row) = tombstone {
+                tombstones.insert(key.clone(), row);
+            }
+
+            !versions.is_empty()
+        });
+    }
+
+    /// Rewrites the rows of keys without any versions as deletions at the start of time, so
+    /// that on restore they replace the earlier generations of their keys and are then expired
+    fn mark_tombstones(
+        &self,
+        columns: &mut [ArrayRef],
+        is_tombstone: &BooleanArray,
+    ) -> anyhow::Result<()> {
+        if is_tombstone.true_count() == 0 {
+            return Ok(());
+        }
+
+        let timestamp_index = self.right_input_schema.timestamp_index;
+        columns[timestamp_index] = zip(
+            is_tombstone,
+            &TimestampNanosecondArray::new_scalar(0),
+            &columns[timestamp_index],
+        )?;
+
+        let meta_index = self
+            .right_input_schema
+            .schema
+            .index_of(UPDATING_META_FIELD)?;
+        let (fields, mut arrays, nulls) = columns[meta_index].as_struct().clone().into_parts();
+        let (retract_index, _) = fields
+            .find("is_retract")
+            .expect("meta struct must have is_retract");
+        arrays[retract_index] = Arc::new(or(arrays[retract_index].as_boolean(), is_tombstone)?);
+        columns[meta_index] = Arc::new(StructArray::new(fields, arrays, nulls));
+
+        Ok(())
+    }
+
+    async fn restore_versions(&mut self, ctx: &mut OperatorContext) -> anyhow::Result<()> {
+        let table = ctx
+            .table_manager
+            .get_uncached_key_value_view("right")
+            .await?;
+
+        // only the latest generation written for each key is current
+        let mut restored: HashMap<OwnedRow, (u64, Vec<(SystemTime, Version)>)> = HashMap::new();
+        let mut stream = Box::pin(table.get_all());
+        while let Some(batch) = stream.next().await {
+            let batch = batch?;
+            if batch.num_rows() == 0 {
+                continue;
+            }
+
+            let generations = batch.columns().last().unwrap().as_primitive::<UInt64Type>();
+            let batch = batch.project(&(0..batch.num_columns() - 1).collect::<Vec<_>>())?;
+
+            let keys = self.key_rows(&batch, &self.right_input_schema)?;
+            let rows = self.right_converter.convert_columns(batch.columns())?;
+            let timestamps = batch
+                .column(self.right_input_schema.timestamp_index)
+                .as_primitive::<TimestampNanosecondType>();
+            let retracts = get_retracts(&batch);
+
+            for i in 0..batch.num_rows() {
+                let generation = generations.value(i);
+                self.generation = self.generation.max(generation + 1);
+
+                let (current, versions) = restored.entry(keys.row(i).owned()).or_default();
+                if generation < *current {
+                    continue;
+                }
+                if generation > *current {
+                    *current = generation;
+                    versions.clear();
+                }
+
+                versions.push((
+                    from_nanos(timestamps.value(i) as u128),
+                    Version {
+                        row: rows.row(i).owned(),
+                        deleted: retracts.is_some_and(|r| r.value(i)),
+                    },
+                ));
+            }
+        }
+        drop(stream);
+
+        self.versions = restored
+            .into_iter()
+            .map(|(key, (_, versions))| (key, versions.into_iter().collect()))
+            .collect();
+
+        if let Some(watermark) = ctx.last_present_watermark() {
+            self.expire_versions(watermark);
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl ArrowOperator for TemporalJoin {
+    fn name(&self) -> String {
+        "TemporalJoin".to_string()
+    }
+
+    fn display(&self) -> DisplayableOperator<'_> {
+        DisplayableOperator {
+            name: Cow::Borrowed("TemporalJoin"),
+            fields: vec![
+                ("join_type", AsDisplayable::Debug(&self.join_type)),
+                ("ttl", AsDisplayable::Debug(&self.ttl)),
+            ],
+        }
+    }
+
+    async fn on_start(&mut self, ctx: &mut OperatorContext) -> DataflowResult<()> {
+        self.restore_versions(ctx).await?;
+        Ok(())
+    }
+
+    async fn process_batch(
+        &mut self,
+        _: RecordBatch,
+        _: &mut OperatorContext,
+        _: &mut dyn Collector,
+    ) -> DataflowResult<()> {
+        unreachable!();
+    }
+
+    async fn process_batch_index(
+        &mut self,
+        index: usize,
+        total_inputs: usize,
+        record_batch: RecordBatch,
+        ctx: &mut OperatorContext,
+        _: &mut dyn Collector,
+    ) -> DataflowResult<()> {
+        match index / (total_inputs / 2) {
+            0 => self.process_left(record_batch, ctx).await?,
+            1 => self.process_right(record_batch)?,
+            _ => unreachable!(),
+        }
+        Ok(())
+    }
+
+    async fn handle_watermark(
+        &mut self,
+        watermark: Watermark,
+        ctx: &mut OperatorContext,
+        collector: &mut dyn Collector,
+    ) -> DataflowResult<Option<Watermark>> {
+        let Some(watermark) = ctx.last_present_watermark() else {
+            return Ok(Some(watermark));
+        };
+
+        let table = ctx
+            .table_manager
+            .get_expiring_time_key_table("left", Some(watermark))
+            .await?;
+
+        let mut ready = vec![];
+        while let Some(time) = table.get_min_time()
+            && time < watermark
+        {
+            ready.extend(table.expire_timestamp(time));
+        }
+
+        let out_schema = ctx.out_schema.clone().unwrap();
+        for batch in ready {
+            if let Some(batch) = self.join_batch(&batch, &out_schema)? {
+                collector.collect(batch).await?;
+            }
+        }
+
+        self.expire_versions(watermark);
+
+        Ok(Some(Watermark::EventTime(watermark)))
+    }
+
+    async fn handle_checkpoint(
+        &mut self,
+        _: CheckpointBarrier,
+        ctx: &mut OperatorContext,
+        _: &mut dyn Collector,
+    ) -> DataflowResult<()> {
+        let watermark = ctx.last_present_watermark();
+        ctx.table_manager
+            .get_expiring_time_key_table("left", watermark)
+            .await?
+            .flush(watermark)
+            .await?;
+
+        // write out the full set of versions for every key that's changed, as a new generation
+        let mut rows = self.right_converter.empty_rows(self.updated_keys.len(), 0);
+        let mut times = vec![];
+        let mut is_tombstone = vec![];
+        for key in self.updated_keys.drain() {
+            if let Some(versions) = self.versions.get(&key) {
+                for (time, version) in versions {
+                    rows.push(version.row.row());
+                    times.push(to_nanos(*time) as i64);
+                    is_tombstone.push(false);
+                }
+            } else if let Some(last) = self.tombstones.remove(&key) {
+                rows.push(last.row());
+                times.push(0);
+                is_tombstone.push(true);
+            }
+        }
+        self.tombstones.clear();
+
+        if rows.num_rows() > 0 {
+            let mut columns = self.right_converter.convert_rows(rows.iter())?;
+            // versions may have been moved up since they were received
+            columns[self.right_input_schema.timestamp_index] =
+                Arc::new(TimestampNanosecondArray::from(times));
+            self.mark_tombstones(&mut columns, &BooleanArray::from(is_tombstone))?;
+            columns.push(Arc::new(UInt64Array::from(vec![
+                self.generation;
+                rows.num_rows()
+            ])));
+            self.generation += 1;
+
+            ctx.table_manager
+                .get_uncached_key_value_view("right")
+                .await?
+                .insert_batch(columns)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    fn tables(&self) -> HashMap<String, TableConfig> {
+        let mut tables = HashMap::new();
+        tables.insert(
+            "left".to_string(),
+            timestamp_table_config(
+                "left",
+                "buffered left join data",
+                Duration::ZERO,
+                false,
+                self.left_input_schema.as_ref().clone(),
+            ),
+        );
+        tables.insert(
+            "right".to_string(),
+            timestamp_table_config(
+                "right",
+                "versions of the right join data",
+                self.ttl,
+                true,
+                self.right_input_schema.as_ref().clone(),
+            ),
+        );
+        tables
+    }
+}
+
+pub struct TemporalJoinConstructor;
+impl OperatorConstructor for TemporalJoinConstructor {
+    type ConfigT = api::TemporalJoinOperator;
+    fn with_config(
+        &self,
+        config: Self::ConfigT,
+        _: Arc<Registry>,
+    ) -> anyhow::Result<ConstructedOperator> {
+        let join_type = match config.join_type() {
+            api::JoinType::Inner => TemporalJoinType::Inner,
+            api::JoinType::Left => TemporalJoinType::Left,
+            jt => unreachable!("invalid temporal join type {:?}", jt),
+        };
+
+        let left_input_schema: ArroyoSchemaRef = Arc::new(config.left_schema.unwrap().try_into()?);
+        let right_input_schema: ArroyoSchemaRef =
+            Arc::new(config.right_schema.unwrap().try_into()?);
+
+        Ok(ConstructedOperator::from_operator(Box::new(
+            TemporalJoin::new(
+                left_input_schema,
+                right_input_schema,
+                join_type,
+                Duration::from_micros(config.ttl_micros),
+            )?,
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow_array::{Int64Array, StringArray};
+    use arrow_schema::{Field, Fields, Schema, TimeUnit};
+
+    const TTL: Duration = Duration::from_secs(60);
+
+    fn time(secs: u64) -> SystemTime {
+        SystemTime::UNIX_EPOCH + Duration::from_secs(secs)
+    }
+
+    fn timestamp_field() -> Field {
+        Field::new(
+            "_timestamp",
+            DataType::Timestamp(TimeUnit::Nanosecond, None),
+            false,
+        )
+    }
+
+    fn meta_fields() -> Fields {
+        Fields::from(vec![Field::new("is_retract", DataType::Boolean, false)])
+    }
+
+    fn left_schema() -> ArroyoSchemaRef {
+        Arc::new(ArroyoSchema::new_keyed(
+            Arc::new(Schema::new(vec![
+                Field::new("currency", DataType::Utf8, false),
+                Field::new("amount", DataType::Int64, false),
+                timestamp_field(),
+            ])),
+            2,
+            vec![0],
+        ))
+    }
+
+    fn right_schema() -> ArroyoSchemaRef {
+        Arc::new(ArroyoSchema::new_keyed(
+            Arc::new(Schema::new(vec![
+                Field::new("currency", DataType::Utf8, false),
+                Field::new("rate", DataType::Int64, false),
+                timestamp_field(),
+                Field::new(UPDATING_META_FIELD, DataType::Struct(meta_fields()), false),
+            ])),
+            2,
+            vec![0],
+        ))
+    }
+
+    fn out_schema() -> ArroyoSchema {
+        ArroyoSchema::new_unkeyed(
+            Arc::new(Schema::new(vec![
+                Field::new("amount", DataType::Int64, false),
+                Field::new("rate", DataType::Int64, true),
+                timestamp_field(),
+            ])),
+            2,
+        )
+    }
+
+    fn temporal_join(join_type: TemporalJoinType) -> TemporalJoin {
+        TemporalJoin::new(left_schema(), right_schema(), join_type, TTL).unwrap()
+    }
+
+    fn timestamps(secs: impl Iterator<Item = u64>) -> ArrayRef {
+        Arc::new(TimestampNanosecondArray::from_iter_values(
+            secs.map(|s| to_nanos(time(s)) as i64),
+        ))
+    }
+
+    /// (currency, rate, time, is_retract)
+    fn right_batch(rows: &[(&str, i64, u64, bool)]) -> RecordBatch {
+        RecordBatch::try_new(
+            right_schema().schema.clone(),
+            vec![
+                Arc::new(StringArray::from_iter_values(rows.iter().map(|r| r.0))),
+                Arc::new(Int64Array::from_iter_values(rows.iter().map(|r| r.1))),
+                timestamps(rows.iter().map(|r| r.2)),
+                Arc::new(StructArray::new(
+                    meta_fields(),
+                    vec![Arc::new(BooleanArray::from_iter(
+                        rows.iter().map(|r| Some(r.3)),
+                    ))],
+                    None,
+                )),
+            ],
+        )
+        .unwrap()
+    }
+
+    /// (currency, amount, time)
+    fn left_batch(rows: &[(&str, i64, u64)]) -> RecordBatch {
+        RecordBatch::try_new(
+            left_schema().schema.clone(),
+            vec![
+                Arc::new(StringArray::from_iter_values(rows.iter().map(|r| r.0))),
+                Arc::new(Int64Array::from_iter_values(rows.iter().map(|r| r.1))),
+                timestamps(rows.iter().map(|r| r.2)),
+            ],
+        )
+        .unwrap()
+    }
+
+    /// Joins the rows, returning the (amount, rate) of each output row
+    fn join(join: &TemporalJoin, rows: &[(&str, i64, u64)]) -> Vec<(i64, Option<i64>)> {
+        let Some(batch) = join.join_batch(&left_batch(rows), &out_schema()).unwrap() else {
+            return vec![];
+        };
+
+        let amounts = batch
+            .column(0)
+            .as_primitive::<arrow_array::types::Int64Type>();
+        let rates = batch
+            .column(1)
+            .as_primitive::<arrow_array::types::Int64Type>();
+        (0..batch.num_rows())
+            .map(|i| (amounts.value(i), rates.is_valid(i).then(|| rates.value(i))))
+            .collect()
+    }
+
+    fn rates() -> RecordBatch {
+        right_batch(&[
+            ("USD", 1, 10, false),
+            ("USD", 2, 20, false),
+            ("EUR", 5, 10, false),
+            ("EUR", 5, 15, true),
+        ])
+    }
+
+    #[test]
+    fn test_join_as_of_left_time() {
+        let left = [
+            ("USD", 100, 5),
+            ("USD", 101, 15),
+            ("USD", 102, 20),
+            ("EUR", 103, 12),
+            ("EUR", 104, 16),
+            ("GBP", 105, 30),
+        ];
+
+        let mut inner = temporal_join(TemporalJoinType::Inner);
+        inner.process_right(rates()).unwrap();
+        assert_eq!(
+            join(&inner, &left),
+            vec![(101, Some(1)), (102, Some(2)), (103, Some(5))]
+        );
+
+        let mut outer = temporal_join(TemporalJoinType::Left);
+        outer.process_right(rates()).unwrap();
+        assert_eq!(
+            join(&outer, &left),
+            vec![
+                (100, None),
+                (101, Some(1)),
+                (102, Some(2)),
+                (103, Some(5)),
+                (104, None),
+                (105, None)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_update_at_same_time_keeps_append() {
+        // an update is a retraction and an append at the same time, which may arrive in
+        // either order
+        let mut join_op = temporal_join(TemporalJoinType::Inner);
+        join_op
+            .process_right(right_batch(&[
+                ("USD", 1, 10, false),
+                ("USD", 1, 20, true),
+                ("USD", 2, 20, false),
+                ("EUR", 5, 10, false),
+                ("EUR", 6, 20, false),
+                ("EUR", 5, 20, true),
+            ]))
+            .unwrap();
+
+        assert_eq!(
+            join(&join_op, &[("USD", 100, 25), ("EUR", 101, 25)]),
+            vec![(100, Some(2)), (101, Some(6))]
+        );
+    }
+
+    #[test]
+    fn test_expire_superseded_versions() {
+        let mut join_op = temporal_join(TemporalJoinType::Inner);
+        join_op.process_right(rates()).unwrap();
+        join_op
+            .process_right(right_batch(&[("USD", 3, 40, false)]))
+            .unwrap();
+        join_op.updated_keys.clear();
+
+        join_op.expire_versions(time(30));
+
+        // the version of USD before the watermark is still needed, as is the later one
+        let usd = join_op.key_rows(&left_batch(&[("USD", 0, 0)]), &left_schema());
+        let usd = usd.unwrap().row(0).owned();
+        assert_eq!(
+            join_op.versions[&usd].keys().copied().collect::<Vec<_>>(),
+            vec![time(20), time(40)]
+        );
+        assert_eq!(
+            join(&join_op, &[("USD", 100, 30), ("USD", 101, 40)]),
+            vec![(100, Some(2)), (101, Some(3))]
+        );
+
+        // EUR was deleted before the watermark, so it's dropped and written out as a tombstone
+        assert_eq!(join_op.versions.len(), 1);
+        assert_eq!(join_op.tombstones.len(), 1);
+        assert_eq!(join_op.updated_keys.len(), 2);
+        assert!(join_op.updated_keys.contains(&usd));
+    }
+
+    #[test]
+    fn test_latest_version_never_expires() {
+        let mut join_op = temporal_join(TemporalJoinType::Inner);
+        join_op
+            .process_right(right_batch(&[("USD", 1, 10, false), ("USD", 2, 20, false)]))
+            .unwrap();
+        join_op.updated_keys.clear();
+
+        // far past the TTL of every version
+        let watermark = 20 + TTL.as_secs() * 100;
+        join_op.expire_versions(time(watermark));
+
+        assert_eq!(
+            join(&join_op, &[("USD", 100, watermark)]),
+            vec![(100, Some(2))]
+        );
+
+        // the version was moved up to the watermark, so that it's written out again
+        let versions = |join_op: &TemporalJoin| {
+            join_op
+                .versions
+                .values()
+                .flat_map(|v| v.keys().copied())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(versions(&join_op), vec![time(watermark)]);
+        assert_eq!(join_op.updated_keys.len(), 1);
+
+        // until it's older than the TTL again, it doesn't need to be
+        join_op.updated_keys.clear();
+        join_op.expire_versions(time(watermark + TTL.as_secs()));
+        assert_eq!(versions(&join_op), vec![time(watermark)]);
+        assert!(join_op.updated_keys.is_empty());
+
+        let later = watermark + TTL.as_secs() + 1;
+        join_op.expire_versions(time(later));
+        assert_eq!(versions(&join_op), vec![time(later)]);
+        assert_eq!(join_op.updated_keys.len(), 1);
+        assert_eq!(join(&join_op, &[("USD", 101, later)]), vec![(101, Some(2))]);
+    }
+}
@@ -1,5 +1,5 @@
 use anyhow::{Result, anyhow};
-use arrow::compute::{partition, sort_to_indices, take};
+use arrow::compute::{concat_batches, partition, sort_to_indices, take};
 use arrow_array::{Array, PrimitiveArray, RecordBatch, types::TimestampNanosecondType};
 use arrow_schema::SchemaRef;
 use arroyo_operator::context::{Collector, OperatorContext};
@@ -11,7 +11,7 @@ use arroyo_planner::schemas::add_timestamp_field_arrow;
 use arroyo_rpc::errors::DataflowResult;
 use arroyo_rpc::grpc::{api, rpc::TableConfig};
 use arroyo_state::timestamp_table_config;
-use arroyo_types::{CheckpointBarrier, Watermark, from_nanos, print_time, to_nanos};
+use arroyo_types::{CheckpointBarrier, Watermark, from_nanos, to_nanos};
 use datafusion::common::ScalarValue;
 use datafusion::{execution::context::SessionContext, physical_plan::ExecutionPlan};
 use futures::{StreamExt, stream::FuturesUnordered};
@@ -40,13 +40,19 @@ use prost::Message;
 use std::time::Duration;
 use tokio::sync::Mutex;
 use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender, unbounded_channel};
-use tracing::warn;
 
+use super::late_records::{
+    drop_late_rows, late_cutoff, lateness_watermark, retract, window_result_ids, with_updating_meta,
+};
 use super::sync::streams::KeyedCloneableStreamFuture;
 type NextBatchFuture<K> = KeyedCloneableStreamFuture<K, SendableRecordBatchStream>;
 
 pub struct TumblingAggregatingWindowFunc<K: Copy> {
     width: Duration,
+    allowed_lateness: Duration,
+    timestamp_index: usize,
+    // the number of group-by keys, which lead the finished aggregates
+    key_count: usize,
     binning_function: Arc<dyn PhysicalExpr>,
     partial_aggregation_plan: Arc<dyn ExecutionPlan>,
     partial_schema: ArroyoSchema,
@@ -71,12 +77,28 @@ impl<K: Copy> TumblingAggregatingWindowFunc<K> {
 
         from_nanos(nanos)
     }
+
+    /// The watermark that the state of closed bins expires on. Bins are emitted as soon as the
+    /// watermark passes them, but their state is kept until this one, which trails it by the
+    /// allowed lateness, so that late records within the bound can still update their results.
+    fn state_watermark(&self, ctx: &OperatorContext) -> Option<SystemTime> {
+        ctx.last_present_watermark()
+            .map(|watermark| lateness_watermark(watermark, self.allowed_lateness))
+    }
+
+    /// With an allowed lateness, results are updating, and late records retract and replace the
+    /// results of the bin they fall into
+    fn is_updating(&self) -> bool {
+        !self.allowed_lateness.is_zero()
+    }
 }
 
 struct BinComputingHolder<K: Copy> {
     active_exec: Option<NextBatchFuture<K>>,
     finished_batches: Vec<RecordBatch>,
     sender: Option<UnboundedSender<RecordBatch>>,
+    // the results emitted for a closed bin, which an update for late records retracts
+    emitted: Option<RecordBatch>,
 }
 
 impl<K: Copy> Default for BinComputingHolder<K> {
@@ -85,10 +107,27 @@ impl<K: Copy> Default for BinComputingHolder<K> {
             active_exec: None,
             finished_batches: Vec::new(),
             sender: None,
+            emitted: None,
         }
     }
 }
 
+impl<K: Copy> BinComputingHolder<K> {
+    /// Finishes the bin's partial aggregation, returning the partial aggregates it produced
+    async fn finish_exec(&mut self) -> DataflowResult<Vec<RecordBatch>> {
+        self.sender.take();
+        let mut batches = vec![];
+        if let Some(mut active_exec) = self.active_exec.take() {
+            while let (_bin, Some((batch, next_exec))) = active_exec.await {
+                active_exec = next_exec;
+                batches.push(batch?);
+            }
+        }
+        self.finished_batches.extend(batches.iter().cloned());
+        Ok(batches)
+    }
+}
+
 type PolledFutureT = <NextBatchFuture<SystemTime> as Future>::Output;
 
 impl TumblingAggregatingWindowFunc<SystemTime> {
@@ -104,6 +143,120 @@ impl TumblingAggregatingWindowFunc<SystemTime> {
         RecordBatch::try_new(schema.clone(), columns)
             .map_err(|err| anyhow::anyhow!("schema: {:?}\nbatch:{:?}\nerr:{}", schema, batch, err))
     }
+
+    /// Computes the results of a bin from its partial aggregates. When updating, these are a
+    /// single batch carrying the ids that a later update retracts them by.
+    async fn compute_bin(
+        &self,
+        bin_start: SystemTime,
+        partials: Vec<RecordBatch>,
+    ) -> DataflowResult<Vec<RecordBatch>> {
+        {
+            let mut batches = self.final_batches_passer.write().unwrap();
+            *batches = partials;
+        }
+        self.finish_execution_plan
+            .reset()
+            .expect("reset execution plan");
+        let mut final_exec = self
+            .finish_execution_plan
+            .execute(0, SessionContext::new().task_ctx())?;
+        let mut results = vec![];
+        while let Some(batch) = final_exec.next().await {
+            let batch = batch?;
+            results.push(Self::add_bin_start_as_timestamp(
+                &batch,
+                bin_start,
+                self.aggregate_with_timestamp_schema.clone(),
+            )?);
+        }
+
+        // ids come from the group-by keys before the final projection moves them around
+        let ids = if self.is_updating() {
+            let batch = concat_batches(&self.aggregate_with_timestamp_schema, &results)?;
+            let window_starts =
+                ScalarValue::TimestampNanosecond(Some(to_nanos(bin_start) as i64), None)
+                    .to_array_of_size(batch.num_rows())?;
+            let ids = window_result_ids(&batch.columns()[..self.key_count], window_starts)?;
+            results = vec![batch];
+            Some(ids)
+        } else {
+            None
+        };
+
+        let mut schema = self.aggregate_with_timestamp_schema.clone();
+        if let Some(final_projection) = self.final_projection.as_ref() {
+            {
+                let mut batches = self.final_batches_passer.write().unwrap();
+                *batches = mem::take(&mut results);
+            }
+            final_projection.reset()?;
+            let mut final_projection_exec =
+                final_projection.execute(0, SessionContext::new().task_ctx())?;
+            while let Some(batch) = final_projection_exec.next().await {
+                results.push(batch?);
+            }
+            schema = final_projection.schema();
+        }
+
+        match ids {
+            Some(ids) => Ok(vec![with_updating_meta(
+                &concat_batches(&schema, &results)?,
+                ids,
+            )?]),
+            None => Ok(results),
+        }
+    }
+
+    /// Folds late records sent to a bin that has already emitted into its partial aggregates, and
+    /// emits the retraction of its previous results together with the updated ones
+    async fn update_closed_bin(
+        &mut self,
+        bin_start: SystemTime,
+        ctx: &mut OperatorContext,
+        collector: &mut dyn Collector,
+    ) -> DataflowResult<()> {
+        let state_watermark = self.state_watermark(ctx);
+        let holder = self.execs.get_mut(&bin_start).expect("just sent to it");
+        let partials = holder.finish_exec().await?;
+        let previous = holder.emitted.take();
+        let finished_batches = holder.finished_batches.clone();
+
+        // only the execs that are still running at a checkpoint are written to the table, so
+        // the late partials have to be written here
+        let table = ctx
+            .table_manager
+            .get_expiring_time_key_table("t", state_watermark)
+            .await?;
+        for batch in partials {
+            table.insert(
+                bin_start,
+                Self::add_bin_start_as_timestamp(
+                    &batch,
+                    bin_start,
+                    self.partial_schema.schema.clone(),
+                )?,
+            );
+        }
+
+        let Some(current) = self.compute_bin(bin_start, finished_batches).await?.pop() else {
+            unreachable!("updating results are a single batch")
+        };
+        let mut batches = vec![];
+        if let Some(previous) = previous {
+            batches.push(retract(&previous)?);
+        }
+        batches.push(current.clone());
+        collector
+            .collect(concat_batches(&current.schema(), &batches)?)
+            .await?;
+
+        self.execs
+            .get_mut(&bin_start)
+            .expect("just updated")
+            .emitted = Some(current);
+        Ok(())
+    }
 }
 
 pub struct TumblingAggregateWindowConstructor;
@@ -120,6 +273,10 @@ impl OperatorConstructor for TumblingAggregateWindowConstructor {
             .input_schema
             .ok_or_else(|| anyhow!("requires input schema"))?
             .try_into()?;
+        let key_count = input_schema
+            .storage_keys()
+            .map(Vec::len)
+            .unwrap_or_default();
         let binning_function = PhysicalExprNode::decode(&mut config.binning_function.as_slice())?;
         let binning_function = parse_physical_expr(
             &binning_function,
@@ -185,6 +342,9 @@ impl OperatorConstructor for TumblingAggregateWindowConstructor {
         Ok(ConstructedOperator::from_operator(Box::new(
             TumblingAggregatingWindowFunc {
                 width,
+                allowed_lateness: Duration::from_micros(config.allowed_lateness_micros),
+                timestamp_index: input_schema.timestamp_index,
+                key_count,
                 binning_function,
                 partial_aggregation_plan,
                 partial_schema,
@@ -211,6 +371,10 @@ impl ArrowOperator for TumblingAggregatingWindowFunc<SystemTime> {
             name: Cow::Borrowed("TumblingAggregatingWindowFunc"),
             fields: vec![
                 ("width", AsDisplayable::Debug(&self.width)),
+                (
+                    "allowed_lateness",
+                    AsDisplayable::Debug(&self.allowed_lateness),
+                ),
                 ("partial_schema", self.partial_schema.schema.as_ref().into()),
                 (
                     "partial_aggregation_plan",
@@ -233,17 +397,37 @@ impl ArrowOperator for TumblingAggregatingWindowFunc<SystemTime> {
 
     async fn on_start(&mut self, ctx: &mut OperatorContext) -> DataflowResult<()> {
         let watermark = ctx.last_present_watermark();
+        let state_watermark = self.state_watermark(ctx);
         let table = ctx
             .table_manager
-            .get_expiring_time_key_table("t", watermark)
+            .get_expiring_time_key_table("t", state_watermark)
             .await?;
-        for (timestamp, batch) in table.all_batches_for_watermark(watermark) {
+        let cutoff =
+            watermark.map(|watermark| late_cutoff(watermark, self.allowed_lateness, self.width));
+        for (timestamp, batch) in table.all_batches_for_watermark(state_watermark) {
             let bin = self.bin_start(*timestamp);
+            if cutoff.is_some_and(|cutoff| bin < cutoff) {
+                continue;
+            }
             let holder = self.execs.entry(bin).or_default();
             batch
                 .iter()
                 .for_each(|batch| holder.finished_batches.push(batch.clone()));
         }
+
+        // bins that closed before we restarted have already emitted their results, which we
+        // recompute so that late records can retract them
+        if let Some(watermark) = watermark {
+            let closed: Vec<_> = self
+                .execs
+                .range(..self.bin_start(watermark))
+                .map(|(bin, holder)| (*bin, holder.finished_batches.clone()))
+                .collect();
+            for (bin, partials) in closed {
+                let emitted = self.compute_bin(bin, partials).await?.pop();
+                self.execs.get_mut(&bin).expect("just restored").emitted = emitted;
+            }
+        }
         Ok(())
     }
 
@@ -251,8 +435,19 @@ impl ArrowOperator for TumblingAggregatingWindowFunc<SystemTime> {
         &mut self,
         batch: RecordBatch,
         ctx: &mut OperatorContext,
-        _: &mut dyn Collector,
+        collector: &mut dyn Collector,
     ) -> DataflowResult<()> {
+        let batch = drop_late_rows(
+            ctx,
+            batch,
+            self.timestamp_index,
+            self.allowed_lateness,
+            self.width,
+        )?;
+        if batch.num_rows() == 0 {
+            return Ok(());
+        }
+
         let bin = self
             .binning_function
             .evaluate(&batch)
@@ -274,22 +469,13 @@ impl ArrowOperator for TumblingAggregatingWindowFunc<SystemTime> {
             .downcast_ref::<PrimitiveArray<TimestampNanosecondType>>()
             .unwrap();
 
+        let closed = ctx
+            .last_present_watermark()
+            .map(|watermark| self.bin_start(watermark));
+
         for range in partition.ranges() {
             // the binning function already rounded down to the bin start.
             let bin_start = from_nanos(typed_bin.value(range.start) as u128);
-            let watermark = ctx.last_present_watermark();
-
-            if let Some(watermark) = watermark
-                && bin_start < self.bin_start(watermark)
-            {
-                warn!(
-                    "bin start {} is before watermark {}, skipping",
-                    print_time(bin_start),
-                    print_time(watermark)
-                );
-                continue;
-            }
-
             let bin_batch = sorted.slice(range.start, range.end - range.start);
             let bin_exec = self.execs.entry(bin_start).or_default();
             if bin_exec.active_exec.is_none() {
@@ -314,6 +500,10 @@ impl ArrowOperator for TumblingAggregatingWindowFunc<SystemTime> {
                 .expect("just set this")
                 .send(bin_batch)
                 .unwrap();
+
+            if closed.is_some_and(|closed| bin_start < closed) {
+                self.update_closed_bin(bin_start, ctx, collector).await?;
+            }
         }
         Ok(())
     }
@@ -324,69 +514,37 @@ impl ArrowOperator for TumblingAggregatingWindowFunc<SystemTime> {
         ctx: &mut OperatorContext,
         collector: &mut dyn Collector,
     ) -> DataflowResult<Option<Watermark>> {
-        if let Some(watermark) = ctx.last_present_watermark() {
-            let bin = self.bin_start(watermark);
-            while !self.execs.is_empty() {
-                let should_pop = {
-                    let Some((first_bin, _exec)) = self.execs.first_key_value() else {
-                        unreachable!("isn't empty")
-                    };
-                    *first_bin < bin
+        if let Some(current_watermark) = ctx.last_present_watermark() {
+            let closed = self.bin_start(current_watermark);
+            let to_emit: Vec<_> = self
+                .execs
+                .range(..closed)
+                .filter(|(_, holder)| holder.emitted.is_none())
+                .map(|(bin, _)| *bin)
+                .collect();
+            for bin in to_emit {
+                let Some(mut holder) = self.execs.remove(&bin) else {
+                    unreachable!("should have an entry")
                 };
-                if should_pop {
-                    let Some((popped_bin, mut exec)) = self.execs.pop_first() else {
-                        unreachable!("should have an entry")
-                    };
-                    if let Some(mut active_exec) = exec.active_exec.take() {
-                        exec.sender.take();
-                        while let (_bin, Some((batch, new_exec))) = active_exec.await {
-                            active_exec = new_exec;
-                            let batch = batch?;
-                            exec.finished_batches.push(batch);
-                        }
-                    }
-                    {
-                        let mut batches = self.final_batches_passer.write().unwrap();
-                        let finished_batches = mem::take(&mut exec.finished_batches);
-                        *batches = finished_batches;
-                    }
-                    self.finish_execution_plan
-                        .reset()
-                        .expect("reset execution plan");
-                    let mut final_exec = self
-                        .finish_execution_plan
-                        .execute(0, SessionContext::new().task_ctx())?;
-                    let mut aggregate_results = vec![];
-                    while let Some(batch) = final_exec.next().await {
-                        let batch = batch?;
-                        let with_timestamp = Self::add_bin_start_as_timestamp(
-                            &batch,
-                            popped_bin,
-                            self.aggregate_with_timestamp_schema.clone(),
-                        )?;
-                        if self.final_projection.is_some() {
-                            aggregate_results.push(with_timestamp);
-                        } else {
-                            collector.collect(with_timestamp).await?;
-                        }
-                    }
-                    if let Some(final_projection) = self.final_projection.as_ref() {
-                        {
-                            let mut batches = self.final_batches_passer.write().unwrap();
-                            *batches = aggregate_results;
-                        }
-                        final_projection.reset()?;
-                        let mut final_projection_exec =
-                            final_projection.execute(0, SessionContext::new().task_ctx())?;
-                        while let Some(batch) = final_projection_exec.next().await {
-                            let batch = batch?;
-                            collector.collect(batch).await?;
-                        }
-                    }
+                holder.finish_exec().await?;
+                let partials = if self.is_updating() {
+                    holder.finished_batches.clone()
                 } else {
-                    break;
+                    mem::take(&mut holder.finished_batches)
+                };
+                let results = self.compute_bin(bin, partials).await?;
+                for batch in results.iter().filter(|batch| batch.num_rows() > 0) {
+                    collector.collect(batch.clone()).await?;
+                }
+                if self.is_updating() {
+                    holder.emitted = results.into_iter().next();
+                    self.execs.insert(bin, holder);
                 }
             }
+
+            // closed bins can't be updated once the allowed lateness has passed them
+            let cutoff = late_cutoff(current_watermark, self.allowed_lateness, self.width);
+            self.execs = self.execs.split_off(&cutoff);
         }
         Ok(Some(watermark))
     }
@@ -436,7 +594,9 @@ impl ArrowOperator for TumblingAggregatingWindowFunc<SystemTime> {
         let watermark = ctx
             .watermark()
             .and_then(|watermark: Watermark| match watermark {
-                Watermark::EventTime(watermark) => Some(watermark),
+                Watermark::EventTime(watermark) => {
+                    Some(lateness_watermark(watermark, self.allowed_lateness))
+                }
                 Watermark::Idle => None,
             });
         let table = ctx
@@ -446,20 +606,13 @@ impl ArrowOperator for TumblingAggregatingWindowFunc<SystemTime> {
 
         // This was a separate map just to the active execs, which could, in corner cases, be much smaller.
         for (bin, exec) in self.execs.iter_mut() {
-            exec.sender.take();
-            let Some(mut active_exec) = exec.active_exec.take() else {
-                continue;
-            };
-            while let (_bin_, Some((batch, next_exec))) = active_exec.await {
-                active_exec = next_exec;
-                let batch = batch?;
+            for batch in exec.finish_exec().await? {
                 let state_batch = Self::add_bin_start_as_timestamp(
                     &batch,
                     *bin,
                     self.partial_schema.schema.clone(),
                 )?;
                 table.insert(*bin, state_batch);
-                exec.finished_batches.push(batch);
             }
         }
         table.flush(watermark).await?;
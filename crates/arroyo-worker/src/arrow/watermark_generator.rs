@@ -6,6 +6,7 @@ use arroyo_operator::operator::{
     ArrowOperator, AsDisplayable, ConstructedOperator, DisplayableOperator, OperatorConstructor,
     Registry,
 };
+use arroyo_rpc::ControlResp;
 use arroyo_rpc::df::ArroyoSchema;
 use arroyo_rpc::errors::DataflowResult;
 use arroyo_rpc::grpc::api::ExpressionWatermarkConfig;
@@ -38,6 +39,12 @@ pub struct WatermarkGenerator {
     last_event: SystemTime,
     idle: bool,
     expression: Arc<dyn PhysicalExpr>,
+    alignment_max_drift: Option<Duration>,
+    last_emitted_watermark: Option<SystemTime>,
+    per_partition: bool,
+    // the largest difference between a record's timestamp and its watermark, which is applied to
+    // the per-partition watermarks computed by the source
+    max_delay: Option<Duration>,
 }
 
 impl WatermarkGenerator {
@@ -45,6 +52,8 @@ impl WatermarkGenerator {
         interval: Duration,
         idle_time: Option<Duration>,
         expression: Arc<dyn PhysicalExpr>,
+        alignment_max_drift: Option<Duration>,
+        per_partition: bool,
     ) -> WatermarkGenerator {
         WatermarkGenerator {
             interval,
@@ -56,8 +65,30 @@ impl WatermarkGenerator {
             last_event: SystemTime::now(),
             idle: false,
             expression,
+            alignment_max_drift,
+            last_emitted_watermark: None,
+            per_partition,
+            max_delay: None,
         }
     }
+
+    /// Reports our watermark to the controller, which uses it to pause the sources that have
+    /// gotten too far ahead of the slowest one
+    async fn report_watermark(&self, ctx: &mut OperatorContext, watermark: Option<SystemTime>) {
+        let Some(max_drift) = self.alignment_max_drift else {
+            return;
+        };
+
+        ctx.control_tx
+            .send(ControlResp::WatermarkReport {
+                node_id: ctx.task_info.node_id,
+                task_index: ctx.task_info.task_index as usize,
+                watermark,
+                max_drift,
+            })
+            .await
+            .unwrap();
+    }
 }
 
 pub struct WatermarkGeneratorConstructor;
@@ -83,6 +114,8 @@ impl OperatorConstructor for WatermarkGeneratorConstructor {
                 Duration::from_micros(config.period_micros),
                 config.idle_time_micros.map(Duration::from_micros),
                 expression,
+                config.alignment_max_drift_micros.map(Duration::from_micros),
+                config.per_partition,
             ),
         )))
     }
@@ -104,6 +137,11 @@ impl ArrowOperator for WatermarkGenerator {
             fields: vec![
                 ("interval", AsDisplayable::Debug(&self.interval)),
                 ("idle_time", AsDisplayable::Debug(&self.idle_time)),
+                (
+                    "alignment_max_drift",
+                    AsDisplayable::Debug(&self.alignment_max_drift),
+                ),
+                ("per_partition", AsDisplayable::Debug(&self.per_partition)),
                 ("expression", AsDisplayable::Debug(&self.expression)),
             ],
         }
@@ -131,9 +169,12 @@ impl ArrowOperator for WatermarkGenerator {
     async fn on_close(
         &mut self,
         final_message: &Option<SignalMessage>,
-        _: &mut OperatorContext,
+        ctx: &mut OperatorContext,
         collector: &mut dyn Collector,
     ) -> DataflowResult<()> {
+        // a finished subtask shouldn't hold back the other sources
+        self.report_watermark(ctx, None).await;
+
         if let Some(SignalMessage::EndOfData) = final_message {
             // send final watermark on close
             collector
@@ -173,6 +214,19 @@ impl ArrowOperator for WatermarkGenerator {
             .downcast_ref::<arrow::array::TimestampNanosecondArray>()
             .unwrap();
 
+        if self.per_partition {
+            // the source emits the watermarks; we just track how far they should be delayed
+            let delay = timestamp_column
+                .values()
+                .iter()
+                .zip(watermark.values())
+                .map(|(t, w)| Duration::from_nanos(t.saturating_sub(*w).max(0) as u64))
+                .max()
+                .unwrap_or_default();
+            self.max_delay = Some(self.max_delay.map_or(delay, |d| d.max(delay)));
+            return Ok(());
+        }
+
         let watermark = from_nanos(kernels::aggregate::min(watermark).unwrap() as u128);
 
         self.state_cache.max_watermark = self.state_cache.max_watermark.max(watermark);
@@ -191,11 +245,57 @@ impl ArrowOperator for WatermarkGenerator {
                 .broadcast_watermark(Watermark::EventTime(watermark))
                 .await?;
             self.state_cache.last_watermark_emitted_at = max_timestamp;
+            self.last_emitted_watermark = Some(watermark);
             self.idle = false;
         }
         Ok(())
     }
 
+    async fn handle_watermark(
+        &mut self,
+        watermark: Watermark,
+        ctx: &mut OperatorContext,
+        collector: &mut dyn Collector,
+    ) -> DataflowResult<Option<Watermark>> {
+        if !self.per_partition {
+            return Ok(Some(watermark));
+        }
+
+        let Watermark::EventTime(source_watermark) = watermark else {
+            // all of the source's partitions are idle
+            self.idle = true;
+            return Ok(Some(watermark));
+        };
+
+        let Some(max_delay) = self.max_delay else {
+            return Ok(None);
+        };
+
+        let watermark = source_watermark
+            .checked_sub(max_delay)
+            .unwrap_or(SystemTime::UNIX_EPOCH);
+
+        if self.idle
+            || self
+                .last_emitted_watermark
+                .is_none_or(|last| watermark > last)
+        {
+            debug!(
+                "[{}] Emitting per-partition watermark {}",
+                ctx.task_info.task_index,
+                to_millis(watermark)
+            );
+            collector
+                .broadcast_watermark(Watermark::EventTime(watermark))
+                .await?;
+            self.state_cache.max_watermark = self.state_cache.max_watermark.max(watermark);
+            self.last_emitted_watermark = Some(watermark);
+            self.idle = false;
+        }
+
+        Ok(None)
+    }
+
     async fn handle_checkpoint(
         &mut self,
         _: CheckpointBarrier,
@@ -225,6 +325,12 @@ impl ArrowOperator for WatermarkGenerator {
             collector.broadcast_watermark(Watermark::Idle).await?;
             self.idle = true;
         }
+
+        if self.idle {
+            self.report_watermark(ctx, None).await;
+        } else if let Some(watermark) = self.last_emitted_watermark {
+            self.report_watermark(ctx, Some(watermark)).await;
+        }
         Ok(())
     }
 }
@@ -1,10 +1,17 @@
 use crate::arrow::async_udf::AsyncUdfConstructor;
+use crate::arrow::deduplicate::DeduplicateConstructor;
 use crate::arrow::incremental_aggregator::IncrementalAggregatingConstructor;
 use crate::arrow::instant_join::InstantJoinConstructor;
+use crate::arrow::interval_join::IntervalJoinConstructor;
 use crate::arrow::join_with_expiration::JoinWithExpirationConstructor;
+use crate::arrow::late_records::LateRecordsConstructor;
 use crate::arrow::lookup_join::LookupJoinConstructor;
+use crate::arrow::match_recognize::MatchRecognizeConstructor;
 use crate::arrow::session_aggregating_window::SessionAggregatingWindowConstructor;
-use crate::arrow::sliding_aggregating_window::SlidingAggregatingWindowConstructor;
+use crate::arrow::sliding_aggregating_window::{
+    CumulatingAggregatingWindowConstructor, SlidingAggregatingWindowConstructor,
+};
+use crate::arrow::temporal_join::TemporalJoinConstructor;
 use crate::arrow::tumbling_aggregating_window::TumblingAggregateWindowConstructor;
 use crate::arrow::watermark_generator::WatermarkGeneratorConstructor;
 use crate::arrow::window_fn::WindowFunctionConstructor;
@@ -15,9 +22,13 @@ use arroyo_datastream::logical::{
     LogicalEdge, LogicalEdgeType, LogicalGraph, LogicalNode, OperatorChain, OperatorName,
 };
 use arroyo_operator::ErasedConstructor;
-use arroyo_operator::context::{BatchReceiver, BatchSender, OperatorContext, batch_bounded};
+use arroyo_operator::context::{
+    BatchReceiver, BatchSender, OperatorContext, SourcePauseHandle, batch_bounded,
+};
 use arroyo_operator::operator::Registry;
-use arroyo_operator::operator::{ChainedOperator, ConstructedOperator, OperatorNode, SourceNode};
+use arroyo_operator::operator::{
+    ChainedOperator, ConstructedOperator, OperatorNode, SourceNode, in_flight_table_config,
+};
 use arroyo_planner::physical::new_registry;
 use arroyo_rpc::config::config;
 use arroyo_rpc::df::ArroyoSchema;
@@ -68,6 +79,7 @@ pub struct QueueNode {
     task_info: Arc<TaskInfo>,
     operator_ids: Vec<String>,
     tx: Sender<ControlMessage>,
+    pause: Option<SourcePauseHandle>,
 }
 
 impl Debug for QueueNode {
@@ -112,10 +124,19 @@ impl SubtaskOrQueueNode {
             SubtaskOrQueueNode::SubtaskNode(sn) => {
                 let (tx, rx) = channel(16);
 
+                let pause = if let OperatorNode::Source(source) = &mut sn.node {
+                    let pause = SourcePauseHandle::new(&tx);
+                    source.pause = Some(pause.clone());
+                    Some(pause)
+                } else {
+                    None
+                };
+
                 let n = SubtaskOrQueueNode::QueueNode(QueueNode {
                     task_info: sn.node.task_info().clone(),
                     operator_ids: sn.node.operator_ids(),
                     tx,
+                    pause,
                 });
 
                 (n, rx)
@@ -173,6 +194,7 @@ impl Program {
         logical: &DiGraph<LogicalNode, LogicalEdge>,
         udfs: &[LocalUdf],
         restore_epoch: Option<u32>,
+        unaligned_checkpoints: bool,
         control_tx: Sender<ControlResp>,
     ) -> Self {
         let assignments = logical
@@ -198,6 +220,7 @@ impl Program {
             &assignments,
             registry,
             restore_epoch,
+            unaligned_checkpoints,
             control_tx,
         )
         .await
@@ -210,6 +233,7 @@ impl Program {
         assignments: &Vec<TaskAssignment>,
         registry: Registry,
         restore_epoch: Option<u32>,
+        unaligned_checkpoints: bool,
         control_tx: Sender<ControlResp>,
     ) -> Program {
         let mut physical = DiGraph::new();
@@ -281,6 +305,7 @@ impl Program {
                         in_schemas.clone(),
                         out_schema.clone(),
                         checkpoint_metadata.as_ref(),
+                        unaligned_checkpoints,
                         control_tx.clone(),
                         registry.clone(),
                     )
@@ -396,6 +421,35 @@ impl RunningEngine {
             .collect()
     }
 
+    /// The pause handles for the sources running on this worker, keyed by the subtask that each
+    /// one feeds into (which is where its watermarks are generated)
+    pub fn source_pause_handles(&self) -> HashMap<(u32, u32), SourcePauseHandle> {
+        let graph = self.program.graph.read().unwrap();
+        graph
+            .externals(Direction::Incoming)
+            .filter(|idx| {
+                let w = graph.node_weight(*idx).unwrap();
+                self.assignments
+                    .get(&(w.id(), w.subtask_idx()))
+                    .unwrap()
+                    .worker_id
+                    == self.worker_id.0
+            })
+            .filter_map(|idx| {
+                let pause = graph.node_weight(idx).unwrap().as_queue().pause.clone()?;
+                let downstream = graph
+                    .neighbors_directed(idx, Direction::Outgoing)
+                    .map(|n| {
+                        let w = graph.node_weight(n).unwrap();
+                        (w.id(), w.subtask_idx() as u32)
+                    })
+                    .collect::<Vec<_>>();
+                Some(downstream.into_iter().map(move |k| (k, pause.clone())))
+            })
+            .flatten()
+            .collect()
+    }
+
     pub fn sink_controls(&self) -> Vec<Sender<ControlMessage>> {
         let graph = self.program.graph.read().unwrap();
         graph
@@ -802,6 +856,7 @@ pub async fn construct_node(
     in_schemas: Vec<Arc<ArroyoSchema>>,
     out_schema: Option<Arc<ArroyoSchema>>,
     restore_from: Option<&CheckpointMetadata>,
+    unaligned_checkpoints: bool,
     control_tx: Sender<ControlResp>,
     registry: Arc<Registry>,
 ) -> OperatorNode {
@@ -835,6 +890,7 @@ pub async fn construct_node(
             )
             .await,
             operator,
+            pause: None,
         })
     } else {
         let mut head = None;
@@ -847,6 +903,12 @@ pub async fn construct_node(
                 unreachable!("sources must be the first node in a chain");
             };
 
+            let mut tables = op.tables();
+            if cur.is_none() && unaligned_checkpoints {
+                // data overtaken by unaligned barriers is stored by the head of the chain
+                tables.extend(in_flight_table_config());
+            }
+
             let ctx = OperatorContext::new(
                 Arc::new(TaskInfo {
                     job_id: job_id.to_string(),
@@ -866,12 +928,14 @@ pub async fn construct_node(
                     in_schemas.clone()
                 },
                 edge.cloned().or(out_schema.clone()),
-                op.tables(),
+                tables,
             )
             .await;
 
             if cur.is_none() {
-                head = Some(ChainedOperator::new(op, ctx));
+                let mut chained = ChainedOperator::new(op, ctx);
+                chained.unaligned_checkpoints = unaligned_checkpoints;
+                head = Some(chained);
                 cur = head.as_mut();
                 input_partitions = 1;
             } else {
@@ -896,12 +960,18 @@ pub fn construct_operator(
         OperatorName::AsyncUdf => Box::new(AsyncUdfConstructor),
         OperatorName::TumblingWindowAggregate => Box::new(TumblingAggregateWindowConstructor),
         OperatorName::SlidingWindowAggregate => Box::new(SlidingAggregatingWindowConstructor),
+        OperatorName::CumulatingWindowAggregate => Box::new(CumulatingAggregatingWindowConstructor),
         OperatorName::SessionWindowAggregate => Box::new(SessionAggregatingWindowConstructor),
+        OperatorName::LateRecords => Box::new(LateRecordsConstructor),
         OperatorName::UpdatingAggregate => Box::new(IncrementalAggregatingConstructor),
         OperatorName::ExpressionWatermark => Box::new(WatermarkGeneratorConstructor),
         OperatorName::Join => Box::new(JoinWithExpirationConstructor),
         OperatorName::InstantJoin => Box::new(InstantJoinConstructor),
+        OperatorName::IntervalJoin => Box::new(IntervalJoinConstructor),
         OperatorName::LookupJoin => Box::new(LookupJoinConstructor),
+        OperatorName::TemporalJoin => Box::new(TemporalJoinConstructor),
+        OperatorName::MatchRecognize => Box::new(MatchRecognizeConstructor),
+        OperatorName::Deduplicate => Box::new(DeduplicateConstructor),
         OperatorName::WindowFunction => Box::new(WindowFunctionConstructor),
         OperatorName::ConnectorSource | OperatorName::ConnectorSink => {
             let op: api::ConnectorOp = prost::Message::decode(config).unwrap();
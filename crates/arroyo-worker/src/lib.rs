@@ -7,11 +7,12 @@ use anyhow::{Context, Result};
 
 use arroyo_rpc::grpc::rpc::worker_grpc_server::{WorkerGrpc, WorkerGrpcServer};
 use arroyo_rpc::grpc::rpc::{
-    CheckpointReq, CheckpointResp, CommitReq, CommitResp, GetWorkerPhaseReq, GetWorkerPhaseResp,
-    HeartbeatReq, JobFinishedReq, JobFinishedResp, LoadCompactedDataReq, LoadCompactedDataRes,
-    MetricFamily, MetricsReq, MetricsResp, NonfatalErrorReq, RegisterWorkerReq, StartExecutionReq,
-    StartExecutionResp, StopExecutionReq, StopExecutionResp, TaskCheckpointCompletedReq,
-    TaskCheckpointEventReq, TaskFailedReq, TaskFinishedReq, TaskStartedReq, WorkerInfo,
+    AlignWatermarksReq, AlignWatermarksResp, CheckpointReq, CheckpointResp, CommitReq, CommitResp,
+    GetWorkerPhaseReq, GetWorkerPhaseResp, HeartbeatReq, JobFinishedReq, JobFinishedResp,
+    LoadCompactedDataReq, LoadCompactedDataRes, MetricFamily, MetricsReq, MetricsResp,
+    NonfatalErrorReq, RegisterWorkerReq, StartExecutionReq, StartExecutionResp, StopExecutionReq,
+    StopExecutionResp, TaskCheckpointCompletedReq, TaskCheckpointEventReq, TaskFailedReq,
+    TaskFinishedReq, TaskStartedReq, WatermarkReportReq, WorkerInfo,
     WorkerInitializationCompleteReq, WorkerPhase, WorkerResources,
 };
 use arroyo_types::{
@@ -33,6 +34,7 @@ use tracing::{debug, error, info, warn};
 
 use crate::utils::to_d2;
 use arroyo_datastream::logical::LogicalProgram;
+use arroyo_operator::context::SourcePauseHandle;
 use arroyo_planner::physical::new_registry;
 use arroyo_rpc::config::config;
 use arroyo_rpc::controller_client;
@@ -107,6 +109,8 @@ struct EngineState {
     sinks: Vec<Sender<ControlMessage>>,
     operator_to_node: HashMap<String, u32>,
     operator_controls: HashMap<u32, Vec<Sender<ControlMessage>>>, // node_id -> vec of control tx
+    source_pauses: HashMap<(u32, u32), SourcePauseHandle>,
+    unaligned_checkpoints: bool,
     shutdown_guard: ShutdownGuard,
 }
 
@@ -392,6 +396,18 @@ impl WorkerServer {
                                 }
                             )).await.err()
                         }
+                        Some(ControlResp::WatermarkReport { node_id, task_index, watermark, max_drift }) => {
+                            controller.report_watermark(Request::new(
+                                WatermarkReportReq {
+                                    worker_id: worker_id.0,
+                                    job_id: job_id.clone(),
+                                    node_id,
+                                    subtask_index: task_index as u32,
+                                    watermark: watermark.map(to_micros),
+                                    max_drift_micros: max_drift.as_micros() as u64,
+                                }
+                            )).await.err()
+                        }
                         Some(ControlResp::TaskStarted {node_id, task_index, start_time}) => {
                             controller.task_started(Request::new(
                                 TaskStartedReq {
@@ -545,6 +561,7 @@ impl WorkerServer {
                 &req.tasks,
                 registry,
                 req.restore_epoch,
+                logical.program_config.unaligned_checkpoints,
                 control_tx.clone(),
             )
             .await;
@@ -572,12 +589,15 @@ impl WorkerServer {
         let sinks = engine.sink_controls();
         let operator_controls = engine.operator_controls();
         let operator_to_node = engine.operator_to_node();
+        let source_pauses = engine.source_pause_handles();
 
         let engine_state = EngineState {
             sources,
             sinks,
             operator_to_node,
             operator_controls,
+            source_pauses,
+            unaligned_checkpoints: logical.program_config.unaligned_checkpoints,
             shutdown_guard: shutdown_guard.child("engine-state"),
         };
 
@@ -644,12 +664,14 @@ impl WorkerGrpc for WorkerServer {
     ) -> Result<Response<CheckpointResp>, Status> {
         let req = request.into_inner();
 
-        let (sinks, sources) = {
+        let (sinks, sources, unaligned) = {
             let phase = self.phase.lock().unwrap();
             match &*phase {
-                WorkerExecutionPhase::Running(engine_state) => {
-                    (engine_state.sinks.clone(), engine_state.sources.clone())
-                }
+                WorkerExecutionPhase::Running(engine_state) => (
+                    engine_state.sinks.clone(),
+                    engine_state.sources.clone(),
+                    engine_state.unaligned_checkpoints,
+                ),
                 _ => {
                     return Err(Status::failed_precondition("Worker not in running phase"));
                 }
@@ -674,6 +696,7 @@ impl WorkerGrpc for WorkerServer {
             min_epoch: req.min_epoch,
             timestamp: from_millis(req.timestamp),
             then_stop: req.then_stop,
+            unaligned,
         };
 
         for n in &sources {
@@ -872,4 +895,36 @@ impl WorkerGrpc for WorkerServer {
             error_message,
         }))
     }
+
+    async fn align_watermarks(
+        &self,
+        request: Request<AlignWatermarksReq>,
+    ) -> Result<Response<AlignWatermarksResp>, Status> {
+        let paused: HashSet<_> = request
+            .into_inner()
+            .paused
+            .into_iter()
+            .map(|s| (s.node_id, s.subtask_index))
+            .collect();
+
+        let phase = self.phase.lock().unwrap();
+        let WorkerExecutionPhase::Running(engine_state) = &*phase else {
+            return Err(Status::failed_precondition("Worker not in running phase"));
+        };
+
+        for (subtask, pause) in &engine_state.source_pauses {
+            let should_pause = paused.contains(subtask);
+            if pause.is_paused() != should_pause {
+                info!(
+                    "{} source feeding {}-{} for watermark alignment",
+                    if should_pause { "Pausing" } else { "Resuming" },
+                    subtask.0,
+                    subtask.1
+                );
+            }
+            pause.set_paused(should_pause);
+        }
+
+        Ok(Response::new(AlignWatermarksResp {}))
+    }
 }
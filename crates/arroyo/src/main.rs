@@ -1,4 +1,5 @@
 mod run;
+mod state;
 
 use anyhow::{anyhow, bail};
 use arroyo_planner::{ArroyoSchemaProvider, SqlConfig};
@@ -79,6 +80,48 @@ struct RunArgs {
     query: Input,
 }
 
+#[derive(Args)]
+struct InspectArgs {
+    /// Directory or URL containing the checkpoints, like the state dir of `arroyo run` or a
+    /// cluster's checkpoint URL
+    #[arg(short = 's', long)]
+    state_dir: String,
+
+    /// Job to inspect; may be omitted if the state dir only contains one job
+    #[arg(short, long)]
+    job: Option<String>,
+
+    /// Checkpoint epoch to inspect; defaults to the latest
+    #[arg(short, long)]
+    epoch: Option<u32>,
+
+    /// Operator to inspect; if omitted, lists the operators in the checkpoint
+    #[arg(short, long)]
+    operator: Option<String>,
+
+    /// Table to read; if omitted, lists the operator's tables
+    #[arg(short, long)]
+    table: Option<String>,
+
+    /// Only print rows with this key
+    #[arg(short, long)]
+    key: Option<String>,
+
+    /// Number of matching rows to skip
+    #[arg(long, default_value = "0")]
+    offset: usize,
+
+    /// Maximum number of rows to print
+    #[arg(short, long, default_value = "100")]
+    limit: usize,
+}
+
+#[derive(Subcommand)]
+enum StateCommands {
+    /// Prints the contents of checkpointed state
+    Inspect(InspectArgs),
+}
+
 #[derive(Subcommand)]
 enum Commands {
     /// Run a query as a local pipeline cluster
@@ -109,6 +152,24 @@ enum Commands {
         wait: Option<u32>,
     },
 
+    /// Inspects checkpointed state
+    State {
+        #[command(subcommand)]
+        command: StateCommands,
+    },
+
+    /// Deletes checkpoint files that are no longer needed by any pipeline
+    Gc {
+        /// List the files that would be deleted without deleting them
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Also delete the checkpoints of jobs that aren't in this cluster's database; only safe
+        /// if no other cluster shares the checkpoint storage
+        #[arg(long)]
+        delete_orphans: bool,
+    },
+
     /// Visualizes a query plan
     Visualize {
         /// Open the visualization in the browser
@@ -176,6 +237,23 @@ async fn main() {
                 exit(1);
             }
         }
+        Commands::State {
+            command: StateCommands::Inspect(args),
+        } => {
+            if let Err(e) = state::inspect(args).await {
+                eprintln!("{e}");
+                exit(1);
+            }
+        }
+        Commands::Gc {
+            dry_run,
+            delete_orphans,
+        } => {
+            if let Err(e) = gc(dry_run, delete_orphans).await {
+                error!("{}", e);
+                exit(1);
+            }
+        }
         Commands::Node { .. } => {
             start_node().await;
         }
@@ -412,6 +490,30 @@ async fn migrate(wait: Option<u32>) -> anyhow::Result<()> {
     Ok(())
 }
 
+async fn gc(dry_run: bool, delete_orphans: bool) -> anyhow::Result<()> {
+    let _guard = arroyo_server_common::init_logging("gc");
+
+    let result =
+        arroyo_api::collect_checkpoint_garbage(&db_source().await, dry_run, delete_orphans).await?;
+
+    for file in &result.files {
+        info!(
+            "{} {}",
+            if dry_run { "Would delete" } else { "Deleted" },
+            file
+        );
+    }
+
+    info!(
+        "{} {} unreferenced checkpoint file(s) ({} bytes)",
+        if dry_run { "Found" } else { "Deleted" },
+        result.files.len(),
+        result.bytes
+    );
+
+    Ok(())
+}
+
 async fn start_control_plane(service: CPService) {
     let _guard = arroyo_server_common::init_logging(service.name());
 
@@ -533,3 +635,37 @@ async fn visualize(query: Input, open: bool) {
         println!("{d2}");
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn test_cli() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn test_gc_args() {
+        let cli = Cli::try_parse_from(["arroyo", "gc"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Gc {
+                dry_run: false,
+                delete_orphans: false
+            }
+        ));
+
+        let cli = Cli::try_parse_from(["arroyo", "gc", "--dry-run", "--delete-orphans"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Gc {
+                dry_run: true,
+                delete_orphans: true
+            }
+        ));
+
+        assert!(Cli::try_parse_from(["arroyo", "gc", "--force"]).is_err());
+    }
+}
@@ -0,0 +1,113 @@
+use crate::InspectArgs;
+use anyhow::{anyhow, bail};
+use arroyo_state::inspect;
+use arroyo_storage::StorageProvider;
+
+pub async fn inspect(args: InspectArgs) -> anyhow::Result<()> {
+    let storage = StorageProvider::for_url(&args.state_dir)
+        .await
+        .map_err(|e| anyhow!("failed to open state dir '{}': {}", args.state_dir, e))?;
+
+    let mut checkpoints = inspect::list_checkpoints(&storage).await?;
+
+    let job_id = match args.job {
+        Some(job_id) => job_id,
+        None => {
+            let mut jobs = checkpoints.keys();
+            match (jobs.next(), jobs.next()) {
+                (Some(job_id), None) => job_id.clone(),
+                (None, _) => bail!("no checkpoints found in '{}'", args.state_dir),
+                (Some(_), Some(_)) => bail!(
+                    "'{}' contains checkpoints for multiple jobs; choose one with --job: {}",
+                    args.state_dir,
+                    checkpoints.keys().cloned().collect::<Vec<_>>().join(", ")
+                ),
+            }
+        }
+    };
+
+    let epochs = checkpoints.remove(&job_id).unwrap_or_default();
+    let epoch = match args.epoch {
+        Some(epoch) => epoch,
+        None => *epochs
+            .last()
+            .ok_or_else(|| anyhow!("no checkpoints found for job '{job_id}'"))?,
+    };
+
+    let Some(operator_id) = args.operator else {
+        let operators = inspect::list_operators(&storage, &job_id, epoch)
+            .await?
+            .ok_or_else(|| anyhow!("no checkpoint {epoch} found for job '{job_id}'"))?;
+
+        println!("Job {job_id}, checkpoint {epoch}");
+        println!(
+            "Available checkpoints: {}",
+            epochs
+                .iter()
+                .map(|e| e.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        println!("Operators:");
+        for operator in operators {
+            println!("  {operator}");
+        }
+        return Ok(());
+    };
+
+    let Some(table) = args.table else {
+        let tables = inspect::list_tables(&storage, &job_id, epoch, &operator_id)
+            .await?
+            .ok_or_else(|| {
+                anyhow!(
+                    "operator '{operator_id}' not found in checkpoint {epoch} of job '{job_id}'"
+                )
+            })?;
+
+        println!(
+            "{:<24} {:<20} {:>6} {:>12}  DESCRIPTION",
+            "TABLE", "TYPE", "FILES", "BYTES"
+        );
+        for table in tables {
+            println!(
+                "{:<24} {:<20} {:>6} {:>12}  {}",
+                table.name,
+                serde_json::to_value(table.table_type)?
+                    .as_str()
+                    .unwrap_or_default(),
+                table.files,
+                table.bytes,
+                table.description
+            );
+        }
+        return Ok(());
+    };
+
+    let rows = inspect::read_table(
+        &storage,
+        &job_id,
+        epoch,
+        &operator_id,
+        &table,
+        args.key.as_deref(),
+        args.offset,
+        args.limit,
+    )
+    .await?
+    .ok_or_else(|| {
+        anyhow!("table '{table}' not found for operator '{operator_id}' in checkpoint {epoch}")
+    })?;
+
+    for row in &rows.rows {
+        println!("{row}");
+    }
+
+    if rows.has_more {
+        eprintln!(
+            "more rows available; use --offset {} to continue",
+            args.offset + rows.rows.len()
+        );
+    }
+
+    Ok(())
+}
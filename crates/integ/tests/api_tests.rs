@@ -5,9 +5,9 @@ use std::time::Duration;
 
 use arroyo_openapi::Client;
 use arroyo_openapi::types::{
-    ConnectionProfilePost, ConnectionSchema, ConnectionTablePost, Format, JsonType, MetricName,
-    PipelinePatch, PipelinePost, SchemaDefinition, StopType, Udf, ValidateQueryPost,
-    ValidateUdfPost, builder,
+    CheckpointGcPost, ConnectionProfilePost, ConnectionSchema, ConnectionTablePost, Format,
+    JsonType, MetricName, PipelinePatch, PipelinePost, SchemaDefinition, StateTableType, StopType,
+    Udf, ValidateQueryPost, ValidateUdfPost, builder,
 };
 use rand::random;
 use rdkafka::admin::{AdminClient, AdminOptions, NewTopic};
@@ -427,6 +427,257 @@ select my_double(cast(counter as bigint)) from impulse;
         .unwrap();
 }
 
+#[tokio::test]
+async fn checkpoint_gc() {
+    let api_client = get_client();
+
+    let query = r#"
+create table impulse with (
+   connector = 'impulse',
+   event_rate = '10'
+);
+
+select count(*) from impulse group by tumble(interval '1 second');
+"#;
+
+    let test_id: u32 = random();
+    let (pipeline_id, job_id, _) = start_and_monitor(test_id, query, &[], 3).await.unwrap();
+
+    let run_id = patch_and_wait(
+        &pipeline_id,
+        None,
+        PipelinePatch::builder().stop(StopType::Checkpoint),
+        "Stopped",
+    )
+    .await
+    .unwrap();
+
+    let latest = api_client
+        .get_job_checkpoints()
+        .pipeline_id(&pipeline_id)
+        .job_id(&job_id)
+        .send()
+        .await
+        .unwrap()
+        .into_inner()
+        .data
+        .iter()
+        .filter(|c| c.finish_time.is_some())
+        .map(|c| c.epoch)
+        .max()
+        .unwrap();
+    let latest_prefix = format!("{job_id}/checkpoints/checkpoint-{latest:0>7}/");
+
+    let result = api_client
+        .checkpoint_gc()
+        .body(CheckpointGcPost::builder().dry_run(true))
+        .send()
+        .await
+        .unwrap()
+        .into_inner();
+
+    assert!(result.dry_run);
+    assert!(
+        !result.files.iter().any(|f| f.starts_with(&latest_prefix)),
+        "garbage collection would delete files of the latest checkpoint: {:?}",
+        result.files
+    );
+
+    api_client
+        .checkpoint_gc()
+        .body(CheckpointGcPost::builder().dry_run(false))
+        .send()
+        .await
+        .unwrap();
+
+    // the job can still restore from its latest checkpoint
+    patch_and_wait(
+        &pipeline_id,
+        Some(run_id),
+        PipelinePatch::builder().stop(StopType::None),
+        "Running",
+    )
+    .await
+    .unwrap();
+
+    patch_and_wait(
+        &pipeline_id,
+        None,
+        PipelinePatch::builder().stop(StopType::Immediate),
+        "Stopped",
+    )
+    .await
+    .unwrap();
+
+    api_client
+        .delete_pipeline()
+        .id(&pipeline_id)
+        .send()
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn checkpoint_state_inspection() {
+    let api_client = get_client();
+
+    let query = r#"
+create table impulse with (
+   connector = 'impulse',
+   event_rate = '10'
+);
+
+select counter % 5 as k, count(*) from impulse group by 1, tumble(interval '1 hour');
+"#;
+
+    let test_id: u32 = random();
+    let (pipeline_id, job_id, _) = start_and_monitor(test_id, query, &[], 3).await.unwrap();
+
+    patch_and_wait(
+        &pipeline_id,
+        None,
+        PipelinePatch::builder().stop(StopType::Checkpoint),
+        "Stopped",
+    )
+    .await
+    .unwrap();
+
+    let epoch = api_client
+        .get_job_checkpoints()
+        .pipeline_id(&pipeline_id)
+        .job_id(&job_id)
+        .send()
+        .await
+        .unwrap()
+        .into_inner()
+        .data
+        .iter()
+        .filter(|c| c.finish_time.is_some())
+        .map(|c| c.epoch)
+        .max()
+        .unwrap();
+
+    let operators = api_client
+        .get_checkpoint_details()
+        .pipeline_id(&pipeline_id)
+        .job_id(&job_id)
+        .epoch(epoch)
+        .send()
+        .await
+        .unwrap()
+        .into_inner()
+        .data;
+
+    // the window's state is kept in an expiring table until the hour is over
+    let mut window_table = None;
+    for operator in &operators {
+        let tables = api_client
+            .get_checkpoint_state_tables()
+            .pipeline_id(&pipeline_id)
+            .job_id(&job_id)
+            .epoch(epoch)
+            .operator_id(&operator.operator_id)
+            .send()
+            .await
+            .unwrap()
+            .into_inner()
+            .data;
+
+        if let Some(table) = tables
+            .into_iter()
+            .find(|t| t.table_type == StateTableType::ExpiringKeyedTime && t.files > 0)
+        {
+            window_table = Some((operator.operator_id.clone(), table.name));
+        }
+    }
+    let (operator_id, table) = window_table.expect("no window state in the checkpoint");
+
+    let read = |limit: u32, offset: u64| {
+        api_client
+            .get_checkpoint_state_table()
+            .pipeline_id(&pipeline_id)
+            .job_id(&job_id)
+            .epoch(epoch)
+            .operator_id(&operator_id)
+            .table(&table)
+            .limit(limit)
+            .offset(offset)
+            .send()
+    };
+
+    let first = read(1, 0).await.unwrap().into_inner();
+    assert_eq!(first.rows.len(), 1);
+    assert_eq!(first.offset, 0);
+    assert!(!first.key_fields.is_empty());
+
+    if first.has_more {
+        let second = read(1, 1).await.unwrap().into_inner();
+        assert_eq!(second.rows.len(), 1);
+        assert_eq!(second.offset, 1);
+    }
+
+    // limits are capped at 1000 rows
+    for limit in [0, 1001] {
+        assert_eq!(
+            read(limit, 0).await.unwrap_err().status().unwrap(),
+            reqwest::StatusCode::BAD_REQUEST
+        );
+    }
+
+    assert_eq!(
+        api_client
+            .get_checkpoint_state_table()
+            .pipeline_id(&pipeline_id)
+            .job_id(&job_id)
+            .epoch(epoch)
+            .operator_id(&operator_id)
+            .table("missing")
+            .send()
+            .await
+            .unwrap_err()
+            .status()
+            .unwrap(),
+        reqwest::StatusCode::NOT_FOUND
+    );
+
+    assert_eq!(
+        api_client
+            .get_checkpoint_state_tables()
+            .pipeline_id(&pipeline_id)
+            .job_id(&job_id)
+            .epoch(epoch)
+            .operator_id("missing")
+            .send()
+            .await
+            .unwrap_err()
+            .status()
+            .unwrap(),
+        reqwest::StatusCode::NOT_FOUND
+    );
+
+    assert_eq!(
+        api_client
+            .get_checkpoint_state_tables()
+            .pipeline_id(&pipeline_id)
+            .job_id(&job_id)
+            .epoch(epoch + 1000)
+            .operator_id(&operator_id)
+            .send()
+            .await
+            .unwrap_err()
+            .status()
+            .unwrap(),
+        reqwest::StatusCode::NOT_FOUND
+    );
+
+    api_client
+        .delete_pipeline()
+        .id(&pipeline_id)
+        .send()
+        .await
+        .unwrap();
+}
+
 fn create_kafka_admin() -> AdminClient<impl ClientContext> {
     ClientConfig::new()
         .set("bootstrap.servers", "localhost:9092")
@@ -4,6 +4,23 @@
  */
 
 export interface paths {
+    "/v1/admin/checkpoint_gc": {
+        parameters: {
+            query?: never;
+            header?: never;
+            path?: never;
+            cookie?: never;
+        };
+        get?: never;
+        put?: never;
+        /** Delete checkpoint files that are no longer needed */
+        post: operations["checkpoint_gc"];
+        delete?: never;
+        options?: never;
+        head?: never;
+        patch?: never;
+        trace?: never;
+    };
     "/v1/connection_profiles": {
         parameters: {
             query?: never;
@@ -335,6 +352,40 @@ export interface paths {
         patch?: never;
         trace?: never;
     };
+    "/v1/pipelines/{pipeline_id}/jobs/{job_id}/checkpoints/{epoch}/operators/{operator_id}/tables": {
+        parameters: {
+            query?: never;
+            header?: never;
+            path?: never;
+            cookie?: never;
+        };
+        /** List the state tables in an operator's checkpoint */
+        get: operations["get_checkpoint_state_tables"];
+        put?: never;
+        post?: never;
+        delete?: never;
+        options?: never;
+        head?: never;
+        patch?: never;
+        trace?: never;
+    };
+    "/v1/pipelines/{pipeline_id}/jobs/{job_id}/checkpoints/{epoch}/operators/{operator_id}/tables/{table}": {
+        parameters: {
+            query?: never;
+            header?: never;
+            path?: never;
+            cookie?: never;
+        };
+        /** Read the rows of a state table in an operator's checkpoint */
+        get: operations["get_checkpoint_state_table"];
+        put?: never;
+        post?: never;
+        delete?: never;
+        options?: never;
+        head?: never;
+        patch?: never;
+        trace?: never;
+    };
     "/v1/pipelines/{pipeline_id}/jobs/{job_id}/errors": {
         parameters: {
             query?: never;
@@ -470,6 +521,16 @@ export interface components {
         CheckpointCollection: {
             data: components["schemas"]["Checkpoint"][];
         };
+        CheckpointGcPost: {
+            delete_orphans?: boolean | null;
+            dry_run?: boolean | null;
+        };
+        CheckpointGcResult: {
+            /** Format: int64 */
+            bytes: number;
+            dry_run: boolean;
+            files: string[];
+        };
         CheckpointEventSpan: {
             description: string;
             event: string;
@@ -842,6 +903,10 @@ export interface components {
             parallelism: number;
             query: string;
             udfs?: components["schemas"]["Udf"][] | null;
+            /** @description If set, checkpoint barriers skip ahead of queued data, which is stored as part of the
+             *     checkpoint; this keeps checkpoints fast when the pipeline is backpressured */
+            unaligned_checkpoints?: boolean | null;
+            worker_overrides?: components["schemas"]["WorkerOverrides"] | null;
         };
         PipelineRestart: {
             force?: boolean | null;
@@ -890,6 +955,43 @@ export interface components {
             required?: boolean;
             readonly sql_name?: string | null;
         });
+        StateTable: {
+            /** Format: int64 */
+            bytes: number;
+            description: string;
+            /**
+             * Format: int32
+             * @description Number of Parquet files referenced by this table in the checkpoint
+             */
+            files: number;
+            name: string;
+            table_type: components["schemas"]["StateTableType"];
+        };
+        StateTableCollection: {
+            data: components["schemas"]["StateTable"][];
+        };
+        StateTableQueryParams: {
+            /** @description Only return rows whose key matches. Single-column keys are matched against the value
+             *     itself, multi-column keys against a JSON array of the key values */
+            key?: string | null;
+            /** Format: int64 */
+            offset?: number | null;
+            /** Format: int32 */
+            limit?: number | null;
+        };
+        StateTableRows: {
+            has_more: boolean;
+            key_fields: string[];
+            /** Format: int64 */
+            offset: number;
+            /** @description Rows that would be restored from the checkpoint, encoded as JSON objects: expired rows
+             *     and superseded generations are dropped. Binary columns (like the bincode-encoded keys and
+             *     values of global keyed tables) are hex-encoded. */
+            rows: unknown[];
+            table: components["schemas"]["StateTable"];
+        };
+        /** @enum {string} */
+        StateTableType: "global_keyed" | "expiring_keyed_time";
         /** @enum {string} */
         StopType: "none" | "checkpoint" | "graceful" | "immediate" | "force";
         StructField: {
@@ -943,6 +1045,32 @@ export interface components {
             definition: string;
             language?: components["schemas"]["UdfLanguage"];
         };
+        /**
+         * @description Per-pipeline overrides for the worker pods created by the Kubernetes scheduler. Values
+         * here are applied on top of the global `kubernetes-scheduler.worker` config.
+         */
+        WorkerOverrides: {
+            affinity?: Record<string, unknown> | null;
+            annotations?: {
+                [key: string]: string;
+            };
+            /** @description CPU for each task slot, as a Kubernetes quantity (e.g., "900m") */
+            cpu_per_slot?: string | null;
+            env?: Record<string, unknown>[];
+            labels?: {
+                [key: string]: string;
+            };
+            /** @description Memory for each task slot, as a Kubernetes quantity (e.g., "2Gi") */
+            memory_per_slot?: string | null;
+            node_selector?: {
+                [key: string]: string;
+            };
+            /** Format: int32 */
+            slots_per_pod?: number | null;
+            tolerations?: Record<string, unknown>[];
+            volume_mounts?: Record<string, unknown>[];
+            volumes?: Record<string, unknown>[];
+        };
     };
     responses: never;
     parameters: never;
@@ -1520,6 +1648,72 @@ export interface operations {
             };
         };
     };
+    get_checkpoint_state_tables: {
+        parameters: {
+            query?: never;
+            header?: never;
+            path: {
+                /** @description Pipeline id */
+                pipeline_id: string;
+                /** @description Job id */
+                job_id: string;
+                /** @description Epoch */
+                epoch: number;
+                /** @description Operator id */
+                operator_id: string;
+            };
+            cookie?: never;
+        };
+        requestBody?: never;
+        responses: {
+            /** @description Got the operator's state tables */
+            200: {
+                headers: {
+                    [name: string]: unknown;
+                };
+                content: {
+                    "application/json": components["schemas"]["StateTableCollection"];
+                };
+            };
+        };
+    };
+    get_checkpoint_state_table: {
+        parameters: {
+            query?: {
+                /** @description Only return rows whose key matches. Single-column keys are matched against the value
+                 *     itself, multi-column keys against a JSON array of the key values */
+                key?: string | null;
+                offset?: number | null;
+                limit?: number | null;
+            };
+            header?: never;
+            path: {
+                /** @description Pipeline id */
+                pipeline_id: string;
+                /** @description Job id */
+                job_id: string;
+                /** @description Epoch */
+                epoch: number;
+                /** @description Operator id */
+                operator_id: string;
+                /** @description Table name */
+                table: string;
+            };
+            cookie?: never;
+        };
+        requestBody?: never;
+        responses: {
+            /** @description Got the table's rows */
+            200: {
+                headers: {
+                    [name: string]: unknown;
+                };
+                content: {
+                    "application/json": components["schemas"]["StateTableRows"];
+                };
+            };
+        };
+    };
     get_job_errors: {
         parameters: {
             query?: {
@@ -1642,6 +1836,30 @@ export interface operations {
             };
         };
     };
+    checkpoint_gc: {
+        parameters: {
+            query?: never;
+            header?: never;
+            path?: never;
+            cookie?: never;
+        };
+        requestBody: {
+            content: {
+                "application/json": components["schemas"]["CheckpointGcPost"];
+            };
+        };
+        responses: {
+            /** @description Collected checkpoint garbage */
+            200: {
+                headers: {
+                    [name: string]: unknown;
+                };
+                content: {
+                    "application/json": components["schemas"]["CheckpointGcResult"];
+                };
+            };
+        };
+    };
     validate_udf: {
         parameters: {
             query?: never;